@@ -0,0 +1,394 @@
+//! C interface for non-Rust frontends
+//!
+//! This crate exposes the investigation, metadata, transcription, and
+//! matching functions of the library as a C ABI, so GUI frontends (Tauri,
+//! Qt, Swift, ...) can embed the engine without re-implementing the pipeline.
+//! It is built as a shared and a static library. A C header can be
+//! generated with `cbindgen` using the `cbindgen.toml` next to it.
+//!
+//! Structured values cross the boundary as UTF-8 encoded JSON strings, using
+//! the serde representation of the corresponding Rust types. Strings returned
+//! by this crate are owned by the caller and must be released with
+//! `dd_string_free`. On failure, functions return NULL and the error message
+//! can be retrieved with `dd_last_error`, its stable code (e.g. `DD-AUD-001`)
+//! with `dd_last_error_code`. Panics don't unwind into the caller, they are
+//! reported the same way with the code `DD-FFI-003`, which is why its release
+//! profile unwinds on panics, unlike the one of the CLI.
+
+use dialog_detective::{
+    DialogDetectiveError, MatcherOptions, MatcherType, MetadataOptions, ProgressEvent,
+    SeriesCandidate, TVSeries, Transcript, TranscriptionOptions, fetch_show_metadata,
+    investigate_case, match_transcript, transcribe_video,
+};
+use serde::Serialize;
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+/// Matcher identifier for the Gemini CLI
+pub const DD_MATCHER_GEMINI: c_int = 0;
+/// Matcher identifier for the Gemini CLI with the gemini-2.5-flash model
+pub const DD_MATCHER_GEMINI_FLASH: c_int = 1;
+/// Matcher identifier for the Claude Code CLI
+pub const DD_MATCHER_CLAUDE: c_int = 2;
+
+/// Callback receiving each progress event as a JSON string
+///
+/// The string is only valid for the duration of the call.
+pub type DdProgressCallback =
+    Option<unsafe extern "C" fn(event_json: *const c_char, user_data: *mut c_void)>;
+
+/// Callback selecting one of multiple series candidates
+///
+/// Receives the candidates as a JSON array and returns the index of the
+/// selected candidate, or a negative value to cancel the investigation.
+pub type DdSelectCallback =
+    Option<unsafe extern "C" fn(candidates_json: *const c_char, user_data: *mut c_void) -> c_int>;
+
+/// Error code of invalid arguments, like NULL strings or malformed JSON
+const INVALID_ARGUMENT: &str = "DD-FFI-001";
+
+/// Error code of results that couldn't be serialized to JSON
+const SERIALIZATION_FAILED: &str = "DD-FFI-002";
+
+/// Error code of panics caught at the boundary
+const PANICKED: &str = "DD-FFI-003";
+
+/// An error to be reported to the caller, with its code
+struct FfiError {
+    code: &'static str,
+    message: String,
+}
+
+impl From<DialogDetectiveError> for FfiError {
+    fn from(error: DialogDetectiveError) -> Self {
+        Self {
+            code: error.code(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<String> for FfiError {
+    fn from(message: String) -> Self {
+        Self {
+            code: INVALID_ARGUMENT,
+            message,
+        }
+    }
+}
+
+thread_local! {
+    /// Code and message of the last error that occurred on this thread
+    static LAST_ERROR: RefCell<Option<(CString, CString)>> = const { RefCell::new(None) };
+}
+
+/// Stores an error to be retrieved with `dd_last_error` and `dd_last_error_code`
+fn set_last_error(error: FfiError) {
+    let code = CString::new(error.code).unwrap_or_default();
+    let message = CString::new(error.message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some((code, message)));
+}
+
+/// Runs the body of an exported function, reporting a panic as an error
+///
+/// Unwinding into C is undefined behavior, so a panic is caught here and
+/// stored like any other error, returning `fallback` instead.
+fn guard<T>(fallback: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        set_last_error(FfiError {
+            code: PANICKED,
+            message: format!("Internal error: {}", panic_message(payload.as_ref())),
+        });
+        fallback
+    })
+}
+
+/// Extracts the message of a panic, if it has one
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Converts an owned string into a C string handed over to the caller
+fn into_c_string(value: String) -> *mut c_char {
+    CString::new(value.replace('\0', " "))
+        .unwrap_or_default()
+        .into_raw()
+}
+
+/// Runs the body of an exported function and serializes its result to JSON
+///
+/// Returns NULL and records the error if the body fails or panics.
+fn respond<T: Serialize>(body: impl FnOnce() -> Result<T, FfiError>) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let json = body().and_then(|value| {
+            serde_json::to_string(&value).map_err(|e| FfiError {
+                code: SERIALIZATION_FAILED,
+                message: e.to_string(),
+            })
+        });
+        match json {
+            Ok(json) => into_c_string(json),
+            Err(error) => {
+                set_last_error(error);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Reads a required UTF-8 string argument
+///
+/// # Safety
+///
+/// `value` must be NULL or point to a valid NUL-terminated string.
+unsafe fn str_arg<'a>(value: *const c_char, name: &str) -> Result<&'a str, String> {
+    if value.is_null() {
+        return Err(format!("Argument '{}' must not be NULL", name));
+    }
+
+    unsafe { CStr::from_ptr(value) }
+        .to_str()
+        .map_err(|_| format!("Argument '{}' is not valid UTF-8", name))
+}
+
+/// Reads an optional season filter from a pointer and length
+///
+/// # Safety
+///
+/// `seasons` must be NULL or point to at least `season_count` values.
+unsafe fn seasons_arg(seasons: *const usize, season_count: usize) -> Option<Vec<usize>> {
+    if seasons.is_null() || season_count == 0 {
+        return None;
+    }
+
+    Some(unsafe { std::slice::from_raw_parts(seasons, season_count) }.to_vec())
+}
+
+/// Maps a matcher identifier to the matcher type
+fn matcher_arg(matcher: c_int) -> Result<MatcherType, String> {
+    match matcher {
+        DD_MATCHER_GEMINI => Ok(MatcherType::Gemini),
+        DD_MATCHER_GEMINI_FLASH => Ok(MatcherType::GeminiFlash),
+        DD_MATCHER_CLAUDE => Ok(MatcherType::Claude),
+        other => Err(format!("Unknown matcher identifier: {}", other)),
+    }
+}
+
+/// Investigates a directory and returns the match results as a JSON array
+///
+/// Progress events are passed to `progress` as JSON objects tagged with an
+/// `event` field. If the show name matches multiple series, `select` is asked
+/// to choose one; without a select callback the best-ranked series is used.
+///
+/// # Safety
+///
+/// All string arguments must be valid NUL-terminated strings, `seasons` must
+/// be NULL or point to `season_count` values, and the callbacks must be safe
+/// to call with `user_data`.
+#[allow(clippy::too_many_arguments)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dd_investigate(
+    directory: *const c_char,
+    model_path: *const c_char,
+    show_name: *const c_char,
+    seasons: *const usize,
+    season_count: usize,
+    matcher: c_int,
+    progress: DdProgressCallback,
+    select: DdSelectCallback,
+    user_data: *mut c_void,
+) -> *mut c_char {
+    respond(|| {
+        let directory = unsafe { str_arg(directory, "directory") }?;
+        let model_path = unsafe { str_arg(model_path, "model_path") }?;
+        let show_name = unsafe { str_arg(show_name, "show_name") }?;
+        let season_filter = unsafe { seasons_arg(seasons, season_count) };
+        let matcher = matcher_arg(matcher)?;
+
+        let progress_callback = |event: ProgressEvent| {
+            if let Some(callback) = progress
+                && let Ok(json) = serde_json::to_string(&event)
+                && let Ok(json) = CString::new(json)
+            {
+                unsafe { callback(json.as_ptr(), user_data) };
+            }
+        };
+
+        let select_series = |candidates: &[SeriesCandidate]| {
+            let Some(callback) = select else {
+                return Ok(0);
+            };
+
+            let json = serde_json::to_string(candidates)
+                .ok()
+                .and_then(|json| CString::new(json).ok())
+                .ok_or(DialogDetectiveError::SelectionCancelled)?;
+            let index = unsafe { callback(json.as_ptr(), user_data) };
+
+            usize::try_from(index)
+                .ok()
+                .filter(|&index| index < candidates.len())
+                .ok_or(DialogDetectiveError::SelectionCancelled)
+        };
+
+        investigate_case(
+            Path::new(directory),
+            Path::new(model_path),
+            show_name,
+            season_filter,
+            matcher,
+            progress_callback,
+            select_series,
+        )
+        .map_err(FfiError::from)
+    })
+}
+
+/// Fetches the episode metadata of a show and returns it as a JSON object
+///
+/// The best-ranked search result for the show name is used.
+///
+/// # Safety
+///
+/// `show_name` must be a valid NUL-terminated string and `seasons` must be
+/// NULL or point to `season_count` values.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dd_fetch_metadata(
+    show_name: *const c_char,
+    seasons: *const usize,
+    season_count: usize,
+) -> *mut c_char {
+    respond(|| {
+        let show_name = unsafe { str_arg(show_name, "show_name") }?;
+        let options = MetadataOptions {
+            season_filter: unsafe { seasons_arg(seasons, season_count) },
+            ..Default::default()
+        };
+
+        fetch_show_metadata(show_name, &options).map_err(FfiError::from)
+    })
+}
+
+/// Transcribes a video file and returns the transcript as a JSON object
+///
+/// # Safety
+///
+/// Both arguments must be valid NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dd_transcribe(
+    video_path: *const c_char,
+    model_path: *const c_char,
+) -> *mut c_char {
+    respond(|| {
+        let video_path = unsafe { str_arg(video_path, "video_path") }?;
+        let model_path = unsafe { str_arg(model_path, "model_path") }?;
+
+        transcribe_video(
+            Path::new(video_path),
+            Path::new(model_path),
+            &TranscriptionOptions::default(),
+        )
+        .map_err(FfiError::from)
+    })
+}
+
+/// Matches a JSON transcript against a JSON series and returns the outcome as JSON
+///
+/// The series JSON has the format returned by `dd_fetch_metadata`, the
+/// transcript JSON the format returned by `dd_transcribe`.
+///
+/// # Safety
+///
+/// Both JSON arguments must be valid NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dd_match(
+    transcript_json: *const c_char,
+    series_json: *const c_char,
+    matcher: c_int,
+) -> *mut c_char {
+    respond(|| {
+        let transcript: Transcript =
+            serde_json::from_str(unsafe { str_arg(transcript_json, "transcript_json") }?)
+                .map_err(|e| format!("Invalid transcript JSON: {}", e))?;
+        let series: TVSeries =
+            serde_json::from_str(unsafe { str_arg(series_json, "series_json") }?)
+                .map_err(|e| format!("Invalid series JSON: {}", e))?;
+        let options = MatcherOptions {
+            matcher: matcher_arg(matcher)?,
+            ..MatcherOptions::default()
+        };
+
+        match_transcript(&transcript, &series, &options).map_err(FfiError::from)
+    })
+}
+
+/// Returns the message of the last error on the calling thread, or NULL
+///
+/// The returned string is owned by the library and stays valid until the
+/// next failing call on the same thread. It must not be freed.
+#[unsafe(no_mangle)]
+pub extern "C" fn dd_last_error() -> *const c_char {
+    guard(ptr::null(), || {
+        LAST_ERROR.with(|last| {
+            last.borrow()
+                .as_ref()
+                .map_or(ptr::null(), |(_, message)| message.as_ptr())
+        })
+    })
+}
+
+/// Returns the code of the last error on the calling thread, or NULL
+///
+/// Codes like `DD-MAT-003` stay the same between releases, so frontends
+/// can branch on them instead of the message. Invalid arguments are
+/// reported as `DD-FFI-001`. The string is owned like the one returned by
+/// `dd_last_error`.
+#[unsafe(no_mangle)]
+pub extern "C" fn dd_last_error_code() -> *const c_char {
+    guard(ptr::null(), || {
+        LAST_ERROR.with(|last| {
+            last.borrow()
+                .as_ref()
+                .map_or(ptr::null(), |(code, _)| code.as_ptr())
+        })
+    })
+}
+
+/// Releases a string returned by this library
+///
+/// # Safety
+///
+/// `value` must be NULL or a string returned by one of the `dd_*` functions
+/// that has not been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dd_string_free(value: *mut c_char) {
+    guard((), || {
+        if !value.is_null() {
+            drop(unsafe { CString::from_raw(value) });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panics_are_reported_as_errors() {
+        let result = respond::<()>(|| panic!("index out of bounds"));
+        assert!(result.is_null());
+
+        let code = unsafe { CStr::from_ptr(dd_last_error_code()) };
+        assert_eq!(code.to_str(), Ok(PANICKED));
+        let message = unsafe { CStr::from_ptr(dd_last_error()) };
+        assert_eq!(message.to_str(), Ok("Internal error: index out of bounds"));
+    }
+}
@@ -0,0 +1,280 @@
+//! Candidate chunking for long-running shows
+//!
+//! Soap operas and anime can have hundreds of episodes, whose summaries
+//! don't fit into a single prompt. This module splits the candidates into
+//! chunks, matches the transcript against each chunk separately, and lets a
+//! final tie-break round choose between the winners of all chunks.
+
+use super::{EpisodeMatcher, EpisodeMatchingError, MatchOutcome, SeasonOutcome};
+use crate::metadata_retrieval::{Episode, Season, TVSeries};
+use crate::speech_to_text::Transcript;
+
+/// Default maximum number of episode candidates in a single prompt
+pub const DEFAULT_MAX_EPISODES_PER_PROMPT: usize = 150;
+
+/// Episode matcher wrapper splitting large candidate lists into chunks
+///
+/// Series with at most `max_episodes` episodes are passed to the wrapped
+/// matcher unchanged.
+pub(crate) struct ChunkedMatcher<M> {
+    inner: M,
+    max_episodes: usize,
+}
+
+impl<M: EpisodeMatcher> ChunkedMatcher<M> {
+    /// Wraps a matcher, limiting its prompts to `max_episodes` candidates
+    ///
+    /// A limit of zero is treated as one.
+    pub fn new(inner: M, max_episodes: usize) -> Self {
+        Self {
+            inner,
+            max_episodes: max_episodes.max(1),
+        }
+    }
+}
+
+impl<M: EpisodeMatcher> EpisodeMatcher for ChunkedMatcher<M> {
+    fn id(&self) -> String {
+        self.inner.id()
+    }
+
+    fn version(&self) -> u32 {
+        self.inner.version()
+    }
+
+    /// Matches against each chunk, then against the winners of all chunks
+    ///
+    /// Chunks without a match are skipped. The outcome's prompt, response,
+    /// confidence and alternatives are those of the tie-break round, while
+    /// its usage covers all rounds. Extra episodes of a multi-episode match
+    /// are taken from the winner's chunk, as the tie-break round only sees
+    /// the winners.
+    fn match_episode(
+        &self,
+        transcript: &Transcript,
+        series: &TVSeries,
+    ) -> Result<MatchOutcome, EpisodeMatchingError> {
+        let chunks = split_series(series, self.max_episodes);
+        if chunks.len() <= 1 {
+            return self.inner.match_episode(transcript, series);
+        }
+
+        let mut winners = Vec::new();
+        let mut extras = Vec::new();
+        let mut usage = None;
+        let mut last_no_match = None;
+        for chunk in &chunks {
+            match self.inner.match_episode(transcript, chunk) {
+                Ok(outcome) => {
+                    usage = [usage, outcome.usage]
+                        .into_iter()
+                        .flatten()
+                        .reduce(|a, b| a + b);
+                    extras.push((outcome.episode.clone(), outcome.extra_episodes));
+                    winners.push(outcome.episode);
+                }
+                Err(e @ EpisodeMatchingError::NoMatchFound { .. }) => last_no_match = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+
+        if winners.is_empty() {
+            return Err(last_no_match.expect("at least two chunks were matched"));
+        }
+
+        // The tie-break round may itself need chunking for very large shows
+        let finalists = group_into_seasons(series, winners);
+        let mut outcome = self.match_episode(transcript, &finalists)?;
+        outcome.usage = [usage, outcome.usage]
+            .into_iter()
+            .flatten()
+            .reduce(|a, b| a + b);
+        if outcome.extra_episodes.is_empty()
+            && let Some((_, extra_episodes)) =
+                extras.into_iter().find(|(e, _)| *e == outcome.episode)
+        {
+            outcome.extra_episodes = extra_episodes;
+        }
+        Ok(outcome)
+    }
+
+    fn infer_season(
+        &self,
+        transcript: &Transcript,
+        series: &TVSeries,
+    ) -> Result<SeasonOutcome, EpisodeMatchingError> {
+        self.inner.infer_season(transcript, series)
+    }
+}
+
+/// Splits a series into series of at most `max_episodes` episodes each
+///
+/// Episodes keep their order and their season, so a season may be spread
+/// over consecutive chunks.
+fn split_series(series: &TVSeries, max_episodes: usize) -> Vec<TVSeries> {
+    let episodes: Vec<Episode> = series
+        .seasons
+        .iter()
+        .flat_map(|s| s.episodes.iter().cloned())
+        .collect();
+
+    episodes
+        .chunks(max_episodes)
+        .map(|chunk| group_into_seasons(series, chunk.to_vec()))
+        .collect()
+}
+
+/// Builds a series from some of its episodes, grouping consecutive episodes
+/// by season
+fn group_into_seasons(series: &TVSeries, episodes: Vec<Episode>) -> TVSeries {
+    let mut seasons: Vec<Season> = Vec::new();
+    for episode in episodes {
+        match seasons.last_mut() {
+            Some(season) if season.season_number == episode.season_number => {
+                season.episodes.push(episode)
+            }
+            _ => seasons.push(Season {
+                season_number: episode.season_number,
+                episodes: vec![episode],
+            }),
+        }
+    }
+
+    TVSeries {
+        name: series.name.clone(),
+        year: series.year,
+        seasons,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn series(seasons: &[usize]) -> TVSeries {
+        TVSeries {
+            name: "Show".to_string(),
+            year: None,
+            seasons: seasons
+                .iter()
+                .enumerate()
+                .map(|(index, &count)| Season {
+                    season_number: index + 1,
+                    episodes: (1..=count)
+                        .map(|number| Episode {
+                            season_number: index + 1,
+                            episode_number: number,
+                            name: format!("Episode {}", number),
+                            summary: String::new(),
+                            summary_source: None,
+                            airdate: None,
+                            runtime: None,
+                            season_name: None,
+                            provider_id: None,
+                            local_title: None,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Picks the highest episode number of each prompt, recording the prompts
+    struct HighestEpisodeMatcher {
+        prompts: Mutex<Vec<usize>>,
+    }
+
+    impl EpisodeMatcher for HighestEpisodeMatcher {
+        fn id(&self) -> String {
+            "highest".to_string()
+        }
+
+        fn match_episode(
+            &self,
+            _transcript: &Transcript,
+            series: &TVSeries,
+        ) -> Result<MatchOutcome, EpisodeMatchingError> {
+            let episodes: Vec<_> = series.seasons.iter().flat_map(|s| &s.episodes).collect();
+            self.prompts.lock().unwrap().push(episodes.len());
+
+            let episode = episodes
+                .into_iter()
+                .max_by_key(|e| e.episode_number)
+                .cloned()
+                .ok_or(EpisodeMatchingError::NoMatchFound {
+                    response: String::new(),
+                })?;
+            Ok(MatchOutcome {
+                episode,
+                confidence: None,
+                alternatives: Vec::new(),
+                reasoning: None,
+                extra_episodes: Vec::new(),
+                prompt: String::new(),
+                response: String::new(),
+                usage: None,
+            })
+        }
+    }
+
+    #[test]
+    fn test_split_series_keeps_seasons() {
+        let chunks = split_series(&series(&[3, 2]), 2);
+
+        let layout: Vec<Vec<(usize, usize)>> = chunks
+            .iter()
+            .map(|chunk| {
+                chunk
+                    .seasons
+                    .iter()
+                    .map(|s| (s.season_number, s.episodes.len()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            layout,
+            vec![vec![(1, 2)], vec![(1, 1), (2, 1)], vec![(2, 1)]]
+        );
+    }
+
+    #[test]
+    fn test_chunked_matching_with_tie_break() {
+        let matcher = ChunkedMatcher::new(
+            HighestEpisodeMatcher {
+                prompts: Mutex::new(Vec::new()),
+            },
+            4,
+        );
+
+        let outcome = matcher
+            .match_episode(&Transcript::new("", "en"), &series(&[5, 5]))
+            .unwrap();
+
+        // Chunks of 4, 4 and 2 episodes, then a tie-break between 3 winners
+        assert_eq!(*matcher.inner.prompts.lock().unwrap(), vec![4, 4, 2, 3]);
+        assert_eq!(
+            (
+                outcome.episode.season_number,
+                outcome.episode.episode_number
+            ),
+            (2, 5)
+        );
+    }
+
+    #[test]
+    fn test_small_series_is_not_chunked() {
+        let matcher = ChunkedMatcher::new(
+            HighestEpisodeMatcher {
+                prompts: Mutex::new(Vec::new()),
+            },
+            10,
+        );
+
+        matcher
+            .match_episode(&Transcript::new("", "en"), &series(&[5, 5]))
+            .unwrap();
+
+        assert_eq!(*matcher.inner.prompts.lock().unwrap(), vec![10]);
+    }
+}
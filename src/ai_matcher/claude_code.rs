@@ -3,19 +3,18 @@
 //! This module provides an implementation of the EpisodeMatcher trait that uses
 //! the Claude Code CLI to match transcripts to episodes.
 
-use super::{EpisodeMatcher, EpisodeMatchingError, SinglePromptGenerator};
-use crate::metadata_retrieval::{Episode, TVSeries};
+use super::response::{parse_episode_response, parse_season_response};
+use super::usage::parse_claude_output;
+use super::{
+    EpisodeMatcher, EpisodeMatchingError, MatchOutcome, MatcherCommand, SeasonOutcome,
+    SinglePromptGenerator,
+};
+use crate::child_process::ChildGuard;
+use crate::metadata_retrieval::TVSeries;
 use crate::speech_to_text::Transcript;
-use serde::Deserialize;
-use std::io::Write;
+use std::io::ErrorKind;
 use std::process::{Command, Stdio};
-
-/// JSON response format expected from Claude Code CLI
-#[derive(Debug, Deserialize)]
-struct ClaudeResponse {
-    season: usize,
-    episode: usize,
-}
+use std::time::Duration;
 
 /// Episode matcher using Claude Code CLI
 ///
@@ -25,61 +24,93 @@ struct ClaudeResponse {
 pub(crate) struct ClaudeCodeMatcher<G: SinglePromptGenerator> {
     /// The prompt generator to use for creating prompts
     generator: G,
+    /// Extra arguments, working directory, and environment of the CLI
+    command: MatcherCommand,
+    /// Time after which a call of the CLI is stopped
+    timeout: Option<Duration>,
 }
 
 impl<G: SinglePromptGenerator> ClaudeCodeMatcher<G> {
     /// Creates a new ClaudeCodeMatcher with the given prompt generator
     pub fn new(generator: G) -> Self {
-        Self { generator }
+        Self {
+            generator,
+            command: MatcherCommand::default(),
+            timeout: None,
+        }
+    }
+
+    /// Runs the CLI with extra arguments, in another directory, or with
+    /// another environment
+    pub fn command(mut self, command: MatcherCommand) -> Self {
+        self.command = command;
+        self
+    }
+
+    /// Stops calls of the CLI that haven't finished after the timeout
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
     }
 
     /// Checks if the claude CLI is installed and available
-    fn is_claude_installed() -> bool {
-        Command::new("claude")
-            .arg("--version")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map(|status| status.success())
-            .unwrap_or(false)
+    ///
+    /// A CLI that doesn't answer within the timeout counts as unavailable.
+    fn is_claude_installed(command: &MatcherCommand, timeout: Option<Duration>) -> bool {
+        let mut cmd = Command::new("claude");
+        command.configure(&mut cmd);
+        ChildGuard::spawn(
+            cmd.arg("--version")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null()),
+        )
+        .and_then(|mut child| child.wait_with_timeout(timeout))
+        .is_ok_and(|output| output.status.success())
     }
 
     /// Sends a prompt to Claude Code CLI and returns the response
-    fn call_claude(prompt: &str) -> Result<String, EpisodeMatchingError> {
+    fn call_claude(
+        prompt: &str,
+        command: &MatcherCommand,
+        timeout: Option<Duration>,
+    ) -> Result<String, EpisodeMatchingError> {
         // Check if claude is installed
-        if !Self::is_claude_installed() {
+        if !Self::is_claude_installed(command, timeout) {
             return Err(EpisodeMatchingError::ServiceError(
                 "Claude CLI not found. Please install it first.".to_string(),
             ));
         }
 
         // Spawn claude process with stdin
-        let mut child = Command::new("claude")
-            .arg("-p")
-            .arg("--output-format")
-            .arg("text")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|e| {
-                EpisodeMatchingError::ServiceError(format!("Failed to spawn claude CLI: {}", e))
-            })?;
+        let mut cmd = Command::new("claude");
+        command.configure(&mut cmd);
+        let mut child = ChildGuard::spawn(
+            cmd.arg("-p")
+                .arg("--output-format")
+                .arg("json")
+                .args(&command.args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()),
+        )
+        .map_err(|e| {
+            EpisodeMatchingError::ServiceError(format!("Failed to spawn claude CLI: {}", e))
+        })?;
 
-        // Write prompt to stdin
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(prompt.as_bytes()).map_err(|e| {
-                EpisodeMatchingError::ServiceError(format!(
-                    "Failed to write to claude stdin: {}",
+        // Write the prompt to stdin, and wait for completion and capture
+        // output, stopping at the timeout
+        let output = child
+            .wait_with_input(prompt.as_bytes().to_vec(), timeout)
+            .map_err(|e| match timeout {
+                Some(timeout) if e.kind() == ErrorKind::TimedOut => {
+                    EpisodeMatchingError::TimedOut(timeout)
+                }
+                _ => EpisodeMatchingError::ServiceError(format!(
+                    "Failed to communicate with claude CLI: {}",
                     e
-                ))
+                )),
             })?;
-        }
-
-        // Wait for completion and capture output
-        let output = child.wait_with_output().map_err(|e| {
-            EpisodeMatchingError::ServiceError(format!("Failed to read claude output: {}", e))
-        })?;
 
         // Check exit code
         if !output.status.success() {
@@ -100,80 +131,63 @@ impl<G: SinglePromptGenerator> ClaudeCodeMatcher<G> {
             }
         })
     }
+}
 
-    /// Extracts JSON from markdown code fence (```json ... ```)
-    fn extract_json_block(response: &str) -> Result<String, EpisodeMatchingError> {
-        // Look for ```json ... ``` block
-        let start_marker = "```json";
-        let end_marker = "```";
-
-        if let Some(start_pos) = response.find(start_marker) {
-            let json_start = start_pos + start_marker.len();
-            let remaining = &response[json_start..];
-
-            if let Some(end_pos) = remaining.find(end_marker) {
-                let json_str = remaining[..end_pos].trim();
-                return Ok(json_str.to_string());
-            }
+impl<G: SinglePromptGenerator> EpisodeMatcher for ClaudeCodeMatcher<G> {
+    // Claude's model names don't name the CLI, like `sonnet`
+    fn id(&self) -> String {
+        match self.command.model() {
+            Some(model) => format!("claude@{}", model),
+            None => "claude".to_string(),
         }
-
-        Err(EpisodeMatchingError::ParseError {
-            reason: "No JSON code block found in response".to_string(),
-            response: response.to_string(),
-        })
     }
 
-    /// Finds an episode in the series by season and episode number
-    fn find_episode(
-        series: &TVSeries,
-        season_num: usize,
-        episode_num: usize,
-        response: &str,
-    ) -> Result<Episode, EpisodeMatchingError> {
-        for season in &series.seasons {
-            if season.season_number == season_num {
-                for episode in &season.episodes {
-                    if episode.episode_number == episode_num {
-                        return Ok(episode.clone());
-                    }
-                }
-            }
-        }
-
-        Err(EpisodeMatchingError::NoMatchFound {
-            response: response.to_string(),
-        })
+    fn version(&self) -> u32 {
+        self.generator.version()
     }
-}
 
-impl<G: SinglePromptGenerator> EpisodeMatcher for ClaudeCodeMatcher<G> {
     fn match_episode(
         &self,
         transcript: &Transcript,
         series: &TVSeries,
-    ) -> Result<Episode, EpisodeMatchingError> {
+    ) -> Result<MatchOutcome, EpisodeMatchingError> {
         // Generate the prompt
         let prompt = self.generator.generate_single_prompt(transcript, series);
 
-        // Call Claude CLI
-        let response = Self::call_claude(&prompt)?;
-
-        // Extract JSON block
-        let json_str = Self::extract_json_block(&response)?;
-
-        // Parse JSON
-        let claude_response: ClaudeResponse =
-            serde_json::from_str(&json_str).map_err(|e| EpisodeMatchingError::ParseError {
-                reason: format!("Failed to parse JSON response: {}", e),
-                response: response.clone(),
-            })?;
+        // Call Claude CLI and unwrap the answer from its JSON envelope
+        let (response, usage) =
+            parse_claude_output(&Self::call_claude(&prompt, &self.command, self.timeout)?);
+
+        // Parse the episode from the response
+        let parsed = parse_episode_response(&response, series)?;
+
+        Ok(MatchOutcome {
+            episode: parsed.episode,
+            confidence: parsed.confidence,
+            alternatives: parsed.alternatives,
+            reasoning: parsed.reasoning,
+            extra_episodes: parsed.extra_episodes,
+            prompt,
+            response,
+            usage,
+        })
+    }
 
-        // Find matching episode
-        Self::find_episode(
-            series,
-            claude_response.season,
-            claude_response.episode,
-            &response,
-        )
+    fn infer_season(
+        &self,
+        transcript: &Transcript,
+        series: &TVSeries,
+    ) -> Result<SeasonOutcome, EpisodeMatchingError> {
+        let prompt = self.generator.generate_season_prompt(transcript, series);
+        let (response, usage) =
+            parse_claude_output(&Self::call_claude(&prompt, &self.command, self.timeout)?);
+        let season = parse_season_response(&response, series)?;
+
+        Ok(SeasonOutcome {
+            season,
+            prompt,
+            response,
+            usage,
+        })
     }
 }
@@ -3,19 +3,18 @@
 //! This module provides an implementation of the EpisodeMatcher trait that uses
 //! the Gemini CLI to match transcripts to episodes.
 
-use super::{EpisodeMatcher, EpisodeMatchingError, SinglePromptGenerator};
-use crate::metadata_retrieval::{Episode, TVSeries};
+use super::response::{parse_episode_response, parse_season_response};
+use super::usage::parse_gemini_output;
+use super::{
+    EpisodeMatcher, EpisodeMatchingError, MatchOutcome, MatcherCommand, SeasonOutcome,
+    SinglePromptGenerator,
+};
+use crate::child_process::ChildGuard;
+use crate::metadata_retrieval::TVSeries;
 use crate::speech_to_text::Transcript;
-use serde::Deserialize;
-use std::io::Write;
+use std::io::ErrorKind;
 use std::process::{Command, Stdio};
-
-/// JSON response format expected from Gemini CLI
-#[derive(Debug, Deserialize)]
-struct GeminiResponse {
-    season: usize,
-    episode: usize,
-}
+use std::time::Duration;
 
 /// Episode matcher using Gemini CLI
 ///
@@ -27,6 +26,10 @@ pub(crate) struct GeminiCliMatcher<G: SinglePromptGenerator> {
     generator: G,
     /// Optional model to use (e.g., "gemini-2.5-flash")
     model: Option<String>,
+    /// Extra arguments, working directory, and environment of the CLI
+    command: MatcherCommand,
+    /// Time after which a call of the CLI is stopped
+    timeout: Option<Duration>,
 }
 
 impl<G: SinglePromptGenerator> GeminiCliMatcher<G> {
@@ -37,57 +40,95 @@ impl<G: SinglePromptGenerator> GeminiCliMatcher<G> {
     /// * `generator` - The prompt generator to use
     /// * `model` - Optional model name (e.g., "gemini-2.5-flash")
     pub fn new(generator: G, model: Option<String>) -> Self {
-        Self { generator, model }
+        Self {
+            generator,
+            model,
+            command: MatcherCommand::default(),
+            timeout: None,
+        }
+    }
+
+    /// Runs the CLI with extra arguments, in another directory, or with
+    /// another environment
+    ///
+    /// A model given in the arguments replaces the one passed to `new`.
+    pub fn command(mut self, command: MatcherCommand) -> Self {
+        if let Some(model) = command.model() {
+            self.model = Some(model.to_string());
+        }
+        self.command = command;
+        self
+    }
+
+    /// Stops calls of the CLI that haven't finished after the timeout
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
     }
 
     /// Checks if the gemini CLI is installed and available
-    fn is_gemini_installed() -> bool {
-        Command::new("gemini")
-            .arg("--version")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map(|status| status.success())
-            .unwrap_or(false)
+    ///
+    /// A CLI that doesn't answer within the timeout counts as unavailable.
+    fn is_gemini_installed(command: &MatcherCommand, timeout: Option<Duration>) -> bool {
+        let mut cmd = Command::new("gemini");
+        command.configure(&mut cmd);
+        ChildGuard::spawn(
+            cmd.arg("--version")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null()),
+        )
+        .and_then(|mut child| child.wait_with_timeout(timeout))
+        .is_ok_and(|output| output.status.success())
     }
 
     /// Sends a prompt to Gemini CLI and returns the response
-    fn call_gemini(prompt: &str, model: &Option<String>) -> Result<String, EpisodeMatchingError> {
+    fn call_gemini(
+        prompt: &str,
+        model: &Option<String>,
+        command: &MatcherCommand,
+        timeout: Option<Duration>,
+    ) -> Result<String, EpisodeMatchingError> {
         // Check if gemini is installed
-        if !Self::is_gemini_installed() {
+        if !Self::is_gemini_installed(command, timeout) {
             return Err(EpisodeMatchingError::ServiceError(
                 "Gemini CLI not found. Please install it first.".to_string(),
             ));
         }
 
-        // Build command with optional model parameter
+        // Build command with optional model parameter, unless the extra
+        // arguments already give one
         let mut cmd = Command::new("gemini");
-        if let Some(model_name) = model {
+        command.configure(&mut cmd);
+        if let Some(model_name) = model
+            && command.model().is_none()
+        {
             cmd.arg("--model").arg(model_name);
         }
+        cmd.arg("--output-format").arg("json");
+        cmd.args(&command.args);
         cmd.stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
         // Spawn gemini process with stdin
-        let mut child = cmd.spawn().map_err(|e| {
+        let mut child = ChildGuard::spawn(&mut cmd).map_err(|e| {
             EpisodeMatchingError::ServiceError(format!("Failed to spawn gemini CLI: {}", e))
         })?;
 
-        // Write prompt to stdin
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(prompt.as_bytes()).map_err(|e| {
-                EpisodeMatchingError::ServiceError(format!(
-                    "Failed to write to gemini stdin: {}",
+        // Write the prompt to stdin, and wait for completion and capture
+        // output, stopping at the timeout
+        let output = child
+            .wait_with_input(prompt.as_bytes().to_vec(), timeout)
+            .map_err(|e| match timeout {
+                Some(timeout) if e.kind() == ErrorKind::TimedOut => {
+                    EpisodeMatchingError::TimedOut(timeout)
+                }
+                _ => EpisodeMatchingError::ServiceError(format!(
+                    "Failed to communicate with gemini CLI: {}",
                     e
-                ))
+                )),
             })?;
-        }
-
-        // Wait for completion and capture output
-        let output = child.wait_with_output().map_err(|e| {
-            EpisodeMatchingError::ServiceError(format!("Failed to read gemini output: {}", e))
-        })?;
 
         // Check exit code
         if !output.status.success() {
@@ -108,80 +149,67 @@ impl<G: SinglePromptGenerator> GeminiCliMatcher<G> {
             }
         })
     }
+}
 
-    /// Extracts JSON from markdown code fence (```json ... ```)
-    fn extract_json_block(response: &str) -> Result<String, EpisodeMatchingError> {
-        // Look for ```json ... ``` block
-        let start_marker = "```json";
-        let end_marker = "```";
-
-        if let Some(start_pos) = response.find(start_marker) {
-            let json_start = start_pos + start_marker.len();
-            let remaining = &response[json_start..];
-
-            if let Some(end_pos) = remaining.find(end_marker) {
-                let json_str = remaining[..end_pos].trim();
-                return Ok(json_str.to_string());
-            }
-        }
-
-        Err(EpisodeMatchingError::ParseError {
-            reason: "No JSON code block found in response".to_string(),
-            response: response.to_string(),
-        })
+impl<G: SinglePromptGenerator> EpisodeMatcher for GeminiCliMatcher<G> {
+    fn id(&self) -> String {
+        self.model.clone().unwrap_or_else(|| "gemini".to_string())
     }
 
-    /// Finds an episode in the series by season and episode number
-    fn find_episode(
-        series: &TVSeries,
-        season_num: usize,
-        episode_num: usize,
-        response: &str,
-    ) -> Result<Episode, EpisodeMatchingError> {
-        for season in &series.seasons {
-            if season.season_number == season_num {
-                for episode in &season.episodes {
-                    if episode.episode_number == episode_num {
-                        return Ok(episode.clone());
-                    }
-                }
-            }
-        }
-
-        Err(EpisodeMatchingError::NoMatchFound {
-            response: response.to_string(),
-        })
+    fn version(&self) -> u32 {
+        self.generator.version()
     }
-}
 
-impl<G: SinglePromptGenerator> EpisodeMatcher for GeminiCliMatcher<G> {
     fn match_episode(
         &self,
         transcript: &Transcript,
         series: &TVSeries,
-    ) -> Result<Episode, EpisodeMatchingError> {
+    ) -> Result<MatchOutcome, EpisodeMatchingError> {
         // Generate the prompt
         let prompt = self.generator.generate_single_prompt(transcript, series);
 
-        // Call Gemini CLI
-        let response = Self::call_gemini(&prompt, &self.model)?;
-
-        // Extract JSON block
-        let json_str = Self::extract_json_block(&response)?;
-
-        // Parse JSON
-        let gemini_response: GeminiResponse =
-            serde_json::from_str(&json_str).map_err(|e| EpisodeMatchingError::ParseError {
-                reason: format!("Failed to parse JSON response: {}", e),
-                response: response.clone(),
-            })?;
+        // Call Gemini CLI and unwrap the answer from its JSON envelope
+        let (response, usage) = parse_gemini_output(&Self::call_gemini(
+            &prompt,
+            &self.model,
+            &self.command,
+            self.timeout,
+        )?);
+
+        // Parse the episode from the response
+        let parsed = parse_episode_response(&response, series)?;
+
+        Ok(MatchOutcome {
+            episode: parsed.episode,
+            confidence: parsed.confidence,
+            alternatives: parsed.alternatives,
+            reasoning: parsed.reasoning,
+            extra_episodes: parsed.extra_episodes,
+            prompt,
+            response,
+            usage,
+        })
+    }
 
-        // Find matching episode
-        Self::find_episode(
-            series,
-            gemini_response.season,
-            gemini_response.episode,
-            &response,
-        )
+    fn infer_season(
+        &self,
+        transcript: &Transcript,
+        series: &TVSeries,
+    ) -> Result<SeasonOutcome, EpisodeMatchingError> {
+        let prompt = self.generator.generate_season_prompt(transcript, series);
+        let (response, usage) = parse_gemini_output(&Self::call_gemini(
+            &prompt,
+            &self.model,
+            &self.command,
+            self.timeout,
+        )?);
+        let season = parse_season_response(&response, series)?;
+
+        Ok(SeasonOutcome {
+            season,
+            prompt,
+            response,
+            usage,
+        })
     }
 }
@@ -4,14 +4,28 @@
 //! using AI/LLM-based analysis. It generates prompts for language models to help solve
 //! the mystery of which episode a video file belongs to.
 
+mod chunking;
 mod claude_code;
 mod gemini_cli;
+mod response;
+mod usage;
 
+pub(crate) use chunking::ChunkedMatcher;
+pub use chunking::DEFAULT_MAX_EPISODES_PER_PROMPT;
 pub(crate) use claude_code::ClaudeCodeMatcher;
 pub(crate) use gemini_cli::GeminiCliMatcher;
+pub use response::{
+    ParsedMatch, extract_json_block, parse_episode_response, parse_season_response,
+};
+pub use usage::TokenUsage;
+pub(crate) use usage::estimate_cost;
 
 use crate::metadata_retrieval::{Episode, TVSeries};
-use crate::speech_to_text::Transcript;
+use crate::speech_to_text::{SPEAKER_TURN_MARKER, Transcript};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Errors that can occur during episode matching
@@ -28,14 +42,182 @@ pub enum EpisodeMatchingError {
     /// No matching episode could be determined
     #[error("No matching episode found in the series\n\nFull LLM response:\n{response}")]
     NoMatchFound { response: String },
+
+    /// The AI CLI didn't answer within the matching timeout
+    #[error("AI service didn't answer within {}s and was stopped", .0.as_secs())]
+    TimedOut(Duration),
+}
+
+impl EpisodeMatchingError {
+    /// Returns the stable code of the error, like `DD-MAT-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            EpisodeMatchingError::ServiceError(_) => "DD-MAT-001",
+            EpisodeMatchingError::ParseError { .. } => "DD-MAT-002",
+            EpisodeMatchingError::NoMatchFound { .. } => "DD-MAT-003",
+            EpisodeMatchingError::TimedOut(_) => "DD-MAT-004",
+        }
+    }
+}
+
+/// How the AI CLI of a matcher is run
+///
+/// Lets runs pin a model version or pass sandbox settings without
+/// changing the prompts. The CLI's own arguments for prompting come first,
+/// followed by `args`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatcherCommand {
+    /// Arguments added to the CLI's, e.g. `--model gemini-2.5-pro`
+    ///
+    /// A model given with `--model` or `-m` replaces the one of the matcher
+    /// type and identifies the matcher, so its matches are cached apart.
+    pub args: Vec<String>,
+
+    /// Directory to run the CLI in, instead of the current one
+    pub working_dir: Option<PathBuf>,
+
+    /// Environment variables to set for the CLI
+    pub env: Vec<(String, String)>,
+}
+
+impl MatcherCommand {
+    /// Returns the model given with `--model` or `-m` in the arguments
+    pub fn model(&self) -> Option<&str> {
+        let mut args = self.args.iter();
+        while let Some(arg) = args.next() {
+            if let Some(model) = arg.strip_prefix("--model=") {
+                return Some(model);
+            }
+            if arg == "--model" || arg == "-m" {
+                return args.next().map(String::as_str);
+            }
+        }
+        None
+    }
+
+    /// Sets the working directory and environment of a command
+    ///
+    /// The arguments are left to the matcher, as they go after its own.
+    pub(crate) fn configure(&self, command: &mut Command) {
+        if let Some(dir) = &self.working_dir {
+            command.current_dir(dir);
+        }
+        command.envs(self.env.iter().map(|(key, value)| (key, value)));
+    }
+}
+
+/// An episode considered as a match, with the matcher's confidence
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EpisodeCandidate {
+    /// The candidate episode
+    pub episode: Episode,
+
+    /// The matcher's confidence in this candidate (0.0 to 1.0), if reported
+    pub confidence: Option<f64>,
+}
+
+/// The outcome of matching a transcript against a series
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchOutcome {
+    /// The episode that best matches the transcript
+    pub episode: Episode,
+
+    /// The matcher's confidence in the match (0.0 to 1.0), if reported
+    pub confidence: Option<f64>,
+
+    /// Other plausible episodes, best first
+    pub alternatives: Vec<EpisodeCandidate>,
+
+    /// The matcher's one-sentence justification of the match, if given
+    #[serde(default)]
+    pub reasoning: Option<String>,
+
+    /// Episodes directly following `episode` in the same video
+    ///
+    /// Non-empty for multi-episode files, e.g. a double episode broadcast
+    /// as one.
+    #[serde(default)]
+    pub extra_episodes: Vec<Episode>,
+
+    /// The exact prompt sent to the LLM
+    #[serde(default)]
+    pub prompt: String,
+
+    /// The full LLM response the episode was parsed from
+    pub response: String,
+
+    /// Tokens consumed by the request, if the AI backend reported them
+    pub usage: Option<TokenUsage>,
+}
+
+/// The outcome of inferring the season of a transcript
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeasonOutcome {
+    /// The season the transcript most likely belongs to
+    pub season: usize,
+
+    /// The exact prompt sent to the LLM
+    pub prompt: String,
+
+    /// The full LLM response the season was parsed from
+    pub response: String,
+
+    /// Tokens consumed by the request, if the AI backend reported them
+    pub usage: Option<TokenUsage>,
+}
+
+/// Share of episodes without a summary above which prompts match by title
+pub(crate) const MISSING_SUMMARY_THRESHOLD: f64 = 0.5;
+
+/// Counts the episodes of a series without a summary
+///
+/// Returns the number of episodes missing a summary and the total number
+/// of episodes.
+pub(crate) fn missing_summaries(series: &TVSeries) -> (usize, usize) {
+    let episodes = series.seasons.iter().flat_map(|s| &s.episodes);
+    let missing = episodes
+        .clone()
+        .filter(|e| e.summary.trim().is_empty())
+        .count();
+    (missing, episodes.count())
+}
+
+/// Whether too many episodes lack a summary to match by summaries
+///
+/// This happens for region-restricted or obscure shows. Such series are
+/// matched by episode titles and air dates instead.
+pub(crate) fn lacks_summaries(series: &TVSeries) -> bool {
+    let (missing, total) = missing_summaries(series);
+    total > 0 && missing as f64 / total as f64 > MISSING_SUMMARY_THRESHOLD
 }
 
 /// Trait for matching transcripts to episodes using AI/LLM analysis
 ///
 /// Implementors of this trait orchestrate the complete matching process:
 /// generating prompts, sending them to LLMs, parsing responses, and
-/// identifying which episode a transcript belongs to.
-pub(crate) trait EpisodeMatcher {
+/// identifying which episode a transcript belongs to. Implement it to use an
+/// AI backend other than the built-in CLIs and pass it to
+/// `Investigation::custom_matcher`. `NaivePromptGenerator` and the
+/// `parse_*_response` helpers can be reused for the prompt and the answer.
+///
+/// Matchers are shared between the threads matching videos in parallel.
+pub trait EpisodeMatcher: Send + Sync {
+    /// Identifies the matcher in matching cache keys
+    ///
+    /// Cached matches are only reused for the same ID, so it should be
+    /// unique per backend and change with every setting that affects the
+    /// results (e.g. the model).
+    fn id(&self) -> String;
+
+    /// Version of the matcher's prompts and response handling
+    ///
+    /// Part of the matching cache keys, so that improving the prompts
+    /// invalidates the matches cached with the old ones. Bump it with every
+    /// change that may lead to different matches. Defaults to 0.
+    fn version(&self) -> u32 {
+        0
+    }
+
     /// Matches a transcript to an episode from the given series
     ///
     /// This method uses AI/LLM analysis to determine which episode
@@ -48,7 +230,7 @@ pub(crate) trait EpisodeMatcher {
     ///
     /// # Returns
     ///
-    /// The episode that best matches the transcript
+    /// The episode that best matches the transcript, along with the raw response
     ///
     /// # Errors
     ///
@@ -58,7 +240,57 @@ pub(crate) trait EpisodeMatcher {
         &self,
         transcript: &Transcript,
         series: &TVSeries,
-    ) -> Result<Episode, EpisodeMatchingError>;
+    ) -> Result<MatchOutcome, EpisodeMatchingError>;
+
+    /// Infers which season of the series a transcript belongs to
+    ///
+    /// This is a cheap pre-pass for narrowing down the episode candidates
+    /// before matching. It is given a short transcript excerpt and only
+    /// needs an overview of the seasons.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the AI service fails, the response cannot be parsed,
+    /// or the answer names a season the series doesn't have. The default
+    /// implementation doesn't support season inference and always fails,
+    /// which makes the pipeline fall back to matching against all seasons.
+    fn infer_season(
+        &self,
+        transcript: &Transcript,
+        series: &TVSeries,
+    ) -> Result<SeasonOutcome, EpisodeMatchingError> {
+        let _ = (transcript, series);
+        Err(EpisodeMatchingError::ServiceError(
+            "Season inference is not supported by this matcher".to_string(),
+        ))
+    }
+}
+
+/// Boxed matchers, such as the ones built for a `MatcherType`, are matchers too
+impl<M: EpisodeMatcher + ?Sized> EpisodeMatcher for Box<M> {
+    fn id(&self) -> String {
+        (**self).id()
+    }
+
+    fn version(&self) -> u32 {
+        (**self).version()
+    }
+
+    fn match_episode(
+        &self,
+        transcript: &Transcript,
+        series: &TVSeries,
+    ) -> Result<MatchOutcome, EpisodeMatchingError> {
+        (**self).match_episode(transcript, series)
+    }
+
+    fn infer_season(
+        &self,
+        transcript: &Transcript,
+        series: &TVSeries,
+    ) -> Result<SeasonOutcome, EpisodeMatchingError> {
+        (**self).infer_season(transcript, series)
+    }
 }
 
 /// Trait for generating prompts for LLM-based episode matching
@@ -66,7 +298,16 @@ pub(crate) trait EpisodeMatcher {
 /// Implementors of this trait take transcript data and episode metadata
 /// to construct effective prompts that help the LLM solve the mystery
 /// of which episode the video belongs to.
-pub(crate) trait SinglePromptGenerator {
+pub trait SinglePromptGenerator: Send + Sync {
+    /// Version of the generated prompts
+    ///
+    /// Matchers using the generator report it as their version (see
+    /// `EpisodeMatcher::version`), so bump it with every change to the
+    /// prompts. Defaults to 0.
+    fn version(&self) -> u32 {
+        0
+    }
+
     /// Generates a prompt for matching a transcript against episodes in a series
     ///
     /// This prompt asks the LLM to analyze the transcript and identify which
@@ -81,13 +322,27 @@ pub(crate) trait SinglePromptGenerator {
     ///
     /// A formatted prompt string ready to send to an LLM
     fn generate_single_prompt(&self, transcript: &Transcript, series: &TVSeries) -> String;
+
+    /// Generates a prompt for inferring the season a transcript belongs to
+    ///
+    /// The answer is expected as JSON in the format `{"season": XX}`.
+    fn generate_season_prompt(&self, transcript: &Transcript, series: &TVSeries) -> String;
 }
 
+/// Version of the prompts of `NaivePromptGenerator`
+///
+/// Bump it whenever a prompt changes, so that matches cached with the old
+/// prompts are made again.
+const NAIVE_PROMPT_VERSION: u32 = 1;
+
 /// A naive prompt generator implementation
 ///
 /// This generator creates straightforward prompts that instruct the LLM
-/// to match transcripts to episodes and return results in JSON format.
-pub(crate) struct NaivePromptGenerator;
+/// to match transcripts to episodes and return results in JSON format,
+/// as expected by `parse_episode_response` and `parse_season_response`.
+/// If most episodes lack a summary, the matching prompt asks to match by
+/// episode titles and air dates instead.
+pub struct NaivePromptGenerator;
 
 impl Default for NaivePromptGenerator {
     fn default() -> Self {
@@ -96,15 +351,26 @@ impl Default for NaivePromptGenerator {
 }
 
 impl SinglePromptGenerator for NaivePromptGenerator {
+    fn version(&self) -> u32 {
+        NAIVE_PROMPT_VERSION
+    }
+
     fn generate_single_prompt(&self, transcript: &Transcript, series: &TVSeries) -> String {
+        let title_only = lacks_summaries(series);
         let mut prompt = String::new();
 
         // Add JSON format instructions
         prompt.push_str("IMPORTANT: Your output to the following MUST be JSON in the FORMAT ");
-        prompt.push_str(r#"{"season": XX, "episode": YY}. "#);
+        prompt.push_str(r#"{"season": XX, "episode": YY, "confidence": C, "reasoning": "R", "alternatives": [{"season": XX, "episode": YY, "confidence": C}], "extra_episodes": [{"season": XX, "episode": YY}]}. "#);
         prompt
             .push_str("NOTHING ELSE IS TO BE RETURNED. ONLY EVER ANSWER WITH THIS JSON Structure.");
         prompt.push_str("The JSON is to be encapsulated in a markdown jsonblock ```json\n\n");
+        prompt.push_str(
+            "C is your confidence in the respective episode as a number between 0.0 and 1.0. ",
+        );
+        prompt.push_str("R is a single sentence justifying your choice, naming the dialogue that ties the transcript to the episode. ");
+        prompt.push_str("List up to 3 other plausible episodes as alternatives, most likely first, or leave the list empty if no other episode fits. ");
+        prompt.push_str("If the transcript spans several consecutive episodes of the same season (e.g. a double episode), answer with the first one and list the following ones as extra_episodes, otherwise leave that list empty.\n\n");
 
         // Add task description
         prompt.push_str("Using this structure answer the following question:\n");
@@ -113,6 +379,16 @@ impl SinglePromptGenerator for NaivePromptGenerator {
             "identified by their Season number, Episode number, title and short summary, ",
         );
         prompt.push_str("match the transcript to the best fitting short summary, to identify which episode the given transcript belongs to.\n\n");
+        if title_only {
+            prompt.push_str("Most candidates have no summary, so match by the episode titles instead: look for names, places, objects and events from the titles in the dialogue. ");
+            prompt.push_str("Use the air dates as additional hints, e.g. for holidays, seasons of the year or current events mentioned in the dialogue.\n\n");
+        }
+        if transcript.text.contains(SPEAKER_TURN_MARKER) {
+            prompt.push_str(&format!(
+                "{} in the transcript marks where a different character starts speaking. Use it to tell who says what, e.g. which characters talk to each other.\n\n",
+                SPEAKER_TURN_MARKER
+            ));
+        }
 
         // Add reflection instruction
         prompt.push_str("Ultrathink about this and reflect on your reasoning, before providing ONLY THE REQUESTED ANSWER FORMAT.\n\n");
@@ -140,10 +416,146 @@ impl SinglePromptGenerator for NaivePromptGenerator {
                     "Season: {}, Episode: {} - {}\n",
                     episode.season_number, episode.episode_number, episode.name
                 ));
-                prompt.push_str(&format!("Summary: {}\n\n", episode.summary));
+                if title_only {
+                    if let Some(airdate) = &episode.airdate {
+                        prompt.push_str(&format!("Aired: {}\n", airdate));
+                    }
+                    if !episode.summary.trim().is_empty() {
+                        prompt.push_str(&format!("Summary: {}\n", episode.summary));
+                    }
+                    prompt.push('\n');
+                } else {
+                    prompt.push_str(&format!("Summary: {}\n\n", episode.summary));
+                }
             }
         }
 
         prompt
     }
+
+    fn generate_season_prompt(&self, transcript: &Transcript, series: &TVSeries) -> String {
+        let mut prompt = String::new();
+
+        // Add JSON format instructions
+        prompt.push_str("IMPORTANT: Your output to the following MUST be JSON in the FORMAT ");
+        prompt.push_str(r#"{"season": XX}. "#);
+        prompt
+            .push_str("NOTHING ELSE IS TO BE RETURNED. ONLY EVER ANSWER WITH THIS JSON Structure.");
+        prompt.push_str("The JSON is to be encapsulated in a markdown jsonblock ```json\n\n");
+
+        // Add task description
+        prompt.push_str("Using this structure answer the following question:\n");
+        prompt.push_str("Based on the given excerpt of a transcript of a tv series episode as well as an overview of the seasons of the series ");
+        prompt.push_str("listing the titles of their episodes, identify which season the transcript most likely belongs to. ");
+        prompt.push_str("Pay attention to character names, relationships and plot points that only fit a specific season.\n\n");
+
+        // Add data header
+        prompt.push_str("Here follows the mentioned data:\n\n");
+
+        // Add transcript section
+        prompt.push_str("=== TRANSCRIPT EXCERPT ===\n");
+        prompt.push_str(&format!("Language: {}\n\n", transcript.language));
+        prompt.push_str(&transcript.text);
+        prompt.push_str("\n\n");
+
+        // Add season overview section
+        prompt.push_str(&format!("=== SEASONS OF '{}' ===\n\n", series.name));
+
+        for season in &series.seasons {
+            let titles = season
+                .episodes
+                .iter()
+                .map(|e| e.name.as_str())
+                .collect::<Vec<_>>()
+                .join("; ");
+            prompt.push_str(&format!("Season {}: {}\n", season.season_number, titles));
+        }
+
+        prompt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_retrieval::Season;
+
+    fn series(summaries: &[&str]) -> TVSeries {
+        TVSeries {
+            name: "Show".to_string(),
+            year: None,
+            seasons: vec![Season {
+                season_number: 1,
+                episodes: summaries
+                    .iter()
+                    .enumerate()
+                    .map(|(index, summary)| Episode {
+                        season_number: 1,
+                        episode_number: index + 1,
+                        name: format!("Episode {}", index + 1),
+                        summary: summary.to_string(),
+                        summary_source: None,
+                        airdate: Some(format!("2001-01-{:02}", index + 1)),
+                        runtime: None,
+                        season_name: None,
+                        provider_id: None,
+                        local_title: None,
+                    })
+                    .collect(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_lacks_summaries() {
+        assert!(!lacks_summaries(&series(&["A heist.", "", "A wedding."])));
+        assert!(lacks_summaries(&series(&["A heist.", "", " "])));
+        assert!(!lacks_summaries(&series(&[])));
+    }
+
+    #[test]
+    fn test_title_only_prompt_lists_air_dates() {
+        let transcript = Transcript::new("Merry Christmas!", "en");
+
+        let prompt = NaivePromptGenerator
+            .generate_single_prompt(&transcript, &series(&["A heist.", "", ""]));
+        assert!(prompt.contains("match by the episode titles instead"));
+        assert!(prompt.contains("Aired: 2001-01-02\n"));
+        assert!(prompt.contains("Summary: A heist.\n"));
+        assert!(!prompt.contains("Summary: \n"));
+
+        let prompt =
+            NaivePromptGenerator.generate_single_prompt(&transcript, &series(&["A heist.", ""]));
+        assert!(!prompt.contains("Aired:"));
+    }
+
+    #[test]
+    fn test_prompt_explains_speaker_turns() {
+        let series = series(&["A heist."]);
+
+        let transcript = Transcript::new("Say my name.", "en");
+        let prompt = NaivePromptGenerator.generate_single_prompt(&transcript, &series);
+        assert!(!prompt.contains("starts speaking"));
+
+        let transcript = Transcript::new("Say my name. [SPEAKER_TURN] Heisenberg.", "en");
+        let prompt = NaivePromptGenerator.generate_single_prompt(&transcript, &series);
+        assert!(prompt.contains("[SPEAKER_TURN] in the transcript marks"));
+    }
+
+    #[test]
+    fn test_matcher_command_model() {
+        let command = |args: &[&str]| MatcherCommand {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            ..MatcherCommand::default()
+        };
+        assert_eq!(command(&[]).model(), None);
+        assert_eq!(command(&["--max-turns", "3"]).model(), None);
+        assert_eq!(
+            command(&["--sandbox", "--model", "gemini-2.5-pro"]).model(),
+            Some("gemini-2.5-pro")
+        );
+        assert_eq!(command(&["--model=opus"]).model(), Some("opus"));
+        assert_eq!(command(&["-m", "sonnet"]).model(), Some("sonnet"));
+        assert_eq!(command(&["--model"]).model(), None);
+    }
 }
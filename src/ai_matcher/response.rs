@@ -0,0 +1,311 @@
+//! Response parsing shared by the CLI-based matchers
+//!
+//! All matchers ask the LLM for the same JSON answer wrapped in a markdown
+//! code fence. This module extracts and interprets that answer.
+
+use super::{EpisodeCandidate, EpisodeMatchingError};
+use crate::metadata_retrieval::{Episode, TVSeries};
+use serde::Deserialize;
+
+/// JSON response format expected from the LLM
+#[derive(Debug, Deserialize)]
+struct EpisodeResponse {
+    season: usize,
+    episode: usize,
+    #[serde(default)]
+    confidence: Option<f64>,
+    #[serde(default)]
+    alternatives: Vec<CandidateResponse>,
+    #[serde(default)]
+    reasoning: Option<String>,
+    #[serde(default)]
+    extra_episodes: Vec<EpisodeNumber>,
+}
+
+/// A further episode contained in the same video, as listed in the LLM response
+#[derive(Debug, Deserialize)]
+struct EpisodeNumber {
+    season: usize,
+    episode: usize,
+}
+
+/// An alternative episode listed in the LLM response
+#[derive(Debug, Deserialize)]
+struct CandidateResponse {
+    season: usize,
+    episode: usize,
+    #[serde(default)]
+    confidence: Option<f64>,
+}
+
+/// An episode match parsed from an LLM response
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedMatch {
+    /// The best matching episode
+    pub episode: Episode,
+    /// The LLM's confidence in the best match (0.0 to 1.0)
+    pub confidence: Option<f64>,
+    /// Other plausible episodes, best first
+    pub alternatives: Vec<EpisodeCandidate>,
+    /// The LLM's short justification of the best match
+    pub reasoning: Option<String>,
+    /// Episodes directly following the best match in the same video
+    pub extra_episodes: Vec<Episode>,
+}
+
+/// JSON response format expected from the LLM when inferring a season
+#[derive(Debug, Deserialize)]
+struct SeasonResponse {
+    season: usize,
+}
+
+/// Extracts JSON from markdown code fence (```json ... ```)
+pub fn extract_json_block(response: &str) -> Result<String, EpisodeMatchingError> {
+    // Look for ```json ... ``` block
+    let start_marker = "```json";
+    let end_marker = "```";
+
+    if let Some(start_pos) = response.find(start_marker) {
+        let json_start = start_pos + start_marker.len();
+        let remaining = &response[json_start..];
+
+        if let Some(end_pos) = remaining.find(end_marker) {
+            let json_str = remaining[..end_pos].trim();
+            return Ok(json_str.to_string());
+        }
+    }
+
+    Err(EpisodeMatchingError::ParseError {
+        reason: "No JSON code block found in response".to_string(),
+        response: response.to_string(),
+    })
+}
+
+/// Finds an episode in the series by season and episode number
+pub(crate) fn find_episode(
+    series: &TVSeries,
+    season_num: usize,
+    episode_num: usize,
+    response: &str,
+) -> Result<Episode, EpisodeMatchingError> {
+    for season in &series.seasons {
+        if season.season_number == season_num {
+            for episode in &season.episodes {
+                if episode.episode_number == episode_num {
+                    return Ok(episode.clone());
+                }
+            }
+        }
+    }
+
+    Err(EpisodeMatchingError::NoMatchFound {
+        response: response.to_string(),
+    })
+}
+
+/// Parses a full LLM response into the matching episode of the series
+///
+/// Extracts the JSON code block, reads the season and episode numbers from
+/// it, and looks the episode up in the series. Confidence values are clamped
+/// to the range 0.0 to 1.0. Alternatives that are not part of the series or
+/// repeat the best match are dropped, as is blank reasoning. Extra episodes
+/// are only kept as long as they continue the best match without a gap
+/// within its season, as a multi-episode file name can only state a range.
+pub fn parse_episode_response(
+    response: &str,
+    series: &TVSeries,
+) -> Result<ParsedMatch, EpisodeMatchingError> {
+    // Extract JSON block
+    let json_str = extract_json_block(response)?;
+
+    // Parse JSON
+    let parsed: EpisodeResponse =
+        serde_json::from_str(&json_str).map_err(|e| EpisodeMatchingError::ParseError {
+            reason: format!("Failed to parse JSON response: {}", e),
+            response: response.to_string(),
+        })?;
+
+    // Find matching episode
+    let episode = find_episode(series, parsed.season, parsed.episode, response)?;
+
+    // Resolve alternatives, skipping unknown and repeated episodes
+    let mut alternatives: Vec<EpisodeCandidate> = Vec::new();
+    for candidate in parsed.alternatives {
+        let Ok(alternative) = find_episode(series, candidate.season, candidate.episode, response)
+        else {
+            continue;
+        };
+
+        if alternative == episode || alternatives.iter().any(|a| a.episode == alternative) {
+            continue;
+        }
+
+        alternatives.push(EpisodeCandidate {
+            episode: alternative,
+            confidence: candidate.confidence.map(|c| c.clamp(0.0, 1.0)),
+        });
+    }
+
+    // Resolve extra episodes, stopping at the first one not continuing the run
+    let mut numbers: Vec<usize> = parsed
+        .extra_episodes
+        .iter()
+        .filter(|e| e.season == episode.season_number)
+        .map(|e| e.episode)
+        .collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+
+    let mut extra_episodes: Vec<Episode> = Vec::new();
+    for number in numbers {
+        let last = extra_episodes.last().unwrap_or(&episode).episode_number;
+        if number <= last {
+            continue;
+        }
+        if number != last + 1 {
+            break;
+        }
+        let Ok(extra) = find_episode(series, episode.season_number, number, response) else {
+            break;
+        };
+        extra_episodes.push(extra);
+    }
+
+    Ok(ParsedMatch {
+        episode,
+        confidence: parsed.confidence.map(|c| c.clamp(0.0, 1.0)),
+        alternatives,
+        reasoning: parsed
+            .reasoning
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty()),
+        extra_episodes,
+    })
+}
+
+/// Parses a full LLM response into a season number of the series
+///
+/// Fails with `NoMatchFound` if the series has no such season.
+pub fn parse_season_response(
+    response: &str,
+    series: &TVSeries,
+) -> Result<usize, EpisodeMatchingError> {
+    let json_str = extract_json_block(response)?;
+
+    let parsed: SeasonResponse =
+        serde_json::from_str(&json_str).map_err(|e| EpisodeMatchingError::ParseError {
+            reason: format!("Failed to parse JSON response: {}", e),
+            response: response.to_string(),
+        })?;
+
+    if series
+        .seasons
+        .iter()
+        .any(|s| s.season_number == parsed.season)
+    {
+        Ok(parsed.season)
+    } else {
+        Err(EpisodeMatchingError::NoMatchFound {
+            response: response.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_retrieval::Season;
+
+    fn series() -> TVSeries {
+        let episode = |number: usize| Episode {
+            season_number: 1,
+            episode_number: number,
+            name: format!("Episode {}", number),
+            summary: String::new(),
+            summary_source: None,
+            airdate: None,
+            runtime: None,
+            season_name: None,
+            provider_id: None,
+            local_title: None,
+        };
+
+        TVSeries {
+            name: "Test Show".to_string(),
+            year: None,
+            seasons: vec![Season {
+                season_number: 1,
+                episodes: vec![episode(1), episode(2), episode(3)],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_parse_episode_response_with_alternatives() {
+        let response = r#"Thinking...
+```json
+{"season": 1, "episode": 2, "confidence": 1.4, "reasoning": " Walt names himself. ", "alternatives": [
+    {"season": 1, "episode": 3, "confidence": 0.2},
+    {"season": 1, "episode": 2, "confidence": 0.1},
+    {"season": 4, "episode": 1, "confidence": 0.1}
+]}
+```"#;
+
+        let parsed = parse_episode_response(response, &series()).unwrap();
+
+        assert_eq!(parsed.episode.episode_number, 2);
+        assert_eq!(parsed.confidence, Some(1.0));
+        assert_eq!(parsed.reasoning.as_deref(), Some("Walt names himself."));
+        assert_eq!(parsed.alternatives.len(), 1);
+        assert_eq!(parsed.alternatives[0].episode.episode_number, 3);
+        assert_eq!(parsed.alternatives[0].confidence, Some(0.2));
+    }
+
+    #[test]
+    fn test_parse_episode_response_minimal() {
+        let response = "```json\n{\"season\": 1, \"episode\": 1}\n```";
+
+        let parsed = parse_episode_response(response, &series()).unwrap();
+
+        assert_eq!(parsed.episode.episode_number, 1);
+        assert_eq!(parsed.confidence, None);
+        assert_eq!(parsed.reasoning, None);
+        assert!(parsed.alternatives.is_empty());
+        assert!(parsed.extra_episodes.is_empty());
+    }
+
+    #[test]
+    fn test_parse_episode_response_with_extra_episodes() {
+        let response = r#"```json
+{"season": 1, "episode": 1, "extra_episodes": [
+    {"season": 1, "episode": 3},
+    {"season": 1, "episode": 2},
+    {"season": 1, "episode": 1}
+]}
+```"#;
+
+        let parsed = parse_episode_response(response, &series()).unwrap();
+
+        let numbers: Vec<usize> = parsed
+            .extra_episodes
+            .iter()
+            .map(|e| e.episode_number)
+            .collect();
+        assert_eq!(numbers, vec![2, 3]);
+
+        // Gaps end the run
+        let response = "```json\n{\"season\": 1, \"episode\": 1, \"extra_episodes\": [{\"season\": 1, \"episode\": 3}]}\n```";
+        let parsed = parse_episode_response(response, &series()).unwrap();
+        assert!(parsed.extra_episodes.is_empty());
+    }
+
+    #[test]
+    fn test_parse_episode_response_unknown_episode() {
+        let response = "```json\n{\"season\": 2, \"episode\": 1}\n```";
+
+        assert!(matches!(
+            parse_episode_response(response, &series()),
+            Err(EpisodeMatchingError::NoMatchFound { .. })
+        ));
+    }
+}
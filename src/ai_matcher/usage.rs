@@ -0,0 +1,236 @@
+//! Token usage tracking for AI matchers
+//!
+//! Both supported CLIs can report the tokens consumed by a request when asked
+//! for JSON output. This module parses those envelopes into the answer text
+//! and a `TokenUsage`, and estimates costs where the CLI doesn't report them.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+/// Tokens consumed by one or more matcher invocations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    /// Tokens sent to the model (prompt, including cached input)
+    pub input_tokens: u64,
+
+    /// Tokens generated by the model (answer, including reasoning)
+    pub output_tokens: u64,
+
+    /// Cost in US dollars, as reported by the CLI or estimated from list prices
+    ///
+    /// None if neither the CLI nor the price table knows the model.
+    pub cost_usd: Option<f64>,
+}
+
+impl TokenUsage {
+    /// Returns the sum of input and output tokens
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+impl Add for TokenUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let cost_usd = match (self.cost_usd, other.cost_usd) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+
+        Self {
+            input_tokens: self.input_tokens + other.input_tokens,
+            output_tokens: self.output_tokens + other.output_tokens,
+            cost_usd,
+        }
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for TokenUsage {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// Output envelope of `claude -p --output-format json`
+#[derive(Debug, Deserialize)]
+struct ClaudeOutput {
+    result: String,
+    total_cost_usd: Option<f64>,
+    usage: Option<ClaudeUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: u64,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+/// Output envelope of `gemini --output-format json`
+#[derive(Debug, Deserialize)]
+struct GeminiOutput {
+    response: String,
+    stats: Option<GeminiStats>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiStats {
+    #[serde(default)]
+    models: HashMap<String, GeminiModelStats>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiModelStats {
+    tokens: GeminiTokens,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiTokens {
+    #[serde(default)]
+    prompt: u64,
+    #[serde(default)]
+    candidates: u64,
+    #[serde(default)]
+    thoughts: u64,
+}
+
+/// List prices in US dollars per million (input, output) tokens
+///
+/// Used for Gemini CLI, which doesn't report costs, and for estimating runs
+/// up front. Longer model names are listed first, as lookups match by prefix.
+const MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("gemini-2.5-flash-lite", 0.10, 0.40),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.5-pro", 1.25, 10.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+];
+
+/// Estimates the cost of a request from the list prices of the model
+pub(crate) fn estimate_cost(model: &str, input_tokens: u64, output_tokens: u64) -> Option<f64> {
+    MODEL_PRICES
+        .iter()
+        .find(|(prefix, _, _)| model.starts_with(prefix))
+        .map(|(_, input_price, output_price)| {
+            (input_tokens as f64 * input_price + output_tokens as f64 * output_price) / 1_000_000.0
+        })
+}
+
+/// Splits Claude CLI JSON output into the answer text and its token usage
+///
+/// Output that isn't a JSON envelope is returned unchanged without usage.
+pub(crate) fn parse_claude_output(output: &str) -> (String, Option<TokenUsage>) {
+    let Ok(envelope) = serde_json::from_str::<ClaudeOutput>(output) else {
+        return (output.to_string(), None);
+    };
+
+    let usage = envelope.usage.map(|usage| TokenUsage {
+        input_tokens: usage.input_tokens
+            + usage.cache_creation_input_tokens
+            + usage.cache_read_input_tokens,
+        output_tokens: usage.output_tokens,
+        cost_usd: envelope.total_cost_usd,
+    });
+
+    (envelope.result, usage)
+}
+
+/// Splits Gemini CLI JSON output into the answer text and its token usage
+///
+/// The CLI reports tokens per model, as it may route parts of a request to
+/// different models. Output that isn't a JSON envelope is returned unchanged
+/// without usage.
+pub(crate) fn parse_gemini_output(output: &str) -> (String, Option<TokenUsage>) {
+    let Ok(envelope) = serde_json::from_str::<GeminiOutput>(output) else {
+        return (output.to_string(), None);
+    };
+
+    let usage = envelope.stats.and_then(|stats| {
+        stats
+            .models
+            .iter()
+            .map(|(model, stats)| {
+                let input_tokens = stats.tokens.prompt;
+                let output_tokens = stats.tokens.candidates + stats.tokens.thoughts;
+                TokenUsage {
+                    input_tokens,
+                    output_tokens,
+                    cost_usd: estimate_cost(model, input_tokens, output_tokens),
+                }
+            })
+            .reduce(Add::add)
+    });
+
+    (envelope.response, usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_claude_output() {
+        let output = r#"{"type":"result","result":"```json\n{\"season\": 1, \"episode\": 2}\n```","total_cost_usd":0.0421,"usage":{"input_tokens":12,"cache_creation_input_tokens":1000,"cache_read_input_tokens":500,"output_tokens":80}}"#;
+
+        let (response, usage) = parse_claude_output(output);
+
+        assert!(response.starts_with("```json"));
+        let usage = usage.unwrap();
+        assert_eq!(usage.input_tokens, 1512);
+        assert_eq!(usage.output_tokens, 80);
+        assert_eq!(usage.cost_usd, Some(0.0421));
+    }
+
+    #[test]
+    fn test_parse_gemini_output_estimates_cost() {
+        let output = r#"{"response":"{\"season\": 1, \"episode\": 2}","stats":{"models":{"gemini-2.5-flash":{"tokens":{"prompt":1000000,"candidates":100000,"total":1300000,"thoughts":100000}}}}}"#;
+
+        let (response, usage) = parse_gemini_output(output);
+
+        assert_eq!(response, r#"{"season": 1, "episode": 2}"#);
+        let usage = usage.unwrap();
+        assert_eq!(usage.input_tokens, 1_000_000);
+        assert_eq!(usage.output_tokens, 200_000);
+        assert!((usage.cost_usd.unwrap() - 0.80).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_plain_output_has_no_usage() {
+        let (response, usage) = parse_gemini_output("```json\n{}\n```");
+
+        assert_eq!(response, "```json\n{}\n```");
+        assert_eq!(usage, None);
+    }
+
+    #[test]
+    fn test_sum_keeps_known_costs() {
+        let known = TokenUsage {
+            input_tokens: 10,
+            output_tokens: 5,
+            cost_usd: Some(0.5),
+        };
+        let unknown = TokenUsage {
+            input_tokens: 1,
+            output_tokens: 1,
+            cost_usd: None,
+        };
+
+        let total: TokenUsage = [known, unknown].into_iter().sum();
+
+        assert_eq!(total.total_tokens(), 17);
+        assert_eq!(total.cost_usd, Some(0.5));
+    }
+}
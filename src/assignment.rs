@@ -0,0 +1,373 @@
+//! Global episode assignment module
+//!
+//! Each video is matched on its own, so two videos can end up with the same
+//! episode. `detect_duplicates` only reports such collisions. This module
+//! resolves them instead: from the candidates and confidences of all matches
+//! it computes the one-to-one assignment of videos to episodes with the
+//! highest total confidence (Hungarian algorithm).
+
+use crate::cross_check::{check_filename, check_runtime};
+use crate::{EpisodeCandidate, MatchResult};
+
+/// Confidence assumed for a best match without a reported confidence
+const DEFAULT_CONFIDENCE: f64 = 1.0;
+
+/// Cost of leaving a video unassigned (equal to a candidate with confidence 0)
+const UNASSIGNED_COST: f64 = 1.0;
+
+/// Cost of assigning a video to an episode that isn't one of its candidates
+const FORBIDDEN_COST: f64 = 1e9;
+
+/// The result of assigning episodes to videos one-to-one
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpisodeAssignment {
+    /// Matches with a unique episode each
+    ///
+    /// Videos assigned to one of their alternatives have that alternative
+    /// as their episode, with the original best match moved to the
+    /// alternatives.
+    pub assigned: Vec<MatchResult>,
+
+    /// Matches whose candidates were all assigned to other videos
+    pub unassigned: Vec<MatchResult>,
+}
+
+/// Assigns each video a unique episode, maximizing the total confidence
+///
+/// Every match contributes its best episode and its alternatives as
+/// candidates. A video is only ever assigned to one of its own candidates; if
+/// all of them are taken by videos with higher confidence, it ends up
+/// unassigned. Matches without collisions keep their best episode, and parts
+/// of a split episode keep sharing it. The order of the matches is preserved
+/// within both lists.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dialog_detective::assign_episodes;
+/// # let matches = Vec::new();
+///
+/// let assignment = assign_episodes(matches);
+/// for unassigned in &assignment.unassigned {
+///     println!("No unique episode for {}", unassigned.video.path.display());
+/// }
+/// ```
+pub fn assign_episodes(matches: Vec<MatchResult>) -> EpisodeAssignment {
+    // Parts share their episode by design and take no part in the assignment
+    let (parts, matches): (Vec<_>, Vec<_>) = matches
+        .into_iter()
+        .enumerate()
+        .partition(|(_, result)| result.part.is_some());
+    if matches.is_empty() {
+        return EpisodeAssignment {
+            assigned: parts.into_iter().map(|(_, result)| result).collect(),
+            unassigned: Vec::new(),
+        };
+    }
+    let (order, matches): (Vec<usize>, Vec<MatchResult>) = matches.into_iter().unzip();
+
+    // Candidates of every video, best first
+    let candidates: Vec<Vec<EpisodeCandidate>> = matches.iter().map(all_candidates).collect();
+
+    // Distinct episodes across all candidates become the columns
+    let mut episodes: Vec<(usize, usize)> = Vec::new();
+    for candidate in candidates.iter().flatten() {
+        let key = episode_key(candidate);
+        if !episodes.contains(&key) {
+            episodes.push(key);
+        }
+    }
+
+    // One row per video; episode columns followed by one "unassigned" column per video
+    let cost: Vec<Vec<f64>> = candidates
+        .iter()
+        .map(|video_candidates| {
+            let mut row = vec![FORBIDDEN_COST; episodes.len()];
+            for candidate in video_candidates {
+                let column = episodes
+                    .iter()
+                    .position(|&key| key == episode_key(candidate))
+                    .expect("episode column exists");
+                let confidence = candidate.confidence.unwrap_or(0.0);
+                row[column] = row[column].min(1.0 - confidence);
+            }
+            row.extend(std::iter::repeat_n(UNASSIGNED_COST, candidates.len()));
+            row
+        })
+        .collect();
+
+    let columns = hungarian(&cost);
+
+    let mut assigned = parts;
+    let mut unassigned = Vec::new();
+    for (((index, mut result), video_candidates), column) in
+        order.into_iter().zip(matches).zip(candidates).zip(columns)
+    {
+        let Some(&key) = episodes.get(column) else {
+            unassigned.push(result);
+            continue;
+        };
+
+        let (chosen, others): (Vec<_>, Vec<_>) = video_candidates
+            .into_iter()
+            .partition(|candidate| episode_key(candidate) == key);
+        let chosen = chosen.into_iter().next().expect("chosen candidate exists");
+
+        // A different episode may resolve or introduce a conflict with the file name
+        let previous_claim = result
+            .filename_conflict
+            .take()
+            .and_then(|conflict| conflict.episode);
+        result.filename_conflict = check_filename(
+            &result.video.path,
+            &chosen.episode,
+            others
+                .iter()
+                .map(|candidate| &candidate.episode)
+                .chain(&previous_claim),
+        );
+
+        if key != (result.episode.season_number, result.episode.episode_number) {
+            // The reasoning and extra episodes belonged to the episode the file lost
+            result.reasoning = None;
+            result.extra_episodes.clear();
+            result.runtime_mismatch = result
+                .video_duration
+                .and_then(|duration| check_runtime(duration, [&chosen.episode]));
+        }
+
+        result.episode = chosen.episode;
+        result.confidence = chosen.confidence;
+        result.alternatives = others;
+        assigned.push((index, result));
+    }
+
+    // Restore the order of the matches around the parts
+    assigned.sort_by_key(|(index, _)| *index);
+    EpisodeAssignment {
+        assigned: assigned.into_iter().map(|(_, result)| result).collect(),
+        unassigned,
+    }
+}
+
+/// Returns the best episode and the alternatives of a match as candidates
+fn all_candidates(result: &MatchResult) -> Vec<EpisodeCandidate> {
+    let best = EpisodeCandidate {
+        episode: result.episode.clone(),
+        confidence: Some(result.confidence.unwrap_or(DEFAULT_CONFIDENCE)),
+    };
+
+    std::iter::once(best)
+        .chain(result.alternatives.iter().cloned())
+        .collect()
+}
+
+/// Identifies the episode of a candidate by season and episode number
+fn episode_key(candidate: &EpisodeCandidate) -> (usize, usize) {
+    (
+        candidate.episode.season_number,
+        candidate.episode.episode_number,
+    )
+}
+
+/// Solves the assignment problem for a cost matrix with at least as many columns as rows
+///
+/// Returns the column assigned to each row, minimizing the total cost.
+fn hungarian(cost: &[Vec<f64>]) -> Vec<usize> {
+    let rows = cost.len();
+    let columns = cost[0].len();
+
+    // Potentials and matching use 1-based indices, index 0 is a virtual column
+    let mut row_potential = vec![0.0; rows + 1];
+    let mut column_potential = vec![0.0; columns + 1];
+    let mut column_row = vec![0usize; columns + 1];
+    let mut previous = vec![0usize; columns + 1];
+
+    for row in 1..=rows {
+        column_row[0] = row;
+        let mut current = 0;
+        let mut min_slack = vec![f64::INFINITY; columns + 1];
+        let mut used = vec![false; columns + 1];
+
+        loop {
+            used[current] = true;
+            let current_row = column_row[current];
+            let mut delta = f64::INFINITY;
+            let mut next = 0;
+
+            for column in 1..=columns {
+                if used[column] {
+                    continue;
+                }
+
+                let slack = cost[current_row - 1][column - 1]
+                    - row_potential[current_row]
+                    - column_potential[column];
+                if slack < min_slack[column] {
+                    min_slack[column] = slack;
+                    previous[column] = current;
+                }
+                if min_slack[column] < delta {
+                    delta = min_slack[column];
+                    next = column;
+                }
+            }
+
+            for column in 0..=columns {
+                if used[column] {
+                    row_potential[column_row[column]] += delta;
+                    column_potential[column] -= delta;
+                } else {
+                    min_slack[column] -= delta;
+                }
+            }
+
+            current = next;
+            if column_row[current] == 0 {
+                break;
+            }
+        }
+
+        // Flip the augmenting path
+        while current != 0 {
+            let prev = previous[current];
+            column_row[current] = column_row[prev];
+            current = prev;
+        }
+    }
+
+    let mut assignment = vec![0; rows];
+    for column in 1..=columns {
+        if column_row[column] != 0 {
+            assignment[column_row[column] - 1] = column - 1;
+        }
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Episode, EpisodePart, StageTimings, VideoFile};
+    use std::path::PathBuf;
+
+    fn episode(number: usize) -> Episode {
+        Episode {
+            season_number: 1,
+            episode_number: number,
+            name: format!("Episode {}", number),
+            summary: String::new(),
+            summary_source: None,
+            airdate: None,
+            runtime: None,
+            season_name: None,
+            provider_id: None,
+            local_title: None,
+        }
+    }
+
+    fn result(file: &str, best: (usize, f64), alternatives: &[(usize, f64)]) -> MatchResult {
+        MatchResult {
+            video: VideoFile::new(file),
+            video_hash: String::new(),
+            episode: episode(best.0),
+            confidence: Some(best.1),
+            alternatives: alternatives
+                .iter()
+                .map(|&(number, confidence)| EpisodeCandidate {
+                    episode: episode(number),
+                    confidence: Some(confidence),
+                })
+                .collect(),
+            reasoning: None,
+            extra_episodes: Vec::new(),
+            filename_conflict: None,
+            runtime_mismatch: None,
+            video_duration: None,
+            part: None,
+            transcript: None,
+            transcript_quality: None,
+            timings: StageTimings::default(),
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn test_collision_resolved_with_alternative() {
+        let matches = vec![
+            result("a.mkv", (5, 0.9), &[(6, 0.3)]),
+            result("b.mkv", (5, 0.6), &[(6, 0.5)]),
+        ];
+
+        let assignment = assign_episodes(matches);
+
+        assert!(assignment.unassigned.is_empty());
+        assert_eq!(assignment.assigned[0].episode.episode_number, 5);
+        assert_eq!(assignment.assigned[1].episode.episode_number, 6);
+        assert_eq!(assignment.assigned[1].confidence, Some(0.5));
+        assert_eq!(
+            assignment.assigned[1].alternatives[0]
+                .episode
+                .episode_number,
+            5
+        );
+    }
+
+    #[test]
+    fn test_collision_without_alternative_leaves_video_unassigned() {
+        let matches = vec![
+            result("a.mkv", (5, 0.9), &[]),
+            result("b.mkv", (5, 0.4), &[]),
+            result("c.mkv", (7, 0.8), &[]),
+        ];
+
+        let assignment = assign_episodes(matches);
+
+        assert_eq!(assignment.assigned.len(), 2);
+        assert_eq!(assignment.assigned[0].video.path, PathBuf::from("a.mkv"));
+        assert_eq!(assignment.assigned[1].video.path, PathBuf::from("c.mkv"));
+        assert_eq!(assignment.unassigned.len(), 1);
+        assert_eq!(assignment.unassigned[0].video.path, PathBuf::from("b.mkv"));
+    }
+
+    #[test]
+    fn test_maximizes_total_confidence() {
+        // Greedy would give a.mkv episode 1 and leave b.mkv with 0.1
+        let matches = vec![
+            result("a.mkv", (1, 0.8), &[(2, 0.7)]),
+            result("b.mkv", (1, 0.75), &[(2, 0.1)]),
+        ];
+
+        let assignment = assign_episodes(matches);
+
+        assert_eq!(assignment.assigned[0].episode.episode_number, 2);
+        assert_eq!(assignment.assigned[1].episode.episode_number, 1);
+    }
+
+    #[test]
+    fn test_parts_keep_their_episode() {
+        let mut matches = vec![
+            result("a.mkv", (5, 0.9), &[]),
+            result("b.mkv", (6, 0.9), &[]),
+            result("c.mkv", (5, 0.9), &[]),
+        ];
+        matches[0].part = Some(EpisodePart {
+            number: 1,
+            count: 2,
+        });
+        matches[2].part = Some(EpisodePart {
+            number: 2,
+            count: 2,
+        });
+
+        let assignment = assign_episodes(matches);
+
+        assert!(assignment.unassigned.is_empty());
+        let episodes: Vec<usize> = assignment
+            .assigned
+            .iter()
+            .map(|r| r.episode.episode_number)
+            .collect();
+        assert_eq!(episodes, vec![5, 6, 5]);
+    }
+}
@@ -1,14 +1,30 @@
 //! Audio extraction module
 //!
 //! This module provides functionality to extract audio from video files
-//! using ffmpeg.
+//! using ffmpeg. The extraction itself is only needed by the Whisper
+//! transcriber and available with the `whisper` feature enabled; the error
+//! type is always present so the top-level error stays the same across
+//! feature sets.
 
+use crate::temp::TempError;
+use std::path::PathBuf;
+use std::time::Duration;
+use thiserror::Error;
+
+#[cfg(feature = "whisper")]
+use crate::child_process::ChildGuard;
+#[cfg(feature = "whisper")]
 use crate::file_resolver::VideoFile;
-use crate::temp::{TempError, TempGuard, create_temp_file};
+#[cfg(feature = "whisper")]
+use crate::temp::{TempGuard, create_temp_file};
+#[cfg(feature = "whisper")]
 use ffmpeg_sidecar::command::{FfmpegCommand, ffmpeg_is_installed};
+#[cfg(feature = "whisper")]
+use std::io::ErrorKind;
+#[cfg(feature = "whisper")]
 use std::ops::Deref;
-use std::path::{Path, PathBuf};
-use thiserror::Error;
+#[cfg(feature = "whisper")]
+use std::path::Path;
 
 /// Errors that can occur during audio extraction
 #[derive(Debug, Error)]
@@ -38,6 +54,25 @@ pub enum AudioExtractionError {
     /// Failed to create temporary file
     #[error("Failed to create temporary file: {0}")]
     TempFileError(#[from] TempError),
+
+    /// FFmpeg didn't finish within the extraction timeout
+    #[error("FFmpeg didn't finish within {}s and was stopped", .0.as_secs())]
+    TimedOut(Duration),
+}
+
+impl AudioExtractionError {
+    /// Returns the stable code of the error, like `DD-AUD-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            AudioExtractionError::FfmpegNotInstalled => "DD-AUD-001",
+            AudioExtractionError::InvalidVideoPath(_) => "DD-AUD-002",
+            AudioExtractionError::InvalidTempPath => "DD-AUD-003",
+            AudioExtractionError::FfmpegSpawnFailed(_) => "DD-AUD-004",
+            AudioExtractionError::FfmpegExecutionFailed(_) => "DD-AUD-005",
+            AudioExtractionError::TempFileError(error) => error.code(),
+            AudioExtractionError::TimedOut(_) => "DD-AUD-006",
+        }
+    }
 }
 
 /// Represents an extracted audio file
@@ -45,12 +80,14 @@ pub enum AudioExtractionError {
 /// This struct wraps a temporary file containing the extracted audio in WAV format
 /// (16kHz, mono, 16-bit PCM), ready for speech-to-text processing with whisper.
 /// The audio file is automatically cleaned up when the `AudioFile` is dropped.
+#[cfg(feature = "whisper")]
 #[derive(Debug)]
 pub(crate) struct AudioFile {
     /// Temporary file containing the extracted audio
     temp_file: TempGuard,
 }
 
+#[cfg(feature = "whisper")]
 impl AudioFile {
     /// Creates a new AudioFile wrapping a temporary file guard
     fn new(temp_file: TempGuard) -> Self {
@@ -58,6 +95,7 @@ impl AudioFile {
     }
 }
 
+#[cfg(feature = "whisper")]
 impl Deref for AudioFile {
     type Target = Path;
 
@@ -75,6 +113,7 @@ impl Deref for AudioFile {
 /// # Arguments
 ///
 /// * `video` - The video file to extract audio from
+/// * `timeout` - Stop FFmpeg if it hasn't finished after this long
 ///
 /// # Returns
 ///
@@ -83,12 +122,16 @@ impl Deref for AudioFile {
 /// # Examples
 ///
 /// ```ignore
-/// let video = VideoFile { path: PathBuf::from("video.mp4") };
-/// let audio = audio_from_video(&video).unwrap();
+/// let video = VideoFile::new("video.mp4");
+/// let audio = audio_from_video(&video, None).unwrap();
 /// // Use &*audio to access the Path
 /// // Audio file is automatically deleted when audio goes out of scope
 /// ```
-pub(crate) fn audio_from_video(video: &VideoFile) -> Result<AudioFile, AudioExtractionError> {
+#[cfg(feature = "whisper")]
+pub(crate) fn audio_from_video(
+    video: &VideoFile,
+    timeout: Option<Duration>,
+) -> Result<AudioFile, AudioExtractionError> {
     // Check if ffmpeg is installed
     if !ffmpeg_is_installed() {
         return Err(AudioExtractionError::FfmpegNotInstalled);
@@ -104,7 +147,8 @@ pub(crate) fn audio_from_video(video: &VideoFile) -> Result<AudioFile, AudioExtr
     // -ac 1: mono audio (single channel, required by whisper)
     // -c:a pcm_s16le: 16-bit PCM little-endian WAV (required by whisper)
     // -y: overwrite output file without asking
-    FfmpegCommand::new()
+    let mut command = FfmpegCommand::new();
+    command
         .input(
             video
                 .path
@@ -121,15 +165,19 @@ pub(crate) fn audio_from_video(video: &VideoFile) -> Result<AudioFile, AudioExtr
                 .path()
                 .to_str()
                 .ok_or_else(|| AudioExtractionError::InvalidTempPath)?,
-        )
-        .spawn()
-        .map_err(|e| AudioExtractionError::FfmpegSpawnFailed(e.to_string()))?
-        .iter()
-        .map_err(|e| AudioExtractionError::FfmpegExecutionFailed(e.to_string()))?
-        .for_each(|_event| {
-            // Iterate through events until completion
-            // We could log progress here if needed
-        });
+        );
+    let mut child = ChildGuard::spawn(command.as_inner_mut())
+        .map_err(|e| AudioExtractionError::FfmpegSpawnFailed(e.to_string()))?;
+
+    // Wait for completion, reading FFmpeg's output so it never blocks on it
+    child
+        .wait_with_timeout(timeout)
+        .map_err(|e| match timeout {
+            Some(timeout) if e.kind() == ErrorKind::TimedOut => {
+                AudioExtractionError::TimedOut(timeout)
+            }
+            _ => AudioExtractionError::FfmpegExecutionFailed(e.to_string()),
+        })?;
 
     // Return AudioFile wrapping the temp file
     Ok(AudioFile::new(temp_audio))
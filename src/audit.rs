@@ -0,0 +1,234 @@
+//! Audit module
+//!
+//! Once a library is organized, the episode markers in its file names say
+//! which episodes it holds. This module compares them with the episode list
+//! of the metadata provider to find the episodes that are missing, the ones
+//! stored more than once, and files claiming to be episodes that don't
+//! exist, e.g. after a numbering mistake.
+
+use crate::file_operations::air_date_time;
+use crate::metadata_retrieval::{Episode, TVSeries};
+use crate::season_inference::episodes_from_path;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// An episode stored in more than one file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateEpisode {
+    /// The episode
+    pub episode: Episode,
+
+    /// The files claiming to be the episode, sorted by path
+    pub paths: Vec<PathBuf>,
+}
+
+/// A file named after an episode the series doesn't have
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnknownEpisodeFile {
+    /// The file
+    pub path: PathBuf,
+
+    /// Season number in the file name
+    pub season_number: usize,
+
+    /// Episode number(s) in the file name
+    pub episode_numbers: Vec<usize>,
+}
+
+/// Outcome of comparing a library with the episode list of its show
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LibraryAudit {
+    /// Name of the series the library was compared with
+    pub series_name: String,
+
+    /// Number of video files found in the library
+    pub file_count: usize,
+
+    /// Episodes that aired but have no file, in episode order
+    ///
+    /// Specials (season 0) only count if the library has any, as they are
+    /// rarely collected.
+    pub missing: Vec<Episode>,
+
+    /// Episodes with more than one file, in episode order
+    pub duplicates: Vec<DuplicateEpisode>,
+
+    /// Files named after episodes that don't exist
+    pub unknown: Vec<UnknownEpisodeFile>,
+
+    /// Files without an episode marker (`S01E02`, `1x02`) in their name
+    pub unrecognized: Vec<PathBuf>,
+}
+
+impl LibraryAudit {
+    /// Whether the library holds every aired episode exactly once
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+            && self.duplicates.is_empty()
+            && self.unknown.is_empty()
+            && self.unrecognized.is_empty()
+    }
+}
+
+/// Compares the video files of a library with the episodes of a series
+///
+/// Episodes airing after `now`, or without an air date in seasons the
+/// library has no files of, aren't reported as missing.
+pub(crate) fn audit_files(series: &TVSeries, paths: &[PathBuf], now: SystemTime) -> LibraryAudit {
+    let mut files: HashMap<(usize, usize), Vec<PathBuf>> = HashMap::new();
+    let mut unknown = Vec::new();
+    let mut unrecognized = Vec::new();
+    let known: HashSet<(usize, usize)> = series
+        .seasons
+        .iter()
+        .flat_map(|season| &season.episodes)
+        .map(|e| (e.season_number, e.episode_number))
+        .collect();
+
+    for path in paths {
+        let Some((season_number, episode_numbers)) = episodes_from_path(path) else {
+            unrecognized.push(path.clone());
+            continue;
+        };
+        if !episode_numbers
+            .iter()
+            .all(|&number| known.contains(&(season_number, number)))
+        {
+            unknown.push(UnknownEpisodeFile {
+                path: path.clone(),
+                season_number,
+                episode_numbers,
+            });
+            continue;
+        }
+        for number in episode_numbers {
+            files
+                .entry((season_number, number))
+                .or_default()
+                .push(path.clone());
+        }
+    }
+
+    let seasons_present: HashSet<usize> = files.keys().map(|(season, _)| *season).collect();
+    let mut missing = Vec::new();
+    let mut duplicates = Vec::new();
+    for episode in series.seasons.iter().flat_map(|season| &season.episodes) {
+        let key = (episode.season_number, episode.episode_number);
+        let Some(episode_files) = files.get_mut(&key) else {
+            let aired = has_aired(episode, now)
+                .unwrap_or_else(|| seasons_present.contains(&episode.season_number));
+            let collected = episode.season_number > 0 || seasons_present.contains(&0);
+            if aired && collected {
+                missing.push(episode.clone());
+            }
+            continue;
+        };
+        if episode_files.len() > 1 {
+            episode_files.sort();
+            duplicates.push(DuplicateEpisode {
+                episode: episode.clone(),
+                paths: episode_files.clone(),
+            });
+        }
+    }
+
+    unknown.sort_by(|a, b| a.path.cmp(&b.path));
+    unrecognized.sort();
+    LibraryAudit {
+        series_name: series.name.clone(),
+        file_count: paths.len(),
+        missing,
+        duplicates,
+        unknown,
+        unrecognized,
+    }
+}
+
+/// Whether an episode aired before `now`, None if its air date is unknown
+fn has_aired(episode: &Episode, now: SystemTime) -> Option<bool> {
+    let aired = episode.airdate.as_deref().and_then(air_date_time)?;
+    Some(aired <= now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_retrieval::Season;
+    use std::time::Duration;
+
+    fn episode(season: usize, number: usize, airdate: Option<&str>) -> Episode {
+        Episode {
+            season_number: season,
+            episode_number: number,
+            name: format!("Episode {}", number),
+            summary: String::new(),
+            summary_source: None,
+            airdate: airdate.map(str::to_string),
+            runtime: None,
+            season_name: None,
+            provider_id: None,
+            local_title: None,
+        }
+    }
+
+    fn series() -> TVSeries {
+        let season = |season_number, episodes| Season {
+            season_number,
+            episodes,
+        };
+        TVSeries {
+            name: "Test Show".to_string(),
+            year: None,
+            seasons: vec![
+                season(0, vec![episode(0, 1, Some("2020-01-01"))]),
+                season(
+                    1,
+                    vec![
+                        episode(1, 1, Some("2020-01-01")),
+                        episode(1, 2, Some("2020-01-08")),
+                        episode(1, 3, Some("2020-01-15")),
+                        episode(1, 4, Some("2020-01-22")),
+                    ],
+                ),
+                season(
+                    2,
+                    vec![episode(2, 1, Some("2030-01-01")), episode(2, 2, None)],
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_audit_files() {
+        let paths: Vec<PathBuf> = [
+            "/tv/Show/Season 01/Show - S01E01-E02 - Episode 1 & Episode 2.mkv",
+            "/tv/Show/Season 01/Show - S01E02 - Episode 2.mkv",
+            "/tv/Show/Season 01/Show - S01E09 - Episode 9.mkv",
+            "/tv/Show/Extras/Making of.mkv",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        // 2025-01-01
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_735_689_600);
+
+        let audit = audit_files(&series(), &paths, now);
+
+        assert_eq!(audit.file_count, 4);
+        let missing: Vec<(usize, usize)> = audit
+            .missing
+            .iter()
+            .map(|e| (e.season_number, e.episode_number))
+            .collect();
+        assert_eq!(missing, vec![(1, 3), (1, 4)]);
+        assert_eq!(audit.duplicates.len(), 1);
+        assert_eq!(audit.duplicates[0].episode.episode_number, 2);
+        assert_eq!(audit.duplicates[0].paths.len(), 2);
+        assert_eq!(audit.unknown.len(), 1);
+        assert_eq!(audit.unknown[0].episode_numbers, vec![9]);
+        assert_eq!(audit.unrecognized, vec![paths[3].clone()]);
+        assert!(!audit.is_complete());
+    }
+}
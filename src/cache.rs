@@ -2,14 +2,54 @@
 //!
 //! This module provides persistent caching functionality using the system's
 //! standard cache directory. Data is serialized to JSON format for storage.
+//!
+//! Entries are written to a temporary file first and then renamed, so that
+//! neither a crash nor a concurrent run leaves a half-written entry behind.
+//! Entries that can't be read anyway are moved aside and treated as missing,
+//! so that a damaged cache never stops a run.
+//!
+//! The most recently used entries are also kept in memory for the rest of
+//! the process, so that loading an entry again, like the metadata of the
+//! show or the transcript of a duplicate file, neither reads nor parses
+//! the file again. They are only used while their file keeps its
+//! modification time, so entries another process replaced or purged, e.g.
+//! while `queue run --watch` keeps running, are read from disk again.
+//!
+//! Machines working on the same media can share their caches through a
+//! `CacheBackend`, like the HTTP key-value store of `cache_remote`. Entries
+//! missing locally are looked up there, and new entries are written to
+//! both. The backend is best-effort: if it can't be reached, runs go on
+//! with the local cache alone.
+//!
+//! Caches holding sensitive data can be opened `encrypted`, sealing their
+//! entries with the key configured for `cache_encryption`.
 
 use serde::{Deserialize, Serialize};
+use std::any::Any;
 use std::fs;
+use std::io::ErrorKind;
 use std::marker::PhantomData;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
+use crate::cache_encryption::{self, CacheCipher};
+use crate::cache_remote::RemoteCache;
+
+/// Directory of the namespaced caches in the cache directory
+const NAMESPACES_DIR: &str = "namespaces";
+
+/// Directory in a cache that unreadable entries are moved to
+const CORRUPT_DIR: &str = "corrupt";
+
+/// Number of entries of all caches kept in memory
+const MEMORY_CAPACITY: usize = 256;
+
+/// Entries loaded or stored by this process, shared by all cache storages
+static MEMORY: LazyLock<Mutex<MemoryCache>> = LazyLock::new(|| Mutex::new(MemoryCache::default()));
+
 /// Errors that can occur during cache operations
 #[derive(Debug, Error)]
 pub enum CacheError {
@@ -48,6 +88,28 @@ pub enum CacheError {
     /// Failed to serialize data for caching
     #[error("Failed to serialize data: {0}")]
     SerializationFailed(#[from] serde_json::Error),
+
+    /// Failed to read the key encrypting cached data
+    #[error("Failed to read cache key {path}: {source}")]
+    KeyReadFailed {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl CacheError {
+    /// Returns the stable code of the error, like `DD-CAC-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            CacheError::CacheDirectoryNotFound => "DD-CAC-001",
+            CacheError::DirectoryCreationFailed { .. } => "DD-CAC-002",
+            CacheError::ReadFailed { .. } => "DD-CAC-003",
+            CacheError::WriteFailed { .. } => "DD-CAC-004",
+            CacheError::DeserializationFailed { .. } => "DD-CAC-005",
+            CacheError::SerializationFailed(_) => "DD-CAC-006",
+            CacheError::KeyReadFailed { .. } => "DD-CAC-007",
+        }
+    }
 }
 
 /// Internal wrapper for cached data with timestamp
@@ -57,23 +119,125 @@ struct CachedItem<T> {
     timestamp: SystemTime,
 }
 
+/// The data of an encrypted entry, in place of the serialized data
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SealedData {
+    /// Hex of the sealed JSON of the data
+    sealed: String,
+}
+
+/// An entry read from a cache file
+enum Entry<T> {
+    /// The entry could be read
+    Readable(CachedItem<T>),
+    /// The entry could be read, but isn't encrypted although the cache is
+    Unsealed(CachedItem<T>),
+    /// The entry is encrypted with another key, or the cache isn't encrypted
+    Sealed,
+    /// The entry is damaged
+    Corrupt,
+}
+
+/// Entries kept in memory, keyed by the path and modification time of their file
+///
+/// Values are stored type-erased, so the caches of all data types share the
+/// capacity. Entries are ordered from least to most recently used.
+#[derive(Default)]
+struct MemoryCache {
+    entries: Vec<(PathBuf, SystemTime, Arc<dyn Any + Send + Sync>)>,
+}
+
+impl MemoryCache {
+    /// Returns the entry of the file and marks it as most recently used
+    ///
+    /// An entry remembered for another modification time of the file is
+    /// outdated and dropped.
+    fn get(&mut self, path: &Path, modified: SystemTime) -> Option<Arc<dyn Any + Send + Sync>> {
+        let position = self.entries.iter().position(|(key, _, _)| key == path)?;
+        let entry = self.entries.remove(position);
+        if entry.1 != modified {
+            return None;
+        }
+        let value = Arc::clone(&entry.2);
+        self.entries.push(entry);
+        Some(value)
+    }
+
+    /// Adds or replaces the entry of the file, dropping the least recently used
+    fn insert(&mut self, path: PathBuf, modified: SystemTime, value: Arc<dyn Any + Send + Sync>) {
+        self.remove(&path);
+        if self.entries.len() >= MEMORY_CAPACITY {
+            self.entries.remove(0);
+        }
+        self.entries.push((path, modified, value));
+    }
+
+    /// Drops the entry of the file
+    fn remove(&mut self, path: &Path) {
+        self.entries.retain(|(key, _, _)| key != path);
+    }
+}
+
+/// Returns the modification time of a file, None if it doesn't exist
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Keeps an entry in memory for the current version of its file
+fn remember<T: Send + Sync + 'static>(file_path: PathBuf, cached_item: CachedItem<T>) {
+    if let Some(modified) = modified(&file_path) {
+        with_memory(|memory| memory.insert(file_path, modified, Arc::new(cached_item)));
+    }
+}
+
+/// Runs a function on the entries kept in memory
+fn with_memory<R>(f: impl FnOnce(&mut MemoryCache) -> R) -> R {
+    f(&mut MEMORY.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// A store shared with other machines, backing the local cache
+///
+/// Entries are keyed by their sanitized identifier within the cache, and
+/// hold the JSON of the local cache files. Backends are best-effort:
+/// failures are treated as misses and otherwise ignored.
+pub(crate) trait CacheBackend: Send + Sync {
+    /// Returns the JSON of an entry, or None if it is missing or can't be fetched
+    fn get(&self, sanitized_id: &str) -> Option<String>;
+
+    /// Writes the JSON of an entry
+    fn put(&self, sanitized_id: &str, content: String);
+
+    /// Deletes an entry
+    fn delete(&self, sanitized_id: &str);
+}
+
 /// A generic cache storage for serializable data
 ///
 /// This structure provides persistent caching of data that implements
 /// `Serialize` and `Deserialize`. Data is stored as JSON files in the
-/// system's standard cache directory.
+/// system's standard cache directory, and recently used entries are kept
+/// in memory as well, which is why the data also has to be `Clone`.
 pub(crate) struct CacheStorage<T> {
     /// The directory where cached data is stored
     cache_dir: PathBuf,
     /// Optional time-to-live for cached items
     ttl: Option<Duration>,
+    /// Where unreadable entries were moved to since the last check
+    quarantined: Mutex<Vec<PathBuf>>,
+    /// The shared remote cache, if one is configured
+    remote: Option<Box<dyn CacheBackend>>,
+    /// Encrypts the entries, if the cache is encrypted and a key is configured
+    cipher: Option<CacheCipher>,
     /// Phantom data for the generic type
     _phantom: PhantomData<T>,
 }
 
 impl<T> CacheStorage<T>
 where
-    T: Serialize + for<'de> Deserialize<'de>,
+    T: Serialize + for<'de> Deserialize<'de> + Clone + Send + Sync + 'static,
 {
     /// Opens or creates a cache storage with the given name
     ///
@@ -102,15 +266,28 @@ where
     /// let cache: CacheStorage<Transcript> = CacheStorage::open("transcripts", Some(Duration::from_secs(86400)))?;
     /// ```
     pub fn open(name: &str, ttl: Option<Duration>) -> Result<Self, CacheError> {
+        Self::open_namespaced(name, None, ttl)
+    }
+
+    /// Opens or creates a cache storage with the given name in a namespace
+    ///
+    /// Namespaced caches are stored in `namespaces/<namespace>/<name>` in
+    /// the cache directory, so their entries never mix with those of the
+    /// shared cache or of other namespaces. Without a namespace, this is
+    /// the same as `open`. The namespace is sanitized like the name.
+    pub fn open_namespaced(
+        name: &str,
+        namespace: Option<&str>,
+        ttl: Option<Duration>,
+    ) -> Result<Self, CacheError> {
         // Get the cache directory for this application
         let proj_dirs = directories::ProjectDirs::from("de", "westhoffswelt", "dialogdetective")
             .ok_or(CacheError::CacheDirectoryNotFound)?;
 
-        // Sanitize the cache name
-        let sanitized_name = sanitize_name(name);
-
         // Build the full cache directory path
-        let cache_dir = proj_dirs.cache_dir().join(&sanitized_name);
+        let cache_dir = cache_dir_in(proj_dirs.cache_dir(), name, namespace);
+        let remote = RemoteCache::from_env(&cache_dir_in(Path::new(""), name, namespace))
+            .map(|remote| Box::new(remote) as Box<dyn CacheBackend>);
 
         // Create the directory if it doesn't exist
         fs::create_dir_all(&cache_dir).map_err(|e| CacheError::DirectoryCreationFailed {
@@ -121,10 +298,25 @@ where
         Ok(Self {
             cache_dir,
             ttl,
+            quarantined: Mutex::new(Vec::new()),
+            remote,
+            cipher: None,
             _phantom: PhantomData,
         })
     }
 
+    /// Encrypts the entries of the cache if a cache key is configured
+    ///
+    /// Entries written from then on are sealed with the key, see
+    /// `cache_encryption`. Unencrypted entries written before are sealed
+    /// when they are read, locally and in the remote cache, while entries
+    /// sealed with another key are treated as missing. Without a key, the
+    /// cache stays unencrypted.
+    pub fn encrypted(mut self) -> Result<Self, CacheError> {
+        self.cipher = CacheCipher::from_config()?;
+        Ok(self)
+    }
+
     /// Loads cached data for the given identifier
     ///
     /// # Arguments
@@ -134,8 +326,11 @@ where
     /// # Returns
     ///
     /// An Option containing the cached data if it exists and is not expired,
-    /// or None if the data doesn't exist or is expired. Returns an error if the data
-    /// exists but cannot be read or deserialized. Expired items are automatically removed.
+    /// or None if the data doesn't exist or is expired. Expired items are
+    /// automatically removed. Items that can't be deserialized, e.g. because
+    /// an earlier version wrote them truncated, are moved to the `corrupt`
+    /// directory of the cache and reported by `take_quarantined`; they are
+    /// treated like missing items. Returns an error if the file can't be read.
     ///
     /// # Examples
     ///
@@ -148,36 +343,75 @@ where
         let sanitized_id = sanitize_name(identifier);
         let file_path = self.cache_dir.join(format!("{}.json", sanitized_id));
 
-        // If file doesn't exist, return None
-        if !file_path.exists() {
-            return Ok(None);
+        // Use the entry kept in memory if there is one for the file as it is
+        let modified = modified(&file_path);
+        let remembered =
+            modified.and_then(|modified| with_memory(|memory| memory.get(&file_path, modified)));
+        if let Some(cached_item) =
+            remembered.and_then(|value| value.downcast::<CachedItem<T>>().ok())
+        {
+            if self.is_expired(cached_item.timestamp) {
+                let _ = self.remove(identifier);
+                return Ok(None);
+            }
+            return Ok(Some(cached_item.data.clone()));
         }
 
+        // If file doesn't exist, look it up in the remote cache
+        let Some(modified) = modified else {
+            return Ok(self.load_remote(&sanitized_id, file_path));
+        };
+
         // Read the file
-        let content = fs::read_to_string(&file_path).map_err(|e| CacheError::ReadFailed {
-            path: file_path.clone(),
-            source: e,
-        })?;
+        let content = match fs::read_to_string(&file_path) {
+            Ok(content) => content,
+            // Not UTF-8, so it can't be JSON either
+            Err(e) if e.kind() == ErrorKind::InvalidData => {
+                self.quarantine(&file_path);
+                return Ok(None);
+            }
+            Err(e) => {
+                return Err(CacheError::ReadFailed {
+                    path: file_path.clone(),
+                    source: e,
+                });
+            }
+        };
 
         // Deserialize the JSON (wrapped with timestamp)
-        let cached_item: CachedItem<T> =
-            serde_json::from_str(&content).map_err(|e| CacheError::DeserializationFailed {
-                path: file_path.clone(),
-                source: e,
-            })?;
-
-        // Check if TTL is set and if the item is expired
-        if let Some(ttl) = self.ttl {
-            if let Ok(age) = SystemTime::now().duration_since(cached_item.timestamp) {
-                if age > ttl {
-                    // Item is expired, remove it
-                    let _ = self.remove(identifier);
-                    return Ok(None);
-                }
+        let (cached_item, unsealed) = match self.decode(&sanitized_id, &content) {
+            Entry::Readable(cached_item) => (cached_item, false),
+            Entry::Unsealed(cached_item) => (cached_item, true),
+            Entry::Sealed => return Ok(None),
+            Entry::Corrupt => {
+                self.quarantine(&file_path);
+                return Ok(None);
             }
+        };
+
+        // Check if the item is expired
+        if self.is_expired(cached_item.timestamp) {
+            // Item is expired, remove it
+            let _ = self.remove(identifier);
+            return Ok(None);
+        }
+
+        if unsealed {
+            // Seals the entry, keeping its age; it's still usable if that fails
+            let _ = self.write_entry(
+                &sanitized_id,
+                file_path,
+                &cached_item.data,
+                cached_item.timestamp,
+            );
+            return Ok(Some(cached_item.data));
         }
 
-        Ok(Some(cached_item.data))
+        // Remembered for the file as it was before reading it, in case it
+        // was replaced meanwhile
+        let data = cached_item.data.clone();
+        with_memory(|memory| memory.insert(file_path, modified, Arc::new(cached_item)));
+        Ok(Some(data))
     }
 
     /// Stores data in the cache with the given identifier
@@ -201,22 +435,48 @@ where
     pub fn store(&self, identifier: &str, data: &T) -> Result<(), CacheError> {
         let sanitized_id = sanitize_name(identifier);
         let file_path = self.cache_dir.join(format!("{}.json", sanitized_id));
+        self.write_entry(&sanitized_id, file_path, data, SystemTime::now())
+    }
 
-        // Wrap data with current timestamp
-        let cached_item = CachedItem {
-            data,
-            timestamp: SystemTime::now(),
+    /// Writes an entry with the given timestamp, locally and to the remote cache
+    fn write_entry(
+        &self,
+        sanitized_id: &str,
+        file_path: PathBuf,
+        data: &T,
+        timestamp: SystemTime,
+    ) -> Result<(), CacheError> {
+        // Wrap data with its timestamp, sealing it for encrypted caches
+        let content = match &self.cipher {
+            Some(cipher) => {
+                let sealed = cipher.seal(
+                    &serde_json::to_vec(data)?,
+                    &associated_data(sanitized_id, timestamp),
+                );
+                let data = SealedData {
+                    sealed: cache_encryption::to_hex(&sealed),
+                };
+                serde_json::to_string_pretty(&CachedItem { data, timestamp })?
+            }
+            None => serde_json::to_string_pretty(&CachedItem { data, timestamp })?,
         };
 
-        // Serialize to JSON
-        let content = serde_json::to_string_pretty(&cached_item)?;
-
-        // Write to file
-        fs::write(&file_path, content).map_err(|e| CacheError::WriteFailed {
-            path: file_path,
-            source: e,
-        })?;
+        if let Err(e) = self.write_file(sanitized_id, &file_path, &content) {
+            with_memory(|memory| memory.remove(&file_path));
+            return Err(CacheError::WriteFailed {
+                path: file_path,
+                source: e,
+            });
+        }
+        if let Some(remote) = &self.remote {
+            remote.put(sanitized_id, content);
+        }
 
+        let cached_item = CachedItem {
+            data: data.clone(),
+            timestamp,
+        };
+        remember(file_path, cached_item);
         Ok(())
     }
 
@@ -239,6 +499,10 @@ where
     pub fn remove(&self, identifier: &str) -> Result<(), CacheError> {
         let sanitized_id = sanitize_name(identifier);
         let file_path = self.cache_dir.join(format!("{}.json", sanitized_id));
+        with_memory(|memory| memory.remove(&file_path));
+        if let Some(remote) = &self.remote {
+            remote.delete(&sanitized_id);
+        }
 
         // Remove file if it exists (ignore error if it doesn't exist)
         if file_path.exists() {
@@ -251,11 +515,170 @@ where
         Ok(())
     }
 
+    /// Returns the identifiers of all entries, as sanitized when stored
+    pub fn ids(&self) -> Result<Vec<String>, CacheError> {
+        let entries = fs::read_dir(&self.cache_dir).map_err(|e| CacheError::ReadFailed {
+            path: self.cache_dir.clone(),
+            source: e,
+        })?;
+
+        let mut ids = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| CacheError::ReadFailed {
+                path: self.cache_dir.clone(),
+                source: e,
+            })?;
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json")
+                && let Some(stem) = path.file_stem()
+            {
+                ids.push(stem.to_string_lossy().into_owned());
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Removes all entries for which the predicate returns true
+    ///
+    /// The predicate gets the identifier and the data of every readable,
+    /// unexpired entry. Returns the identifiers of the removed entries.
+    pub fn remove_where(
+        &self,
+        mut predicate: impl FnMut(&str, &T) -> bool,
+    ) -> Result<Vec<String>, CacheError> {
+        let mut removed = Vec::new();
+        for id in self.ids()? {
+            if let Some(data) = self.load(&id)?
+                && predicate(&id, &data)
+            {
+                self.remove(&id)?;
+                removed.push(id);
+            }
+        }
+        Ok(removed)
+    }
+
     /// Returns the path to the cache directory
     pub fn cache_dir(&self) -> &PathBuf {
         &self.cache_dir
     }
 
+    /// Writes the JSON of an entry to its file
+    ///
+    /// Writes to a file of this process and moves it into place, which
+    /// replaces an existing entry in one step.
+    fn write_file(
+        &self,
+        sanitized_id: &str,
+        file_path: &Path,
+        content: &str,
+    ) -> std::io::Result<()> {
+        let temp_path = self
+            .cache_dir
+            .join(format!("{}.json.{}.tmp", sanitized_id, process::id()));
+        let written =
+            fs::write(&temp_path, content).and_then(|_| fs::rename(&temp_path, file_path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        written
+    }
+
+    /// Loads an entry missing locally from the remote cache
+    ///
+    /// Entries found are stored locally as well, so later runs don't need
+    /// the remote cache for them. Unreadable and expired entries are
+    /// treated as missing.
+    fn load_remote(&self, sanitized_id: &str, file_path: PathBuf) -> Option<T> {
+        let content = self.remote.as_ref()?.get(sanitized_id)?;
+        let (cached_item, unsealed) = match self.decode(sanitized_id, &content) {
+            Entry::Readable(cached_item) => (cached_item, false),
+            Entry::Unsealed(cached_item) => (cached_item, true),
+            Entry::Sealed | Entry::Corrupt => return None,
+        };
+        if self.is_expired(cached_item.timestamp) {
+            return None;
+        }
+        if unsealed {
+            let _ = self.write_entry(
+                sanitized_id,
+                file_path,
+                &cached_item.data,
+                cached_item.timestamp,
+            );
+            return Some(cached_item.data);
+        }
+
+        let _ = self.write_file(sanitized_id, &file_path, &content);
+        let data = cached_item.data.clone();
+        remember(file_path, cached_item);
+        Some(data)
+    }
+
+    /// Deserializes the JSON of an entry, opening sealed data
+    ///
+    /// Sealed data only opens for the identifier and timestamp it was
+    /// sealed with.
+    fn decode(&self, sanitized_id: &str, content: &str) -> Entry<T> {
+        if let Ok(item) = serde_json::from_str::<CachedItem<SealedData>>(content) {
+            let associated_data = associated_data(sanitized_id, item.timestamp);
+            let data = self
+                .cipher
+                .as_ref()
+                .zip(cache_encryption::from_hex(&item.data.sealed))
+                .and_then(|(cipher, sealed)| cipher.open(&sealed, &associated_data))
+                .and_then(|plaintext| serde_json::from_slice(&plaintext).ok());
+            return match data {
+                Some(data) => Entry::Readable(CachedItem {
+                    data,
+                    timestamp: item.timestamp,
+                }),
+                None => Entry::Sealed,
+            };
+        }
+
+        match serde_json::from_str::<CachedItem<T>>(content) {
+            Ok(cached_item) if self.cipher.is_some() => Entry::Unsealed(cached_item),
+            Ok(cached_item) => Entry::Readable(cached_item),
+            Err(_) => Entry::Corrupt,
+        }
+    }
+
+    /// Returns whether an item stored at the given time has exceeded the TTL
+    fn is_expired(&self, timestamp: SystemTime) -> bool {
+        self.ttl.is_some_and(|ttl| {
+            SystemTime::now()
+                .duration_since(timestamp)
+                .is_ok_and(|age| age > ttl)
+        })
+    }
+
+    /// Returns where unreadable entries were moved to since the last call
+    pub fn take_quarantined(&self) -> Vec<PathBuf> {
+        std::mem::take(&mut *self.quarantined.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Moves an unreadable entry to the `corrupt` directory of the cache
+    ///
+    /// Deletes it instead if it can't be moved, so it isn't read again.
+    fn quarantine(&self, file_path: &Path) {
+        let corrupt_dir = self.cache_dir.join(CORRUPT_DIR);
+        let Some(file_name) = file_path.file_name() else {
+            return;
+        };
+        let target = corrupt_dir.join(file_name);
+        let moved = fs::create_dir_all(&corrupt_dir).and_then(|_| fs::rename(file_path, &target));
+        if moved.is_err() {
+            let _ = fs::remove_file(file_path);
+            return;
+        }
+        self.quarantined
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(target);
+    }
+
     /// Removes all expired items from the cache
     ///
     /// This method scans all cached items and removes those that have exceeded
@@ -312,6 +735,7 @@ where
                         if let Ok(age) = SystemTime::now().duration_since(cached_item.timestamp) {
                             if age > ttl {
                                 // Remove expired file
+                                with_memory(|memory| memory.remove(&path));
                                 if fs::remove_file(&path).is_ok() {
                                     removed_count += 1;
                                 }
@@ -330,11 +754,59 @@ where
     }
 }
 
+/// Returns the data a sealed entry is bound to, its identifier and timestamp
+///
+/// Keeps entries in a shared cache from being swapped between identifiers
+/// or given another timestamp without being noticed.
+fn associated_data(sanitized_id: &str, timestamp: SystemTime) -> Vec<u8> {
+    let nanos = timestamp
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos());
+    format!("{}@{}", sanitized_id, nanos).into_bytes()
+}
+
+/// Returns the names of all cache namespaces, as sanitized when created
+pub(crate) fn namespaces() -> Result<Vec<String>, CacheError> {
+    let proj_dirs = directories::ProjectDirs::from("de", "westhoffswelt", "dialogdetective")
+        .ok_or(CacheError::CacheDirectoryNotFound)?;
+    let namespaces_dir = proj_dirs.cache_dir().join(NAMESPACES_DIR);
+
+    let entries = match fs::read_dir(&namespaces_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(CacheError::ReadFailed {
+                path: namespaces_dir,
+                source: e,
+            });
+        }
+    };
+
+    let mut namespaces: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    namespaces.sort();
+    Ok(namespaces)
+}
+
+/// Returns the directory of a cache in the given cache root directory
+pub(crate) fn cache_dir_in(root: &Path, name: &str, namespace: Option<&str>) -> PathBuf {
+    match namespace {
+        Some(namespace) => root
+            .join(NAMESPACES_DIR)
+            .join(sanitize_name(namespace))
+            .join(sanitize_name(name)),
+        None => root.join(sanitize_name(name)),
+    }
+}
+
 /// Sanitizes a name for use in file paths
 ///
 /// Converts to lowercase and replaces all characters that are not
 /// a-z, 0-9, or hyphen with underscores.
-fn sanitize_name(name: &str) -> String {
+pub(crate) fn sanitize_name(name: &str) -> String {
     name.to_lowercase()
         .chars()
         .map(|c| {
@@ -350,6 +822,20 @@ fn sanitize_name(name: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Creates a cache storage in the given directory
+    fn storage_in<T>(dir: &Path, remote: Option<RemoteCache>) -> CacheStorage<T> {
+        CacheStorage {
+            cache_dir: dir.to_path_buf(),
+            ttl: None,
+            quarantined: Mutex::new(Vec::new()),
+            remote: remote.map(|remote| Box::new(remote) as Box<dyn CacheBackend>),
+            cipher: None,
+            _phantom: PhantomData,
+        }
+    }
 
     #[test]
     fn test_sanitize_name() {
@@ -359,4 +845,220 @@ mod tests {
         assert_eq!(sanitize_name("Special!@#$%"), "special_____");
         assert_eq!(sanitize_name("Mixed123ABC"), "mixed123abc");
     }
+
+    #[test]
+    fn test_cache_dir_in() {
+        let root = Path::new("/cache");
+        assert_eq!(
+            cache_dir_in(root, "matching", None),
+            PathBuf::from("/cache/matching")
+        );
+        assert_eq!(
+            cache_dir_in(root, "matching", Some("Kids TV")),
+            PathBuf::from("/cache/namespaces/kids_tv/matching")
+        );
+    }
+
+    #[test]
+    fn test_load_quarantines_corrupt_entries() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_cache_corrupt");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(&temp_dir).unwrap();
+        let cache: CacheStorage<Vec<u32>> = storage_in(&temp_dir, None);
+
+        cache.store("intact", &vec![1, 2, 3]).unwrap();
+        assert_eq!(cache.load("intact").unwrap(), Some(vec![1, 2, 3]));
+
+        fs::write(temp_dir.join("truncated.json"), "{\"data\": [1, 2").unwrap();
+        assert_eq!(cache.load("truncated").unwrap(), None);
+        assert!(!temp_dir.join("truncated.json").exists());
+        assert_eq!(
+            cache.take_quarantined(),
+            vec![temp_dir.join(CORRUPT_DIR).join("truncated.json")]
+        );
+        assert!(cache.take_quarantined().is_empty());
+
+        // Nothing but the stored entries is left in the cache
+        let names: Vec<_> = fs::read_dir(&temp_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names.len(), 2);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_load_from_memory() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_cache_memory");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(&temp_dir).unwrap();
+        let cache: CacheStorage<String> = storage_in(&temp_dir, None);
+
+        // Loaded from memory while the file keeps its modification time
+        let path = temp_dir.join("show.json");
+        cache.store("show", &"Breaking Bad".to_string()).unwrap();
+        let stored = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(&path, "damaged").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(stored)
+            .unwrap();
+        assert_eq!(cache.load("show").unwrap().as_deref(), Some("Breaking Bad"));
+
+        // Entries replaced or removed by another process are read from disk
+        let item = CachedItem {
+            data: "Better Call Saul".to_string(),
+            timestamp: SystemTime::now(),
+        };
+        fs::write(&path, serde_json::to_string(&item).unwrap()).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(stored + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(
+            cache.load("show").unwrap().as_deref(),
+            Some("Better Call Saul")
+        );
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cache.load("show").unwrap(), None);
+        cache.store("show", &"Breaking Bad".to_string()).unwrap();
+
+        // Entries of another type at the same path are read from the file
+        let other: CacheStorage<u32> = storage_in(&temp_dir, None);
+        assert_eq!(other.load("show").unwrap(), None);
+
+        cache.remove("show").unwrap();
+        assert_eq!(cache.load("show").unwrap(), None);
+
+        let mut memory = MemoryCache::default();
+        let modified = SystemTime::UNIX_EPOCH;
+        for index in 0..=MEMORY_CAPACITY {
+            memory.insert(PathBuf::from(index.to_string()), modified, Arc::new(index));
+        }
+        assert!(memory.get(Path::new("0"), modified).is_none());
+        assert!(memory.get(Path::new("1"), modified).is_some());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_encrypted_entries() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_cache_encrypted");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(&temp_dir).unwrap();
+        let plain: CacheStorage<String> = storage_in(&temp_dir, None);
+        let mut cache: CacheStorage<String> = storage_in(&temp_dir, None);
+        cache.cipher = CacheCipher::new("secret", [0; 16]);
+
+        plain
+            .store("old", &"Written unencrypted".to_string())
+            .unwrap();
+        cache.store("new", &"Say my name".to_string()).unwrap();
+        with_memory(|memory| {
+            memory.remove(&temp_dir.join("old.json"));
+            memory.remove(&temp_dir.join("new.json"));
+        });
+
+        let content = fs::read_to_string(temp_dir.join("new.json")).unwrap();
+        assert!(!content.contains("Say my name"));
+        assert_eq!(cache.load("new").unwrap().as_deref(), Some("Say my name"));
+        assert_eq!(
+            cache.load("old").unwrap().as_deref(),
+            Some("Written unencrypted")
+        );
+        // Unencrypted entries are sealed once read
+        let content = fs::read_to_string(temp_dir.join("old.json")).unwrap();
+        assert!(!content.contains("Written unencrypted"));
+        with_memory(|memory| memory.remove(&temp_dir.join("old.json")));
+        assert_eq!(
+            cache.load("old").unwrap().as_deref(),
+            Some("Written unencrypted")
+        );
+
+        // Entries moved to another identifier don't open
+        fs::copy(temp_dir.join("new.json"), temp_dir.join("moved.json")).unwrap();
+        assert_eq!(cache.load("moved").unwrap(), None);
+
+        // Without the key, sealed entries are missing but not corrupt
+        with_memory(|memory| memory.remove(&temp_dir.join("new.json")));
+        assert_eq!(plain.load("new").unwrap(), None);
+        assert!(temp_dir.join("new.json").exists());
+        assert!(plain.take_quarantined().is_empty());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_ids_and_remove_where() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_cache_remove_where");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(&temp_dir).unwrap();
+        let cache: CacheStorage<String> = storage_in(&temp_dir, None);
+
+        cache.store("Breaking Bad", &"keep".to_string()).unwrap();
+        cache.store("Doctor Who", &"drop".to_string()).unwrap();
+        cache.store("Fawlty Towers", &"drop".to_string()).unwrap();
+        assert_eq!(
+            cache.ids().unwrap(),
+            vec!["breaking_bad", "doctor_who", "fawlty_towers"]
+        );
+
+        let removed = cache
+            .remove_where(|id, data| data == "drop" && id != "fawlty_towers")
+            .unwrap();
+        assert_eq!(removed, vec!["doctor_who"]);
+        assert_eq!(cache.ids().unwrap(), vec!["breaking_bad", "fawlty_towers"]);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_load_from_remote() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_cache_remote");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // Answers a single request with a cached entry
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let item = CachedItem {
+                data: "Pilot".to_string(),
+                timestamp: SystemTime::now(),
+            };
+            let body = serde_json::to_string(&item).unwrap();
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            request_line
+        });
+
+        let remote = RemoteCache::new(&base_url, None, Path::new("transcripts"));
+        let cache: CacheStorage<String> = storage_in(&temp_dir, remote);
+        assert_eq!(cache.load("abc").unwrap().as_deref(), Some("Pilot"));
+        assert_eq!(
+            server.join().unwrap().trim(),
+            "GET /transcripts/abc.json HTTP/1.1"
+        );
+        assert!(temp_dir.join("abc.json").exists());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
 }
@@ -0,0 +1,306 @@
+//! Encryption of cache entries at rest
+//!
+//! Cached transcripts are close to full scripts of the shows' dialogue. If
+//! a key is configured, their entries are encrypted before being written,
+//! so the cache directory (and a shared remote cache) only holds
+//! ciphertext.
+//!
+//! The key is read from `DIALOG_DETECTIVE_CACHE_KEY`, or from a `cache.key`
+//! file in the configuration directory. As it may be a passphrase, the
+//! encryption key is derived from it with Argon2id and the random salt of
+//! the installation, kept in `cache.salt` next to the key file. Entries are
+//! sealed with XChaCha20-Poly1305 and a random nonce, behind a header
+//! holding the format version, the salt, and the nonce, so any machine
+//! knowing the key can open them. The caller passes associated data, like
+//! the identifier of the entry, which has to match to open it again.
+//!
+//! Deriving a key is expensive on purpose, so only the keys of a few other
+//! salts, e.g. of other machines sharing a remote cache, are derived per
+//! run. Entries with further salts are treated as sealed with another key.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+use crate::cache::CacheError;
+
+/// Environment variable holding the cache key
+const KEY_ENV: &str = "DIALOG_DETECTIVE_CACHE_KEY";
+
+/// File in the configuration directory holding the cache key
+const KEY_FILE: &str = "cache.key";
+
+/// File in the configuration directory holding the salt of the installation
+const SALT_FILE: &str = "cache.salt";
+
+/// Most keys of other salts derived by a cipher
+const MAX_OTHER_SALTS: usize = 4;
+
+/// Marks entries in the current format, followed by its version
+const MAGIC: &[u8; 4] = b"DDC\x02";
+
+/// Length of the salt the encryption key is derived with
+const SALT_LENGTH: usize = 16;
+
+/// Length of the XChaCha20-Poly1305 nonce
+const NONCE_LENGTH: usize = 24;
+
+/// Length of the header in front of the ciphertext
+const HEADER_LENGTH: usize = MAGIC.len() + SALT_LENGTH + NONCE_LENGTH;
+
+/// A salt the encryption key is derived with
+type Salt = [u8; SALT_LENGTH];
+
+/// Encrypts and decrypts cache entries with a configured key
+pub(crate) struct CacheCipher {
+    /// The configured key, kept to derive the keys of other salts
+    passphrase: Vec<u8>,
+    /// Salt of the entries sealed by this cipher
+    salt: Salt,
+    /// Encryption key of the own salt
+    key: Key,
+    /// Encryption keys of other salts derived so far, None if not derivable
+    other_keys: Mutex<HashMap<Salt, Option<Key>>>,
+}
+
+impl CacheCipher {
+    /// Creates a cipher from a key and a salt, or None if the key is blank
+    ///
+    /// Deriving the encryption key takes a moment on purpose, which makes
+    /// guessing a passphrase from the cached entries expensive. Still, a
+    /// long random key, like the output of `openssl rand -hex 32`, is best.
+    pub fn new(key: &str, salt: Salt) -> Option<Self> {
+        let key = key.trim();
+        if key.is_empty() {
+            return None;
+        }
+        let passphrase = key.as_bytes().to_vec();
+        Some(Self {
+            key: derive_key(&passphrase, &salt),
+            passphrase,
+            salt,
+            other_keys: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Creates a cipher from the configured key, or None if there is none
+    ///
+    /// `DIALOG_DETECTIVE_CACHE_KEY` takes precedence over the key file.
+    pub fn from_config() -> Result<Option<Self>, CacheError> {
+        let key = match std::env::var(KEY_ENV) {
+            Ok(key) if !key.trim().is_empty() => key,
+            _ => {
+                let Some(path) = config_file_path(KEY_FILE) else {
+                    return Ok(None);
+                };
+                match fs::read_to_string(&path) {
+                    Ok(key) => key,
+                    Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+                    Err(source) => return Err(CacheError::KeyReadFailed { path, source }),
+                }
+            }
+        };
+        Ok(Self::new(&key, installation_salt()))
+    }
+
+    /// Encrypts an entry, returning the header followed by the ciphertext
+    ///
+    /// The associated data isn't stored, but has to be passed again to
+    /// open the entry.
+    pub fn seal(&self, plaintext: &[u8], associated_data: &[u8]) -> Vec<u8> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: plaintext,
+            aad: associated_data,
+        };
+        let ciphertext = XChaCha20Poly1305::new(&self.key)
+            .encrypt(&nonce, payload)
+            .expect("entries fit into a single message");
+
+        let mut sealed = Vec::with_capacity(HEADER_LENGTH + ciphertext.len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&self.salt);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        sealed
+    }
+
+    /// Decrypts an entry sealed with the same key and associated data
+    ///
+    /// Returns None if the entry was sealed with another key or other
+    /// associated data, or modified.
+    pub fn open(&self, sealed: &[u8], associated_data: &[u8]) -> Option<Vec<u8>> {
+        let entry = sealed.strip_prefix(MAGIC)?;
+        if entry.len() < SALT_LENGTH + NONCE_LENGTH {
+            return None;
+        }
+        let (salt, entry) = entry.split_at(SALT_LENGTH);
+        let (nonce, ciphertext) = entry.split_at(NONCE_LENGTH);
+        let salt: Salt = salt.try_into().ok()?;
+        let payload = Payload {
+            msg: ciphertext,
+            aad: associated_data,
+        };
+        XChaCha20Poly1305::new(&self.key_for(&salt)?)
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .ok()
+    }
+
+    /// Returns the encryption key for a salt
+    ///
+    /// Keys of other salts are derived on first use, up to
+    /// `MAX_OTHER_SALTS` of them; further salts get None.
+    fn key_for(&self, salt: &Salt) -> Option<Key> {
+        if *salt == self.salt {
+            return Some(self.key);
+        }
+        let mut keys = self.other_keys.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(key) = keys.get(salt) {
+            return *key;
+        }
+        let key = (keys.len() < MAX_OTHER_SALTS).then(|| derive_key(&self.passphrase, salt));
+        keys.insert(*salt, key);
+        key
+    }
+}
+
+/// Derives the encryption key from the configured key with Argon2id
+fn derive_key(passphrase: &[u8], salt: &Salt) -> Key {
+    let mut key = Key::default();
+    argon2::Argon2::default()
+        .hash_password_into(passphrase, salt, &mut key)
+        .expect("the salt and key lengths are valid for Argon2");
+    key
+}
+
+/// Returns a new random salt
+fn random_salt() -> Salt {
+    let mut salt = [0u8; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// Returns the salt of this installation, creating it on first use
+///
+/// Falls back to a salt for this run alone if the salt file can't be read
+/// or written, which only costs the entries of earlier runs.
+fn installation_salt() -> Salt {
+    let Some(path) = config_file_path(SALT_FILE) else {
+        return random_salt();
+    };
+    let read = || {
+        fs::read_to_string(&path)
+            .ok()
+            .and_then(|hex| from_hex(hex.trim()))
+            .and_then(|salt| Salt::try_from(salt).ok())
+    };
+    if let Some(salt) = read() {
+        return salt;
+    }
+
+    let salt = random_salt();
+    let created = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::File::create_new(&path))
+        .and_then(|mut file| std::io::Write::write_all(&mut file, to_hex(&salt).as_bytes()));
+    match created {
+        Ok(()) => salt,
+        // Another process created it first
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => read().unwrap_or(salt),
+        Err(_) => salt,
+    }
+}
+
+/// Returns the path of a file in the configuration directory, if it is known
+fn config_file_path(name: &str) -> Option<PathBuf> {
+    let proj_dirs = directories::ProjectDirs::from("de", "westhoffswelt", "dialogdetective")?;
+    Some(proj_dirs.config_dir().join(name))
+}
+
+/// Encodes bytes as lowercase hex
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decodes hex, returning None for anything but an even number of hex digits
+pub(crate) fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let cipher = CacheCipher::new("correct horse battery staple", random_salt()).unwrap();
+        let plaintext = "Say my name. ".repeat(20);
+
+        let sealed = cipher.seal(plaintext.as_bytes(), b"breaking_bad");
+        assert!(sealed.starts_with(MAGIC));
+        assert!(!String::from_utf8_lossy(&sealed).contains("Say my name"));
+        assert_eq!(
+            cipher.open(&sealed, b"breaking_bad").unwrap(),
+            plaintext.as_bytes()
+        );
+
+        // Another installation with the same key uses another salt, but can open it
+        let same_key = CacheCipher::new("correct horse battery staple", random_salt()).unwrap();
+        assert_eq!(
+            same_key.open(&sealed, b"breaking_bad").unwrap(),
+            plaintext.as_bytes()
+        );
+        assert_ne!(same_key.seal(plaintext.as_bytes(), b"breaking_bad"), sealed);
+
+        // Another key, other associated data, or a modified entry can't open it
+        let other = CacheCipher::new("another key", random_salt()).unwrap();
+        assert!(other.open(&sealed, b"breaking_bad").is_none());
+        assert!(cipher.open(&sealed, b"better_call_saul").is_none());
+        let mut modified = sealed.clone();
+        modified[HEADER_LENGTH + 3] ^= 1;
+        assert!(cipher.open(&modified, b"breaking_bad").is_none());
+        assert!(cipher.open(&sealed[..10], b"breaking_bad").is_none());
+
+        assert!(CacheCipher::new("  \n", random_salt()).is_none());
+    }
+
+    #[test]
+    fn test_other_salts_limited() {
+        let cipher = CacheCipher::new("secret", random_salt()).unwrap();
+        let other = CacheCipher {
+            other_keys: Mutex::new(
+                (0..MAX_OTHER_SALTS)
+                    .map(|_| (random_salt(), None))
+                    .collect(),
+            ),
+            ..CacheCipher::new("secret", random_salt()).unwrap()
+        };
+        let sealed = cipher.seal(b"Say my name", b"");
+        assert!(other.open(&sealed, b"").is_none());
+        assert_eq!(
+            other.open(&other.seal(b"Say my name", b""), b"").unwrap(),
+            b"Say my name"
+        );
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(&[0x00, 0xab, 0x7f]), "00ab7f");
+        assert_eq!(from_hex("00ab7f"), Some(vec![0x00, 0xab, 0x7f]));
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+}
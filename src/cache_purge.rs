@@ -0,0 +1,245 @@
+//! Cache purge module
+//!
+//! The caches keep what was learned about a user's videos long after a
+//! run: transcripts of their dialogue, the matches, and the lookups of
+//! their file hashes. Purging removes everything cached about a show or a
+//! single video, e.g. before passing a library on, or to have a file
+//! identified from scratch. The history is a database of its own and is
+//! purged by the caller.
+
+use crate::DialogDetectiveError;
+use crate::cache::{self, CacheStorage, sanitize_name};
+use crate::file_resolver::{HashOptions, compute_video_hash};
+use crate::hash_lookup::{HashMatch, opensubtitles_hash};
+use crate::metadata_retrieval::SeriesCandidate;
+use crate::retry::RetryPolicy;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Cache entries removed by `purge_show_cache` or `purge_video_cache`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CachePurge {
+    /// Content hashes of the videos whose transcripts and matches were purged
+    pub video_hashes: Vec<String>,
+
+    /// Number of cache entries removed
+    pub removed_entries: usize,
+}
+
+/// Removes everything cached about a show
+///
+/// Removes search results listing the show, its episode lists, hash
+/// lookups identifying files as the show, and all matches against it in
+/// every cache namespace. The transcripts of the matched videos are
+/// removed as well, together with those of `video_hashes`, e.g. the files
+/// the history recorded for the show. Show names are compared ignoring
+/// case, while episode lists are found by the series IDs of the show in
+/// the search results.
+pub fn purge_show_cache(
+    show_name: &str,
+    video_hashes: &[String],
+) -> Result<CachePurge, DialogDetectiveError> {
+    let mut removed_entries = 0;
+
+    // Series IDs of the show, with the search results listing them
+    let mut series_ids = Vec::new();
+    let search_cache = CacheStorage::<Vec<SeriesCandidate>>::open("search", None)?;
+    removed_entries += search_cache
+        .remove_where(|search_id, candidates| {
+            let before = series_ids.len();
+            for candidate in candidates {
+                if same_show(&candidate.name, show_name) {
+                    series_ids.push((search_id.to_string(), candidate.id));
+                }
+            }
+            series_ids.len() > before
+        })?
+        .len();
+
+    let metadata_cache = CacheStorage::<serde_json::Value>::open("metadata", None)?;
+    for id in metadata_cache.ids()? {
+        if is_series_entry(&id, &series_ids) {
+            metadata_cache.remove(&id)?;
+            removed_entries += 1;
+        }
+    }
+
+    let hash_cache = CacheStorage::<Option<HashMatch>>::open("opensubtitles", None)?;
+    removed_entries += hash_cache
+        .remove_where(|_, hash_match| {
+            hash_match
+                .as_ref()
+                .is_some_and(|hash_match| same_show(&hash_match.show_name, show_name))
+        })?
+        .len();
+
+    let show_key = sanitize_name(show_name);
+    let mut hashes: BTreeSet<String> = video_hashes.iter().map(|h| sanitize_name(h)).collect();
+    for matching_cache in matching_caches()? {
+        for id in matching_cache.ids()? {
+            let Some(hash) = matched_video(&id, &show_key) else {
+                continue;
+            };
+            // The key can't tell "Doctor Who" matched against season 2005
+            // from "Doctor Who 2005", so the show recorded in the entry
+            // decides where there is one
+            let recorded_show = matching_cache.load(&id)?.and_then(|entry| {
+                entry
+                    .get("show")
+                    .and_then(|show| show.as_str().map(str::to_string))
+            });
+            if recorded_show.is_none_or(|recorded| same_show(&recorded, show_name)) {
+                matching_cache.remove(&id)?;
+                removed_entries += 1;
+                hashes.insert(hash.to_string());
+            }
+        }
+    }
+
+    let transcript_cache = CacheStorage::<serde_json::Value>::open("transcripts", None)?;
+    let transcripts: BTreeSet<String> = transcript_cache.ids()?.into_iter().collect();
+    for hash in hashes.intersection(&transcripts) {
+        transcript_cache.remove(hash)?;
+        removed_entries += 1;
+    }
+
+    Ok(CachePurge {
+        video_hashes: hashes.into_iter().collect(),
+        removed_entries,
+    })
+}
+
+/// Removes everything cached about a video file
+///
+/// Removes its transcript, its matches against any show in every cache
+/// namespace, and the lookup of its file hash.
+pub fn purge_video_cache(video_path: &Path) -> Result<CachePurge, DialogDetectiveError> {
+    let (video_hash, _) = compute_video_hash(
+        video_path,
+        &HashOptions::default(),
+        &RetryPolicy::default(),
+        &mut |_, _| true,
+    )?;
+    let mut removed_entries = 0;
+
+    let transcript_cache = CacheStorage::<serde_json::Value>::open("transcripts", None)?;
+    if transcript_cache.ids()?.contains(&video_hash) {
+        transcript_cache.remove(&video_hash)?;
+        removed_entries += 1;
+    }
+
+    let prefix = format!("{}_", video_hash);
+    for matching_cache in matching_caches()? {
+        for id in matching_cache.ids()? {
+            if id.starts_with(&prefix) {
+                matching_cache.remove(&id)?;
+                removed_entries += 1;
+            }
+        }
+    }
+
+    if let Some(hash) = opensubtitles_hash(video_path)? {
+        let hash_cache = CacheStorage::<serde_json::Value>::open("opensubtitles", None)?;
+        if hash_cache.ids()?.contains(&sanitize_name(&hash)) {
+            hash_cache.remove(&hash)?;
+            removed_entries += 1;
+        }
+    }
+
+    Ok(CachePurge {
+        video_hashes: vec![video_hash],
+        removed_entries,
+    })
+}
+
+/// Opens the matching cache shared by all runs and that of every namespace
+///
+/// Only the entry identifiers are needed, so the entries aren't typed.
+fn matching_caches() -> Result<Vec<CacheStorage<serde_json::Value>>, DialogDetectiveError> {
+    let mut caches = vec![CacheStorage::open("matching", None)?];
+    for namespace in cache::namespaces()? {
+        caches.push(CacheStorage::open_namespaced(
+            "matching",
+            Some(&namespace),
+            None,
+        )?);
+    }
+    Ok(caches)
+}
+
+/// Returns whether two show names are the same, ignoring case and whitespace
+fn same_show(name: &str, show_name: &str) -> bool {
+    name.trim().to_lowercase() == show_name.trim().to_lowercase()
+}
+
+/// Returns whether a metadata cache entry holds the episodes of a series
+///
+/// Entries are identified as `<provider>_<series ID>`, optionally followed
+/// by `_seasons_<seasons>`, and the search results listing the series as
+/// `<provider>_<query>`. Series IDs are only unique for a provider, so the
+/// entry's provider has to be that of the search results.
+fn is_series_entry(id: &str, series_ids: &[(String, u64)]) -> bool {
+    let series = id.split_once("_seasons_").map_or(id, |(series, _)| series);
+    series_ids.iter().any(|(search_id, series_id)| {
+        series
+            .strip_suffix(&format!("_{}", series_id))
+            .is_some_and(|provider| search_id.starts_with(&format!("{}_", provider)))
+    })
+}
+
+/// Returns the video hash of a matching cache entry against a show
+///
+/// Entries are identified as `<hash>_<show>_<seasons>_<metadata>_<matcher>_v<version>`
+/// with the show sanitized, where seasons are `all`, `auto`, or numbers
+/// joined by hyphens. Checking the seasons keeps "Breaking" from taking the
+/// entries of "Breaking Bad".
+fn matched_video<'a>(id: &'a str, show_key: &str) -> Option<&'a str> {
+    let (hash, rest) = id.split_once('_')?;
+    let rest = rest.strip_prefix(show_key)?.strip_prefix('_')?;
+    let (seasons, _) = rest.split_once('_')?;
+    let known_seasons = seasons == "all"
+        || seasons == "auto"
+        || seasons
+            .split('-')
+            .all(|season| !season.is_empty() && season.bytes().all(|b| b.is_ascii_digit()));
+    known_seasons.then_some(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matched_video() {
+        let show_key = sanitize_name("Breaking Bad");
+        assert_eq!(
+            matched_video("abc123_breaking_bad_all_tvmaze_claude-code_v1", &show_key),
+            Some("abc123")
+        );
+        assert_eq!(
+            matched_video("abc123_breaking_bad_1-2_gemini_v0", &show_key),
+            Some("abc123")
+        );
+        assert_eq!(
+            matched_video(
+                "abc123_breaking_bad_auto_tvmaze-dvd_claude-code_v1",
+                "breaking"
+            ),
+            None
+        );
+        assert_eq!(
+            matched_video("abc123_doctor_who_all_tvmaze_claude-code_v1", &show_key),
+            None
+        );
+        assert!(same_show(" breaking BAD", "Breaking Bad"));
+    }
+
+    #[test]
+    fn test_is_series_entry() {
+        let series_ids = vec![("tvmaze_de_breaking_bad".to_string(), 169)];
+        assert!(is_series_entry("tvmaze_de_169", &series_ids));
+        assert!(is_series_entry("tvmaze_169_seasons_1_2", &series_ids));
+        assert!(!is_series_entry("tvmaze_de_1169", &series_ids));
+        assert!(!is_series_entry("trakt_169", &series_ids));
+    }
+}
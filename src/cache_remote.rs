@@ -0,0 +1,178 @@
+//! Remote cache module
+//!
+//! Machines working on the same media can share their caches through an
+//! HTTP key-value store given by `DIALOG_DETECTIVE_CACHE_URL`, used as a
+//! `CacheBackend` by the cache storages.
+//!
+//! The store is best-effort. Requests are made while a video is processed,
+//! so once a store couldn't be reached, it is left alone for the rest of
+//! the process instead of having every cache access wait for it again.
+
+use crate::cache::CacheBackend;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Environment variable holding the base URL of a shared remote cache
+const REMOTE_URL_ENV: &str = "DIALOG_DETECTIVE_CACHE_URL";
+
+/// Environment variable holding a bearer token for the remote cache
+const REMOTE_TOKEN_ENV: &str = "DIALOG_DETECTIVE_CACHE_TOKEN";
+
+/// Timeout of a request to the remote cache
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Timeout of connecting to the remote cache
+const REMOTE_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Base URLs of the stores that couldn't be reached by this process
+static UNREACHABLE: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// A cache shared by several machines through an HTTP key-value store
+///
+/// Entries are read with `GET`, written with `PUT`, and deleted with
+/// `DELETE` at `<base URL>/<cache>/<identifier>.json`, where `<cache>` is
+/// the path of the cache in the local cache directory, e.g. `transcripts`
+/// or `namespaces/kids_tv/matching`. The bodies are the JSON of the local
+/// cache files, and missing entries are answered with any non-success
+/// status, usually 404.
+pub(crate) struct RemoteCache {
+    client: reqwest::blocking::Client,
+    /// Base URL of the store, shared by all its caches
+    base_url: String,
+    /// URL of the cache, without a trailing slash
+    url: String,
+    token: Option<String>,
+}
+
+impl RemoteCache {
+    /// Creates the remote cache for a cache path if `DIALOG_DETECTIVE_CACHE_URL` is set
+    pub(crate) fn from_env(cache_path: &Path) -> Option<Self> {
+        let base_url = std::env::var(REMOTE_URL_ENV).ok()?;
+        let token = std::env::var(REMOTE_TOKEN_ENV).ok();
+        Self::new(&base_url, token, cache_path)
+    }
+
+    /// Creates the remote cache for a cache path, ignoring blank URLs and tokens
+    pub(crate) fn new(base_url: &str, token: Option<String>, cache_path: &Path) -> Option<Self> {
+        let base_url = base_url.trim().trim_end_matches('/');
+        if base_url.is_empty() {
+            return None;
+        }
+        let cache_path: Vec<_> = cache_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        let client = reqwest::blocking::Client::builder()
+            .timeout(REMOTE_TIMEOUT)
+            .connect_timeout(REMOTE_CONNECT_TIMEOUT)
+            .build()
+            .ok()?;
+
+        Some(Self {
+            client,
+            base_url: base_url.to_string(),
+            url: format!("{}/{}", base_url, cache_path.join("/")),
+            token: token.filter(|token| !token.trim().is_empty()),
+        })
+    }
+
+    /// Sends a request for an entry, unless the store couldn't be reached before
+    ///
+    /// A failure to connect or a timeout marks the store as unreachable.
+    fn send(
+        &self,
+        request: reqwest::blocking::RequestBuilder,
+    ) -> Option<reqwest::blocking::Response> {
+        if self.is_unreachable() {
+            return None;
+        }
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        match request.send() {
+            Ok(response) => Some(response),
+            Err(e) => {
+                if e.is_connect() || e.is_timeout() {
+                    lock_unreachable().insert(self.base_url.clone());
+                }
+                None
+            }
+        }
+    }
+
+    /// Returns whether the store couldn't be reached by this process
+    fn is_unreachable(&self) -> bool {
+        lock_unreachable().contains(&self.base_url)
+    }
+
+    /// Returns the URL of an entry
+    fn entry_url(&self, sanitized_id: &str) -> String {
+        format!("{}/{}.json", self.url, sanitized_id)
+    }
+}
+
+impl CacheBackend for RemoteCache {
+    fn get(&self, sanitized_id: &str) -> Option<String> {
+        let response = self.send(self.client.get(self.entry_url(sanitized_id)))?;
+        if !response.status().is_success() {
+            return None;
+        }
+        response.text().ok()
+    }
+
+    fn put(&self, sanitized_id: &str, content: String) {
+        let request = self
+            .client
+            .put(self.entry_url(sanitized_id))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(content);
+        self.send(request);
+    }
+
+    fn delete(&self, sanitized_id: &str) {
+        self.send(self.client.delete(self.entry_url(sanitized_id)));
+    }
+}
+
+/// Locks the base URLs of the unreachable stores
+fn lock_unreachable() -> MutexGuard<'static, HashSet<String>> {
+    UNREACHABLE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::cache_dir_in;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_remote_cache_url() {
+        let path = cache_dir_in(Path::new(""), "matching", Some("Kids TV"));
+        let remote =
+            RemoteCache::new("https://kv.example.com/dd/ ", Some(" ".into()), &path).unwrap();
+        assert_eq!(
+            remote.url,
+            "https://kv.example.com/dd/namespaces/kids_tv/matching"
+        );
+        assert_eq!(remote.token, None);
+        assert!(RemoteCache::new("  ", None, &path).is_none());
+    }
+
+    #[test]
+    fn test_unreachable_remote_disabled() {
+        // Nothing listens on the port once the listener is dropped
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let transcripts = RemoteCache::new(&base_url, None, Path::new("transcripts")).unwrap();
+        let matching = RemoteCache::new(&base_url, None, Path::new("matching")).unwrap();
+        assert!(!matching.is_unreachable());
+        assert_eq!(transcripts.get("abc"), None);
+        assert!(transcripts.is_unreachable());
+        assert!(matching.is_unreachable());
+    }
+}
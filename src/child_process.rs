@@ -0,0 +1,413 @@
+//! Child process module
+//!
+//! FFmpeg, the AI CLIs, and provider plugins run as child processes. Each
+//! is spawned behind a guard that kills it if it is still running when the
+//! guard is dropped, so returning early with an error or panicking doesn't
+//! leave it behind. Running children are also tracked in a registry, so
+//! they can be killed when the process is terminated by a signal, where no
+//! destructors run.
+//!
+//! On Unix, each child is started in a process group of its own, and the
+//! whole group is killed. The AI CLIs are usually npm shims running Node,
+//! which would keep running (and keep the pipes open) if only the shim was
+//! killed.
+//!
+//! A damaged video can make FFmpeg hang, and an AI CLI can wait forever on
+//! a stalled connection. Instead of stalling the whole run, such a process
+//! is killed once its stage timeout has passed, so the video fails and the
+//! investigation moves on.
+
+use std::io::{self, Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Interval of checking whether a process has exited
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Most children tracked at once
+///
+/// Atomics instead of a locked collection keep the registry usable from a
+/// signal handler. Further children are still killed by their guards.
+const MAX_TRACKED: usize = 64;
+
+/// Process IDs of the running children, 0 for a free slot
+static RUNNING: [AtomicU32; MAX_TRACKED] = [const { AtomicU32::new(0) }; MAX_TRACKED];
+
+/// A child process that is killed if it still runs when dropped
+#[derive(Debug)]
+pub(crate) struct ChildGuard {
+    child: Child,
+    /// Slot of the child in the registry, None once it has exited
+    slot: Option<usize>,
+    /// Exit status of the child, once it was reaped
+    status: Option<ExitStatus>,
+}
+
+impl ChildGuard {
+    /// Spawns a command as a tracked child process
+    pub fn spawn(command: &mut Command) -> io::Result<Self> {
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(command, 0);
+        let child = command.spawn()?;
+        let slot = track(child.id());
+        Ok(Self {
+            child,
+            slot,
+            status: None,
+        })
+    }
+
+    /// Waits for the process to exit and collects its output, like
+    /// `Child::wait_with_output`
+    ///
+    /// Without a timeout, this waits as long as the process runs. Otherwise
+    /// the process is killed once the timeout has passed, and an error of
+    /// kind `TimedOut` is returned. Piped stdout and stderr are read while
+    /// waiting, so the process doesn't block on a full pipe.
+    pub fn wait_with_timeout(&mut self, timeout: Option<Duration>) -> io::Result<Output> {
+        let stdout = self.child.stdout.take().map(read_in_background);
+        let stderr = self.child.stderr.take().map(read_in_background);
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let status = loop {
+            if let Some(status) = self.try_wait()? {
+                break status;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.kill();
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+
+        let collect = |reader: Option<thread::JoinHandle<Vec<u8>>>| {
+            reader
+                .map(|reader| reader.join().unwrap_or_default())
+                .unwrap_or_default()
+        };
+        Ok(Output {
+            status,
+            stdout: collect(stdout),
+            stderr: collect(stderr),
+        })
+    }
+
+    /// Writes input to the piped stdin of the process, then waits like
+    /// `wait_with_timeout`
+    ///
+    /// The input is written on another thread, so a process that doesn't
+    /// read it is still stopped by the timeout, and one that writes its
+    /// output first doesn't deadlock. stdin is closed once the input was
+    /// written. A process exiting without reading all of it is judged by
+    /// its exit status, while other errors writing it are returned.
+    pub fn wait_with_input(
+        &mut self,
+        input: Vec<u8>,
+        timeout: Option<Duration>,
+    ) -> io::Result<Output> {
+        let writer = self.child.stdin.take().map(|mut stdin| {
+            thread::spawn(move || match stdin.write_all(&input) {
+                Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e),
+                _ => Ok(()),
+            })
+        });
+        let output = self.wait_with_timeout(timeout)?;
+        if let Some(writer) = writer {
+            writer.join().unwrap_or(Ok(()))?;
+        }
+        Ok(output)
+    }
+
+    /// Reaps the process if it has exited
+    ///
+    /// The process is removed from the registry before it is reaped, as its
+    /// process ID may be reused from then on. What is left of its process
+    /// group, e.g. a CLI started by a shim, is killed, so it can't keep the
+    /// pipes open.
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        if self.status.is_none() && self.has_exited()? {
+            self.kill_group();
+            self.untrack();
+            self.status = self.child.try_wait()?;
+        }
+        Ok(self.status)
+    }
+
+    /// Returns whether the process has exited, without reaping it
+    #[cfg(unix)]
+    fn has_exited(&self) -> io::Result<bool> {
+        let pid = self.child.id() as libc::id_t;
+        loop {
+            // SAFETY: waitid only writes to the zeroed siginfo_t passed
+            let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+            let flags = libc::WEXITED | libc::WNOHANG | libc::WNOWAIT;
+            if unsafe { libc::waitid(libc::P_PID, pid, &mut info, flags) } == 0 {
+                // SAFETY: si_pid is set by waitid, and left 0 if nothing exited
+                return Ok(unsafe { info.si_pid() } != 0);
+            }
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+    }
+
+    /// Returns whether the process has exited
+    ///
+    /// Without signal handlers, reaping it early does no harm.
+    #[cfg(not(unix))]
+    fn has_exited(&mut self) -> io::Result<bool> {
+        Ok(self.child.try_wait()?.is_some())
+    }
+
+    /// Kills the process and waits for it to exit
+    fn kill(&mut self) {
+        self.kill_group();
+        // Its process ID may be reused once it was waited for
+        self.untrack();
+        self.status = self.child.wait().ok();
+    }
+
+    /// Kills the process group of the process, which it leads
+    ///
+    /// Must not be called once the process was reaped, as the group ID may
+    /// have been reused by then.
+    #[cfg(unix)]
+    fn kill_group(&mut self) {
+        if let Ok(pid @ 1..) = libc::pid_t::try_from(self.child.id()) {
+            // SAFETY: kill only sends a signal to the process group
+            unsafe {
+                libc::kill(-pid, libc::SIGKILL);
+            }
+        }
+    }
+
+    /// Kills the process, as it has no process group of its own
+    #[cfg(not(unix))]
+    fn kill_group(&mut self) {
+        self.child.kill().ok();
+    }
+
+    /// Removes the process from the registry
+    ///
+    /// The slot is left alone if `kill_child_processes` has already freed
+    /// it, as another child may have taken it since.
+    fn untrack(&mut self) {
+        if let Some(slot) = self.slot.take() {
+            let pid = self.child.id();
+            RUNNING[slot]
+                .compare_exchange(pid, 0, Ordering::SeqCst, Ordering::SeqCst)
+                .ok();
+        }
+    }
+}
+
+impl Deref for ChildGuard {
+    type Target = Child;
+
+    fn deref(&self) -> &Self::Target {
+        &self.child
+    }
+}
+
+impl DerefMut for ChildGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.child
+    }
+}
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        if matches!(self.try_wait(), Ok(None)) {
+            self.kill();
+        }
+        self.untrack();
+    }
+}
+
+/// Runs a command as a tracked child process and collects its output
+///
+/// Like `Command::output`, stdin is closed and stdout and stderr are
+/// captured, but the process is killed along with this one.
+pub(crate) fn output(command: &mut Command) -> io::Result<Output> {
+    ChildGuard::spawn(
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )?
+    .wait_with_timeout(None)
+}
+
+/// Adds a process to the registry, returning its slot if one was free
+fn track(pid: u32) -> Option<usize> {
+    RUNNING.iter().position(|slot| {
+        slot.compare_exchange(0, pid, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    })
+}
+
+/// Reads a pipe to its end on another thread
+fn read_in_background(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        pipe.read_to_end(&mut buffer).ok();
+        buffer
+    })
+}
+
+/// Kills the child processes that are still running
+///
+/// Meant for shutting down, e.g. from a signal handler of your own: the
+/// processes and their process groups are killed without being waited
+/// for, and only calls that are safe in a signal handler are made.
+#[cfg(unix)]
+pub fn kill_child_processes() {
+    for slot in &RUNNING {
+        let pid = slot.swap(0, Ordering::SeqCst);
+        if let Ok(pid @ 1..) = libc::pid_t::try_from(pid) {
+            // SAFETY: kill only sends a signal to the process group
+            unsafe {
+                libc::kill(-pid, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+/// Kills the child processes when the process is interrupted, terminated,
+/// or panics
+///
+/// Installs handlers for SIGINT, SIGTERM, and SIGHUP that kill the running
+/// children and remove temporary files before the signal takes its default
+/// effect. Meant to be called once at the start of a program; replaces
+/// handlers installed before.
+///
+/// Panics unwinding the stack leave the children to their guards and the
+/// temporary files to theirs, as the panic may still be caught. Only where
+/// panics abort the process, a panic hook does the cleanup before calling
+/// the previous one.
+#[cfg(unix)]
+pub fn kill_child_processes_on_exit() {
+    extern "C" fn handle(signal: libc::c_int) {
+        kill_child_processes();
+        crate::temp::remove_registered_files();
+        // SAFETY: signal and raise are async-signal-safe
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+
+    let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+        // SAFETY: the handler only makes async-signal-safe calls
+        unsafe {
+            libc::signal(signal, handler);
+        }
+    }
+
+    if cfg!(panic = "abort") {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            kill_child_processes();
+            crate::temp::remove_registered_files();
+            previous(info);
+        }));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+
+    fn is_tracked(pid: u32) -> bool {
+        RUNNING
+            .iter()
+            .any(|slot| slot.load(Ordering::SeqCst) == pid)
+    }
+
+    #[test]
+    fn test_wait_with_timeout() {
+        let mut child = ChildGuard::spawn(
+            Command::new("sh")
+                .args(["-c", "echo done"])
+                .stdout(Stdio::piped()),
+        )
+        .unwrap();
+        let pid = child.id();
+        let output = child
+            .wait_with_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"done\n");
+        assert!(!is_tracked(pid));
+
+        let started = Instant::now();
+        let mut child = ChildGuard::spawn(Command::new("sleep").arg("10")).unwrap();
+        let error = child
+            .wait_with_timeout(Some(Duration::from_millis(200)))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_wait_with_input() {
+        // cat writes its output while reading, which would fill stdout
+        // before stdin was written on the same thread
+        let input = vec![b'x'; 1 << 20];
+        let mut child = ChildGuard::spawn(
+            Command::new("cat")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped()),
+        )
+        .unwrap();
+        let output = child
+            .wait_with_input(input.clone(), Some(Duration::from_secs(20)))
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, input);
+
+        // A process not reading its input is stopped by the timeout
+        let mut child =
+            ChildGuard::spawn(Command::new("sleep").arg("10").stdin(Stdio::piped())).unwrap();
+        let error = child
+            .wait_with_input(input, Some(Duration::from_millis(200)))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_wait_kills_process_group() {
+        // The background sleep inherits stdout and would keep it open
+        let started = Instant::now();
+        let mut child = ChildGuard::spawn(
+            Command::new("sh")
+                .args(["-c", "sleep 10 & echo started"])
+                .stdout(Stdio::piped()),
+        )
+        .unwrap();
+        let output = child
+            .wait_with_timeout(Some(Duration::from_secs(20)))
+            .unwrap();
+        assert_eq!(output.stdout, b"started\n");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_child_guard_kills_on_drop() {
+        let child = ChildGuard::spawn(Command::new("sleep").arg("10")).unwrap();
+        let pid = child.id();
+        assert!(is_tracked(pid));
+
+        drop(child);
+        assert!(!is_tracked(pid));
+        // The process was killed and waited for, so it no longer exists
+        let exists = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+        assert!(!exists);
+    }
+}
@@ -0,0 +1,300 @@
+//! Evidence cross-check module
+//!
+//! Release file names often state the episode already (`Show.S02E05.mkv`).
+//! Such a name is evidence independent of the dialogue: if it disagrees with
+//! the match, one of the two is wrong, and the file is better reviewed than
+//! renamed on the word of either source alone. The same goes for the length
+//! of the video, which should roughly match the runtime of the episode -
+//! unless the episode is split into several files, whose lengths add up.
+
+use crate::season_inference::episode_from_path;
+use crate::{Episode, MatchResult};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Factor by which video duration and episode runtime may differ
+///
+/// Generous, as runtimes are often given as broadcast slots including ads
+/// (a 42 minute episode listed with 60 minutes).
+const RUNTIME_TOLERANCE: f64 = 2.0;
+
+/// An episode claimed by the file name that disagrees with the match
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FilenameConflict {
+    /// Season number claimed by the file name
+    pub season_number: usize,
+
+    /// Episode number claimed by the file name
+    pub episode_number: usize,
+
+    /// The claimed episode, if it is part of the known episodes
+    pub episode: Option<Episode>,
+}
+
+/// Compares the episode claimed by a file name with the matched episode
+///
+/// Returns None if the file name doesn't state an episode or agrees with the
+/// match. `known_episodes` is searched for the claimed episode's metadata.
+pub(crate) fn check_filename<'a>(
+    path: &Path,
+    matched: &Episode,
+    known_episodes: impl IntoIterator<Item = &'a Episode>,
+) -> Option<FilenameConflict> {
+    let (season_number, episode_number) = episode_from_path(path)?;
+    if (season_number, episode_number) == (matched.season_number, matched.episode_number) {
+        return None;
+    }
+
+    let episode = known_episodes
+        .into_iter()
+        .find(|e| e.season_number == season_number && e.episode_number == episode_number)
+        .cloned();
+
+    Some(FilenameConflict {
+        season_number,
+        episode_number,
+        episode,
+    })
+}
+
+/// Factor applied to the confidence of a match with a runtime mismatch
+pub(crate) const RUNTIME_MISMATCH_PENALTY: f64 = 0.5;
+
+/// A video whose duration doesn't fit the runtime of the matched episode
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuntimeMismatch {
+    /// Duration of the video
+    pub video_duration: Duration,
+
+    /// Scheduled runtime of the matched episode
+    pub episode_runtime: Duration,
+}
+
+/// Compares the duration of a video with the runtime of the matched episodes
+///
+/// The runtimes of all episodes in the video are added up. Returns None if
+/// the duration or any runtime is unknown, or they differ by less than a
+/// factor of two, e.g. a 25 minute file matched to a 90 minute special.
+pub(crate) fn check_runtime<'a>(
+    video_duration: Duration,
+    matched: impl IntoIterator<Item = &'a Episode>,
+) -> Option<RuntimeMismatch> {
+    let episode_runtime = total_runtime(matched)?;
+    if video_duration.is_zero() || episode_runtime.is_zero() {
+        return None;
+    }
+
+    let ratio = video_duration.as_secs_f64() / episode_runtime.as_secs_f64();
+    if (1.0 / RUNTIME_TOLERANCE..=RUNTIME_TOLERANCE).contains(&ratio) {
+        return None;
+    }
+
+    Some(RuntimeMismatch {
+        video_duration,
+        episode_runtime,
+    })
+}
+
+/// Adds up the runtimes of episodes, None if any of them is unknown
+fn total_runtime<'a>(episodes: impl IntoIterator<Item = &'a Episode>) -> Option<Duration> {
+    episodes
+        .into_iter()
+        .map(|e| {
+            e.runtime
+                .map(|minutes| Duration::from_secs(u64::from(minutes) * 60))
+        })
+        .sum()
+}
+
+/// Minimum confidence of matches to be considered parts of one episode
+const PART_MIN_CONFIDENCE: f64 = 0.8;
+
+/// One of several files an episode is split into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EpisodePart {
+    /// Position of the file within the episode, starting at 1
+    pub number: usize,
+
+    /// Number of files the episode is split into
+    pub count: usize,
+}
+
+/// Labels matches of the same episode as its parts, if they look like parts
+///
+/// All matches of the episode must be confident (or report no confidence),
+/// and their durations must add up to the episode's runtime better than
+/// each of them alone does. Parts are numbered in the order of the matches.
+pub(crate) fn detect_parts(results: &mut [MatchResult]) {
+    let mut groups: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (index, result) in results.iter().enumerate() {
+        groups
+            .entry((result.episode.season_number, result.episode.episode_number))
+            .or_default()
+            .push(index);
+    }
+
+    for indices in groups.into_values().filter(|indices| indices.len() > 1) {
+        let group: Vec<&MatchResult> = indices.iter().map(|&index| &results[index]).collect();
+        if !are_parts(&group) {
+            continue;
+        }
+
+        let count = indices.len();
+        for (position, index) in indices.into_iter().enumerate() {
+            results[index].part = Some(EpisodePart {
+                number: position + 1,
+                count,
+            });
+        }
+    }
+}
+
+/// Checks whether matches of the same episode are parts of it
+fn are_parts(group: &[&MatchResult]) -> bool {
+    let confident = group.iter().all(|r| {
+        r.extra_episodes.is_empty() && r.confidence.is_none_or(|c| c >= PART_MIN_CONFIDENCE)
+    });
+    let Some(durations) = group
+        .iter()
+        .map(|r| r.video_duration)
+        .collect::<Option<Vec<Duration>>>()
+    else {
+        return false;
+    };
+    let Some(runtime) = total_runtime([&group[0].episode]) else {
+        return false;
+    };
+    if !confident || runtime.is_zero() || durations.iter().any(Duration::is_zero) {
+        return false;
+    }
+
+    // Distance on a logarithmic scale, so half and double are equally far off
+    let distance = |duration: Duration| (duration.as_secs_f64() / runtime.as_secs_f64()).ln().abs();
+    let total: Duration = durations.iter().sum();
+    check_runtime(total, [&group[0].episode]).is_none()
+        && durations.iter().all(|&d| distance(d) > distance(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(season: usize, number: usize) -> Episode {
+        Episode {
+            season_number: season,
+            episode_number: number,
+            name: format!("Episode {}", number),
+            summary: String::new(),
+            summary_source: None,
+            airdate: None,
+            runtime: None,
+            season_name: None,
+            provider_id: None,
+            local_title: None,
+        }
+    }
+
+    #[test]
+    fn test_check_filename() {
+        let known = [episode(1, 2), episode(1, 3)];
+        let path = Path::new("/tv/Show.S01E02.mkv");
+
+        assert_eq!(check_filename(path, &episode(1, 2), &known), None);
+        assert_eq!(
+            check_filename(path, &episode(1, 3), &known),
+            Some(FilenameConflict {
+                season_number: 1,
+                episode_number: 2,
+                episode: Some(episode(1, 2)),
+            })
+        );
+        assert_eq!(
+            check_filename(Path::new("/tv/title_t00.mkv"), &episode(1, 3), &known),
+            None
+        );
+    }
+
+    #[test]
+    fn test_check_runtime() {
+        let mut special = episode(1, 1);
+        special.runtime = Some(90);
+        let mut regular = episode(1, 2);
+        regular.runtime = Some(60);
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+
+        assert_eq!(
+            check_runtime(minutes(25), [&special]),
+            Some(RuntimeMismatch {
+                video_duration: minutes(25),
+                episode_runtime: minutes(90),
+            })
+        );
+        assert_eq!(check_runtime(minutes(42), [&regular]), None);
+        assert_eq!(check_runtime(minutes(25), [&episode(1, 3)]), None);
+
+        // Double episodes run as long as both episodes together
+        assert_eq!(check_runtime(minutes(170), [&special, &special]), None);
+    }
+
+    #[test]
+    fn test_detect_parts() {
+        let mut long = episode(1, 1);
+        long.runtime = Some(60);
+        let minutes = |m: u64| Some(Duration::from_secs(m * 60));
+        let result =
+            |file: &str, duration: Option<Duration>, confidence: Option<f64>| MatchResult {
+                video: crate::VideoFile::new(file),
+                video_hash: String::new(),
+                episode: long.clone(),
+                confidence,
+                alternatives: Vec::new(),
+                reasoning: None,
+                extra_episodes: Vec::new(),
+                filename_conflict: None,
+                runtime_mismatch: None,
+                video_duration: duration,
+                part: None,
+                transcript: None,
+                transcript_quality: None,
+                timings: crate::StageTimings::default(),
+                usage: None,
+            };
+
+        let mut halves = vec![
+            result("a.mkv", minutes(22), Some(0.9)),
+            result("b.mkv", minutes(21), None),
+        ];
+        detect_parts(&mut halves);
+        assert_eq!(
+            halves.iter().map(|r| r.part).collect::<Vec<_>>(),
+            vec![
+                Some(EpisodePart {
+                    number: 1,
+                    count: 2
+                }),
+                Some(EpisodePart {
+                    number: 2,
+                    count: 2
+                }),
+            ]
+        );
+
+        // Two full-length copies are duplicates, not parts
+        let mut copies = vec![
+            result("a.mkv", minutes(58), Some(0.9)),
+            result("b.mkv", minutes(59), Some(0.9)),
+        ];
+        detect_parts(&mut copies);
+        assert!(copies.iter().all(|r| r.part.is_none()));
+
+        // Doubtful matches are left alone
+        let mut doubtful = vec![
+            result("a.mkv", minutes(30), Some(0.9)),
+            result("b.mkv", minutes(30), Some(0.4)),
+        ];
+        detect_parts(&mut doubtful);
+        assert!(doubtful.iter().all(|r| r.part.is_none()));
+    }
+}
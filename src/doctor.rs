@@ -0,0 +1,506 @@
+//! Health check module
+//!
+//! A run depends on FFmpeg, a Whisper model, an AI CLI with valid
+//! credentials, the metadata APIs, and writable cache directories. Any of
+//! them missing only shows up once the investigation reaches it, possibly
+//! after transcribing for an hour. This module checks all of them up front
+//! and suggests a fix for every problem found.
+
+use crate::MatcherType;
+use crate::ai_matcher::MatcherCommand;
+use crate::child_process::{self, ChildGuard};
+use crate::model_downloader::{cached_model_path, validate_model_file};
+use humansize::{BINARY, format_size};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// How long to wait for the metadata APIs to respond
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for the matcher's CLI to report its version
+const VERSION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Outcome of a single health check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// Everything is in order
+    Ok,
+    /// Works, but some features are unavailable or slower
+    Warning,
+    /// Runs will fail until this is fixed
+    Failed,
+}
+
+/// Result of checking one dependency
+#[derive(Debug, Clone)]
+pub struct HealthCheck {
+    /// What was checked, e.g. "FFmpeg"
+    pub name: String,
+
+    /// Whether the check passed
+    pub status: CheckStatus,
+
+    /// What was found, e.g. a version or an error
+    pub detail: String,
+
+    /// How to fix a warning or failure
+    pub fix: Option<String>,
+}
+
+impl HealthCheck {
+    fn ok(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warning(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Warning,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn failed(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Failed,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Checks everything a run depends on
+///
+/// The Whisper model is the one given by path, or else the cached model of
+/// the given name. Checking the metadata APIs needs network access, but no
+/// matcher call is made, so the check costs no tokens.
+pub fn run_health_checks(
+    matcher: MatcherType,
+    model: &str,
+    model_path: Option<&Path>,
+) -> Vec<HealthCheck> {
+    let mut checks = check_ffmpeg();
+    checks.push(check_whisper_model(model, model_path));
+    checks.push(check_gpu());
+    checks.extend(check_matcher(matcher, &MatcherCommand::default()));
+    checks.extend(check_metadata_apis());
+    checks.push(check_cache_directory());
+    checks.push(check_temp_directory());
+    checks
+}
+
+/// Checks that FFmpeg and ffprobe can be run
+#[cfg(feature = "ffmpeg")]
+fn check_ffmpeg() -> Vec<HealthCheck> {
+    use ffmpeg_sidecar::command::ffmpeg_is_installed;
+    use ffmpeg_sidecar::ffprobe::{ffprobe_is_installed, ffprobe_version};
+    use ffmpeg_sidecar::version::ffmpeg_version;
+
+    let install = "Install FFmpeg with your package manager (e.g. `brew install ffmpeg` or `apt install ffmpeg`) or place it next to this executable";
+    let ffmpeg = if !ffmpeg_is_installed() {
+        HealthCheck::failed("FFmpeg", "not found", install)
+    } else {
+        match ffmpeg_version() {
+            Ok(version) => HealthCheck::ok("FFmpeg", format!("version {}", version)),
+            Err(e) => HealthCheck::failed("FFmpeg", format!("can't be run: {}", e), install),
+        }
+    };
+    // ffprobe is only needed for tags and embedded subtitles
+    let ffprobe = if !ffprobe_is_installed() {
+        HealthCheck::warning(
+            "ffprobe",
+            "not found, --write-tags and --subtitles embed won't work",
+            "ffprobe ships with FFmpeg; make sure it's installed next to ffmpeg",
+        )
+    } else {
+        match ffprobe_version() {
+            Ok(version) => HealthCheck::ok("ffprobe", format!("version {}", version)),
+            Err(e) => HealthCheck::warning(
+                "ffprobe",
+                format!("can't be run: {}", e),
+                "Reinstall FFmpeg, which includes ffprobe",
+            ),
+        }
+    };
+
+    vec![ffmpeg, ffprobe]
+}
+
+/// Reports that audio extraction isn't built in
+#[cfg(not(feature = "ffmpeg"))]
+fn check_ffmpeg() -> Vec<HealthCheck> {
+    vec![HealthCheck::warning(
+        "FFmpeg",
+        "not built in, audio can't be extracted",
+        "Rebuild with the `ffmpeg` feature, or pass a custom transcriber to the library",
+    )]
+}
+
+/// Checks that the Whisper model exists and looks like a GGML model
+fn check_whisper_model(model: &str, model_path: Option<&Path>) -> HealthCheck {
+    let name = "Whisper model";
+    let path = match model_path {
+        Some(path) => path.to_path_buf(),
+        None => match cached_model_path(model) {
+            Ok(path) => path,
+            Err(e) => {
+                return HealthCheck::failed(
+                    name,
+                    e.to_string(),
+                    "Make sure the cache directory can be created, or pass --model-path",
+                );
+            }
+        },
+    };
+
+    if model_path.is_none() && !path.exists() {
+        return HealthCheck::warning(
+            name,
+            format!("'{}' not downloaded yet", model),
+            format!(
+                "It's downloaded to {} on the first run, or pass --model-path to use an existing model",
+                path.display()
+            ),
+        );
+    }
+
+    match validate_model_file(&path) {
+        Ok(size) => HealthCheck::ok(
+            name,
+            format!("{} ({})", path.display(), format_size(size, BINARY)),
+        ),
+        Err(e) if model_path.is_some() => HealthCheck::failed(
+            name,
+            e.to_string(),
+            "Pass a GGML model from https://huggingface.co/ggerganov/whisper.cpp",
+        ),
+        Err(e) => HealthCheck::failed(
+            name,
+            e.to_string(),
+            format!("Delete {} so the model is downloaded again", path.display()),
+        ),
+    }
+}
+
+/// Reports the GPU backend Whisper was built with
+fn check_gpu() -> HealthCheck {
+    let name = "GPU acceleration";
+    if let Some(backend) = gpu_backend() {
+        return HealthCheck::ok(name, backend);
+    }
+
+    // A CPU build on a machine with an NVIDIA GPU leaves speed on the table
+    let has_nvidia = child_process::output(Command::new("nvidia-smi").arg("-L"))
+        .is_ok_and(|output| output.status.success());
+    if has_nvidia {
+        HealthCheck::warning(
+            name,
+            "none, transcribing on the CPU although an NVIDIA GPU was found",
+            "Use a CUDA release build, or rebuild with `--features cuda`",
+        )
+    } else {
+        HealthCheck::warning(
+            name,
+            "none, transcribing on the CPU",
+            "Rebuild with `--features cuda`, `vulkan`, or `hipblas` if you have a supported GPU",
+        )
+    }
+}
+
+/// Returns the GPU backend compiled into Whisper, if any
+fn gpu_backend() -> Option<&'static str> {
+    if cfg!(feature = "cuda") {
+        Some("CUDA")
+    } else if cfg!(feature = "hipblas") {
+        Some("ROCm/hipBLAS")
+    } else if cfg!(feature = "vulkan") {
+        Some("Vulkan")
+    } else if cfg!(all(target_os = "macos", feature = "whisper")) {
+        Some("Metal")
+    } else {
+        None
+    }
+}
+
+/// Checks that the matcher's CLI is installed and signed in
+///
+/// The CLI is run the way the matcher runs it, in the command's working
+/// directory and with its environment, whose variables also count as
+/// credentials. Takes a few seconds at most and costs no tokens, so a run
+/// can check this before transcribing instead of failing at the first
+/// matching step.
+pub fn check_matcher(matcher: MatcherType, command: &MatcherCommand) -> Vec<HealthCheck> {
+    let (program, label, install, login) = match matcher {
+        MatcherType::Gemini | MatcherType::GeminiFlash => (
+            "gemini",
+            "Gemini CLI",
+            "Install it with `npm install -g @google/gemini-cli`",
+            "Run `gemini` once and sign in, or set GEMINI_API_KEY",
+        ),
+        MatcherType::Claude => (
+            "claude",
+            "Claude Code CLI",
+            "Install it with `npm install -g @anthropic-ai/claude-code`",
+            "Run `claude` once and log in, or set ANTHROPIC_API_KEY",
+        ),
+    };
+
+    if let Some(dir) = &command.working_dir
+        && !dir.is_dir()
+    {
+        return vec![HealthCheck::failed(
+            label,
+            format!("working directory {} doesn't exist", dir.display()),
+            "Pass an existing directory to --matcher-dir",
+        )];
+    }
+
+    let mut cmd = Command::new(program);
+    command.configure(&mut cmd);
+    let version = ChildGuard::spawn(
+        cmd.arg("--version")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null()),
+    )
+    .and_then(|mut child| child.wait_with_timeout(Some(VERSION_TIMEOUT)));
+    let installed = match version {
+        Ok(output) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout);
+            let version = version.lines().next().unwrap_or_default().trim();
+            HealthCheck::ok(label, format!("version {}", version))
+        }
+        Ok(output) => {
+            return vec![HealthCheck::failed(
+                label,
+                format!("`{} --version` failed with {}", program, output.status),
+                install,
+            )];
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return vec![HealthCheck::failed(label, "not found in PATH", install)];
+        }
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            return vec![HealthCheck::failed(
+                label,
+                format!(
+                    "`{} --version` didn't finish within {}s",
+                    program,
+                    VERSION_TIMEOUT.as_secs()
+                ),
+                install,
+            )];
+        }
+        Err(e) => {
+            return vec![HealthCheck::failed(
+                label,
+                format!("`{} --version` couldn't be run: {}", program, e),
+                install,
+            )];
+        }
+    };
+
+    let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    let credentials = find_credentials(matcher, home.as_deref(), |var| {
+        match command.env.iter().find(|(key, _)| key == var) {
+            Some((_, value)) => !value.is_empty(),
+            None => std::env::var_os(var).is_some_and(|value| !value.is_empty()),
+        }
+    });
+    let auth = match credentials {
+        Some(source) => HealthCheck::ok("Matcher credentials", source),
+        None => HealthCheck::warning(
+            "Matcher credentials",
+            format!("none found for the {}", label),
+            login,
+        ),
+    };
+
+    vec![installed, auth]
+}
+
+/// Looks for the credentials the matcher's CLI signs in with
+///
+/// Only checks that an API key or a login exists, not that it's still
+/// valid. Returns where the credentials were found.
+fn find_credentials(
+    matcher: MatcherType,
+    home: Option<&Path>,
+    env_is_set: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let (env_vars, files): (&[&str], &[&str]) = match matcher {
+        MatcherType::Gemini | MatcherType::GeminiFlash => (
+            &[
+                "GEMINI_API_KEY",
+                "GOOGLE_API_KEY",
+                "GOOGLE_GENAI_USE_VERTEXAI",
+            ],
+            &[".gemini/oauth_creds.json"],
+        ),
+        MatcherType::Claude => (
+            &["ANTHROPIC_API_KEY", "CLAUDE_CODE_OAUTH_TOKEN"],
+            &[".claude/.credentials.json"],
+        ),
+    };
+
+    if let Some(var) = env_vars.iter().find(|var| env_is_set(var)) {
+        return Some(format!("{} is set", var));
+    }
+    let home = home?;
+    if let Some(file) = files.iter().map(|f| home.join(f)).find(|f| f.is_file()) {
+        return Some(file.display().to_string());
+    }
+
+    // On macOS, Claude Code keeps its token in the keychain and only notes the account
+    let claude_config = home.join(".claude.json");
+    if matcher == MatcherType::Claude
+        && fs::read_to_string(&claude_config)
+            .is_ok_and(|config| config.contains("\"oauthAccount\""))
+    {
+        return Some(claude_config.display().to_string());
+    }
+
+    None
+}
+
+/// Checks that TVMaze, and TMDB if configured, can be reached
+fn check_metadata_apis() -> Vec<HealthCheck> {
+    let client = match reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            return vec![HealthCheck::failed(
+                "Metadata APIs",
+                e.to_string(),
+                "Check the TLS setup of this system",
+            )];
+        }
+    };
+    let reach = |name: &str, url: &str, query: &[(&str, &str)], fix: &str| match client
+        .get(url)
+        .query(query)
+        .send()
+    {
+        Ok(response) if response.status().is_success() => HealthCheck::ok(name, "reachable"),
+        Ok(response) => HealthCheck::failed(
+            name,
+            format!("responded with HTTP {}", response.status().as_u16()),
+            fix,
+        ),
+        Err(e) => HealthCheck::failed(name, format!("unreachable: {}", e), fix),
+    };
+
+    let mut checks = vec![reach(
+        "TVMaze API",
+        "https://api.tvmaze.com/shows/1",
+        &[],
+        "Check your network connection and proxy settings (HTTPS_PROXY)",
+    )];
+    match std::env::var("TMDB_API_KEY") {
+        Ok(key) if !key.trim().is_empty() => checks.push(reach(
+            "TMDB API",
+            "https://api.themoviedb.org/3/configuration",
+            &[("api_key", key.as_str())],
+            "Check TMDB_API_KEY, or unset it to skip TMDB summaries",
+        )),
+        _ => {}
+    }
+    checks
+}
+
+/// Checks that the cache directory can be written to
+fn check_cache_directory() -> HealthCheck {
+    let name = "Cache directory";
+    let Some(dirs) = directories::ProjectDirs::from("de", "westhoffswelt", "dialogdetective")
+    else {
+        return HealthCheck::failed(
+            name,
+            "can't determine the cache directory",
+            "Make sure HOME is set",
+        );
+    };
+
+    check_writable(name, dirs.cache_dir())
+}
+
+/// Checks that temporary audio files can be written
+fn check_temp_directory() -> HealthCheck {
+    check_writable("Temporary directory", &std::env::temp_dir())
+}
+
+/// Checks that a file can be created in a directory, creating it if needed
+fn check_writable(name: &str, dir: &Path) -> HealthCheck {
+    let probe: PathBuf = dir.join(format!(".doctor-{}", ulid::Ulid::new()));
+    let written = fs::create_dir_all(dir).and_then(|_| fs::write(&probe, b"ok"));
+    let _ = fs::remove_file(&probe);
+
+    match written {
+        Ok(()) => HealthCheck::ok(name, format!("{} is writable", dir.display())),
+        Err(e) => HealthCheck::failed(
+            name,
+            format!("{} isn't writable: {}", dir.display(), e),
+            format!("Fix the permissions of {}", dir.display()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_credentials() {
+        let home = std::env::temp_dir().join("dialog_detective_test_doctor_home");
+        fs::create_dir_all(home.join(".gemini")).unwrap();
+        fs::write(home.join(".gemini/oauth_creds.json"), "{}").unwrap();
+        fs::write(home.join(".claude.json"), r#"{"oauthAccount": {}}"#).unwrap();
+        let unset = |_: &str| false;
+
+        assert_eq!(
+            find_credentials(MatcherType::Gemini, Some(&home), |var| var
+                == "GEMINI_API_KEY"),
+            Some("GEMINI_API_KEY is set".to_string())
+        );
+        assert_eq!(
+            find_credentials(MatcherType::GeminiFlash, Some(&home), unset),
+            Some(home.join(".gemini/oauth_creds.json").display().to_string())
+        );
+        assert_eq!(
+            find_credentials(MatcherType::Claude, Some(&home), unset),
+            Some(home.join(".claude.json").display().to_string())
+        );
+
+        fs::remove_file(home.join(".claude.json")).unwrap();
+        assert_eq!(
+            find_credentials(MatcherType::Claude, Some(&home), unset),
+            None
+        );
+        assert_eq!(find_credentials(MatcherType::Claude, None, unset), None);
+
+        fs::remove_dir_all(&home).ok();
+    }
+
+    #[test]
+    fn test_check_matcher_working_dir() {
+        let command = MatcherCommand {
+            working_dir: Some(PathBuf::from("/nonexistent/dialog_detective")),
+            ..MatcherCommand::default()
+        };
+        let checks = check_matcher(MatcherType::Claude, &command);
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].status, CheckStatus::Failed);
+        assert!(checks[0].detail.contains("/nonexistent/dialog_detective"));
+    }
+}
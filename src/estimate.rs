@@ -0,0 +1,158 @@
+//! Run estimation module
+//!
+//! This module provides the heuristics behind `estimate_case`: given which
+//! transcripts and matches are already cached, it predicts how much work an
+//! investigation still needs, how long it will roughly take, and what the
+//! matcher calls will cost.
+
+use crate::MatcherType;
+use crate::ai_matcher::{TokenUsage, estimate_cost};
+use serde::Serialize;
+use std::time::Duration;
+
+/// Rough wall-clock time to extract and transcribe one episode (base model)
+const TRANSCRIPTION_TIME: Duration = Duration::from_secs(90);
+
+/// Rough wall-clock time of one matcher call
+const MATCHING_TIME: Duration = Duration::from_secs(30);
+
+/// Transcript length assumed when no cached transcript is available
+///
+/// Corresponds to roughly 45 minutes of dialogue.
+const DEFAULT_TRANSCRIPT_CHARS: usize = 32_000;
+
+/// Tokens the matcher is assumed to generate per call, including reasoning
+const OUTPUT_TOKENS_PER_CALL: u64 = 1_000;
+
+/// Predicted work, duration, and cost of an investigation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaseEstimate {
+    /// Number of video files found
+    pub video_count: usize,
+
+    /// Number of videos without a cached transcript
+    pub transcriptions_needed: usize,
+
+    /// Number of videos without a cached match
+    pub matcher_calls_needed: usize,
+
+    /// Estimated tokens and cost of all needed matcher calls
+    pub usage: TokenUsage,
+
+    /// Estimated wall-clock duration of the remaining work
+    pub duration: Duration,
+}
+
+/// Accumulates the per-video predictions into a `CaseEstimate`
+pub(crate) struct Estimator {
+    matcher_type: MatcherType,
+    prompt_chars: usize,
+    estimate: CaseEstimate,
+}
+
+impl Estimator {
+    /// Creates an estimator for prompts of the given size (without transcript)
+    pub fn new(matcher_type: MatcherType, prompt_chars: usize) -> Self {
+        Self {
+            matcher_type,
+            prompt_chars,
+            estimate: CaseEstimate {
+                video_count: 0,
+                transcriptions_needed: 0,
+                matcher_calls_needed: 0,
+                usage: TokenUsage::default(),
+                duration: Duration::ZERO,
+            },
+        }
+    }
+
+    /// Adds a video to the estimate
+    ///
+    /// `cached_transcript_chars` is the length of the cached transcript, if
+    /// there is one, and `match_cached` whether its match is cached.
+    pub fn add_video(&mut self, cached_transcript_chars: Option<usize>, match_cached: bool) {
+        self.estimate.video_count += 1;
+
+        if cached_transcript_chars.is_none() {
+            self.estimate.transcriptions_needed += 1;
+            self.estimate.duration += TRANSCRIPTION_TIME;
+        }
+
+        if !match_cached {
+            let transcript_chars = cached_transcript_chars.unwrap_or(DEFAULT_TRANSCRIPT_CHARS);
+            let input_tokens = estimate_tokens(self.prompt_chars + transcript_chars);
+
+            self.estimate.matcher_calls_needed += 1;
+            self.estimate.duration += MATCHING_TIME;
+            self.estimate.usage += TokenUsage {
+                input_tokens,
+                output_tokens: OUTPUT_TOKENS_PER_CALL,
+                cost_usd: estimate_cost(
+                    pricing_model(self.matcher_type),
+                    input_tokens,
+                    OUTPUT_TOKENS_PER_CALL,
+                ),
+            };
+        }
+    }
+
+    /// Returns the accumulated estimate
+    pub fn finish(self) -> CaseEstimate {
+        self.estimate
+    }
+}
+
+/// Estimates the token count of a text (about four characters per token)
+fn estimate_tokens(chars: usize) -> u64 {
+    chars.div_ceil(4) as u64
+}
+
+/// The model a matcher type is billed as
+fn pricing_model(matcher_type: MatcherType) -> &'static str {
+    match matcher_type {
+        // Gemini CLI defaults to the pro model
+        MatcherType::Gemini => "gemini-2.5-pro",
+        MatcherType::GeminiFlash => "gemini-2.5-flash",
+        MatcherType::Claude => "claude-sonnet-4",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_videos_need_no_work() {
+        let mut estimator = Estimator::new(MatcherType::GeminiFlash, 4_000);
+        estimator.add_video(Some(8_000), true);
+
+        let estimate = estimator.finish();
+
+        assert_eq!(estimate.video_count, 1);
+        assert_eq!(estimate.transcriptions_needed, 0);
+        assert_eq!(estimate.matcher_calls_needed, 0);
+        assert_eq!(estimate.usage, TokenUsage::default());
+        assert_eq!(estimate.duration, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_uncached_videos_are_estimated() {
+        let mut estimator = Estimator::new(MatcherType::GeminiFlash, 4_000);
+        // Cached transcript, but not matched yet
+        estimator.add_video(Some(8_000), false);
+        // Nothing cached, default transcript length is assumed
+        estimator.add_video(None, false);
+
+        let estimate = estimator.finish();
+
+        assert_eq!(estimate.transcriptions_needed, 1);
+        assert_eq!(estimate.matcher_calls_needed, 2);
+        assert_eq!(estimate.usage.input_tokens, 3_000 + 9_000);
+        assert_eq!(estimate.usage.output_tokens, 2_000);
+        assert!(estimate.usage.cost_usd.is_some());
+        assert_eq!(
+            estimate.duration,
+            TRANSCRIPTION_TIME + MATCHING_TIME + MATCHING_TIME
+        );
+    }
+}
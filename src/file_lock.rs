@@ -0,0 +1,96 @@
+//! File lock module
+//!
+//! Advisory locks keeping several processes from working on the same file,
+//! like a model download or the job queue. The lock is held on an open lock
+//! file next to the locked one, so the OS releases it when the process
+//! exits, even if it crashed. There are no stale locks to detect, and the
+//! lock file itself is left in place: removing it would let another process
+//! lock a new file while the old one is still locked.
+
+use std::fs::{File, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a waiting process tries to take the lock again
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A held lock, released when dropped
+#[derive(Debug)]
+pub(crate) struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Locks a path by its lock file, waiting while another process holds it
+    ///
+    /// `on_wait` is called once if the lock is held elsewhere. Gives up and
+    /// returns None after `timeout`, or waits for as long as it takes
+    /// without one.
+    pub(crate) fn acquire(
+        lock_path: &Path,
+        timeout: Option<Duration>,
+        on_wait: impl FnOnce(),
+    ) -> io::Result<Option<Self>> {
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path)?;
+        let started = Instant::now();
+        let mut on_wait = Some(on_wait);
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Some(Self { _file: file })),
+                Err(TryLockError::WouldBlock) => {}
+                Err(TryLockError::Error(e)) => return Err(e),
+            }
+            if let Some(on_wait) = on_wait.take() {
+                on_wait();
+            }
+            if timeout.is_some_and(|timeout| started.elapsed() > timeout) {
+                return Ok(None);
+            }
+            thread::sleep(RETRY_INTERVAL);
+        }
+    }
+}
+
+/// Returns the path of the lock file of a path, `<path>.lock`
+pub(crate) fn lock_path(path: &Path) -> PathBuf {
+    let mut lock = path.as_os_str().to_owned();
+    lock.push(".lock");
+    PathBuf::from(lock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_file_lock() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_file_lock");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let path = lock_path(&temp_dir.join("queue.json"));
+        assert_eq!(path, temp_dir.join("queue.json.lock"));
+
+        let lock = FileLock::acquire(&path, None, || {}).unwrap().unwrap();
+        let mut waited = false;
+        let timeout = Some(Duration::from_millis(200));
+        let second = FileLock::acquire(&path, timeout, || waited = true).unwrap();
+        assert!(second.is_none());
+        assert!(waited);
+
+        let holder = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            drop(lock);
+        });
+        let second = FileLock::acquire(&path, None, || {}).unwrap();
+        holder.join().unwrap();
+        assert!(second.is_some());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+}
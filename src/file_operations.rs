@@ -1,8 +1,13 @@
-use crate::{Episode, MatchResult};
+use crate::release_tags::parse_release_tags;
+use crate::retry::RetryPolicy;
+use crate::{Episode, EpisodePart, MatchResult};
+use humansize::{BINARY, format_size};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fs;
-use std::io;
+use std::fs::{self, File, FileTimes};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
 /// Errors that can occur during file operations
@@ -12,10 +17,58 @@ pub enum FileOperationError {
     Io(#[from] io::Error),
 
     #[error("Invalid format string: {0}")]
-    InvalidFormat(String),
+    InvalidFormat(#[from] FormatError),
 
     #[error("Missing file extension for: {0}")]
     MissingExtension(String),
+
+    #[error("Missing file name for: {0}")]
+    MissingFileName(String),
+
+    #[error(
+        "Not enough free space in {}: {} needed, {} available",
+        path.display(),
+        format_size(*required, BINARY),
+        format_size(*available, BINARY)
+    )]
+    InsufficientSpace {
+        path: PathBuf,
+        required: u64,
+        available: u64,
+    },
+
+    #[error("Destination already exists: {0}")]
+    DestinationExists(String),
+
+    #[error("Renaming {} failed, all renames were undone: {source}", path.display())]
+    RolledBack { path: PathBuf, source: io::Error },
+
+    #[error(
+        "Renaming {} failed, and undoing the renames failed at {}: {source}",
+        path.display(),
+        stuck.display()
+    )]
+    RollbackFailed {
+        path: PathBuf,
+        stuck: PathBuf,
+        source: io::Error,
+    },
+}
+
+impl FileOperationError {
+    /// Returns the stable code of the error, like `DD-FOP-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            FileOperationError::Io(_) => "DD-FOP-001",
+            FileOperationError::InvalidFormat(error) => error.code(),
+            FileOperationError::MissingExtension(_) => "DD-FOP-002",
+            FileOperationError::MissingFileName(_) => "DD-FOP-003",
+            FileOperationError::InsufficientSpace { .. } => "DD-FOP-004",
+            FileOperationError::DestinationExists(_) => "DD-FOP-005",
+            FileOperationError::RolledBack { .. } => "DD-FOP-006",
+            FileOperationError::RollbackFailed { .. } => "DD-FOP-007",
+        }
+    }
 }
 
 /// Represents a planned file operation (rename or copy)
@@ -27,8 +80,267 @@ pub struct PlannedOperation {
     pub destination: PathBuf,
     /// Original episode matched (for display)
     pub episode: Episode,
+    /// Further episodes contained in the file, named along with `episode`
+    pub extra_episodes: Vec<Episode>,
     /// Duplicate suffix applied (if any)
     pub duplicate_suffix: Option<usize>,
+    /// Part of a split episode the file holds (if any)
+    pub part: Option<EpisodePart>,
+    /// Whether the file is moved aside as a duplicate instead of being named
+    pub quarantined: bool,
+}
+
+/// Name of the folder duplicates are moved into by `DuplicatePolicy::Quarantine`
+pub const DUPLICATES_DIR: &str = "duplicates";
+
+/// How to handle several files matching the same episode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Keep all files, numbering later ones `(2)`, `(3)`, ... (default)
+    #[default]
+    Suffix,
+    /// Keep the file with the highest video resolution, measured with ffprobe
+    HighestResolution,
+    /// Keep the largest file
+    Largest,
+    /// Keep the most confident match and move the others into `duplicates/`
+    Quarantine,
+}
+
+/// Match results split by a duplicate policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateResolution {
+    /// Matches to rename or copy, at most one per episode unless the policy
+    /// is `DuplicatePolicy::Suffix`
+    pub kept: Vec<MatchResult>,
+
+    /// Matches that lost against another file of the same episode
+    ///
+    /// Left untouched, or moved aside with `plan_quarantine` if the policy
+    /// is `DuplicatePolicy::Quarantine`.
+    pub discarded: Vec<MatchResult>,
+}
+
+/// The default file naming format, e.g. `Show - S01E02 - Title.mkv`
+pub const DEFAULT_FORMAT: &str = "{show} - S{season:02}E{episode:02} - {title}.{ext}";
+
+/// Placeholders that `format_filename` replaces
+pub const FORMAT_PLACEHOLDERS: &[&str] = &[
+    "show",
+    "season",
+    "episode",
+    "title",
+    "ext",
+    "season_name",
+    "show_year",
+    "provider_id",
+    "quality",
+    "source_tag",
+    "group",
+    "title_local",
+];
+
+/// Longest file name most file systems allow, in bytes
+const MAX_FILENAME_BYTES: usize = 255;
+
+/// Longest path many Windows programs can open (`MAX_PATH` without the null)
+const MAX_WINDOWS_PATH: usize = 259;
+
+/// Shortest length names are cut to for keeping a Windows path short
+///
+/// In deeper directories a readable name is worth a longer path, which
+/// extended-length paths can handle.
+const MIN_FILENAME_BYTES: usize = 80;
+
+/// Placeholders that accept a padding width, like `{season:02}`
+const PADDED_PLACEHOLDERS: &[&str] = &["season", "episode"];
+
+/// Placeholders a format should contain to produce usable, distinct names
+const EXPECTED_PLACEHOLDERS: &[&str] = &["episode", "ext"];
+
+/// Errors in the syntax of a file naming format
+///
+/// Positions are byte offsets into the format string.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FormatError {
+    #[error("'{{' at position {0} is never closed")]
+    UnclosedPlaceholder(usize),
+
+    #[error("'}}' at position {0} has no matching '{{'")]
+    UnmatchedBrace(usize),
+
+    #[error("empty placeholder at position {0}")]
+    EmptyPlaceholder(usize),
+
+    #[error("{0} has an invalid padding, expected a width like {{episode:02}}")]
+    InvalidPadding(String),
+
+    #[error("{0} can't be padded, only {{season}} and {{episode}} can")]
+    PaddingNotSupported(String),
+
+    #[error("unknown placeholder {0}")]
+    UnknownPlaceholder(String),
+}
+
+impl FormatError {
+    /// Returns the stable code of the error, like `DD-FMT-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            FormatError::UnclosedPlaceholder(_) => "DD-FMT-001",
+            FormatError::UnmatchedBrace(_) => "DD-FMT-002",
+            FormatError::EmptyPlaceholder(_) => "DD-FMT-003",
+            FormatError::InvalidPadding(_) => "DD-FMT-004",
+            FormatError::PaddingNotSupported(_) => "DD-FMT-005",
+            FormatError::UnknownPlaceholder(_) => "DD-FMT-006",
+        }
+    }
+}
+
+/// Problems in a format that don't stop it from being rendered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatWarning {
+    /// A placeholder that isn't replaced and would end up in the file name
+    UnknownPlaceholder(String),
+    /// A placeholder the format doesn't contain, e.g. `{ext}`
+    MissingPlaceholder(&'static str),
+}
+
+impl std::fmt::Display for FormatWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatWarning::UnknownPlaceholder(placeholder) => {
+                write!(f, "unknown placeholder {}", placeholder)
+            }
+            FormatWarning::MissingPlaceholder("ext") => {
+                write!(f, "no {{ext}}, files will lose their extension")
+            }
+            FormatWarning::MissingPlaceholder(name) => {
+                write!(
+                    f,
+                    "no {{{}}}, different episodes may get the same name",
+                    name
+                )
+            }
+        }
+    }
+}
+
+/// Checks the placeholder syntax of a file naming format
+///
+/// Fails on malformed placeholders, and returns warnings for unknown or
+/// missing ones. Literal braces aren't supported in formats.
+pub fn check_format(format: &str) -> Result<Vec<FormatWarning>, FormatError> {
+    let mut warnings = Vec::new();
+    let mut used = Vec::new();
+    let mut position = 0;
+
+    while let Some(offset) = format[position..].find(['{', '}']) {
+        let start = position + offset;
+        if format[start..].starts_with('}') {
+            return Err(FormatError::UnmatchedBrace(start));
+        }
+
+        // The placeholder ends at the next brace, which has to close it
+        let end = match format[start + 1..].find(['{', '}']) {
+            Some(length) if format[start + 1 + length..].starts_with('}') => start + 1 + length,
+            _ => return Err(FormatError::UnclosedPlaceholder(start)),
+        };
+        let placeholder = &format[start..=end];
+        let (name, padding) = match format[start + 1..end].split_once(':') {
+            Some((name, padding)) => (name, Some(padding)),
+            None => (&format[start + 1..end], None),
+        };
+
+        if name.is_empty() {
+            return Err(FormatError::EmptyPlaceholder(start));
+        }
+        if !FORMAT_PLACEHOLDERS.contains(&name) {
+            let warning = FormatWarning::UnknownPlaceholder(placeholder.to_string());
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        } else if let Some(padding) = padding {
+            if !PADDED_PLACEHOLDERS.contains(&name) {
+                return Err(FormatError::PaddingNotSupported(placeholder.to_string()));
+            }
+            if padding.parse::<usize>().is_err() {
+                return Err(FormatError::InvalidPadding(placeholder.to_string()));
+            }
+        }
+
+        used.push(name);
+        position = end + 1;
+    }
+
+    for &name in EXPECTED_PLACEHOLDERS {
+        if !used.contains(&name) {
+            warnings.push(FormatWarning::MissingPlaceholder(name));
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Checks that a format can be used for naming files
+///
+/// Unlike `check_format`, unknown placeholders are errors, since they would
+/// end up verbatim in the file names.
+pub fn validate_format(format: &str) -> Result<(), FormatError> {
+    for warning in check_format(format)? {
+        if let FormatWarning::UnknownPlaceholder(placeholder) = warning {
+            return Err(FormatError::UnknownPlaceholder(placeholder));
+        }
+    }
+    Ok(())
+}
+
+/// Metadata for the placeholders beyond episode numbers and titles
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FilenameMetadata<'a> {
+    /// Name of the season; `{season_name}` falls back to "Season N", or
+    /// "Specials" for season 0
+    pub season_name: Option<&'a str>,
+    /// Premiere year of the show; `{show_year}` is empty if unknown
+    pub show_year: Option<u16>,
+    /// Provider ID of the (first) episode; `{provider_id}` is empty if unknown
+    pub provider_id: Option<u64>,
+    /// Resolution and codec of the original release for `{quality}`
+    pub quality: Option<&'a str>,
+    /// Source of the original release (e.g. "WEB-DL") for `{source_tag}`
+    pub source_tag: Option<&'a str>,
+    /// Release group of the original release for `{group}`
+    pub group: Option<&'a str>,
+    /// Episode title(s) in the title language for `{title_local}`, which
+    /// falls back to `{title}` if unknown
+    pub local_title: Option<&'a str>,
+}
+
+/// How words in generated file names are separated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilenameStyle {
+    /// Keep the spaces of the format and the metadata (default)
+    #[default]
+    Spaces,
+    /// Separate words with dots, e.g. `Show.S01E01.Title.mkv`
+    Dots,
+    /// Separate words with underscores, e.g. `Show_S01E01_Title.mkv`
+    Underscores,
+}
+
+/// Options for naming the files of planned operations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NamingOptions {
+    /// Premiere year of the show, for the `{show_year}` placeholder
+    pub show_year: Option<u16>,
+    /// Transliterate show names, episode titles, and season names to ASCII
+    ///
+    /// For file systems and network shares that can't handle other
+    /// characters. Literal text in the format is kept as it is.
+    pub ascii: bool,
+    /// Word separator of the file names
+    pub style: FilenameStyle,
+    /// Lowercase the whole file name
+    pub lowercase: bool,
 }
 
 /// Sanitizes a string for use in filenames by replacing problematic characters
@@ -63,54 +375,359 @@ pub fn sanitize_filename(name: &str) -> String {
 /// - `{title}` - Episode title (sanitized)
 /// - `{ext}` - File extension (without dot)
 ///
+/// Use `format_filename_with` for the metadata placeholders `{season_name}`,
+/// `{show_year}`, `{provider_id}`, and the release tags, which are left
+/// empty or use a fallback here.
+///
+/// Multi-episode files pass all of their episodes, first to last. The
+/// episode number then becomes a range (`01-E02`) and the titles are joined
+/// with `&`, e.g. `S01E01-E02 - Title1 & Title2`.
+///
 /// # Examples
 ///
 /// ```
+/// use dialog_detective::format_filename;
+///
 /// let result = format_filename(
 ///     "{show} - S{season:02}E{episode:02} - {title}.{ext}",
 ///     "Breaking Bad",
 ///     1,
-///     2,
-///     "Cat's in the Bag...",
+///     &[(2, "Cat's in the Bag...")],
 ///     "mp4"
 /// );
-/// assert_eq!(result, "Breaking Bad - S01E02 - Cat's in the Bag....mp4");
+/// assert_eq!(result, "Breaking Bad - S01E02 - Cat's in the Bag.mp4");
 /// ```
 pub fn format_filename(
     format: &str,
     show_name: &str,
     season: usize,
-    episode: usize,
-    title: &str,
+    episodes: &[(usize, &str)],
+    extension: &str,
+) -> String {
+    format_filename_with(
+        format,
+        show_name,
+        season,
+        episodes,
+        extension,
+        &FilenameMetadata::default(),
+    )
+}
+
+/// Formats a filename like `format_filename`, with additional metadata
+///
+/// Supports all placeholders of `format_filename`, plus:
+/// - `{season_name}` - Season name, e.g. "Specials" (sanitized)
+/// - `{show_year}` - Premiere year of the show
+/// - `{provider_id}` - Provider ID of the first episode
+/// - `{quality}`, `{source_tag}`, `{group}` - Release tags of the original
+///   file, e.g. "1080p x265", "WEB-DL", and the release group (sanitized)
+/// - `{title_local}` - Episode title in the title language, or `{title}`
+///   if there is none (sanitized)
+///
+/// Names longer than 255 bytes are shortened by cutting the longest of the
+/// title, show name, and season name, keeping the episode numbers and the
+/// extension. On Windows, reserved names like `CON` get an underscore.
+pub fn format_filename_with(
+    format: &str,
+    show_name: &str,
+    season: usize,
+    episodes: &[(usize, &str)],
+    extension: &str,
+    metadata: &FilenameMetadata,
+) -> String {
+    format_filename_within(
+        format,
+        show_name,
+        season,
+        episodes,
+        extension,
+        metadata,
+        MAX_FILENAME_BYTES,
+    )
+}
+
+/// Formats a filename that is at most `max_bytes` long
+///
+/// Too long names are shortened by cutting the longest of the title, show
+/// name, and season name, so the episode numbers and the extension stay
+/// intact. Only if that's not enough, the end of the name before the
+/// extension is cut. On Windows, reserved names like `CON` are escaped.
+fn format_filename_within(
+    format: &str,
+    show_name: &str,
+    season: usize,
+    episodes: &[(usize, &str)],
     extension: &str,
+    metadata: &FilenameMetadata,
+    max_bytes: usize,
 ) -> String {
-    let sanitized_title = sanitize_filename(title);
-    let sanitized_show = sanitize_filename(show_name);
+    let title = episodes
+        .iter()
+        .map(|(_, title)| *title)
+        .collect::<Vec<_>>()
+        .join(" & ");
+    let first = episodes.first().map_or(0, |(number, _)| *number);
+    let last = episodes.last().map_or(0, |(number, _)| *number);
+    let show_year = metadata
+        .show_year
+        .map(|y| y.to_string())
+        .unwrap_or_default();
+    let provider_id = metadata
+        .provider_id
+        .map(|id| id.to_string())
+        .unwrap_or_default();
+    let release_tag = |tag: Option<&str>| tag.map(sanitize_filename).unwrap_or_default();
+    let (quality, source_tag, group) = (
+        release_tag(metadata.quality),
+        release_tag(metadata.source_tag),
+        release_tag(metadata.group),
+    );
+
+    let render = |[title, show, season_name, local_title]: &[String; 4]| {
+        let mut result = format.to_string();
 
-    let mut result = format.to_string();
+        // Replace {show}
+        result = result.replace("{show}", show);
 
-    // Replace {show}
-    result = result.replace("{show}", &sanitized_show);
+        // Replace {season} and {season:NN}
+        result = replace_with_padding(&result, "season", season);
 
-    // Replace {season} and {season:NN}
-    result = replace_with_padding(&result, "season", season);
+        // Replace {episode} and {episode:NN}, as a range for multi-episode files
+        result = replace_placeholder(&result, "episode", |width| {
+            if last > first {
+                format!("{:0width$}-E{:0width$}", first, last, width = width)
+            } else {
+                format!("{:0width$}", first, width = width)
+            }
+        });
+
+        // Replace {title} and {title_local}
+        result = result.replace("{title}", title);
+        result = result.replace("{title_local}", local_title);
+
+        // Replace {ext}
+        result = result.replace("{ext}", extension);
+
+        // Replace the metadata placeholders
+        result = result.replace("{season_name}", season_name);
+        result = result.replace("{show_year}", &show_year);
+        result = result.replace("{provider_id}", &provider_id);
+
+        // Replace the release tags of the original file
+        result = result.replace("{quality}", &quality);
+        result = result.replace("{source_tag}", &source_tag);
+        result.replace("{group}", &group)
+    };
+
+    let mut values = [
+        sanitize_filename(&title),
+        sanitize_filename(show_name),
+        match metadata.season_name {
+            Some(name) => sanitize_filename(name),
+            None if season == 0 => "Specials".to_string(),
+            None => format!("Season {}", season),
+        },
+        sanitize_filename(metadata.local_title.unwrap_or(&title)),
+    ];
+    let mut result = render(&values);
 
-    // Replace {episode} and {episode:NN}
-    result = replace_with_padding(&result, "episode", episode);
+    // Shorten the longest value used by the format until the name fits
+    let names = ["{title}", "{show}", "{season_name}", "{title_local}"];
+    while result.len() > max_bytes {
+        let mut used: Vec<usize> = (0..names.len())
+            .filter(|&i| format.contains(names[i]) && !values[i].is_empty())
+            .collect();
+        used.sort_by_key(|&i| std::cmp::Reverse(values[i].len()));
+        let Some(&longest) = used.first() else {
+            break;
+        };
+
+        // Cut each occurrence by its share, but not below the next longest
+        let length = values[longest].len();
+        let cut = (result.len() - max_bytes).div_ceil(format.matches(names[longest]).count());
+        let next = used.get(1).map_or(0, |&i| values[i].len());
+        let target = match length.saturating_sub(cut).max(next) {
+            target if target < length => target,
+            _ => length.saturating_sub(cut),
+        };
+        values[longest] = truncate_bytes(&values[longest], target)
+            .trim_end_matches(|c: char| c.is_whitespace() || c == '.' || c == '-')
+            .to_string();
+        result = render(&values);
+    }
 
-    // Replace {title}
-    result = result.replace("{title}", &sanitized_title);
+    // Cut the rest of the name if the values weren't enough
+    if result.len() > max_bytes {
+        let suffix = format!(".{}", extension);
+        let stem = result.strip_suffix(&suffix).unwrap_or(&result);
+        let suffix = &result[stem.len()..];
+        let stem = truncate_bytes(stem, max_bytes.saturating_sub(suffix.len()));
+        result = format!("{}{}", stem.trim_end(), suffix);
+    }
 
-    // Replace {ext}
-    result = result.replace("{ext}", extension);
+    if cfg!(windows) {
+        result = escape_reserved_name(&result);
+    }
 
     result
 }
 
+/// Applies a naming style to a formatted file name
+///
+/// With dots or underscores, words are joined by the separator and dashes
+/// standing alone are dropped, so `Show - S01E01 - Title.mkv` becomes
+/// `Show.S01E01.Title.mkv`. Words ending in the separator don't double it
+/// (`Mr. Robot` becomes `Mr.Robot`), and the extension is kept apart.
+pub fn apply_filename_style(name: &str, style: FilenameStyle, lowercase: bool) -> String {
+    let separator = match style {
+        FilenameStyle::Spaces => None,
+        FilenameStyle::Dots => Some("."),
+        FilenameStyle::Underscores => Some("_"),
+    };
+
+    let name = match separator {
+        Some(separator) => {
+            let (stem, extension) = match name.rsplit_once('.') {
+                Some((stem, extension)) if !extension.contains(char::is_whitespace) => {
+                    (stem, Some(extension))
+                }
+                _ => (name, None),
+            };
+            let words: Vec<&str> = stem
+                .split_whitespace()
+                .filter(|word| !word.chars().all(|c| c == '-'))
+                .map(|word| word.trim_end_matches(separator))
+                .filter(|word| !word.is_empty())
+                .collect();
+            let stem = words.join(separator);
+            match extension {
+                Some(extension) => format!("{}.{}", stem, extension),
+                None => stem,
+            }
+        }
+        None => name.to_string(),
+    };
+
+    if lowercase { name.to_lowercase() } else { name }
+}
+
+/// Transliterates text to ASCII, e.g. "Café Ōkami" to "Cafe Okami"
+///
+/// Characters without a transliteration become underscores.
+fn transliterate(text: &str) -> String {
+    deunicode::deunicode_with_tofu(text, "_")
+}
+
+/// Cuts a string to at most `max_bytes`, without splitting a character
+fn truncate_bytes(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Escapes file names that Windows reserves for devices
+///
+/// Appends an underscore to reserved names like `CON` or `nul.mkv`, which
+/// become `CON_` and `nul_.mkv`, and drops trailing spaces and dots, which
+/// Windows would remove silently.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn escape_reserved_name(name: &str) -> String {
+    let name = name.trim_end_matches([' ', '.']);
+    let (stem, rest) = name.split_at(name.find('.').unwrap_or(name.len()));
+    let upper = stem.trim_end().to_ascii_uppercase();
+    let reserved = matches!(upper.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((upper.starts_with("COM") || upper.starts_with("LPT"))
+            && upper.len() == 4
+            && upper.as_bytes()[3].is_ascii_digit()
+            && upper.as_bytes()[3] != b'0');
+
+    if reserved {
+        format!("{}_{}", stem, rest)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns how long the names of files in a directory may be, in bytes
+///
+/// On Windows, names are also shortened for the whole path to stay within
+/// `MAX_WINDOWS_PATH`, which Explorer and many players can't open beyond.
+fn filename_budget(dir: &Path) -> usize {
+    if cfg!(windows) {
+        let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        windows_filename_budget(&dir.to_string_lossy())
+    } else {
+        MAX_FILENAME_BYTES
+    }
+}
+
+/// Returns the name length keeping a path in a Windows directory short
+///
+/// Paths are measured in UTF-16 units, names in bytes, which are never
+/// fewer, so the budget errs on the short side.
+fn windows_filename_budget(dir: &str) -> usize {
+    let dir_length = dir.trim_end_matches(['\\', '/']).encode_utf16().count();
+    MAX_WINDOWS_PATH
+        .saturating_sub(dir_length + 1)
+        .clamp(MIN_FILENAME_BYTES, MAX_FILENAME_BYTES)
+}
+
+/// Returns a path for file system calls that isn't limited to `MAX_PATH`
+///
+/// On Windows, the path is made absolute and turned into an extended-length
+/// path, so files in deep directories and on UNC shares (`\\NAS\share`)
+/// can be renamed and copied. Elsewhere the path is used as it is.
+fn fs_path(path: &Path) -> Cow<'_, Path> {
+    if !cfg!(windows) {
+        return Cow::Borrowed(path);
+    }
+    let Ok(absolute) = std::path::absolute(path) else {
+        return Cow::Borrowed(path);
+    };
+    match absolute.to_str().and_then(extended_path) {
+        Some(extended) => Cow::Owned(PathBuf::from(extended)),
+        None => Cow::Owned(absolute),
+    }
+}
+
+/// Turns an absolute Windows path into an extended-length path
+///
+/// `C:\dir` becomes `\\?\C:\dir` and `\\server\share` becomes
+/// `\\?\UNC\server\share`. Returns None for relative paths and paths that
+/// already have a `\\?\` or `\\.\` prefix.
+fn extended_path(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return None;
+    }
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{}", unc));
+    }
+
+    let bytes = path.as_bytes();
+    let drive =
+        bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\';
+    drive.then(|| format!(r"\\?\{}", path))
+}
+
 /// Helper function to replace placeholders with optional zero-padding
 ///
 /// Handles both `{name}` and `{name:NN}` where NN is the padding width
 fn replace_with_padding(text: &str, name: &str, value: usize) -> String {
+    replace_placeholder(text, name, |width| {
+        format!("{:0width$}", value, width = width)
+    })
+}
+
+/// Replaces `{name}` and `{name:NN}` with the output of `format`
+///
+/// `format` is called with the padding width, which is 0 for `{name}`.
+fn replace_placeholder(text: &str, name: &str, format: impl Fn(usize) -> String) -> String {
     let mut result = text.to_string();
 
     // First, handle padded versions like {season:02}
@@ -121,7 +738,7 @@ fn replace_with_padding(text: &str, name: &str, value: usize) -> String {
             let padding_str = &full_pattern[pattern_start.len()..full_pattern.len() - 1];
 
             if let Ok(width) = padding_str.parse::<usize>() {
-                let formatted = format!("{:0width$}", value, width = width);
+                let formatted = format(width);
                 result = result.replace(full_pattern, &formatted);
             }
         } else {
@@ -131,7 +748,7 @@ fn replace_with_padding(text: &str, name: &str, value: usize) -> String {
 
     // Then handle unpadded version like {season}
     let simple_pattern = format!("{{{name}}}");
-    result = result.replace(&simple_pattern, &value.to_string());
+    result = result.replace(&simple_pattern, &format(0));
 
     result
 }
@@ -157,18 +774,192 @@ pub fn detect_duplicates(matches: &[MatchResult]) -> HashMap<(usize, usize), Vec
     groups
 }
 
+/// Keeps one file per episode according to the policy
+///
+/// Parts of a split episode are never duplicates. Files whose resolution or
+/// size can't be determined lose against those whose can; remaining ties go
+/// to the earlier match. The order of the matches is preserved within both
+/// lists.
+pub fn apply_duplicate_policy(
+    matches: Vec<MatchResult>,
+    policy: DuplicatePolicy,
+) -> DuplicateResolution {
+    if policy == DuplicatePolicy::Suffix {
+        return DuplicateResolution {
+            kept: matches,
+            discarded: Vec::new(),
+        };
+    }
+
+    let mut keepers: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut best: HashMap<(usize, usize), (u64, f64)> = HashMap::new();
+    for (index, match_result) in matches.iter().enumerate() {
+        if match_result.part.is_some() {
+            continue;
+        }
+
+        let key = (
+            match_result.episode.season_number,
+            match_result.episode.episode_number,
+        );
+        let rank = match policy {
+            DuplicatePolicy::HighestResolution => {
+                (video_resolution(&match_result.video.path).unwrap_or(0), 0.0)
+            }
+            DuplicatePolicy::Largest => (
+                fs::metadata(&match_result.video.path).map_or(0, |m| m.len()),
+                0.0,
+            ),
+            _ => (0, match_result.confidence.unwrap_or(1.0)),
+        };
+        if best.get(&key).is_none_or(|current| rank > *current) {
+            best.insert(key, rank);
+            keepers.insert(key, index);
+        }
+    }
+
+    let (kept, discarded): (Vec<_>, Vec<_>) =
+        matches.into_iter().enumerate().partition(|(index, m)| {
+            m.part.is_some()
+                || keepers[&(m.episode.season_number, m.episode.episode_number)] == *index
+        });
+
+    DuplicateResolution {
+        kept: kept.into_iter().map(|(_, m)| m).collect(),
+        discarded: discarded.into_iter().map(|(_, m)| m).collect(),
+    }
+}
+
+/// Measures the resolution of a video in pixels using ffprobe
+///
+/// Returns None if ffprobe is unavailable or the file has no video stream.
+#[cfg(feature = "ffmpeg")]
+fn video_resolution(path: &Path) -> Option<u64> {
+    let output = crate::child_process::output(
+        std::process::Command::new(ffmpeg_sidecar::ffprobe::ffprobe_path())
+            .args(["-v", "error", "-select_streams", "v:0"])
+            .args(["-show_entries", "stream=width,height", "-of", "csv=p=0:s=x"])
+            .arg(path),
+    )
+    .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    parse_resolution(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Measures the resolution of a video, unavailable without the `ffmpeg` feature
+#[cfg(not(feature = "ffmpeg"))]
+fn video_resolution(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Parses ffprobe's `WIDTHxHEIGHT` output into a pixel count
+#[cfg_attr(not(feature = "ffmpeg"), allow(dead_code))]
+fn parse_resolution(output: &str) -> Option<u64> {
+    let (width, height) = output.lines().next()?.trim().split_once('x')?;
+    Some(width.parse::<u64>().ok()? * height.parse::<u64>().ok()?)
+}
+
+/// Plans moving discarded duplicates into a `duplicates/` folder
+///
+/// The folder is created next to the source files for rename mode, or in
+/// the output directory for copy mode. Files keep their original names.
+pub fn plan_quarantine(
+    discarded: &[MatchResult],
+    output_dir: Option<&Path>,
+) -> Result<Vec<PlannedOperation>, FileOperationError> {
+    discarded
+        .iter()
+        .map(|match_result| {
+            let source = &match_result.video.path;
+            let file_name = source
+                .file_name()
+                .ok_or_else(|| FileOperationError::MissingFileName(source.display().to_string()))?;
+            let base_dir = output_dir
+                .or_else(|| source.parent())
+                .unwrap_or(Path::new(""));
+
+            Ok(PlannedOperation {
+                source: source.clone(),
+                destination: base_dir.join(DUPLICATES_DIR).join(file_name),
+                episode: match_result.episode.clone(),
+                extra_episodes: match_result.extra_episodes.clone(),
+                duplicate_suffix: None,
+                part: None,
+                quarantined: true,
+            })
+        })
+        .collect()
+}
+
+/// Joins the local titles of a match's episodes for `{title_local}`
+///
+/// Episodes without a local title contribute their title. Returns None if
+/// none of the episodes has a local title.
+fn local_title(match_result: &MatchResult) -> Option<String> {
+    let episodes: Vec<&Episode> = std::iter::once(&match_result.episode)
+        .chain(&match_result.extra_episodes)
+        .collect();
+    if episodes.iter().all(|episode| episode.local_title.is_none()) {
+        return None;
+    }
+
+    let titles: Vec<&str> = episodes
+        .iter()
+        .map(|episode| episode.local_title.as_deref().unwrap_or(&episode.name))
+        .collect();
+    Some(titles.join(" & "))
+}
+
 /// Plans file operations with duplicate handling via suffix strategy
 ///
 /// For duplicate episodes, adds numeric suffix starting from 2:
 /// - First occurrence: `name.ext`
 /// - Second occurrence: `name (2).ext`
 /// - Third occurrence: `name (3).ext`
+///
+/// Parts of a split episode are not duplicates and are named `name - Part N.ext`.
 pub fn plan_operations(
     matches: &[MatchResult],
     show_name: &str,
     format: &str,
     output_dir: Option<&Path>,
 ) -> Result<Vec<PlannedOperation>, FileOperationError> {
+    plan_operations_with(
+        matches,
+        show_name,
+        format,
+        output_dir,
+        &NamingOptions::default(),
+    )
+}
+
+/// Plans file operations like `plan_operations`, with naming options
+///
+/// Season names and provider IDs for the format are taken from the matched
+/// episodes, release tags from the original file names. The planned
+/// operations keep the original episode metadata, even if the file names
+/// are transliterated.
+pub fn plan_operations_with(
+    matches: &[MatchResult],
+    show_name: &str,
+    format: &str,
+    output_dir: Option<&Path>,
+    options: &NamingOptions,
+) -> Result<Vec<PlannedOperation>, FileOperationError> {
+    validate_format(format)?;
+
+    let text = |text: &str| -> String {
+        if options.ascii {
+            transliterate(text)
+        } else {
+            text.to_string()
+        }
+    };
+    let show_name = text(show_name);
+
     let groups = detect_duplicates(matches);
     let mut operations = Vec::new();
 
@@ -188,19 +979,33 @@ pub fn plan_operations(
                 FileOperationError::MissingExtension(match_result.video.path.display().to_string())
             })?;
 
-        // Generate base filename
-        let base_name = format_filename(
-            format,
-            show_name,
-            match_result.episode.season_number,
-            match_result.episode.episode_number,
-            &match_result.episode.name,
-            extension,
-        );
+        // Generate base filename, naming multi-episode files after all episodes
+        let titles: Vec<(usize, String)> = std::iter::once(&match_result.episode)
+            .chain(&match_result.extra_episodes)
+            .map(|episode| (episode.episode_number, text(&episode.name)))
+            .collect();
+        let episodes: Vec<(usize, &str)> = titles
+            .iter()
+            .map(|(number, title)| (*number, title.as_str()))
+            .collect();
+        let local_title = local_title(match_result).map(|title| text(&title));
+        let season_name = match_result.episode.season_name.as_deref().map(text);
+        let release = parse_release_tags(&match_result.video.path);
+        let metadata = FilenameMetadata {
+            season_name: season_name.as_deref(),
+            show_year: options.show_year,
+            provider_id: match_result.episode.provider_id,
+            quality: release.quality.as_deref(),
+            source_tag: release.source.as_deref(),
+            group: release.group.as_deref(),
+            local_title: local_title.as_deref(),
+        };
 
-        // Determine if this is a duplicate and which occurrence
+        // Determine if this is a part, or a duplicate and which occurrence
         let group = &groups[&key];
-        let (final_name, suffix) = if group.len() > 1 {
+        let (marker, suffix) = if let Some(part) = match_result.part {
+            (format!(" - Part {}", part.number), None)
+        } else if group.len() > 1 {
             // Find which occurrence this is
             let occurrence = group
                 .iter()
@@ -209,74 +1014,169 @@ pub fn plan_operations(
 
             if occurrence == 0 {
                 // First occurrence, no suffix
-                (base_name.clone(), None)
+                (String::new(), None)
             } else {
                 // Add suffix (2), (3), etc.
                 let suffix_num = occurrence + 1;
-                let name_without_ext = base_name
-                    .strip_suffix(&format!(".{}", extension))
-                    .unwrap_or(&base_name);
-                let suffixed = format!("{} ({}).{}", name_without_ext, suffix_num, extension);
-                (suffixed, Some(suffix_num))
+                (format!(" ({})", suffix_num), Some(suffix_num))
             }
         } else {
             // Not a duplicate
-            (base_name, None)
+            (String::new(), None)
         };
 
-        // Determine destination path
-        let destination = if let Some(output) = output_dir {
-            output.join(&final_name)
+        // Generate the filename, leaving room for the marker
+        let dir = output_dir.or_else(|| match_result.video.path.parent());
+        let max_bytes = dir.map_or(MAX_FILENAME_BYTES, filename_budget);
+        let base_name = format_filename_within(
+            format,
+            &show_name,
+            match_result.episode.season_number,
+            &episodes,
+            extension,
+            &metadata,
+            max_bytes - marker.len(),
+        );
+        let final_name = if marker.is_empty() {
+            base_name
         } else {
-            // For rename mode, destination is in same directory as source
-            match_result
-                .video
-                .path
-                .parent()
-                .map(|p| p.join(&final_name))
-                .unwrap_or_else(|| PathBuf::from(&final_name))
+            let name_without_ext = base_name
+                .strip_suffix(&format!(".{}", extension))
+                .unwrap_or(&base_name);
+            format!("{}{}.{}", name_without_ext, marker, extension)
         };
+        let final_name = apply_filename_style(&final_name, options.style, options.lowercase);
+
+        // Copies go to the output directory, renamed files stay next to the source
+        let destination = dir
+            .map(|dir| dir.join(&final_name))
+            .unwrap_or_else(|| PathBuf::from(&final_name));
 
         operations.push(PlannedOperation {
             source: match_result.video.path.clone(),
             destination,
             episode: match_result.episode.clone(),
+            extra_episodes: match_result.extra_episodes.clone(),
             duplicate_suffix: suffix,
+            part: match_result.part,
+            quarantined: false,
         });
     }
 
     Ok(operations)
 }
 
-/// Executes rename operations in place
-pub fn execute_rename(
-    operations: &[PlannedOperation],
-) -> Result<Vec<io::Error>, FileOperationError> {
+/// Returns the access and modification times of a file, as far as known
+fn source_times(metadata: &fs::Metadata) -> FileTimes {
+    let mut times = FileTimes::new();
+    if let Ok(accessed) = metadata.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    if let Ok(modified) = metadata.modified() {
+        times = times.set_modified(modified);
+    }
+    times
+}
+
+/// Sets the modification time of each renamed or copied file to its air date
+///
+/// Meant for archival libraries sorting by date. Files of episodes without
+/// a known air date and quarantined duplicates are left alone. Returns the
+/// errors of files whose time couldn't be set.
+pub fn set_air_date_mtimes(operations: &[PlannedOperation]) -> Vec<io::Error> {
     let mut errors = Vec::new();
 
-    for op in operations {
-        if let Err(e) = fs::rename(&op.source, &op.destination) {
+    for op in operations.iter().filter(|op| !op.quarantined) {
+        let Some(aired) = op.episode.airdate.as_deref().and_then(air_date_time) else {
+            continue;
+        };
+        let result = File::options()
+            .write(true)
+            .open(fs_path(&op.destination))
+            .and_then(|file| file.set_modified(aired));
+        if let Err(e) = result {
             errors.push(e);
         }
     }
 
-    Ok(errors)
+    errors
 }
 
-/// Executes copy operations to output directory
+/// Converts an air date (`YYYY-MM-DD`) to noon UTC of that day
 ///
-/// Creates the output directory if it doesn't exist.
-pub fn execute_copy(
+/// Noon keeps the date the same when shown in most time zones.
+pub(crate) fn air_date_time(date: &str) -> Option<SystemTime> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar, counting
+    // years from March so the leap day comes last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 24 * 60 * 60 + 12 * 60 * 60;
+    let offset = Duration::from_secs(seconds.unsigned_abs());
+    if seconds >= 0 {
+        SystemTime::UNIX_EPOCH.checked_add(offset)
+    } else {
+        SystemTime::UNIX_EPOCH.checked_sub(offset)
+    }
+}
+
+/// Options for renaming files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenameOptions {
+    /// Move existing files at a destination to the system trash
+    ///
+    /// If disabled, they are overwritten. Enabled by default.
+    pub trash_replaced: bool,
+
+    /// How renames failing transiently, e.g. on network shares, are retried
+    pub retry: RetryPolicy,
+}
+
+impl Default for RenameOptions {
+    fn default() -> Self {
+        Self {
+            trash_replaced: true,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// Executes rename operations in place
+///
+/// Existing files at a destination are moved to the system trash.
+pub fn execute_rename(
     operations: &[PlannedOperation],
-    output_dir: &Path,
 ) -> Result<Vec<io::Error>, FileOperationError> {
-    // Create output directory if it doesn't exist
-    fs::create_dir_all(output_dir)?;
+    execute_rename_with(operations, RenameOptions::default())
+}
 
+/// Executes rename operations in place with the given options
+pub fn execute_rename_with(
+    operations: &[PlannedOperation],
+    options: RenameOptions,
+) -> Result<Vec<io::Error>, FileOperationError> {
     let mut errors = Vec::new();
 
     for op in operations {
-        if let Err(e) = fs::copy(&op.source, &op.destination) {
+        let result = create_quarantine_dir(op)
+            .and_then(|_| dispose_replaced(op, operations, options.trash_replaced))
+            .and_then(|_| {
+                let (source, destination) = (fs_path(&op.source), fs_path(&op.destination));
+                options.retry.run(|| fs::rename(&source, &destination))
+            });
+        if let Err(e) = result {
             errors.push(e);
         }
     }
@@ -284,9 +1184,412 @@ pub fn execute_copy(
     Ok(errors)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Moves an existing file at the destination of an operation to the trash
+///
+/// Does nothing if trashing is disabled, the destination doesn't exist, or
+/// it is itself a file of the batch, which is moved by its own operation.
+fn dispose_replaced(
+    op: &PlannedOperation,
+    operations: &[PlannedOperation],
+    trash_replaced: bool,
+) -> io::Result<()> {
+    if !trash_replaced || !replaces_file(op, operations) {
+        return Ok(());
+    }
+
+    trash::delete(&op.destination).map_err(io::Error::other)
+}
+
+/// Checks whether an operation replaces a file that isn't part of the batch
+fn replaces_file(op: &PlannedOperation, operations: &[PlannedOperation]) -> bool {
+    op.destination != op.source
+        && fs_path(&op.destination).exists()
+        && !operations
+            .iter()
+            .any(|other| other.source == op.destination)
+}
+
+/// Executes rename operations as one transaction
+///
+/// Either all files are renamed or none is. Every file is first moved to a
+/// temporary name next to it, then to its destination, so files may also
+/// swap names. If any rename fails, all completed renames are undone in
+/// reverse order. Fails up front if a destination is taken by a file that
+/// isn't renamed itself, as overwriting it couldn't be undone.
+pub fn execute_rename_atomic(operations: &[PlannedOperation]) -> Result<(), FileOperationError> {
+    for op in operations {
+        let taken = fs_path(&op.destination).exists()
+            && !operations
+                .iter()
+                .any(|other| other.source == op.destination);
+        if taken {
+            return Err(FileOperationError::DestinationExists(
+                op.destination.display().to_string(),
+            ));
+        }
+    }
+
+    // Every completed move, undone from last to first on failure
+    let mut journal: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut failure = None;
+
+    for op in operations {
+        let staged = staging_path(&op.source);
+        if let Err(e) = fs::rename(fs_path(&op.source), fs_path(&staged)) {
+            failure = Some((op.source.clone(), e));
+            break;
+        }
+        journal.push((op.source.clone(), staged));
+    }
+
+    if failure.is_none() {
+        let staged: Vec<PathBuf> = journal.iter().map(|(_, to)| to.clone()).collect();
+        for (op, staged) in operations.iter().zip(staged) {
+            if let Err(e) = create_quarantine_dir(op)
+                .and_then(|_| fs::rename(fs_path(&staged), fs_path(&op.destination)))
+            {
+                failure = Some((op.source.clone(), e));
+                break;
+            }
+            journal.push((staged, op.destination.clone()));
+        }
+    }
+
+    let Some((path, source)) = failure else {
+        return Ok(());
+    };
+
+    for (from, to) in journal.into_iter().rev() {
+        if fs::rename(fs_path(&to), fs_path(&from)).is_err() {
+            return Err(FileOperationError::RollbackFailed {
+                path,
+                stuck: to,
+                source,
+            });
+        }
+    }
+
+    Err(FileOperationError::RolledBack { path, source })
+}
+
+/// Returns a unique temporary name next to a file, used while renaming it
+fn staging_path(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.renaming", file_name, ulid::Ulid::new()))
+}
+
+/// Checks that the files to copy fit onto the output directory's filesystem
+///
+/// Sums up the sizes of all source files and compares them with the space
+/// available on the filesystem of `output_dir`, or of its closest existing
+/// parent if it doesn't exist yet. Passes if the available space can't be
+/// determined on this platform.
+pub fn check_free_space(
+    operations: &[PlannedOperation],
+    output_dir: &Path,
+) -> Result<(), FileOperationError> {
+    let mut required = 0;
+    for op in operations {
+        required += fs::metadata(&op.source)?.len();
+    }
+
+    let Some(existing) = output_dir.ancestors().find(|dir| dir.exists()) else {
+        return Ok(());
+    };
+    match available_space(existing)? {
+        Some(available) if available < required => Err(FileOperationError::InsufficientSpace {
+            path: output_dir.to_path_buf(),
+            required,
+            available,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the space available to unprivileged users on the filesystem of `path`
+#[cfg(unix)]
+#[allow(clippy::useless_conversion)] // statvfs field types differ between platforms
+fn available_space(path: &Path) -> io::Result<Option<u64>> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+
+    // SAFETY: c_path is a valid NUL-terminated string, and stat is only
+    // read after statvfs reported success
+    let stat = unsafe {
+        if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        stat.assume_init()
+    };
+
+    Ok(Some(
+        u64::from(stat.f_bavail).saturating_mul(u64::from(stat.f_frsize)),
+    ))
+}
+
+/// Returns the space available on the filesystem of `path`, unknown on this platform
+#[cfg(not(unix))]
+fn available_space(_path: &Path) -> io::Result<Option<u64>> {
+    Ok(None)
+}
+
+/// Size of the chunks files are copied in, and progress is reported for
+const COPY_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Options for copying files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CopyOptions {
+    /// Compare the BLAKE3 hash of each copy with the original
+    ///
+    /// Copies are read back after being synced to disk. On Linux they are
+    /// dropped from the page cache first, so the data comes from the disk
+    /// or share; elsewhere the read may be served from memory and only
+    /// checks the write path. Copies that differ are removed and reported
+    /// as errors.
+    pub verify: bool,
+
+    /// Move existing files at a destination to the system trash
+    ///
+    /// If disabled, they are overwritten. Enabled by default.
+    pub trash_replaced: bool,
+
+    /// How copies failing transiently, e.g. on network shares, are retried
+    ///
+    /// A failed copy is started over from the beginning.
+    pub retry: RetryPolicy,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            verify: false,
+            trash_replaced: true,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// Progress of copying a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CopyProgress {
+    /// Bytes of the file copied so far, reported once per chunk
+    Copying {
+        source: PathBuf,
+        copied: u64,
+        total: u64,
+    },
+
+    /// The copy is being compared with the original
+    Verifying { source: PathBuf },
+
+    /// The file was copied (and verified, if requested)
+    Copied { source: PathBuf },
+}
+
+/// Executes copy operations to output directory
+///
+/// Creates the output directory if it doesn't exist. Fails before copying
+/// anything if the files don't fit onto its filesystem. Existing files at a
+/// destination are moved to the system trash.
+pub fn execute_copy(
+    operations: &[PlannedOperation],
+    output_dir: &Path,
+) -> Result<Vec<io::Error>, FileOperationError> {
+    execute_copy_with(operations, output_dir, CopyOptions::default(), |_| {})
+}
+
+/// Executes copy operations to output directory, reporting their progress
+///
+/// Like `execute_copy`, optionally verifying each copy against its original.
+pub fn execute_copy_with<F>(
+    operations: &[PlannedOperation],
+    output_dir: &Path,
+    options: CopyOptions,
+    mut progress: F,
+) -> Result<Vec<io::Error>, FileOperationError>
+where
+    F: FnMut(CopyProgress),
+{
+    check_free_space(operations, output_dir)?;
+
+    // Create output directory if it doesn't exist
+    fs::create_dir_all(fs_path(output_dir))?;
+
+    let mut errors = Vec::new();
+
+    for op in operations {
+        let result = create_quarantine_dir(op)
+            .and_then(|_| dispose_replaced(op, operations, options.trash_replaced))
+            .and_then(|_| copy_file(op, options, &mut progress));
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+
+    Ok(errors)
+}
+
+/// Executes copy operations as hard links in the output directory
+///
+/// For libraries next to a download directory: the original stays in place
+/// (e.g. to keep seeding) without taking up space twice. Files are copied
+/// instead if the output directory is on another file system. Existing
+/// files at a destination are never replaced but reported as errors.
+pub fn execute_hardlink(
+    operations: &[PlannedOperation],
+    output_dir: &Path,
+) -> Result<Vec<io::Error>, FileOperationError> {
+    fs::create_dir_all(fs_path(output_dir))?;
+
+    let mut errors = Vec::new();
+
+    for op in operations {
+        let result = if fs_path(&op.destination).exists() {
+            Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", op.destination.display()),
+            ))
+        } else {
+            create_quarantine_dir(op).and_then(|_| {
+                match fs::hard_link(fs_path(&op.source), fs_path(&op.destination)) {
+                    Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                        copy_file(op, CopyOptions::default(), &mut |_| {})
+                    }
+                    result => result,
+                }
+            })
+        };
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+
+    Ok(errors)
+}
+
+/// Copies a single file, starting over while it fails transiently
+fn copy_file(
+    op: &PlannedOperation,
+    options: CopyOptions,
+    progress: &mut dyn FnMut(CopyProgress),
+) -> io::Result<()> {
+    options
+        .retry
+        .run(|| copy_file_once(op, options, &mut *progress))
+}
+
+/// Copies a single file in chunks, hashing it on the way if it is verified
+fn copy_file_once(
+    op: &PlannedOperation,
+    options: CopyOptions,
+    progress: &mut dyn FnMut(CopyProgress),
+) -> io::Result<()> {
+    let destination = fs_path(&op.destination);
+    let mut reader = File::open(fs_path(&op.source))?;
+    let metadata = reader.metadata()?;
+    let total = metadata.len();
+    let mut writer = File::create(&destination)?;
+
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; COPY_CHUNK_SIZE];
+    let mut copied = 0;
+    progress(CopyProgress::Copying {
+        source: op.source.clone(),
+        copied,
+        total,
+    });
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read])?;
+        if options.verify {
+            hasher.update(&buffer[..read]);
+        }
+
+        copied += read as u64;
+        progress(CopyProgress::Copying {
+            source: op.source.clone(),
+            copied,
+            total,
+        });
+    }
+    writer.set_times(source_times(&metadata))?;
+    writer.sync_all()?;
+    fs::set_permissions(&destination, metadata.permissions())?;
+
+    if options.verify {
+        progress(CopyProgress::Verifying {
+            source: op.source.clone(),
+        });
+        drop_cached_pages(&writer);
+        if read_back_hash(&destination)? != hasher.finalize() {
+            fs::remove_file(&destination)?;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Copy of {} differs from the original and was removed",
+                    op.source.display()
+                ),
+            ));
+        }
+    }
+
+    progress(CopyProgress::Copied {
+        source: op.source.clone(),
+    });
+    Ok(())
+}
+
+/// Asks the system to drop the cached pages of a synced file
+///
+/// Reading the file afterwards goes to the disk or share instead of
+/// returning what was just written from memory. Best effort: if the
+/// system ignores the advice, the read is served from the cache.
+#[cfg(target_os = "linux")]
+fn drop_cached_pages(file: &File) {
+    use std::os::fd::AsRawFd;
+    // SAFETY: the descriptor belongs to the open file
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn drop_cached_pages(_file: &File) {}
+
+/// Hashes a file by reading it back in chunks
+fn read_back_hash(path: &Path) -> io::Result<blake3::Hash> {
+    let mut reader = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; COPY_CHUNK_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+/// Creates the `duplicates/` folder a quarantined file is moved into
+fn create_quarantine_dir(op: &PlannedOperation) -> io::Result<()> {
+    match op.destination.parent() {
+        Some(dir) if op.quarantined => fs::create_dir_all(fs_path(dir)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
     fn test_sanitize_filename() {
@@ -303,8 +1606,7 @@ mod tests {
             "{show} - S{season:02}E{episode:02} - {title}.{ext}",
             "Breaking Bad",
             1,
-            2,
-            "Cat's in the Bag...",
+            &[(2, "Cat's in the Bag...")],
             "mp4",
         );
         // Trailing dots are trimmed by sanitize_filename
@@ -314,13 +1616,307 @@ mod tests {
             "{show} S{season}E{episode} {title}.{ext}",
             "Game of Thrones",
             3,
-            9,
-            "The Rains of Castamere",
+            &[(9, "The Rains of Castamere")],
             "mkv",
         );
         assert_eq!(result2, "Game of Thrones S3E9 The Rains of Castamere.mkv");
     }
 
+    #[test]
+    fn test_format_filename_multi_episode() {
+        let result = format_filename(
+            "{show} - S{season:02}E{episode:02} - {title}.{ext}",
+            "Doctor Who",
+            4,
+            &[(12, "The Stolen Earth"), (13, "Journey's End")],
+            "mkv",
+        );
+        assert_eq!(
+            result,
+            "Doctor Who - S04E12-E13 - The Stolen Earth & Journey's End.mkv"
+        );
+
+        let result2 = format_filename(
+            "S{season}E{episode} {title}.{ext}",
+            "Doctor Who",
+            4,
+            &[(12, "A"), (13, "B"), (14, "C")],
+            "mkv",
+        );
+        assert_eq!(result2, "S4E12-E14 A & B & C.mkv");
+    }
+
+    #[test]
+    fn test_format_filename_with_metadata() {
+        let metadata = FilenameMetadata {
+            season_name: Some("Book One: Water"),
+            show_year: Some(2005),
+            provider_id: Some(12345),
+            ..Default::default()
+        };
+        let result = format_filename_with(
+            "{show} ({show_year}) - {season_name} - S{season:02}E{episode:02} [{provider_id}].{ext}",
+            "Avatar",
+            1,
+            &[(1, "The Boy in the Iceberg")],
+            "mkv",
+            &metadata,
+        );
+        assert_eq!(
+            result,
+            "Avatar (2005) - Book One- Water - S01E01 [12345].mkv"
+        );
+
+        // Without metadata, season names fall back and the rest is empty
+        let result = format_filename(
+            "{season_name}|{show_year}|{provider_id}",
+            "Show",
+            0,
+            &[(1, "Title")],
+            "mkv",
+        );
+        assert_eq!(result, "Specials||");
+        let result = format_filename("{season_name}", "Show", 3, &[(1, "Title")], "mkv");
+        assert_eq!(result, "Season 3");
+    }
+
+    #[test]
+    fn test_format_filename_title_local() {
+        let format = "S{season:02}E{episode:02} - {title_local} ({title}).{ext}";
+        let metadata = FilenameMetadata {
+            local_title: Some("Der Junge im Eisberg"),
+            ..Default::default()
+        };
+        let result = format_filename_with(
+            format,
+            "Avatar",
+            1,
+            &[(1, "The Boy in the Iceberg")],
+            "mkv",
+            &metadata,
+        );
+        assert_eq!(
+            result,
+            "S01E01 - Der Junge im Eisberg (The Boy in the Iceberg).mkv"
+        );
+
+        // Without a local title, the title is used
+        let result = format_filename(format, "Avatar", 1, &[(1, "Pilot")], "mkv");
+        assert_eq!(result, "S01E01 - Pilot (Pilot).mkv");
+    }
+
+    #[test]
+    fn test_format_filename_truncates_long_names() {
+        let format = "{show} - S{season:02}E{episode:02} - {title}.{ext}";
+
+        // A long CJK title is cut at a character boundary
+        let title = "進撃の巨人".repeat(30);
+        let result = format_filename(format, "Attack on Titan", 1, &[(1, &title)], "mkv");
+        assert!(result.len() <= MAX_FILENAME_BYTES);
+        assert!(result.starts_with("Attack on Titan - S01E01 - 進撃の巨人"));
+        assert!(result.ends_with(".mkv"));
+
+        // A long show name is cut instead of the short title
+        let show = "Show ".repeat(60);
+        let result = format_filename(format, &show, 1, &[(1, "Title")], "mkv");
+        assert!(result.len() <= MAX_FILENAME_BYTES);
+        assert!(result.ends_with(" - S01E01 - Title.mkv"));
+
+        // Literal text is cut last, keeping the extension
+        let literal = "x".repeat(300);
+        let result = format_filename(&format!("{}.{{ext}}", literal), "Show", 1, &[], "mkv");
+        assert_eq!(result.len(), MAX_FILENAME_BYTES);
+        assert!(result.ends_with("x.mkv"));
+
+        // Short names are left alone
+        let result = format_filename(format, "Show", 1, &[(1, "Title")], "mkv");
+        assert_eq!(result, "Show - S01E01 - Title.mkv");
+    }
+
+    #[test]
+    fn test_plan_operations_leaves_room_for_parts() {
+        let part = EpisodePart {
+            number: 2,
+            count: 2,
+        };
+        let mut matches = vec![result("a.mkv", Some(0.9), Some(part))];
+        matches[0].episode.name = "Title ".repeat(60);
+
+        let operations = plan_operations(&matches, "Show", "{title}.{ext}", None).unwrap();
+        let name = operations[0].destination.to_str().unwrap();
+        assert!(name.len() <= MAX_FILENAME_BYTES);
+        assert!(name.ends_with(" - Part 2.mkv"));
+    }
+
+    #[test]
+    fn test_plan_operations_ascii() {
+        let mut matches = vec![result("a.mkv", Some(0.9), None)];
+        matches[0].episode.name = "Café Ōkami".to_string();
+        matches[0].episode.season_name = Some("Zweite Staffel für Überlänge".to_string());
+
+        let options = NamingOptions {
+            ascii: true,
+            ..Default::default()
+        };
+        let operations = plan_operations_with(
+            &matches,
+            "Pokémon",
+            "{show} - {season_name} - {title}.{ext}",
+            None,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            operations[0].destination,
+            PathBuf::from("Pokemon - Zweite Staffel fur Uberlange - Cafe Okami.mkv")
+        );
+        // The original title is kept for everything but the file name
+        assert_eq!(operations[0].episode.name, "Café Ōkami");
+    }
+
+    #[test]
+    fn test_plan_operations_keeps_release_tags() {
+        let mut matches = vec![
+            result("Show.S01E01.1080p.WEB-DL.x265-GROUP.mkv", Some(0.9), None),
+            result("show 1x02.mkv", Some(0.9), None),
+        ];
+        matches[1].episode.episode_number = 2;
+
+        let operations = plan_operations(
+            &matches,
+            "Show",
+            "{show} - S{season:02}E{episode:02} [{quality}][{source_tag}][{group}].{ext}",
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            operations[0].destination,
+            PathBuf::from("Show - S01E01 [1080p x265][WEB-DL][GROUP].mkv")
+        );
+        // Files without release tags leave the placeholders empty
+        assert_eq!(
+            operations[1].destination,
+            PathBuf::from("Show - S01E02 [][][].mkv")
+        );
+    }
+
+    #[test]
+    fn test_apply_filename_style() {
+        let name = "Show - S01E02 - Title & More.mkv";
+        assert_eq!(
+            apply_filename_style(name, FilenameStyle::Dots, true),
+            "show.s01e02.title.&.more.mkv"
+        );
+        assert_eq!(
+            apply_filename_style(name, FilenameStyle::Underscores, false),
+            "Show_S01E02_Title_&_More.mkv"
+        );
+        assert_eq!(
+            apply_filename_style(name, FilenameStyle::Spaces, false),
+            name
+        );
+        assert_eq!(
+            apply_filename_style(
+                "Show - S01E02 - Title - Part 2.mkv",
+                FilenameStyle::Dots,
+                false
+            ),
+            "Show.S01E02.Title.Part.2.mkv"
+        );
+        assert_eq!(
+            apply_filename_style(
+                "Mr. Robot - S01E01 - eps1.0_hellofriend.mov",
+                FilenameStyle::Dots,
+                false
+            ),
+            "Mr.Robot.S01E01.eps1.0_hellofriend.mov"
+        );
+    }
+
+    #[test]
+    fn test_escape_reserved_name() {
+        assert_eq!(escape_reserved_name("CON"), "CON_");
+        assert_eq!(escape_reserved_name("nul.mkv"), "nul_.mkv");
+        assert_eq!(escape_reserved_name("Com1.tar.gz"), "Com1_.tar.gz");
+        assert_eq!(escape_reserved_name("LPT0.mkv"), "LPT0.mkv");
+        assert_eq!(escape_reserved_name("Console.mkv"), "Console.mkv");
+        assert_eq!(escape_reserved_name("Title. "), "Title");
+    }
+
+    #[test]
+    fn test_extended_path() {
+        assert_eq!(
+            extended_path(r"C:\TV\Show\file.mkv").as_deref(),
+            Some(r"\\?\C:\TV\Show\file.mkv")
+        );
+        assert_eq!(
+            extended_path(r"\\NAS\share\TV\file.mkv").as_deref(),
+            Some(r"\\?\UNC\NAS\share\TV\file.mkv")
+        );
+        assert_eq!(extended_path(r"\\?\C:\TV"), None);
+        assert_eq!(extended_path(r"\\.\pipe\name"), None);
+        assert_eq!(extended_path(r"TV\file.mkv"), None);
+    }
+
+    #[test]
+    fn test_windows_filename_budget() {
+        assert_eq!(windows_filename_budget(r"C:\TV"), 253);
+        assert_eq!(windows_filename_budget(r"C:\TV\"), 253);
+        assert_eq!(windows_filename_budget("C:"), MAX_FILENAME_BYTES);
+        let deep = format!(r"\\NAS\share\{}", "x".repeat(200));
+        assert_eq!(windows_filename_budget(&deep), MIN_FILENAME_BYTES);
+    }
+
+    #[test]
+    fn test_check_format() {
+        assert_eq!(
+            check_format("{show} - S{season:02}E{episode:02} - {title}.{ext}"),
+            Ok(vec![])
+        );
+        assert_eq!(
+            check_format("{show} - {tilte} {tilte}.{ext}"),
+            Ok(vec![
+                FormatWarning::UnknownPlaceholder("{tilte}".to_string()),
+                FormatWarning::MissingPlaceholder("episode"),
+            ])
+        );
+
+        assert_eq!(
+            check_format("{show - {title}"),
+            Err(FormatError::UnclosedPlaceholder(0))
+        );
+        assert_eq!(
+            check_format("{episode}.{ext"),
+            Err(FormatError::UnclosedPlaceholder(10))
+        );
+        assert_eq!(
+            check_format("{episode}}.{ext}"),
+            Err(FormatError::UnmatchedBrace(9))
+        );
+        assert_eq!(check_format("{}"), Err(FormatError::EmptyPlaceholder(0)));
+        assert_eq!(
+            check_format("{episode:xx}.{ext}"),
+            Err(FormatError::InvalidPadding("{episode:xx}".to_string()))
+        );
+        assert_eq!(
+            check_format("{title:02}.{ext}"),
+            Err(FormatError::PaddingNotSupported("{title:02}".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_plan_operations_rejects_unknown_placeholders() {
+        let matches = vec![result("a.mkv", Some(0.9), None)];
+
+        assert!(matches!(
+            plan_operations(&matches, "Show", "{show} {episode} {typo}.{ext}", None),
+            Err(FileOperationError::InvalidFormat(
+                FormatError::UnknownPlaceholder(_)
+            ))
+        ));
+        assert!(plan_operations(&matches, "Show", "{show} {episode}.{ext}", None).is_ok());
+    }
+
     #[test]
     fn test_replace_with_padding() {
         assert_eq!(
@@ -336,4 +1932,313 @@ mod tests {
             "Season 5"
         );
     }
+
+    fn result(file: &str, confidence: Option<f64>, part: Option<EpisodePart>) -> MatchResult {
+        MatchResult {
+            video: crate::VideoFile::new(file),
+            video_hash: String::new(),
+            episode: Episode {
+                season_number: 1,
+                episode_number: 1,
+                name: "Pilot".to_string(),
+                summary: String::new(),
+                summary_source: None,
+                airdate: None,
+                runtime: Some(60),
+                season_name: None,
+                provider_id: None,
+                local_title: None,
+            },
+            confidence,
+            alternatives: Vec::new(),
+            reasoning: None,
+            extra_episodes: Vec::new(),
+            filename_conflict: None,
+            runtime_mismatch: None,
+            video_duration: None,
+            part,
+            transcript: None,
+            transcript_quality: None,
+            timings: crate::StageTimings::default(),
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_operations_names_parts() {
+        let part = |number| Some(EpisodePart { number, count: 2 });
+        let matches = vec![
+            result("/tv/a.mkv", None, part(1)),
+            result("/tv/b.mkv", None, part(2)),
+        ];
+
+        let operations = plan_operations(&matches, "Show", "{show} - {title}.{ext}", None).unwrap();
+
+        assert_eq!(
+            operations[0].destination,
+            PathBuf::from("/tv/Show - Pilot - Part 1.mkv")
+        );
+        assert_eq!(
+            operations[1].destination,
+            PathBuf::from("/tv/Show - Pilot - Part 2.mkv")
+        );
+        assert!(operations.iter().all(|op| op.duplicate_suffix.is_none()));
+    }
+
+    #[test]
+    fn test_duplicate_policy_largest() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_duplicate_policy");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let small = temp_dir.join("small.mkv");
+        let large = temp_dir.join("large.mkv");
+        fs::write(&small, vec![0u8; 10]).unwrap();
+        fs::write(&large, vec![0u8; 20]).unwrap();
+
+        let matches = vec![
+            result(small.to_str().unwrap(), Some(0.9), None),
+            result(large.to_str().unwrap(), Some(0.8), None),
+        ];
+        let resolution = apply_duplicate_policy(matches.clone(), DuplicatePolicy::Largest);
+        assert_eq!(resolution.kept, vec![matches[1].clone()]);
+        assert_eq!(resolution.discarded, vec![matches[0].clone()]);
+
+        // The suffix policy keeps everything
+        let resolution = apply_duplicate_policy(matches.clone(), DuplicatePolicy::Suffix);
+        assert_eq!(resolution.kept, matches);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_quarantine_keeps_most_confident() {
+        let matches = vec![
+            result("/tv/a.mkv", Some(0.7), None),
+            result("/tv/b.mkv", Some(0.9), None),
+        ];
+
+        let resolution = apply_duplicate_policy(matches, DuplicatePolicy::Quarantine);
+        assert_eq!(resolution.kept[0].video.path, PathBuf::from("/tv/b.mkv"));
+
+        let operations = plan_quarantine(&resolution.discarded, None).unwrap();
+        assert_eq!(
+            operations[0].destination,
+            PathBuf::from("/tv/duplicates/a.mkv")
+        );
+        assert!(operations[0].quarantined);
+    }
+
+    #[test]
+    fn test_check_free_space() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_free_space");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let source = temp_dir.join("a.mkv");
+        fs::write(&source, vec![0u8; 1_000]).unwrap();
+
+        let operations = plan_operations(
+            &[result(source.to_str().unwrap(), None, None)],
+            "Show",
+            "{title}.{ext}",
+            Some(&temp_dir.join("out")),
+        )
+        .unwrap();
+
+        // A missing output directory is checked on its parent's filesystem
+        assert!(check_free_space(&operations, &temp_dir.join("out/nested")).is_ok());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_execute_hardlink() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_hardlink");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(&temp_dir).unwrap();
+        let source = temp_dir.join("a.mkv");
+        fs::write(&source, "video").unwrap();
+        let output_dir = temp_dir.join("library");
+
+        let operations = plan_operations(
+            &[result(source.to_str().unwrap(), None, None)],
+            "Show",
+            "{title}.{ext}",
+            Some(&output_dir),
+        )
+        .unwrap();
+        let errors = execute_hardlink(&operations, &output_dir).unwrap();
+        assert!(errors.is_empty());
+        assert!(source.exists());
+        assert_eq!(
+            fs::read_to_string(output_dir.join("Pilot.mkv")).unwrap(),
+            "video"
+        );
+
+        // Files already in the library are kept
+        let errors = execute_hardlink(&operations, &output_dir).unwrap();
+        assert_eq!(errors[0].kind(), io::ErrorKind::AlreadyExists);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_execute_copy_with_verification() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_copy_verification");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let source = temp_dir.join("a.mkv");
+        fs::write(&source, vec![7u8; 1_000]).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let output_dir = temp_dir.join("out");
+
+        let operations = plan_operations(
+            &[result(source.to_str().unwrap(), None, None)],
+            "Show",
+            "{title}.{ext}",
+            Some(&output_dir),
+        )
+        .unwrap();
+        let mut events = Vec::new();
+        let errors = execute_copy_with(
+            &operations,
+            &output_dir,
+            CopyOptions {
+                verify: true,
+                ..CopyOptions::default()
+            },
+            |event| events.push(event),
+        )
+        .unwrap();
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            fs::read(output_dir.join("Pilot.mkv")).unwrap(),
+            vec![7u8; 1_000]
+        );
+        assert_eq!(
+            fs::metadata(output_dir.join("Pilot.mkv"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            modified
+        );
+        assert_eq!(
+            events.last(),
+            Some(&CopyProgress::Copied {
+                source: source.clone()
+            })
+        );
+        assert!(events.contains(&CopyProgress::Copying {
+            source: source.clone(),
+            copied: 1_000,
+            total: 1_000,
+        }));
+        assert!(events.contains(&CopyProgress::Verifying { source }));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_execute_rename_atomic() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_rename_atomic");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let a = temp_dir.join("a.mkv");
+        let b = temp_dir.join("b.mkv");
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+        let rename = |source: &Path, destination: &Path| {
+            let mut op = plan_operations(
+                &[result(source.to_str().unwrap(), None, None)],
+                "Show",
+                "{title}.{ext}",
+                None,
+            )
+            .unwrap()
+            .remove(0);
+            op.destination = destination.to_path_buf();
+            op
+        };
+
+        // Files can swap their names
+        execute_rename_atomic(&[rename(&a, &b), rename(&b, &a)]).unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "b");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "a");
+
+        // A failing rename undoes the others
+        let c = temp_dir.join("c.mkv");
+        let missing = temp_dir.join("missing.mkv");
+        let error =
+            execute_rename_atomic(&[rename(&a, &c), rename(&missing, &temp_dir.join("d.mkv"))])
+                .unwrap_err();
+        assert!(matches!(error, FileOperationError::RolledBack { path, .. } if path == missing));
+        assert_eq!(fs::read_to_string(&a).unwrap(), "b");
+        assert!(!c.exists());
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 2);
+
+        // Existing files aren't overwritten
+        assert!(matches!(
+            execute_rename_atomic(&[rename(&a, &b)]),
+            Err(FileOperationError::DestinationExists(_))
+        ));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_air_date_time() {
+        let day = |days: u64| Duration::from_secs(days * 24 * 60 * 60 + 12 * 60 * 60);
+        assert_eq!(
+            air_date_time("2008-01-20"),
+            Some(SystemTime::UNIX_EPOCH + day(13_898))
+        );
+        assert_eq!(
+            air_date_time("1969-12-31"),
+            Some(SystemTime::UNIX_EPOCH - Duration::from_secs(12 * 60 * 60))
+        );
+        assert_eq!(air_date_time("2008-13-01"), None);
+        assert_eq!(air_date_time("soon"), None);
+    }
+
+    #[test]
+    fn test_replaces_file() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_replaces_file");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let a = temp_dir.join("a.mkv");
+        let b = temp_dir.join("b.mkv");
+        let c = temp_dir.join("c.mkv");
+        for file in [&a, &b, &c] {
+            fs::write(file, "").unwrap();
+        }
+        let rename = |source: &Path, destination: &Path| {
+            let mut op = plan_operations(
+                &[result(source.to_str().unwrap(), None, None)],
+                "Show",
+                "{title}.{ext}",
+                None,
+            )
+            .unwrap()
+            .remove(0);
+            op.destination = destination.to_path_buf();
+            op
+        };
+
+        // b is moved away by its own operation, c would be overwritten
+        let operations = [rename(&a, &b), rename(&b, &c)];
+        assert!(!replaces_file(&operations[0], &operations));
+        assert!(replaces_file(&operations[1], &operations));
+
+        // Renaming to the same name replaces nothing
+        assert!(!replaces_file(&rename(&a, &a), &[]));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_parse_resolution() {
+        assert_eq!(parse_resolution("1920x1080\n"), Some(1920 * 1080));
+        assert_eq!(parse_resolution(""), None);
+    }
 }
@@ -1,12 +1,18 @@
 //! File resolver module for investigating video files
 //!
 //! This module provides functionality to scan directories and identify video files
-//! by analyzing their content using MIME type detection.
+//! by analyzing their content using MIME type detection. Files with a video
+//! extension that detection misses are checked with ffprobe.
 
+use crate::SkipReason;
+use crate::retry::RetryPolicy;
+use serde::Serialize;
 use std::fs::{self, File};
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
+use walkdir::WalkDir;
 
 /// Errors that can occur during file resolution
 #[derive(Debug, Error)]
@@ -24,114 +30,564 @@ pub enum FileResolverError {
     ReadEntryFailed(#[from] io::Error),
 }
 
+impl FileResolverError {
+    /// Returns the stable code of the error, like `DD-FIL-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            FileResolverError::NotADirectory(_) => "DD-FIL-001",
+            FileResolverError::ReadDirectoryFailed { .. } => "DD-FIL-002",
+            FileResolverError::ReadEntryFailed(_) => "DD-FIL-003",
+        }
+    }
+}
+
 /// Represents a detected video file
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Files found by an investigation come with their size and MIME type. The
+/// hash and duration are filled in as the investigation computes them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct VideoFile {
     /// Path to the video file
     pub path: PathBuf,
+
+    /// Size of the file in bytes
+    pub size: Option<u64>,
+
+    /// MIME type detected from the file's content, like `video/x-matroska`
+    ///
+    /// None for videos recognized by their extension and ffprobe instead.
+    pub mime_type: Option<String>,
+
+    /// Duration of the video, measured while transcribing it
+    pub duration: Option<Duration>,
+
+    /// BLAKE3 hash of the file's content
+    pub hash: Option<String>,
+}
+
+impl VideoFile {
+    /// Creates a video file of which only the path is known
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            size: None,
+            mime_type: None,
+            duration: None,
+            hash: None,
+        }
+    }
+}
+
+/// Files that aren't investigated, with the reason
+pub(crate) type SkippedFiles = Vec<(PathBuf, SkipReason)>;
+
+/// Files found by `find_files`
+#[derive(Debug, Default)]
+pub(crate) struct ScanResult {
+    /// The video files
+    pub videos: Vec<VideoFile>,
+
+    /// The other files, with why they aren't investigated
+    pub skipped: SkippedFiles,
+
+    /// Paths that couldn't be read, with the error
+    ///
+    /// They are also among the skipped files, with `SkipReason::Unreadable`.
+    pub warnings: Vec<(PathBuf, String)>,
+}
+
+impl ScanResult {
+    /// Probes a file and adds it to the videos or the skipped files
+    ///
+    /// Returns whether the file is a video.
+    fn add_file(&mut self, path: PathBuf, retry: &RetryPolicy) -> bool {
+        match probe_video(&path, retry) {
+            Ok(Ok(video)) => {
+                self.videos.push(video);
+                return true;
+            }
+            Ok(Err(reason)) => self.skipped.push((path, reason)),
+            Err(e) => self.add_unreadable(path, e.to_string()),
+        }
+        false
+    }
+
+    /// Adds a path that couldn't be read
+    fn add_unreadable(&mut self, path: PathBuf, message: String) {
+        self.warnings.push((path.clone(), message));
+        self.skipped.push((path, SkipReason::Unreadable));
+    }
+}
+
+/// Extensions of video containers, compared ignoring case
+///
+/// Files with these extensions whose content isn't recognized (e.g. some
+/// MPEG transport streams) are checked with ffprobe before being rejected.
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "3gp", "avi", "flv", "m2ts", "m4v", "mkv", "mov", "mp4", "mpeg", "mpg", "mts", "ogv", "ts",
+    "vob", "webm", "wmv",
+];
+
+/// Progress of a directory scan, reported after each directory
+///
+/// A directory is reported once the files directly in it were looked at,
+/// before its subdirectories are scanned.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DirectoryScan {
+    /// The directory that was scanned
+    pub path: PathBuf,
+
+    /// Number of files directly in the directory
+    pub files: usize,
+
+    /// Number of those files that aren't videos
+    pub rejected: usize,
+
+    /// Number of directories scanned so far, including this one
+    pub total_directories: usize,
+
+    /// Number of files looked at so far in all directories
+    pub total_files: usize,
 }
 
 /// Investigates a directory recursively to find all video files
 ///
-/// This function scans the given directory and all subdirectories,
-/// analyzing each file to detect video files by their content (not extension).
+/// This function scans the given directory and all subdirectories in sorted
+/// order, analyzing each file to detect video files by their content (not
+/// extension).
+/// Files with a video extension whose content isn't recognized are checked
+/// with ffprobe.
 ///
 /// # Arguments
 ///
 /// * `dir_path` - The directory path to investigate
+/// * `retry` - How to retry reads failing transiently, e.g. on network shares
 ///
 /// # Returns
 ///
 /// A vector of `VideoFile` structs representing all discovered video files,
 /// or an error if the directory cannot be read.
-pub(crate) fn scan_for_videos(dir_path: &Path) -> Result<Vec<VideoFile>, FileResolverError> {
-    let mut video_files = Vec::new();
-    scan_directory_recursive(dir_path, &mut video_files)?;
-    Ok(video_files)
+pub(crate) fn scan_for_videos(
+    dir_path: &Path,
+    retry: &RetryPolicy,
+) -> Result<Vec<VideoFile>, FileResolverError> {
+    let mut result = ScanResult::default();
+    scan_directory(dir_path, retry, &mut result, &mut |_| {})?;
+    Ok(result.videos)
+}
+
+/// Finds the videos at a path, which is either a directory or a single file
+///
+/// Directories are scanned like with `scan_for_videos`. A file yields
+/// itself if it is a video, and nothing otherwise.
+pub(crate) fn find_videos(
+    path: &Path,
+    retry: &RetryPolicy,
+) -> Result<Vec<VideoFile>, FileResolverError> {
+    if path.is_file() {
+        return Ok(probe_video(path, retry)
+            .ok()
+            .and_then(Result::ok)
+            .into_iter()
+            .collect());
+    }
+
+    scan_for_videos(path, retry)
+}
+
+/// Finds the videos at a path like `find_videos`, along with the other files
+///
+/// Returns the videos and the paths of all files that aren't videos, with
+/// why they were rejected: `SkipReason::NotAVideo`, `SkipReason::Unreadable`
+/// for files and directories that couldn't be read, or for files with a
+/// video extension, why ffprobe couldn't confirm them. Paths that couldn't
+/// be read are also listed as warnings with the error. `progress` is called
+/// after each directory scanned.
+pub(crate) fn find_files(
+    path: &Path,
+    retry: &RetryPolicy,
+    progress: &mut dyn FnMut(&DirectoryScan),
+) -> Result<ScanResult, FileResolverError> {
+    let mut result = ScanResult::default();
+    if path.is_file() {
+        result.add_file(path.to_path_buf(), retry);
+    } else {
+        scan_directory(path, retry, &mut result, progress)?;
+    }
+
+    Ok(result)
+}
+
+/// Extensions of files that are still being downloaded, compared ignoring case
+///
+/// Torrent clients write to `.part` (Transmission, Deluge, Vuze), `.!qB`
+/// (qBittorrent), or `.!ut` (µTorrent) files and rename them when done;
+/// browsers use the others.
+const DOWNLOAD_EXTENSIONS: &[&str] = &[
+    "part",
+    "partial",
+    "!qb",
+    "!ut",
+    "crdownload",
+    "download",
+    "opdownload",
+];
+
+/// Extension of the control file aria2 keeps next to a file it downloads
+const ARIA2_CONTROL_EXTENSION: &str = "aria2";
+
+/// How long to watch recently modified videos for growth
+pub(crate) const GROWTH_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Files modified longer ago than this aren't watched for growth
+const RECENTLY_MODIFIED: Duration = Duration::from_secs(60);
+
+/// Returns whether a file is marked as still being downloaded
+///
+/// Downloads are marked by an extension like `episode.mkv.part`, or by an
+/// aria2 control file next to them (`episode.mkv.aria2`).
+pub(crate) fn has_download_marker(path: &Path) -> bool {
+    let download_extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            DOWNLOAD_EXTENSIONS
+                .iter()
+                .any(|download| extension.eq_ignore_ascii_case(download))
+        });
+    if download_extension {
+        return true;
+    }
+
+    let mut control_file = path.as_os_str().to_owned();
+    control_file.push(".");
+    control_file.push(ARIA2_CONTROL_EXTENSION);
+    Path::new(&control_file).is_file()
 }
 
-/// Recursively scans a directory and collects video files
-fn scan_directory_recursive(
+/// Returns the files that are still being written
+///
+/// Files modified within the last minute are watched for `interval`; those
+/// whose size or modification time changed meanwhile are returned. Files
+/// modified earlier are taken to be complete, so there is no wait unless a
+/// file was modified recently.
+pub(crate) fn growing_files(paths: &[&Path], interval: Duration) -> Vec<PathBuf> {
+    let state = |path: &Path| {
+        let metadata = fs::metadata(path).ok()?;
+        Some((metadata.len(), metadata.modified().ok()?))
+    };
+
+    let now = SystemTime::now();
+    let recent: Vec<_> = paths
+        .iter()
+        .filter_map(|path| {
+            let (size, modified) = state(path)?;
+            let age = now.duration_since(modified).unwrap_or_default();
+            (age < RECENTLY_MODIFIED).then_some((*path, (size, modified)))
+        })
+        .collect();
+    if recent.is_empty() {
+        return Vec::new();
+    }
+
+    std::thread::sleep(interval);
+    recent
+        .into_iter()
+        .filter(|(path, before)| state(path).is_some_and(|after| after != *before))
+        .map(|(path, _)| path.to_path_buf())
+        .collect()
+}
+
+/// Sets aside the files that are still being downloaded
+///
+/// Returns the videos to investigate, the other files, and the files set
+/// aside with the reason. Files with a download marker are set aside
+/// whether or not they look like videos yet; videos are then watched for
+/// growth like with `growing_files`.
+pub(crate) fn set_aside_downloads(
+    videos: Vec<VideoFile>,
+    other_files: SkippedFiles,
+    interval: Duration,
+) -> (Vec<VideoFile>, SkippedFiles, SkippedFiles) {
+    let (marked_others, other_files): (Vec<_>, Vec<_>) = other_files
+        .into_iter()
+        .partition(|(path, _)| has_download_marker(path));
+    let (marked_videos, videos): (Vec<_>, Vec<_>) = videos
+        .into_iter()
+        .partition(|video| has_download_marker(&video.path));
+
+    let paths: Vec<&Path> = videos.iter().map(|video| video.path.as_path()).collect();
+    let growing = growing_files(&paths, interval);
+    let (growing_videos, videos): (Vec<_>, Vec<_>) = videos
+        .into_iter()
+        .partition(|video| growing.contains(&video.path));
+
+    let set_aside = marked_videos
+        .into_iter()
+        .map(|video| video.path)
+        .chain(marked_others.into_iter().map(|(path, _)| path))
+        .map(|path| (path, SkipReason::Downloading))
+        .chain(
+            growing_videos
+                .into_iter()
+                .map(|video| (video.path, SkipReason::StillGrowing)),
+        )
+        .collect();
+    (videos, other_files, set_aside)
+}
+
+/// Scans a directory tree and collects video files and other files
+///
+/// The tree is walked iteratively, following symbolic links, in sorted
+/// order with the files of each directory before its subdirectories, so
+/// every run finds the files in the same order. Entries that can't be read,
+/// like subdirectories without permission, are added as unreadable instead
+/// of failing the scan; only the directory itself has to be readable.
+fn scan_directory(
     dir_path: &Path,
-    video_files: &mut Vec<VideoFile>,
+    retry: &RetryPolicy,
+    result: &mut ScanResult,
+    progress: &mut dyn FnMut(&DirectoryScan),
 ) -> Result<(), FileResolverError> {
     if !dir_path.is_dir() {
         return Err(FileResolverError::NotADirectory(dir_path.to_path_buf()));
     }
 
-    for entry in fs::read_dir(dir_path).map_err(|e| FileResolverError::ReadDirectoryFailed {
-        path: dir_path.to_path_buf(),
-        source: e,
-    })? {
-        let entry = entry?;
-        let path = entry.path();
+    // Shares that are slow to respond get retried before the walk starts
+    retry
+        .run(|| fs::read_dir(dir_path))
+        .map_err(|e| FileResolverError::ReadDirectoryFailed {
+            path: dir_path.to_path_buf(),
+            source: e,
+        })?;
 
-        if path.is_dir() {
-            // Recursively investigate subdirectories
-            scan_directory_recursive(&path, video_files)?;
-        } else if path.is_file() {
+    let walker = WalkDir::new(dir_path).follow_links(true).sort_by(|a, b| {
+        a.file_type()
+            .is_dir()
+            .cmp(&b.file_type().is_dir())
+            .then_with(|| a.file_name().cmp(b.file_name()))
+    });
+    let mut scan = DirectoryScan::default();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let path = e.path().unwrap_or(dir_path).to_path_buf();
+                let message = match e.io_error() {
+                    Some(error) => error.to_string(),
+                    None => e.to_string(),
+                };
+                result.add_unreadable(path, message);
+                continue;
+            }
+        };
+
+        if entry.file_type().is_dir() {
+            // The files of the previous directory all came before this one
+            if scan.total_directories > 0 {
+                progress(&scan);
+            }
+            scan.path = entry.into_path();
+            scan.files = 0;
+            scan.rejected = 0;
+            scan.total_directories += 1;
+        } else if entry.file_type().is_file() {
             // Analyze file to determine if it's a video
-            if is_video_file(&path) {
-                video_files.push(VideoFile { path });
+            scan.files += 1;
+            scan.total_files += 1;
+            if !result.add_file(entry.into_path(), retry) {
+                scan.rejected += 1;
             }
         }
     }
+    if scan.total_directories > 0 {
+        progress(&scan);
+    }
 
     Ok(())
 }
 
 /// Analyzes a file to determine if it's a video file
 ///
-/// Returns true if the file is a recognized video format, false otherwise.
-/// Only reads the first 8KB of the file for efficiency.
-fn is_video_file(file_path: &Path) -> bool {
+/// Returns the video file with its size and MIME type if the file is a
+/// recognized video format, why it isn't one, or the error if the file
+/// couldn't be read. Only reads the first 8KB
+/// of the file for efficiency. Files with a video extension that aren't
+/// recognized are accepted if ffprobe finds a video stream in them; if
+/// ffprobe can't be run, they are rejected, as extensions like `.ts` are
+/// also used for other files.
+fn probe_video(file_path: &Path, retry: &RetryPolicy) -> io::Result<Result<VideoFile, SkipReason>> {
     // Only read the first 8KB for file type detection
     const BUFFER_SIZE: usize = 8192;
 
-    let mut file = match File::open(file_path) {
-        Ok(f) => f,
-        Err(_) => return false,
-    };
-
     let mut buffer = vec![0u8; BUFFER_SIZE];
-    let bytes_read = match file.read(&mut buffer) {
-        Ok(n) => n,
-        Err(_) => return false,
-    };
+    let (bytes_read, size) = retry.run(|| {
+        let mut file = File::open(file_path)?;
+        Ok((file.read(&mut buffer)?, file.metadata()?.len()))
+    })?;
 
     // Truncate buffer to actual bytes read
     buffer.truncate(bytes_read);
 
-    infer::is_video(&buffer)
+    let video = VideoFile {
+        size: Some(size),
+        ..VideoFile::new(file_path)
+    };
+    if let Some(kind) =
+        infer::get(&buffer).filter(|kind| kind.matcher_type() == infer::MatcherType::Video)
+    {
+        return Ok(Ok(VideoFile {
+            mime_type: Some(kind.mime_type().to_string()),
+            ..video
+        }));
+    }
+
+    if !has_video_extension(file_path) {
+        return Ok(Err(SkipReason::NotAVideo));
+    }
+    Ok(match has_video_stream(file_path) {
+        Some(true) => Ok(video),
+        Some(false) => Err(SkipReason::NoVideoStream),
+        None => Err(SkipReason::Unverified),
+    })
+}
+
+/// Returns whether a file has the extension of a video container
+fn has_video_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            VIDEO_EXTENSIONS
+                .iter()
+                .any(|video| extension.eq_ignore_ascii_case(video))
+        })
+}
+
+/// Checks with ffprobe whether a file is a container with a video stream
+///
+/// Returns None if ffprobe can't be run. Files ffprobe can't read as a
+/// container at all have no video stream either.
+#[cfg(feature = "ffmpeg")]
+fn has_video_stream(path: &Path) -> Option<bool> {
+    let output = crate::child_process::output(
+        std::process::Command::new(ffmpeg_sidecar::ffprobe::ffprobe_path())
+            .args(["-v", "error", "-select_streams", "v"])
+            .args(["-show_entries", "stream=codec_type", "-of", "csv=p=0"])
+            .arg(path),
+    )
+    .ok()?;
+
+    Some(output.status.success() && !String::from_utf8_lossy(&output.stdout).trim().is_empty())
+}
+
+/// Checks whether a file has a video stream, unknown without the `ffmpeg` feature
+#[cfg(not(feature = "ffmpeg"))]
+fn has_video_stream(_path: &Path) -> Option<bool> {
+    None
+}
+
+/// Smallest read buffer used for hashing
+///
+/// BLAKE3 only hashes in parallel once it gets more than a few chunks at a
+/// time, so smaller buffers would waste the other cores.
+const MIN_HASH_BUFFER_SIZE: usize = 128 * 1024;
+
+/// How video files are read for hashing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashOptions {
+    /// Size of each read when hashing with buffered reads, in bytes
+    ///
+    /// Larger reads keep fast network shares busy; values below 128 KiB
+    /// are raised to it.
+    pub buffer_size: usize,
+
+    /// Maps the files into memory instead of reading them
+    ///
+    /// Fastest on local SSDs, but a file changing or a share disconnecting
+    /// while it is mapped crashes the process instead of failing the read.
+    pub mmap: bool,
+}
+
+impl Default for HashOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: 4 * 1024 * 1024,
+            mmap: false,
+        }
+    }
 }
 
 /// Computes BLAKE3 hash of a video file for use as a cache key
 ///
-/// This function uses memory-mapped I/O with parallel processing (rayon) to
-/// efficiently hash large video files. The OS handles paging, so the entire
-/// file is NOT loaded into RAM. Multiple CPU cores are used for hashing.
+/// The file is read in chunks of `options.buffer_size` or, with
+/// `options.mmap`, memory-mapped; either way it is never loaded into RAM as
+/// a whole, and multiple CPU cores are used for hashing (rayon). Both ways
+/// give the same hash.
 ///
 /// # Arguments
 ///
 /// * `video_path` - Path to the video file to hash
+/// * `options` - How to read the file
+/// * `retry` - How to retry reads failing transiently, e.g. on network shares
+/// * `progress` - Called with the bytes hashed so far and the file size after
+///   each read (only before and after hashing with `mmap`); returning false
+///   cancels hashing
 ///
 /// # Returns
 ///
-/// A hex-encoded BLAKE3 hash string, or an error if the file cannot be read.
+/// A hex-encoded BLAKE3 hash string with the number of bytes hashed, or an
+/// error if the file cannot be read or hashing was cancelled.
 ///
 /// # Examples
 ///
 /// ```ignore
-/// let hash = compute_video_hash(Path::new("video.mp4"))?;
+/// let (hash, _) = compute_video_hash(
+///     Path::new("video.mp4"),
+///     &HashOptions::default(),
+///     &RetryPolicy::default(),
+///     &mut |_, _| true,
+/// )?;
 /// println!("Video hash: {}", hash);
 /// ```
-pub(crate) fn compute_video_hash(video_path: &Path) -> Result<String, FileResolverError> {
-    let hash = blake3::Hasher::new()
-        .update_mmap_rayon(video_path)
-        .map_err(FileResolverError::ReadEntryFailed)?
-        .finalize();
+pub(crate) fn compute_video_hash(
+    video_path: &Path,
+    options: &HashOptions,
+    retry: &RetryPolicy,
+    progress: &mut dyn FnMut(u64, u64) -> bool,
+) -> Result<(String, u64), FileResolverError> {
+    let cancelled = || io::Error::other("hashing was cancelled");
+    let hasher = retry
+        .run(|| {
+            let mut hasher = blake3::Hasher::new();
+            if options.mmap {
+                let total = fs::metadata(video_path)?.len();
+                if !progress(0, total) {
+                    return Err(cancelled());
+                }
+                hasher.update_mmap_rayon(video_path)?;
+                progress(hasher.count(), total);
+            } else {
+                let mut file = File::open(video_path)?;
+                let total = file.metadata()?.len();
+                let mut buffer = vec![0u8; options.buffer_size.max(MIN_HASH_BUFFER_SIZE)];
+                loop {
+                    let bytes_read = match file.read(&mut buffer) {
+                        Ok(0) => break,
+                        Ok(n) => n,
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e),
+                    };
+                    hasher.update_rayon(&buffer[..bytes_read]);
+                    if !progress(hasher.count(), total) {
+                        return Err(cancelled());
+                    }
+                }
+            }
+            Ok(hasher)
+        })
+        .map_err(FileResolverError::ReadEntryFailed)?;
 
-    Ok(hash.to_hex().to_string())
+    Ok((hasher.finalize().to_hex().to_string(), hasher.count()))
 }
 
 #[cfg(test)]
@@ -141,7 +597,10 @@ mod tests {
 
     #[test]
     fn test_scan_nonexistent_directory() {
-        let result = scan_for_videos(Path::new("/nonexistent/path/that/does/not/exist"));
+        let result = scan_for_videos(
+            Path::new("/nonexistent/path/that/does/not/exist"),
+            &RetryPolicy::default(),
+        );
         assert!(result.is_err());
     }
 
@@ -152,10 +611,231 @@ mod tests {
         let temp_file = temp_dir.join("test_file.txt");
         File::create(&temp_file).unwrap();
 
-        let result = scan_for_videos(&temp_file);
+        let result = scan_for_videos(&temp_file, &RetryPolicy::default());
         assert!(result.is_err());
 
         // Cleanup
         fs::remove_file(&temp_file).ok();
     }
+
+    #[test]
+    fn test_find_videos_accepts_single_files() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_find_videos");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // An EBML header declaring a Matroska document
+        let video = temp_dir.join("episode.mkv");
+        let mut header = vec![0x1A, 0x45, 0xDF, 0xA3, 0x93, 0x42, 0x82, 0x88];
+        header.extend_from_slice(b"matroska\0");
+        fs::write(&video, header).unwrap();
+        let text = temp_dir.join("episode.nfo");
+        fs::write(&text, "Not a video").unwrap();
+
+        let retry = RetryPolicy::default();
+        let videos = find_videos(&video, &retry).unwrap();
+        assert_eq!(videos.len(), 1);
+        assert_eq!(videos[0].path, video);
+        assert_eq!(videos[0].size, Some(17));
+        assert_eq!(videos[0].mime_type.as_deref(), Some("video/x-matroska"));
+        assert!(find_videos(&text, &retry).unwrap().is_empty());
+        assert_eq!(find_videos(&temp_dir, &retry).unwrap().len(), 1);
+        let result = find_files(&temp_dir, &retry, &mut |_| {}).unwrap();
+        assert_eq!(result.videos.len(), 1);
+        assert_eq!(result.skipped, vec![(text, SkipReason::NotAVideo)]);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_find_files_reports_directories() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_directory_scans");
+        let season = temp_dir.join("Season 1");
+        fs::create_dir_all(&season).unwrap();
+        fs::write(temp_dir.join("show.nfo"), "Not a video").unwrap();
+        fs::write(season.join("season.nfo"), "Not a video").unwrap();
+        fs::write(season.join("poster.jpg"), "Not a video either").unwrap();
+
+        let mut scans = Vec::new();
+        find_files(&temp_dir, &RetryPolicy::default(), &mut |scan| {
+            scans.push(scan.clone())
+        })
+        .unwrap();
+        assert_eq!(
+            scans,
+            vec![
+                DirectoryScan {
+                    path: temp_dir.clone(),
+                    files: 1,
+                    rejected: 1,
+                    total_directories: 1,
+                    total_files: 1,
+                },
+                DirectoryScan {
+                    path: season,
+                    files: 2,
+                    rejected: 2,
+                    total_directories: 2,
+                    total_files: 3,
+                },
+            ]
+        );
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_find_files_in_sorted_order() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_sorted_scan");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(temp_dir.join("a")).unwrap();
+        for name in ["b.nfo", "a/c.nfo", "d.nfo"] {
+            fs::write(temp_dir.join(name), "Not a video").unwrap();
+        }
+        std::os::unix::fs::symlink(temp_dir.join("missing"), temp_dir.join("dangling")).unwrap();
+
+        let result = find_files(&temp_dir, &RetryPolicy::default(), &mut |_| {}).unwrap();
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].0, temp_dir.join("dangling"));
+        assert_eq!(
+            result.skipped,
+            vec![
+                (temp_dir.join("b.nfo"), SkipReason::NotAVideo),
+                (temp_dir.join("d.nfo"), SkipReason::NotAVideo),
+                (temp_dir.join("dangling"), SkipReason::Unreadable),
+                (temp_dir.join("a/c.nfo"), SkipReason::NotAVideo),
+            ]
+        );
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_unrecognized_files_with_video_extensions() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_video_extensions");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let script = temp_dir.join("player.ts");
+        fs::write(&script, "export const player = {};").unwrap();
+        let notes = temp_dir.join("notes.txt");
+        fs::write(&notes, "Not a video").unwrap();
+
+        assert!(has_video_extension(Path::new("episode.M2TS")));
+        assert!(!has_video_extension(Path::new("episode.nfo")));
+
+        // Not a container ffprobe can read, or ffprobe isn't available
+        let retry = RetryPolicy::default();
+        assert!(matches!(
+            probe_video(&script, &retry),
+            Ok(Err(SkipReason::NoVideoStream | SkipReason::Unverified))
+        ));
+        assert!(matches!(
+            probe_video(&notes, &retry),
+            Ok(Err(SkipReason::NotAVideo))
+        ));
+        assert!(probe_video(&temp_dir.join("missing.ts"), &retry).is_err());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_has_download_marker() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_download_marker");
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("episode.mkv.aria2"), "").unwrap();
+
+        assert!(has_download_marker(Path::new("episode.mkv.part")));
+        assert!(has_download_marker(Path::new("episode.mkv.!qB")));
+        assert!(has_download_marker(Path::new("episode.mkv.crdownload")));
+        assert!(has_download_marker(&temp_dir.join("episode.mkv")));
+        assert!(!has_download_marker(&temp_dir.join("other.mkv")));
+        assert!(!has_download_marker(Path::new("the.partisan.mkv")));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_set_aside_downloads() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_set_aside_downloads");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let complete = temp_dir.join("complete.mkv");
+        let growing = temp_dir.join("growing.mkv");
+        let partial = temp_dir.join("partial.mkv.part");
+        fs::write(&complete, "complete").unwrap();
+        fs::write(&growing, "growing").unwrap();
+        fs::write(&partial, "").unwrap();
+        File::options()
+            .write(true)
+            .open(&complete)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(3600))
+            .unwrap();
+
+        let writer = {
+            let growing = growing.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                fs::write(&growing, "growing a bit more").unwrap();
+            })
+        };
+        let (videos, others, set_aside) = set_aside_downloads(
+            vec![VideoFile::new(&complete), VideoFile::new(&growing)],
+            vec![
+                (partial.clone(), SkipReason::NotAVideo),
+                (temp_dir.join("episode.nfo"), SkipReason::NotAVideo),
+            ],
+            Duration::from_millis(300),
+        );
+        writer.join().unwrap();
+
+        assert_eq!(videos, vec![VideoFile::new(&complete)]);
+        assert_eq!(
+            others,
+            vec![(temp_dir.join("episode.nfo"), SkipReason::NotAVideo)]
+        );
+        assert_eq!(
+            set_aside,
+            vec![
+                (partial, SkipReason::Downloading),
+                (growing, SkipReason::StillGrowing),
+            ]
+        );
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_compute_video_hash_read_modes() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_hash_modes");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let video = temp_dir.join("episode.mkv");
+        let content: Vec<u8> = (0..1_000_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&video, &content).unwrap();
+
+        let retry = RetryPolicy::default();
+        let buffered = HashOptions {
+            buffer_size: 1,
+            mmap: false,
+        };
+        let mapped = HashOptions {
+            mmap: true,
+            ..HashOptions::default()
+        };
+        let mut reported = Vec::new();
+        let (hash, bytes) = compute_video_hash(&video, &buffered, &retry, &mut |done, total| {
+            reported.push((done, total));
+            true
+        })
+        .unwrap();
+        assert_eq!(hash, blake3::hash(&content).to_hex().to_string());
+        assert_eq!(bytes, 1_000_000);
+        assert_eq!(reported.first(), Some(&(131_072, 1_000_000)));
+        assert_eq!(reported.last(), Some(&(1_000_000, 1_000_000)));
+        assert_eq!(
+            compute_video_hash(&video, &mapped, &retry, &mut |_, _| true).unwrap(),
+            (hash, bytes)
+        );
+        assert!(compute_video_hash(&video, &buffered, &retry, &mut |_, _| false).is_err());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
 }
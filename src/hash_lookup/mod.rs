@@ -0,0 +1,108 @@
+/// Episode identification by file hash.
+///
+/// Files that have been shared before are often already known to
+/// subtitle databases by their OpenSubtitles "moviehash". Looking that hash
+/// up is much cheaper than transcribing and matching the dialogue, so it is
+/// tried first and the dialogue pipeline is only used on misses.
+mod opensubtitles;
+mod opensubtitles_types;
+
+pub(crate) use opensubtitles::OpenSubtitlesLookup;
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use thiserror::Error;
+
+/// Number of bytes hashed at the start and at the end of a file.
+const HASH_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Errors that can occur while looking up a file hash.
+#[derive(Debug, Error)]
+pub(crate) enum HashLookupError {
+    /// Request to the hash database failed
+    #[error("Request failed: {0}")]
+    RequestError(String),
+
+    /// Failed to parse the database's JSON response
+    #[error("Failed to parse API response: {0}")]
+    ParseError(String),
+}
+
+/// An episode identified by its file hash.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct HashMatch {
+    /// Name of the series the episode belongs to
+    pub show_name: String,
+    /// The season number of the episode
+    pub season_number: usize,
+    /// The episode number within the season
+    pub episode_number: usize,
+}
+
+/// Computes the OpenSubtitles hash of a file.
+///
+/// The hash is the file size plus the sum of all 64-bit little-endian words
+/// in the first and last 64 KiB of the file, wrapping on overflow, formatted
+/// as 16 hex digits. Returns None for files smaller than 64 KiB, which the
+/// algorithm isn't defined for.
+pub(crate) fn opensubtitles_hash(path: &Path) -> io::Result<Option<String>> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    if size < HASH_CHUNK_SIZE {
+        return Ok(None);
+    }
+
+    let mut hash = size;
+    let mut chunk = vec![0u8; HASH_CHUNK_SIZE as usize];
+    for offset in [0, size - HASH_CHUNK_SIZE] {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut chunk)?;
+
+        for word in chunk.chunks_exact(8) {
+            let word = u64::from_le_bytes(word.try_into().expect("chunk of 8 bytes"));
+            hash = hash.wrapping_add(word);
+        }
+    }
+
+    Ok(Some(format!("{:016x}", hash)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_opensubtitles_hash() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_opensubtitles_hash");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // Zeroed file: the hash is just the size
+        let zeroed = temp_dir.join("zeroed.bin");
+        fs::write(&zeroed, vec![0u8; 200_000]).unwrap();
+        assert_eq!(
+            opensubtitles_hash(&zeroed).unwrap().as_deref(),
+            Some("0000000000030d40")
+        );
+
+        // Words in both hashed chunks are summed up, wrapping on overflow
+        let mut content = vec![0u8; 200_000];
+        content[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        content[200_000 - 8..].copy_from_slice(&2u64.to_le_bytes());
+        let words = temp_dir.join("words.bin");
+        fs::write(&words, content).unwrap();
+        assert_eq!(
+            opensubtitles_hash(&words).unwrap().as_deref(),
+            Some("0000000000030d41")
+        );
+
+        // Too small to be hashed
+        let small = temp_dir.join("small.bin");
+        fs::write(&small, vec![0u8; 1_000]).unwrap();
+        assert_eq!(opensubtitles_hash(&small).unwrap(), None);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+}
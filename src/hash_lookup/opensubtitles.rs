@@ -0,0 +1,146 @@
+/// OpenSubtitles hash lookup implementation.
+///
+/// Searches the subtitles of https://www.opensubtitles.com by file hash and
+/// reads the episode from the subtitles that were matched by that hash.
+/// Requires an OpenSubtitles API key.
+use super::opensubtitles_types::{OpenSubtitlesFeature, OpenSubtitlesSearchResponse};
+use super::{HashLookupError, HashMatch};
+use std::collections::HashMap;
+
+/// Environment variable holding the OpenSubtitles API key.
+const API_KEY_ENV: &str = "OPENSUBTITLES_API_KEY";
+
+/// User agent sent with every request, as required by the API.
+const USER_AGENT: &str = concat!("DialogDetective v", env!("CARGO_PKG_VERSION"));
+
+/// Episode lookup backed by the OpenSubtitles REST API.
+pub(crate) struct OpenSubtitlesLookup {
+    client: reqwest::blocking::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl OpenSubtitlesLookup {
+    /// Creates a new OpenSubtitles lookup using the given API key.
+    pub fn new(api_key: String) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            base_url: "https://api.opensubtitles.com/api/v1".to_string(),
+            api_key,
+        }
+    }
+
+    /// Creates a new OpenSubtitles lookup if `OPENSUBTITLES_API_KEY` is set in the environment.
+    pub fn from_env() -> Option<Self> {
+        std::env::var(API_KEY_ENV)
+            .ok()
+            .filter(|key| !key.trim().is_empty())
+            .map(Self::new)
+    }
+
+    /// Looks up the episode a file hash belongs to.
+    ///
+    /// Returns None if no subtitle was matched by the hash or the hash
+    /// belongs to a movie rather than an episode.
+    pub fn lookup(&self, hash: &str) -> Result<Option<HashMatch>, HashLookupError> {
+        let url = format!("{}/subtitles", self.base_url);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Api-Key", &self.api_key)
+            .header("User-Agent", USER_AGENT)
+            .query(&[("moviehash", hash)])
+            .send()
+            .map_err(|e| HashLookupError::RequestError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(HashLookupError::RequestError(format!(
+                "HTTP {} {}",
+                response.status().as_u16(),
+                response.status().canonical_reason().unwrap_or("Unknown")
+            )));
+        }
+
+        let response: OpenSubtitlesSearchResponse = response
+            .json()
+            .map_err(|e| HashLookupError::ParseError(e.to_string()))?;
+
+        Ok(most_common_episode(
+            response
+                .data
+                .into_iter()
+                .filter(|subtitle| subtitle.attributes.moviehash_match)
+                .filter_map(|subtitle| subtitle.attributes.feature_details),
+        ))
+    }
+}
+
+/// Returns the episode most of the hash-matched subtitles belong to.
+///
+/// Different uploaders occasionally tag the same file differently, so the
+/// episode agreed on by the most subtitles wins.
+fn most_common_episode(features: impl Iterator<Item = OpenSubtitlesFeature>) -> Option<HashMatch> {
+    let mut votes: HashMap<HashMatch, usize> = HashMap::new();
+
+    for feature in features {
+        if feature.feature_type.as_deref() != Some("Episode") {
+            continue;
+        }
+
+        if let (Some(show_name), Some(season_number), Some(episode_number)) = (
+            feature.parent_title,
+            feature.season_number,
+            feature.episode_number,
+        ) {
+            *votes
+                .entry(HashMatch {
+                    show_name,
+                    season_number,
+                    episode_number,
+                })
+                .or_default() += 1;
+        }
+    }
+
+    votes
+        .into_iter()
+        // Ties go to the lowest episode, so the result doesn't depend on map order
+        .max_by(|(a, a_votes), (b, b_votes)| a_votes.cmp(b_votes).then_with(|| b.cmp(a)))
+        .map(|(hash_match, _)| hash_match)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_common_episode_ignores_movies_and_incomplete_features() {
+        let response: OpenSubtitlesSearchResponse = serde_json::from_str(
+            r#"{"data": [
+                {"attributes": {"moviehash_match": true, "feature_details": {"feature_type": "Episode", "season_number": 1, "episode_number": 3, "parent_title": "Breaking Bad"}}},
+                {"attributes": {"moviehash_match": true, "feature_details": {"feature_type": "Episode", "season_number": 1, "episode_number": 3, "parent_title": "Breaking Bad"}}},
+                {"attributes": {"moviehash_match": true, "feature_details": {"feature_type": "Episode", "season_number": 1, "episode_number": 4, "parent_title": "Breaking Bad"}}},
+                {"attributes": {"moviehash_match": true, "feature_details": {"feature_type": "Movie", "parent_title": null}}},
+                {"attributes": {"moviehash_match": true, "feature_details": {"feature_type": "Episode", "season_number": null, "episode_number": 1, "parent_title": "Breaking Bad"}}}
+            ]}"#,
+        )
+        .unwrap();
+
+        let episode = most_common_episode(
+            response
+                .data
+                .into_iter()
+                .filter_map(|subtitle| subtitle.attributes.feature_details),
+        );
+
+        assert_eq!(
+            episode,
+            Some(HashMatch {
+                show_name: "Breaking Bad".to_string(),
+                season_number: 1,
+                episode_number: 3,
+            })
+        );
+    }
+}
@@ -0,0 +1,44 @@
+/// OpenSubtitles API response types for deserialization.
+///
+/// These structures mirror the subset of the OpenSubtitles REST API JSON
+/// responses needed to identify an episode by its file hash.
+use serde::Deserialize;
+
+// =========================================================
+// Subtitle search types (/subtitles)
+// =========================================================
+
+/// Response envelope of the `/subtitles` search endpoint.
+#[derive(Debug, Deserialize)]
+pub(super) struct OpenSubtitlesSearchResponse {
+    pub data: Vec<OpenSubtitlesSubtitle>,
+}
+
+/// A single subtitle within a search response.
+#[derive(Debug, Deserialize)]
+pub(super) struct OpenSubtitlesSubtitle {
+    pub attributes: OpenSubtitlesAttributes,
+}
+
+/// Attributes of a subtitle.
+#[derive(Debug, Deserialize)]
+pub(super) struct OpenSubtitlesAttributes {
+    /// Whether the subtitle was found by the hash (rather than by name)
+    #[serde(default)]
+    pub moviehash_match: bool,
+    /// The movie or episode the subtitle belongs to
+    pub feature_details: Option<OpenSubtitlesFeature>,
+}
+
+/// The movie or episode a subtitle belongs to.
+#[derive(Debug, Deserialize)]
+pub(super) struct OpenSubtitlesFeature {
+    /// "Episode" for TV episodes, "Movie" for movies
+    pub feature_type: Option<String>,
+    /// Season number (episodes only)
+    pub season_number: Option<usize>,
+    /// Episode number within the season (episodes only)
+    pub episode_number: Option<usize>,
+    /// Name of the series (episodes only)
+    pub parent_title: Option<String>,
+}
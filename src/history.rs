@@ -0,0 +1,470 @@
+//! History module
+//!
+//! Once a file is renamed, nothing tells where it came from or why it got
+//! its name. The history records every file of every investigation in a
+//! SQLite database: its hash, original path, the matched episode with the
+//! confidence, and what was done with it. Runs can be audited later, and
+//! a file can be traced back by its hash after being renamed again.
+
+use crate::MatchResult;
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+/// Name of the history database in the data directory
+const HISTORY_FILE: &str = "history.db";
+
+/// How long to wait for another process writing to the database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors that can occur while working with the history
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    /// Could not determine the data directory
+    #[error("Could not determine data directory")]
+    DataDirectoryNotFound,
+
+    /// Failed to create the directory of the database
+    #[error("Failed to create {path}: {source}")]
+    IoFailed { path: PathBuf, source: io::Error },
+
+    /// The database couldn't be read or written
+    #[error("History database error: {0}")]
+    Database(#[from] rusqlite::Error),
+}
+
+impl HistoryError {
+    /// Returns the stable code of the error, like `DD-HIS-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            HistoryError::DataDirectoryNotFound => "DD-HIS-001",
+            HistoryError::IoFailed { .. } => "DD-HIS-002",
+            HistoryError::Database(_) => "DD-HIS-003",
+        }
+    }
+}
+
+/// What was done with an investigated file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HistoryAction {
+    /// Matched in a dry run, nothing was changed
+    DryRun,
+    /// Renamed in place
+    Renamed,
+    /// Copied into an output directory
+    Copied,
+    /// Hard linked into a library by the download hook
+    Linked,
+    /// Handed to Sonarr for import
+    Imported,
+    /// Set aside for review
+    Review,
+    /// Not matched confidently enough to be used
+    Unmatched,
+    /// Renaming, copying, or linking the file failed
+    Failed,
+}
+
+impl HistoryAction {
+    /// All actions, in the order they are declared
+    const ALL: [HistoryAction; 8] = [
+        HistoryAction::DryRun,
+        HistoryAction::Renamed,
+        HistoryAction::Copied,
+        HistoryAction::Linked,
+        HistoryAction::Imported,
+        HistoryAction::Review,
+        HistoryAction::Unmatched,
+        HistoryAction::Failed,
+    ];
+
+    /// Returns the name the action is stored and displayed as
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HistoryAction::DryRun => "dry-run",
+            HistoryAction::Renamed => "renamed",
+            HistoryAction::Copied => "copied",
+            HistoryAction::Linked => "linked",
+            HistoryAction::Imported => "imported",
+            HistoryAction::Review => "review",
+            HistoryAction::Unmatched => "unmatched",
+            HistoryAction::Failed => "failed",
+        }
+    }
+
+    /// Parses a stored action name
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.as_str() == name)
+    }
+}
+
+/// A file recorded in the history
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    /// ID of the entry, increasing with every recorded file
+    pub id: i64,
+
+    /// ID shared by all files recorded by the same run
+    pub run_id: String,
+
+    /// Time the file was recorded, in seconds since the Unix epoch
+    pub recorded_at: u64,
+
+    /// BLAKE3 hash of the video file's content
+    pub video_hash: String,
+
+    /// Path of the file when it was investigated
+    pub original_path: PathBuf,
+
+    /// Path the file was renamed, copied, or linked to, if any
+    pub destination: Option<PathBuf>,
+
+    /// Name of the show the file was matched against
+    pub show: String,
+
+    /// Season number of the matched episode
+    pub season: usize,
+
+    /// Episode number of the matched episode
+    pub episode: usize,
+
+    /// Title of the matched episode
+    pub episode_title: String,
+
+    /// The matcher's confidence in the match, if known
+    pub confidence: Option<f64>,
+
+    /// What was done with the file
+    pub action: HistoryAction,
+}
+
+/// Filters for looking up history entries
+#[derive(Debug, Clone)]
+pub struct HistoryQuery {
+    /// Only shows whose name contains this text (ignoring ASCII case)
+    pub show: Option<String>,
+
+    /// Only files whose original path or destination contains this text
+    pub path: Option<String>,
+
+    /// Only files with this content hash
+    pub video_hash: Option<String>,
+
+    /// Only files recorded by this run
+    pub run_id: Option<String>,
+
+    /// Maximum number of entries returned, newest first
+    pub limit: usize,
+}
+
+impl Default for HistoryQuery {
+    fn default() -> Self {
+        Self {
+            show: None,
+            path: None,
+            video_hash: None,
+            run_id: None,
+            limit: 50,
+        }
+    }
+}
+
+/// Returns the path of the history database in the data directory
+pub fn default_history_path() -> Result<PathBuf, HistoryError> {
+    let proj_dirs = directories::ProjectDirs::from("de", "westhoffswelt", "dialogdetective")
+        .ok_or(HistoryError::DataDirectoryNotFound)?;
+    Ok(proj_dirs.data_local_dir().join(HISTORY_FILE))
+}
+
+/// The history database, recording files under a run ID of its own
+pub struct History {
+    connection: Connection,
+    run_id: String,
+}
+
+impl History {
+    /// Opens a history database, creating it if it doesn't exist
+    ///
+    /// Every opened history starts a new run; files recorded through it
+    /// share its run ID.
+    pub fn open(path: &Path) -> Result<Self, HistoryError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|source| HistoryError::IoFailed {
+                path: dir.to_path_buf(),
+                source,
+            })?;
+        }
+
+        let connection = Connection::open(path)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                run_id TEXT NOT NULL,
+                recorded_at INTEGER NOT NULL,
+                video_hash TEXT NOT NULL,
+                original_path TEXT NOT NULL,
+                destination TEXT,
+                show TEXT NOT NULL,
+                season INTEGER NOT NULL,
+                episode INTEGER NOT NULL,
+                episode_title TEXT NOT NULL,
+                confidence REAL,
+                action TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS history_video_hash ON history (video_hash);
+            CREATE INDEX IF NOT EXISTS history_run_id ON history (run_id);",
+        )?;
+
+        Ok(Self {
+            connection,
+            run_id: ulid::Ulid::new().to_string(),
+        })
+    }
+
+    /// Returns the ID of the run recorded by this history
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Records what was done with a matched file
+    ///
+    /// Relative paths are stored as absolute paths.
+    pub fn record(
+        &self,
+        show: &str,
+        result: &MatchResult,
+        action: HistoryAction,
+        destination: Option<&Path>,
+    ) -> Result<(), HistoryError> {
+        let recorded_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let original_path = absolute(&result.video.path);
+        let destination = destination.map(absolute);
+
+        self.connection.execute(
+            "INSERT INTO history (
+                run_id, recorded_at, video_hash, original_path, destination, show,
+                season, episode, episode_title, confidence, action
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                self.run_id,
+                recorded_at as i64,
+                result.video_hash,
+                original_path.to_string_lossy(),
+                destination.as_ref().map(|d| d.to_string_lossy()),
+                show,
+                result.episode.season_number as i64,
+                result.episode.episode_number as i64,
+                result.episode.name,
+                result.confidence,
+                action.as_str(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Looks up recorded files, newest first
+    pub fn query(&self, query: &HistoryQuery) -> Result<Vec<HistoryEntry>, HistoryError> {
+        let contains = |text: &Option<String>| text.as_ref().map(|t| format!("%{}%", t));
+        let mut statement = self.connection.prepare(
+            "SELECT id, run_id, recorded_at, video_hash, original_path, destination, show,
+                season, episode, episode_title, confidence, action
+            FROM history
+            WHERE (?1 IS NULL OR show LIKE ?1)
+                AND (?2 IS NULL OR original_path LIKE ?2 OR destination LIKE ?2)
+                AND (?3 IS NULL OR video_hash = ?3)
+                AND (?4 IS NULL OR run_id = ?4)
+            ORDER BY id DESC
+            LIMIT ?5",
+        )?;
+
+        let rows = statement.query_map(
+            params![
+                contains(&query.show),
+                contains(&query.path),
+                query.video_hash,
+                query.run_id,
+                query.limit as i64,
+            ],
+            |row| {
+                let action: String = row.get(11)?;
+                Ok(HistoryEntry {
+                    id: row.get(0)?,
+                    run_id: row.get(1)?,
+                    recorded_at: row.get::<_, i64>(2)? as u64,
+                    video_hash: row.get(3)?,
+                    original_path: PathBuf::from(row.get::<_, String>(4)?),
+                    destination: row.get::<_, Option<String>>(5)?.map(PathBuf::from),
+                    show: row.get(6)?,
+                    season: row.get::<_, i64>(7)? as usize,
+                    episode: row.get::<_, i64>(8)? as usize,
+                    episode_title: row.get(9)?,
+                    confidence: row.get(10)?,
+                    action: HistoryAction::parse(&action).unwrap_or(HistoryAction::Failed),
+                })
+            },
+        )?;
+
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Removes all files recorded for a show (ignoring ASCII case)
+    ///
+    /// Returns the content hashes of the removed entries, once per hash.
+    pub fn remove_show(&self, show: &str) -> Result<Vec<String>, HistoryError> {
+        let mut statement = self.connection.prepare(
+            "SELECT DISTINCT video_hash FROM history WHERE show = ?1 COLLATE NOCASE
+            ORDER BY video_hash",
+        )?;
+        let hashes = statement
+            .query_map(params![show], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+
+        self.connection.execute(
+            "DELETE FROM history WHERE show = ?1 COLLATE NOCASE",
+            params![show],
+        )?;
+        Ok(hashes)
+    }
+
+    /// Removes all files recorded with the given content hash
+    ///
+    /// Returns the number of removed entries.
+    pub fn remove_video(&self, video_hash: &str) -> Result<usize, HistoryError> {
+        Ok(self.connection.execute(
+            "DELETE FROM history WHERE video_hash = ?1",
+            params![video_hash],
+        )?)
+    }
+}
+
+/// Makes a path absolute without resolving links, falling back to the path
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Episode, StageTimings, VideoFile};
+
+    fn result(file: &str, hash: &str, episode_number: usize) -> MatchResult {
+        MatchResult {
+            video: VideoFile::new(file),
+            video_hash: hash.to_string(),
+            episode: Episode {
+                season_number: 1,
+                episode_number,
+                name: "Pilot".to_string(),
+                summary: String::new(),
+                summary_source: None,
+                airdate: None,
+                runtime: None,
+                season_name: None,
+                provider_id: None,
+                local_title: None,
+            },
+            confidence: Some(0.9),
+            alternatives: Vec::new(),
+            reasoning: None,
+            extra_episodes: Vec::new(),
+            filename_conflict: None,
+            runtime_mismatch: None,
+            video_duration: None,
+            part: None,
+            transcript: None,
+            transcript_quality: None,
+            timings: StageTimings::default(),
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn test_history_record_and_query() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_history");
+        fs::remove_dir_all(&temp_dir).ok();
+        let path = temp_dir.join(HISTORY_FILE);
+
+        let history = History::open(&path).unwrap();
+        let renamed = Path::new("/tv/Breaking Bad - S01E01 - Pilot.mkv");
+        history
+            .record(
+                "Breaking Bad",
+                &result("/rips/a.mkv", "aaa", 1),
+                HistoryAction::Renamed,
+                Some(renamed),
+            )
+            .unwrap();
+        history
+            .record(
+                "Doctor Who",
+                &result("/rips/b.mkv", "bbb", 2),
+                HistoryAction::Review,
+                None,
+            )
+            .unwrap();
+        let run_id = history.run_id().to_string();
+        drop(history);
+
+        let history = History::open(&path).unwrap();
+        assert_ne!(history.run_id(), run_id);
+        let all = history.query(&HistoryQuery::default()).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].show, "Doctor Who");
+        assert_eq!(all[0].action, HistoryAction::Review);
+        assert_eq!(all[1].destination.as_deref(), Some(renamed));
+        assert_eq!(all[1].run_id, run_id);
+
+        let by_show = HistoryQuery {
+            show: Some("breaking".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(history.query(&by_show).unwrap()[0].video_hash, "aaa");
+        let by_path = HistoryQuery {
+            path: Some("S01E01".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(history.query(&by_path).unwrap().len(), 1);
+        let by_hash = HistoryQuery {
+            video_hash: Some("bbb".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(history.query(&by_hash).unwrap()[0].episode, 2);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_history_remove() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_history_remove");
+        fs::remove_dir_all(&temp_dir).ok();
+        let history = History::open(&temp_dir.join(HISTORY_FILE)).unwrap();
+        for (show, file, hash) in [
+            ("Breaking Bad", "/rips/a.mkv", "aaa"),
+            ("Breaking Bad", "/rips/a2.mkv", "aaa"),
+            ("Breaking Bad", "/rips/c.mkv", "ccc"),
+            ("Doctor Who", "/rips/b.mkv", "bbb"),
+        ] {
+            history
+                .record(show, &result(file, hash, 1), HistoryAction::DryRun, None)
+                .unwrap();
+        }
+
+        assert_eq!(
+            history.remove_show("breaking bad").unwrap(),
+            vec!["aaa", "ccc"]
+        );
+        assert_eq!(history.remove_video("bbb").unwrap(), 1);
+        assert_eq!(history.remove_video("bbb").unwrap(), 0);
+        assert!(history.query(&HistoryQuery::default()).unwrap().is_empty());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+}
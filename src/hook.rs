@@ -0,0 +1,252 @@
+//! Download hook module
+//!
+//! Torrent clients like qBittorrent and Deluge can run a program when a
+//! download completes, passing nothing but its path. Everything else the
+//! investigation needs comes from a named profile in a JSON file, and
+//! since nobody watches the output of such a run, it is written as JSON
+//! lines to a log file instead.
+
+use crate::MatcherType;
+use crate::file_operations::DEFAULT_FORMAT;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use thiserror::Error;
+
+/// Name of the profiles file in the configuration directory
+const PROFILES_FILE: &str = "profiles.json";
+
+/// Name of the hook log in the data directory
+const HOOK_LOG_FILE: &str = "hook.log";
+
+/// Errors that can occur while loading a hook profile
+#[derive(Debug, Error)]
+pub enum HookError {
+    /// Could not determine the configuration directory
+    #[error("Could not determine configuration directory")]
+    ConfigDirectoryNotFound,
+
+    /// Failed to read the profiles file
+    #[error("Failed to read profiles from {path}: {source}")]
+    ReadFailed { path: PathBuf, source: io::Error },
+
+    /// The profiles file isn't valid
+    #[error("Failed to parse profiles in {path}: {source}")]
+    ParseFailed {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    /// No profile of the given name exists
+    #[error("Profile '{0}' not found")]
+    ProfileNotFound(String),
+}
+
+impl HookError {
+    /// Returns the stable code of the error, like `DD-HOK-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            HookError::ConfigDirectoryNotFound => "DD-HOK-001",
+            HookError::ReadFailed { .. } => "DD-HOK-002",
+            HookError::ParseFailed { .. } => "DD-HOK-003",
+            HookError::ProfileNotFound(_) => "DD-HOK-004",
+        }
+    }
+}
+
+/// How downloads handled by a profile are identified and filed
+///
+/// Profiles are read from a JSON object mapping profile names to profiles:
+///
+/// ```json
+/// {
+///   "breaking-bad": {
+///     "show": "Breaking Bad",
+///     "library": "/media/tv/Breaking Bad",
+///     "seasons": [5]
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookProfile {
+    /// Name of the TV series the downloads belong to
+    pub show: String,
+
+    /// Directory the identified files are hard linked into
+    pub library: PathBuf,
+
+    /// Seasons to match against (empty matches against all seasons)
+    #[serde(default)]
+    pub seasons: Vec<usize>,
+
+    /// File naming format
+    #[serde(default = "default_format")]
+    pub format: String,
+
+    /// AI backend, e.g. "claude" (defaults to Gemini Flash)
+    #[serde(default)]
+    pub matcher: Option<MatcherType>,
+
+    /// Whisper model name (defaults to "base")
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Log file (defaults to `hook.log` in the data directory)
+    #[serde(default)]
+    pub log: Option<PathBuf>,
+}
+
+/// Returns the default format for profiles without one
+fn default_format() -> String {
+    DEFAULT_FORMAT.to_string()
+}
+
+/// Returns the path of the profiles file in the configuration directory
+pub fn default_profiles_path() -> Result<PathBuf, HookError> {
+    let proj_dirs = directories::ProjectDirs::from("de", "westhoffswelt", "dialogdetective")
+        .ok_or(HookError::ConfigDirectoryNotFound)?;
+    Ok(proj_dirs.config_dir().join(PROFILES_FILE))
+}
+
+/// Returns the path of the hook log in the data directory
+pub fn default_hook_log_path() -> Result<PathBuf, HookError> {
+    let proj_dirs = directories::ProjectDirs::from("de", "westhoffswelt", "dialogdetective")
+        .ok_or(HookError::ConfigDirectoryNotFound)?;
+    Ok(proj_dirs.data_local_dir().join(HOOK_LOG_FILE))
+}
+
+/// Loads a profile by name from a profiles file
+pub fn load_hook_profile(path: &Path, name: &str) -> Result<HookProfile, HookError> {
+    let content = fs::read_to_string(path).map_err(|source| HookError::ReadFailed {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let mut profiles: HashMap<String, HookProfile> =
+        serde_json::from_str(&content).map_err(|source| HookError::ParseFailed {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    profiles
+        .remove(name)
+        .ok_or_else(|| HookError::ProfileNotFound(name.to_string()))
+}
+
+/// Something that happened during a hook run, logged as one JSON line
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HookEvent {
+    /// The hook was called for a download
+    Started { profile: String, path: PathBuf },
+
+    /// A file was identified and linked into the library
+    Linked {
+        source: PathBuf,
+        destination: PathBuf,
+        season: usize,
+        episode: usize,
+        confidence: Option<f64>,
+    },
+
+    /// A file was left alone, e.g. because its match wasn't confident enough
+    Skipped { source: PathBuf, reason: String },
+
+    /// A file, or the whole run if there is no source, failed
+    Failed {
+        source: Option<PathBuf>,
+        error: String,
+    },
+
+    /// The run is over
+    Finished {
+        linked: usize,
+        skipped: usize,
+        failed: usize,
+    },
+}
+
+/// Appends hook events to a log file as JSON lines
+pub struct HookLog {
+    file: File,
+}
+
+impl HookLog {
+    /// Opens a log file for appending, creating it and its directory if needed
+    pub fn open(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    /// Writes an event with the current time in seconds since the Unix epoch
+    pub fn write(&mut self, event: &HookEvent) -> io::Result<()> {
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        writeln!(self.file, "{}", log_line(time, event))
+    }
+}
+
+/// Renders an event as a JSON line with its time
+fn log_line(time: u64, event: &HookEvent) -> String {
+    #[derive(Serialize)]
+    struct Entry<'a> {
+        time: u64,
+        #[serde(flatten)]
+        event: &'a HookEvent,
+    }
+
+    serde_json::to_string(&Entry { time, event }).expect("hook events serialize to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_hook_profile() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_hook_profile");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let path = temp_dir.join(PROFILES_FILE);
+        fs::write(
+            &path,
+            r#"{
+                "bb": {"show": "Breaking Bad", "library": "/tv/bb", "matcher": "claude"},
+                "who": {"show": "Doctor Who", "library": "/tv/who", "seasons": [4]}
+            }"#,
+        )
+        .unwrap();
+
+        let profile = load_hook_profile(&path, "bb").unwrap();
+        assert_eq!(profile.show, "Breaking Bad");
+        assert_eq!(profile.matcher, Some(MatcherType::Claude));
+        assert_eq!(profile.format, DEFAULT_FORMAT);
+        assert!(profile.seasons.is_empty());
+        assert_eq!(load_hook_profile(&path, "who").unwrap().seasons, vec![4]);
+        assert!(matches!(
+            load_hook_profile(&path, "dw"),
+            Err(HookError::ProfileNotFound(_))
+        ));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_log_line() {
+        let event = HookEvent::Skipped {
+            source: PathBuf::from("/downloads/a.mkv"),
+            reason: "low confidence".to_string(),
+        };
+
+        assert_eq!(
+            log_line(1_700_000_000, &event),
+            r#"{"time":1700000000,"event":"skipped","source":"/downloads/a.mkv","reason":"low confidence"}"#
+        );
+    }
+}
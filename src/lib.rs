@@ -4,37 +4,84 @@
 //! analyzing their audio content, and solving the mystery of their true identity.
 
 mod ai_matcher;
+mod assignment;
 mod audio_extraction;
+mod audit;
 mod cache;
+mod cache_encryption;
+mod cache_purge;
+mod cache_remote;
+mod child_process;
+mod cross_check;
+mod doctor;
+mod estimate;
+mod file_lock;
 mod file_operations;
 mod file_resolver;
+mod hash_lookup;
+mod history;
+mod hook;
+mod localization;
 mod metadata_retrieval;
+mod metrics;
+mod plan_diff;
+mod queue;
+mod release_tags;
+mod retry;
+mod season_inference;
+mod show_detection;
+mod sonarr;
 mod speech_to_text;
+mod subtitles;
+mod tagging;
 mod temp;
+mod trakt;
+mod triage;
 
 // Public submodule for model downloading
 pub mod model_downloader;
 
-use ai_matcher::{ClaudeCodeMatcher, EpisodeMatcher, GeminiCliMatcher, NaivePromptGenerator};
-use audio_extraction::audio_from_video;
+// C interface for embedding the engine into non-Rust frontends
+
+use ai_matcher::{
+    ChunkedMatcher, ClaudeCodeMatcher, GeminiCliMatcher, lacks_summaries, missing_summaries,
+};
+use audit::audit_files;
 use cache::CacheStorage;
-use file_resolver::{VideoFile, compute_video_hash, scan_for_videos};
+use cross_check::{RUNTIME_MISMATCH_PENALTY, check_filename, check_runtime, detect_parts};
+use estimate::Estimator;
+use file_resolver::{
+    GROWTH_CHECK_INTERVAL, compute_video_hash, find_files, find_videos, has_download_marker,
+    set_aside_downloads,
+};
+use hash_lookup::{HashMatch, OpenSubtitlesLookup, opensubtitles_hash};
 use metadata_retrieval::{
-    CachedMetadataProvider, Episode, MetadataProvider, TVSeries, TvMazeProvider,
+    CachedMetadataProvider, EnrichedMetadataProvider, FallbackMetadataProvider,
+    LocalizedMetadataProvider, PRIMARY_LANGUAGE, PluginProvider, SummaryProvider,
+    TmdbSummaryProvider, TraktProvider, TvMazeProvider, WikipediaProvider, provider_key,
 };
-use speech_to_text::{Transcript, audio_to_text};
-use std::time::Duration;
+use model_downloader::DownloadProgress;
+use season_inference::{narrow_to_season, season_from_path, transcript_excerpt};
+use show_detection::{confirm_guesses, guess_show_names};
+use std::time::{Duration, Instant, SystemTime};
 
 /// Computes a cache key for matching results
 ///
 /// The cache key is composed of the video hash, show name, season filter,
-/// and matcher type to ensure cached results are only reused when all
-/// matching parameters are identical.
+/// metadata provider and language, and matcher ID and version to ensure
+/// cached results are only reused when all matching parameters are
+/// identical. Matches narrowed down by season inference are keyed
+/// separately from matches against all seasons. The provider key (see
+/// `provider_key`) includes the episode order, as alternate orders number
+/// the same episodes differently.
 fn compute_matching_cache_key(
     video_hash: &str,
     show_name: &str,
     season_filter: &Option<Vec<usize>>,
-    matcher_type: MatcherType,
+    infer_season: bool,
+    metadata_key: &str,
+    matcher_id: &str,
+    matcher_version: u32,
 ) -> String {
     // Sanitize show name (lowercase, replace non-alphanumeric with underscores)
     let sanitized_show = show_name
@@ -60,44 +107,177 @@ fn compute_matching_cache_key(
                 .collect::<Vec<_>>()
                 .join("-")
         }
+        _ if infer_season => "auto".to_string(),
         _ => "all".to_string(),
     };
 
-    // Format matcher type
-    let matcher_str = match matcher_type {
-        MatcherType::Gemini => "gemini",
-        MatcherType::GeminiFlash => "gemini-flash",
-        MatcherType::Claude => "claude",
-    };
-
     format!(
-        "{}_{}_{}_{}",
-        video_hash, sanitized_show, seasons_str, matcher_str
+        "{}_{}_{}_{}_{}_v{}",
+        video_hash, sanitized_show, seasons_str, metadata_key, matcher_id, matcher_version
     )
 }
 
+/// Derives a cache namespace from the root directory of a library
+///
+/// The namespace is the directory's name followed by a short hash of its
+/// full path, like `tv-1a2b3c4d`, so it stays readable while libraries in
+/// directories of the same name get different namespaces. The path is
+/// canonicalized if possible, so relative and absolute paths agree.
+pub fn cache_namespace_for(library: &Path) -> String {
+    let path = library
+        .canonicalize()
+        .unwrap_or_else(|_| library.to_path_buf());
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "library".to_string());
+    let hash = blake3::hash(path.to_string_lossy().as_bytes()).to_hex();
+    format!("{}-{}", name, &hash[..8])
+}
+
 // Re-export error types
 pub use ai_matcher::EpisodeMatchingError;
 pub use audio_extraction::AudioExtractionError;
 pub use cache::CacheError;
 pub use file_operations::FileOperationError;
-pub use file_resolver::FileResolverError;
+pub use file_resolver::{DirectoryScan, FileResolverError, HashOptions, VideoFile};
 pub use metadata_retrieval::MetadataRetrievalError;
-pub use metadata_retrieval::SeriesCandidate;
+pub use model_downloader::ModelDownloadError;
+
+// Re-export metadata types
+pub use metadata_retrieval::{
+    Episode, EpisodeOrder, MetadataProvider, MetadataProviderType, PluginError, ProviderPlugin,
+    Season, SeriesCandidate, SummarySource, TVSeries, default_plugins_path, load_provider_plugin,
+};
 pub use speech_to_text::SpeechToTextError;
 
+// Re-export transcription types
+pub use model_downloader::ModelSpec;
+#[cfg(feature = "whisper")]
+pub use speech_to_text::WhisperTranscriber;
+pub use speech_to_text::{
+    SPEAKER_TURN_MARKER, Transcriber, Transcript, TranscriptEvidence, TranscriptQuality,
+    TranscriptReuse, TranscriptSegment, TranscriptSource,
+};
+
+// Re-export matching types
+pub use ai_matcher::{
+    DEFAULT_MAX_EPISODES_PER_PROMPT, EpisodeCandidate, EpisodeMatcher, MatchOutcome,
+    MatcherCommand, NaivePromptGenerator, ParsedMatch, SeasonOutcome, SinglePromptGenerator,
+    TokenUsage, extract_json_block, parse_episode_response, parse_season_response,
+};
+
+// Re-export assignment types
+pub use assignment::{EpisodeAssignment, assign_episodes};
+
+// Re-export cross-check types
+pub use cross_check::{EpisodePart, FilenameConflict, RuntimeMismatch};
+
+// Re-export child process functions
+#[cfg(unix)]
+pub use child_process::{kill_child_processes, kill_child_processes_on_exit};
+
+// Re-export retry types
+pub use retry::{RetryPolicy, TransientError, gave_up_retrying, is_transient};
+
+// Re-export season inference types
+pub use season_inference::SeasonSource;
+
+// Re-export show detection types
+pub use show_detection::{ShowDetection, ShowGuess};
+
+// Re-export library audit types
+pub use audit::{DuplicateEpisode, LibraryAudit, UnknownEpisodeFile};
+
+// Re-export estimation types
+pub use estimate::CaseEstimate;
+
+// Re-export localization types
+pub use localization::{Language, localize};
+
+// Re-export metrics types
+pub use metrics::{RunMetrics, RunSummary, serve_metrics};
+
+// Re-export health check types
+pub use doctor::{CheckStatus, HealthCheck, check_matcher, run_health_checks};
+
+// Re-export triage types
+pub use triage::{Triage, TriageThresholds, triage_matches};
+
 // Re-export file operations types
 pub use file_operations::{
-    PlannedOperation, detect_duplicates, execute_copy, execute_rename, format_filename,
-    plan_operations, sanitize_filename,
+    CopyOptions, CopyProgress, DEFAULT_FORMAT, DUPLICATES_DIR, DuplicatePolicy,
+    DuplicateResolution, FORMAT_PLACEHOLDERS, FilenameMetadata, FilenameStyle, FormatError,
+    FormatWarning, NamingOptions, PlannedOperation, RenameOptions, apply_duplicate_policy,
+    apply_filename_style, check_format, check_free_space, detect_duplicates, execute_copy,
+    execute_copy_with, execute_hardlink, execute_rename, execute_rename_atomic,
+    execute_rename_with, format_filename, format_filename_with, plan_operations,
+    plan_operations_with, plan_quarantine, sanitize_filename, set_air_date_mtimes, validate_format,
+};
+
+// Re-export download hook types
+pub use hook::{
+    HookError, HookEvent, HookLog, HookProfile, default_hook_log_path, default_profiles_path,
+    load_hook_profile,
+};
+
+// Re-export cache purge types
+pub use cache_purge::{CachePurge, purge_show_cache, purge_video_cache};
+
+// Re-export plan diff types
+pub use plan_diff::{
+    PlanChange, PlanDiff, PlanDiffError, PlanEntry, SavedPlan, default_plans_dir, diff_plan,
+    load_plan, save_plan,
 };
 
+// Re-export history types
+pub use history::{
+    History, HistoryAction, HistoryEntry, HistoryError, HistoryQuery, default_history_path,
+};
+
+// Re-export job queue types
+pub use queue::{JobQueue, QueueError, QueuedJob, default_queue_path};
+
+// Re-export Sonarr integration types
+pub use sonarr::{SonarrClient, SonarrError, SonarrImport, SonarrImportMode};
+
+// Re-export Trakt integration types
+pub use trakt::{TraktClient, TraktCollection, TraktDeviceCode, TraktError};
+
+// Re-export release tag parsing
+pub use release_tags::{ReleaseTags, parse_release_tags};
+
+// Re-export tagging and subtitle functions
+pub use subtitles::{SubtitleMode, write_subtitles};
+pub use tagging::write_tags;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, mpsc};
+use std::thread;
 use thiserror::Error;
 
+/// Default number of matcher calls running at the same time
+///
+/// Kept low so that runs stay within the rate limits of the AI backends.
+pub const DEFAULT_PARALLEL_MATCHES: usize = 2;
+
+/// Default quality score below which a transcript is made again with the
+/// fallback transcriber (see `Investigation::quality_fallback`)
+pub const DEFAULT_MIN_TRANSCRIPT_QUALITY: f32 = 0.5;
+
+/// Default match confidence below which a video is escalated to the next
+/// transcriber (see `Investigation::escalate_to`)
+pub const DEFAULT_ESCALATION_CONFIDENCE: f64 = 0.8;
+
 /// AI matcher type selection
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Deserializes from kebab-case names like `gemini-flash`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum MatcherType {
     /// Use Gemini CLI for episode matching
     Gemini,
@@ -107,11 +287,42 @@ pub enum MatcherType {
     Claude,
 }
 
+/// Creates the episode matcher for the given matcher type
+///
+/// The AI CLI is run as configured by `command`, and its calls are stopped
+/// after `timeout`, if given.
+fn build_matcher(
+    matcher_type: MatcherType,
+    command: &MatcherCommand,
+    timeout: Option<Duration>,
+) -> Box<dyn EpisodeMatcher> {
+    let prompt_generator = NaivePromptGenerator::default();
+    let command = command.clone();
+    match matcher_type {
+        MatcherType::Gemini => Box::new(
+            GeminiCliMatcher::new(prompt_generator, None)
+                .command(command)
+                .timeout(timeout),
+        ),
+        MatcherType::GeminiFlash => Box::new(
+            GeminiCliMatcher::new(prompt_generator, Some("gemini-2.5-flash".to_string()))
+                .command(command)
+                .timeout(timeout),
+        ),
+        MatcherType::Claude => Box::new(
+            ClaudeCodeMatcher::new(prompt_generator)
+                .command(command)
+                .timeout(timeout),
+        ),
+    }
+}
+
 /// Progress event emitted during investigation
 ///
 /// These events allow library users to track progress and provide feedback
 /// during the investigation process.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event")]
 pub enum ProgressEvent {
     /// Investigation started
     Started {
@@ -119,21 +330,67 @@ pub enum ProgressEvent {
         show_name: String,
     },
 
+    /// The Whisper model is being downloaded
+    ///
+    /// Only emitted by investigations created with
+    /// `Investigation::with_model`, before the metadata is fetched.
+    ModelDownload { progress: DownloadProgress },
+
+    /// The language of the show's audio was detected
+    ///
+    /// Emitted with `LanguageStrategy::Detected` after transcribing the
+    /// first video, before the metadata is fetched in that language.
+    LanguageDetected {
+        video_path: PathBuf,
+        /// ISO 639-1 code of the detected language
+        language: String,
+    },
+
     /// Fetching episode metadata
     FetchingMetadata { show_name: String },
 
     /// Metadata successfully fetched
     MetadataFetched {
         series_name: String,
+        /// Premiere year of the series, if known
+        year: Option<u16>,
         season_count: usize,
     },
 
+    /// Most episodes of the series have no summary
+    ///
+    /// Emitted after the metadata was fetched if the share of episodes
+    /// without a summary is too large to match by summaries. Such series
+    /// are matched by episode titles and air dates instead, which is less
+    /// reliable.
+    SummariesMissing { missing: usize, total: usize },
+
     /// Scanning directory for video files
     ScanningVideos,
 
+    /// A directory was scanned for video files
+    ///
+    /// Emitted between `ScanningVideos` and `VideosFound` for every
+    /// directory, so that scans of large trees show their progress.
+    DirectoryScanned { scan: DirectoryScan },
+
     /// Video files found
     VideosFound { count: usize },
 
+    /// A file was set aside instead of being investigated
+    ///
+    /// Emitted after `VideosFound` for files that are still being
+    /// downloaded, and for files with a video extension that were
+    /// rejected. Files that simply aren't videos are only reported in the
+    /// outcomes, and unreadable ones as `ScanWarning`.
+    FileSkipped { path: PathBuf, reason: SkipReason },
+
+    /// A file or directory couldn't be read while scanning
+    ///
+    /// Emitted after `VideosFound`. The path was skipped and the scan went
+    /// on with the rest of the tree.
+    ScanWarning { path: PathBuf, message: String },
+
     /// Processing a specific video file
     ProcessingVideo {
         index: usize,
@@ -144,8 +401,24 @@ pub enum ProgressEvent {
     /// Computing hash of video file
     Hashing { video_path: PathBuf },
 
+    /// Part of the video file was hashed
+    ///
+    /// Reported about once per percent. Files hashed with `HashOptions::mmap`
+    /// only report the start and the end.
+    HashingProgress {
+        video_path: PathBuf,
+        bytes_done: u64,
+        total_bytes: u64,
+    },
+
     /// Hash computation finished
-    HashingFinished { video_path: PathBuf },
+    HashingFinished {
+        video_path: PathBuf,
+        /// Size of the hashed file in bytes
+        bytes: u64,
+        /// Time spent reading and hashing the file
+        duration: Duration,
+    },
 
     /// Extracting audio from video
     AudioExtraction {
@@ -178,6 +451,79 @@ pub enum ProgressEvent {
         language: String,
     },
 
+    /// Quality of a video's transcript, reported for fresh and cached ones
+    TranscriptScored {
+        video_path: PathBuf,
+        quality: TranscriptQuality,
+    },
+
+    /// The transcript scored below the minimum quality, so the video is
+    /// transcribed again with the fallback transcriber
+    ///
+    /// The better of both transcripts is used.
+    TranscriptionRetried {
+        video_path: PathBuf,
+        /// Quality score of the first transcript
+        score: f32,
+        /// Model of the fallback transcriber, if it reports one
+        model: Option<String>,
+    },
+
+    /// The match of a video is too uncertain, so it is transcribed and
+    /// matched again with the next transcriber of the escalation ladder
+    ///
+    /// Emitted after all videos were matched once. The more confident of
+    /// both matches is kept.
+    Escalating {
+        video_path: PathBuf,
+        /// Confidence of the match so far
+        confidence: f64,
+        /// Step of the ladder, counted from 1
+        step: usize,
+        /// Model of the step's transcriber, if it reports one
+        model: Option<String>,
+    },
+
+    /// The transcript has too little dialogue to match (see
+    /// `Transcript::has_dialogue`), so the video is left unmatched without
+    /// asking the matcher
+    NoDialogue {
+        video_path: PathBuf,
+        /// Spoken words found in the transcript
+        words: usize,
+    },
+
+    /// Episode identified by the file hash, skipping transcription and matching
+    HashMatched {
+        video_path: PathBuf,
+        episode: Episode,
+    },
+
+    /// Season of a video inferred ahead of matching
+    SeasonInferred {
+        video_path: PathBuf,
+        season: usize,
+        source: SeasonSource,
+    },
+
+    /// A prompt was sent to the matcher and answered
+    ///
+    /// Only emitted if enabled with `Investigation::capture_prompts`, as it
+    /// carries the full prompt (including the transcript) and response.
+    PromptExchanged {
+        video_path: PathBuf,
+        kind: PromptKind,
+        prompt: String,
+        response: String,
+    },
+
+    /// Video handed to the matching workers
+    ///
+    /// Matching runs in the background while later videos are processed, so
+    /// the matching events of a video may arrive between the events of
+    /// other videos.
+    MatchingQueued { video_path: PathBuf },
+
     /// Matching video to an episode
     Matching {
         index: usize,
@@ -197,21 +543,270 @@ pub enum ProgressEvent {
         episode: Episode,
     },
 
+    /// A processing stage of a video finished
+    ///
+    /// Reported in addition to the stage-specific events, carrying the
+    /// wall-clock time the stage took. Not emitted for cache hits.
+    StageCompleted { stage: Stage, duration: Duration },
+
+    /// An unreadable cache entry was moved aside
+    ///
+    /// The entry was treated as missing, so its data was computed again.
+    /// Emitted before `Complete` for every entry found during the run.
+    CacheEntryQuarantined { path: PathBuf },
+
     /// Investigation complete
     Complete { match_count: usize },
 }
 
+/// Purpose of a prompt sent to the matcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum PromptKind {
+    /// Matching a transcript to an episode
+    EpisodeMatch,
+    /// Inferring the season from a transcript excerpt
+    SeasonInference,
+}
+
+/// Processing stage of a single video file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Stage {
+    /// Computing the content hash of the video
+    Hashing,
+    /// Extracting the audio track
+    AudioExtraction,
+    /// Transcribing the audio to text
+    Transcription,
+    /// Matching the transcript to an episode
+    Matching,
+}
+
+/// Wall-clock time spent in each processing stage of a video file
+///
+/// Stages that did not run, e.g. because their result was cached, are `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct StageTimings {
+    /// Time spent computing the content hash
+    pub hashing: Option<Duration>,
+
+    /// Time spent extracting the audio track
+    pub audio_extraction: Option<Duration>,
+
+    /// Time spent transcribing the audio
+    pub transcription: Option<Duration>,
+
+    /// Time spent matching the transcript to an episode
+    pub matching: Option<Duration>,
+}
+
+impl StageTimings {
+    /// Returns the time spent in the given stage, if it ran
+    pub fn get(&self, stage: Stage) -> Option<Duration> {
+        match stage {
+            Stage::Hashing => self.hashing,
+            Stage::AudioExtraction => self.audio_extraction,
+            Stage::Transcription => self.transcription,
+            Stage::Matching => self.matching,
+        }
+    }
+
+    /// Adds the given duration to a stage
+    ///
+    /// Durations recorded multiple times for the same stage are summed up,
+    /// which also allows aggregating the timings of several files.
+    pub fn record(&mut self, stage: Stage, duration: Duration) {
+        let slot = match stage {
+            Stage::Hashing => &mut self.hashing,
+            Stage::AudioExtraction => &mut self.audio_extraction,
+            Stage::Transcription => &mut self.transcription,
+            Stage::Matching => &mut self.matching,
+        };
+        *slot = Some(slot.unwrap_or_default() + duration);
+    }
+
+    /// Returns the total time spent in all stages
+    pub fn total(&self) -> Duration {
+        [
+            self.hashing,
+            self.audio_extraction,
+            self.transcription,
+            self.matching,
+        ]
+        .into_iter()
+        .flatten()
+        .sum()
+    }
+}
+
 /// Represents the result of matching a video file to an episode
 ///
 /// This structure contains the "evidence" that correlates a video file
 /// with a specific episode from a TV series.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MatchResult {
     /// The video file that was matched
     pub video: VideoFile,
 
+    /// BLAKE3 hash of the video file's content
+    pub video_hash: String,
+
     /// The episode that was matched
     pub episode: Episode,
+
+    /// The matcher's confidence in the match (0.0 to 1.0)
+    ///
+    /// None if the matcher didn't report it, or for matches cached by
+    /// earlier versions.
+    pub confidence: Option<f64>,
+
+    /// Other plausible episodes reported by the matcher, best first
+    pub alternatives: Vec<EpisodeCandidate>,
+
+    /// The matcher's one-sentence justification of the match
+    ///
+    /// None if the matcher didn't give one, or for matches cached by
+    /// earlier versions.
+    pub reasoning: Option<String>,
+
+    /// Episodes directly following `episode` in the same video
+    ///
+    /// Non-empty for multi-episode files, which are named after all of
+    /// their episodes (`S01E01-E02 - Title1 & Title2`).
+    pub extra_episodes: Vec<Episode>,
+
+    /// The episode stated in the file name, if it disagrees with the match
+    pub filename_conflict: Option<FilenameConflict>,
+
+    /// The video's duration and the episode's runtime, if they differ wildly
+    ///
+    /// The confidence of such matches is lowered. Only checked if both the
+    /// duration (measured during transcription) and the runtime are known.
+    pub runtime_mismatch: Option<RuntimeMismatch>,
+
+    /// Duration of the video, measured during transcription
+    ///
+    /// None for videos identified by their file hash.
+    pub video_duration: Option<Duration>,
+
+    /// The part of the episode this video holds, if the episode is split
+    ///
+    /// Set for confident matches of the same episode whose durations add
+    /// up to its runtime. Parts are named `- Part N` instead of being
+    /// treated as duplicates, and are not checked for a runtime mismatch.
+    pub part: Option<EpisodePart>,
+
+    /// The transcript the match was based on
+    ///
+    /// Only included if requested with `Investigation::include_transcripts`,
+    /// and never for videos identified by their file hash.
+    pub transcript: Option<TranscriptEvidence>,
+
+    /// Quality of the transcript the match was based on
+    ///
+    /// None for videos identified by their file hash.
+    pub transcript_quality: Option<TranscriptQuality>,
+
+    /// Time spent in each processing stage of the video
+    pub timings: StageTimings,
+
+    /// Tokens consumed by matching this video (None for cached matches or
+    /// if the AI backend didn't report usage)
+    pub usage: Option<TokenUsage>,
+}
+
+/// What happened to a file of an investigation
+///
+/// `Investigation::run_outcomes` returns one for every file found, so each
+/// of them can be accounted for instead of inferring failures from missing
+/// matches.
+#[derive(Debug)]
+pub enum InvestigationOutcome {
+    /// The video was matched to an episode
+    Matched(Box<MatchResult>),
+
+    /// The video was transcribed, but no episode could be matched to it
+    Unmatched {
+        path: PathBuf,
+        reason: UnmatchedReason,
+        /// The transcript, if requested with `Investigation::include_transcripts`
+        transcript: Option<TranscriptEvidence>,
+    },
+
+    /// The file wasn't investigated
+    Skipped { path: PathBuf, reason: SkipReason },
+
+    /// Investigating the video failed, e.g. because it couldn't be read
+    Failed {
+        path: PathBuf,
+        error: DialogDetectiveError,
+    },
+}
+
+impl InvestigationOutcome {
+    /// Returns the path of the file
+    pub fn path(&self) -> &Path {
+        match self {
+            InvestigationOutcome::Matched(result) => &result.video.path,
+            InvestigationOutcome::Unmatched { path, .. }
+            | InvestigationOutcome::Skipped { path, .. }
+            | InvestigationOutcome::Failed { path, .. } => path,
+        }
+    }
+}
+
+/// Why no episode could be matched to a video
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum UnmatchedReason {
+    /// The transcript has no dialogue to match
+    NoDialogue,
+
+    /// The matcher found no episode fitting the dialogue
+    NoMatchFound { response: String },
+}
+
+impl fmt::Display for UnmatchedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnmatchedReason::NoDialogue => write!(f, "no dialogue found"),
+            UnmatchedReason::NoMatchFound { .. } => write!(f, "no matching episode found"),
+        }
+    }
+}
+
+/// Why a file wasn't investigated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SkipReason {
+    /// The file's content isn't a video
+    NotAVideo,
+
+    /// The file is marked as still being downloaded, like `episode.mkv.part`
+    Downloading,
+
+    /// The file grew while it was watched, so it is still being written
+    StillGrowing,
+
+    /// The file has a video extension, but ffprobe found no video stream
+    NoVideoStream,
+
+    /// The file has a video extension, but its content isn't recognized
+    /// and ffprobe isn't available to check it
+    Unverified,
+
+    /// The file or directory couldn't be read, e.g. for lack of permission
+    Unreadable,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::NotAVideo => write!(f, "not a video"),
+            SkipReason::Downloading => write!(f, "download in progress"),
+            SkipReason::StillGrowing => write!(f, "still being written"),
+            SkipReason::NoVideoStream => write!(f, "no video stream found"),
+            SkipReason::Unverified => write!(f, "unrecognized video, ffprobe unavailable"),
+            SkipReason::Unreadable => write!(f, "unreadable"),
+        }
+    }
 }
 
 /// Top-level error type for DialogDetective operations
@@ -229,6 +824,10 @@ pub enum DialogDetectiveError {
     #[error("Speech-to-text error: {0}")]
     SpeechToText(#[from] SpeechToTextError),
 
+    /// Error while downloading the Whisper model
+    #[error("Model download error: {0}")]
+    ModelDownload(#[from] ModelDownloadError),
+
     /// Error during metadata retrieval
     #[error("Metadata retrieval error: {0}")]
     MetadataRetrieval(#[from] MetadataRetrievalError),
@@ -245,105 +844,232 @@ pub enum DialogDetectiveError {
     #[error("Series selection cancelled")]
     SelectionCancelled,
 
+    /// More videos were found than the limit, and going on wasn't confirmed
+    #[error("Found {count} videos, more than the limit of {limit}")]
+    TooManyFiles { count: usize, limit: usize },
+
     /// IO error
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
 }
 
-/// Investigates a directory for video files and matches them to episodes
-///
-/// This function scans the given directory recursively for video files,
-/// extracts audio from each video, transcribes the audio to text using Whisper,
-/// fetches episode metadata for the given show, and uses AI to match each video
-/// to its corresponding episode.
-///
-/// Progress events are emitted through the provided callback, allowing library
-/// users to track progress, display status, or remain silent.
-///
-/// # Arguments
-///
-/// * `directory` - The directory path to investigate
-/// * `model_path` - Path to the Whisper model file (e.g., ggml-base.bin)
-/// * `show_name` - The name of the TV show to fetch metadata for
-/// * `season_filter` - Optional list of season numbers to filter (None fetches all seasons)
-/// * `matcher_type` - The AI matcher to use (Gemini or Claude)
-/// * `progress_callback` - Closure called with progress events (can be empty for silent operation)
-///
-/// # Returns
-///
-/// A vector of `MatchResult` containing the matched video files and their episodes
+impl DialogDetectiveError {
+    /// Returns the stable code of the error, like `DD-AUD-001`
+    ///
+    /// Errors of the pipeline stages return the code of the underlying
+    /// error. Codes are meant for branching on failures in wrappers: unlike
+    /// the messages, they stay the same between releases.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DialogDetectiveError::FileResolver(error) => error.code(),
+            DialogDetectiveError::AudioExtraction(error) => error.code(),
+            DialogDetectiveError::SpeechToText(error) => error.code(),
+            DialogDetectiveError::ModelDownload(error) => error.code(),
+            DialogDetectiveError::MetadataRetrieval(error) => error.code(),
+            DialogDetectiveError::Cache(error) => error.code(),
+            DialogDetectiveError::EpisodeMatching(error) => error.code(),
+            DialogDetectiveError::SelectionCancelled => "DD-GEN-001",
+            DialogDetectiveError::TooManyFiles { .. } => "DD-GEN-002",
+            DialogDetectiveError::Io(error) if gave_up_retrying(error) => "DD-IO-002",
+            DialogDetectiveError::Io(_) => "DD-IO-001",
+        }
+    }
+}
+
+/// Options for fetching show metadata
+#[derive(Debug, Clone)]
+pub struct MetadataOptions {
+    /// Optional list of season numbers to fetch (None fetches all seasons)
+    pub season_filter: Option<Vec<usize>>,
+
+    /// Provider-specific ID of the series to fetch (e.g. from `search_shows`)
+    ///
+    /// If None, the best-ranked search result for the show name is used.
+    pub series_id: Option<u64>,
+
+    /// Whether to read from and write to the on-disk metadata caches
+    pub use_cache: bool,
+
+    /// The primary metadata provider
+    ///
+    /// Series IDs are provider-specific, so `series_id` has to come from
+    /// the same provider.
+    pub provider: MetadataProviderType,
+
+    /// Language of the episode titles and summaries as an ISO 639-1 code
+    ///
+    /// Requires a TMDB API key; see `LanguageStrategy`. None keeps the
+    /// primary provider's English metadata.
+    pub language: Option<String>,
+
+    /// Language of the episodes' `local_title`, as an ISO 639-1 code
+    ///
+    /// Lets files be named in another language than the metadata is in,
+    /// e.g. with `{title_local}`. Requires a TMDB API key.
+    pub title_language: Option<String>,
+
+    /// Order the episodes are numbered in, e.g. the DVD order
+    ///
+    /// Only TVMaze has alternate orders; Trakt always uses its default one.
+    pub episode_order: EpisodeOrder,
+}
+
+impl Default for MetadataOptions {
+    fn default() -> Self {
+        Self {
+            season_filter: None,
+            series_id: None,
+            use_cache: true,
+            provider: MetadataProviderType::TvMaze,
+            language: None,
+            title_language: None,
+            episode_order: EpisodeOrder::Default,
+        }
+    }
+}
+
+/// Which language the episode titles and summaries are fetched in
 ///
-/// # Examples
+/// Matching works best when the matcher compares dialogue with summaries in
+/// the same language. TVMaze only has English metadata, so other languages
+/// are taken from TMDB, which requires `TMDB_API_KEY` to be set. Without
+/// the key, or for episodes TMDB has no translation of, the English
+/// metadata is used.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LanguageStrategy {
+    /// The primary provider's metadata, in English
+    #[default]
+    Original,
+
+    /// Metadata in the given language, as an ISO 639-1 code like "de"
+    Fixed(String),
+
+    /// Metadata in the language Whisper detects in the first video
+    ///
+    /// The first video is transcribed before the metadata is fetched. Its
+    /// transcript is cached, so it isn't transcribed twice.
+    Detected,
+}
+
+/// Builds the default metadata provider stack
 ///
-/// ```no_run
-/// use dialog_detective::{investigate_case, ProgressEvent, MatcherType};
-/// use std::path::Path;
+/// The options' provider is used as the primary one: TVMaze in the options'
+/// episode order, Trakt, or a provider plugin, with Wikipedia as the last
+/// resort for series they don't find. See `stacked_metadata_provider` for
+/// the layers added on top of each.
+fn default_metadata_provider(
+    options: &MetadataOptions,
+) -> Result<Box<dyn MetadataProvider>, DialogDetectiveError> {
+    let primary = match &options.provider {
+        MetadataProviderType::TvMaze => stacked_metadata_provider(
+            TvMazeProvider::new().with_order(options.episode_order),
+            options,
+        )?,
+        MetadataProviderType::Trakt => {
+            let trakt = TraktProvider::from_env().ok_or_else(TraktProvider::missing_client_id)?;
+            stacked_metadata_provider(trakt, options)?
+        }
+        MetadataProviderType::Wikipedia => {
+            return stacked_metadata_provider(WikipediaProvider::new(), options);
+        }
+        MetadataProviderType::Plugin(plugin) => {
+            stacked_metadata_provider(PluginProvider::new(plugin.clone()), options)?
+        }
+    };
+    let last_resort = stacked_metadata_provider(WikipediaProvider::new(), options)?;
+
+    Ok(Box::new(FallbackMetadataProvider::new(vec![
+        primary,
+        last_resort,
+    ])))
+}
+
+/// Adds localization, enrichment, and caching to a primary provider
 ///
-/// // With progress output and season filtering
-/// let matches = investigate_case(
-///     Path::new("/path/to/videos"),
-///     Path::new("models/ggml-base.bin"),
-///     "Breaking Bad",
-///     Some(vec![1, 2]),  // Only seasons 1 and 2
-///     MatcherType::Gemini,
-///     |event| {
-///         match event {
-///             ProgressEvent::ProcessingVideo { index, total, video_path } => {
-///                 println!("[{}/{}] Processing: {}", index, total, video_path.display());
-///             }
-///             _ => {} // Handle other events as needed
-///         }
-///     }
-/// ).unwrap();
+/// Titles and summaries are localized to the options' language, titles in
+/// the title language are added as local titles, and missing summaries are
+/// filled from secondary sources (all TMDB, if an API key is configured).
+/// The result is optionally wrapped with the search and metadata caches.
+fn stacked_metadata_provider<P>(
+    provider: P,
+    options: &MetadataOptions,
+) -> Result<Box<dyn MetadataProvider>, DialogDetectiveError>
+where
+    P: MetadataProvider + 'static,
+{
+    let language = options
+        .language
+        .clone()
+        .filter(|language| language != PRIMARY_LANGUAGE);
+    let title_language = options.title_language.clone();
+    match (language, title_language, TmdbSummaryProvider::from_env()) {
+        (None, None, _) | (_, _, None) => {
+            with_metadata_caches(enriched_metadata_provider(provider), options.use_cache)
+        }
+        (language, title_language, Some(localizer)) => {
+            let localized = LocalizedMetadataProvider::new(
+                provider,
+                Box::new(localizer),
+                language,
+                title_language,
+            );
+            with_metadata_caches(enriched_metadata_provider(localized), options.use_cache)
+        }
+    }
+}
+
+/// Wraps a provider to fill missing summaries from secondary sources
 ///
-/// // Silent operation with all seasons
-/// let matches = investigate_case(
-///     Path::new("/path/to/videos"),
-///     Path::new("models/ggml-base.bin"),
-///     "Breaking Bad",
-///     None,  // All seasons
-///     MatcherType::Claude,
-///     |_| {} // Ignore all progress events
-/// ).unwrap();
-/// ```
-pub fn investigate_case<F, S>(
-    directory: &Path,
-    model_path: &Path,
-    show_name: &str,
-    season_filter: Option<Vec<usize>>,
-    matcher_type: MatcherType,
-    mut progress_callback: F,
-    select_series: S,
-) -> Result<Vec<MatchResult>, DialogDetectiveError>
+/// TMDB is used if an API key is configured.
+fn enriched_metadata_provider<P>(provider: P) -> EnrichedMetadataProvider<P>
 where
-    F: FnMut(ProgressEvent),
-    S: FnOnce(&[SeriesCandidate]) -> Result<usize, DialogDetectiveError>,
+    P: MetadataProvider,
 {
-    progress_callback(ProgressEvent::Started {
-        directory: directory.to_path_buf(),
-        show_name: show_name.to_string(),
-    });
+    let summary_providers: Vec<Box<dyn SummaryProvider>> = TmdbSummaryProvider::from_env()
+        .into_iter()
+        .map(|p| Box::new(p) as Box<dyn SummaryProvider>)
+        .collect();
+    EnrichedMetadataProvider::new(provider, summary_providers)
+}
 
-    // Fetch episode metadata with caching
-    progress_callback(ProgressEvent::FetchingMetadata {
-        show_name: show_name.to_string(),
-    });
+/// Wraps a provider with the search and metadata caches, if enabled
+fn with_metadata_caches<P>(
+    provider: P,
+    use_cache: bool,
+) -> Result<Box<dyn MetadataProvider>, DialogDetectiveError>
+where
+    P: MetadataProvider + 'static,
+{
+    if !use_cache {
+        return Ok(Box::new(provider));
+    }
 
     // Initialize caches with 1-day TTL (24 hours)
     let one_day = Some(Duration::from_secs(24 * 60 * 60));
     let search_cache = CacheStorage::<Vec<SeriesCandidate>>::open("search", one_day)?;
     let metadata_cache = CacheStorage::<TVSeries>::open("metadata", one_day)?;
-    let transcript_cache = CacheStorage::<Transcript>::open("transcripts", one_day)?;
-    let matching_cache = CacheStorage::<Episode>::open("matching", one_day)?;
-
-    // Clean expired caches at startup
-    transcript_cache.clean()?;
-    matching_cache.clean()?;
 
     // Wrap the provider with caching
-    let tvmaze_provider = TvMazeProvider::new();
-    let provider = CachedMetadataProvider::new(tvmaze_provider, search_cache, metadata_cache);
+    Ok(Box::new(CachedMetadataProvider::new(
+        provider,
+        search_cache,
+        metadata_cache,
+    )))
+}
 
+/// Searches for the show and fetches the series selected by the caller
+///
+/// A single search result is selected automatically, otherwise
+/// `select_series` is asked to choose one of the candidates.
+fn fetch_selected_series<S>(
+    provider: &dyn MetadataProvider,
+    show_name: &str,
+    season_filter: &Option<Vec<usize>>,
+    select_series: S,
+) -> Result<TVSeries, DialogDetectiveError>
+where
+    S: FnOnce(&[SeriesCandidate]) -> Result<usize, DialogDetectiveError>,
+{
     // Search for series candidates and let the caller select one
     let candidates = provider.search_series(show_name)?;
 
@@ -356,136 +1082,2296 @@ where
         &candidates[index]
     };
 
-    let series = provider.fetch_series(selected_candidate, season_filter.clone())?;
+    Ok(provider.fetch_series(selected_candidate, season_filter.clone())?)
+}
 
-    progress_callback(ProgressEvent::MetadataFetched {
-        series_name: series.name.clone(),
-        season_count: series.seasons.len(),
-    });
+/// Searches the metadata provider for shows matching the given name
+///
+/// Returns up to 10 candidates sorted by relevance. Results are cached for
+/// 24 hours. Use the `id` of a candidate as `MetadataOptions::series_id` to
+/// fetch a specific show with `fetch_show_metadata`.
+pub fn search_shows(show_name: &str) -> Result<Vec<SeriesCandidate>, DialogDetectiveError> {
+    let provider = default_metadata_provider(&MetadataOptions::default())?;
+    Ok(provider.search_series(show_name)?)
+}
 
-    // Scan directory for video files
-    progress_callback(ProgressEvent::ScanningVideos);
-    let videos = scan_for_videos(directory)?;
+/// Detects the show of the videos in a directory from their names
+///
+/// Show names are read from the video file names (`Breaking.Bad.S01E02.mkv`)
+/// and from the directory and its parents (`Doctor Who (2005)/Season 4`).
+/// They are confirmed by searching the metadata provider, which has to know
+/// a series by exactly that name. The candidates of the first confirmed
+/// name are returned; if there are several, the caller has to choose.
+pub fn detect_show(video_dir: &Path) -> Result<ShowDetection, DialogDetectiveError> {
+    let video_paths: Vec<PathBuf> = find_videos(video_dir, &RetryPolicy::default())?
+        .into_iter()
+        .map(|video| video.path)
+        .collect();
+    let guesses = guess_show_names(video_dir, &video_paths);
+
+    let provider = default_metadata_provider(&MetadataOptions::default())?;
+    let candidates = confirm_guesses(provider.as_ref(), &guesses)?;
+    Ok(ShowDetection {
+        guesses,
+        candidates,
+    })
+}
 
-    if videos.is_empty() {
-        progress_callback(ProgressEvent::VideosFound { count: 0 });
-        return Ok(Vec::new());
-    }
+/// Compares an organized library with the episode list of its show
+///
+/// Reads the season and episode numbers from the names of the videos in
+/// the library (`S01E02`, `1x02`, `S04E12-E13`), fetches the show's
+/// episodes like `fetch_show_metadata`, and reports the episodes that are
+/// missing or stored more than once, and the files that don't name a real
+/// episode. Nothing is transcribed or matched, so the names have to be
+/// right; use an investigation to identify misnamed files.
+pub fn audit_library(
+    library: &Path,
+    show_name: &str,
+    options: &MetadataOptions,
+) -> Result<LibraryAudit, DialogDetectiveError> {
+    let series = fetch_show_metadata(show_name, options)?;
+    let paths: Vec<PathBuf> = find_videos(library, &RetryPolicy::default())?
+        .into_iter()
+        .map(|video| video.path)
+        .collect();
+
+    Ok(audit_files(&series, &paths, SystemTime::now()))
+}
 
-    progress_callback(ProgressEvent::VideosFound {
-        count: videos.len(),
-    });
+/// Fetches the episode metadata of a TV show
+///
+/// This is the metadata half of `investigate_case` as a standalone function:
+/// it searches TVMaze for the show, fills in missing episode summaries from
+/// secondary sources, and returns the series with all (or the filtered)
+/// seasons. Search results and metadata are cached for 24 hours unless
+/// caching is disabled in the options.
+///
+/// # Arguments
+///
+/// * `show_name` - The name of the TV show to fetch metadata for
+/// * `options` - Season filter, optional series ID, and caching behaviour
+///
+/// # Examples
+///
+/// ```no_run
+/// use dialog_detective::{fetch_show_metadata, MetadataOptions};
+///
+/// let series = fetch_show_metadata(
+///     "Breaking Bad",
+///     &MetadataOptions {
+///         season_filter: Some(vec![1]),
+///         ..Default::default()
+///     },
+/// ).unwrap();
+///
+/// for season in &series.seasons {
+///     println!("Season {}: {} episodes", season.season_number, season.episodes.len());
+/// }
+/// ```
+pub fn fetch_show_metadata(
+    show_name: &str,
+    options: &MetadataOptions,
+) -> Result<TVSeries, DialogDetectiveError> {
+    let provider = default_metadata_provider(options)?;
+    let candidates = provider.search_series(show_name)?;
 
-    // Initialize the matcher based on the selected type
-    let prompt_generator = NaivePromptGenerator::default();
-    let matcher: Box<dyn EpisodeMatcher> = match matcher_type {
-        MatcherType::Gemini => Box::new(GeminiCliMatcher::new(prompt_generator, None)),
-        MatcherType::GeminiFlash => Box::new(GeminiCliMatcher::new(
-            prompt_generator,
-            Some("gemini-2.5-flash".to_string()),
-        )),
-        MatcherType::Claude => Box::new(ClaudeCodeMatcher::new(prompt_generator)),
+    let candidate = match options.series_id {
+        Some(id) => candidates.iter().find(|c| c.id == id).ok_or_else(|| {
+            MetadataRetrievalError::SeriesNotFound(format!("{} (id {})", show_name, id))
+        })?,
+        // Candidates are sorted by relevance, so the first one is the best guess
+        None => candidates
+            .first()
+            .ok_or_else(|| MetadataRetrievalError::SeriesNotFound(show_name.to_string()))?,
     };
 
-    let mut match_results = Vec::new();
+    Ok(provider.fetch_series(candidate, options.season_filter.clone())?)
+}
 
-    // Process each video file: transcribe then match immediately
-    for (index, video) in videos.iter().enumerate() {
-        progress_callback(ProgressEvent::ProcessingVideo {
-            index,
-            total: videos.len(),
-            video_path: video.path.clone(),
-        });
+/// Fetches the episode metadata of a TV show into the caches ahead of a run
+///
+/// Searches and fetches exactly like an investigation with the same
+/// options, so a later run finds the show in the search and metadata
+/// caches, e.g. without a network connection. If the show name matches
+/// multiple series, `select_series` is asked to choose one, as during an
+/// investigation. Cached metadata expires after 24 hours.
+pub fn prefetch_show_metadata<S>(
+    show_name: &str,
+    options: &MetadataOptions,
+    select_series: S,
+) -> Result<TVSeries, DialogDetectiveError>
+where
+    S: FnOnce(&[SeriesCandidate]) -> Result<usize, DialogDetectiveError>,
+{
+    let provider = default_metadata_provider(&MetadataOptions {
+        use_cache: true,
+        ..options.clone()
+    })?;
+    fetch_selected_series(
+        provider.as_ref(),
+        show_name,
+        &options.season_filter,
+        select_series,
+    )
+}
 
-        // Compute video hash for cache lookup
-        progress_callback(ProgressEvent::Hashing {
-            video_path: video.path.clone(),
-        });
-        let video_hash = compute_video_hash(&video.path)?;
-        progress_callback(ProgressEvent::HashingFinished {
-            video_path: video.path.clone(),
-        });
+/// Options for transcribing a single video file
+#[derive(Debug, Clone)]
+pub struct TranscriptionOptions {
+    /// Whether to read from and write to the on-disk transcript cache
+    pub use_cache: bool,
 
-        let transcript = if let Some(cached_transcript) = transcript_cache.load(&video_hash)? {
-            // Cache hit - use cached transcript
+    /// Which cached transcripts made with other models may be reused
+    pub reuse: TranscriptReuse,
+}
+
+impl Default for TranscriptionOptions {
+    fn default() -> Self {
+        Self {
+            use_cache: true,
+            reuse: TranscriptReuse::default(),
+        }
+    }
+}
+
+/// Transcribes the audio of a video file to text
+///
+/// This is the transcription half of `investigate_case` as a standalone
+/// function: the audio track is extracted with FFmpeg and transcribed with
+/// Whisper. Transcripts are cached by the video's content hash for 24 hours
+/// unless caching is disabled in the options.
+///
+/// # Arguments
+///
+/// * `video_path` - Path to the video file to transcribe
+/// * `model_path` - Path to the Whisper model file (e.g., ggml-base.bin)
+/// * `options` - Caching behaviour
+///
+/// # Examples
+///
+/// ```no_run
+/// use dialog_detective::{transcribe_video, TranscriptionOptions};
+/// use std::path::Path;
+///
+/// let transcript = transcribe_video(
+///     Path::new("/path/to/video.mkv"),
+///     Path::new("models/ggml-base.bin"),
+///     &TranscriptionOptions::default(),
+/// ).unwrap();
+///
+/// println!("[{}] {}", transcript.language, transcript.text);
+/// ```
+#[cfg(feature = "whisper")]
+pub fn transcribe_video(
+    video_path: &Path,
+    model_path: &Path,
+    options: &TranscriptionOptions,
+) -> Result<Transcript, DialogDetectiveError> {
+    transcribe_video_with(video_path, &WhisperTranscriber::new(model_path), options)
+}
+
+/// Transcribes a video file using the given transcriber
+///
+/// Works like `transcribe_video`, but delegates the transcription itself to
+/// any `Transcriber` implementation. This is the entry point for builds
+/// without the `whisper` feature.
+pub fn transcribe_video_with(
+    video_path: &Path,
+    transcriber: &dyn Transcriber,
+    options: &TranscriptionOptions,
+) -> Result<Transcript, DialogDetectiveError> {
+    let video = VideoFile::new(video_path);
+    let (video_hash, _) = compute_video_hash(
+        &video.path,
+        &HashOptions::default(),
+        &RetryPolicy::default(),
+        &mut |_, _| true,
+    )?;
+
+    let transcript_cache = if options.use_cache {
+        let one_day = Some(Duration::from_secs(24 * 60 * 60));
+        Some(CacheStorage::<Transcript>::open("transcripts", one_day)?.encrypted()?)
+    } else {
+        None
+    };
+
+    let (transcript, _) = transcribe_with_cache(
+        &video,
+        &video_hash,
+        transcriber,
+        None,
+        transcript_cache.as_ref(),
+        options.reuse,
+        &mut |_| {},
+    )?;
+
+    Ok(transcript)
+}
+
+/// A transcriber to transcribe again with if a transcript scores too low
+#[derive(Clone, Copy)]
+struct QualityFallback<'t> {
+    transcriber: &'t dyn Transcriber,
+    min_quality: f32,
+}
+
+/// Loads a transcript from the cache or creates it with the given transcriber
+///
+/// Cached transcripts are only used if `reuse` accepts the model they were
+/// made with. Emits a cache hit event or forwards the transcriber's progress
+/// events, followed by the transcription finished event. Fresh transcripts
+/// are stamped with the transcriber's model (unless the transcriber set one)
+/// and stored in the cache if one is given. Returns the transcript along
+/// with where it came from.
+///
+/// With a fallback, transcripts of the fallback's model are taken from the
+/// cache as well, and transcripts scoring below its minimum quality are
+/// made again with it, keeping the better one.
+fn transcribe_with_cache<F>(
+    video: &VideoFile,
+    video_hash: &str,
+    transcriber: &dyn Transcriber,
+    fallback: Option<QualityFallback<'_>>,
+    transcript_cache: Option<&CacheStorage<Transcript>>,
+    reuse: TranscriptReuse,
+    progress_callback: &mut F,
+) -> Result<(Transcript, TranscriptSource), DialogDetectiveError>
+where
+    F: FnMut(ProgressEvent),
+{
+    let model = transcriber.model();
+    let fallback_model = fallback.map(|f| f.transcriber.model());
+
+    let cached = match transcript_cache {
+        Some(cache) => cache.load(video_hash)?.filter(|cached| {
+            let cached_model = cached.model.as_deref();
+            reuse.accepts(cached_model, model.as_deref())
+                || fallback_model
+                    .as_ref()
+                    .is_some_and(|m| reuse.accepts(cached_model, m.as_deref()))
+        }),
+        None => None,
+    };
+    let (mut transcript, mut source) = match cached {
+        Some(cached_transcript) => {
             progress_callback(ProgressEvent::TranscriptCacheHit {
                 video_path: video.path.clone(),
                 language: cached_transcript.language.clone(),
             });
-            cached_transcript
-        } else {
-            // Cache miss - extract audio and transcribe
-            progress_callback(ProgressEvent::AudioExtraction {
+            (cached_transcript, TranscriptSource::Cache)
+        }
+        None => (
+            transcribe_fresh(video, transcriber, progress_callback)?,
+            TranscriptSource::Fresh,
+        ),
+    };
+
+    if let Some(fallback) = fallback {
+        let score = transcript.quality().score;
+        let made_by_fallback =
+            transcript.model.is_some() && fallback_model.as_ref() == Some(&transcript.model);
+        if score < fallback.min_quality && !made_by_fallback {
+            progress_callback(ProgressEvent::TranscriptionRetried {
                 video_path: video.path.clone(),
-                temp_path: PathBuf::new(), // Will be set after extraction
+                score,
+                model: fallback_model.flatten(),
             });
-            let audio = audio_from_video(video)?;
-            progress_callback(ProgressEvent::AudioExtractionFinished {
-                video_path: video.path.clone(),
-                temp_path: audio.to_path_buf(),
+            let retried = transcribe_fresh(video, fallback.transcriber, progress_callback)?;
+            if retried.quality().score >= score {
+                transcript = retried;
+                source = TranscriptSource::Fresh;
+            }
+        }
+    }
+    if source == TranscriptSource::Cache {
+        return Ok((transcript, source));
+    }
+
+    // Store in cache for future use
+    if let Some(cache) = transcript_cache {
+        cache.store(video_hash, &transcript)?;
+    }
+
+    progress_callback(ProgressEvent::TranscriptionFinished {
+        video_path: video.path.clone(),
+        language: transcript.language.clone(),
+        text: transcript.text.clone(),
+    });
+
+    Ok((transcript, source))
+}
+
+/// Transcribes a video, stamping the transcript with the transcriber's model
+///
+/// The whole call is timed as the transcription stage unless the
+/// transcriber reports that stage itself.
+fn transcribe_fresh<F>(
+    video: &VideoFile,
+    transcriber: &dyn Transcriber,
+    progress_callback: &mut F,
+) -> Result<Transcript, DialogDetectiveError>
+where
+    F: FnMut(ProgressEvent),
+{
+    let started = Instant::now();
+    let mut reported_transcription = false;
+    let mut transcript = transcriber.transcribe(&video.path, &mut |event| {
+        if let ProgressEvent::StageCompleted {
+            stage: Stage::Transcription,
+            ..
+        } = event
+        {
+            reported_transcription = true;
+        }
+        progress_callback(event);
+    })?;
+    if !reported_transcription {
+        progress_callback(ProgressEvent::StageCompleted {
+            stage: Stage::Transcription,
+            duration: started.elapsed(),
+        });
+    }
+
+    if transcript.model.is_none() {
+        transcript.model = transcriber.model();
+    }
+
+    Ok(transcript)
+}
+
+/// Options for matching a transcript to an episode
+#[derive(Debug, Clone)]
+pub struct MatcherOptions {
+    /// The AI matcher to use
+    pub matcher: MatcherType,
+
+    /// Maximum number of episode candidates in a single prompt
+    ///
+    /// Larger series are matched in chunks, followed by a tie-break round
+    /// between the best matches of all chunks.
+    pub max_episodes_per_prompt: usize,
+}
+
+impl Default for MatcherOptions {
+    fn default() -> Self {
+        Self {
+            matcher: MatcherType::GeminiFlash,
+            max_episodes_per_prompt: DEFAULT_MAX_EPISODES_PER_PROMPT,
+        }
+    }
+}
+
+/// Matches a transcript to an episode of the given series
+///
+/// This is the matching half of `investigate_case` as a standalone function.
+/// It builds the same prompt, calls the same AI backend, and parses the
+/// response with the same logic, but accepts any transcript — for example
+/// subtitles pasted by a user — and a series obtained from
+/// `fetch_show_metadata` or constructed by hand. Results are not cached.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dialog_detective::{
+///     fetch_show_metadata, match_transcript, MatcherOptions, MetadataOptions, Transcript,
+/// };
+///
+/// let series = fetch_show_metadata("Breaking Bad", &MetadataOptions::default()).unwrap();
+/// let transcript = Transcript::new("Say my name.", "en");
+///
+/// let outcome = match_transcript(&transcript, &series, &MatcherOptions::default()).unwrap();
+/// println!(
+///     "S{:02}E{:02} - {}",
+///     outcome.episode.season_number, outcome.episode.episode_number, outcome.episode.name
+/// );
+/// ```
+pub fn match_transcript(
+    transcript: &Transcript,
+    series: &TVSeries,
+    options: &MatcherOptions,
+) -> Result<MatchOutcome, DialogDetectiveError> {
+    let matcher = ChunkedMatcher::new(
+        build_matcher(options.matcher, &MatcherCommand::default(), None),
+        options.max_episodes_per_prompt,
+    );
+    Ok(matcher.match_episode(transcript, series)?)
+}
+
+/// Investigates a directory for video files and matches them to episodes
+///
+/// This function scans the given directory recursively for video files,
+/// extracts audio from each video, transcribes the audio to text using Whisper,
+/// fetches episode metadata for the given show, and uses AI to match each video
+/// to its corresponding episode.
+///
+/// Progress events are emitted through the provided callback, allowing library
+/// users to track progress, display status, or remain silent.
+///
+/// # Arguments
+///
+/// * `directory` - The directory path to investigate
+/// * `model` - Path to the Whisper model file (e.g., ggml-base.bin), or a
+///   `ModelSpec` naming a model to download if it isn't cached
+/// * `show_name` - The name of the TV show to fetch metadata for
+/// * `season_filter` - Optional list of season numbers to filter (None fetches all seasons)
+/// * `matcher_type` - The AI matcher to use (Gemini or Claude)
+/// * `progress_callback` - Closure called with progress events (can be empty for silent operation)
+/// * `select_series` - Closure choosing one of multiple series candidates by index
+///
+/// # Returns
+///
+/// A vector of `MatchResult` containing the matched video files and their episodes
+///
+/// # Examples
+///
+/// ```no_run
+/// use dialog_detective::{investigate_case, MatcherType, ModelSpec, ProgressEvent};
+/// use std::path::Path;
+///
+/// // With progress output and season filtering
+/// let matches = investigate_case(
+///     Path::new("/path/to/videos"),
+///     Path::new("models/ggml-base.bin"),
+///     "Breaking Bad",
+///     Some(vec![1, 2]),  // Only seasons 1 and 2
+///     MatcherType::Gemini,
+///     |event| {
+///         match event {
+///             ProgressEvent::ProcessingVideo { index, total, video_path } => {
+///                 println!("[{}/{}] Processing: {}", index, total, video_path.display());
+///             }
+///             _ => {} // Handle other events as needed
+///         }
+///     },
+///     |_candidates| Ok(0), // Pick the best-ranked series
+/// ).unwrap();
+///
+/// // Silent operation with all seasons
+/// let matches = investigate_case(
+///     Path::new("/path/to/videos"),
+///     ModelSpec::Named("small".to_string()),
+///     "Breaking Bad",
+///     None,  // All seasons
+///     MatcherType::Claude,
+///     |_| {}, // Ignore all progress events
+///     |_| Ok(0),
+/// ).unwrap();
+/// ```
+#[cfg(feature = "whisper")]
+pub fn investigate_case<F, S>(
+    directory: &Path,
+    model: impl Into<ModelSpec>,
+    show_name: &str,
+    season_filter: Option<Vec<usize>>,
+    matcher_type: MatcherType,
+    progress_callback: F,
+    select_series: S,
+) -> Result<Vec<MatchResult>, DialogDetectiveError>
+where
+    F: FnMut(ProgressEvent),
+    S: FnOnce(&[SeriesCandidate]) -> Result<usize, DialogDetectiveError>,
+{
+    Investigation::with_model(directory, show_name, model)
+        .season_filter(season_filter)
+        .matcher(matcher_type)
+        .run(progress_callback, select_series)
+}
+
+/// Investigates a directory using the given transcriber
+///
+/// Works like `investigate_case`, but delegates transcription to any
+/// `Transcriber` implementation instead of the built-in Whisper one. This is
+/// the entry point for builds without the `whisper` feature.
+pub fn investigate_case_with<F, S>(
+    directory: &Path,
+    transcriber: &dyn Transcriber,
+    show_name: &str,
+    season_filter: Option<Vec<usize>>,
+    matcher_type: MatcherType,
+    progress_callback: F,
+    select_series: S,
+) -> Result<Vec<MatchResult>, DialogDetectiveError>
+where
+    F: FnMut(ProgressEvent),
+    S: FnOnce(&[SeriesCandidate]) -> Result<usize, DialogDetectiveError>,
+{
+    Investigation::new(directory, show_name, transcriber)
+        .season_filter(season_filter)
+        .matcher(matcher_type)
+        .run(progress_callback, select_series)
+}
+
+/// Builder for configuring and running an investigation
+///
+/// `investigate_case` covers the common case with positional arguments. The
+/// builder exposes every option of the pipeline and is the place new options
+/// are added to.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dialog_detective::{Investigation, MatcherType, WhisperTranscriber};
+///
+/// let matches = Investigation::new(
+///     "/path/to/videos",
+///     "Breaking Bad",
+///     WhisperTranscriber::new("models/ggml-base.bin"),
+/// )
+/// .matcher(MatcherType::Claude)
+/// .infer_season(true)
+/// .run(|_| {}, |_| Ok(0))
+/// .unwrap();
+/// ```
+pub struct Investigation<'a> {
+    directory: PathBuf,
+    show_name: String,
+    transcriber: Box<dyn Transcriber + 'a>,
+    #[cfg(feature = "whisper")]
+    model: Option<ModelSpec>,
+    quality_fallback: Option<Box<dyn Transcriber + 'a>>,
+    min_transcript_quality: f32,
+    escalation: Vec<Box<dyn Transcriber + 'a>>,
+    escalation_confidence: f64,
+    season_filter: Option<Vec<usize>>,
+    language_strategy: LanguageStrategy,
+    title_language: Option<String>,
+    episode_order: EpisodeOrder,
+    metadata_provider: MetadataProviderType,
+    matcher_type: MatcherType,
+    custom_matcher: Option<Box<dyn EpisodeMatcher + 'a>>,
+    max_episodes_per_prompt: usize,
+    matcher_command: MatcherCommand,
+    matching_timeout: Option<Duration>,
+    parallel_matches: usize,
+    infer_season: bool,
+    include_transcripts: bool,
+    transcript_reuse: TranscriptReuse,
+    cache_namespace: Option<String>,
+    capture_prompts: bool,
+    io_retry: RetryPolicy,
+    hash_options: HashOptions,
+    max_files: Option<(usize, ConfirmFiles<'a>)>,
+}
+
+/// Asked whether to investigate the number of videos found, see `Investigation::max_files`
+type ConfirmFiles<'a> = Box<dyn FnOnce(usize) -> bool + 'a>;
+
+impl<'a> Investigation<'a> {
+    /// Creates an investigation of a directory for the given show
+    ///
+    /// The directory may also be a single video file. Defaults to all
+    /// seasons, the Gemini Flash matcher, no season inference, and no
+    /// transcripts on the match results.
+    pub fn new(
+        directory: impl Into<PathBuf>,
+        show_name: impl Into<String>,
+        transcriber: impl Transcriber + 'a,
+    ) -> Self {
+        Self {
+            directory: directory.into(),
+            show_name: show_name.into(),
+            transcriber: Box::new(transcriber),
+            #[cfg(feature = "whisper")]
+            model: None,
+            quality_fallback: None,
+            min_transcript_quality: DEFAULT_MIN_TRANSCRIPT_QUALITY,
+            escalation: Vec::new(),
+            escalation_confidence: DEFAULT_ESCALATION_CONFIDENCE,
+            season_filter: None,
+            language_strategy: LanguageStrategy::default(),
+            title_language: None,
+            episode_order: EpisodeOrder::Default,
+            metadata_provider: MetadataProviderType::TvMaze,
+            matcher_type: MatcherType::GeminiFlash,
+            custom_matcher: None,
+            max_episodes_per_prompt: DEFAULT_MAX_EPISODES_PER_PROMPT,
+            matcher_command: MatcherCommand::default(),
+            matching_timeout: None,
+            parallel_matches: DEFAULT_PARALLEL_MATCHES,
+            infer_season: false,
+            include_transcripts: false,
+            transcript_reuse: TranscriptReuse::default(),
+            cache_namespace: None,
+            capture_prompts: false,
+            io_retry: RetryPolicy::default(),
+            hash_options: HashOptions::default(),
+            max_files: None,
+        }
+    }
+
+    /// Creates an investigation transcribing with the given Whisper model
+    ///
+    /// Unlike passing a `WhisperTranscriber`, the model may be given by name
+    /// or left to `ModelSpec::Auto`. It is resolved when the investigation
+    /// runs, downloading it if necessary and reporting the download as
+    /// `ProgressEvent::ModelDownload`.
+    #[cfg(feature = "whisper")]
+    pub fn with_model(
+        directory: impl Into<PathBuf>,
+        show_name: impl Into<String>,
+        model: impl Into<ModelSpec>,
+    ) -> Self {
+        // The transcriber is replaced once the model is resolved
+        let transcriber = WhisperTranscriber::new(PathBuf::new());
+        Self {
+            model: Some(model.into()),
+            ..Self::new(directory, show_name, transcriber)
+        }
+    }
+
+    /// Transcribes videos again with another transcriber if their transcript scores low
+    ///
+    /// Transcripts scoring below `min_transcript_quality` (see
+    /// `Transcript::quality`) are made again with the fallback, typically
+    /// a larger Whisper model, and the better of both is matched. Cached
+    /// transcripts of the fallback's model are reused as well.
+    pub fn quality_fallback(mut self, transcriber: impl Transcriber + 'a) -> Self {
+        self.quality_fallback = Some(Box::new(transcriber));
+        self
+    }
+
+    /// Sets the quality score below which the fallback transcriber is used
+    ///
+    /// Defaults to `DEFAULT_MIN_TRANSCRIPT_QUALITY`. Without a fallback
+    /// transcriber, the quality is only reported.
+    pub fn min_transcript_quality(mut self, min_quality: f32) -> Self {
+        self.min_transcript_quality = min_quality;
+        self
+    }
+
+    /// Adds a step to the escalation ladder for uncertain matches
+    ///
+    /// Start with a fast transcriber, e.g. a small model sampling only the
+    /// first minutes (see `WhisperTranscriber::sample_length`), and add
+    /// slower but more thorough ones: a longer sample, a larger model, the
+    /// full audio. Once all videos were matched, each match less confident
+    /// than `escalation_confidence` is transcribed and matched again with
+    /// the first step, then the next, until it is confident enough. The
+    /// more confident match is kept, and a failing step keeps the match so
+    /// far. Cached matches have no confidence and are never escalated.
+    pub fn escalate_to(mut self, transcriber: impl Transcriber + 'a) -> Self {
+        self.escalation.push(Box::new(transcriber));
+        self
+    }
+
+    /// Sets the match confidence below which a video is escalated
+    ///
+    /// Defaults to `DEFAULT_ESCALATION_CONFIDENCE`, the confidence above
+    /// which the CLI applies matches without review.
+    pub fn escalation_confidence(mut self, min_confidence: f64) -> Self {
+        self.escalation_confidence = min_confidence;
+        self
+    }
+
+    /// Restricts matching to the given seasons (None matches against all seasons)
+    pub fn season_filter(mut self, season_filter: Option<Vec<usize>>) -> Self {
+        self.season_filter = season_filter;
+        self
+    }
+
+    /// Sets which language the episode titles and summaries are fetched in
+    ///
+    /// Defaults to `LanguageStrategy::Original`, the English metadata.
+    pub fn language_strategy(mut self, language_strategy: LanguageStrategy) -> Self {
+        self.language_strategy = language_strategy;
+        self
+    }
+
+    /// Fetches the episode titles in another language for naming the files
+    ///
+    /// The titles end up in `Episode::local_title` and the `{title_local}`
+    /// placeholder, while matching uses the metadata of the
+    /// `language_strategy`. Takes an ISO 639-1 code like "de" and requires
+    /// a TMDB API key, like localized metadata.
+    pub fn title_language(mut self, language: impl Into<String>) -> Self {
+        self.title_language = Some(language.into());
+        self
+    }
+
+    /// Sets the order the episodes are numbered in
+    ///
+    /// Matching and naming use the episode numbers of this order, so it
+    /// should be the one the library follows. Shows without the order keep
+    /// the default one. Defaults to `EpisodeOrder::Default`.
+    pub fn episode_order(mut self, episode_order: EpisodeOrder) -> Self {
+        self.episode_order = episode_order;
+        self
+    }
+
+    /// Selects the primary metadata provider
+    ///
+    /// Defaults to `MetadataProviderType::TvMaze`. Trakt requires the
+    /// client ID of a Trakt API app in `TRAKT_CLIENT_ID`; plugins are
+    /// loaded with `load_provider_plugin`.
+    pub fn metadata_provider(mut self, provider: MetadataProviderType) -> Self {
+        self.metadata_provider = provider;
+        self
+    }
+
+    /// Selects the AI matcher
+    pub fn matcher(mut self, matcher_type: MatcherType) -> Self {
+        self.matcher_type = matcher_type;
+        self
+    }
+
+    /// Uses a custom episode matcher instead of one of the built-in ones
+    ///
+    /// Takes precedence over the matcher selected with `matcher`. Matches are
+    /// cached under the custom matcher's ID.
+    pub fn custom_matcher(mut self, matcher: impl EpisodeMatcher + 'a) -> Self {
+        self.custom_matcher = Some(Box::new(matcher));
+        self
+    }
+
+    /// Limits the number of episode candidates in a single prompt
+    ///
+    /// Series with more episodes than this, such as long-running soap operas
+    /// and anime, are matched in chunks. The best matches of all chunks then
+    /// compete in a final tie-break round. Defaults to
+    /// `DEFAULT_MAX_EPISODES_PER_PROMPT`.
+    pub fn max_episodes_per_prompt(mut self, max_episodes: usize) -> Self {
+        self.max_episodes_per_prompt = max_episodes;
+        self
+    }
+
+    /// Sets how the AI CLI of the matcher is run
+    ///
+    /// Adds arguments to its calls, e.g. to pin a model version, and sets
+    /// its working directory and environment. Doesn't apply to a custom
+    /// matcher. Defaults to the CLI's own arguments in the current
+    /// directory.
+    pub fn matcher_command(mut self, command: MatcherCommand) -> Self {
+        self.matcher_command = command;
+        self
+    }
+
+    /// Stops matcher calls that haven't finished after the given time
+    ///
+    /// The AI CLI is killed once the timeout has passed, and the video
+    /// fails with `EpisodeMatchingError::TimedOut`, or the investigation
+    /// unless it keeps going. Chunked series time out per call. Doesn't
+    /// apply to a custom matcher. Defaults to no timeout.
+    pub fn matching_timeout(mut self, timeout: Duration) -> Self {
+        self.matching_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how many matcher calls may run at the same time
+    ///
+    /// Matching runs alongside transcription, so the matches of earlier
+    /// videos are made while later videos are still being transcribed.
+    /// Raise this for large batches if the AI backend's rate limits allow
+    /// it. Defaults to `DEFAULT_PARALLEL_MATCHES`; zero is treated as one.
+    pub fn parallel_matches(mut self, parallel_matches: usize) -> Self {
+        self.parallel_matches = parallel_matches;
+        self
+    }
+
+    /// Enables the season inference pre-pass
+    ///
+    /// Only takes effect without a season filter. Before matching, the season
+    /// of each video is read from season markers in its path (`S02E05`,
+    /// `Season 2`, ...) or, failing that, guessed by the matcher from a short
+    /// transcript excerpt. The video is then only matched against the
+    /// episodes of that season, which makes matching cheaper and more
+    /// accurate. If no season can be inferred, all seasons are used.
+    pub fn infer_season(mut self, infer_season: bool) -> Self {
+        self.infer_season = infer_season;
+        self
+    }
+
+    /// Attaches the transcript of each video to its match result
+    ///
+    /// Lets callers display or store the dialogue a match was based on
+    /// without reading the transcript cache themselves.
+    pub fn include_transcripts(mut self, include_transcripts: bool) -> Self {
+        self.include_transcripts = include_transcripts;
+        self
+    }
+
+    /// Sets which cached transcripts made with other models may be reused
+    ///
+    /// By default, only transcripts made with the transcriber's model are
+    /// reused, so switching models re-transcribes the videos.
+    pub fn transcript_reuse(mut self, transcript_reuse: TranscriptReuse) -> Self {
+        self.transcript_reuse = transcript_reuse;
+        self
+    }
+
+    /// Keeps cached matches in a namespace of their own
+    ///
+    /// Matches are cached by video, show, seasons, metadata provider,
+    /// language, episode order, and matcher, but not by the library they
+    /// are filed into. Libraries that should never share matches, e.g.
+    /// a curated one, can each use their own namespace (see
+    /// `cache_namespace_for`). Transcripts and metadata are still shared.
+    /// Defaults to no namespace.
+    pub fn cache_namespace(mut self, namespace: Option<String>) -> Self {
+        self.cache_namespace = namespace;
+        self
+    }
+
+    /// Emits the exact prompt and response of every matcher call
+    ///
+    /// Reported as `ProgressEvent::PromptExchanged`, so mismatches can be
+    /// investigated and prompts iterated on. Cached matches don't call the
+    /// matcher and therefore emit nothing.
+    pub fn capture_prompts(mut self, capture_prompts: bool) -> Self {
+        self.capture_prompts = capture_prompts;
+        self
+    }
+
+    /// Sets how reading the video files is retried on transient errors
+    ///
+    /// Meant for files on network shares; defaults to three retries.
+    pub fn io_retry(mut self, io_retry: RetryPolicy) -> Self {
+        self.io_retry = io_retry;
+        self
+    }
+
+    /// Sets how the video files are read for hashing
+    ///
+    /// Defaults to buffered reads of 4 MiB; see `HashOptions`.
+    pub fn hash_options(mut self, hash_options: HashOptions) -> Self {
+        self.hash_options = hash_options;
+        self
+    }
+
+    /// Stops before investigating more videos than `limit` unless confirmed
+    ///
+    /// Guards against being pointed at a whole library by mistake. If the
+    /// scan finds more videos than `limit`, `confirm` is asked with their
+    /// number; unless it returns true, the investigation fails with
+    /// `DialogDetectiveError::TooManyFiles` before any video is hashed or
+    /// transcribed. Defaults to no limit.
+    pub fn max_files(mut self, limit: usize, confirm: impl FnOnce(usize) -> bool + 'a) -> Self {
+        self.max_files = Some((limit, Box::new(confirm)));
+        self
+    }
+
+    /// Runs the investigation
+    ///
+    /// Progress events are emitted through `progress_callback`. If the show
+    /// name matches multiple series, `select_series` is asked to choose one
+    /// by index. Any video failing to be matched fails the investigation;
+    /// use `run_outcomes` to learn about every file instead. Videos without
+    /// dialogue are left out of the results.
+    pub fn run<F, S>(
+        self,
+        progress_callback: F,
+        select_series: S,
+    ) -> Result<Vec<MatchResult>, DialogDetectiveError>
+    where
+        F: FnMut(ProgressEvent),
+        S: FnOnce(&[SeriesCandidate]) -> Result<usize, DialogDetectiveError>,
+    {
+        let outcomes = self.investigate(progress_callback, select_series, false)?;
+        Ok(outcomes
+            .into_iter()
+            .filter_map(|outcome| match outcome {
+                InvestigationOutcome::Matched(result) => Some(*result),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Runs the investigation, reporting what happened to every file
+    ///
+    /// Works like `run`, but a video that can't be read, transcribed, or
+    /// matched doesn't stop the investigation. Videos come first, in the
+    /// order they were investigated, followed by the skipped files. Videos
+    /// without dialogue are reported as unmatched without asking the
+    /// matcher. Failing to fetch the show's metadata or to open the caches
+    /// still fails the whole investigation.
+    pub fn run_outcomes<F, S>(
+        self,
+        progress_callback: F,
+        select_series: S,
+    ) -> Result<Vec<InvestigationOutcome>, DialogDetectiveError>
+    where
+        F: FnMut(ProgressEvent),
+        S: FnOnce(&[SeriesCandidate]) -> Result<usize, DialogDetectiveError>,
+    {
+        self.investigate(progress_callback, select_series, true)
+    }
+
+    /// Transcribes the first video to detect the language of the show's audio
+    ///
+    /// The transcript is cached like any other, so the video isn't
+    /// transcribed again when its turn comes. Returns None if there is no
+    /// video or it can't be transcribed; the failure is then reported when
+    /// the video is investigated.
+    fn detect_language<F>(
+        &self,
+        transcript_cache: &CacheStorage<Transcript>,
+        progress_callback: &mut F,
+    ) -> Option<String>
+    where
+        F: FnMut(ProgressEvent),
+    {
+        let scan = find_files(&self.directory, &self.io_retry, &mut |_| {}).ok()?;
+        let video = scan
+            .videos
+            .into_iter()
+            .find(|video| !has_download_marker(&video.path))?;
+        let (video_hash, _) = compute_video_hash(
+            &video.path,
+            &self.hash_options,
+            &self.io_retry,
+            &mut |_, _| true,
+        )
+        .ok()?;
+
+        let (transcript, _) = transcribe_with_cache(
+            &video,
+            &video_hash,
+            self.transcriber.as_ref(),
+            None,
+            Some(transcript_cache),
+            self.transcript_reuse,
+            progress_callback,
+        )
+        .ok()?;
+
+        progress_callback(ProgressEvent::LanguageDetected {
+            video_path: video.path,
+            language: transcript.language.clone(),
+        });
+        Some(transcript.language)
+    }
+
+    /// Runs the investigation, stopping at the first failed video unless
+    /// `keep_going` is set
+    fn investigate<F, S>(
+        mut self,
+        mut progress_callback: F,
+        select_series: S,
+        keep_going: bool,
+    ) -> Result<Vec<InvestigationOutcome>, DialogDetectiveError>
+    where
+        F: FnMut(ProgressEvent),
+        S: FnOnce(&[SeriesCandidate]) -> Result<usize, DialogDetectiveError>,
+    {
+        let show_name = self.show_name.as_str();
+
+        progress_callback(ProgressEvent::Started {
+            directory: self.directory.clone(),
+            show_name: show_name.to_string(),
+        });
+
+        #[cfg(feature = "whisper")]
+        if let Some(model) = self.model.take() {
+            let model_path = model.resolve_with(|progress| {
+                progress_callback(ProgressEvent::ModelDownload { progress })
+            })?;
+            self.transcriber = Box::new(WhisperTranscriber::new(model_path));
+        }
+
+        // Initialize caches with 1-day TTL (24 hours)
+        let one_day = Some(Duration::from_secs(24 * 60 * 60));
+        let transcript_cache =
+            CacheStorage::<Transcript>::open("transcripts", one_day)?.encrypted()?;
+        let matching_cache = CacheStorage::<CachedMatch>::open_namespaced(
+            "matching",
+            self.cache_namespace.as_deref(),
+            one_day,
+        )?;
+        let hash_cache = CacheStorage::<Option<HashMatch>>::open("opensubtitles", one_day)?;
+
+        // Clean expired caches at startup
+        transcript_cache.clean()?;
+        matching_cache.clean()?;
+        hash_cache.clean()?;
+
+        // Identify known files by hash first (if an API key is configured)
+        let hash_lookup = OpenSubtitlesLookup::from_env();
+
+        let language = match &self.language_strategy {
+            LanguageStrategy::Original => None,
+            LanguageStrategy::Fixed(language) => Some(language.clone()),
+            LanguageStrategy::Detected => {
+                self.detect_language(&transcript_cache, &mut progress_callback)
+            }
+        };
+
+        // Fetch episode metadata with caching
+        progress_callback(ProgressEvent::FetchingMetadata {
+            show_name: show_name.to_string(),
+        });
+
+        let provider = default_metadata_provider(&MetadataOptions {
+            language,
+            title_language: self.title_language.clone(),
+            provider: self.metadata_provider.clone(),
+            episode_order: self.episode_order,
+            ..MetadataOptions::default()
+        })?;
+        let series = fetch_selected_series(
+            provider.as_ref(),
+            show_name,
+            &self.season_filter,
+            select_series,
+        )?;
+
+        progress_callback(ProgressEvent::MetadataFetched {
+            series_name: series.name.clone(),
+            year: series.year,
+            season_count: series.seasons.len(),
+        });
+
+        if lacks_summaries(&series) {
+            let (missing, total) = missing_summaries(&series);
+            progress_callback(ProgressEvent::SummariesMissing { missing, total });
+        }
+
+        // Scan directory for video files
+        progress_callback(ProgressEvent::ScanningVideos);
+        let io_retry = self.io_retry;
+        let hash_options = self.hash_options;
+        let scan = find_files(&self.directory, &io_retry, &mut |scan| {
+            progress_callback(ProgressEvent::DirectoryScanned { scan: scan.clone() })
+        })?;
+        let (videos, other_files, set_aside) =
+            set_aside_downloads(scan.videos, scan.skipped, GROWTH_CHECK_INTERVAL);
+        progress_callback(ProgressEvent::VideosFound {
+            count: videos.len(),
+        });
+        for (path, message) in scan.warnings {
+            progress_callback(ProgressEvent::ScanWarning { path, message });
+        }
+        let skipped: Vec<_> = other_files.into_iter().chain(set_aside).collect();
+        for (path, reason) in &skipped {
+            if !matches!(reason, SkipReason::NotAVideo | SkipReason::Unreadable) {
+                progress_callback(ProgressEvent::FileSkipped {
+                    path: path.clone(),
+                    reason: *reason,
+                });
+            }
+        }
+        let skipped = skipped
+            .into_iter()
+            .map(|(path, reason)| InvestigationOutcome::Skipped { path, reason });
+
+        if videos.is_empty() {
+            return Ok(skipped.collect());
+        }
+        if let Some((limit, confirm)) = self.max_files.take()
+            && videos.len() > limit
+            && !confirm(videos.len())
+        {
+            return Err(DialogDetectiveError::TooManyFiles {
+                count: videos.len(),
+                limit,
             });
+        }
 
-            progress_callback(ProgressEvent::Transcription {
-                video_path: video.path.clone(),
-                temp_path: audio.to_path_buf(),
+        // Initialize the custom matcher or the one of the selected type
+        let matcher = match self.custom_matcher {
+            Some(matcher) => matcher,
+            None => build_matcher(
+                self.matcher_type,
+                &self.matcher_command,
+                self.matching_timeout,
+            ),
+        };
+        let matcher = ChunkedMatcher::new(matcher, self.max_episodes_per_prompt);
+        let matcher_id = matcher.id();
+        let matcher_version = matcher.version();
+        let metadata_key = provider_key(&provider.id(), provider.language().as_deref());
+
+        // Season inference is pointless if the user already chose the seasons
+        let infer_season = self.infer_season && self.season_filter.is_none();
+        let quality_fallback =
+            self.quality_fallback
+                .as_deref()
+                .map(|transcriber| QualityFallback {
+                    transcriber,
+                    min_quality: self.min_transcript_quality,
+                });
+
+        let context = MatchContext {
+            series: &series,
+            matcher: &matcher,
+            infer_season,
+            capture_prompts: self.capture_prompts,
+            total: videos.len(),
+        };
+        let mut collector = MatchCollector {
+            progress_callback,
+            timings: vec![StageTimings::default(); videos.len()],
+            results: vec![None; videos.len()],
+            other_outcomes: (0..videos.len()).map(|_| None).collect(),
+            keep_going,
+            pending: HashMap::new(),
+            matching_cache: &matching_cache,
+            matcher_id: &matcher_id,
+            show_name,
+            series: &series,
+        };
+
+        // Videos are transcribed one by one while matching runs on a pool of
+        // workers, as matcher calls spend most of their time waiting
+        let (job_sender, job_receiver) = mpsc::channel::<MatchJob>();
+        let (message_sender, message_receiver) = mpsc::channel::<WorkerMessage>();
+        let job_receiver = Mutex::new(job_receiver);
+
+        thread::scope(|scope| -> Result<(), DialogDetectiveError> {
+            for _ in 0..self.parallel_matches.max(1) {
+                let message_sender = message_sender.clone();
+                let job_receiver = &job_receiver;
+                let context = &context;
+                scope.spawn(move || {
+                    loop {
+                        let Ok(job) = job_receiver.lock().expect("job queue poisoned").recv()
+                        else {
+                            break;
+                        };
+
+                        let index = job.index;
+                        let outcome = match_video(&job, context, &mut |event| {
+                            message_sender.send(WorkerMessage::Event(index, event)).ok();
+                        });
+                        if message_sender
+                            .send(WorkerMessage::Matched(index, outcome))
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(message_sender);
+
+            // The next video is hashed while the current one's audio is
+            // extracted and transcribed, so large files aren't read twice in
+            // a row. Each video needs a ticket to be hashed, and the loop
+            // hands out one ahead of the video it works on.
+            let (ticket_sender, ticket_receiver) = mpsc::channel::<()>();
+            let (hash_sender, hash_receiver) = mpsc::channel::<HashMessage>();
+            let videos = &videos;
+            scope.spawn(move || {
+                for video in videos {
+                    if ticket_receiver.recv().is_err() {
+                        break;
+                    }
+
+                    let started = Instant::now();
+                    let mut reported = None;
+                    let hash = compute_video_hash(
+                        &video.path,
+                        &hash_options,
+                        &io_retry,
+                        &mut |bytes_done, total_bytes| {
+                            let percent = (bytes_done * 100).checked_div(total_bytes);
+                            if reported == Some(percent) {
+                                return true;
+                            }
+                            reported = Some(percent);
+                            let message = HashMessage::Progress {
+                                bytes_done,
+                                total_bytes,
+                            };
+                            hash_sender.send(message).is_ok()
+                        },
+                    );
+                    let message = HashMessage::Hashed(hash, started.elapsed());
+                    if hash_sender.send(message).is_err() {
+                        break;
+                    }
+                }
             });
-            let transcript = audio_to_text(&audio, model_path)?;
+            ticket_sender.send(()).ok();
+
+            for (index, video) in videos.iter().enumerate() {
+                let mut progress = |event: ProgressEvent| collector.emit(index, event);
+
+                progress(ProgressEvent::ProcessingVideo {
+                    index,
+                    total: videos.len(),
+                    video_path: video.path.clone(),
+                });
+
+                // Let the next video be hashed while this one is processed
+                ticket_sender.send(()).ok();
+
+                // Wait for the video hash for cache lookup
+                progress(ProgressEvent::Hashing {
+                    video_path: video.path.clone(),
+                });
+                let (hash, duration) = loop {
+                    match hash_receiver
+                        .recv()
+                        .expect("hashing thread outlives the video loop")
+                    {
+                        HashMessage::Progress {
+                            bytes_done,
+                            total_bytes,
+                        } => progress(ProgressEvent::HashingProgress {
+                            video_path: video.path.clone(),
+                            bytes_done,
+                            total_bytes,
+                        }),
+                        HashMessage::Hashed(hash, duration) => break (hash, duration),
+                    }
+                };
+                let (video_hash, bytes) = match hash {
+                    Ok(hash) => hash,
+                    Err(error) => {
+                        collector.fail(index, video.path.clone(), None, error.into())?;
+                        continue;
+                    }
+                };
+                progress(ProgressEvent::HashingFinished {
+                    video_path: video.path.clone(),
+                    bytes,
+                    duration,
+                });
+                progress(ProgressEvent::StageCompleted {
+                    stage: Stage::Hashing,
+                    duration,
+                });
+                let mut video = VideoFile {
+                    hash: Some(video_hash.clone()),
+                    ..video.clone()
+                };
+
+                if let Some(lookup) = &hash_lookup
+                    && let Some(episode) = identify_by_hash(&video, lookup, &hash_cache, &series)?
+                {
+                    progress(ProgressEvent::HashMatched {
+                        video_path: video.path.clone(),
+                        episode: episode.clone(),
+                    });
+
+                    collector.finish(
+                        index,
+                        MatchResult {
+                            video: video.clone(),
+                            video_hash: video_hash.clone(),
+                            episode,
+                            confidence: Some(1.0),
+                            alternatives: Vec::new(),
+                            reasoning: None,
+                            extra_episodes: Vec::new(),
+                            filename_conflict: None,
+                            runtime_mismatch: None,
+                            video_duration: None,
+                            part: None,
+                            transcript: None,
+                            transcript_quality: None,
+                            timings: StageTimings::default(),
+                            usage: None,
+                        },
+                    );
+                    continue;
+                }
+
+                let transcribed = transcribe_with_cache(
+                    &video,
+                    &video_hash,
+                    self.transcriber.as_ref(),
+                    quality_fallback,
+                    Some(&transcript_cache),
+                    self.transcript_reuse,
+                    &mut progress,
+                );
+                let (transcript, transcript_source) = match transcribed {
+                    Ok(transcribed) => transcribed,
+                    Err(error) => {
+                        collector.fail(index, video.path.clone(), None, error)?;
+                        continue;
+                    }
+                };
+                let quality = transcript.quality();
+                progress(ProgressEvent::TranscriptScored {
+                    video_path: video.path.clone(),
+                    quality,
+                });
+                let video_duration = transcript.duration;
+                video.duration = video_duration;
+                let evidence = self.include_transcripts.then(|| TranscriptEvidence {
+                    transcript: transcript.clone(),
+                    source: transcript_source,
+                });
+
+                // Without dialogue, the matcher could only guess
+                if !transcript.has_dialogue() {
+                    progress(ProgressEvent::NoDialogue {
+                        video_path: video.path.clone(),
+                        words: transcript.spoken_words(),
+                    });
+                    collector.other_outcomes[index] = Some(InvestigationOutcome::Unmatched {
+                        path: video.path.clone(),
+                        reason: UnmatchedReason::NoDialogue,
+                        transcript: evidence,
+                    });
+                    continue;
+                }
+
+                // Match the video to an episode (with caching)
+                let matching_cache_key = compute_matching_cache_key(
+                    &video_hash,
+                    show_name,
+                    &self.season_filter,
+                    infer_season,
+                    &metadata_key,
+                    &matcher_id,
+                    matcher_version,
+                );
+
+                if let Some(cached) = matching_cache.load(&matching_cache_key)? {
+                    // Cache hit - use cached matching result
+                    let record = cached.into_record(&matcher_id);
+                    progress(ProgressEvent::MatchingCacheHit {
+                        video_path: video.path.clone(),
+                        episode: record.episode.clone(),
+                    });
+
+                    collector.finish(
+                        index,
+                        MatchResult {
+                            video: video.clone(),
+                            video_hash: video_hash.clone(),
+                            episode: record.episode,
+                            confidence: record.confidence,
+                            alternatives: record.alternatives,
+                            reasoning: record.reasoning,
+                            extra_episodes: record.extra_episodes,
+                            filename_conflict: None,
+                            runtime_mismatch: None,
+                            video_duration,
+                            part: None,
+                            transcript: evidence,
+                            transcript_quality: Some(quality),
+                            timings: StageTimings::default(),
+                            usage: None,
+                        },
+                    );
+                } else {
+                    // Cache miss - hand the video to the matching workers
+                    progress(ProgressEvent::MatchingQueued {
+                        video_path: video.path.clone(),
+                    });
+
+                    collector.pending.insert(
+                        index,
+                        PendingMatch {
+                            video: video.clone(),
+                            video_hash,
+                            cache_key: matching_cache_key,
+                            transcript: evidence,
+                            transcript_quality: Some(quality),
+                            video_duration,
+                        },
+                    );
+                    job_sender
+                        .send(MatchJob {
+                            index,
+                            video: video.clone(),
+                            transcript,
+                        })
+                        .expect("matching workers outlive the job queue");
+                }
+
+                // Report matches finished in the meantime
+                while let Ok(message) = message_receiver.try_recv() {
+                    collector.receive(message)?;
+                }
+            }
+
+            // Wait for the remaining matches
+            drop(job_sender);
+            for message in message_receiver {
+                collector.receive(message)?;
+            }
+
+            Ok(())
+        })?;
+
+        // Give uncertain matches another chance with more thorough transcribers
+        for (step, transcriber) in self.escalation.iter().enumerate() {
+            for index in 0..videos.len() {
+                let Some(result) = &collector.results[index] else {
+                    continue;
+                };
+                let Some(confidence) = result
+                    .confidence
+                    .filter(|confidence| *confidence < self.escalation_confidence)
+                else {
+                    continue;
+                };
+
+                let cache_key = compute_matching_cache_key(
+                    &result.video_hash,
+                    show_name,
+                    &self.season_filter,
+                    infer_season,
+                    &metadata_key,
+                    &matcher_id,
+                    matcher_version,
+                );
+                collector.escalate(
+                    index,
+                    Escalation {
+                        step: step + 1,
+                        confidence,
+                        transcriber: transcriber.as_ref(),
+                        cache_key,
+                        include_transcript: self.include_transcripts,
+                    },
+                    &context,
+                )?;
+            }
+        }
+
+        let mut match_results: Vec<MatchResult> = collector.results.into_iter().flatten().collect();
+        check_runtimes(&mut match_results);
+
+        let quarantined = transcript_cache.take_quarantined().into_iter();
+        let quarantined = quarantined
+            .chain(matching_cache.take_quarantined())
+            .chain(hash_cache.take_quarantined());
+        for path in quarantined {
+            (collector.progress_callback)(ProgressEvent::CacheEntryQuarantined { path });
+        }
+
+        (collector.progress_callback)(ProgressEvent::Complete {
+            match_count: match_results.len(),
+        });
+
+        // Videos without another outcome were matched, in the same order
+        let mut match_results = match_results.into_iter();
+        let outcomes = collector
+            .other_outcomes
+            .into_iter()
+            .map(|outcome| {
+                outcome.unwrap_or_else(|| {
+                    InvestigationOutcome::Matched(Box::new(
+                        match_results.next().expect("every video has an outcome"),
+                    ))
+                })
+            })
+            .chain(skipped)
+            .collect();
+
+        Ok(outcomes)
+    }
+}
+
+/// Cross-checks the duration of each video with the runtime of its episodes
+///
+/// Runs once all videos are matched, as files sharing an episode may turn
+/// out to be its parts instead of mismatches. Other mismatching matches get
+/// their confidence lowered.
+fn check_runtimes(results: &mut [MatchResult]) {
+    detect_parts(results);
+
+    for result in results.iter_mut().filter(|r| r.part.is_none()) {
+        let episodes = std::iter::once(&result.episode).chain(&result.extra_episodes);
+        result.runtime_mismatch = result
+            .video_duration
+            .and_then(|duration| check_runtime(duration, episodes));
+        if result.runtime_mismatch.is_some() {
+            result.confidence = result.confidence.map(|c| c * RUNTIME_MISMATCH_PENALTY);
+        }
+    }
+}
+
+/// A transcribed video waiting for a matching worker
+struct MatchJob {
+    index: usize,
+    video: VideoFile,
+    transcript: Transcript,
+}
+
+/// Everything a matching worker shares with the other workers
+struct MatchContext<'s> {
+    series: &'s TVSeries,
+    matcher: &'s dyn EpisodeMatcher,
+    infer_season: bool,
+    capture_prompts: bool,
+    total: usize,
+}
+
+/// A message from the hashing thread about the video hashed at the moment
+enum HashMessage {
+    /// Part of the video was hashed
+    Progress { bytes_done: u64, total_bytes: u64 },
+
+    /// The hash of the video and the time it took
+    Hashed(Result<(String, u64), FileResolverError>, Duration),
+}
+
+/// A message from a matching worker about the video at the given index
+enum WorkerMessage {
+    /// A progress event to forward
+    Event(usize, ProgressEvent),
+
+    /// The outcome of matching the video
+    Matched(usize, Result<MatchOutcome, DialogDetectiveError>),
+}
+
+/// A match with everything the matcher said about it
+///
+/// Stored in the matching cache, so that cached matches keep their
+/// confidence and reasoning, and can be triaged with other thresholds
+/// without matching them again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MatchRecord {
+    episode: Episode,
+    #[serde(default)]
+    extra_episodes: Vec<Episode>,
+    confidence: Option<f64>,
+    #[serde(default)]
+    alternatives: Vec<EpisodeCandidate>,
+    reasoning: Option<String>,
+    /// The matcher's raw response (None for migrated entries)
+    response: Option<String>,
+    /// ID of the matcher that made the match
+    matcher: String,
+    /// Version of DialogDetective that made the match (None for migrated
+    /// entries)
+    version: Option<String>,
+    /// Name of the show the video was matched against (None for entries
+    /// made before it was recorded)
+    #[serde(default)]
+    show: Option<String>,
+}
+
+impl MatchRecord {
+    /// Records the outcome of a matcher call against a show
+    fn new(outcome: &MatchOutcome, matcher_id: &str, show_name: &str) -> Self {
+        Self {
+            episode: outcome.episode.clone(),
+            extra_episodes: outcome.extra_episodes.clone(),
+            confidence: outcome.confidence,
+            alternatives: outcome.alternatives.clone(),
+            reasoning: outcome.reasoning.clone(),
+            response: Some(outcome.response.clone()),
+            matcher: matcher_id.to_string(),
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            show: Some(show_name.to_string()),
+        }
+    }
+}
 
-            // Store in cache for future use
-            transcript_cache.store(&video_hash, &transcript)?;
+/// An entry of the matching cache
+///
+/// Earlier versions cached only the episode, flattened so that entries
+/// made before multi-episode files were detected still loaded. Such
+/// entries are migrated to a record when read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum CachedMatch {
+    Record(MatchRecord),
+    Legacy {
+        #[serde(flatten)]
+        episode: Episode,
+        #[serde(default)]
+        extra_episodes: Vec<Episode>,
+    },
+}
+
+impl CachedMatch {
+    /// Returns the record of the entry, made by the given matcher
+    ///
+    /// The matcher is part of the cache key, so it is known for legacy
+    /// entries as well.
+    fn into_record(self, matcher_id: &str) -> MatchRecord {
+        match self {
+            CachedMatch::Record(record) => record,
+            CachedMatch::Legacy {
+                episode,
+                extra_episodes,
+            } => MatchRecord {
+                episode,
+                extra_episodes,
+                confidence: None,
+                alternatives: Vec::new(),
+                reasoning: None,
+                response: None,
+                matcher: matcher_id.to_string(),
+                version: None,
+                show: None,
+            },
+        }
+    }
+}
 
-            progress_callback(ProgressEvent::TranscriptionFinished {
+/// A video handed to the matching workers, with what is needed to finish it
+struct PendingMatch {
+    video: VideoFile,
+    video_hash: String,
+    cache_key: String,
+    transcript: Option<TranscriptEvidence>,
+    transcript_quality: Option<TranscriptQuality>,
+    video_duration: Option<Duration>,
+}
+
+/// A video to transcribe and match again with a step of the escalation ladder
+struct Escalation<'t> {
+    step: usize,
+    confidence: f64,
+    transcriber: &'t dyn Transcriber,
+    cache_key: String,
+    include_transcript: bool,
+}
+
+/// Collects match results and progress of all videos in video order
+///
+/// Videos that failed or couldn't be matched have an entry in
+/// `other_outcomes` instead of `results`.
+struct MatchCollector<'s, F> {
+    progress_callback: F,
+    timings: Vec<StageTimings>,
+    results: Vec<Option<MatchResult>>,
+    other_outcomes: Vec<Option<InvestigationOutcome>>,
+    keep_going: bool,
+    pending: HashMap<usize, PendingMatch>,
+    matching_cache: &'s CacheStorage<CachedMatch>,
+    matcher_id: &'s str,
+    show_name: &'s str,
+    series: &'s TVSeries,
+}
+
+impl<F: FnMut(ProgressEvent)> MatchCollector<'_, F> {
+    /// Forwards a progress event of a video, recording its stage timings
+    fn emit(&mut self, index: usize, event: ProgressEvent) {
+        if let ProgressEvent::StageCompleted { stage, duration } = event {
+            self.timings[index].record(stage, duration);
+        }
+        (self.progress_callback)(event);
+    }
+
+    /// Handles a message from a matching worker
+    ///
+    /// Fresh matches are cached. A failed match is handled like `fail`.
+    fn receive(&mut self, message: WorkerMessage) -> Result<(), DialogDetectiveError> {
+        match message {
+            WorkerMessage::Event(index, event) => self.emit(index, event),
+            WorkerMessage::Matched(index, outcome) => {
+                let pending = self
+                    .pending
+                    .remove(&index)
+                    .expect("matched video is pending");
+                let outcome = match outcome {
+                    Ok(outcome) => outcome,
+                    Err(error) => {
+                        return self.fail(index, pending.video.path, pending.transcript, error);
+                    }
+                };
+
+                self.matching_cache.store(
+                    &pending.cache_key,
+                    &CachedMatch::Record(MatchRecord::new(
+                        &outcome,
+                        self.matcher_id,
+                        self.show_name,
+                    )),
+                )?;
+
+                self.finish(
+                    index,
+                    MatchResult {
+                        video: pending.video,
+                        video_hash: pending.video_hash,
+                        episode: outcome.episode,
+                        confidence: outcome.confidence,
+                        alternatives: outcome.alternatives,
+                        reasoning: outcome.reasoning,
+                        extra_episodes: outcome.extra_episodes,
+                        filename_conflict: None,
+                        runtime_mismatch: None,
+                        video_duration: pending.video_duration,
+                        part: None,
+                        transcript: pending.transcript,
+                        transcript_quality: pending.transcript_quality,
+                        timings: StageTimings::default(),
+                        usage: outcome.usage,
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Transcribes and matches a matched video again, keeping the more confident match
+    ///
+    /// Failures keep the earlier match. Tokens and time spent are added to
+    /// the result either way.
+    fn escalate(
+        &mut self,
+        index: usize,
+        escalation: Escalation<'_>,
+        context: &MatchContext,
+    ) -> Result<(), DialogDetectiveError> {
+        let video = match &self.results[index] {
+            Some(result) => result.video.clone(),
+            None => return Ok(()),
+        };
+        self.emit(
+            index,
+            ProgressEvent::Escalating {
                 video_path: video.path.clone(),
-                language: transcript.language.clone(),
-                text: transcript.text.clone(),
+                confidence: escalation.confidence,
+                step: escalation.step,
+                model: escalation.transcriber.model(),
+            },
+        );
+
+        let mut emit = |event| self.emit(index, event);
+        let rematched =
+            transcribe_fresh(&video, escalation.transcriber, &mut emit).and_then(|transcript| {
+                let job = MatchJob {
+                    index,
+                    video: video.clone(),
+                    transcript,
+                };
+                let outcome = match_video(&job, context, &mut emit)?;
+                Ok((job.transcript, outcome))
             });
 
-            transcript
+        let spent = std::mem::take(&mut self.timings[index]);
+        let result = self.results[index]
+            .as_mut()
+            .expect("escalated video is matched");
+        for stage in [
+            Stage::Hashing,
+            Stage::AudioExtraction,
+            Stage::Transcription,
+            Stage::Matching,
+        ] {
+            if let Some(duration) = spent.get(stage) {
+                result.timings.record(stage, duration);
+            }
+        }
+
+        let Ok((transcript, outcome)) = rematched else {
+            return Ok(());
+        };
+        result.usage = match (result.usage, outcome.usage) {
+            (Some(usage), Some(more)) => Some(usage + more),
+            (usage, more) => usage.or(more),
         };
+        if outcome.confidence <= Some(escalation.confidence) {
+            return Ok(());
+        }
 
-        // Match the video to an episode (with caching)
-        let matching_cache_key =
-            compute_matching_cache_key(&video_hash, show_name, &season_filter, matcher_type);
+        self.matching_cache.store(
+            &escalation.cache_key,
+            &CachedMatch::Record(MatchRecord::new(&outcome, self.matcher_id, self.show_name)),
+        )?;
+        result.filename_conflict = check_filename(
+            &result.video.path,
+            &outcome.episode,
+            self.series.seasons.iter().flat_map(|s| &s.episodes),
+        );
+        result.episode = outcome.episode;
+        result.confidence = outcome.confidence;
+        result.alternatives = outcome.alternatives;
+        result.reasoning = outcome.reasoning;
+        result.extra_episodes = outcome.extra_episodes;
+        result.transcript_quality = Some(transcript.quality());
+        result.transcript = escalation.include_transcript.then_some(TranscriptEvidence {
+            transcript,
+            source: TranscriptSource::Fresh,
+        });
+        Ok(())
+    }
 
-        let episode = if let Some(cached_episode) = matching_cache.load(&matching_cache_key)? {
-            // Cache hit - use cached matching result
-            progress_callback(ProgressEvent::MatchingCacheHit {
-                video_path: video.path.clone(),
-                episode: cached_episode.clone(),
-            });
-            cached_episode
-        } else {
-            // Cache miss - perform matching
-            progress_callback(ProgressEvent::Matching {
-                index,
-                total: videos.len(),
-                video_path: video.path.clone(),
-            });
+    /// Records that a video failed, or fails the investigation
+    ///
+    /// Unless the investigation keeps going, the error is returned. Matchers
+    /// finding no episode make the video unmatched rather than failed.
+    fn fail(
+        &mut self,
+        index: usize,
+        path: PathBuf,
+        transcript: Option<TranscriptEvidence>,
+        error: DialogDetectiveError,
+    ) -> Result<(), DialogDetectiveError> {
+        if !self.keep_going {
+            return Err(error);
+        }
 
-            let episode = matcher.match_episode(&transcript, &series)?;
+        self.other_outcomes[index] = Some(match error {
+            DialogDetectiveError::EpisodeMatching(EpisodeMatchingError::NoMatchFound {
+                response,
+            }) => InvestigationOutcome::Unmatched {
+                path,
+                reason: UnmatchedReason::NoMatchFound { response },
+                transcript,
+            },
+            error => InvestigationOutcome::Failed { path, error },
+        });
+        Ok(())
+    }
+
+    /// Records the result of a video with its timings
+    ///
+    /// The match is cross-checked against the episode stated in the file name.
+    fn finish(&mut self, index: usize, mut result: MatchResult) {
+        result.filename_conflict = check_filename(
+            &result.video.path,
+            &result.episode,
+            self.series.seasons.iter().flat_map(|s| &s.episodes),
+        );
+        result.timings = std::mem::take(&mut self.timings[index]);
+        self.results[index] = Some(result);
+    }
+}
 
-            // Store in cache for future use
-            matching_cache.store(&matching_cache_key, &episode)?;
+/// Matches a transcribed video, narrowing it down to its season first if enabled
+///
+/// Runs on a matching worker. The returned outcome's usage includes the
+/// season inference.
+fn match_video(
+    job: &MatchJob,
+    context: &MatchContext,
+    progress: &mut dyn FnMut(ProgressEvent),
+) -> Result<MatchOutcome, DialogDetectiveError> {
+    let video = &job.video;
+    let started = Instant::now();
+
+    // Narrow the candidates down to the inferred season, if enabled
+    let mut inference_usage = None;
+    let mut narrowed_series = None;
+    if context.infer_season {
+        let (inferred, outcome) =
+            infer_video_season(video, &job.transcript, context.series, context.matcher);
+
+        if let Some(outcome) = outcome {
+            inference_usage = outcome.usage;
+            if context.capture_prompts {
+                progress(ProgressEvent::PromptExchanged {
+                    video_path: video.path.clone(),
+                    kind: PromptKind::SeasonInference,
+                    prompt: outcome.prompt,
+                    response: outcome.response,
+                });
+            }
+        }
 
-            progress_callback(ProgressEvent::MatchingFinished {
+        if let Some((season, source)) = inferred {
+            progress(ProgressEvent::SeasonInferred {
                 video_path: video.path.clone(),
-                episode: episode.clone(),
+                season,
+                source,
             });
+            narrowed_series = narrow_to_season(context.series, season);
+        }
+    }
+
+    progress(ProgressEvent::Matching {
+        index: job.index,
+        total: context.total,
+        video_path: video.path.clone(),
+    });
+
+    let mut outcome = context.matcher.match_episode(
+        &job.transcript,
+        narrowed_series.as_ref().unwrap_or(context.series),
+    )?;
+    if context.capture_prompts {
+        progress(ProgressEvent::PromptExchanged {
+            video_path: video.path.clone(),
+            kind: PromptKind::EpisodeMatch,
+            prompt: outcome.prompt.clone(),
+            response: outcome.response.clone(),
+        });
+    }
+    outcome.usage = [inference_usage, outcome.usage]
+        .into_iter()
+        .flatten()
+        .reduce(|a, b| a + b);
+
+    progress(ProgressEvent::MatchingFinished {
+        video_path: video.path.clone(),
+        episode: outcome.episode.clone(),
+    });
+    progress(ProgressEvent::StageCompleted {
+        stage: Stage::Matching,
+        duration: started.elapsed(),
+    });
+
+    Ok(outcome)
+}
+
+/// Identifies a video by its OpenSubtitles hash
+///
+/// Lookups (including misses) are cached by hash. A hit is only accepted if
+/// it belongs to the investigated show and the episode is part of the
+/// fetched seasons. Lookup failures are treated as misses, so the video
+/// falls back to the dialogue pipeline.
+fn identify_by_hash(
+    video: &VideoFile,
+    lookup: &OpenSubtitlesLookup,
+    cache: &CacheStorage<Option<HashMatch>>,
+    series: &TVSeries,
+) -> Result<Option<Episode>, DialogDetectiveError> {
+    let Some(hash) = opensubtitles_hash(&video.path)? else {
+        return Ok(None);
+    };
+
+    let hash_match = match cache.load(&hash)? {
+        Some(cached) => cached,
+        None => match lookup.lookup(&hash) {
+            Ok(hash_match) => {
+                cache.store(&hash, &hash_match)?;
+                hash_match
+            }
+            Err(_) => None,
+        },
+    };
+
+    let Some(hash_match) = hash_match else {
+        return Ok(None);
+    };
+
+    // Compare show names loosely ("Marvel's Agents of S.H.I.E.L.D." vs "Marvels Agents of SHIELD")
+    let normalize = |name: &str| -> String {
+        name.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    if normalize(&hash_match.show_name) != normalize(&series.name) {
+        return Ok(None);
+    }
+
+    Ok(series
+        .seasons
+        .iter()
+        .filter(|season| season.season_number == hash_match.season_number)
+        .flat_map(|season| &season.episodes)
+        .find(|episode| episode.episode_number == hash_match.episode_number)
+        .cloned())
+}
+
+/// Infers the season of a video, first from its path, then from its transcript
+///
+/// Returns the season with its source (if one could be inferred) and the
+/// matcher's outcome, if it was asked. Inference is best-effort, so matcher
+/// failures are treated as "no season".
+fn infer_video_season(
+    video: &VideoFile,
+    transcript: &Transcript,
+    series: &TVSeries,
+    matcher: &dyn EpisodeMatcher,
+) -> (Option<(usize, SeasonSource)>, Option<SeasonOutcome>) {
+    if let Some(season) = season_from_path(&video.path)
+        && series.seasons.iter().any(|s| s.season_number == season)
+    {
+        return (Some((season, SeasonSource::Filename)), None);
+    }
+
+    match matcher.infer_season(&transcript_excerpt(transcript), series) {
+        Ok(outcome) => (
+            Some((outcome.season, SeasonSource::Transcript)),
+            Some(outcome),
+        ),
+        Err(_) => (None, None),
+    }
+}
+
+/// Estimates the work, duration, and cost of an investigation without running it
+///
+/// Fetches the show metadata, scans the directory, and hashes every video to
+/// check which transcripts and matches are already cached. From this it
+/// predicts how many transcriptions and matcher calls are still needed, a
+/// rough duration, and the token usage and cost of the matcher calls. Nothing
+/// is transcribed or matched, so no Whisper model is required.
+///
+/// Cached matches are keyed by the metadata provider, its language and
+/// episode order, and whether seasons are inferred, so pass the metadata
+/// options and the cache namespace of the investigation to find them (see
+/// `Investigation::cache_namespace`). A detected metadata language isn't
+/// known without transcribing, so pass the language it was detected as
+/// before, if any.
+///
+/// Emits the start, metadata, scanning, and hashing progress events of
+/// `investigate_case`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dialog_detective::{estimate_case, MatcherType, MetadataOptions};
+/// use std::path::Path;
+///
+/// let estimate = estimate_case(
+///     Path::new("/path/to/videos"),
+///     "Breaking Bad",
+///     &MetadataOptions::default(),
+///     false,
+///     MatcherType::GeminiFlash,
+///     None,
+///     |_| {},
+///     |_| Ok(0),
+/// ).unwrap();
+///
+/// println!(
+///     "{} matcher calls, ~{} tokens",
+///     estimate.matcher_calls_needed,
+///     estimate.usage.total_tokens()
+/// );
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn estimate_case<F, S>(
+    directory: &Path,
+    show_name: &str,
+    metadata: &MetadataOptions,
+    infer_season: bool,
+    matcher_type: MatcherType,
+    cache_namespace: Option<&str>,
+    mut progress_callback: F,
+    select_series: S,
+) -> Result<CaseEstimate, DialogDetectiveError>
+where
+    F: FnMut(ProgressEvent),
+    S: FnOnce(&[SeriesCandidate]) -> Result<usize, DialogDetectiveError>,
+{
+    progress_callback(ProgressEvent::Started {
+        directory: directory.to_path_buf(),
+        show_name: show_name.to_string(),
+    });
+    progress_callback(ProgressEvent::FetchingMetadata {
+        show_name: show_name.to_string(),
+    });
+
+    let one_day = Some(Duration::from_secs(24 * 60 * 60));
+    let transcript_cache = CacheStorage::<Transcript>::open("transcripts", one_day)?.encrypted()?;
+    let matching_cache =
+        CacheStorage::<CachedMatch>::open_namespaced("matching", cache_namespace, one_day)?;
+
+    let season_filter = &metadata.season_filter;
+    let provider = default_metadata_provider(metadata)?;
+    let series = fetch_selected_series(provider.as_ref(), show_name, season_filter, select_series)?;
+
+    progress_callback(ProgressEvent::MetadataFetched {
+        series_name: series.name.clone(),
+        year: series.year,
+        season_count: series.seasons.len(),
+    });
+
+    progress_callback(ProgressEvent::ScanningVideos);
+    let videos = find_files(directory, &RetryPolicy::default(), &mut |scan| {
+        progress_callback(ProgressEvent::DirectoryScanned { scan: scan.clone() })
+    })?
+    .videos;
+    progress_callback(ProgressEvent::VideosFound {
+        count: videos.len(),
+    });
+
+    // The prompt without transcript is the same for every video
+    let empty_transcript = Transcript::new("", "");
+    let prompt_chars = NaivePromptGenerator
+        .generate_single_prompt(&empty_transcript, &series)
+        .len();
+    let mut estimator = Estimator::new(matcher_type, prompt_chars);
+    let matcher = build_matcher(matcher_type, &MatcherCommand::default(), None);
+    let (matcher_id, matcher_version) = (matcher.id(), matcher.version());
+    let metadata_key = provider_key(&provider.id(), provider.language().as_deref());
+
+    for (index, video) in videos.iter().enumerate() {
+        progress_callback(ProgressEvent::ProcessingVideo {
+            index,
+            total: videos.len(),
+            video_path: video.path.clone(),
+        });
+        progress_callback(ProgressEvent::Hashing {
+            video_path: video.path.clone(),
+        });
+        let started = Instant::now();
+        let (video_hash, bytes) = compute_video_hash(
+            &video.path,
+            &HashOptions::default(),
+            &RetryPolicy::default(),
+            &mut |bytes_done, total_bytes| {
+                progress_callback(ProgressEvent::HashingProgress {
+                    video_path: video.path.clone(),
+                    bytes_done,
+                    total_bytes,
+                });
+                true
+            },
+        )?;
+        progress_callback(ProgressEvent::HashingFinished {
+            video_path: video.path.clone(),
+            bytes,
+            duration: started.elapsed(),
+        });
+
+        let cached_transcript = transcript_cache.load(&video_hash)?;
+        let matching_cache_key = compute_matching_cache_key(
+            &video_hash,
+            show_name,
+            season_filter,
+            infer_season,
+            &metadata_key,
+            &matcher_id,
+            matcher_version,
+        );
+        let match_cached = matching_cache.load(&matching_cache_key)?.is_some();
+
+        estimator.add_video(cached_transcript.map(|t| t.text.len()), match_cached);
+    }
+
+    Ok(estimator.finish())
+}
 
-            episode
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_timings_record_accumulates() {
+        let mut timings = StageTimings::default();
+        timings.record(Stage::Transcription, Duration::from_secs(2));
+        timings.record(Stage::Transcription, Duration::from_secs(3));
+        timings.record(Stage::Hashing, Duration::from_millis(500));
+
+        assert_eq!(timings.transcription, Some(Duration::from_secs(5)));
+        assert_eq!(timings.get(Stage::Matching), None);
+        assert_eq!(timings.total(), Duration::from_millis(5500));
+    }
+
+    #[test]
+    fn test_matching_cache_key_versions() {
+        let key = |seasons: Option<Vec<usize>>, infer_season, version| {
+            compute_matching_cache_key(
+                "abc",
+                "Breaking Bad",
+                &seasons,
+                infer_season,
+                "tvmaze",
+                "claude",
+                version,
+            )
         };
 
-        let match_result = MatchResult {
-            video: video.clone(),
-            episode,
+        assert_eq!(
+            key(Some(vec![2, 1]), false, 1),
+            "abc_breaking_bad_1-2_tvmaze_claude_v1"
+        );
+        assert_eq!(key(None, true, 1), "abc_breaking_bad_auto_tvmaze_claude_v1");
+        assert_ne!(key(None, false, 1), key(None, false, 2));
+    }
+
+    #[test]
+    fn test_matching_cache_key_metadata() {
+        let key = |provider: &dyn MetadataProvider| {
+            let metadata_key = provider_key(&provider.id(), provider.language().as_deref());
+            compute_matching_cache_key("abc", "Lost", &None, false, &metadata_key, "claude", 1)
         };
 
-        match_results.push(match_result);
+        let default_order = key(&TvMazeProvider::new());
+        let dvd_order = key(&TvMazeProvider::new().with_order(EpisodeOrder::Dvd));
+        assert_ne!(default_order, dvd_order);
+        assert_ne!(default_order, key(&WikipediaProvider::new()));
+        let german = provider_key("tvmaze", Some("de"));
+        assert_ne!(
+            default_order,
+            compute_matching_cache_key("abc", "Lost", &None, false, &german, "claude", 1)
+        );
     }
 
-    progress_callback(ProgressEvent::Complete {
-        match_count: match_results.len(),
-    });
+    #[test]
+    fn test_cached_match_migration() {
+        let legacy: CachedMatch = serde_json::from_str(
+            r#"{"season_number": 1, "episode_number": 2, "name": "Cat's in the Bag...",
+                "summary": "The body."}"#,
+        )
+        .unwrap();
+        let record = legacy.into_record("claude");
+        assert_eq!(record.episode.episode_number, 2);
+        assert_eq!(record.confidence, None);
+        assert_eq!(record.matcher, "claude");
+        assert_eq!(record.version, None);
+
+        let outcome = MatchOutcome {
+            episode: record.episode.clone(),
+            confidence: Some(0.7),
+            alternatives: Vec::new(),
+            reasoning: Some("The barrel is mentioned.".to_string()),
+            extra_episodes: Vec::new(),
+            prompt: String::new(),
+            response: r#"{"season": 1, "episode": 2}"#.to_string(),
+            usage: None,
+        };
+        let json = serde_json::to_string(&CachedMatch::Record(MatchRecord::new(
+            &outcome,
+            "claude",
+            "Breaking Bad",
+        )))
+        .unwrap();
+        let record = serde_json::from_str::<CachedMatch>(&json)
+            .unwrap()
+            .into_record("gemini-flash");
+        assert_eq!(record.confidence, Some(0.7));
+        assert_eq!(
+            record.reasoning.as_deref(),
+            Some("The barrel is mentioned.")
+        );
+        assert_eq!(record.response.as_deref(), Some(outcome.response.as_str()));
+        assert_eq!(record.matcher, "claude");
+        assert_eq!(record.version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn test_cache_namespace_for() {
+        let a = cache_namespace_for(Path::new("/media/a/TV"));
+        let b = cache_namespace_for(Path::new("/media/b/TV"));
+
+        assert!(a.starts_with("TV-"), "{}", a);
+        assert_eq!(a.len(), "TV-".len() + 8);
+        assert_ne!(a, b);
+        assert_eq!(a, cache_namespace_for(Path::new("/media/a/TV")));
+    }
 
-    Ok(match_results)
+    #[test]
+    fn test_error_codes() {
+        let code = |error: DialogDetectiveError| error.code();
+        assert_eq!(
+            code(AudioExtractionError::FfmpegNotInstalled.into()),
+            "DD-AUD-001"
+        );
+        assert_eq!(
+            code(
+                EpisodeMatchingError::NoMatchFound {
+                    response: String::new()
+                }
+                .into()
+            ),
+            "DD-MAT-003"
+        );
+        assert_eq!(
+            code(EpisodeMatchingError::TimedOut(Duration::from_secs(60)).into()),
+            "DD-MAT-004"
+        );
+        assert_eq!(code(DialogDetectiveError::SelectionCancelled), "DD-GEN-001");
+        assert_eq!(
+            code(DialogDetectiveError::TooManyFiles {
+                count: 2000,
+                limit: 1000
+            }),
+            "DD-GEN-002"
+        );
+        assert_eq!(
+            code(io::Error::from(io::ErrorKind::NotFound).into()),
+            "DD-IO-001"
+        );
+
+        let transient = RetryPolicy {
+            retries: 0,
+            ..RetryPolicy::default()
+        }
+        .run(|| -> io::Result<()> { Err(io::ErrorKind::TimedOut.into()) })
+        .unwrap_err();
+        assert_eq!(code(transient.into()), "DD-IO-002");
+
+        let format_error = FileOperationError::InvalidFormat(FormatError::UnmatchedBrace(3));
+        assert_eq!(format_error.code(), "DD-FMT-002");
+    }
 }
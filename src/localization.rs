@@ -0,0 +1,657 @@
+//! Localization module
+//!
+//! DialogDetective is run by home users sorting their own recordings, and
+//! not all of them read English. The messages of the command line
+//! interface are looked up by key in a catalog holding each message in
+//! every supported language, and `{name}` placeholders in them are filled
+//! in when rendering.
+
+use std::env;
+use std::fmt;
+
+/// A language the command line interface can be shown in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    /// English, also used for unsupported locales
+    #[default]
+    English,
+    /// German
+    German,
+}
+
+impl Language {
+    /// Parses a language code or locale, e.g. "de", "de-AT", or "de_DE.UTF-8"
+    ///
+    /// Returns None for unsupported languages and the "C" and "POSIX" locales.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let language = code
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "en" => Some(Language::English),
+            "de" => Some(Language::German),
+            _ => None,
+        }
+    }
+
+    /// Detects the language from the locale environment variables
+    ///
+    /// Follows the POSIX precedence of `LC_ALL`, `LC_MESSAGES`, and `LANG`;
+    /// the first one set decides. Falls back to English.
+    pub fn detect() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|locale| Self::from_code(&locale))
+            .unwrap_or_default()
+    }
+}
+
+/// Messages by key, in English and German
+///
+/// Placeholders are written as `{name}` and have to be the same in both
+/// languages.
+const MESSAGES: &[(&str, &str, &str)] = &[
+    // Progress
+    (
+        "progress.investigating",
+        "📺 Investigating: {show}",
+        "📺 Ermittlung: {show}",
+    ),
+    (
+        "progress.language_detected",
+        "🗣️ Detected language: {language}",
+        "🗣️ Erkannte Sprache: {language}",
+    ),
+    (
+        "progress.fetching_metadata",
+        "📡 Fetching metadata... ",
+        "📡 Lade Metadaten... ",
+    ),
+    (
+        "progress.metadata_fetched",
+        "✓ ({count} seasons)",
+        "✓ ({count} Staffeln)",
+    ),
+    (
+        "progress.summaries_missing",
+        "⚠️  {missing} of {total} episodes have no summary, matching by titles and air dates",
+        "⚠️  {missing} von {total} Episoden haben keine Zusammenfassung, Zuordnung über Titel und Ausstrahlungsdaten",
+    ),
+    (
+        "progress.scanning",
+        "🔎 Scanning directory... ",
+        "🔎 Durchsuche Verzeichnis... ",
+    ),
+    (
+        "progress.directories_scanned",
+        "{directories} folders, {files} files ",
+        "{directories} Ordner, {files} Dateien ",
+    ),
+    (
+        "progress.no_videos",
+        "✗ No videos found",
+        "✗ Keine Videos gefunden",
+    ),
+    (
+        "progress.videos_found",
+        "✓ ({count} files)",
+        "✓ ({count} Dateien)",
+    ),
+    (
+        "progress.hashing",
+        "   ├─ Computing hash... ",
+        "   ├─ Berechne Hash... ",
+    ),
+    (
+        "progress.extracting",
+        "   ├─ Extracting audio... ",
+        "   ├─ Extrahiere Audio... ",
+    ),
+    (
+        "progress.transcribing",
+        "   ├─ Transcribing... ",
+        "   ├─ Transkribiere... ",
+    ),
+    (
+        "progress.transcript_cached",
+        "   ├─ Transcript cached... ✓ ({language})",
+        "   ├─ Transkript im Cache... ✓ ({language})",
+    ),
+    (
+        "progress.transcript_quality",
+        "   ├─ Transcript quality: {score}",
+        "   ├─ Transkript-Qualität: {score}",
+    ),
+    (
+        "progress.escalating",
+        "🔁 {file}: Match uncertain ({percent}%), trying again with step {step} ({model})...",
+        "🔁 {file}: Zuordnung unsicher ({percent}%), neuer Versuch mit Stufe {step} ({model})...",
+    ),
+    (
+        "progress.transcription_retried",
+        "   ├─ Low transcript quality ({score}), transcribing again with {model}... 🔁",
+        "   ├─ Geringe Transkript-Qualität ({score}), erneutes Transkribieren mit {model}... 🔁",
+    ),
+    (
+        "progress.hash_matched",
+        "   └─ Identified by file hash... ✓ ({episode})",
+        "   └─ Am Datei-Hash erkannt... ✓ ({episode})",
+    ),
+    (
+        "progress.no_dialogue",
+        "   └─ No dialogue found ({words} words), not matching 🔇",
+        "   └─ Kein Dialog gefunden ({words} Wörter), keine Zuordnung 🔇",
+    ),
+    (
+        "progress.matching_queued",
+        "   └─ Matching in background... ⏳",
+        "   └─ Zuordnung im Hintergrund... ⏳",
+    ),
+    (
+        "progress.season_inferred",
+        "🧭 {file}: Season {season} inferred ({source})",
+        "🧭 {file}: Staffel {season} ermittelt ({source})",
+    ),
+    (
+        "progress.season_from_filename",
+        "from filename",
+        "aus dem Dateinamen",
+    ),
+    (
+        "progress.season_from_dialogue",
+        "from dialogue",
+        "aus den Dialogen",
+    ),
+    (
+        "progress.matching",
+        "🤖 [{index}/{total}] {file}: Matching...",
+        "🤖 [{index}/{total}] {file}: Ordne zu...",
+    ),
+    (
+        "progress.matched",
+        "🎯 {file}: Matched... ✓ ({episode})",
+        "🎯 {file}: Zugeordnet... ✓ ({episode})",
+    ),
+    (
+        "progress.match_cached",
+        "   └─ Match cached... ✓ ({episode})",
+        "   └─ Zuordnung im Cache... ✓ ({episode})",
+    ),
+    (
+        "progress.prompt_exchanged",
+        "💬 {file}: {kind} prompt ({prompt} chars) answered ({response} chars)",
+        "💬 {file}: {kind}-Prompt ({prompt} Zeichen) beantwortet ({response} Zeichen)",
+    ),
+    (
+        "progress.stage_took",
+        "   ⏱️  {stage} took {duration}",
+        "   ⏱️  {stage} dauerte {duration}",
+    ),
+    (
+        "progress.cache_entry_quarantined",
+        "⚠️  Unreadable cache entry moved to {path}",
+        "⚠️  Unlesbarer Cache-Eintrag verschoben nach {path}",
+    ),
+    (
+        "progress.scan_warning",
+        "⚠️  Skipping unreadable {path}: {message}",
+        "⚠️  Überspringe nicht lesbares {path}: {message}",
+    ),
+    (
+        "progress.file_skipped",
+        "⏳ Skipping {file}: {reason}",
+        "⏳ Überspringe {file}: {reason}",
+    ),
+    ("skip.not_a_video", "not a video", "kein Video"),
+    (
+        "skip.downloading",
+        "download in progress",
+        "Download läuft noch",
+    ),
+    (
+        "skip.still_growing",
+        "still being written",
+        "wird noch geschrieben",
+    ),
+    (
+        "skip.no_video_stream",
+        "ffprobe found no video stream",
+        "ffprobe fand keine Videospur",
+    ),
+    (
+        "skip.unverified",
+        "content not recognized and ffprobe unavailable",
+        "Inhalt nicht erkannt und ffprobe nicht verfügbar",
+    ),
+    ("skip.unreadable", "not readable", "nicht lesbar"),
+    // Show detection and series selection
+    (
+        "detect.detecting",
+        "🔎 No show name given, detecting it from the file names...",
+        "🔎 Kein Serienname angegeben, ermittle ihn aus den Dateinamen...",
+    ),
+    (
+        "detect.failed",
+        "❌ Show detection failed: {error}",
+        "❌ Serienerkennung fehlgeschlagen: {error}",
+    ),
+    (
+        "detect.no_guesses",
+        "❌ Error: Could not detect the show, the names don't mention one",
+        "❌ Fehler: Serie nicht erkannt, die Namen nennen keine",
+    ),
+    (
+        "detect.unknown_guesses",
+        "❌ Error: Could not detect the show, none of these is a known series: {guesses}",
+        "❌ Fehler: Serie nicht erkannt, keiner dieser Namen ist eine bekannte Serie: {guesses}",
+    ),
+    (
+        "detect.ambiguous",
+        "❌ Error: The detected show is ambiguous: {names}",
+        "❌ Fehler: Die erkannte Serie ist mehrdeutig: {names}",
+    ),
+    (
+        "detect.pass_show_name",
+        "   Pass the show name after the directory",
+        "   Gib den Seriennamen nach dem Verzeichnis an",
+    ),
+    (
+        "detect.detected",
+        "📺 Detected show: {show}",
+        "📺 Erkannte Serie: {show}",
+    ),
+    (
+        "select.prompt",
+        "🔎 Multiple matches found — select the correct series",
+        "🔎 Mehrere Treffer gefunden — wähle die richtige Serie",
+    ),
+    (
+        "confirm.many_files",
+        "⚠️  Found {count} videos, more than --max-files. Investigate all of them?",
+        "⚠️  {count} Videos gefunden, mehr als --max-files. Alle untersuchen?",
+    ),
+    (
+        "select.unknown_year",
+        "{name} (unknown year)",
+        "{name} (Jahr unbekannt)",
+    ),
+    (
+        "select.using",
+        "⚠️  Multiple series found, using {series}",
+        "⚠️  Mehrere Serien gefunden, verwende {series}",
+    ),
+    // Errors
+    ("error.generic", "❌ Error: {error}", "❌ Fehler: {error}"),
+    (
+        "error.directory_missing",
+        "❌ Error: Directory does not exist: {path}",
+        "❌ Fehler: Verzeichnis existiert nicht: {path}",
+    ),
+    (
+        "error.not_a_directory",
+        "❌ Error: Path is not a directory: {path}",
+        "❌ Fehler: Pfad ist kein Verzeichnis: {path}",
+    ),
+    (
+        "error.invalid_format",
+        "❌ Error: Invalid --format: {error}",
+        "❌ Fehler: Ungültiges --format: {error}",
+    ),
+    (
+        "error.try_format_check",
+        "   Try: dialog_detective format check \"{format}\"",
+        "   Versuche: dialog_detective format check \"{format}\"",
+    ),
+    (
+        "error.model_missing",
+        "❌ Error: Model file does not exist: {path}",
+        "❌ Fehler: Modelldatei existiert nicht: {path}",
+    ),
+    (
+        "error.model_not_a_file",
+        "❌ Error: Model path is not a file: {path}",
+        "❌ Fehler: Modellpfad ist keine Datei: {path}",
+    ),
+    (
+        "error.unsupported_model",
+        "❌ Error: Unsupported model '{model}'",
+        "❌ Fehler: Nicht unterstütztes Modell '{model}'",
+    ),
+    (
+        "error.supported_models",
+        "Supported models:",
+        "Unterstützte Modelle:",
+    ),
+    (
+        "error.tip_list_models",
+        "💡 Tip: Use --list-models to see all available models with details",
+        "💡 Tipp: --list-models zeigt alle verfügbaren Modelle mit Details",
+    ),
+    (
+        "error.model_download",
+        "❌ Error: Failed to download Whisper model '{model}': {error}",
+        "❌ Fehler: Whisper-Modell '{model}' konnte nicht heruntergeladen werden: {error}",
+    ),
+    (
+        "error.tip_model_path",
+        "💡 Tip: You can manually specify a model path with --model-path",
+        "💡 Tipp: Mit --model-path lässt sich eine Modelldatei angeben",
+    ),
+    (
+        "error.tip_offline",
+        "💡 Tip: Run once without --offline to download the model",
+        "💡 Tipp: Einmal ohne --offline ausführen, um das Modell herunterzuladen",
+    ),
+    (
+        "error.output_dir_required",
+        "❌ Error: --output-dir is required when using --mode copy",
+        "❌ Fehler: --mode copy benötigt --output-dir",
+    ),
+    (
+        "error.sonarr_url_required",
+        "❌ Error: --sonarr-url is required when using --mode sonarr",
+        "❌ Fehler: --mode sonarr benötigt --sonarr-url",
+    ),
+    (
+        "error.sonarr_key_required",
+        "❌ Error: --sonarr-api-key or SONARR_API_KEY is required when using --mode sonarr",
+        "❌ Fehler: --mode sonarr benötigt --sonarr-api-key oder SONARR_API_KEY",
+    ),
+    (
+        "error.trakt_credentials_required",
+        "❌ Error: TRAKT_CLIENT_ID and TRAKT_CLIENT_SECRET of a Trakt API app are required for Trakt",
+        "❌ Fehler: Trakt benötigt TRAKT_CLIENT_ID und TRAKT_CLIENT_SECRET einer Trakt-API-App",
+    ),
+    (
+        "error.trakt_login_required",
+        "❌ Error: --trakt-collect requires signing in with `dialog_detective trakt login` first",
+        "❌ Fehler: --trakt-collect erfordert vorher eine Anmeldung mit `dialog_detective trakt login`",
+    ),
+    (
+        "error.min_transcript_quality",
+        "❌ Error: --min-transcript-quality must be between 0 and 1",
+        "❌ Fehler: --min-transcript-quality muss zwischen 0 und 1 liegen",
+    ),
+    (
+        "error.thresholds",
+        "❌ Error: Confidence thresholds must be between 0 and 1, with --review-threshold not above --auto-apply-threshold",
+        "❌ Fehler: Schwellwerte müssen zwischen 0 und 1 liegen, --review-threshold nicht über --auto-apply-threshold",
+    ),
+    (
+        "error.prompt_directory",
+        "❌ Error: Failed to create prompt directory {path}: {error}",
+        "❌ Fehler: Prompt-Verzeichnis {path} konnte nicht angelegt werden: {error}",
+    ),
+    (
+        "error.matcher_unavailable",
+        "❌ Error: {name}: {detail}",
+        "❌ Fehler: {name}: {detail}",
+    ),
+    (
+        "error.matcher_warning",
+        "⚠️  {name}: {detail}",
+        "⚠️  {name}: {detail}",
+    ),
+    ("error.tip_matcher", "💡 Tip: {fix}", "💡 Tipp: {fix}"),
+    (
+        "error.review_file",
+        "❌ Failed to write review file: {error}",
+        "❌ Prüfdatei konnte nicht geschrieben werden: {error}",
+    ),
+    (
+        "error.investigation_failed",
+        "❌ Investigation failed ({code}): {error}",
+        "❌ Ermittlung fehlgeschlagen ({code}): {error}",
+    ),
+    (
+        "error.too_many_files",
+        "❌ Stopped: found {count} videos, more than --max-files {limit}. Check the directory, or pass a higher --max-files (0 for no limit) to go on.",
+        "❌ Abgebrochen: {count} Videos gefunden, mehr als --max-files {limit}. Prüfe das Verzeichnis oder gib ein höheres --max-files an (0 für kein Limit), um fortzufahren.",
+    ),
+    (
+        "error.plan_failed",
+        "❌ Failed to plan operations: {error}",
+        "❌ Dateioperationen konnten nicht geplant werden: {error}",
+    ),
+    (
+        "error.rename_failed",
+        "❌ Rename operation failed: {error}",
+        "❌ Umbenennen fehlgeschlagen: {error}",
+    ),
+    (
+        "error.copy_failed",
+        "❌ Copy operation failed: {error}",
+        "❌ Kopieren fehlgeschlagen: {error}",
+    ),
+    // Results
+    (
+        "result.no_matches",
+        "❌ Case closed: No matches found",
+        "❌ Fall abgeschlossen: Keine Zuordnungen gefunden",
+    ),
+    (
+        "result.failed_videos",
+        "⚠️  Failed to investigate {count} file(s):",
+        "⚠️  {count} Datei(en) nicht untersucht:",
+    ),
+    (
+        "result.none_confident",
+        "❌ Case closed: No matches confident enough to apply",
+        "❌ Fall abgeschlossen: Keine Zuordnung sicher genug zum Anwenden",
+    ),
+    (
+        "result.review_written",
+        "📝 Wrote {count} match(es) for review to {path}",
+        "📝 {count} Zuordnung(en) zur Prüfung in {path} geschrieben",
+    ),
+    (
+        "result.dry_run",
+        "📋 Dry Run - No files will be modified:",
+        "📋 Probelauf - Es werden keine Dateien verändert:",
+    ),
+    (
+        "result.dry_run_hint",
+        "💡 Use --mode rename or --mode copy to apply these changes",
+        "💡 Mit --mode rename oder --mode copy werden die Änderungen angewendet",
+    ),
+    (
+        "result.diff_since",
+        "📋 Dry Run - Changes since the dry run of {time}:",
+        "📋 Probelauf - Änderungen seit dem Probelauf vom {time}:",
+    ),
+    (
+        "result.diff_first",
+        "💡 No earlier dry run of this directory, showing the whole plan",
+        "💡 Kein früherer Probelauf dieses Verzeichnisses, zeige den ganzen Plan",
+    ),
+    (
+        "result.diff_changed",
+        "  ~ [{kind}] {source} → {destination} (was {previous})",
+        "  ~ [{kind}] {source} → {destination} (vorher {previous})",
+    ),
+    (
+        "result.diff_removed",
+        "  - {file} (no longer planned)",
+        "  - {file} (nicht mehr geplant)",
+    ),
+    (
+        "result.diff_unchanged",
+        "  {count} file(s) unchanged",
+        "  {count} Datei(en) unverändert",
+    ),
+    (
+        "result.renaming",
+        "📝 Renaming files...",
+        "📝 Benenne Dateien um...",
+    ),
+    (
+        "result.renamed",
+        "✅ Successfully renamed {count} file(s)",
+        "✅ {count} Datei(en) umbenannt",
+    ),
+    (
+        "result.rename_errors",
+        "❌ Failed to rename {count} file(s):",
+        "❌ {count} Datei(en) nicht umbenannt:",
+    ),
+    (
+        "result.copying",
+        "📦 Copying files to {path}...",
+        "📦 Kopiere Dateien nach {path}...",
+    ),
+    (
+        "result.copied_to",
+        "✅ Successfully copied {count} file(s) to {path}",
+        "✅ {count} Datei(en) nach {path} kopiert",
+    ),
+    (
+        "result.copied",
+        "✅ Successfully copied {count} file(s)",
+        "✅ {count} Datei(en) kopiert",
+    ),
+    (
+        "result.copy_errors",
+        "❌ Failed to copy {count} file(s):",
+        "❌ {count} Datei(en) nicht kopiert:",
+    ),
+    (
+        "result.transient_hint",
+        "💡 Some files failed with network errors that may be temporary, try again later",
+        "💡 Einige Dateien scheiterten an eventuell vorübergehenden Netzwerkfehlern, versuche es später erneut",
+    ),
+    (
+        "result.completed_with_errors",
+        "⚠️  Operation completed with errors:",
+        "⚠️  Vorgang mit Fehlern abgeschlossen:",
+    ),
+    // Run summary
+    ("summary.title", "📊 Run summary", "📊 Zusammenfassung"),
+    (
+        "summary.videos",
+        "   ├─ Videos processed: {count}",
+        "   ├─ Verarbeitete Videos: {count}",
+    ),
+    (
+        "summary.transcripts",
+        "   ├─ Transcripts: {cached} cached, {created} transcribed{rate}",
+        "   ├─ Transkripte: {cached} aus dem Cache, {created} transkribiert{rate}",
+    ),
+    (
+        "summary.matches",
+        "   ├─ Matches: {cached} cached, {hashed} by file hash, {created} matched{rate}",
+        "   ├─ Zuordnungen: {cached} aus dem Cache, {hashed} per Datei-Hash, {created} zugeordnet{rate}",
+    ),
+    (
+        "summary.hit_rate",
+        ", {percent}% from cache",
+        ", {percent}% aus dem Cache",
+    ),
+    (
+        "summary.transcription_time",
+        "   ├─ Transcription time: {duration}",
+        "   ├─ Transkriptionszeit: {duration}",
+    ),
+    (
+        "summary.confidence",
+        "   ├─ Average confidence: {percent}%",
+        "   ├─ Durchschnittliche Sicherheit: {percent}%",
+    ),
+    (
+        "summary.matcher_failures",
+        "   ├─ Matcher failures: {count}",
+        "   ├─ Matcher-Fehler: {count}",
+    ),
+    (
+        "summary.written",
+        "   └─ Disk written: {size}",
+        "   └─ Geschrieben: {size}",
+    ),
+];
+
+/// Renders the message with the given key in a language
+///
+/// Placeholders are replaced by the argument of the same name; those
+/// without an argument are kept as they are. Unknown keys are returned
+/// unchanged, so a missing message is easy to spot.
+pub fn localize(language: Language, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let Some((_, english, german)) = MESSAGES.iter().find(|(k, _, _)| *k == key) else {
+        return key.to_string();
+    };
+    let template = match language {
+        Language::English => english,
+        Language::German => german,
+    };
+
+    let mut message = template.to_string();
+    for (name, value) in args {
+        message = message.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the names of the placeholders in a message template
+    fn placeholders(template: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = template
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_language_from_code() {
+        assert_eq!(Language::from_code("de"), Some(Language::German));
+        assert_eq!(Language::from_code("de_DE.UTF-8"), Some(Language::German));
+        assert_eq!(Language::from_code("DE-at"), Some(Language::German));
+        assert_eq!(Language::from_code("en_US"), Some(Language::English));
+        assert_eq!(Language::from_code("C.UTF-8"), None);
+        assert_eq!(Language::from_code("fr_FR"), None);
+    }
+
+    #[test]
+    fn test_localize() {
+        assert_eq!(
+            localize(Language::German, "progress.videos_found", &[("count", &3)]),
+            "✓ (3 Dateien)"
+        );
+        assert_eq!(
+            localize(
+                Language::English,
+                "progress.season_inferred",
+                &[("file", &"a.mkv"), ("season", &2)]
+            ),
+            "🧭 a.mkv: Season 2 inferred ({source})"
+        );
+        assert_eq!(
+            localize(Language::German, "no.such.key", &[]),
+            "no.such.key"
+        );
+    }
+
+    #[test]
+    fn test_messages_consistent() {
+        for (index, (key, english, german)) in MESSAGES.iter().enumerate() {
+            assert!(
+                MESSAGES[..index].iter().all(|(k, _, _)| k != key),
+                "duplicate key {}",
+                key
+            );
+            assert_eq!(
+                placeholders(english),
+                placeholders(german),
+                "placeholders of {}",
+                key
+            );
+        }
+    }
+}
@@ -1,10 +1,126 @@
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 use dialog_detective::{
-    DialogDetectiveError, MatcherType, ProgressEvent, SeriesCandidate, execute_copy,
-    execute_rename, investigate_case, model_downloader, plan_operations,
+    CheckStatus, CopyOptions, CopyProgress, DEFAULT_FORMAT, DEFAULT_MAX_EPISODES_PER_PROMPT,
+    DEFAULT_MIN_TRANSCRIPT_QUALITY, DEFAULT_PARALLEL_MATCHES, DUPLICATES_DIR, DialogDetectiveError,
+    DuplicatePolicy, Episode, EpisodeMatchingError, EpisodeOrder, FORMAT_PLACEHOLDERS,
+    FilenameMetadata, FilenameStyle, FormatWarning, HashOptions, History, HistoryAction,
+    HistoryQuery, HookEvent, HookLog, HookProfile, Investigation, InvestigationOutcome, JobQueue,
+    Language, LanguageStrategy, MatchResult, MatcherCommand, MatcherType, MetadataOptions,
+    MetadataProviderType, ModelSpec, NamingOptions, PlannedOperation, PluginError, ProgressEvent,
+    PromptKind, QueuedJob, RenameOptions, RetryPolicy, RunMetrics, RunSummary, SavedPlan,
+    SeasonSource, SeriesCandidate, SkipReason, SonarrClient, SonarrImportMode, Stage, StageTimings,
+    SubtitleMode, TokenUsage, TraktClient, TranscriptReuse, Triage, TriageThresholds,
+    UnmatchedReason, WhisperTranscriber, apply_duplicate_policy, assign_episodes, audit_library,
+    cache_namespace_for, check_format, check_free_space, check_matcher, default_history_path,
+    default_hook_log_path, default_plans_dir, default_plugins_path, default_profiles_path,
+    default_queue_path, detect_show, diff_plan, estimate_case, execute_copy_with, execute_hardlink,
+    execute_rename_atomic, execute_rename_with, format_filename_with, gave_up_retrying,
+    load_hook_profile, load_plan, load_provider_plugin, localize, model_downloader,
+    plan_operations, plan_operations_with, plan_quarantine, prefetch_show_metadata,
+    purge_show_cache, purge_video_cache, run_health_checks, save_plan, serve_metrics,
+    set_air_date_mtimes, triage_matches, validate_format, write_subtitles, write_tags,
 };
-use std::path::PathBuf;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Exit code: every video was matched (and the changes were applied)
+const EXIT_MATCHED: i32 = 0;
+
+/// Exit code: invalid arguments or options
+const EXIT_USAGE: i32 = 1;
+
+/// Exit code: some videos were matched, others need review, weren't
+/// matched, or failed to be renamed or copied
+const EXIT_PARTIAL: i32 = 2;
+
+/// Exit code: no video was matched confidently
+const EXIT_UNMATCHED: i32 = 3;
+
+/// Exit code: the environment failed, e.g. a missing tool or model, a
+/// network error, or an unwritable directory
+const EXIT_ENVIRONMENT: i32 = 4;
+
+/// Number of videos above which a run asks before going on
+const DEFAULT_MAX_FILES: usize = 1000;
+
+/// How much is printed, set once from the command line
+static VERBOSITY: OnceLock<Verbosity> = OnceLock::new();
+
+/// Amount of output, from errors only to every progress event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+    /// Errors only (`-q`)
+    Quiet,
+    /// Progress per video and the results
+    Normal,
+    /// Every processing step of each video (`-v`)
+    Verbose,
+    /// Stage timings and matcher calls as well (`-vv`)
+    Debug,
+}
+
+impl Verbosity {
+    /// Maps the `-q` and `-v` flags to a level
+    fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+}
+
+/// Returns the verbosity of this run (normal for subcommands)
+fn verbosity() -> Verbosity {
+    VERBOSITY.get().copied().unwrap_or(Verbosity::Normal)
+}
+
+/// Language of the output, set once from the command line
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+/// Returns the language of the output (from the locale for subcommands)
+fn language() -> Language {
+    *LANGUAGE.get_or_init(Language::detect)
+}
+
+/// Renders a message in the output language, filling in its placeholders
+macro_rules! tr {
+    ($key:literal) => {
+        localize(language(), $key, &[])
+    };
+    ($key:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        localize(
+            language(),
+            $key,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+],
+        )
+    };
+}
+
+/// Prints to stdout unless running quietly
+macro_rules! out {
+    ($($arg:tt)*) => {
+        if verbosity() > Verbosity::Quiet {
+            print!($($arg)*);
+        }
+    };
+}
+
+/// Prints a line to stdout unless running quietly
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        if verbosity() > Verbosity::Quiet {
+            println!($($arg)*);
+        }
+    };
+}
 
 /// DialogDetective - Automatically identify and rename unknown video files
 ///
@@ -16,27 +132,85 @@ use std::process;
 #[command(
     after_help = "💡 TIP: Use --season to filter episodes for faster, cheaper, more accurate matching!"
 )]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Directory containing video files to process
-    #[arg(required_unless_present = "list_models")]
+    #[arg(required_unless_present_any = ["list_models", "map"])]
     video_dir: Option<PathBuf>,
 
     /// Name of the TV series (e.g., "Breaking Bad")
-    #[arg(required_unless_present = "list_models")]
+    ///
+    /// Detected from the file and directory names if left out.
     show_name: Option<String>,
 
+    /// Investigate a directory for a show, instead of VIDEO_DIR and SHOW_NAME
+    ///
+    /// Can be given several times to process several directories, each
+    /// with its own show, in one run that loads the Whisper model once:
+    /// --map "/downloads/bb=Breaking Bad" --map "/downloads/expanse=The Expanse"
+    #[arg(
+        long,
+        value_name = "DIR=SHOW",
+        value_parser = parse_mapping,
+        conflicts_with_all = ["video_dir", "show_name"]
+    )]
+    map: Vec<(PathBuf, String)>,
+
     /// List all available Whisper models and exit
     #[arg(long)]
     list_models: bool,
 
-    /// Select Whisper model by name (auto-downloads if needed)
+    /// Estimate the remaining work, duration, and token cost, then exit
+    ///
+    /// Hashes all videos and checks the caches to predict how many
+    /// transcriptions and matcher calls are needed, without running them.
+    #[arg(long)]
+    estimate: bool,
+
+    /// Never prompt, e.g. when running in a container or from cron
+    ///
+    /// Picks the search result whose name matches the show name exactly, or
+    /// else the most relevant one, instead of asking. Implied when stdin is
+    /// not a terminal.
+    #[arg(long)]
+    non_interactive: bool,
+
+    /// Ask before investigating more than this many videos (0 for no limit)
+    ///
+    /// Guards against pointing DialogDetective at a whole library by
+    /// mistake. Without a terminal to ask on, the run stops instead; raise
+    /// the limit to go on.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_FILES)]
+    max_files: usize,
+
+    /// Stop extracting the audio of a video after this long, e.g. 10m
+    ///
+    /// FFmpeg can hang on damaged files. The video fails and the run moves
+    /// on to the next one.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    extraction_timeout: Option<Duration>,
+
+    /// Stop transcribing a video after this long, e.g. 1h
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    transcription_timeout: Option<Duration>,
+
+    /// Stop a call of the AI matcher after this long, e.g. 5m
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    matching_timeout: Option<Duration>,
+
+    /// Select Whisper model by name or path (auto-downloads if needed)
     ///
     /// By default, the 'base' model is used. Use this flag to select a different
-    /// model from the supported list. Use --list-models to see all available models.
+    /// model from the supported list, or give the path of a model file. 'auto'
+    /// uses the best model already downloaded. Use --list-models to see all
+    /// available models.
     ///
-    /// Examples: tiny, base, small, medium, large-v3-turbo, base-q8_0
-    #[arg(long, value_name = "NAME", conflicts_with = "model_path")]
-    model: Option<String>,
+    /// Examples: tiny, base, small, medium, large-v3-turbo, base-q8_0, ./ggml-custom.bin
+    #[arg(long, value_name = "NAME|PATH", conflicts_with = "model_path")]
+    model: Option<ModelSpec>,
 
     /// Override with custom model file path (advanced)
     ///
@@ -45,6 +219,59 @@ struct Cli {
     #[arg(long, value_name = "PATH", conflicts_with = "model")]
     model_path: Option<PathBuf>,
 
+    /// Never download a Whisper model
+    ///
+    /// Fails if the selected model hasn't been downloaded before, instead of
+    /// downloading it.
+    #[arg(long)]
+    offline: bool,
+
+    /// Which cached transcripts made with other Whisper models to reuse
+    #[arg(long, value_name = "POLICY", value_enum, default_value_t = Reuse::SameModel)]
+    reuse_transcripts: Reuse,
+
+    /// Keep cached matches apart from those of other libraries
+    ///
+    /// Matches are cached per video, show, seasons, and matcher. If the
+    /// same show is filed into libraries with different settings, like
+    /// another episode order, give each library its own namespace. "auto"
+    /// derives it from the library: the output directory, or else the
+    /// video directory.
+    #[arg(long, value_name = "NAME|auto", value_parser = parse_cache_namespace)]
+    cache_namespace: Option<CacheNamespace>,
+
+    /// Transcribe again with this Whisper model if a transcript scores low
+    ///
+    /// Transcripts that look unreliable (much repetition, unsure tokens,
+    /// little speech found) are made again with this model, usually a larger
+    /// one, and the better transcript is used.
+    ///
+    /// Examples: medium, large-v3-turbo
+    #[arg(long, value_name = "NAME|PATH")]
+    fallback_model: Option<ModelSpec>,
+
+    /// Quality score (0-1) below which --fallback-model is used
+    #[arg(long, value_name = "SCORE", default_value_t = DEFAULT_MIN_TRANSCRIPT_QUALITY)]
+    min_transcript_quality: f32,
+
+    /// Transcribe only the start of each video, e.g. 5m
+    ///
+    /// Much faster for long videos. Combine with --escalate to transcribe
+    /// more of the videos that can't be matched confidently.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    sample: Option<Duration>,
+
+    /// Transcribe and match again with this model if a match is uncertain
+    ///
+    /// Matches less confident than --auto-apply-threshold are retried with
+    /// each step in turn, until one is confident enough. A step is a model,
+    /// optionally followed by how much of the start of the video to
+    /// transcribe. Can be repeated to build a ladder.
+    ///
+    /// Examples: base@15m, large-v3-turbo@15m, large-v3-turbo
+    #[arg(long, value_name = "MODEL[@DURATION]", value_parser = parse_escalation_step)]
+    escalate: Vec<EscalationStep>,
+
     /// Filter to specific season(s) - can be repeated (RECOMMENDED)
     ///
     /// Using season filtering speeds up matching, reduces token usage,
@@ -52,31 +279,566 @@ struct Cli {
     #[arg(short, long = "season", value_name = "N")]
     seasons: Vec<usize>,
 
+    /// Infer each file's season before matching (when no --season is given)
+    ///
+    /// Reads the season from markers like S02E05 or "Season 2" in file and
+    /// directory names, or lets the AI guess it from a short transcript
+    /// excerpt, then matches only against the episodes of that season.
+    #[arg(long, conflicts_with = "seasons")]
+    infer_season: bool,
+
+    /// Language of the episode titles and summaries given to the AI
+    ///
+    /// "original" uses the English metadata, "detected" the language Whisper
+    /// detects in the first video, and a code like "de" that language.
+    /// Other languages than English require TMDB_API_KEY to be set.
+    #[arg(
+        long,
+        value_name = "original|detected|CODE",
+        default_value = "original",
+        value_parser = parse_language_strategy
+    )]
+    metadata_language: LanguageStrategy,
+
+    /// Language of the episode titles for {title_local} in --format, e.g. de
+    ///
+    /// Lets files be named with translated titles while matching against
+    /// the English metadata, or the other way around. Requires
+    /// TMDB_API_KEY to be set.
+    #[arg(long, value_name = "CODE", value_parser = parse_language_code)]
+    title_language: Option<String>,
+
+    /// Order the episodes are numbered in, as your library expects it
+    ///
+    /// Some shows, like Firefly, were released on DVD in another order than
+    /// they were broadcast. Shows without the order keep the default one.
+    #[arg(long, value_name = "ORDER", value_enum, default_value_t = Order::Default)]
+    episode_order: Order,
+
+    /// Where the series and episode metadata comes from
+    ///
+    /// One of tvmaze (default), trakt, wikipedia, or the name of a provider
+    /// plugin in providers.json in the configuration directory. Trakt
+    /// requires the client ID of a Trakt API app in TRAKT_CLIENT_ID. It
+    /// only has the default episode order. Shows the provider doesn't find
+    /// are looked up on Wikipedia.
+    #[arg(long, value_name = "PROVIDER", value_parser = parse_metadata_provider, default_value = "tvmaze")]
+    metadata_provider: Provider,
+
     /// AI backend to use for episode matching
     #[arg(short = 'm', long, value_enum, default_value_t = Matcher::GeminiFlash)]
     matcher: Matcher,
 
+    /// Pass an extra argument to the matcher's CLI, repeatable
+    ///
+    /// Pins a model version with --matcher-arg=--model=gemini-2.5-pro, which
+    /// replaces the model of the selected matcher and keeps its matches
+    /// apart in the cache. Other arguments, like sandbox settings or
+    /// --max-turns, are passed on as they are.
+    #[arg(long = "matcher-arg", value_name = "ARG", allow_hyphen_values = true)]
+    matcher_args: Vec<String>,
+
+    /// Run the matcher's CLI in this directory
+    #[arg(long, value_name = "DIR")]
+    matcher_dir: Option<PathBuf>,
+
+    /// Set an environment variable for the matcher's CLI, repeatable
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_env_var)]
+    matcher_env: Vec<(String, String)>,
+
+    /// Maximum number of episodes listed in a single matching prompt
+    ///
+    /// Shows with more episodes are matched in chunks, followed by a final
+    /// round between the best matches of all chunks.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_EPISODES_PER_PROMPT)]
+    max_episodes_per_prompt: usize,
+
+    /// Number of matcher calls to run at the same time
+    ///
+    /// Matching runs while later files are transcribed. Higher values
+    /// finish large batches sooner but may hit the AI backend's rate limits.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_PARALLEL_MATCHES)]
+    parallel_matches: usize,
+
     /// Operation mode: what to do after matching
     #[arg(long, value_enum, default_value_t = Mode::DryRun)]
     mode: Mode,
 
+    /// In dry runs, only list what changed since the last dry run
+    ///
+    /// Every dry run saves its plan for the directory. With --diff, only
+    /// new files, files planned with another name than before, and files
+    /// no longer planned are listed.
+    #[arg(long)]
+    diff: bool,
+
     /// Output directory for copy mode (required when mode=copy)
     #[arg(short = 'o', long, value_name = "DIR")]
     output_dir: Option<PathBuf>,
 
+    /// URL of the Sonarr instance for sonarr mode (e.g. http://localhost:8989)
+    #[arg(long, value_name = "URL")]
+    sonarr_url: Option<String>,
+
+    /// Sonarr API key for sonarr mode (defaults to SONARR_API_KEY)
+    #[arg(long, value_name = "KEY")]
+    sonarr_api_key: Option<String>,
+
+    /// Whether Sonarr moves or copies the files into its library
+    #[arg(long, value_name = "MODE", value_enum, default_value_t = SonarrImport::Move)]
+    sonarr_import: SonarrImport,
+
+    /// Add the renamed or copied episodes to your Trakt collection
+    ///
+    /// Requires TRAKT_CLIENT_ID and TRAKT_CLIENT_SECRET of a Trakt API app,
+    /// and signing in with `trakt login` first.
+    #[arg(long)]
+    trakt_collect: bool,
+
+    /// Set the modification time of renamed or copied files to the air date
+    ///
+    /// For archival libraries sorted by date. By default, files keep the
+    /// modification time they had.
+    #[arg(long)]
+    air_date_mtime: bool,
+
+    /// Write the matched episode into renamed or copied MKV files
+    ///
+    /// Sets the container title and Matroska tags for show, season, episode,
+    /// and air date, so the identity travels with the file. Uses mkvpropedit
+    /// if installed, otherwise remuxes the file with FFmpeg.
+    #[arg(long)]
+    write_tags: bool,
+
+    /// Write the transcripts of renamed or copied files as subtitles
+    ///
+    /// "sidecar" writes an SRT file next to each video, named like it with
+    /// the spoken language, e.g. "Show - S01E01.en.srt". "embed" adds it as
+    /// a subtitle track to MKV files instead (requires FFmpeg).
+    #[arg(long, value_enum, value_name = "MODE")]
+    subtitles: Option<Subtitles>,
+
+    /// Overwrite existing files instead of moving them to the trash
+    ///
+    /// By default, a file already at a new name is moved to the system
+    /// trash before it is replaced. Useful on headless servers without one.
+    #[arg(long)]
+    no_trash: bool,
+
+    /// Rename all files or none in rename mode
+    ///
+    /// If any rename fails, the files already renamed get their old names
+    /// back. Refuses to overwrite existing files.
+    #[arg(long)]
+    atomic: bool,
+
+    /// Verify every copy against its original in copy mode
+    ///
+    /// Compares the BLAKE3 hashes of both files, reading the copy back from
+    /// the disk (on Linux; elsewhere possibly from the page cache). Copies
+    /// that differ are removed and reported as failed.
+    #[arg(long)]
+    verify_copies: bool,
+
+    /// Give every file a unique episode instead of numbering duplicates
+    ///
+    /// When several files match the same episode, the file with the highest
+    /// confidence keeps it and the others fall back to their next best
+    /// candidate episode. Files left without a unique episode are skipped.
+    #[arg(long)]
+    resolve_duplicates: bool,
+
+    /// What to do with files still sharing an episode
+    ///
+    /// Applied after --resolve-duplicates, to the matches being renamed or
+    /// copied. Files that lose against another file are left untouched,
+    /// except with `quarantine`.
+    #[arg(long, value_enum, default_value_t = Duplicates::Suffix)]
+    duplicate_policy: Duplicates,
+
+    /// Minimum match confidence (0-1) for a file to be renamed or copied
+    ///
+    /// Matches below this threshold are set aside for review instead of
+    /// being applied automatically.
+    #[arg(long, value_name = "CONFIDENCE", default_value_t = TriageThresholds::default().auto_apply)]
+    auto_apply_threshold: f64,
+
+    /// Minimum match confidence (0-1) for a match to be kept for review
+    ///
+    /// Matches below this threshold are reported as unmatched.
+    #[arg(long, value_name = "CONFIDENCE", default_value_t = TriageThresholds::default().review)]
+    review_threshold: f64,
+
+    /// Write the matches needing review to this file as JSON
+    #[arg(long, value_name = "PATH")]
+    review_file: Option<PathBuf>,
+
+    /// Write the prompt and response of every matcher call to this directory
+    ///
+    /// Creates one `<file>.<kind>.prompt.txt` and `<file>.<kind>.response.txt`
+    /// pair per call, for investigating mismatches. Cached matches don't call
+    /// the matcher and produce no files.
+    #[arg(long, value_name = "DIR")]
+    dump_prompts: Option<PathBuf>,
+
+    /// Keep Prometheus metrics of the run in this file
+    ///
+    /// Updated as the run progresses, for the textfile collector of the
+    /// node exporter (use a `.prom` file in its directory).
+    #[arg(long, value_name = "PATH")]
+    metrics_file: Option<PathBuf>,
+
+    /// Don't record the files of this run in the history
+    #[arg(long)]
+    no_history: bool,
+
+    /// Retries of file reads, renames, and copies failing transiently
+    ///
+    /// Network shares occasionally time out or lose their connection; such
+    /// errors are retried with a growing delay. Use 0 to fail right away.
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    io_retries: u32,
+
+    /// Size of each read when hashing video files (e.g. 512K, 16M)
+    ///
+    /// Larger reads are faster on fast network shares.
+    #[arg(long, value_name = "SIZE", default_value = "4M", value_parser = parse_size)]
+    hash_buffer_size: usize,
+
+    /// Memory-map video files for hashing instead of reading them
+    ///
+    /// Fastest on local SSDs. Avoid it for network shares: a disconnect
+    /// while hashing crashes the program instead of being retried.
+    #[arg(long)]
+    hash_mmap: bool,
+
+    /// Language of the output (detected from the locale by default)
+    #[arg(long, value_enum, value_name = "LANG")]
+    lang: Option<Lang>,
+
+    /// Print errors only, e.g. for cron jobs
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print every processing step (-vv adds stage timings and matcher calls)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
     /// File naming format
     ///
     /// Supported variables:
-    ///   {show}    - Series name
-    ///   {season}  - Season number (use {season:02} for zero-padding)
-    ///   {episode} - Episode number (use {episode:02} for zero-padding)
-    ///   {title}   - Episode title
-    ///   {ext}     - Original file extension
-    #[arg(
-        long,
-        default_value = "{show} - S{season:02}E{episode:02} - {title}.{ext}"
-    )]
+    ///   {show}        - Series name
+    ///   {season}      - Season number (use {season:02} for zero-padding)
+    ///   {episode}     - Episode number (use {episode:02} for zero-padding)
+    ///   {title}       - Episode title
+    ///   {title_local} - Episode title in --title-language ({title} if unknown)
+    ///   {ext}         - Original file extension
+    ///   {season_name} - Season name ("Season 1" or "Specials" if it has none)
+    ///   {show_year}   - Premiere year of the series
+    ///   {provider_id} - Metadata provider's episode ID (e.g. TVMaze)
+    ///   {quality}     - Resolution and codec of the original file (e.g. "1080p x265")
+    ///   {source_tag}  - Source of the original file (e.g. "WEB-DL")
+    ///   {group}       - Release group of the original file
+    #[arg(long, default_value = DEFAULT_FORMAT)]
     format: String,
+
+    /// Transliterate names in file names to ASCII (e.g. "Pokémon" to "Pokemon")
+    ///
+    /// For file systems and network shares that can't handle non-ASCII
+    /// characters. Applies to show names, episode titles, and season names.
+    #[arg(long)]
+    ascii_filenames: bool,
+
+    /// How words in file names are separated
+    #[arg(long, value_name = "STYLE", value_enum, default_value_t = Style::Spaces)]
+    filename_style: Style,
+
+    /// Lowercase file names (e.g. "show.s01e01.title.mkv" with --filename-style dots)
+    #[arg(long)]
+    lowercase: bool,
+}
+
+/// Subcommands besides investigating a directory
+#[derive(Subcommand)]
+enum Command {
+    /// Work with file naming formats
+    Format {
+        #[command(subcommand)]
+        action: FormatAction,
+    },
+
+    /// Identify a finished download and hard link it into a library
+    ///
+    /// Meant to be called by torrent clients on completion. Show, library,
+    /// and the other settings come from a profile. Nothing is printed; the
+    /// run is logged as JSON lines to the profile's log file. Exits with 0
+    /// if every video was linked, 2 if some weren't, 3 if none were, and 4
+    /// on errors.
+    Hook {
+        /// Name of the profile to use
+        #[arg(long, value_name = "NAME")]
+        profile: String,
+
+        /// Profiles file (defaults to profiles.json in the config directory)
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+
+        /// Add the download to the job queue instead of identifying it now
+        #[arg(long)]
+        queue: bool,
+
+        /// Run the queued job before all others (with --queue)
+        #[arg(long, requires = "queue")]
+        urgent: bool,
+
+        /// The downloaded file or directory
+        path: PathBuf,
+    },
+
+    /// Work with the job queue filled by `hook --queue`
+    Queue {
+        #[command(subcommand)]
+        action: QueueAction,
+    },
+
+    /// Show the investigated files, newest first
+    ///
+    /// Every run records each file with its content hash, original path,
+    /// matched episode, confidence, and what was done with it.
+    History {
+        /// Only shows whose name contains this text
+        #[arg(long, value_name = "TEXT")]
+        show: Option<String>,
+
+        /// Only files whose original or new path contains this text
+        #[arg(long, value_name = "TEXT")]
+        file: Option<String>,
+
+        /// Only files with this content hash
+        #[arg(long, value_name = "HASH")]
+        hash: Option<String>,
+
+        /// Only files of this run
+        #[arg(long, value_name = "ID")]
+        run: Option<String>,
+
+        /// Maximum number of files to show
+        #[arg(long, value_name = "N", default_value_t = 50)]
+        limit: usize,
+
+        /// Print the entries as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Compare an organized library with the show's episode list
+    ///
+    /// Reads the episode numbers from the file names (S01E02, 1x02) and
+    /// lists missing episodes, episodes stored more than once, and files
+    /// named after episodes that don't exist. Nothing is transcribed. Exits
+    /// with 0 if the library is complete and 2 if anything was found.
+    Audit {
+        /// The library directory of the show
+        library: PathBuf,
+
+        /// Name of the show (detected from the library if not given)
+        show: Option<String>,
+
+        /// Only compare with these season(s) - can be repeated
+        #[arg(short, long = "season", value_name = "N")]
+        seasons: Vec<usize>,
+
+        /// Print the audit as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Fetch a show's metadata and the Whisper model ahead of a run
+    ///
+    /// Fills the metadata caches and downloads the model, so that a later
+    /// run with the same show, seasons, and metadata options works without
+    /// a network connection (apart from the AI backend). Cached metadata
+    /// expires after 24 hours.
+    Prefetch {
+        /// Name of the show
+        show: String,
+
+        /// Only fetch these season(s), as given to the run later
+        #[arg(short, long = "season", value_name = "N", num_args = 1..)]
+        seasons: Vec<usize>,
+
+        /// Whisper model to download (defaults to the one a run would use)
+        #[arg(long, value_name = "NAME|PATH")]
+        model: Option<ModelSpec>,
+
+        /// Language of the episode titles and summaries, e.g. de
+        #[arg(long, value_name = "CODE", value_parser = parse_language_code)]
+        metadata_language: Option<String>,
+
+        /// Language of the episode titles for {title_local}, e.g. de
+        #[arg(long, value_name = "CODE", value_parser = parse_language_code)]
+        title_language: Option<String>,
+
+        /// Order the episodes are numbered in
+        #[arg(long, value_name = "ORDER", value_enum, default_value_t = Order::Default)]
+        episode_order: Order,
+
+        /// Where the series and episode metadata comes from
+        #[arg(long, value_name = "PROVIDER", value_parser = parse_metadata_provider, default_value = "tvmaze")]
+        metadata_provider: Provider,
+    },
+
+    /// Sign in to Trakt for --trakt-collect, or sign out again
+    ///
+    /// Requires TRAKT_CLIENT_ID and TRAKT_CLIENT_SECRET of a Trakt API app.
+    Trakt {
+        #[command(subcommand)]
+        action: TraktAction,
+    },
+
+    /// Manage the downloaded Whisper models
+    Models {
+        #[command(subcommand)]
+        action: ModelsAction,
+    },
+
+    /// Manage the cached transcripts, matches, and metadata
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Check FFmpeg, the Whisper model, the AI backend, and more
+    ///
+    /// Verifies everything a run depends on up front and suggests a fix for
+    /// every problem found. Exits with 4 if anything would make a run fail.
+    Doctor {
+        /// AI backend to check
+        #[arg(long, value_enum, default_value_t = Matcher::GeminiFlash)]
+        matcher: Matcher,
+
+        /// Whisper model to check
+        #[arg(long, value_name = "NAME", default_value = model_downloader::DEFAULT_MODEL)]
+        model: String,
+
+        /// Whisper model file to check instead of a downloaded model
+        #[arg(long, value_name = "PATH")]
+        model_path: Option<PathBuf>,
+    },
+}
+
+/// Actions of the `format` subcommand
+#[derive(Subcommand)]
+enum FormatAction {
+    /// Validate a format and preview the file names it produces
+    Check {
+        /// The format to check, as passed to --format
+        format: String,
+    },
+}
+
+/// Actions of the `models` subcommand
+#[derive(Subcommand)]
+enum ModelsAction {
+    /// Remove downloaded models that haven't been used for a while
+    Prune {
+        /// Remove models unused for this long, e.g. 90d, 12w, or 36h
+        #[arg(long, value_name = "AGE", value_parser = parse_duration)]
+        unused_for: Duration,
+
+        /// Only list the models that would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Actions of the `cache` subcommand
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Remove everything cached and recorded about a show or a video file
+    ///
+    /// Removes transcripts, matches in every cache namespace, metadata,
+    /// hash lookups, and history entries.
+    #[command(group = clap::ArgGroup::new("target").required(true))]
+    Purge {
+        /// Name of the show to purge
+        #[arg(long, value_name = "NAME", group = "target")]
+        show: Option<String>,
+
+        /// Video file to purge, identified by its content hash
+        #[arg(long, value_name = "PATH", group = "target")]
+        file: Option<PathBuf>,
+    },
+}
+
+/// Actions of the `trakt` subcommand
+#[derive(Subcommand)]
+enum TraktAction {
+    /// Sign in by entering a code on the Trakt website
+    Login,
+
+    /// Sign out and forget the stored token
+    Logout,
+}
+
+/// Actions of the `queue` subcommand
+#[derive(Subcommand)]
+enum QueueAction {
+    /// List the queued jobs in the order they run
+    List,
+
+    /// Identify and link the queued downloads one at a time
+    Run {
+        /// Run the smallest downloads first (urgent jobs still come first)
+        #[arg(long)]
+        smallest_first: bool,
+
+        /// Keep running and wait for new jobs once the queue is empty
+        #[arg(long)]
+        watch: bool,
+
+        /// Unload a Whisper model after it was unused for this long, e.g. 10m
+        ///
+        /// Models stay loaded between jobs, so a job doesn't wait for its
+        /// model to load if an earlier one used the same model recently.
+        #[arg(long, value_name = "AGE", default_value = "10m", value_parser = parse_duration)]
+        model_keepalive: Duration,
+
+        /// Serve Prometheus metrics at /metrics on this address, e.g. 127.0.0.1:9184
+        ///
+        /// The metrics add up over all jobs, with the number of queued jobs
+        /// on top. Meant for --watch, where the runner lives long enough to
+        /// be scraped.
+        #[arg(long, value_name = "ADDRESS")]
+        metrics_listen: Option<String>,
+    },
+
+    /// Move a job to a position in the queue
+    Move {
+        /// ID of the job
+        id: u64,
+
+        /// New position, counted from 1
+        position: usize,
+    },
+
+    /// Hold a job back until it is resumed
+    Pause {
+        /// ID of the job
+        id: u64,
+    },
+
+    /// Let a paused job run again
+    Resume {
+        /// ID of the job
+        id: u64,
+    },
+
+    /// Remove a job from the queue
+    Cancel {
+        /// ID of the job
+        id: u64,
+    },
 }
 
 /// AI backend selection
@@ -100,6 +862,133 @@ impl From<Matcher> for MatcherType {
     }
 }
 
+/// Order the episodes are numbered in
+#[derive(Clone, Copy, ValueEnum)]
+enum Order {
+    /// TVMaze's main order, usually the broadcast order (default)
+    Default,
+    /// The order of the DVD release
+    Dvd,
+    /// The order the episodes were first broadcast in
+    Broadcast,
+}
+
+impl From<Order> for EpisodeOrder {
+    fn from(o: Order) -> Self {
+        match o {
+            Order::Default => EpisodeOrder::Default,
+            Order::Dvd => EpisodeOrder::Dvd,
+            Order::Broadcast => EpisodeOrder::Broadcast,
+        }
+    }
+}
+
+/// Cache namespace selection of `--cache-namespace`
+#[derive(Debug, Clone)]
+enum CacheNamespace {
+    /// Derived from the library's directory
+    Auto,
+    /// Given by name
+    Named(String),
+}
+
+/// Metadata provider selection
+#[derive(Debug, Clone)]
+enum Provider {
+    /// TVMaze (default, needs no account)
+    Tvmaze,
+    /// Trakt (requires TRAKT_CLIENT_ID)
+    Trakt,
+    /// The episode list articles of the English Wikipedia
+    Wikipedia,
+    /// A provider plugin, by its name in the plugins file
+    Plugin(String),
+}
+
+/// Policy for reusing cached transcripts
+#[derive(Clone, Copy, ValueEnum)]
+enum Reuse {
+    /// Only reuse transcripts made with the selected model (default)
+    SameModel,
+    /// Also reuse transcripts made with a larger model
+    SameOrBetter,
+    /// Reuse any cached transcript
+    Any,
+}
+
+impl From<Reuse> for TranscriptReuse {
+    fn from(r: Reuse) -> Self {
+        match r {
+            Reuse::SameModel => TranscriptReuse::SameModel,
+            Reuse::SameOrBetter => TranscriptReuse::SameOrBetterModel,
+            Reuse::Any => TranscriptReuse::AnyModel,
+        }
+    }
+}
+
+/// Duplicate handling policy
+#[derive(Clone, Copy, ValueEnum)]
+enum Duplicates {
+    /// Keep all files, numbering duplicates "(2)", "(3)", ... (default)
+    Suffix,
+    /// Keep the file with the highest resolution (requires ffprobe)
+    HighestResolution,
+    /// Keep the largest file
+    Largest,
+    /// Keep the most confident match, move the others into "duplicates/"
+    Quarantine,
+}
+
+impl From<Duplicates> for DuplicatePolicy {
+    fn from(duplicates: Duplicates) -> Self {
+        match duplicates {
+            Duplicates::Suffix => DuplicatePolicy::Suffix,
+            Duplicates::HighestResolution => DuplicatePolicy::HighestResolution,
+            Duplicates::Largest => DuplicatePolicy::Largest,
+            Duplicates::Quarantine => DuplicatePolicy::Quarantine,
+        }
+    }
+}
+
+/// File name word separator
+#[derive(Clone, Copy, ValueEnum)]
+enum Style {
+    /// Keep the spaces of the format and the metadata (default)
+    Spaces,
+    /// Separate words with dots, like "Show.S01E01.Title.mkv"
+    Dots,
+    /// Separate words with underscores, like "Show_S01E01_Title.mkv"
+    Underscores,
+}
+
+impl From<Style> for FilenameStyle {
+    fn from(style: Style) -> Self {
+        match style {
+            Style::Spaces => FilenameStyle::Spaces,
+            Style::Dots => FilenameStyle::Dots,
+            Style::Underscores => FilenameStyle::Underscores,
+        }
+    }
+}
+
+/// Where to write subtitles generated from the transcripts
+#[derive(Clone, Copy, ValueEnum)]
+enum Subtitles {
+    /// An SRT file next to the video
+    Sidecar,
+    /// A subtitle track in MKV files, an SRT file next to other videos
+    Embed,
+}
+
+impl From<Subtitles> for SubtitleMode {
+    fn from(subtitles: Subtitles) -> Self {
+        match subtitles {
+            Subtitles::Sidecar => SubtitleMode::Sidecar,
+            Subtitles::Embed => SubtitleMode::Embed,
+        }
+    }
+}
+
 /// Operation mode
 #[derive(Clone, Copy, ValueEnum)]
 enum Mode {
@@ -109,35 +998,143 @@ enum Mode {
     Rename,
     /// Copy files to output directory with new names
     Copy,
+    /// Hand the files to Sonarr's manual import instead of renaming them
+    Sonarr,
 }
 
-/// Handles progress events and prints formatted output to stdout
-fn handle_progress_event(event: ProgressEvent) {
-    match event {
-        ProgressEvent::Started { show_name, .. } => {
-            println!("🔍 DialogDetective");
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("📺 Investigating: {}", show_name);
-        }
-        ProgressEvent::FetchingMetadata { .. } => {
-            print!("📡 Fetching metadata... ");
-            std::io::Write::flush(&mut std::io::stdout()).ok();
+/// How Sonarr imports files
+#[derive(Clone, Copy, ValueEnum)]
+enum Lang {
+    /// English
+    En,
+    /// German
+    De,
+}
+
+impl From<Lang> for Language {
+    fn from(lang: Lang) -> Self {
+        match lang {
+            Lang::En => Language::English,
+            Lang::De => Language::German,
         }
-        ProgressEvent::MetadataFetched { season_count, .. } => {
-            println!("✓ ({} seasons)", season_count);
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SonarrImport {
+    /// Move the files into the library
+    Move,
+    /// Copy the files into the library, keeping the originals
+    Copy,
+}
+
+impl From<SonarrImport> for SonarrImportMode {
+    fn from(import: SonarrImport) -> Self {
+        match import {
+            SonarrImport::Move => SonarrImportMode::Move,
+            SonarrImport::Copy => SonarrImportMode::Copy,
+        }
+    }
+}
+
+/// Returns the verbosity from which a progress event is printed
+fn event_verbosity(event: &ProgressEvent) -> Verbosity {
+    match event {
+        ProgressEvent::Hashing { .. }
+        | ProgressEvent::HashingProgress { .. }
+        | ProgressEvent::HashingFinished { .. }
+        | ProgressEvent::AudioExtraction { .. }
+        | ProgressEvent::AudioExtractionFinished { .. }
+        | ProgressEvent::Transcription { .. }
+        | ProgressEvent::TranscriptionFinished { .. }
+        | ProgressEvent::TranscriptCacheHit { .. }
+        | ProgressEvent::TranscriptScored { .. } => Verbosity::Verbose,
+        ProgressEvent::Matching { .. }
+        | ProgressEvent::PromptExchanged { .. }
+        | ProgressEvent::StageCompleted { .. } => Verbosity::Debug,
+        _ => Verbosity::Normal,
+    }
+}
+
+/// Handles progress events and prints formatted output to stdout
+fn handle_progress_event(event: ProgressEvent) {
+    if verbosity() < event_verbosity(&event) {
+        return;
+    }
+
+    match event {
+        ProgressEvent::Started { show_name, .. } => {
+            outln!("🔍 DialogDetective");
+            outln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            outln!("{}", tr!("progress.investigating", show = show_name));
+        }
+        ProgressEvent::ModelDownload { progress } => {
+            model_downloader::print_download_progress(progress);
+        }
+        ProgressEvent::LanguageDetected { language, .. } => {
+            outln!("{}", tr!("progress.language_detected", language = language));
+        }
+        ProgressEvent::FetchingMetadata { .. } => {
+            out!("{}", tr!("progress.fetching_metadata"));
+            std::io::Write::flush(&mut std::io::stdout()).ok();
+        }
+        ProgressEvent::MetadataFetched { season_count, .. } => {
+            outln!("{}", tr!("progress.metadata_fetched", count = season_count));
+        }
+        ProgressEvent::SummariesMissing { missing, total } => {
+            outln!(
+                "{}",
+                tr!(
+                    "progress.summaries_missing",
+                    missing = missing,
+                    total = total
+                )
+            );
         }
         ProgressEvent::ScanningVideos => {
-            print!("🔎 Scanning directory... ");
+            out!("{}", tr!("progress.scanning"));
+            std::io::Write::flush(&mut std::io::stdout()).ok();
+        }
+        ProgressEvent::DirectoryScanned { scan } => {
+            out!(
+                "\r{}{}",
+                tr!("progress.scanning"),
+                tr!(
+                    "progress.directories_scanned",
+                    directories = scan.total_directories,
+                    files = scan.total_files,
+                )
+            );
             std::io::Write::flush(&mut std::io::stdout()).ok();
         }
         ProgressEvent::VideosFound { count } => {
             if count == 0 {
-                println!("✗ No videos found");
+                outln!("{}", tr!("progress.no_videos"));
             } else {
-                println!("✓ ({} files)", count);
-                println!();
+                outln!("{}", tr!("progress.videos_found", count = count));
+                outln!();
             }
         }
+        ProgressEvent::ScanWarning { path, message } => {
+            outln!(
+                "{}",
+                tr!(
+                    "progress.scan_warning",
+                    path = path.display(),
+                    message = message,
+                )
+            );
+        }
+        ProgressEvent::FileSkipped { path, reason } => {
+            outln!(
+                "{}",
+                tr!(
+                    "progress.file_skipped",
+                    file = path.display(),
+                    reason = skip_reason_text(reason),
+                )
+            );
+        }
         ProgressEvent::ProcessingVideo {
             index,
             total,
@@ -147,429 +1144,3332 @@ fn handle_progress_event(event: ProgressEvent) {
                 .file_name()
                 .and_then(|n| n.to_str())
                 .unwrap_or("unknown");
-            println!("🎬 [{}/{}] {}", index + 1, total, filename);
+            outln!("🎬 [{}/{}] {}", index + 1, total, filename);
         }
         ProgressEvent::Hashing { .. } => {
-            print!("   ├─ Computing hash... ");
+            out!("{}", tr!("progress.hashing"));
+            std::io::Write::flush(&mut std::io::stdout()).ok();
+        }
+        ProgressEvent::HashingProgress {
+            bytes_done,
+            total_bytes,
+            ..
+        } => {
+            let percent = (bytes_done * 100).checked_div(total_bytes).unwrap_or(100);
+            out!("\r{}{:>3}% ", tr!("progress.hashing"), percent);
             std::io::Write::flush(&mut std::io::stdout()).ok();
         }
         ProgressEvent::AudioExtraction { .. } => {
-            print!("   ├─ Extracting audio... ");
+            out!("{}", tr!("progress.extracting"));
             std::io::Write::flush(&mut std::io::stdout()).ok();
         }
         ProgressEvent::Transcription { .. } => {
-            print!("   ├─ Transcribing... ");
+            out!("{}", tr!("progress.transcribing"));
             std::io::Write::flush(&mut std::io::stdout()).ok();
         }
         ProgressEvent::TranscriptionFinished { language, .. } => {
-            println!("✓ ({})", language);
+            outln!("✓ ({})", language);
         }
         ProgressEvent::TranscriptCacheHit { language, .. } => {
-            println!("   ├─ Transcript cached... ✓ ({})", language);
+            outln!("{}", tr!("progress.transcript_cached", language = language));
         }
-        ProgressEvent::Matching { .. } => {
-            print!("   └─ Matching episode... ");
-            std::io::Write::flush(&mut std::io::stdout()).ok();
+        ProgressEvent::HashMatched { episode, .. } => {
+            outln!(
+                "{}",
+                tr!("progress.hash_matched", episode = episode_line(&episode))
+            );
+        }
+        ProgressEvent::TranscriptScored { quality, .. } => {
+            outln!(
+                "{}",
+                tr!(
+                    "progress.transcript_quality",
+                    score = format!("{:.2}", quality.score)
+                )
+            );
+        }
+        ProgressEvent::Escalating {
+            video_path,
+            confidence,
+            step,
+            model,
+        } => {
+            outln!(
+                "{}",
+                tr!(
+                    "progress.escalating",
+                    file = display_name(&video_path),
+                    percent = (confidence * 100.0).round(),
+                    step = step,
+                    model = model.unwrap_or_default(),
+                )
+            );
+        }
+        ProgressEvent::TranscriptionRetried { score, model, .. } => {
+            outln!(
+                "{}",
+                tr!(
+                    "progress.transcription_retried",
+                    score = format!("{:.2}", score),
+                    model = model.unwrap_or_default(),
+                )
+            );
+        }
+        ProgressEvent::NoDialogue { words, .. } => {
+            outln!("{}", tr!("progress.no_dialogue", words = words));
+        }
+        ProgressEvent::MatchingQueued { .. } => {
+            outln!("{}", tr!("progress.matching_queued"));
+        }
+        // Matching runs alongside later videos, so its events name the file
+        ProgressEvent::SeasonInferred {
+            video_path,
+            season,
+            source,
+        } => {
+            let from = match source {
+                SeasonSource::Filename => tr!("progress.season_from_filename"),
+                SeasonSource::Transcript => tr!("progress.season_from_dialogue"),
+            };
+            outln!(
+                "{}",
+                tr!(
+                    "progress.season_inferred",
+                    file = display_name(&video_path),
+                    season = season,
+                    source = from,
+                )
+            );
+        }
+        ProgressEvent::Matching {
+            index,
+            total,
+            video_path,
+        } => {
+            outln!(
+                "{}",
+                tr!(
+                    "progress.matching",
+                    index = index + 1,
+                    total = total,
+                    file = display_name(&video_path),
+                )
+            );
+        }
+        ProgressEvent::MatchingFinished {
+            video_path,
+            episode,
+        } => {
+            outln!(
+                "{}",
+                tr!(
+                    "progress.matched",
+                    file = display_name(&video_path),
+                    episode = episode_line(&episode),
+                )
+            );
         }
         ProgressEvent::MatchingCacheHit { episode, .. } => {
-            println!(
-                "   └─ Match cached... ✓ (S{:02}E{:02} - {})",
-                episode.season_number, episode.episode_number, episode.name
+            outln!(
+                "{}",
+                tr!("progress.match_cached", episode = episode_line(&episode))
             );
         }
-        ProgressEvent::HashingFinished { .. }
-        | ProgressEvent::AudioExtractionFinished { .. }
-        | ProgressEvent::MatchingFinished { .. } => {
-            println!("✓");
+        ProgressEvent::HashingFinished {
+            bytes, duration, ..
+        } => {
+            // Tiny files hash too fast for a meaningful rate
+            let seconds = duration.as_secs_f64();
+            if seconds >= 0.01 {
+                let rate = (bytes as f64 / seconds) as u64;
+                outln!("✓ ({}/s)", humansize::format_size(rate, humansize::BINARY));
+            } else {
+                outln!("✓");
+            }
+        }
+        ProgressEvent::AudioExtractionFinished { .. } => {
+            outln!("✓");
+        }
+        ProgressEvent::PromptExchanged {
+            video_path,
+            kind,
+            prompt,
+            response,
+        } => {
+            outln!(
+                "{}",
+                tr!(
+                    "progress.prompt_exchanged",
+                    file = display_name(&video_path),
+                    kind = prompt_kind_name(kind),
+                    prompt = prompt.chars().count(),
+                    response = response.chars().count(),
+                )
+            );
+        }
+        ProgressEvent::StageCompleted { stage, duration } => {
+            outln!(
+                "{}",
+                tr!(
+                    "progress.stage_took",
+                    stage = stage_name(stage),
+                    duration = format_duration(duration),
+                )
+            );
+        }
+        ProgressEvent::CacheEntryQuarantined { path } => {
+            outln!(
+                "{}",
+                tr!("progress.cache_entry_quarantined", path = path.display())
+            );
         }
         ProgressEvent::Complete { .. } => {
-            println!();
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            outln!();
+            outln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
         }
     }
 }
 
-/// Displays all available Whisper models with download status and exits
-fn display_model_list_and_exit() {
-    use std::collections::HashMap;
+/// Sets the modification times of the files to their air dates, warning about failures
+fn set_air_dates(operations: &[PlannedOperation]) {
+    for error in set_air_date_mtimes(operations) {
+        outln!("⚠️  Failed to set air date: {}", error);
+    }
+}
 
-    println!("🔍 Available Whisper Models");
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    println!();
+/// How often `queue run --watch` looks for new jobs
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
-    // Get cache directory
-    let cache_dir = match model_downloader::get_cache_dir() {
-        Ok(dir) => dir,
-        Err(e) => {
-            eprintln!("❌ Error: Failed to access cache directory: {}", e);
-            process::exit(1);
+/// Whisper models loaded by earlier jobs, kept for later ones
+///
+/// Loading a large model takes a while, so jobs using the same model share
+/// one loaded copy until it was unused for the keep-alive time.
+#[derive(Default)]
+struct LoadedModels {
+    transcribers: RefCell<HashMap<String, (WhisperTranscriber, Instant)>>,
+}
+
+impl LoadedModels {
+    /// Returns a transcriber with the named model loaded, loading it if needed
+    fn transcriber(&self, model_name: &str) -> Result<WhisperTranscriber, String> {
+        let mut transcribers = self.transcribers.borrow_mut();
+        if let Some((transcriber, last_used)) = transcribers.get_mut(model_name) {
+            *last_used = Instant::now();
+            return Ok(transcriber.clone());
         }
-    };
 
-    println!("📁 Cache directory: {}", cache_dir.display());
-    println!();
+        let model_path = ModelSpec::Named(model_name.to_string())
+            .resolve_with(|_| {})
+            .map_err(|e| format!("Failed to get Whisper model: {}", e))?;
+        let transcriber = WhisperTranscriber::new(model_path);
+        transcriber
+            .preload()
+            .map_err(|e| format!("Failed to load Whisper model: {}", e))?;
+        transcribers.insert(
+            model_name.to_string(),
+            (transcriber.clone(), Instant::now()),
+        );
+        Ok(transcriber)
+    }
+
+    /// Unloads the models unused for the keep-alive time, returning their names
+    fn unload_idle(&self, keepalive: Duration) -> Vec<String> {
+        let mut transcribers = self.transcribers.borrow_mut();
+        let idle: Vec<String> = transcribers
+            .iter()
+            .filter(|(_, (_, last_used))| last_used.elapsed() >= keepalive)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &idle {
+            transcribers.remove(name);
+        }
+        idle
+    }
+}
 
-    // Get list of cached models
-    let cached_models = match model_downloader::list_cached_models() {
-        Ok(models) => models,
+/// Identifies a download with a profile and links it into the library
+///
+/// Returns the usual exit codes: matched if every video was linked,
+/// partial if some were, unmatched if none were (or there were none), and
+/// environment if the hook couldn't run at all. Records the progress in
+/// the metrics if given, e.g. to serve them from the queue runner.
+fn run_hook(
+    profile_name: &str,
+    config: Option<&Path>,
+    path: &Path,
+    models: &LoadedModels,
+    metrics: Option<&Mutex<RunMetrics>>,
+) -> i32 {
+    let profile = match config
+        .map_or_else(default_profiles_path, |c| Ok(c.to_path_buf()))
+        .and_then(|config| load_hook_profile(&config, profile_name))
+    {
+        Ok(profile) => profile,
         Err(e) => {
-            eprintln!("⚠️  Warning: Failed to read cached models: {}", e);
-            Vec::new()
+            eprintln!("❌ Error: {}", e);
+            return EXIT_ENVIRONMENT;
+        }
+    };
+    let log_path = match &profile.log {
+        Some(log) => Ok(log.clone()),
+        None => default_hook_log_path(),
+    };
+    let log = log_path.map_err(|e| e.to_string()).and_then(|log_path| {
+        HookLog::open(&log_path)
+            .map_err(|e| format!("Failed to open log {}: {}", log_path.display(), e))
+    });
+    let mut log = match log {
+        Ok(log) => log,
+        Err(e) => {
+            eprintln!("❌ Error: {}", e);
+            return EXIT_ENVIRONMENT;
+        }
+    };
+    let mut record = |event: HookEvent| {
+        if let Err(e) = log.write(&event) {
+            eprintln!("⚠️  Failed to write hook log: {}", e);
         }
     };
 
-    // Create a map for quick lookup
-    let cached_map: HashMap<String, &model_downloader::CachedModelInfo> = cached_models
-        .iter()
-        .map(|m| (m.model_name.clone(), m))
-        .collect();
+    record(HookEvent::Started {
+        profile: profile_name.to_string(),
+        path: path.to_path_buf(),
+    });
+    let (triage, operations) = match identify_download(&profile, path, models, metrics) {
+        Ok(identified) => identified,
+        Err(error) => {
+            record(HookEvent::Failed {
+                source: None,
+                error,
+            });
+            return EXIT_ENVIRONMENT;
+        }
+    };
 
-    // Display all models
-    let all_models = model_downloader::supported_models();
+    let history = open_history();
+    let mut history_failed = false;
+    let mut remember = |result: &MatchResult, action, destination: Option<&Path>| {
+        record_history(
+            history.as_ref(),
+            &mut history_failed,
+            &profile.show,
+            result,
+            action,
+            destination,
+        );
+    };
 
-    println!("Available Models:");
-    for model in all_models.iter() {
-        if let Some(info) = cached_map.get(*model) {
-            println!("  ✓ {:<30} ({})", model, info.size_human_readable());
-        } else {
-            println!("  ○ {:<30} (not downloaded)", model);
+    let (mut linked, mut skipped, mut failed) = (0, 0, 0);
+    for result in &triage.review {
+        remember(result, HistoryAction::Review, None);
+    }
+    for result in &triage.unmatched {
+        remember(result, HistoryAction::Unmatched, None);
+    }
+    for result in triage.review.iter().chain(&triage.unmatched) {
+        skipped += 1;
+        record(HookEvent::Skipped {
+            source: result.video.path.clone(),
+            reason: format!("match to {} not confident enough", episode_label(result)),
+        });
+    }
+    for (op, result) in operations.iter().zip(&triage.auto_apply) {
+        let outcome = execute_hardlink(std::slice::from_ref(op), &profile.library)
+            .map_err(|e| e.to_string())
+            .and_then(|errors| {
+                errors
+                    .into_iter()
+                    .next()
+                    .map_or(Ok(()), |e| Err(e.to_string()))
+            });
+        match outcome {
+            Ok(()) => {
+                linked += 1;
+                remember(result, HistoryAction::Linked, Some(&op.destination));
+                record(HookEvent::Linked {
+                    source: op.source.clone(),
+                    destination: op.destination.clone(),
+                    season: op.episode.season_number,
+                    episode: op.episode.episode_number,
+                    confidence: result.confidence,
+                });
+            }
+            Err(error) => {
+                failed += 1;
+                remember(result, HistoryAction::Failed, None);
+                record(HookEvent::Failed {
+                    source: Some(op.source.clone()),
+                    error,
+                });
+            }
         }
     }
 
-    println!();
-    println!("💡 Tips:");
-    println!("  - Use --model <NAME> to select a model (e.g., --model tiny)");
-    println!("  - Models are downloaded automatically on first use");
-    println!("  - Smaller models are faster but less accurate");
-    println!("  - Quantized models have -q suffix (smaller size, slightly lower quality)");
-    println!();
-
-    if !cached_models.is_empty() {
-        let total_size: u64 = cached_models.iter().map(|m| m.size_bytes).sum();
-        println!(
-            "📊 Total cached: {} models, {} used",
-            cached_models.len(),
-            humansize::format_size(total_size, humansize::BINARY)
-        );
+    record(HookEvent::Finished {
+        linked,
+        skipped,
+        failed,
+    });
+    if linked == 0 {
+        EXIT_UNMATCHED
+    } else if skipped + failed > 0 {
+        EXIT_PARTIAL
+    } else {
+        EXIT_MATCHED
     }
-
-    process::exit(0);
 }
 
-/// Presents an interactive series selection prompt using `dialoguer::Select`.
-///
-/// Builds display labels with year disambiguation: if two candidates share
-/// the same name, both get a "(year)" suffix to tell them apart.
-fn select_series_interactive(
-    candidates: &[SeriesCandidate],
-) -> Result<usize, DialogDetectiveError> {
-    use std::collections::HashMap;
-
-    // Count how many times each name appears so we know when to show the year
-    let mut name_counts: HashMap<&str, usize> = HashMap::new();
-    for candidate in candidates {
-        *name_counts.entry(&candidate.name).or_default() += 1;
+/// Adds a download to the job queue for `queue run`
+fn queue_download(profile: &str, config: Option<&Path>, path: &Path, urgent: bool) -> i32 {
+    // Torrent clients may pass a relative path, but the queue runs elsewhere
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let config = config.map(|c| fs::canonicalize(c).unwrap_or_else(|_| c.to_path_buf()));
+    let queued = default_queue_path()
+        .and_then(|queue_path| JobQueue::open(&queue_path))
+        .and_then(|mut queue| {
+            let id = queue.push(profile, config.as_deref(), &path, urgent).id;
+            queue.save().map(|_| id)
+        });
+    match queued {
+        Ok(id) => {
+            outln!("📥 Queued job {}: {}", id, path.display());
+            EXIT_MATCHED
+        }
+        Err(e) => {
+            eprintln!("❌ Error: {}", e);
+            EXIT_ENVIRONMENT
+        }
     }
+}
 
-    // Build display labels with year disambiguation
-    let display_items: Vec<String> = candidates
-        .iter()
-        .map(|c| {
-            if name_counts.get(c.name.as_str()).copied().unwrap_or(0) > 1 {
-                match c.year {
-                    Some(year) => format!("{} ({})", c.name, year),
-                    None => format!("{} (unknown year)", c.name),
+/// Signs in to or out of Trakt and exits
+fn run_trakt_action_and_exit(action: &TraktAction) -> ! {
+    let Some(trakt) = TraktClient::from_env() else {
+        eprintln!("{}", tr!("error.trakt_credentials_required"));
+        process::exit(EXIT_USAGE);
+    };
+
+    match action {
+        TraktAction::Login => {
+            let code = match trakt.request_device_code() {
+                Ok(code) => code,
+                Err(e) => {
+                    eprintln!("❌ Error: Failed to start signing in to Trakt: {}", e);
+                    process::exit(EXIT_ENVIRONMENT);
                 }
-            } else {
-                c.name.clone()
+            };
+            outln!(
+                "🔑 Open {} and enter the code {}",
+                code.verification_url,
+                code.user_code
+            );
+            outln!(
+                "⏳ Waiting for you to sign in (the code expires in {} minute(s))...",
+                code.expires_in.as_secs().div_ceil(60)
+            );
+            if let Err(e) = trakt.wait_for_authorization(&code) {
+                eprintln!("❌ Error: Failed to sign in to Trakt: {}", e);
+                process::exit(EXIT_ENVIRONMENT);
             }
-        })
-        .collect();
+            outln!("✅ Signed in to Trakt");
+        }
+        TraktAction::Logout => match trakt.sign_out() {
+            Ok(true) => outln!("✅ Signed out of Trakt"),
+            Ok(false) => outln!("✨ Not signed in to Trakt"),
+            Err(e) => {
+                eprintln!("❌ Error: Failed to sign out of Trakt: {}", e);
+                process::exit(EXIT_ENVIRONMENT);
+            }
+        },
+    }
+    process::exit(0);
+}
 
-    println!();
+/// Removes the cache entries and history entries of a show or video file, then exits
+fn purge_cache_and_exit(show: Option<&str>, file: Option<&Path>) -> ! {
+    fn fail(e: impl std::fmt::Display) -> ! {
+        eprintln!("❌ Error: {}", e);
+        process::exit(EXIT_ENVIRONMENT);
+    }
 
-    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
-        .with_prompt("🔎 Multiple matches found — select the correct series")
-        .items(&display_items)
-        .default(0)
-        .interact_opt()
-        .map_err(|e| DialogDetectiveError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+    let history = default_history_path()
+        .and_then(|path| History::open(&path))
+        .unwrap_or_else(|e| fail(e));
 
-    match selection {
-        Some(index) => Ok(index),
-        None => Err(DialogDetectiveError::SelectionCancelled),
-    }
-}
+    let (purge, history_entries) = match (show, file) {
+        (Some(show), _) => {
+            let hashes = history.remove_show(show).unwrap_or_else(|e| fail(e));
+            let purge = purge_show_cache(show, &hashes).unwrap_or_else(|e| fail(e));
+            (purge, hashes.len())
+        }
+        (None, Some(file)) => {
+            let purge = purge_video_cache(file).unwrap_or_else(|e| fail(e));
+            let mut removed = 0;
+            for hash in &purge.video_hashes {
+                removed += history.remove_video(hash).unwrap_or_else(|e| fail(e));
+            }
+            (purge, removed)
+        }
+        (None, None) => unreachable!("clap requires --show or --file"),
+    };
 
-fn main() {
-    let cli = Cli::parse();
+    outln!(
+        "🗑️  Removed {} cache entries of {} video(s) and {} history entries",
+        purge.removed_entries,
+        purge.video_hashes.len(),
+        history_entries
+    );
+    process::exit(0);
+}
 
-    // Handle --list-models flag
-    if cli.list_models {
-        display_model_list_and_exit();
+/// Removes the models unused for the given time and exits
+fn prune_models_and_exit(unused_for: Duration, dry_run: bool) -> ! {
+    let stale = match model_downloader::stale_models(unused_for) {
+        Ok(stale) => stale,
+        Err(e) => {
+            eprintln!("❌ Error: Failed to read cached models: {}", e);
+            process::exit(EXIT_ENVIRONMENT);
+        }
+    };
+    if stale.is_empty() {
+        outln!("✨ No models to prune");
+        process::exit(0);
     }
 
-    // Unwrap required arguments (safe because of required_unless_present)
-    let video_dir = cli.video_dir.expect("video_dir should be present");
-    let show_name = cli.show_name.expect("show_name should be present");
-
-    // Validate arguments
-    if !video_dir.exists() {
-        eprintln!(
-            "❌ Error: Directory does not exist: {}",
-            video_dir.display()
-        );
-        process::exit(1);
+    let mut reclaimed = 0;
+    let mut failed = false;
+    for model in &stale {
+        let last_used = model
+            .last_used
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or_else(String::new, |d| format_utc_time(d.as_secs()));
+        let size = if dry_run {
+            Ok(model.size_bytes)
+        } else {
+            model_downloader::remove_cached_model(model)
+        };
+        match size {
+            Ok(size) => {
+                reclaimed += size;
+                outln!(
+                    "  🗑️  {:<30} {:>10}  last used {}",
+                    model.model_name,
+                    humansize::format_size(size, humansize::BINARY),
+                    last_used
+                );
+            }
+            Err(e) => {
+                eprintln!("❌ Error: {}", e);
+                failed = true;
+            }
+        }
     }
 
-    if !video_dir.is_dir() {
-        eprintln!("❌ Error: Path is not a directory: {}", video_dir.display());
-        process::exit(1);
+    let reclaimed = humansize::format_size(reclaimed, humansize::BINARY);
+    if dry_run {
+        outln!("📊 Would reclaim {} (dry run)", reclaimed);
+    } else {
+        outln!("📊 Reclaimed {}", reclaimed);
     }
+    process::exit(if failed { EXIT_ENVIRONMENT } else { 0 });
+}
 
-    // Resolve model path: custom path, selected model, or default 'base'
-    let model_path = if let Some(custom_path) = cli.model_path {
-        // Custom model path provided - validate it exists
-        if !custom_path.exists() {
-            eprintln!(
-                "❌ Error: Model file does not exist: {}",
-                custom_path.display()
-            );
-            process::exit(1);
-        }
+/// Runs an action of the `queue` subcommand and exits
+fn run_queue_action_and_exit(action: &QueueAction) -> ! {
+    let queue_path = default_queue_path().unwrap_or_else(|e| {
+        eprintln!("❌ Error: {}", e);
+        process::exit(EXIT_ENVIRONMENT);
+    });
+    let open = || {
+        JobQueue::open(&queue_path).unwrap_or_else(|e| {
+            eprintln!("❌ Error: {}", e);
+            process::exit(EXIT_ENVIRONMENT);
+        })
+    };
 
-        if !custom_path.is_file() {
-            eprintln!(
-                "❌ Error: Model path is not a file: {}",
-                custom_path.display()
-            );
-            process::exit(1);
+    let changed = match action {
+        QueueAction::List => {
+            print_queue(open().jobs());
+            process::exit(EXIT_MATCHED);
         }
+        QueueAction::Run {
+            smallest_first,
+            watch,
+            model_keepalive,
+            metrics_listen,
+        } => run_queue_and_exit(
+            open,
+            *smallest_first,
+            *watch,
+            *model_keepalive,
+            metrics_listen.as_deref(),
+        ),
+        QueueAction::Move { id, position } => {
+            let mut queue = open();
+            queue
+                .move_job(*id, *position)
+                .and_then(|_| queue.save())
+                .map(|_| format!("↕️  Moved job {} to position {}", id, position))
+        }
+        QueueAction::Pause { id } => {
+            let mut queue = open();
+            queue
+                .set_paused(*id, true)
+                .and_then(|_| queue.save())
+                .map(|_| format!("⏸️  Paused job {}", id))
+        }
+        QueueAction::Resume { id } => {
+            let mut queue = open();
+            queue
+                .set_paused(*id, false)
+                .and_then(|_| queue.save())
+                .map(|_| format!("▶️  Resumed job {}", id))
+        }
+        QueueAction::Cancel { id } => {
+            let mut queue = open();
+            queue
+                .cancel(*id)
+                .and_then(|job| queue.save().map(|_| job))
+                .map(|job| format!("🗑️  Cancelled job {}: {}", id, job.path.display()))
+        }
+    };
 
-        custom_path
-    } else {
-        // Determine which model to use
-        let model_name = cli.model.as_deref().unwrap_or("base");
-
-        // Validate model name against supported list
-        let supported = model_downloader::supported_models();
-        if !supported.contains(&model_name) {
-            eprintln!("❌ Error: Unsupported model '{}'", model_name);
-            eprintln!();
-            eprintln!("Supported models:");
-            for (i, model) in supported.iter().enumerate() {
-                eprint!("  {}", model);
-                if (i + 1) % 4 == 0 {
-                    eprintln!();
-                } else {
-                    eprint!("  ");
-                }
-            }
-            if supported.len() % 4 != 0 {
-                eprintln!();
-            }
-            eprintln!();
-            eprintln!("💡 Tip: Use --list-models to see all available models with details");
-            process::exit(1);
+    match changed {
+        Ok(message) => {
+            outln!("{}", message);
+            process::exit(EXIT_MATCHED);
         }
+        Err(e) => {
+            eprintln!("❌ Error: {}", e);
+            process::exit(EXIT_USAGE);
+        }
+    }
+}
 
-        // Download model if needed
-        match model_downloader::ensure_model_available(model_name) {
-            Ok(path) => path,
+/// Works off the job queue one job at a time and exits
+///
+/// The queue is only locked while taking a job, so downloads can be queued
+/// and jobs reordered while another one runs. Exits with the worst exit
+/// code of the jobs.
+fn run_queue_and_exit(
+    open: impl Fn() -> JobQueue,
+    smallest_first: bool,
+    watch: bool,
+    model_keepalive: Duration,
+    metrics_listen: Option<&str>,
+) -> ! {
+    let models = LoadedModels::default();
+    let metrics = metrics_listen.map(|address| {
+        let metrics = Arc::new(Mutex::new(RunMetrics::new()));
+        match serve_metrics(address, Arc::clone(&metrics)) {
+            Ok(address) => outln!("📈 Serving metrics at http://{}/metrics", address),
             Err(e) => {
-                eprintln!(
-                    "❌ Error: Failed to download Whisper model '{}': {}",
-                    model_name, e
-                );
-                eprintln!("💡 Tip: You can manually specify a model path with --model-path");
-                process::exit(1);
+                eprintln!("❌ Error: Failed to serve metrics on {}: {}", address, e);
+                process::exit(EXIT_ENVIRONMENT);
             }
         }
-    };
+        metrics
+    });
+    let mut exit_codes = Vec::new();
+    let mut waiting = false;
+    loop {
+        let running = {
+            let mut queue = open();
+            let running = queue.start_next(smallest_first).and_then(|running| {
+                queue.save()?;
+                Ok(running)
+            });
+            let running = running.unwrap_or_else(|e| {
+                eprintln!("❌ Error: {}", e);
+                process::exit(EXIT_ENVIRONMENT);
+            });
+            if let Some(metrics) = &metrics {
+                let waiting = queue.jobs().iter().filter(|j| !j.running).count();
+                lock_metrics(metrics).set_queued_jobs(waiting);
+            }
+            running
+        };
+        let Some(running) = running else {
+            if !watch {
+                break;
+            }
+            if !waiting {
+                outln!("👀 Waiting for new jobs...");
+                waiting = true;
+            }
+            for model in models.unload_idle(model_keepalive) {
+                outln!("💤 Unloaded Whisper model '{}'", model);
+            }
+            std::thread::sleep(QUEUE_POLL_INTERVAL);
+            continue;
+        };
+        waiting = false;
 
-    // Validate mode-specific requirements
-    if matches!(cli.mode, Mode::Copy) && cli.output_dir.is_none() {
-        eprintln!("❌ Error: --output-dir is required when using --mode copy");
-        process::exit(1);
+        let job = &running.job;
+        outln!(
+            "🔎 Job {} ({}): {}",
+            job.id,
+            job.profile,
+            job.path.display()
+        );
+        let code = run_hook(
+            &job.profile,
+            job.config.as_deref(),
+            &job.path,
+            &models,
+            metrics.as_deref(),
+        );
+        let outcome = match code {
+            EXIT_MATCHED => "all linked",
+            EXIT_PARTIAL => "partly linked",
+            EXIT_UNMATCHED => "nothing linked",
+            _ => "failed",
+        };
+        outln!("   └─ {} (see the hook log)", outcome);
+        exit_codes.push(code);
+
+        let mut queue = open();
+        queue.finish(running);
+        if let Err(e) = queue.save() {
+            eprintln!("❌ Error: {}", e);
+            process::exit(EXIT_ENVIRONMENT);
+        }
     }
 
-    // Convert seasons filter
-    let season_filter = if cli.seasons.is_empty() {
+    outln!();
+    outln!("🏁 Ran {} job(s)", exit_codes.len());
+    process::exit(combined_exit_code(&exit_codes));
+}
+
+/// Locks metrics shared with the metrics endpoint
+fn lock_metrics(metrics: &Mutex<RunMetrics>) -> MutexGuard<'_, RunMetrics> {
+    metrics.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Prints the queued jobs
+fn print_queue(jobs: &[QueuedJob]) {
+    if jobs.is_empty() {
+        outln!("📭 The queue is empty");
+        return;
+    }
+
+    outln!("📬 {} queued job(s):", jobs.len());
+    for (position, job) in jobs.iter().enumerate() {
+        let mut flags = Vec::new();
+        if job.urgent {
+            flags.push("urgent");
+        }
+        if job.paused {
+            flags.push("paused");
+        }
+        if job.running {
+            flags.push("running");
+        }
+        let flags = if flags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", flags.join(", "))
+        };
+        outln!(
+            "  {}. #{} {} ({}, {}){}",
+            position + 1,
+            job.id,
+            job.path.display(),
+            job.profile,
+            humansize::format_size(job.size, humansize::BINARY),
+            flags
+        );
+    }
+}
+
+/// Investigates a download with the settings of a hook profile
+///
+/// Returns the triaged matches and the operations linking the confident
+/// ones into the library.
+fn identify_download(
+    profile: &HookProfile,
+    path: &Path,
+    models: &LoadedModels,
+    metrics: Option<&Mutex<RunMetrics>>,
+) -> Result<(Triage, Vec<PlannedOperation>), String> {
+    validate_format(&profile.format).map_err(|e| format!("Invalid format: {}", e))?;
+    let model_name = profile
+        .model
+        .as_deref()
+        .unwrap_or(model_downloader::DEFAULT_MODEL);
+    let transcriber = models.transcriber(model_name)?;
+
+    let season_filter = (!profile.seasons.is_empty()).then(|| profile.seasons.clone());
+    let investigation = Investigation::new(path, profile.show.as_str(), transcriber)
+        .season_filter(season_filter)
+        .matcher(profile.matcher.unwrap_or(MatcherType::GeminiFlash));
+    let matches = investigation.run(
+        |event| {
+            if let Some(metrics) = metrics {
+                lock_metrics(metrics).record(&event);
+            }
+        },
+        |candidates| Ok(select_series_non_interactive(&profile.show, candidates)),
+    );
+    if let Some(metrics) = metrics {
+        let mut metrics = lock_metrics(metrics);
+        match &matches {
+            Ok(matches) => metrics.record_matches(matches),
+            Err(e) => metrics.record_failure(e),
+        }
+    }
+    let matches = matches.map_err(|e| format!("Investigation failed: {}", e))?;
+
+    let triage = triage_matches(matches, TriageThresholds::default());
+    let operations = plan_operations(
+        &triage.auto_apply,
+        &profile.show,
+        &profile.format,
+        Some(&profile.library),
+    )
+    .map_err(|e| format!("Failed to plan operations: {}", e))?;
+    Ok((triage, operations))
+}
+
+/// Adds the matched episodes to the Trakt collection, reporting the outcome
+///
+/// The files are already in place, so failures only print a warning.
+fn add_to_trakt_collection(
+    trakt: &TraktClient,
+    matches: &[MatchResult],
+    series_name: &str,
+    year: Option<u16>,
+) {
+    outln!("📚 Adding the episodes to your Trakt collection...");
+    match trakt.collect(matches, series_name, year) {
+        Ok(collection) => {
+            outln!(
+                "✅ Added {} episode(s) to Trakt, {} already collected",
+                collection.added,
+                collection.existing
+            );
+            if collection.not_found > 0 {
+                outln!("⚠️  Trakt doesn't know {} episode(s)", collection.not_found);
+            }
+        }
+        Err(e) => outln!("⚠️  Failed to update the Trakt collection: {}", e),
+    }
+}
+
+/// Hands the matches to Sonarr's manual import, reporting skipped files
+///
+/// Returns the exit code of the import and the files queued for import.
+fn import_into_sonarr(
+    sonarr: &SonarrClient,
+    matches: &[MatchResult],
+    series_name: &str,
+    year: Option<u16>,
+    mode: SonarrImportMode,
+) -> (i32, Vec<PathBuf>) {
+    outln!("📡 Handing {} file(s) to Sonarr...", matches.len());
+    outln!();
+
+    let import = match sonarr.import(matches, series_name, year, mode) {
+        Ok(import) => import,
+        Err(e) => {
+            eprintln!("❌ Sonarr import failed: {}", e);
+            return (EXIT_ENVIRONMENT, Vec::new());
+        }
+    };
+
+    for path in &import.queued {
+        outln!("  ✓ {}", path.display());
+    }
+    for (path, reason) in &import.skipped {
+        outln!("  ✗ {} - {}", path.display(), reason);
+    }
+    outln!();
+    match import.command_id {
+        Some(id) => outln!(
+            "✅ Queued {} file(s) for import in Sonarr (command {})",
+            import.queued.len(),
+            id
+        ),
+        None => outln!("❌ No file could be handed to Sonarr"),
+    }
+    let code = if import.queued.is_empty() {
+        EXIT_UNMATCHED
+    } else if !import.skipped.is_empty() {
+        EXIT_PARTIAL
+    } else {
+        EXIT_MATCHED
+    };
+    (code, import.queued)
+}
+
+/// Prints the planned operations of a dry run
+fn print_plan(operations: &[PlannedOperation], operation_type: &str) {
+    for op in operations {
+        let source_name = op
+            .source
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+        let dest_name = op
+            .destination
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+
+        if let Some(suffix) = op.duplicate_suffix {
+            outln!(
+                "  [{}] {} → {} (duplicate #{})",
+                operation_type,
+                source_name,
+                dest_name,
+                suffix
+            );
+        } else if op.quarantined {
+            outln!(
+                "  [{}] {} → {}/ (duplicate)",
+                operation_type,
+                source_name,
+                DUPLICATES_DIR
+            );
+        } else if let Some(part) = op.part {
+            outln!(
+                "  [{}] {} → {} (part {} of {})",
+                operation_type,
+                source_name,
+                dest_name,
+                part.number,
+                part.count
+            );
+        } else {
+            outln!("  [{}] {} → {}", operation_type, source_name, dest_name);
+        }
+    }
+}
+
+/// Prints what changed in a dry run since the saved plan
+fn print_plan_diff(previous: &SavedPlan, operations: &[PlannedOperation], operation_type: &str) {
+    let diff = diff_plan(previous, operations);
+    outln!(
+        "{}",
+        tr!(
+            "result.diff_since",
+            time = format_utc_time(previous.saved_at)
+        )
+    );
+    outln!();
+
+    for entry in &diff.added {
+        outln!(
+            "  + [{}] {} → {}",
+            operation_type,
+            display_name(&entry.source),
+            display_name(&entry.destination)
+        );
+    }
+    for change in &diff.changed {
+        outln!(
+            "{}",
+            tr!(
+                "result.diff_changed",
+                kind = operation_type,
+                source = display_name(&change.source),
+                destination = display_name(&change.destination),
+                previous = display_name(&change.previous),
+            )
+        );
+    }
+    for entry in &diff.removed {
+        outln!(
+            "{}",
+            tr!("result.diff_removed", file = display_name(&entry.source))
+        );
+    }
+    outln!("{}", tr!("result.diff_unchanged", count = diff.unchanged));
+}
+
+/// Loads the plan of the last dry run of a directory, warning about failures
+fn load_previous_plan(video_dir: &Path) -> Option<SavedPlan> {
+    match default_plans_dir().and_then(|plans_dir| load_plan(&plans_dir, video_dir)) {
+        Ok(plan) => plan,
+        Err(e) => {
+            outln!("⚠️  Failed to load the last dry run: {}", e);
+            None
+        }
+    }
+}
+
+/// Saves the plan of a dry run for a later --diff, warning about failures
+fn save_dry_run_plan(video_dir: &Path, operations: &[PlannedOperation]) {
+    let plan = SavedPlan::new(video_dir, operations);
+    if let Err(e) = default_plans_dir().and_then(|plans_dir| save_plan(&plans_dir, &plan)) {
+        outln!("⚠️  Failed to save the plan for --diff: {}", e);
+    }
+}
+
+/// Pairs planned operations with the matches they were planned for
+fn operation_results<'a>(
+    operations: &'a [PlannedOperation],
+    results: impl Iterator<Item = &'a MatchResult>,
+) -> Vec<(&'a PlannedOperation, &'a MatchResult)> {
+    let results: Vec<&MatchResult> = results.collect();
+    operations
+        .iter()
+        .filter_map(|op| {
+            let result = results.iter().find(|r| r.video.path == op.source)?;
+            Some((op, *result))
+        })
+        .collect()
+}
+
+/// Writes the transcripts as subtitles, reporting failures
+fn write_transcript_subtitles(
+    operations: &[PlannedOperation],
+    matches: &[MatchResult],
+    mode: SubtitleMode,
+) {
+    outln!("💬 Writing subtitles...");
+    for error in write_subtitles(operations, matches, mode) {
+        outln!("⚠️  Failed to write subtitles: {}", error);
+    }
+}
+
+/// Writes the matched episodes into the files, reporting failures
+fn write_container_tags(operations: &[PlannedOperation], show_name: &str) {
+    outln!("🏷️  Writing tags...");
+    for error in write_tags(operations, show_name) {
+        outln!("⚠️  Failed to write tags: {}", error);
+    }
+}
+
+/// Prints the progress of copying a file on a single, updating line
+fn print_copy_progress(event: CopyProgress) {
+    match event {
+        CopyProgress::Copying {
+            source,
+            copied,
+            total,
+        } => {
+            let percent = (copied * 100).checked_div(total).unwrap_or(100);
+            out!("\r  📦 {} {:>3}%", display_name(&source), percent);
+        }
+        CopyProgress::Verifying { .. } => out!(" 🔍"),
+        CopyProgress::Copied { .. } => {
+            outln!(" ✓");
+            return;
+        }
+    }
+    std::io::Write::flush(&mut std::io::stdout()).ok();
+}
+
+/// Formats a duration for display (e.g. "850ms", "12.3s", "4m 05s")
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs_f64();
+    if secs < 1.0 {
+        format!("{}ms", duration.as_millis())
+    } else if secs < 60.0 {
+        format!("{:.1}s", secs)
+    } else {
+        let total = duration.as_secs();
+        format!("{}m {:02}s", total / 60, total % 60)
+    }
+}
+
+/// Assigns every match a unique episode and reports the files left without one
+fn resolve_duplicates(matches: Vec<MatchResult>) -> Vec<MatchResult> {
+    let assignment = assign_episodes(matches);
+
+    if !assignment.unassigned.is_empty() {
+        outln!(
+            "⚠️  Skipping {} file(s) without a unique episode:",
+            assignment.unassigned.len()
+        );
+        for result in &assignment.unassigned {
+            outln!(
+                "  ✗ {} (S{:02}E{:02} taken)",
+                file_name(result),
+                result.episode.season_number,
+                result.episode.episode_number
+            );
+        }
+        outln!();
+    }
+
+    assignment.assigned
+}
+
+/// Splits the outcomes of an investigation into the matches and the failed videos
+///
+/// Videos the matcher found no episode for count as failed. Videos without
+/// dialogue and skipped files are left out, like `Investigation::run` does.
+fn split_outcomes(
+    outcomes: Vec<InvestigationOutcome>,
+) -> (Vec<MatchResult>, Vec<(PathBuf, DialogDetectiveError)>) {
+    let mut matches = Vec::new();
+    let mut failed = Vec::new();
+    for outcome in outcomes {
+        match outcome {
+            InvestigationOutcome::Matched(result) => matches.push(*result),
+            InvestigationOutcome::Failed { path, error } => failed.push((path, error)),
+            InvestigationOutcome::Unmatched {
+                path,
+                reason: UnmatchedReason::NoMatchFound { response },
+                ..
+            } => failed.push((path, EpisodeMatchingError::NoMatchFound { response }.into())),
+            InvestigationOutcome::Unmatched { .. } | InvestigationOutcome::Skipped { .. } => {}
+        }
+    }
+    (matches, failed)
+}
+
+/// Prints the videos that failed, with the first line of their errors
+fn print_failed_videos(failed: &[(PathBuf, DialogDetectiveError)]) {
+    if failed.is_empty() {
+        return;
+    }
+    outln!("{}", tr!("result.failed_videos", count = failed.len()));
+    for (path, error) in failed {
+        let message = error.to_string();
+        let message = message.lines().next().unwrap_or_default();
+        outln!("  ✗ {} [{}] {}", display_name(path), error.code(), message);
+    }
+    outln!();
+}
+
+/// Prints the matches that are not applied automatically
+fn print_triage(triage: &Triage) {
+    if !triage.review.is_empty() {
+        outln!("🔎 Needs review ({} file(s)):", triage.review.len());
+        for result in &triage.review {
+            let alternatives: Vec<String> = result
+                .alternatives
+                .iter()
+                .map(|candidate| {
+                    format!(
+                        "S{:02}E{:02}",
+                        candidate.episode.season_number, candidate.episode.episode_number
+                    )
+                })
+                .collect();
+
+            out!(
+                "  ? {} → {} - {}{}",
+                file_name(result),
+                episode_label(result),
+                episode_titles(result),
+                format_confidence(result.confidence)
+            );
+            if alternatives.is_empty() {
+                outln!();
+            } else {
+                outln!(" [alternatives: {}]", alternatives.join(", "));
+            }
+            print_reasoning(result);
+
+            if let Some(conflict) = &result.filename_conflict {
+                let title = conflict
+                    .episode
+                    .as_ref()
+                    .map(|episode| format!(" - {}", episode.name))
+                    .unwrap_or_default();
+                outln!(
+                    "    ⚠️  File name says S{:02}E{:02}{}",
+                    conflict.season_number,
+                    conflict.episode_number,
+                    title
+                );
+            }
+
+            if let Some(mismatch) = &result.runtime_mismatch {
+                outln!(
+                    "    ⚠️  File runs {} min, episode runs {} min",
+                    mismatch.video_duration.as_secs() / 60,
+                    mismatch.episode_runtime.as_secs() / 60
+                );
+            }
+        }
+        outln!();
+    }
+
+    if !triage.unmatched.is_empty() {
+        outln!("❓ Unmatched ({} file(s)):", triage.unmatched.len());
+        for result in &triage.unmatched {
+            outln!(
+                "  ✗ {} (best guess {}{})",
+                file_name(result),
+                episode_label(result),
+                format_confidence(result.confidence)
+            );
+            print_reasoning(result);
+        }
+        outln!();
+    }
+}
+
+/// Formats the episode numbers of a match for display (e.g. "S01E01-E02")
+fn episode_label(result: &MatchResult) -> String {
+    let episode = &result.episode;
+    match result.extra_episodes.last() {
+        Some(last) => format!(
+            "S{:02}E{:02}-E{:02}",
+            episode.season_number, episode.episode_number, last.episode_number
+        ),
+        None => format!(
+            "S{:02}E{:02}",
+            episode.season_number, episode.episode_number
+        ),
+    }
+}
+
+/// Formats an episode for progress lines, e.g. "S01E02 - Cat's in the Bag..."
+fn episode_line(episode: &Episode) -> String {
+    format!(
+        "S{:02}E{:02} - {}",
+        episode.season_number, episode.episode_number, episode.name
+    )
+}
+
+/// Joins the episode titles of a match for display
+fn episode_titles(result: &MatchResult) -> String {
+    std::iter::once(&result.episode)
+        .chain(&result.extra_episodes)
+        .map(|episode| episode.name.as_str())
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+/// Prints the matcher's reasoning below a listed match, if it gave one
+fn print_reasoning(result: &MatchResult) {
+    if let Some(reasoning) = &result.reasoning {
+        outln!("    💭 {}", reasoning);
+    }
+}
+
+/// Formats a match confidence for display, empty if none was reported
+fn format_confidence(confidence: Option<f64>) -> String {
+    confidence
+        .map(|c| format!(" ({:.0}%)", c * 100.0))
+        .unwrap_or_default()
+}
+
+/// Returns the file name of a matched video for display
+fn file_name(result: &MatchResult) -> &str {
+    display_name(&result.video.path)
+}
+
+/// Returns the file name of a path for display
+fn display_name(path: &Path) -> &str {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+}
+
+/// Returns the short name of a prompt kind, as used for dumped prompts
+fn prompt_kind_name(kind: PromptKind) -> &'static str {
+    match kind {
+        PromptKind::EpisodeMatch => "match",
+        PromptKind::SeasonInference => "season",
+    }
+}
+
+/// Returns the localized text of why a file was skipped
+fn skip_reason_text(reason: SkipReason) -> String {
+    match reason {
+        SkipReason::NotAVideo => tr!("skip.not_a_video"),
+        SkipReason::Downloading => tr!("skip.downloading"),
+        SkipReason::StillGrowing => tr!("skip.still_growing"),
+        SkipReason::NoVideoStream => tr!("skip.no_video_stream"),
+        SkipReason::Unverified => tr!("skip.unverified"),
+        SkipReason::Unreadable => tr!("skip.unreadable"),
+    }
+}
+
+/// Returns the name of a processing stage for display
+fn stage_name(stage: Stage) -> &'static str {
+    match stage {
+        Stage::Hashing => "hashing",
+        Stage::AudioExtraction => "extraction",
+        Stage::Transcription => "transcription",
+        Stage::Matching => "matching",
+    }
+}
+
+/// Writes the prompt and response of a `PromptExchanged` event to the dump directory
+fn dump_prompt(dump_dir: &Path, event: &ProgressEvent) -> io::Result<()> {
+    let ProgressEvent::PromptExchanged {
+        video_path,
+        kind,
+        prompt,
+        response,
+    } = event
+    else {
+        return Ok(());
+    };
+
+    let file_name = video_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown");
+    let kind = prompt_kind_name(*kind);
+
+    fs::write(
+        dump_dir.join(format!("{}.{}.prompt.txt", file_name, kind)),
+        prompt,
+    )?;
+    fs::write(
+        dump_dir.join(format!("{}.{}.response.txt", file_name, kind)),
+        response,
+    )
+}
+
+/// Writes the metrics file, warning only about the first failure
+fn write_metrics(metrics: &RunMetrics, path: &Path, failed: &mut bool) {
+    if let Err(e) = metrics.write_to(path)
+        && !*failed
+    {
+        *failed = true;
+        eprintln!("⚠️  Failed to write metrics to {}: {}", path.display(), e);
+    }
+}
+
+/// Opens the history database, warning if it can't be used
+fn open_history() -> Option<History> {
+    match default_history_path().and_then(|path| History::open(&path)) {
+        Ok(history) => Some(history),
+        Err(e) => {
+            eprintln!("⚠️  Not recording the history: {}", e);
+            None
+        }
+    }
+}
+
+/// Records a file in the history, warning only about the first failure
+fn record_history(
+    history: Option<&History>,
+    failed: &mut bool,
+    show_name: &str,
+    result: &MatchResult,
+    action: HistoryAction,
+    destination: Option<&Path>,
+) {
+    if let Some(history) = history
+        && let Err(e) = history.record(show_name, result, action, destination)
+        && !*failed
+    {
+        *failed = true;
+        eprintln!("⚠️  Failed to record the history: {}", e);
+    }
+}
+
+/// Writes the matches needing review to a JSON file
+fn write_review_file(path: &Path, review: &[MatchResult]) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(review).map_err(io::Error::other)?;
+    fs::write(path, json)
+}
+
+/// Formats a token count for display (e.g. "850", "45k", "1.2M")
+fn format_tokens(tokens: u64) -> String {
+    if tokens >= 1_000_000 {
+        format!("{:.1}M", tokens as f64 / 1_000_000.0)
+    } else if tokens >= 1_000 {
+        format!("{}k", (tokens as f64 / 1_000.0).round())
+    } else {
+        tokens.to_string()
+    }
+}
+
+/// Prints the tokens and estimated cost used for matching across all matches
+fn print_usage_summary(matches: &[MatchResult]) {
+    let usage: Option<TokenUsage> = matches.iter().filter_map(|m| m.usage).reduce(|a, b| a + b);
+
+    if let Some(usage) = usage {
+        match usage.cost_usd {
+            Some(cost) => outln!(
+                "🪙 Matching used ~{} tokens, est. ${:.2}",
+                format_tokens(usage.total_tokens()),
+                cost
+            ),
+            None => outln!(
+                "🪙 Matching used ~{} tokens",
+                format_tokens(usage.total_tokens())
+            ),
+        }
+        outln!();
+    }
+}
+
+/// Prints the cache hit rates, confidence, and more of the whole run
+fn print_run_summary(summary: &RunSummary) {
+    let rate = |rate: Option<f64>| {
+        rate.map(|r| tr!("summary.hit_rate", percent = format!("{:.0}", r * 100.0)))
+            .unwrap_or_default()
+    };
+
+    outln!();
+    outln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    outln!("{}", tr!("summary.title"));
+    outln!(
+        "{}",
+        tr!("summary.videos", count = summary.videos_processed)
+    );
+    outln!(
+        "{}",
+        tr!(
+            "summary.transcripts",
+            cached = summary.transcripts_cached,
+            created = summary.transcripts_created,
+            rate = rate(summary.transcript_hit_rate()),
+        )
+    );
+    outln!(
+        "{}",
+        tr!(
+            "summary.matches",
+            cached = summary.matches_cached,
+            hashed = summary.hash_matches,
+            created = summary.matches_created,
+            rate = rate(summary.match_hit_rate()),
+        )
+    );
+    outln!(
+        "{}",
+        tr!(
+            "summary.transcription_time",
+            duration = format_duration(summary.transcription_time)
+        )
+    );
+    if let Some(confidence) = summary.average_confidence {
+        let percent = format!("{:.0}", confidence * 100.0);
+        outln!("{}", tr!("summary.confidence", percent = percent));
+    }
+    outln!(
+        "{}",
+        tr!("summary.matcher_failures", count = summary.matcher_failures)
+    );
+    outln!(
+        "{}",
+        tr!(
+            "summary.written",
+            size = humansize::format_size(summary.bytes_written, humansize::BINARY)
+        )
+    );
+    outln!();
+}
+
+/// Prints the time spent in each processing stage across all matches
+fn print_timing_summary(matches: &[MatchResult]) {
+    let mut totals = StageTimings::default();
+    for stage in [
+        Stage::Hashing,
+        Stage::AudioExtraction,
+        Stage::Transcription,
+        Stage::Matching,
+    ] {
+        for result in matches {
+            if let Some(duration) = result.timings.get(stage) {
+                totals.record(stage, duration);
+            }
+        }
+    }
+
+    let parts: Vec<String> = [
+        ("hashing", totals.hashing),
+        ("extraction", totals.audio_extraction),
+        ("transcription", totals.transcription),
+        ("matching", totals.matching),
+    ]
+    .into_iter()
+    .filter_map(|(name, duration)| duration.map(|d| format!("{} {}", name, format_duration(d))))
+    .collect();
+
+    if !parts.is_empty() {
+        outln!("⏱️  Time spent: {}", parts.join(", "));
+        outln!();
+    }
+}
+
+/// Displays all available Whisper models with download status and exits
+fn display_model_list_and_exit() {
+    outln!("🔍 Available Whisper Models");
+    outln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    outln!();
+
+    // Get cache directory
+    let cache_dir = match model_downloader::get_cache_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("❌ Error: Failed to access cache directory: {}", e);
+            process::exit(EXIT_ENVIRONMENT);
+        }
+    };
+
+    outln!("📁 Cache directory: {}", cache_dir.display());
+    outln!();
+
+    // Get list of cached models
+    let cached_models = match model_downloader::list_cached_models() {
+        Ok(models) => models,
+        Err(e) => {
+            eprintln!("⚠️  Warning: Failed to read cached models: {}", e);
+            Vec::new()
+        }
+    };
+
+    // Create a map for quick lookup
+    let cached_map: HashMap<String, &model_downloader::CachedModelInfo> = cached_models
+        .iter()
+        .map(|m| (m.model_name.clone(), m))
+        .collect();
+
+    // Display all models
+    let all_models = model_downloader::supported_models();
+
+    outln!("Available Models:");
+    for model in all_models.iter() {
+        if let Some(info) = cached_map.get(*model) {
+            outln!("  ✓ {:<30} ({})", model, info.size_human_readable());
+        } else {
+            outln!("  ○ {:<30} (not downloaded)", model);
+        }
+    }
+
+    outln!();
+    outln!("💡 Tips:");
+    outln!("  - Use --model <NAME> to select a model (e.g., --model tiny)");
+    outln!("  - Models are downloaded automatically on first use");
+    outln!("  - Smaller models are faster but less accurate");
+    outln!("  - Quantized models have -q suffix (smaller size, slightly lower quality)");
+    outln!();
+
+    if !cached_models.is_empty() {
+        let total_size: u64 = cached_models.iter().map(|m| m.size_bytes).sum();
+        outln!(
+            "📊 Total cached: {} models, {} used",
+            cached_models.len(),
+            humansize::format_size(total_size, humansize::BINARY)
+        );
+    }
+
+    process::exit(0);
+}
+
+/// Validates a file naming format, previews it with sample episodes, and exits
+///
+/// Exits with an error if the format is malformed or would be rejected when
+/// renaming because of unknown placeholders.
+fn check_format_and_exit(format: &str) {
+    let warnings = match check_format(format) {
+        Ok(warnings) => warnings,
+        Err(e) => {
+            eprintln!("❌ Invalid format: {}", e);
+            process::exit(EXIT_USAGE);
+        }
+    };
+
+    outln!("🔍 Format: {}", format);
+    outln!();
+    let metadata = FilenameMetadata {
+        season_name: None,
+        show_year: Some(2011),
+        provider_id: Some(12345),
+        quality: Some("1080p x265"),
+        source_tag: Some("WEB-DL"),
+        group: Some("GROUP"),
+        local_title: None,
+    };
+    outln!(
+        "  Single episode: {}",
+        format_filename_with(
+            format,
+            "Example Show",
+            1,
+            &[(2, "Sample Episode")],
+            "mkv",
+            &metadata
+        )
+    );
+    outln!(
+        "  Double episode: {}",
+        format_filename_with(
+            format,
+            "Example Show",
+            1,
+            &[(2, "Sample Episode"), (3, "Another One")],
+            "mkv",
+            &metadata
+        )
+    );
+
+    if warnings.is_empty() {
+        outln!();
+        outln!("✓ Format is valid");
+        process::exit(0);
+    }
+
+    outln!();
+    for warning in &warnings {
+        outln!("⚠️  Warning: {}", warning);
+    }
+    if warnings
+        .iter()
+        .any(|w| matches!(w, FormatWarning::UnknownPlaceholder(_)))
+    {
+        let supported: Vec<String> = FORMAT_PLACEHOLDERS
+            .iter()
+            .map(|name| format!("{{{}}}", name))
+            .collect();
+        outln!("   Supported placeholders: {}", supported.join(", "));
+        eprintln!();
+        eprintln!("❌ Unknown placeholders aren't allowed when renaming files");
+        process::exit(EXIT_USAGE);
+    }
+
+    process::exit(0);
+}
+
+/// Compares a library with the episode list of its show and lists the gaps
+fn audit_library_and_exit(target: &Target, seasons: &[usize], json: bool) -> ! {
+    let library = target.video_dir.as_path();
+    if !library.is_dir() {
+        eprintln!("{}", tr!("error.not_a_directory", path = library.display()));
+        process::exit(EXIT_USAGE);
+    }
+
+    let options = MetadataOptions {
+        season_filter: (!seasons.is_empty()).then(|| seasons.to_vec()),
+        series_id: target.series_id,
+        ..Default::default()
+    };
+    let audit = match audit_library(library, &target.show_name, &options) {
+        Ok(audit) => audit,
+        Err(e) => {
+            eprintln!("❌ Audit failed: {}", e);
+            process::exit(EXIT_ENVIRONMENT);
+        }
+    };
+    let exit_code = if audit.is_complete() {
+        EXIT_MATCHED
+    } else {
+        EXIT_PARTIAL
+    };
+
+    if json {
+        outln!(
+            "{}",
+            serde_json::to_string(&audit).expect("audits serialize to JSON")
+        );
+        process::exit(exit_code);
+    }
+
+    let relative = |path: &Path| {
+        path.strip_prefix(library)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    outln!(
+        "🗂️  {}: {} video file(s) in {}",
+        audit.series_name,
+        audit.file_count,
+        library.display()
+    );
+    if !audit.missing.is_empty() {
+        outln!();
+        outln!("❓ Missing episodes ({}):", audit.missing.len());
+        for episode in &audit.missing {
+            outln!(
+                "   S{:02}E{:02} - {}",
+                episode.season_number,
+                episode.episode_number,
+                episode.name
+            );
+        }
+    }
+    if !audit.duplicates.is_empty() {
+        outln!();
+        outln!(
+            "🔁 Episodes stored more than once ({}):",
+            audit.duplicates.len()
+        );
+        for duplicate in &audit.duplicates {
+            let episode = &duplicate.episode;
+            outln!(
+                "   S{:02}E{:02} - {}",
+                episode.season_number,
+                episode.episode_number,
+                episode.name
+            );
+            for path in &duplicate.paths {
+                outln!("      {}", relative(path));
+            }
+        }
+    }
+    if !audit.unknown.is_empty() {
+        outln!();
+        outln!(
+            "🚫 Files named after unknown episodes ({}):",
+            audit.unknown.len()
+        );
+        for file in &audit.unknown {
+            let episodes: Vec<String> = file
+                .episode_numbers
+                .iter()
+                .map(|number| format!("E{:02}", number))
+                .collect();
+            outln!(
+                "   {} (S{:02}{})",
+                relative(&file.path),
+                file.season_number,
+                episodes.join("-")
+            );
+        }
+    }
+    if !audit.unrecognized.is_empty() {
+        outln!();
+        outln!(
+            "⚠️  Files without an episode number in the name ({}):",
+            audit.unrecognized.len()
+        );
+        for path in &audit.unrecognized {
+            outln!("   {}", relative(path));
+        }
+    }
+
+    outln!();
+    if audit.is_complete() {
+        outln!("✅ Every aired episode is in the library exactly once");
+    } else {
+        outln!("💡 Run an investigation on misnamed files to identify them by their dialogue");
+    }
+    process::exit(exit_code);
+}
+
+/// Prints the history entries matching a query and exits
+fn show_history_and_exit(query: &HistoryQuery, json: bool) -> ! {
+    let entries = default_history_path()
+        .and_then(|path| History::open(&path))
+        .and_then(|history| history.query(query));
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("❌ Error: {}", e);
+            process::exit(EXIT_ENVIRONMENT);
+        }
+    };
+
+    if json {
+        for entry in &entries {
+            outln!(
+                "{}",
+                serde_json::to_string(entry).expect("history entries serialize to JSON")
+            );
+        }
+        process::exit(0);
+    }
+
+    if entries.is_empty() {
+        outln!("📭 No investigated files recorded");
+        process::exit(0);
+    }
+    for entry in &entries {
+        let confidence = entry
+            .confidence
+            .map(|c| format!(" ({:.0}%)", c * 100.0))
+            .unwrap_or_default();
+        outln!(
+            "{}  {:<9}  {} S{:02}E{:02} - {}{}",
+            format_utc_time(entry.recorded_at),
+            entry.action.as_str(),
+            entry.show,
+            entry.season,
+            entry.episode,
+            entry.episode_title,
+            confidence
+        );
+        match &entry.destination {
+            Some(destination) => outln!(
+                "   {} → {}",
+                entry.original_path.display(),
+                destination.display()
+            ),
+            None => outln!("   {}", entry.original_path.display()),
+        }
+        outln!("   run {}, hash {}", entry.run_id, entry.video_hash);
+    }
+    process::exit(0);
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time
+fn format_utc_time(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+
+    // Civil date from days since 1970-01-01, with years counted from March
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60
+    )
+}
+
+/// Runs the health checks, displays the results, and exits
+fn run_doctor_and_exit(matcher: MatcherType, model: &str, model_path: Option<&Path>) -> ! {
+    outln!("🩺 Checking the detective's equipment...");
+    outln!();
+
+    let checks = run_health_checks(matcher, model, model_path);
+    for check in &checks {
+        let icon = match check.status {
+            CheckStatus::Ok => "✅",
+            CheckStatus::Warning => "⚠️ ",
+            CheckStatus::Failed => "❌",
+        };
+        outln!("{} {}: {}", icon, check.name, check.detail);
+        if let Some(fix) = &check.fix {
+            outln!("   💡 {}", fix);
+        }
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Failed)
+        .count();
+    outln!();
+    if failed == 0 {
+        outln!("✅ Ready to investigate");
+        process::exit(EXIT_MATCHED);
+    }
+    outln!("❌ {} problem(s) would make a run fail", failed);
+    process::exit(EXIT_ENVIRONMENT);
+}
+
+/// Returns how the matcher's CLI is run, as given on the command line
+fn matcher_command(cli: &Cli) -> MatcherCommand {
+    MatcherCommand {
+        args: cli.matcher_args.clone(),
+        working_dir: cli.matcher_dir.clone(),
+        env: cli.matcher_env.clone(),
+    }
+}
+
+/// Checks the matcher's CLI and exits if it can't be run
+///
+/// Runs before the Whisper model is loaded, so a missing or broken CLI is
+/// reported right away instead of after transcribing the first videos.
+/// Missing credentials are only warned about, as the CLI may sign in in a
+/// way the check doesn't know.
+fn check_matcher_or_exit(cli: &Cli) {
+    let checks = check_matcher(cli.matcher.into(), &matcher_command(cli));
+    let mut failed = false;
+    for check in &checks {
+        let (name, detail) = (&check.name, &check.detail);
+        match check.status {
+            CheckStatus::Ok => continue,
+            CheckStatus::Warning => {
+                eprintln!(
+                    "{}",
+                    tr!("error.matcher_warning", name = name, detail = detail)
+                );
+            }
+            CheckStatus::Failed => {
+                failed = true;
+                eprintln!(
+                    "{}",
+                    tr!("error.matcher_unavailable", name = name, detail = detail)
+                );
+            }
+        }
+        if let Some(fix) = &check.fix {
+            eprintln!("{}", tr!("error.tip_matcher", fix = fix));
+        }
+    }
+    if failed {
+        process::exit(EXIT_ENVIRONMENT);
+    }
+}
+
+/// Estimates the remaining work of an investigation and displays it
+fn display_estimate(
+    video_dir: &std::path::Path,
+    show_name: &str,
+    metadata: &MetadataOptions,
+    infer_season: bool,
+    matcher_type: MatcherType,
+    cache_namespace: Option<&str>,
+    select_series: impl FnOnce(&[SeriesCandidate]) -> Result<usize, DialogDetectiveError>,
+) {
+    let has_season_filter = metadata.season_filter.is_some();
+    let estimate = match estimate_case(
+        video_dir,
+        show_name,
+        metadata,
+        infer_season,
+        matcher_type,
+        cache_namespace,
+        handle_progress_event,
+        select_series,
+    ) {
+        Ok(estimate) => estimate,
+        Err(e) => {
+            eprintln!("\n❌ Estimate failed: {}", e);
+            process::exit(EXIT_ENVIRONMENT);
+        }
+    };
+
+    outln!();
+    outln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    outln!("📊 Estimate");
+    outln!(
+        "   ├─ Videos: {} ({} transcripts cached, {} matches cached)",
+        estimate.video_count,
+        estimate.video_count - estimate.transcriptions_needed,
+        estimate.video_count - estimate.matcher_calls_needed
+    );
+    outln!(
+        "   ├─ Transcriptions needed: {}",
+        estimate.transcriptions_needed
+    );
+    outln!(
+        "   ├─ Matcher calls needed: {}",
+        estimate.matcher_calls_needed
+    );
+    match estimate.usage.cost_usd {
+        Some(cost) => outln!(
+            "   ├─ Tokens: ~{}, est. ${:.2}",
+            format_tokens(estimate.usage.total_tokens()),
+            cost
+        ),
+        None => outln!(
+            "   ├─ Tokens: ~{}",
+            format_tokens(estimate.usage.total_tokens())
+        ),
+    }
+    outln!("   └─ Duration: ~{}", format_duration(estimate.duration));
+    outln!();
+
+    if !has_season_filter && estimate.matcher_calls_needed > 0 {
+        outln!("💡 Use --season to narrow the episode list and reduce token usage");
+    }
+}
+
+/// Fetches a show's metadata and a Whisper model into the caches and exits
+fn prefetch_and_exit(
+    target: &Target,
+    options: &MetadataOptions,
+    model: &ModelSpec,
+    non_interactive: bool,
+) -> ! {
+    outln!("📺 Fetching metadata for {}...", target.show_name);
+    let series = match prefetch_show_metadata(
+        &target.show_name,
+        options,
+        series_selector(target, non_interactive),
+    ) {
+        Ok(series) => series,
+        Err(e) => {
+            eprintln!("❌ Prefetch failed: {}", e);
+            process::exit(EXIT_ENVIRONMENT);
+        }
+    };
+    let episode_count: usize = series.seasons.iter().map(|s| s.episodes.len()).sum();
+    outln!(
+        "✅ Cached {} episode(s) in {} season(s) of {}",
+        episode_count,
+        series.seasons.len(),
+        series.name
+    );
+
+    let model_path = resolve_additional_model(model, false);
+    outln!("✅ Whisper model ready: {}", model_path.display());
+    process::exit(EXIT_MATCHED);
+}
+
+/// Presents an interactive series selection prompt using `dialoguer::Select`.
+///
+/// Builds display labels with year disambiguation: if two candidates share
+/// the same name, both get a "(year)" suffix to tell them apart.
+fn select_series_interactive(
+    candidates: &[SeriesCandidate],
+) -> Result<usize, DialogDetectiveError> {
+    // Count how many times each name appears so we know when to show the year
+    let mut name_counts: HashMap<&str, usize> = HashMap::new();
+    for candidate in candidates {
+        *name_counts.entry(&candidate.name).or_default() += 1;
+    }
+
+    // Build display labels with year disambiguation
+    let display_items: Vec<String> = candidates
+        .iter()
+        .map(|c| {
+            if name_counts.get(c.name.as_str()).copied().unwrap_or(0) > 1 {
+                match c.year {
+                    Some(year) => format!("{} ({})", c.name, year),
+                    None => tr!("select.unknown_year", name = c.name),
+                }
+            } else {
+                c.name.clone()
+            }
+        })
+        .collect();
+
+    outln!();
+
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt(tr!("select.prompt"))
+        .items(&display_items)
+        .default(0)
+        .interact_opt()
+        .map_err(|e| DialogDetectiveError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+
+    match selection {
+        Some(index) => Ok(index),
+        None => Err(DialogDetectiveError::SelectionCancelled),
+    }
+}
+
+/// Asks whether to go on with more videos than `--max-files`
+///
+/// Failing to ask counts as a no.
+fn confirm_many_files(count: usize) -> bool {
+    outln!();
+    dialoguer::Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt(tr!("confirm.many_files", count = count))
+        .default(false)
+        .interact()
+        .unwrap_or(false)
+}
+
+/// A directory to investigate, with the show its videos belong to
+struct Target {
+    video_dir: PathBuf,
+    show_name: String,
+
+    /// Series chosen while detecting the show
+    series_id: Option<u64>,
+}
+
+/// Detects the show of a directory without a show name, or exits
+///
+/// Only asks which series is meant if several are known by the detected
+/// name. Without a terminal, that ambiguity is an error instead.
+fn detect_show_or_exit(video_dir: PathBuf, non_interactive: bool) -> Target {
+    outln!("{}", tr!("detect.detecting"));
+    let detection = match detect_show(&video_dir) {
+        Ok(detection) => detection,
+        Err(e) => {
+            eprintln!("{}", tr!("detect.failed", error = e));
+            process::exit(EXIT_ENVIRONMENT);
+        }
+    };
+
+    let candidates = &detection.candidates;
+    let index = match candidates.len() {
+        0 => {
+            let guesses: Vec<String> = detection.guesses.iter().map(|g| g.to_string()).collect();
+            if guesses.is_empty() {
+                eprintln!("{}", tr!("detect.no_guesses"));
+            } else {
+                eprintln!(
+                    "{}",
+                    tr!("detect.unknown_guesses", guesses = guesses.join(", "))
+                );
+            }
+            eprintln!("{}", tr!("detect.pass_show_name"));
+            process::exit(EXIT_USAGE);
+        }
+        1 => 0,
+        _ if non_interactive => {
+            let names: Vec<String> = candidates.iter().map(display_candidate).collect();
+            eprintln!("{}", tr!("detect.ambiguous", names = names.join(", ")));
+            eprintln!("{}", tr!("detect.pass_show_name"));
+            process::exit(EXIT_USAGE);
+        }
+        _ => match select_series_interactive(candidates) {
+            Ok(index) => index,
+            Err(e) => {
+                eprintln!("{}", tr!("error.generic", error = e));
+                process::exit(EXIT_USAGE);
+            }
+        },
+    };
+
+    let candidate = &candidates[index];
+    outln!(
+        "{}",
+        tr!("detect.detected", show = display_candidate(candidate))
+    );
+    outln!();
+    Target {
+        video_dir,
+        show_name: candidate.name.clone(),
+        series_id: Some(candidate.id),
+    }
+}
+
+/// Formats a series candidate with its premiere year, if known
+fn display_candidate(candidate: &SeriesCandidate) -> String {
+    match candidate.year {
+        Some(year) => format!("{} ({})", candidate.name, year),
+        None => candidate.name.clone(),
+    }
+}
+
+/// Parses a `DIR=SHOW` mapping of `--map`
+fn parse_mapping(value: &str) -> Result<(PathBuf, String), String> {
+    match value.rsplit_once('=') {
+        Some((dir, show)) if !dir.is_empty() && !show.trim().is_empty() => {
+            Ok((PathBuf::from(dir), show.trim().to_string()))
+        }
+        _ => Err("expected DIR=SHOW, e.g. \"/downloads/bb=Breaking Bad\"".to_string()),
+    }
+}
+
+/// Parses a `KEY=VALUE` environment variable of `--matcher-env`
+fn parse_env_var(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err("expected KEY=VALUE, e.g. GEMINI_SANDBOX=docker".to_string()),
+    }
+}
+
+/// Parses a byte size of `--hash-buffer-size`, like `524288`, `512K`, or `4M`
+fn parse_size(value: &str) -> Result<usize, String> {
+    let value = value.trim();
+    let (digits, factor) = match value.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&value[..i], 1024),
+        Some((i, 'm' | 'M')) => (&value[..i], 1024 * 1024),
+        Some((i, 'g' | 'G')) => (&value[..i], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+        .filter(|&n| n > 0)
+        .ok_or_else(|| "expected a size in bytes, e.g. 524288, 512K, or 4M".to_string())
+}
+
+/// Parses a duration like `90d`, `12w`, `36h`, `10m`, or `30s`
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (digits, unit) = match value.char_indices().last() {
+        Some((i, 's')) => (&value[..i], 1),
+        Some((i, 'm')) => (&value[..i], 60),
+        Some((i, 'h')) => (&value[..i], 60 * 60),
+        Some((i, 'd')) => (&value[..i], 24 * 60 * 60),
+        Some((i, 'w')) => (&value[..i], 7 * 24 * 60 * 60),
+        _ => ("", 0),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .map(Duration::from_secs)
+        .ok_or_else(|| "expected a duration with a unit, e.g. 30s, 10m, 36h, or 90d".to_string())
+}
+
+/// Parses the namespace of `--cache-namespace`
+fn parse_cache_namespace(value: &str) -> Result<CacheNamespace, String> {
+    match value.trim() {
+        "" => Err("expected a name or auto".to_string()),
+        "auto" => Ok(CacheNamespace::Auto),
+        name => Ok(CacheNamespace::Named(name.to_string())),
+    }
+}
+
+/// Returns the cache namespace of a target, if one was requested
+fn cache_namespace(cli: &Cli, target: &Target) -> Option<String> {
+    match cli.cache_namespace.as_ref()? {
+        CacheNamespace::Named(name) => Some(name.clone()),
+        CacheNamespace::Auto => Some(cache_namespace_for(
+            cli.output_dir.as_deref().unwrap_or(&target.video_dir),
+        )),
+    }
+}
+
+/// Parses the provider of `--metadata-provider`
+fn parse_metadata_provider(value: &str) -> Result<Provider, String> {
+    match value.to_lowercase().as_str() {
+        "tvmaze" => Ok(Provider::Tvmaze),
+        "trakt" => Ok(Provider::Trakt),
+        "wikipedia" => Ok(Provider::Wikipedia),
+        _ if value.trim().is_empty() => {
+            Err("expected tvmaze, trakt, wikipedia, or a provider plugin".to_string())
+        }
+        _ => Ok(Provider::Plugin(value.to_string())),
+    }
+}
+
+/// Resolves the metadata provider, loading a plugin from the plugins file
+fn resolve_metadata_provider(provider: &Provider) -> Result<MetadataProviderType, PluginError> {
+    Ok(match provider {
+        Provider::Tvmaze => MetadataProviderType::TvMaze,
+        Provider::Trakt => MetadataProviderType::Trakt,
+        Provider::Wikipedia => MetadataProviderType::Wikipedia,
+        Provider::Plugin(name) => {
+            MetadataProviderType::Plugin(load_provider_plugin(&default_plugins_path()?, name)?)
+        }
+    })
+}
+
+/// Parses the language strategy of `--metadata-language`
+fn parse_language_strategy(value: &str) -> Result<LanguageStrategy, String> {
+    match value.to_lowercase().as_str() {
+        "original" => Ok(LanguageStrategy::Original),
+        "detected" => Ok(LanguageStrategy::Detected),
+        code => parse_language_code(code)
+            .map(LanguageStrategy::Fixed)
+            .map_err(|_| "expected original, detected, or a language code like de".to_string()),
+    }
+}
+
+/// Parses a two-letter ISO 639-1 language code like `de`
+fn parse_language_code(value: &str) -> Result<String, String> {
+    if value.len() == 2 && value.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(value.to_lowercase())
+    } else {
+        Err("expected a two-letter language code like de".to_string())
+    }
+}
+
+/// A step of the escalation ladder, given with `--escalate`
+#[derive(Debug, Clone)]
+struct EscalationStep {
+    model: ModelSpec,
+    sample_length: Option<Duration>,
+}
+
+/// Parses an escalation step like `large-v3-turbo@15m` or `medium`
+///
+/// Anything after the last `@` that isn't a duration is part of the model,
+/// so model paths may contain `@`.
+fn parse_escalation_step(value: &str) -> Result<EscalationStep, String> {
+    let (model, sample_length) = match value.rsplit_once('@') {
+        Some((model, sample)) => match parse_duration(sample) {
+            Ok(length) => (model, Some(length)),
+            Err(_) => (value, None),
+        },
+        None => (value, None),
+    };
+    let Ok(model) = model.parse();
+    Ok(EscalationStep {
+        model,
+        sample_length,
+    })
+}
+
+/// Applies the stage timeouts of the command line to a transcriber
+fn with_timeouts(mut transcriber: WhisperTranscriber, cli: &Cli) -> WhisperTranscriber {
+    if let Some(timeout) = cli.extraction_timeout {
+        transcriber = transcriber.extraction_timeout(timeout);
+    }
+    if let Some(timeout) = cli.transcription_timeout {
+        transcriber = transcriber.transcription_timeout(timeout);
+    }
+    transcriber
+}
+
+/// Resolves a model used besides the main one, exiting if that fails
+///
+/// Downloads the model unless running offline, like the main model.
+fn resolve_additional_model(model: &ModelSpec, offline: bool) -> PathBuf {
+    let resolved = if offline {
+        model.resolve_cached()
+    } else {
+        model.resolve_with(|progress| {
+            if verbosity() > Verbosity::Quiet {
+                model_downloader::print_download_progress(progress);
+            }
+        })
+    };
+    resolved.unwrap_or_else(|e| {
+        eprintln!("{}", tr!("error.model_download", model = model, error = e));
+        process::exit(EXIT_ENVIRONMENT);
+    })
+}
+
+/// Picks a series without asking
+///
+/// Prefers the only candidate named exactly like the show (ignoring case),
+/// and otherwise takes the first, most relevant one.
+fn select_series_non_interactive(show_name: &str, candidates: &[SeriesCandidate]) -> usize {
+    let exact: Vec<usize> = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.name.eq_ignore_ascii_case(show_name.trim()))
+        .map(|(i, _)| i)
+        .collect();
+    match exact.as_slice() {
+        [index] => *index,
+        _ => 0,
+    }
+}
+
+/// Returns the series selection to use, prompting only if interactive
+///
+/// A series already chosen during show detection is selected again
+/// without asking.
+fn series_selector(
+    target: &Target,
+    non_interactive: bool,
+) -> impl FnOnce(&[SeriesCandidate]) -> Result<usize, DialogDetectiveError> + '_ {
+    let show_name = target.show_name.as_str();
+    move |candidates| {
+        let detected = candidates
+            .iter()
+            .position(|c| Some(c.id) == target.series_id);
+        if let Some(index) = detected {
+            Ok(index)
+        } else if non_interactive {
+            let index = select_series_non_interactive(show_name, candidates);
+            let series = display_candidate(&candidates[index]);
+            outln!("{}", tr!("select.using", series = series));
+            Ok(index)
+        } else {
+            select_series_interactive(candidates)
+        }
+    }
+}
+
+fn main() {
+    // Clap exits with 2 on usage errors, which is reserved for partial results
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        process::exit(if e.use_stderr() { EXIT_USAGE } else { 0 });
+    });
+    VERBOSITY
+        .set(Verbosity::from_flags(cli.quiet, cli.verbose))
+        .expect("verbosity is set once");
+    LANGUAGE
+        .set(cli.lang.map_or_else(Language::detect, Language::from))
+        .expect("language is set once");
+
+    // FFmpeg and the AI CLIs shouldn't outlive an interrupted run
+    #[cfg(unix)]
+    dialog_detective::kill_child_processes_on_exit();
+
+    let non_interactive = cli.non_interactive || !io::stdin().is_terminal();
+
+    // Handle subcommands
+    if let Some(Command::Format {
+        action: FormatAction::Check { format },
+    }) = &cli.command
+    {
+        check_format_and_exit(format);
+    }
+    if let Some(Command::Hook {
+        profile,
+        config,
+        queue,
+        urgent,
+        path,
+    }) = &cli.command
+    {
+        process::exit(if *queue {
+            queue_download(profile, config.as_deref(), path, *urgent)
+        } else {
+            run_hook(
+                profile,
+                config.as_deref(),
+                path,
+                &LoadedModels::default(),
+                None,
+            )
+        });
+    }
+    if let Some(Command::Queue { action }) = &cli.command {
+        run_queue_action_and_exit(action);
+    }
+    if let Some(Command::Trakt { action }) = &cli.command {
+        run_trakt_action_and_exit(action);
+    }
+    if let Some(Command::Cache {
+        action: CacheAction::Purge { show, file },
+    }) = &cli.command
+    {
+        purge_cache_and_exit(show.as_deref(), file.as_deref());
+    }
+    if let Some(Command::Models {
+        action: ModelsAction::Prune {
+            unused_for,
+            dry_run,
+        },
+    }) = &cli.command
+    {
+        prune_models_and_exit(*unused_for, *dry_run);
+    }
+
+    if let Some(Command::Doctor {
+        matcher,
+        model,
+        model_path,
+    }) = &cli.command
+    {
+        run_doctor_and_exit((*matcher).into(), model, model_path.as_deref());
+    }
+
+    if let Some(Command::Audit {
+        library,
+        show,
+        seasons,
+        json,
+    }) = &cli.command
+    {
+        let target = match show {
+            Some(show) => Target {
+                video_dir: library.clone(),
+                show_name: show.clone(),
+                series_id: None,
+            },
+            None => detect_show_or_exit(library.clone(), non_interactive),
+        };
+        audit_library_and_exit(&target, seasons, *json);
+    }
+
+    if let Some(Command::Prefetch {
+        show,
+        seasons,
+        model,
+        metadata_language,
+        title_language,
+        episode_order,
+        metadata_provider,
+    }) = &cli.command
+    {
+        let provider = match resolve_metadata_provider(metadata_provider) {
+            Ok(provider) => provider,
+            Err(e) => {
+                eprintln!("❌ Error: {}", e);
+                process::exit(EXIT_ENVIRONMENT);
+            }
+        };
+        let options = MetadataOptions {
+            season_filter: (!seasons.is_empty()).then(|| seasons.clone()),
+            provider,
+            language: metadata_language.clone(),
+            title_language: title_language.clone(),
+            episode_order: (*episode_order).into(),
+            ..Default::default()
+        };
+        let target = Target {
+            video_dir: PathBuf::new(),
+            show_name: show.clone(),
+            series_id: None,
+        };
+        prefetch_and_exit(
+            &target,
+            &options,
+            &model.clone().unwrap_or_default(),
+            non_interactive,
+        );
+    }
+
+    if let Some(Command::History {
+        show,
+        file,
+        hash,
+        run,
+        limit,
+        json,
+    }) = &cli.command
+    {
+        let query = HistoryQuery {
+            show: show.clone(),
+            path: file.clone(),
+            video_hash: hash.clone(),
+            run_id: run.clone(),
+            limit: *limit,
+        };
+        show_history_and_exit(&query, *json);
+    }
+
+    // Handle --list-models flag
+    if cli.list_models {
+        display_model_list_and_exit();
+    }
+
+    // Unwrap required arguments (safe because of required_unless_present_any)
+    let dirs: Vec<PathBuf> = if cli.map.is_empty() {
+        vec![cli.video_dir.clone().expect("video_dir should be present")]
+    } else {
+        cli.map.iter().map(|(dir, _)| dir.clone()).collect()
+    };
+
+    // Validate arguments
+    for video_dir in &dirs {
+        if !video_dir.exists() {
+            eprintln!(
+                "{}",
+                tr!("error.directory_missing", path = video_dir.display())
+            );
+            process::exit(EXIT_USAGE);
+        }
+
+        if !video_dir.is_dir() {
+            eprintln!(
+                "{}",
+                tr!("error.not_a_directory", path = video_dir.display())
+            );
+            process::exit(EXIT_USAGE);
+        }
+    }
+
+    let targets: Vec<Target> = if cli.map.is_empty() {
+        let video_dir = dirs
+            .into_iter()
+            .next()
+            .expect("video_dir should be present");
+        match &cli.show_name {
+            Some(show_name) => vec![Target {
+                video_dir,
+                show_name: show_name.clone(),
+                series_id: None,
+            }],
+            None => vec![detect_show_or_exit(video_dir, non_interactive)],
+        }
+    } else {
+        cli.map
+            .iter()
+            .map(|(video_dir, show_name)| Target {
+                video_dir: video_dir.clone(),
+                show_name: show_name.clone(),
+                series_id: None,
+            })
+            .collect()
+    };
+
+    // Reject broken formats before any expensive work
+    if let Err(e) = validate_format(&cli.format) {
+        eprintln!("{}", tr!("error.invalid_format", error = e));
+        eprintln!("{}", tr!("error.try_format_check", format = cli.format));
+        process::exit(EXIT_USAGE);
+    }
+
+    // Convert seasons filter
+    let season_filter = if cli.seasons.is_empty() {
         None
     } else {
         Some(cli.seasons.clone())
     };
 
-    // Run the investigation with progress callback
-    match investigate_case(
-        &video_dir,
-        &model_path,
-        &show_name,
+    let metadata_provider = match resolve_metadata_provider(&cli.metadata_provider) {
+        Ok(provider) => provider,
+        Err(e) => {
+            eprintln!("❌ Error: {}", e);
+            process::exit(EXIT_ENVIRONMENT);
+        }
+    };
+
+    // Handle --estimate flag (needs no Whisper model)
+    if cli.estimate {
+        let metadata = MetadataOptions {
+            season_filter: season_filter.clone(),
+            provider: metadata_provider.clone(),
+            // A detected language isn't known without transcribing
+            language: match &cli.metadata_language {
+                LanguageStrategy::Fixed(language) => Some(language.clone()),
+                LanguageStrategy::Original | LanguageStrategy::Detected => None,
+            },
+            title_language: cli.title_language.clone(),
+            episode_order: cli.episode_order.into(),
+            ..Default::default()
+        };
+        for target in &targets {
+            display_estimate(
+                &target.video_dir,
+                &target.show_name,
+                &metadata,
+                cli.infer_season,
+                cli.matcher.into(),
+                cache_namespace(&cli, target).as_deref(),
+                series_selector(target, non_interactive),
+            );
+        }
+        process::exit(0);
+    }
+
+    // Check the matcher before spending time on the model and transcription
+    check_matcher_or_exit(&cli);
+
+    // Resolve model path: custom path, selected model, or default 'base'
+    let model = match (cli.model_path.clone(), cli.model.clone()) {
+        (Some(custom_path), _) => ModelSpec::Path(custom_path),
+        (None, Some(model)) => model,
+        (None, None) => ModelSpec::Named(model_downloader::DEFAULT_MODEL.to_string()),
+    };
+    match &model {
+        ModelSpec::Path(custom_path) => {
+            // Custom model path provided - validate it exists
+            if !custom_path.exists() {
+                eprintln!(
+                    "{}",
+                    tr!("error.model_missing", path = custom_path.display())
+                );
+                process::exit(EXIT_USAGE);
+            }
+
+            if !custom_path.is_file() {
+                eprintln!(
+                    "{}",
+                    tr!("error.model_not_a_file", path = custom_path.display())
+                );
+                process::exit(EXIT_USAGE);
+            }
+        }
+        ModelSpec::Named(model_name) => {
+            // Validate model name against supported list
+            let supported = model_downloader::supported_models();
+            if !supported.contains(&model_name.as_str()) {
+                eprintln!("{}", tr!("error.unsupported_model", model = model_name));
+                eprintln!();
+                eprintln!("{}", tr!("error.supported_models"));
+                for (i, model) in supported.iter().enumerate() {
+                    eprint!("  {}", model);
+                    if (i + 1) % 4 == 0 {
+                        eprintln!();
+                    } else {
+                        eprint!("  ");
+                    }
+                }
+                if supported.len() % 4 != 0 {
+                    eprintln!();
+                }
+                eprintln!();
+                eprintln!("{}", tr!("error.tip_list_models"));
+                process::exit(EXIT_USAGE);
+            }
+        }
+        ModelSpec::Auto => {}
+    }
+
+    // Download model if needed
+    let resolved = if cli.offline {
+        model.resolve_cached()
+    } else {
+        model.resolve_with(|progress| {
+            if verbosity() > Verbosity::Quiet {
+                model_downloader::print_download_progress(progress);
+            }
+        })
+    };
+    let model_path = match resolved {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("{}", tr!("error.model_download", model = model, error = e));
+            if cli.offline {
+                eprintln!("{}", tr!("error.tip_offline"));
+            } else {
+                eprintln!("{}", tr!("error.tip_model_path"));
+            }
+            process::exit(EXIT_ENVIRONMENT);
+        }
+    };
+
+    if !(0.0..=1.0).contains(&cli.min_transcript_quality) {
+        eprintln!("{}", tr!("error.min_transcript_quality"));
+        process::exit(EXIT_USAGE);
+    }
+
+    // Transcribers of the same model share it instead of loading it again
+    let transcriber = with_timeouts(WhisperTranscriber::new(&model_path), &cli);
+    let transcriber_for = |model: &ModelSpec| {
+        let path = resolve_additional_model(model, cli.offline);
+        if path == model_path {
+            transcriber.clone()
+        } else {
+            with_timeouts(WhisperTranscriber::new(path), &cli)
+        }
+    };
+    let fallback = cli.fallback_model.as_ref().map(transcriber_for);
+    let escalation: Vec<WhisperTranscriber> = cli
+        .escalate
+        .iter()
+        .map(|step| {
+            let transcriber = transcriber_for(&step.model);
+            match step.sample_length {
+                Some(length) => transcriber.sample_length(length),
+                None => transcriber,
+            }
+        })
+        .collect();
+    let transcriber = match cli.sample {
+        Some(length) => transcriber.sample_length(length),
+        None => transcriber,
+    };
+
+    // Validate mode-specific requirements
+    if matches!(cli.mode, Mode::Copy) && cli.output_dir.is_none() {
+        eprintln!("{}", tr!("error.output_dir_required"));
+        process::exit(EXIT_USAGE);
+    }
+    let sonarr = match (&cli.mode, &cli.sonarr_url) {
+        (Mode::Sonarr, None) => {
+            eprintln!("{}", tr!("error.sonarr_url_required"));
+            process::exit(EXIT_USAGE);
+        }
+        (Mode::Sonarr, Some(url)) => {
+            let client = match &cli.sonarr_api_key {
+                Some(key) => Some(SonarrClient::new(url, key.clone())),
+                None => SonarrClient::from_env(url),
+            };
+            if client.is_none() {
+                eprintln!("{}", tr!("error.sonarr_key_required"));
+                process::exit(EXIT_USAGE);
+            }
+            client
+        }
+        _ => None,
+    };
+    let trakt = if cli.trakt_collect {
+        let Some(client) = TraktClient::from_env() else {
+            eprintln!("{}", tr!("error.trakt_credentials_required"));
+            process::exit(EXIT_USAGE);
+        };
+        if !client.is_signed_in() {
+            eprintln!("{}", tr!("error.trakt_login_required"));
+            process::exit(EXIT_USAGE);
+        }
+        Some(client)
+    } else {
+        None
+    };
+
+    let thresholds = TriageThresholds {
+        auto_apply: cli.auto_apply_threshold,
+        review: cli.review_threshold,
+    };
+    if !(0.0..=1.0).contains(&thresholds.auto_apply)
+        || !(0.0..=1.0).contains(&thresholds.review)
+        || thresholds.review > thresholds.auto_apply
+    {
+        eprintln!("{}", tr!("error.thresholds"));
+        process::exit(EXIT_USAGE);
+    }
+
+    if let Some(dump_dir) = &cli.dump_prompts
+        && let Err(e) = fs::create_dir_all(dump_dir)
+    {
+        eprintln!(
+            "{}",
+            tr!(
+                "error.prompt_directory",
+                path = dump_dir.display(),
+                error = e
+            )
+        );
+        process::exit(EXIT_ENVIRONMENT);
+    }
+
+    let mut runner = CaseRunner {
+        cli: &cli,
+        transcriber,
+        fallback,
+        escalation,
         season_filter,
-        cli.matcher.into(),
-        handle_progress_event,
-        select_series_interactive,
+        thresholds,
+        sonarr,
+        trakt,
+        metadata_provider,
+        non_interactive,
+        metrics: RunMetrics::new(),
+        metrics_failed: false,
+        metrics_written: None,
+        history: if cli.no_history { None } else { open_history() },
+        history_failed: false,
+        review: Vec::new(),
+    };
+    let mut exit_codes = Vec::new();
+    for target in &targets {
+        if targets.len() > 1 {
+            outln!("📂 {} → {}", target.video_dir.display(), target.show_name);
+            outln!();
+        }
+        exit_codes.push(runner.solve(target));
+    }
+    print_run_summary(&runner.metrics.summary());
+
+    if let Some(review_file) = &cli.review_file {
+        if let Err(e) = write_review_file(review_file, &runner.review) {
+            eprintln!("{}", tr!("error.review_file", error = e));
+            process::exit(EXIT_ENVIRONMENT);
+        }
+        outln!(
+            "{}",
+            tr!(
+                "result.review_written",
+                count = runner.review.len(),
+                path = review_file.display(),
+            )
+        );
+        outln!();
+    }
+
+    process::exit(combined_exit_code(&exit_codes));
+}
+
+/// Combines the exit codes of several directories or queued jobs into one
+///
+/// Failures, like an environment that failed, win, the highest first.
+/// Otherwise the code stays matched if all were matched and unmatched if
+/// none were, while any mix is partial. No codes at all count as matched.
+fn combined_exit_code(codes: &[i32]) -> i32 {
+    let outcomes = [EXIT_MATCHED, EXIT_PARTIAL, EXIT_UNMATCHED];
+    let failure = codes
+        .iter()
+        .copied()
+        .filter(|code| !outcomes.contains(code))
+        .max();
+    if let Some(failure) = failure {
+        return failure;
+    }
+    match codes {
+        [] => EXIT_MATCHED,
+        [first, rest @ ..] if rest.iter().all(|code| code == first) => *first,
+        _ => EXIT_PARTIAL,
+    }
+}
+
+/// Shortest time between two writes of the metrics file during a run
+const METRICS_WRITE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Investigates directories one after another, sharing the Whisper model
+///
+/// Settings come from the command line; the metrics, the history, and the
+/// matches needing review are shared by all directories.
+struct CaseRunner<'a> {
+    cli: &'a Cli,
+    transcriber: WhisperTranscriber,
+    fallback: Option<WhisperTranscriber>,
+    escalation: Vec<WhisperTranscriber>,
+    season_filter: Option<Vec<usize>>,
+    thresholds: TriageThresholds,
+    sonarr: Option<SonarrClient>,
+    trakt: Option<TraktClient>,
+    metadata_provider: MetadataProviderType,
+    non_interactive: bool,
+    metrics: RunMetrics,
+    metrics_failed: bool,
+    /// When the metrics file was last written while investigating
+    metrics_written: Option<Instant>,
+    history: Option<History>,
+    history_failed: bool,
+    review: Vec<MatchResult>,
+}
+
+impl CaseRunner<'_> {
+    /// Records a file in the history
+    fn remember(
+        &mut self,
+        show_name: &str,
+        result: &MatchResult,
+        action: HistoryAction,
+        destination: Option<&Path>,
+    ) {
+        record_history(
+            self.history.as_ref(),
+            &mut self.history_failed,
+            show_name,
+            result,
+            action,
+            destination,
+        );
+    }
+
+    /// Records a renamed or copied file in the history, or that it failed
+    fn remember_outcome(
+        &mut self,
+        show_name: &str,
+        result: &MatchResult,
+        action: HistoryAction,
+        op: &PlannedOperation,
+        done: bool,
     ) {
-        Ok(matches) => {
-            if matches.is_empty() {
-                println!("❌ Case closed: No matches found");
-                return;
+        if done {
+            self.remember(show_name, result, action, Some(&op.destination));
+        } else {
+            self.remember(show_name, result, HistoryAction::Failed, None);
+        }
+    }
+
+    /// Investigates a directory and applies the confident matches
+    ///
+    /// Returns the exit code of the directory.
+    fn solve(&mut self, target: &Target) -> i32 {
+        let video_dir = target.video_dir.as_path();
+        let show_name = target.show_name.as_str();
+
+        // Run the investigation with progress callback
+        let mut investigation = Investigation::new(video_dir, show_name, self.transcriber.clone())
+            .season_filter(self.season_filter.clone())
+            .matcher(self.cli.matcher.into())
+            .matcher_command(matcher_command(self.cli))
+            .max_episodes_per_prompt(self.cli.max_episodes_per_prompt)
+            .parallel_matches(self.cli.parallel_matches)
+            .infer_season(self.cli.infer_season)
+            .language_strategy(self.cli.metadata_language.clone())
+            .episode_order(self.cli.episode_order.into())
+            .metadata_provider(self.metadata_provider.clone())
+            .include_transcripts(self.cli.subtitles.is_some())
+            .transcript_reuse(self.cli.reuse_transcripts.into())
+            .cache_namespace(cache_namespace(self.cli, target))
+            .capture_prompts(self.cli.dump_prompts.is_some())
+            .io_retry(RetryPolicy::with_retries(self.cli.io_retries))
+            .hash_options(HashOptions {
+                buffer_size: self.cli.hash_buffer_size,
+                mmap: self.cli.hash_mmap,
+            })
+            .min_transcript_quality(self.cli.min_transcript_quality)
+            .escalation_confidence(self.cli.auto_apply_threshold);
+        if let Some(fallback) = &self.fallback {
+            investigation = investigation.quality_fallback(fallback.clone());
+        }
+        for step in &self.escalation {
+            investigation = investigation.escalate_to(step.clone());
+        }
+        if let Some(language) = &self.cli.title_language {
+            investigation = investigation.title_language(language.clone());
+        }
+        if let Some(timeout) = self.cli.matching_timeout {
+            investigation = investigation.matching_timeout(timeout);
+        }
+        if self.cli.max_files > 0 {
+            let non_interactive = self.non_interactive;
+            investigation = investigation.max_files(self.cli.max_files, move |count| {
+                !non_interactive && confirm_many_files(count)
+            });
+        }
+
+        // The provider's series name and premiere year are only known once the metadata was fetched
+        let mut series_name = None;
+        let mut show_year = None;
+        let progress = |event: ProgressEvent| {
+            self.metrics.record(&event);
+            // Progress events come in bursts, so the file is rewritten at
+            // most once per interval, and once more after the run
+            if let Some(path) = &self.cli.metrics_file
+                && self
+                    .metrics_written
+                    .is_none_or(|written| written.elapsed() >= METRICS_WRITE_INTERVAL)
+            {
+                write_metrics(&self.metrics, path, &mut self.metrics_failed);
+                self.metrics_written = Some(Instant::now());
+            }
+            if let ProgressEvent::MetadataFetched {
+                series_name: name,
+                year,
+                ..
+            } = &event
+            {
+                series_name = Some(name.clone());
+                show_year = *year;
             }
+            if let (Some(dump_dir), ProgressEvent::PromptExchanged { .. }) =
+                (&self.cli.dump_prompts, &event)
+                && let Err(e) = dump_prompt(dump_dir, &event)
+            {
+                eprintln!(
+                    "⚠️  Failed to write prompt to {}: {}",
+                    dump_dir.display(),
+                    e
+                );
+            }
+            handle_progress_event(event);
+        };
 
-            // Plan file operations
-            let output_dir = cli.output_dir.as_deref();
-            let operations = match plan_operations(&matches, &show_name, &cli.format, output_dir) {
-                Ok(ops) => ops,
-                Err(e) => {
-                    eprintln!("\n❌ Failed to plan operations: {}", e);
-                    process::exit(1);
+        // Videos that fail, e.g. by timing out, are reported and left out
+        let result = investigation
+            .run_outcomes(progress, series_selector(target, self.non_interactive))
+            .map(split_outcomes);
+        match &result {
+            Ok((_, failed)) => {
+                for (_, error) in failed {
+                    self.metrics.record_failed_video(error);
+                }
+            }
+            Err(e) => self.metrics.record_failure(e),
+        }
+        if let Some(path) = &self.cli.metrics_file {
+            write_metrics(&self.metrics, path, &mut self.metrics_failed);
+        }
+
+        match result {
+            Ok((matches, failed)) => {
+                print_failed_videos(&failed);
+                if matches.is_empty() {
+                    outln!("{}", tr!("result.no_matches"));
+                    return EXIT_UNMATCHED;
+                }
+
+                print_timing_summary(&matches);
+                print_usage_summary(&matches);
+                self.metrics.record_matches(&matches);
+
+                // Give every file a unique episode if requested
+                let matches = if self.cli.resolve_duplicates {
+                    resolve_duplicates(matches)
+                } else {
+                    matches
+                };
+
+                // Only confident matches are applied, the rest is set aside
+                let triage = triage_matches(matches, self.thresholds);
+                print_triage(&triage);
+                let complete =
+                    failed.is_empty() && triage.review.is_empty() && triage.unmatched.is_empty();
+
+                for result in &triage.review {
+                    self.remember(show_name, result, HistoryAction::Review, None);
+                }
+                for result in &triage.unmatched {
+                    self.remember(show_name, result, HistoryAction::Unmatched, None);
+                }
+                self.review.extend(triage.review);
+
+                let matches = triage.auto_apply;
+                if matches.is_empty() {
+                    outln!("{}", tr!("result.none_confident"));
+                    return EXIT_UNMATCHED;
+                }
+
+                // Keep one file per episode unless duplicates are numbered
+                let policy = DuplicatePolicy::from(self.cli.duplicate_policy);
+                let resolution = apply_duplicate_policy(matches, policy);
+                let matches = resolution.kept;
+                if !resolution.discarded.is_empty() && policy != DuplicatePolicy::Quarantine {
+                    outln!(
+                        "⚠️  Skipping {} duplicate file(s):",
+                        resolution.discarded.len()
+                    );
+                    for result in &resolution.discarded {
+                        outln!("  ✗ {} ({} kept)", file_name(result), episode_label(result));
+                    }
+                    outln!();
                 }
-            };
 
-            // Display results based on mode
-            match cli.mode {
-                Mode::DryRun => {
-                    println!("📋 Dry Run - No files will be modified:");
-                    println!();
-
-                    for op in &operations {
-                        let source_name = op
-                            .source
-                            .file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or("unknown");
-                        let dest_name = op
-                            .destination
-                            .file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or("unknown");
+                // Plan file operations
+                let output_dir = self.cli.output_dir.as_deref();
+                let naming = NamingOptions {
+                    show_year,
+                    ascii: self.cli.ascii_filenames,
+                    style: self.cli.filename_style.into(),
+                    lowercase: self.cli.lowercase,
+                };
+                let operations = plan_operations_with(
+                    &matches,
+                    show_name,
+                    &self.cli.format,
+                    output_dir,
+                    &naming,
+                )
+                .and_then(|mut operations| {
+                    if policy == DuplicatePolicy::Quarantine {
+                        operations.extend(plan_quarantine(&resolution.discarded, output_dir)?);
+                    }
+                    Ok(operations)
+                });
+                let operations = match operations {
+                    Ok(ops) => ops,
+                    Err(e) => {
+                        eprintln!("\n{}", tr!("error.plan_failed", error = e));
+                        return EXIT_ENVIRONMENT;
+                    }
+                };
 
+                // Display results based on mode
+                match self.cli.mode {
+                    Mode::DryRun => {
                         let operation_type = if output_dir.is_some() {
                             "COPY"
                         } else {
                             "RENAME"
                         };
+                        let previous = if self.cli.diff {
+                            load_previous_plan(video_dir)
+                        } else {
+                            None
+                        };
+                        match &previous {
+                            Some(previous) => {
+                                print_plan_diff(previous, &operations, operation_type)
+                            }
+                            None => {
+                                if self.cli.diff {
+                                    outln!("{}", tr!("result.diff_first"));
+                                }
+                                outln!("{}", tr!("result.dry_run"));
+                                outln!();
+                                print_plan(&operations, operation_type);
+                            }
+                        }
+                        save_dry_run_plan(video_dir, &operations);
+
+                        if let Some(output) = output_dir
+                            && let Err(e) = check_free_space(&operations, output)
+                        {
+                            outln!();
+                            outln!("⚠️  {}", e);
+                        }
 
-                        if let Some(suffix) = op.duplicate_suffix {
-                            println!(
-                                "  [{}] {} → {} (duplicate #{})",
-                                operation_type, source_name, dest_name, suffix
+                        outln!("{}", tr!("result.dry_run_hint"));
+                        let results = matches.iter().chain(&resolution.discarded);
+                        for (op, result) in operation_results(&operations, results) {
+                            self.remember(
+                                show_name,
+                                result,
+                                HistoryAction::DryRun,
+                                Some(&op.destination),
                             );
-                        } else {
-                            println!("  [{}] {} → {}", operation_type, source_name, dest_name);
                         }
                     }
 
-                    println!("💡 Use --mode rename or --mode copy to apply these changes");
-                }
+                    Mode::Rename => {
+                        outln!("{}", tr!("result.renaming"));
+                        outln!();
 
-                Mode::Rename => {
-                    println!("📝 Renaming files...");
-                    println!();
-
-                    match execute_rename(&operations) {
-                        Ok(errors) if errors.is_empty() => {
-                            for op in &operations {
-                                let source_name = op
-                                    .source
-                                    .file_name()
-                                    .and_then(|n| n.to_str())
-                                    .unwrap_or("unknown");
-                                let dest_name = op
-                                    .destination
-                                    .file_name()
-                                    .and_then(|n| n.to_str())
-                                    .unwrap_or("unknown");
-
-                                println!("  ✓ {} → {}", source_name, dest_name);
-                            }
-                            println!();
-                            println!("✅ Successfully renamed {} file(s)", operations.len());
+                        let renamed = if self.cli.atomic {
+                            execute_rename_atomic(&operations).map(|_| Vec::new())
+                        } else {
+                            let options = RenameOptions {
+                                trash_replaced: !self.cli.no_trash,
+                                retry: RetryPolicy::with_retries(self.cli.io_retries),
+                            };
+                            execute_rename_with(&operations, options)
+                        };
+                        let results = matches.iter().chain(&resolution.discarded);
+                        for (op, result) in operation_results(&operations, results) {
+                            let done = renamed.is_ok()
+                                && op.destination.exists()
+                                && (op.source == op.destination || !op.source.exists());
+                            self.remember_outcome(
+                                show_name,
+                                result,
+                                HistoryAction::Renamed,
+                                op,
+                                done,
+                            );
                         }
-                        Ok(errors) => {
-                            let success_count = operations.len() - errors.len();
-
-                            println!("⚠️  Operation completed with errors:");
-                            println!();
-                            println!("✅ Successfully renamed {} file(s)", success_count);
-                            println!("❌ Failed to rename {} file(s):", errors.len());
-
-                            for (op, error) in operations.iter().zip(errors.iter()) {
-                                let source_name = op
-                                    .source
-                                    .file_name()
-                                    .and_then(|n| n.to_str())
-                                    .unwrap_or("unknown");
-                                println!("  ✗ {} - {}", source_name, error);
+                        match renamed {
+                            Ok(errors) if errors.is_empty() => {
+                                if let Some(subtitles) = self.cli.subtitles {
+                                    write_transcript_subtitles(
+                                        &operations,
+                                        &matches,
+                                        subtitles.into(),
+                                    );
+                                }
+                                if self.cli.write_tags {
+                                    write_container_tags(&operations, show_name);
+                                }
+                                if self.cli.air_date_mtime {
+                                    set_air_dates(&operations);
+                                }
+                                for op in &operations {
+                                    let source_name = op
+                                        .source
+                                        .file_name()
+                                        .and_then(|n| n.to_str())
+                                        .unwrap_or("unknown");
+                                    let dest_name = op
+                                        .destination
+                                        .file_name()
+                                        .and_then(|n| n.to_str())
+                                        .unwrap_or("unknown");
+
+                                    outln!("  ✓ {} → {}", source_name, dest_name);
+                                }
+                                outln!();
+                                outln!("{}", tr!("result.renamed", count = operations.len()));
+                                if let Some(trakt) = &self.trakt {
+                                    let name = series_name.as_deref().unwrap_or(show_name);
+                                    add_to_trakt_collection(trakt, &matches, name, show_year);
+                                }
                             }
+                            Ok(errors) => {
+                                let success_count = operations.len() - errors.len();
 
-                            process::exit(1);
-                        }
-                        Err(e) => {
-                            eprintln!("\n❌ Rename operation failed: {}", e);
-                            process::exit(1);
+                                outln!("{}", tr!("result.completed_with_errors"));
+                                outln!();
+                                outln!("{}", tr!("result.renamed", count = success_count));
+                                outln!("{}", tr!("result.rename_errors", count = errors.len()));
+
+                                for (op, error) in operations.iter().zip(errors.iter()) {
+                                    let source_name = op
+                                        .source
+                                        .file_name()
+                                        .and_then(|n| n.to_str())
+                                        .unwrap_or("unknown");
+                                    outln!("  ✗ {} - {}", source_name, error);
+                                }
+                                if errors.iter().any(gave_up_retrying) {
+                                    outln!("{}", tr!("result.transient_hint"));
+                                }
+
+                                return EXIT_PARTIAL;
+                            }
+                            Err(e) => {
+                                eprintln!("\n{}", tr!("error.rename_failed", error = e));
+                                return EXIT_ENVIRONMENT;
+                            }
                         }
                     }
-                }
 
-                Mode::Copy => {
-                    let output = cli.output_dir.as_ref().unwrap(); // Safe unwrap, validated earlier
-                    println!("📦 Copying files to {}...", output.display());
-                    println!();
-
-                    match execute_copy(&operations, output) {
-                        Ok(errors) if errors.is_empty() => {
-                            for op in &operations {
-                                let source_name = op
-                                    .source
-                                    .file_name()
-                                    .and_then(|n| n.to_str())
-                                    .unwrap_or("unknown");
-                                let dest_name = op
-                                    .destination
-                                    .file_name()
-                                    .and_then(|n| n.to_str())
-                                    .unwrap_or("unknown");
-
-                                println!("  ✓ {} → {}", source_name, dest_name);
+                    Mode::Copy => {
+                        let output = self.cli.output_dir.as_ref().unwrap(); // Safe unwrap, validated earlier
+                        outln!("{}", tr!("result.copying", path = output.display()));
+                        outln!();
+
+                        let options = CopyOptions {
+                            verify: self.cli.verify_copies,
+                            trash_replaced: !self.cli.no_trash,
+                            retry: RetryPolicy::with_retries(self.cli.io_retries),
+                        };
+                        let copied =
+                            execute_copy_with(&operations, output, options, print_copy_progress);
+                        let results = matches.iter().chain(&resolution.discarded);
+                        for (op, result) in operation_results(&operations, results) {
+                            let done = copied.is_ok() && op.destination.exists();
+                            if done && let Ok(metadata) = fs::metadata(&op.destination) {
+                                self.metrics.record_written(metadata.len());
                             }
-                            println!();
-                            println!(
-                                "✅ Successfully copied {} file(s) to {}",
-                                operations.len(),
-                                output.display()
+                            self.remember_outcome(
+                                show_name,
+                                result,
+                                HistoryAction::Copied,
+                                op,
+                                done,
                             );
                         }
-                        Ok(errors) => {
-                            let success_count = operations.len() - errors.len();
-
-                            println!("⚠️  Operation completed with errors:");
-                            println!();
-                            println!("✅ Successfully copied {} file(s)", success_count);
-                            println!("❌ Failed to copy {} file(s):", errors.len());
-
-                            for (op, error) in operations.iter().zip(errors.iter()) {
-                                let source_name = op
-                                    .source
-                                    .file_name()
-                                    .and_then(|n| n.to_str())
-                                    .unwrap_or("unknown");
-                                println!("  ✗ {} - {}", source_name, error);
+                        match copied {
+                            Ok(errors) if errors.is_empty() => {
+                                if let Some(subtitles) = self.cli.subtitles {
+                                    write_transcript_subtitles(
+                                        &operations,
+                                        &matches,
+                                        subtitles.into(),
+                                    );
+                                }
+                                if self.cli.write_tags {
+                                    write_container_tags(&operations, show_name);
+                                }
+                                if self.cli.air_date_mtime {
+                                    set_air_dates(&operations);
+                                }
+                                for op in &operations {
+                                    let source_name = op
+                                        .source
+                                        .file_name()
+                                        .and_then(|n| n.to_str())
+                                        .unwrap_or("unknown");
+                                    let dest_name = op
+                                        .destination
+                                        .file_name()
+                                        .and_then(|n| n.to_str())
+                                        .unwrap_or("unknown");
+
+                                    outln!("  ✓ {} → {}", source_name, dest_name);
+                                }
+                                outln!();
+                                outln!(
+                                    "{}",
+                                    tr!(
+                                        "result.copied_to",
+                                        count = operations.len(),
+                                        path = output.display(),
+                                    )
+                                );
+                                if let Some(trakt) = &self.trakt {
+                                    let name = series_name.as_deref().unwrap_or(show_name);
+                                    add_to_trakt_collection(trakt, &matches, name, show_year);
+                                }
+                            }
+                            Ok(errors) => {
+                                let success_count = operations.len() - errors.len();
+
+                                // End the progress line of the failed file
+                                outln!();
+                                outln!("{}", tr!("result.completed_with_errors"));
+                                outln!();
+                                outln!("{}", tr!("result.copied", count = success_count));
+                                outln!("{}", tr!("result.copy_errors", count = errors.len()));
+
+                                for (op, error) in operations.iter().zip(errors.iter()) {
+                                    let source_name = op
+                                        .source
+                                        .file_name()
+                                        .and_then(|n| n.to_str())
+                                        .unwrap_or("unknown");
+                                    outln!("  ✗ {} - {}", source_name, error);
+                                }
+                                if errors.iter().any(gave_up_retrying) {
+                                    outln!("{}", tr!("result.transient_hint"));
+                                }
+
+                                return EXIT_PARTIAL;
+                            }
+                            Err(e) => {
+                                eprintln!("\n{}", tr!("error.copy_failed", error = e));
+                                return EXIT_ENVIRONMENT;
                             }
+                        }
+                    }
 
-                            process::exit(1);
+                    Mode::Sonarr => {
+                        let sonarr = self.sonarr.as_ref().unwrap(); // Safe unwrap, validated earlier
+                        let name = series_name.as_deref().unwrap_or(show_name);
+                        let (code, queued) = import_into_sonarr(
+                            sonarr,
+                            &matches,
+                            name,
+                            show_year,
+                            self.cli.sonarr_import.into(),
+                        );
+                        for result in &matches {
+                            let path = fs::canonicalize(&result.video.path)
+                                .unwrap_or_else(|_| result.video.path.clone());
+                            let action = if queued.contains(&path) {
+                                HistoryAction::Imported
+                            } else {
+                                HistoryAction::Failed
+                            };
+                            self.remember(show_name, result, action, None);
                         }
-                        Err(e) => {
-                            eprintln!("\n❌ Copy operation failed: {}", e);
-                            process::exit(1);
+                        if code != EXIT_MATCHED {
+                            return code;
                         }
                     }
                 }
+
+                if complete { EXIT_MATCHED } else { EXIT_PARTIAL }
+            }
+            Err(DialogDetectiveError::TooManyFiles { count, limit }) => {
+                eprintln!(
+                    "\n{}",
+                    tr!("error.too_many_files", count = count, limit = limit)
+                );
+                EXIT_USAGE
+            }
+            Err(e) => {
+                eprintln!(
+                    "\n{}",
+                    tr!("error.investigation_failed", code = e.code(), error = e)
+                );
+                EXIT_ENVIRONMENT
             }
-        }
-        Err(e) => {
-            eprintln!("\n❌ Investigation failed: {}", e);
-            process::exit(1);
         }
     }
 }
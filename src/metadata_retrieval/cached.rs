@@ -17,9 +17,9 @@ where
     P: MetadataProvider,
 {
     provider: P,
-    /// Cache for search results, keyed by lowercased query string
+    /// Cache for search results, keyed by provider + lowercased query string
     search_cache: CacheStorage<Vec<SeriesCandidate>>,
-    /// Cache for episode metadata, keyed by provider ID + season filter
+    /// Cache for episode metadata, keyed by provider + series ID + season filter
     metadata_cache: CacheStorage<TVSeries>,
 }
 
@@ -40,34 +40,49 @@ where
         }
     }
 
-    /// Generates a cache key for a search query.
-    fn search_cache_key(series_name: &str) -> String {
-        series_name.to_lowercase()
+    /// Identifies the wrapped provider and its language in cache keys.
+    fn provider_key(&self) -> String {
+        provider_key(&self.provider.id(), self.provider.language().as_deref())
     }
+}
 
-    /// Generates a cache key for episode metadata.
-    ///
-    /// Uses the provider-specific ID to ensure different shows with
-    /// similar names are cached separately.
-    fn metadata_cache_key(
-        candidate: &SeriesCandidate,
-        season_numbers: &Option<Vec<usize>>,
-    ) -> String {
-        match season_numbers {
-            None => format!("tvmaze_{}", candidate.id),
-            Some(seasons) => {
-                let mut seasons_sorted = seasons.clone();
-                seasons_sorted.sort_unstable();
-                format!(
-                    "tvmaze_{}_seasons_{}",
-                    candidate.id,
-                    seasons_sorted
-                        .iter()
-                        .map(|s| s.to_string())
-                        .collect::<Vec<_>>()
-                        .join("_")
-                )
-            }
+/// Generates the cache key prefix for a provider and its language.
+pub(crate) fn provider_key(provider_id: &str, language: Option<&str>) -> String {
+    match language {
+        Some(language) => format!("{}_{}", provider_id, language),
+        None => provider_id.to_string(),
+    }
+}
+
+/// Generates a cache key for a search query.
+fn search_cache_key(provider_key: &str, series_name: &str) -> String {
+    format!("{}_{}", provider_key, series_name.to_lowercase())
+}
+
+/// Generates a cache key for episode metadata.
+///
+/// Uses the provider-specific series ID to ensure different shows with
+/// similar names are cached separately.
+fn metadata_cache_key(
+    provider_key: &str,
+    candidate: &SeriesCandidate,
+    season_numbers: &Option<Vec<usize>>,
+) -> String {
+    match season_numbers {
+        None => format!("{}_{}", provider_key, candidate.id),
+        Some(seasons) => {
+            let mut seasons_sorted = seasons.clone();
+            seasons_sorted.sort_unstable();
+            format!(
+                "{}_{}_seasons_{}",
+                provider_key,
+                candidate.id,
+                seasons_sorted
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join("_")
+            )
         }
     }
 }
@@ -76,11 +91,19 @@ impl<P> MetadataProvider for CachedMetadataProvider<P>
 where
     P: MetadataProvider,
 {
+    fn id(&self) -> String {
+        self.provider.id()
+    }
+
+    fn language(&self) -> Option<String> {
+        self.provider.language()
+    }
+
     fn search_series(
         &self,
         series_name: &str,
     ) -> Result<Vec<SeriesCandidate>, MetadataRetrievalError> {
-        let cache_key = Self::search_cache_key(series_name);
+        let cache_key = search_cache_key(&self.provider_key(), series_name);
 
         // Try to load from cache
         match self.search_cache.load(&cache_key) {
@@ -104,7 +127,7 @@ where
         candidate: &SeriesCandidate,
         season_numbers: Option<Vec<usize>>,
     ) -> Result<TVSeries, MetadataRetrievalError> {
-        let cache_key = Self::metadata_cache_key(candidate, &season_numbers);
+        let cache_key = metadata_cache_key(&self.provider_key(), candidate, &season_numbers);
 
         // Try to load from cache
         match self.metadata_cache.load(&cache_key) {
@@ -123,3 +146,59 @@ where
         Ok(series)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn candidate(id: u64) -> SeriesCandidate {
+        SeriesCandidate {
+            id,
+            name: "Breaking Bad".to_string(),
+            year: Some(2008),
+        }
+    }
+
+    #[test]
+    fn test_cache_keys_differ_by_provider_and_language() {
+        let providers = [
+            provider_key("tvmaze", None),
+            provider_key("tvmaze+tmdb", None),
+            provider_key("tvmaze", Some("en")),
+            provider_key("tvmaze", Some("de")),
+        ];
+
+        let search_keys: HashSet<String> = providers
+            .iter()
+            .map(|provider| search_cache_key(provider, "Breaking Bad"))
+            .collect();
+        let metadata_keys: HashSet<String> = providers
+            .iter()
+            .map(|provider| metadata_cache_key(provider, &candidate(169), &None))
+            .collect();
+
+        assert_eq!(search_keys.len(), providers.len());
+        assert_eq!(metadata_keys.len(), providers.len());
+    }
+
+    #[test]
+    fn test_metadata_cache_key_differs_by_series_and_seasons() {
+        let provider = provider_key("tvmaze", None);
+
+        let keys: HashSet<String> = [
+            metadata_cache_key(&provider, &candidate(169), &None),
+            metadata_cache_key(&provider, &candidate(170), &None),
+            metadata_cache_key(&provider, &candidate(169), &Some(vec![1])),
+            metadata_cache_key(&provider, &candidate(169), &Some(vec![1, 2])),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(keys.len(), 4);
+        assert_eq!(
+            metadata_cache_key(&provider, &candidate(169), &Some(vec![2, 1])),
+            metadata_cache_key(&provider, &candidate(169), &Some(vec![1, 2]))
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Summary enrichment for metadata providers
+//!
+//! Some episodes come back from the primary provider without a summary,
+//! which leaves the matcher with nothing but a title to work with. This
+//! module provides a wrapper that fills those gaps from secondary sources
+//! before the series is handed to the matcher.
+
+use super::{MetadataProvider, MetadataRetrievalError, SeriesCandidate, SummarySource, TVSeries};
+use std::collections::HashMap;
+
+/// A secondary source of episode summaries.
+///
+/// Summary providers are only consulted for episodes whose summary is
+/// missing after the primary provider has been queried.
+pub(crate) trait SummaryProvider {
+    /// Identifies the provider in the cache keys of enriched metadata.
+    fn id(&self) -> String;
+
+    /// The source recorded on episodes filled in by this provider.
+    fn source(&self) -> SummarySource;
+
+    /// Fetches episode summaries for the given seasons of a series.
+    ///
+    /// Returns a map keyed by `(season_number, episode_number)`. Episodes
+    /// the provider has no summary for are simply absent from the map.
+    fn fetch_summaries(
+        &self,
+        candidate: &SeriesCandidate,
+        season_numbers: &[usize],
+    ) -> Result<HashMap<(usize, usize), String>, MetadataRetrievalError>;
+}
+
+/// A metadata provider wrapper that fills missing summaries from secondary sources.
+///
+/// Summary providers are consulted in order. Each one is only asked for the
+/// seasons that still contain episodes without a summary. Failures of a
+/// summary provider are ignored, as enrichment is best-effort and must never
+/// fail an otherwise successful metadata fetch.
+pub(crate) struct EnrichedMetadataProvider<P>
+where
+    P: MetadataProvider,
+{
+    provider: P,
+    summary_providers: Vec<Box<dyn SummaryProvider>>,
+}
+
+impl<P> EnrichedMetadataProvider<P>
+where
+    P: MetadataProvider,
+{
+    /// Creates a new enriching wrapper around the given provider.
+    pub fn new(provider: P, summary_providers: Vec<Box<dyn SummaryProvider>>) -> Self {
+        Self {
+            provider,
+            summary_providers,
+        }
+    }
+}
+
+impl<P> MetadataProvider for EnrichedMetadataProvider<P>
+where
+    P: MetadataProvider,
+{
+    /// Combines the IDs of all providers (e.g. `tvmaze+tmdb`), as the
+    /// summary providers change the fetched metadata.
+    fn id(&self) -> String {
+        std::iter::once(self.provider.id())
+            .chain(self.summary_providers.iter().map(|p| p.id()))
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+
+    fn language(&self) -> Option<String> {
+        self.provider.language()
+    }
+
+    fn search_series(
+        &self,
+        series_name: &str,
+    ) -> Result<Vec<SeriesCandidate>, MetadataRetrievalError> {
+        self.provider.search_series(series_name)
+    }
+
+    fn fetch_series(
+        &self,
+        candidate: &SeriesCandidate,
+        season_numbers: Option<Vec<usize>>,
+    ) -> Result<TVSeries, MetadataRetrievalError> {
+        let mut series = self.provider.fetch_series(candidate, season_numbers)?;
+
+        for summary_provider in &self.summary_providers {
+            let incomplete_seasons = seasons_missing_summaries(&series);
+            if incomplete_seasons.is_empty() {
+                break;
+            }
+
+            if let Ok(summaries) = summary_provider.fetch_summaries(candidate, &incomplete_seasons)
+            {
+                fill_missing_summaries(&mut series, &summaries, summary_provider.source());
+            }
+        }
+
+        Ok(series)
+    }
+}
+
+/// Returns the numbers of all seasons containing at least one episode without a summary.
+fn seasons_missing_summaries(series: &TVSeries) -> Vec<usize> {
+    series
+        .seasons
+        .iter()
+        .filter(|season| season.episodes.iter().any(|e| e.summary.is_empty()))
+        .map(|season| season.season_number)
+        .collect()
+}
+
+/// Fills empty episode summaries from the given map and records their source.
+///
+/// Episodes that already have a summary are left untouched. Returns the
+/// number of episodes that were filled in.
+fn fill_missing_summaries(
+    series: &mut TVSeries,
+    summaries: &HashMap<(usize, usize), String>,
+    source: SummarySource,
+) -> usize {
+    let mut filled = 0;
+
+    for season in &mut series.seasons {
+        for episode in &mut season.episodes {
+            if !episode.summary.is_empty() {
+                continue;
+            }
+
+            let key = (episode.season_number, episode.episode_number);
+            if let Some(summary) = summaries.get(&key).map(|s| s.trim())
+                && !summary.is_empty()
+            {
+                episode.summary = summary.to_string();
+                episode.summary_source = Some(source);
+                filled += 1;
+            }
+        }
+    }
+
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_retrieval::{Episode, Season};
+
+    fn episode(season: usize, number: usize, summary: &str) -> Episode {
+        Episode {
+            season_number: season,
+            episode_number: number,
+            name: format!("Episode {}", number),
+            summary: summary.to_string(),
+            summary_source: (!summary.is_empty()).then_some(SummarySource::TvMaze),
+            airdate: None,
+            runtime: None,
+            season_name: None,
+            provider_id: None,
+            local_title: None,
+        }
+    }
+
+    fn series() -> TVSeries {
+        TVSeries {
+            name: "Test Show".to_string(),
+            year: None,
+            seasons: vec![
+                Season {
+                    season_number: 1,
+                    episodes: vec![episode(1, 1, "Known"), episode(1, 2, "")],
+                },
+                Season {
+                    season_number: 2,
+                    episodes: vec![episode(2, 1, "Also known")],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_seasons_missing_summaries() {
+        assert_eq!(seasons_missing_summaries(&series()), vec![1]);
+    }
+
+    #[test]
+    fn test_fill_missing_summaries_only_fills_gaps() {
+        let mut series = series();
+        let mut summaries = HashMap::new();
+        summaries.insert((1, 1), "Replacement".to_string());
+        summaries.insert((1, 2), "  Filled in  ".to_string());
+
+        let filled = fill_missing_summaries(&mut series, &summaries, SummarySource::Tmdb);
+
+        assert_eq!(filled, 1);
+        let episodes = &series.seasons[0].episodes;
+        assert_eq!(episodes[0].summary, "Known");
+        assert_eq!(episodes[0].summary_source, Some(SummarySource::TvMaze));
+        assert_eq!(episodes[1].summary, "Filled in");
+        assert_eq!(episodes[1].summary_source, Some(SummarySource::Tmdb));
+    }
+
+    #[test]
+    fn test_fill_missing_summaries_ignores_blank_values() {
+        let mut series = series();
+        let mut summaries = HashMap::new();
+        summaries.insert((1, 2), "   ".to_string());
+
+        let filled = fill_missing_summaries(&mut series, &summaries, SummarySource::Tmdb);
+
+        assert_eq!(filled, 0);
+        assert_eq!(series.seasons[0].episodes[1].summary_source, None);
+    }
+}
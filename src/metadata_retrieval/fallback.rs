@@ -0,0 +1,200 @@
+/// Fallback chain of metadata providers.
+///
+/// No single source knows every show: TVMaze and Trakt cover most of them,
+/// while old or regional ones may only be found on Wikipedia. This module
+/// provides a wrapper that asks a list of providers in order and uses the
+/// first one that finds the series.
+use super::{MetadataProvider, MetadataRetrievalError, SeriesCandidate, TVSeries};
+use std::cell::Cell;
+
+/// A metadata provider trying several providers in order.
+///
+/// A search falls through to the next provider if one doesn't find the
+/// series. Errors of the first provider, like a failed request, are
+/// returned right away, so a provider that is down doesn't silently give
+/// way to a less accurate one. Candidate IDs are provider-specific, so
+/// `fetch_series` asks the provider that returned the candidates of the
+/// last search.
+pub(crate) struct FallbackMetadataProvider {
+    providers: Vec<Box<dyn MetadataProvider>>,
+    /// Index of the provider whose candidates the last search returned
+    searched: Cell<usize>,
+}
+
+impl FallbackMetadataProvider {
+    /// Creates a chain asking the given providers in order.
+    pub fn new(providers: Vec<Box<dyn MetadataProvider>>) -> Self {
+        Self {
+            providers,
+            searched: Cell::new(0),
+        }
+    }
+}
+
+impl MetadataProvider for FallbackMetadataProvider {
+    fn id(&self) -> String {
+        self.providers
+            .iter()
+            .map(|provider| provider.id())
+            .collect::<Vec<_>>()
+            .join(">")
+    }
+
+    fn language(&self) -> Option<String> {
+        self.providers.get(self.searched.get())?.language()
+    }
+
+    fn search_series(
+        &self,
+        series_name: &str,
+    ) -> Result<Vec<SeriesCandidate>, MetadataRetrievalError> {
+        for (index, provider) in self.providers.iter().enumerate() {
+            match provider.search_series(series_name) {
+                Ok(candidates) if !candidates.is_empty() => {
+                    self.searched.set(index);
+                    return Ok(candidates);
+                }
+                Ok(_) | Err(MetadataRetrievalError::SeriesNotFound(_)) => continue,
+                Err(e) if index == 0 => return Err(e),
+                // Later providers are a last resort; failing them changes nothing
+                Err(_) => continue,
+            }
+        }
+
+        Err(MetadataRetrievalError::SeriesNotFound(
+            series_name.to_string(),
+        ))
+    }
+
+    fn fetch_series(
+        &self,
+        candidate: &SeriesCandidate,
+        season_numbers: Option<Vec<usize>>,
+    ) -> Result<TVSeries, MetadataRetrievalError> {
+        let provider = self
+            .providers
+            .get(self.searched.get())
+            .ok_or_else(|| MetadataRetrievalError::SeriesNotFound(candidate.name.clone()))?;
+        provider.fetch_series(candidate, season_numbers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A provider knowing a single series, or failing every request
+    struct FakeProvider {
+        id: &'static str,
+        known: Option<&'static str>,
+        failing: bool,
+    }
+
+    impl MetadataProvider for FakeProvider {
+        fn id(&self) -> String {
+            self.id.to_string()
+        }
+
+        fn search_series(
+            &self,
+            series_name: &str,
+        ) -> Result<Vec<SeriesCandidate>, MetadataRetrievalError> {
+            if self.failing {
+                return Err(MetadataRetrievalError::RequestError("down".to_string()));
+            }
+            match self.known {
+                Some(name) if name == series_name => Ok(vec![SeriesCandidate {
+                    id: 1,
+                    name: name.to_string(),
+                    year: None,
+                }]),
+                _ => Err(MetadataRetrievalError::SeriesNotFound(
+                    series_name.to_string(),
+                )),
+            }
+        }
+
+        fn fetch_series(
+            &self,
+            candidate: &SeriesCandidate,
+            _season_numbers: Option<Vec<usize>>,
+        ) -> Result<TVSeries, MetadataRetrievalError> {
+            Ok(TVSeries {
+                name: format!("{} from {}", candidate.name, self.id),
+                year: None,
+                seasons: Vec::new(),
+            })
+        }
+    }
+
+    fn chain(first: FakeProvider, second: FakeProvider) -> FallbackMetadataProvider {
+        FallbackMetadataProvider::new(vec![Box::new(first), Box::new(second)])
+    }
+
+    #[test]
+    fn test_fallback_search_and_fetch() {
+        let provider = chain(
+            FakeProvider {
+                id: "primary",
+                known: Some("Breaking Bad"),
+                failing: false,
+            },
+            FakeProvider {
+                id: "wiki",
+                known: Some("Fawlty Towers"),
+                failing: false,
+            },
+        );
+        assert_eq!(provider.id(), "primary>wiki");
+
+        let candidates = provider.search_series("Fawlty Towers").unwrap();
+        let series = provider.fetch_series(&candidates[0], None).unwrap();
+        assert_eq!(series.name, "Fawlty Towers from wiki");
+
+        let candidates = provider.search_series("Breaking Bad").unwrap();
+        let series = provider.fetch_series(&candidates[0], None).unwrap();
+        assert_eq!(series.name, "Breaking Bad from primary");
+
+        assert!(matches!(
+            provider.search_series("Unknown"),
+            Err(MetadataRetrievalError::SeriesNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_fallback_errors() {
+        let provider = chain(
+            FakeProvider {
+                id: "primary",
+                known: None,
+                failing: true,
+            },
+            FakeProvider {
+                id: "wiki",
+                known: Some("Fawlty Towers"),
+                failing: false,
+            },
+        );
+        assert!(matches!(
+            provider.search_series("Fawlty Towers"),
+            Err(MetadataRetrievalError::RequestError(_))
+        ));
+
+        let provider = chain(
+            FakeProvider {
+                id: "primary",
+                known: None,
+                failing: false,
+            },
+            FakeProvider {
+                id: "wiki",
+                known: None,
+                failing: true,
+            },
+        );
+        assert!(matches!(
+            provider.search_series("Fawlty Towers"),
+            Err(MetadataRetrievalError::SeriesNotFound(_))
+        ));
+    }
+}
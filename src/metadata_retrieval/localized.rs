@@ -0,0 +1,322 @@
+//! Localized metadata for metadata providers
+//!
+//! TVMaze only knows the English titles and summaries of an episode. When
+//! the videos are dubbed, the matcher has to compare German dialogue with
+//! English summaries, and a title quoted in the dialogue ("Die Schlacht von
+//! Winterfell") doesn't match at all. This module provides a wrapper that
+//! replaces titles and summaries with those of a source that has them in
+//! the language of the audio, and keeps titles in a language for naming the
+//! files in.
+
+use super::{
+    MetadataProvider, MetadataRetrievalError, PRIMARY_LANGUAGE, SeriesCandidate, SummarySource,
+    TVSeries,
+};
+use std::collections::HashMap;
+
+/// Title and summary of an episode in another language
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LocalizedEpisode {
+    /// Episode title, None if the source has no translation
+    pub name: Option<String>,
+    /// Episode summary, None if the source has no translation
+    pub summary: Option<String>,
+}
+
+/// A source of episode titles and summaries in other languages.
+pub(crate) trait Localizer {
+    /// Identifies the localizer in the cache keys of localized metadata.
+    fn id(&self) -> String;
+
+    /// The source recorded on episodes whose summary was localized.
+    fn source(&self) -> SummarySource;
+
+    /// Fetches the localized episodes of the given seasons of a series.
+    ///
+    /// `language` is an ISO 639-1 code like "de". Returns a map keyed by
+    /// `(season_number, episode_number)`; episodes without a translation
+    /// are absent from the map.
+    fn fetch_localized(
+        &self,
+        candidate: &SeriesCandidate,
+        season_numbers: &[usize],
+        language: &str,
+    ) -> Result<HashMap<(usize, usize), LocalizedEpisode>, MetadataRetrievalError>;
+}
+
+/// A metadata provider wrapper that localizes episode titles and summaries.
+///
+/// Titles and summaries are replaced with those in `language`, and the
+/// titles in `title_language` are kept as `Episode::local_title`, so files
+/// can be named in another language than the one matched against. Either
+/// may be the primary provider's language. Episodes the localizer has no
+/// translation for keep the provider's metadata. Like enrichment,
+/// localization is best-effort: if the localizer fails, the series is
+/// returned as the provider fetched it.
+pub(crate) struct LocalizedMetadataProvider<P>
+where
+    P: MetadataProvider,
+{
+    provider: P,
+    localizer: Box<dyn Localizer>,
+    language: Option<String>,
+    title_language: Option<String>,
+}
+
+impl<P> LocalizedMetadataProvider<P>
+where
+    P: MetadataProvider,
+{
+    /// Creates a wrapper localizing the given provider's metadata.
+    ///
+    /// A `language` of None keeps the provider's titles and summaries, a
+    /// `title_language` of None leaves the local titles empty.
+    pub fn new(
+        provider: P,
+        localizer: Box<dyn Localizer>,
+        language: Option<String>,
+        title_language: Option<String>,
+    ) -> Self {
+        Self {
+            provider,
+            localizer,
+            language,
+            title_language,
+        }
+    }
+}
+
+impl<P> MetadataProvider for LocalizedMetadataProvider<P>
+where
+    P: MetadataProvider,
+{
+    fn id(&self) -> String {
+        format!("{}+{}", self.provider.id(), self.localizer.id())
+    }
+
+    /// Both languages, as the local titles are part of the metadata
+    fn language(&self) -> Option<String> {
+        let language = self.language.as_deref().unwrap_or(PRIMARY_LANGUAGE);
+        match &self.title_language {
+            Some(title_language) => Some(format!("{}_titles-{}", language, title_language)),
+            None => Some(language.to_string()),
+        }
+    }
+
+    fn search_series(
+        &self,
+        series_name: &str,
+    ) -> Result<Vec<SeriesCandidate>, MetadataRetrievalError> {
+        self.provider.search_series(series_name)
+    }
+
+    fn fetch_series(
+        &self,
+        candidate: &SeriesCandidate,
+        season_numbers: Option<Vec<usize>>,
+    ) -> Result<TVSeries, MetadataRetrievalError> {
+        let mut series = self.provider.fetch_series(candidate, season_numbers)?;
+
+        let seasons: Vec<usize> = series.seasons.iter().map(|s| s.season_number).collect();
+        let fetch = |language: &str| {
+            self.localizer
+                .fetch_localized(candidate, &seasons, language)
+                .ok()
+        };
+        let localized = self.language.as_deref().and_then(fetch);
+        let local_titles = match self.title_language.as_deref() {
+            None => None,
+            Some(PRIMARY_LANGUAGE) => Some(episode_titles(&series)),
+            Some(language) if self.language.as_deref() == Some(language) => {
+                localized.as_ref().map(translated_titles)
+            }
+            Some(language) => fetch(language).as_ref().map(translated_titles),
+        };
+
+        if let Some(localized) = &localized {
+            localize_episodes(&mut series, localized, self.localizer.source());
+        }
+        if let Some(local_titles) = &local_titles {
+            for season in &mut series.seasons {
+                for episode in &mut season.episodes {
+                    let key = (episode.season_number, episode.episode_number);
+                    episode.local_title = local_titles.get(&key).cloned();
+                }
+            }
+        }
+
+        Ok(series)
+    }
+}
+
+/// Collects the titles of all episodes of a series
+fn episode_titles(series: &TVSeries) -> HashMap<(usize, usize), String> {
+    series
+        .seasons
+        .iter()
+        .flat_map(|season| &season.episodes)
+        .map(|e| ((e.season_number, e.episode_number), e.name.clone()))
+        .collect()
+}
+
+/// Collects the translated titles, leaving out blank and placeholder ones
+fn translated_titles(
+    localized: &HashMap<(usize, usize), LocalizedEpisode>,
+) -> HashMap<(usize, usize), String> {
+    localized
+        .iter()
+        .filter_map(|(&key, episode)| {
+            let name = episode.name.as_deref()?.trim();
+            (!name.is_empty() && !is_placeholder_title(name, key.1))
+                .then(|| (key, name.to_string()))
+        })
+        .collect()
+}
+
+/// Replaces episode titles and summaries with their localized versions.
+///
+/// Blank translations and placeholder titles like "Episode 5" (which some
+/// sources use for untranslated episodes) are ignored. Returns the number
+/// of episodes that were changed.
+fn localize_episodes(
+    series: &mut TVSeries,
+    localized: &HashMap<(usize, usize), LocalizedEpisode>,
+    source: SummarySource,
+) -> usize {
+    let mut changed = 0;
+
+    for season in &mut series.seasons {
+        for episode in &mut season.episodes {
+            let key = (episode.season_number, episode.episode_number);
+            let Some(translation) = localized.get(&key) else {
+                continue;
+            };
+
+            let mut localized = false;
+            if let Some(name) = translation.name.as_deref().map(str::trim)
+                && !name.is_empty()
+                && !is_placeholder_title(name, episode.episode_number)
+            {
+                episode.name = name.to_string();
+                localized = true;
+            }
+            if let Some(summary) = translation.summary.as_deref().map(str::trim)
+                && !summary.is_empty()
+            {
+                episode.summary = summary.to_string();
+                episode.summary_source = Some(source);
+                localized = true;
+            }
+            if localized {
+                changed += 1;
+            }
+        }
+    }
+
+    changed
+}
+
+/// Checks for generic titles like "Episode 5" or "Folge 5"
+fn is_placeholder_title(name: &str, episode_number: usize) -> bool {
+    let words: Vec<&str> = name.split_whitespace().collect();
+    words.len() == 2 && words[1] == episode_number.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_retrieval::{Episode, Season};
+
+    fn series() -> TVSeries {
+        let episode = |number: usize, name: &str| Episode {
+            season_number: 1,
+            episode_number: number,
+            name: name.to_string(),
+            summary: format!("Summary of {}", name),
+            summary_source: Some(SummarySource::TvMaze),
+            airdate: None,
+            runtime: None,
+            season_name: None,
+            provider_id: None,
+            local_title: None,
+        };
+        TVSeries {
+            name: "Test Show".to_string(),
+            year: None,
+            seasons: vec![Season {
+                season_number: 1,
+                episodes: vec![
+                    episode(1, "Pilot"),
+                    episode(2, "The Heist"),
+                    episode(3, "Goodbye"),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_localize_episodes() {
+        let mut series = series();
+        let mut localized = HashMap::new();
+        localized.insert(
+            (1, 1),
+            LocalizedEpisode {
+                name: Some(" Der Anfang ".to_string()),
+                summary: Some("Alles beginnt.".to_string()),
+            },
+        );
+        localized.insert(
+            (1, 2),
+            LocalizedEpisode {
+                name: Some("Folge 2".to_string()),
+                summary: Some("  ".to_string()),
+            },
+        );
+        localized.insert(
+            (1, 3),
+            LocalizedEpisode {
+                name: None,
+                summary: Some("Abschied.".to_string()),
+            },
+        );
+
+        let changed = localize_episodes(&mut series, &localized, SummarySource::Tmdb);
+
+        assert_eq!(changed, 2);
+        let episodes = &series.seasons[0].episodes;
+        assert_eq!(episodes[0].name, "Der Anfang");
+        assert_eq!(episodes[0].summary, "Alles beginnt.");
+        assert_eq!(episodes[0].summary_source, Some(SummarySource::Tmdb));
+        assert_eq!(episodes[1].name, "The Heist");
+        assert_eq!(episodes[1].summary_source, Some(SummarySource::TvMaze));
+        assert_eq!(episodes[2].name, "Goodbye");
+        assert_eq!(episodes[2].summary, "Abschied.");
+    }
+
+    #[test]
+    fn test_translated_titles() {
+        let mut localized = HashMap::new();
+        let episode = |name: Option<&str>| LocalizedEpisode {
+            name: name.map(str::to_string),
+            summary: None,
+        };
+        localized.insert((1, 1), episode(Some(" Der Anfang ")));
+        localized.insert((1, 2), episode(Some("Folge 2")));
+        localized.insert((1, 3), episode(None));
+
+        let titles = translated_titles(&localized);
+
+        assert_eq!(titles.len(), 1);
+        assert_eq!(titles[&(1, 1)], "Der Anfang");
+        assert_eq!(episode_titles(&series())[&(1, 2)], "The Heist");
+    }
+
+    #[test]
+    fn test_is_placeholder_title() {
+        assert!(is_placeholder_title("Episode 5", 5));
+        assert!(is_placeholder_title("Folge 5", 5));
+        assert!(!is_placeholder_title("Folge 5", 6));
+        assert!(!is_placeholder_title("Apollo 13", 5));
+        assert!(!is_placeholder_title("Pilot", 1));
+    }
+}
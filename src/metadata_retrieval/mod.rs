@@ -4,15 +4,36 @@
 /// with their associated metadata (names, summaries, etc.), as well as traits
 /// for implementing metadata providers.
 mod cached;
+mod enriched;
+mod fallback;
+mod localized;
+mod plugin;
+mod tmdb;
+mod tmdb_types;
+mod trakt;
+mod trakt_types;
 mod tvmaze;
 mod tvmaze_types;
+mod wikipedia;
+mod wikipedia_types;
 
-pub(crate) use cached::CachedMetadataProvider;
+pub(crate) use cached::{CachedMetadataProvider, provider_key};
+pub(crate) use enriched::{EnrichedMetadataProvider, SummaryProvider};
+pub(crate) use fallback::FallbackMetadataProvider;
+pub(crate) use localized::{LocalizedEpisode, LocalizedMetadataProvider, Localizer};
+pub(crate) use plugin::PluginProvider;
+pub use plugin::{PluginError, ProviderPlugin, default_plugins_path, load_provider_plugin};
+pub(crate) use tmdb::TmdbSummaryProvider;
+pub(crate) use trakt::TraktProvider;
 pub(crate) use tvmaze::TvMazeProvider;
+pub(crate) use wikipedia::WikipediaProvider;
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+/// Language of the primary provider's episode titles and summaries
+pub(crate) const PRIMARY_LANGUAGE: &str = "en";
+
 /// Errors that can occur during metadata retrieval operations.
 #[derive(Debug, Error)]
 pub enum MetadataRetrievalError {
@@ -31,6 +52,23 @@ pub enum MetadataRetrievalError {
     /// The API returned invalid or unexpected data
     #[error("API returned invalid data: {0}")]
     InvalidData(String),
+
+    /// The provider needs credentials that aren't configured
+    #[error("Missing credentials: {0}")]
+    MissingCredentials(String),
+}
+
+impl MetadataRetrievalError {
+    /// Returns the stable code of the error, like `DD-MET-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            MetadataRetrievalError::RequestError(_) => "DD-MET-001",
+            MetadataRetrievalError::ParseError(_) => "DD-MET-002",
+            MetadataRetrievalError::SeriesNotFound(_) => "DD-MET-003",
+            MetadataRetrievalError::InvalidData(_) => "DD-MET-004",
+            MetadataRetrievalError::MissingCredentials(_) => "DD-MET-005",
+        }
+    }
 }
 
 /// A candidate TV series returned from a search query.
@@ -39,7 +77,7 @@ pub enum MetadataRetrievalError {
 /// they want. Contains just enough information for display and selection.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SeriesCandidate {
-    /// Provider-specific ID (e.g. TVMaze show ID)
+    /// Provider-specific ID (e.g. TVMaze or Trakt show ID)
     pub id: u64,
     /// Series name as returned by the provider
     pub name: String,
@@ -47,6 +85,53 @@ pub struct SeriesCandidate {
     pub year: Option<u16>,
 }
 
+/// The source an episode summary was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SummarySource {
+    /// Summary provided by TVMaze, the primary metadata provider
+    TvMaze,
+    /// Summary filled in from The Movie Database (TMDB)
+    Tmdb,
+    /// Summary provided by Trakt, when used as the primary metadata provider
+    Trakt,
+    /// Summary taken from an episode list article on Wikipedia
+    Wikipedia,
+}
+
+/// The primary source of series and episode metadata.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MetadataProviderType {
+    /// TVMaze, which needs no account
+    #[default]
+    TvMaze,
+    /// Trakt, which needs the client ID of a Trakt API app in
+    /// `TRAKT_CLIENT_ID`
+    Trakt,
+    /// The episode list articles of the English Wikipedia
+    ///
+    /// Also the last resort for shows the other providers don't find.
+    Wikipedia,
+    /// An external command configured in the plugins file (see
+    /// `ProviderPlugin`)
+    Plugin(ProviderPlugin),
+}
+
+/// The order episodes are numbered in.
+///
+/// Some shows were released in another order than they were broadcast,
+/// like Firefly on DVD. Libraries follow one of them, so matching and
+/// naming have to use the same one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EpisodeOrder {
+    /// The provider's main order, usually the broadcast order
+    #[default]
+    Default,
+    /// The order of the DVD release
+    Dvd,
+    /// The order the episodes were first broadcast in
+    Broadcast,
+}
+
 /// Represents a single episode of a TV series.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Episode {
@@ -58,11 +143,30 @@ pub struct Episode {
     pub name: String,
     /// A brief summary or description of the episode
     pub summary: String,
+    /// Where the summary came from (None if no source provided one)
+    #[serde(default)]
+    pub summary_source: Option<SummarySource>,
+    /// Original air date as an ISO date like "2008-01-20", if known
+    #[serde(default)]
+    pub airdate: Option<String>,
+    /// Scheduled runtime in minutes, if known
+    #[serde(default)]
+    pub runtime: Option<u32>,
+    /// Name of the season, if the provider has one (e.g. "Specials")
+    #[serde(default)]
+    pub season_name: Option<String>,
+    /// Provider-specific episode ID (e.g. TVMaze episode ID)
+    #[serde(default)]
+    pub provider_id: Option<u64>,
+    /// The episode title in the title language, if one was requested and
+    /// the title is translated to it (see `LanguageStrategy`)
+    #[serde(default)]
+    pub local_title: Option<String>,
 }
 
 /// Represents a season of a TV series.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub(crate) struct Season {
+pub struct Season {
     /// The season number
     pub season_number: usize,
     /// List of episodes in this season
@@ -71,9 +175,12 @@ pub(crate) struct Season {
 
 /// Represents a complete TV series with all seasons and episodes.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub(crate) struct TVSeries {
+pub struct TVSeries {
     /// The name of the TV series
     pub name: String,
+    /// Premiere year, if known
+    #[serde(default)]
+    pub year: Option<u16>,
     /// List of seasons in this series
     pub seasons: Vec<Season>,
 }
@@ -83,7 +190,23 @@ pub(crate) struct TVSeries {
 /// The retrieval process is split into two steps: searching for candidates
 /// and then fetching full episode data for the selected candidate. This
 /// allows the caller to present multiple matches and let the user choose.
-pub(crate) trait MetadataProvider {
+///
+/// Implement this trait to plug a custom metadata source into the library.
+pub trait MetadataProvider {
+    /// Identifies the provider in cache keys.
+    ///
+    /// Candidate IDs are provider-specific, so cached search results and
+    /// metadata are only reused for the provider that returned them.
+    fn id(&self) -> String;
+
+    /// The language of the episode titles and summaries, if configurable.
+    ///
+    /// Metadata in different languages is cached separately. Defaults to
+    /// None for providers with a fixed language.
+    fn language(&self) -> Option<String> {
+        None
+    }
+
     /// Searches for TV series matching the given name.
     ///
     /// Returns up to 10 candidates sorted by relevance score.
@@ -0,0 +1,386 @@
+/// Metadata provider plugins running as external commands.
+///
+/// Niche sources like a local Kodi database or a private tracker don't
+/// belong in this crate, so any program speaking a small JSON protocol can
+/// act as a metadata provider. It is started once per request, gets the
+/// request as a JSON object on stdin, and writes its answer as JSON to
+/// stdout:
+///
+/// | Request | Answer |
+/// |---------|--------|
+/// | `{"version": 1, "action": "search", "query": "Breaking Bad"}` | An array of candidates like `[{"id": 81189, "name": "Breaking Bad", "year": 2008}]`, empty if the show is unknown |
+/// | `{"version": 1, "action": "fetch", "series": {"id": 81189, "name": "Breaking Bad", "year": 2008}, "seasons": [1]}` | The series like `{"name": "Breaking Bad", "year": 2008, "seasons": [{"season_number": 1, "episodes": [{"season_number": 1, "episode_number": 1, "name": "Pilot", "summary": "..."}]}]}` |
+///
+/// `seasons` is null if all seasons are wanted. Episodes may have every
+/// field of `Episode`; the optional ones can be left out. A plugin fails a
+/// request by exiting with a non-zero status, its stderr is the message.
+use super::{MetadataProvider, MetadataRetrievalError, SeriesCandidate, TVSeries};
+use crate::child_process::ChildGuard;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use thiserror::Error;
+
+/// Name of the plugins file in the configuration directory
+const PLUGINS_FILE: &str = "providers.json";
+
+/// Version of the protocol sent with every request
+const PROTOCOL_VERSION: u32 = 1;
+
+/// Errors that can occur while loading a provider plugin
+#[derive(Debug, Error)]
+pub enum PluginError {
+    /// Could not determine the configuration directory
+    #[error("Could not determine configuration directory")]
+    ConfigDirectoryNotFound,
+
+    /// Failed to read the plugins file
+    #[error("Failed to read provider plugins from {path}: {source}")]
+    ReadFailed { path: PathBuf, source: io::Error },
+
+    /// The plugins file isn't valid
+    #[error("Failed to parse provider plugins in {path}: {source}")]
+    ParseFailed {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    /// No plugin of the given name exists
+    #[error("Provider plugin '{0}' not found")]
+    PluginNotFound(String),
+}
+
+impl PluginError {
+    /// Returns the stable code of the error, like `DD-PLG-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            PluginError::ConfigDirectoryNotFound => "DD-PLG-001",
+            PluginError::ReadFailed { .. } => "DD-PLG-002",
+            PluginError::ParseFailed { .. } => "DD-PLG-003",
+            PluginError::PluginNotFound(_) => "DD-PLG-004",
+        }
+    }
+}
+
+/// An external command acting as a metadata provider
+///
+/// Plugins are read from a JSON object mapping plugin names to commands:
+///
+/// ```json
+/// {
+///   "kodi": {
+///     "command": "/usr/local/bin/kodi-episodes",
+///     "args": ["--database", "/home/me/.kodi/userdata/Database/MyVideos131.db"]
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderPlugin {
+    /// Name of the plugin, the key in the plugins file
+    #[serde(skip)]
+    pub name: String,
+
+    /// Program to run
+    pub command: PathBuf,
+
+    /// Arguments passed to the program
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// Returns the path of the plugins file in the configuration directory
+pub fn default_plugins_path() -> Result<PathBuf, PluginError> {
+    let proj_dirs = directories::ProjectDirs::from("de", "westhoffswelt", "dialogdetective")
+        .ok_or(PluginError::ConfigDirectoryNotFound)?;
+    Ok(proj_dirs.config_dir().join(PLUGINS_FILE))
+}
+
+/// Loads a plugin by name from a plugins file
+pub fn load_provider_plugin(path: &Path, name: &str) -> Result<ProviderPlugin, PluginError> {
+    let content = fs::read_to_string(path).map_err(|source| PluginError::ReadFailed {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let mut plugins: HashMap<String, ProviderPlugin> =
+        serde_json::from_str(&content).map_err(|source| PluginError::ParseFailed {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    let mut plugin = plugins
+        .remove(name)
+        .ok_or_else(|| PluginError::PluginNotFound(name.to_string()))?;
+    plugin.name = name.to_string();
+    Ok(plugin)
+}
+
+/// A request sent to a plugin
+#[derive(Debug, Serialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum PluginRequest<'a> {
+    Search {
+        query: &'a str,
+    },
+    Fetch {
+        series: &'a SeriesCandidate,
+        seasons: Option<&'a [usize]>,
+    },
+}
+
+/// A request with the protocol version
+#[derive(Debug, Serialize)]
+struct VersionedRequest<'a> {
+    version: u32,
+    #[serde(flatten)]
+    request: PluginRequest<'a>,
+}
+
+/// Metadata provider asking a provider plugin.
+pub(crate) struct PluginProvider {
+    plugin: ProviderPlugin,
+}
+
+impl PluginProvider {
+    /// Creates a new provider running the given plugin.
+    pub fn new(plugin: ProviderPlugin) -> Self {
+        Self { plugin }
+    }
+
+    /// Runs the plugin with a request and deserializes its answer.
+    fn request<T: DeserializeOwned>(
+        &self,
+        request: PluginRequest,
+    ) -> Result<T, MetadataRetrievalError> {
+        let input = serde_json::to_vec(&VersionedRequest {
+            version: PROTOCOL_VERSION,
+            request,
+        })
+        .map_err(|e| MetadataRetrievalError::InvalidData(e.to_string()))?;
+
+        let mut child = ChildGuard::spawn(
+            Command::new(&self.plugin.command)
+                .args(&self.plugin.args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()),
+        )
+        .map_err(|e| {
+            MetadataRetrievalError::RequestError(format!(
+                "Failed to run provider plugin '{}': {}",
+                self.plugin.name, e
+            ))
+        })?;
+
+        // stdin is closed once the request was written, so the plugin sees
+        // its end
+        let output = child
+            .wait_with_input(input, None)
+            .map_err(|e| MetadataRetrievalError::RequestError(e.to_string()))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(MetadataRetrievalError::RequestError(format!(
+                "Provider plugin '{}' failed ({}): {}",
+                self.plugin.name,
+                output.status,
+                stderr.trim()
+            )));
+        }
+
+        serde_json::from_slice(&output.stdout)
+            .map_err(|e| MetadataRetrievalError::ParseError(e.to_string()))
+    }
+}
+
+impl MetadataProvider for PluginProvider {
+    fn id(&self) -> String {
+        format!("plugin-{}", self.plugin.name)
+    }
+
+    fn search_series(
+        &self,
+        series_name: &str,
+    ) -> Result<Vec<SeriesCandidate>, MetadataRetrievalError> {
+        let candidates: Vec<SeriesCandidate> =
+            self.request(PluginRequest::Search { query: series_name })?;
+
+        if candidates.is_empty() {
+            return Err(MetadataRetrievalError::SeriesNotFound(
+                series_name.to_string(),
+            ));
+        }
+
+        Ok(candidates)
+    }
+
+    fn fetch_series(
+        &self,
+        candidate: &SeriesCandidate,
+        season_numbers: Option<Vec<usize>>,
+    ) -> Result<TVSeries, MetadataRetrievalError> {
+        let mut series: TVSeries = self.request(PluginRequest::Fetch {
+            series: candidate,
+            seasons: season_numbers.as_deref(),
+        })?;
+
+        // Plugins may ignore the season filter and answer with every season
+        series.seasons.retain(|season| {
+            season_numbers
+                .as_ref()
+                .is_none_or(|filter| filter.contains(&season.season_number))
+        });
+        series.seasons.sort_by_key(|s| s.season_number);
+        for season in &mut series.seasons {
+            season.episodes.sort_by_key(|e| e.episode_number);
+        }
+
+        Ok(series)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_provider_plugin() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_provider_plugins");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let path = temp_dir.join(PLUGINS_FILE);
+        fs::write(
+            &path,
+            r#"{
+                "kodi": {"command": "/usr/local/bin/kodi-episodes", "args": ["--database", "videos.db"]},
+                "tracker": {"command": "tracker-provider"}
+            }"#,
+        )
+        .unwrap();
+
+        let plugin = load_provider_plugin(&path, "kodi").unwrap();
+        assert_eq!(plugin.name, "kodi");
+        assert_eq!(
+            plugin.command,
+            PathBuf::from("/usr/local/bin/kodi-episodes")
+        );
+        assert_eq!(plugin.args, vec!["--database", "videos.db"]);
+        assert!(
+            load_provider_plugin(&path, "tracker")
+                .unwrap()
+                .args
+                .is_empty()
+        );
+        assert!(matches!(
+            load_provider_plugin(&path, "unknown"),
+            Err(PluginError::PluginNotFound(_))
+        ));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_request_json() {
+        let search = serde_json::to_value(VersionedRequest {
+            version: PROTOCOL_VERSION,
+            request: PluginRequest::Search {
+                query: "Breaking Bad",
+            },
+        })
+        .unwrap();
+        assert_eq!(
+            search,
+            serde_json::json!({"version": 1, "action": "search", "query": "Breaking Bad"})
+        );
+
+        let candidate = SeriesCandidate {
+            id: 81189,
+            name: "Breaking Bad".to_string(),
+            year: Some(2008),
+        };
+        let fetch = serde_json::to_value(VersionedRequest {
+            version: PROTOCOL_VERSION,
+            request: PluginRequest::Fetch {
+                series: &candidate,
+                seasons: None,
+            },
+        })
+        .unwrap();
+        assert_eq!(
+            fetch,
+            serde_json::json!({
+                "version": 1,
+                "action": "fetch",
+                "series": {"id": 81189, "name": "Breaking Bad", "year": 2008},
+                "seasons": null
+            })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_plugin_provider() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_plugin_provider");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let script = temp_dir.join("provider.sh");
+        fs::write(
+            &script,
+            r#"request=$(cat)
+case "$request" in
+  *'"query":"Unknown"'*) echo '[]' ;;
+  *'"action":"search"'*) echo '[{"id": 7, "name": "Fawlty Towers", "year": 1975}]' ;;
+  *'"action":"fetch"'*) echo '{"name": "Fawlty Towers", "seasons": [
+    {"season_number": 2, "episodes": [{"season_number": 2, "episode_number": 1, "name": "Communication Problems", "summary": ""}]},
+    {"season_number": 1, "episodes": [
+      {"season_number": 1, "episode_number": 2, "name": "The Builders", "summary": ""},
+      {"season_number": 1, "episode_number": 1, "name": "A Touch of Class", "summary": "", "airdate": "1975-09-19"}
+    ]}]}' ;;
+  *) echo "unexpected request" >&2; exit 1 ;;
+esac
+"#,
+        )
+        .unwrap();
+        let provider = PluginProvider::new(ProviderPlugin {
+            name: "test".to_string(),
+            command: PathBuf::from("sh"),
+            args: vec![script.to_string_lossy().into_owned()],
+        });
+        assert_eq!(provider.id(), "plugin-test");
+
+        let candidates = provider.search_series("Fawlty Towers").unwrap();
+        assert_eq!(candidates[0].id, 7);
+        assert!(matches!(
+            provider.search_series("Unknown"),
+            Err(MetadataRetrievalError::SeriesNotFound(_))
+        ));
+
+        let series = provider
+            .fetch_series(&candidates[0], Some(vec![1]))
+            .unwrap();
+        assert_eq!(series.seasons.len(), 1);
+        let episodes = &series.seasons[0].episodes;
+        assert_eq!(episodes[0].name, "A Touch of Class");
+        assert_eq!(episodes[0].airdate.as_deref(), Some("1975-09-19"));
+        assert_eq!(episodes[1].name, "The Builders");
+
+        let broken = PluginProvider::new(ProviderPlugin {
+            name: "broken".to_string(),
+            command: PathBuf::from("sh"),
+            args: vec![
+                "-c".to_string(),
+                "echo database locked >&2; exit 2".to_string(),
+            ],
+        });
+        match broken.search_series("Fawlty Towers") {
+            Err(MetadataRetrievalError::RequestError(message)) => {
+                assert!(message.contains("database locked"), "{}", message)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+}
@@ -0,0 +1,173 @@
+/// TMDB summary provider implementation.
+///
+/// Used as a secondary source to fill in episode summaries that TVMaze
+/// does not provide, and to localize episode titles and summaries.
+/// Requires a TMDB API key.
+use super::tmdb_types::{TmdbEpisode, TmdbSearchResponse, TmdbSeason};
+use super::{
+    LocalizedEpisode, Localizer, MetadataRetrievalError, SeriesCandidate, SummaryProvider,
+    SummarySource,
+};
+use std::collections::HashMap;
+
+/// Environment variable holding the TMDB API key.
+const API_KEY_ENV: &str = "TMDB_API_KEY";
+
+/// Summary provider backed by The Movie Database (TMDB).
+///
+/// Looks up the series on https://api.themoviedb.org by name (and premiere
+/// year, if known) and fetches the per-season episode overviews.
+pub(crate) struct TmdbSummaryProvider {
+    client: reqwest::blocking::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl TmdbSummaryProvider {
+    /// Creates a new TMDB provider using the given API key.
+    pub fn new(api_key: String) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            base_url: "https://api.themoviedb.org/3".to_string(),
+            api_key,
+        }
+    }
+
+    /// Creates a new TMDB provider if `TMDB_API_KEY` is set in the environment.
+    pub fn from_env() -> Option<Self> {
+        std::env::var(API_KEY_ENV)
+            .ok()
+            .filter(|key| !key.trim().is_empty())
+            .map(Self::new)
+    }
+
+    /// Performs a GET request and deserializes the JSON response.
+    fn get_json<T>(&self, url: &str, query: &[(&str, String)]) -> Result<T, MetadataRetrievalError>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        let response = self
+            .client
+            .get(url)
+            .query(&[("api_key", self.api_key.as_str())])
+            .query(query)
+            .send()
+            .map_err(|e| MetadataRetrievalError::RequestError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(MetadataRetrievalError::RequestError(format!(
+                "HTTP {} {}",
+                response.status().as_u16(),
+                response.status().canonical_reason().unwrap_or("Unknown")
+            )));
+        }
+
+        response
+            .json()
+            .map_err(|e| MetadataRetrievalError::ParseError(e.to_string()))
+    }
+
+    /// Resolves the TMDB show ID for a series candidate.
+    fn find_show_id(&self, candidate: &SeriesCandidate) -> Result<u64, MetadataRetrievalError> {
+        let url = format!("{}/search/tv", self.base_url);
+
+        let mut query = vec![("query", candidate.name.clone())];
+        if let Some(year) = candidate.year {
+            query.push(("first_air_date_year", year.to_string()));
+        }
+
+        let response: TmdbSearchResponse = self.get_json(&url, &query)?;
+
+        response
+            .results
+            .first()
+            .map(|show| show.id)
+            .ok_or_else(|| MetadataRetrievalError::SeriesNotFound(candidate.name.clone()))
+    }
+
+    /// Fetches the episodes of the given seasons, optionally in a language.
+    ///
+    /// Without a language, TMDB answers in English.
+    fn fetch_episodes(
+        &self,
+        candidate: &SeriesCandidate,
+        season_numbers: &[usize],
+        language: Option<&str>,
+    ) -> Result<Vec<TmdbEpisode>, MetadataRetrievalError> {
+        let show_id = self.find_show_id(candidate)?;
+        let query: Vec<(&str, String)> = language
+            .map(|language| ("language", language.to_string()))
+            .into_iter()
+            .collect();
+        let mut episodes = Vec::new();
+
+        for season_number in season_numbers {
+            let url = format!("{}/tv/{}/season/{}", self.base_url, show_id, season_number);
+
+            // A season missing on TMDB shouldn't prevent fetching the others
+            match self.get_json::<TmdbSeason>(&url, &query) {
+                Ok(season) => episodes.extend(season.episodes),
+                Err(_) => continue,
+            }
+        }
+
+        Ok(episodes)
+    }
+}
+
+impl SummaryProvider for TmdbSummaryProvider {
+    fn id(&self) -> String {
+        "tmdb".to_string()
+    }
+
+    fn source(&self) -> SummarySource {
+        SummarySource::Tmdb
+    }
+
+    fn fetch_summaries(
+        &self,
+        candidate: &SeriesCandidate,
+        season_numbers: &[usize],
+    ) -> Result<HashMap<(usize, usize), String>, MetadataRetrievalError> {
+        let mut summaries = HashMap::new();
+
+        for episode in self.fetch_episodes(candidate, season_numbers, None)? {
+            if let Some(overview) = episode.overview.filter(|o| !o.trim().is_empty()) {
+                summaries.insert((episode.season_number, episode.episode_number), overview);
+            }
+        }
+
+        Ok(summaries)
+    }
+}
+
+impl Localizer for TmdbSummaryProvider {
+    fn id(&self) -> String {
+        "tmdb".to_string()
+    }
+
+    fn source(&self) -> SummarySource {
+        SummarySource::Tmdb
+    }
+
+    fn fetch_localized(
+        &self,
+        candidate: &SeriesCandidate,
+        season_numbers: &[usize],
+        language: &str,
+    ) -> Result<HashMap<(usize, usize), LocalizedEpisode>, MetadataRetrievalError> {
+        let episodes = self.fetch_episodes(candidate, season_numbers, Some(language))?;
+
+        Ok(episodes
+            .into_iter()
+            .map(|episode| {
+                let key = (episode.season_number, episode.episode_number);
+                let localized = LocalizedEpisode {
+                    name: episode.name,
+                    summary: episode.overview,
+                };
+                (key, localized)
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,46 @@
+/// TMDB API response types for deserialization.
+///
+/// These structures mirror the subset of the TMDB v3 JSON responses needed
+/// to fill in missing episode summaries and localize episode metadata.
+use serde::Deserialize;
+
+// =========================================================
+// Search endpoint types (/search/tv)
+// =========================================================
+
+/// Response envelope of the `/search/tv` endpoint.
+#[derive(Debug, Deserialize)]
+pub(super) struct TmdbSearchResponse {
+    pub results: Vec<TmdbSearchShow>,
+}
+
+/// A single show within a search response.
+#[derive(Debug, Deserialize)]
+pub(super) struct TmdbSearchShow {
+    pub id: u64,
+}
+
+// =========================================================
+// Season types (/tv/{id}/season/{number})
+// =========================================================
+
+/// Response of the season details endpoint.
+#[derive(Debug, Deserialize)]
+pub(super) struct TmdbSeason {
+    pub episodes: Vec<TmdbEpisode>,
+}
+
+/// A single episode within a season response.
+#[derive(Debug, Deserialize)]
+pub(super) struct TmdbEpisode {
+    /// Season number (0 for specials)
+    pub season_number: usize,
+    /// Episode number within the season
+    pub episode_number: usize,
+    /// Episode title in the requested language
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Plain-text episode overview (empty string if unknown)
+    #[serde(default)]
+    pub overview: Option<String>,
+}
@@ -0,0 +1,260 @@
+/// Trakt metadata provider implementation.
+///
+/// An alternative primary provider for users who track their library on
+/// Trakt. Reading metadata only needs the client ID of a Trakt API app, no
+/// signed-in user.
+use super::trakt_types::{TraktEpisode, TraktSearchResult, TraktSeason};
+use super::{
+    Episode, MetadataProvider, MetadataRetrievalError, Season, SeriesCandidate, SummarySource,
+    TVSeries,
+};
+use serde::de::DeserializeOwned;
+
+/// Environment variable holding the client ID of the Trakt API app.
+const CLIENT_ID_ENV: &str = "TRAKT_CLIENT_ID";
+
+/// Maximum number of search results to return as candidates.
+const MAX_CANDIDATES: usize = 10;
+
+/// Metadata provider for the Trakt API.
+///
+/// Searches https://api.trakt.tv for shows and fetches all seasons with
+/// their episodes in a single request.
+pub(crate) struct TraktProvider {
+    client: reqwest::blocking::Client,
+    base_url: String,
+    client_id: String,
+}
+
+impl TraktProvider {
+    /// Creates a new Trakt provider using the given client ID.
+    pub fn new(client_id: String) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            base_url: "https://api.trakt.tv".to_string(),
+            client_id,
+        }
+    }
+
+    /// Creates a new Trakt provider if `TRAKT_CLIENT_ID` is set in the environment.
+    pub fn from_env() -> Option<Self> {
+        Self::client_id_from_env().map(Self::new)
+    }
+
+    /// Reads the client ID from `TRAKT_CLIENT_ID`, ignoring blank values.
+    pub fn client_id_from_env() -> Option<String> {
+        std::env::var(CLIENT_ID_ENV)
+            .ok()
+            .filter(|id| !id.trim().is_empty())
+    }
+
+    /// Returns an error for running without a client ID.
+    pub fn missing_client_id() -> MetadataRetrievalError {
+        MetadataRetrievalError::MissingCredentials(format!("{} is not set", CLIENT_ID_ENV))
+    }
+
+    /// Performs a GET request and deserializes the JSON response.
+    ///
+    /// Returns None if the resource doesn't exist.
+    fn get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Option<T>, MetadataRetrievalError> {
+        let response = self
+            .client
+            .get(url)
+            .header("trakt-api-version", "2")
+            .header("trakt-api-key", &self.client_id)
+            .query(query)
+            .send()
+            .map_err(|e| MetadataRetrievalError::RequestError(e.to_string()))?;
+
+        if response.status() == 404 {
+            return Ok(None);
+        }
+
+        if !response.status().is_success() {
+            return Err(MetadataRetrievalError::RequestError(format!(
+                "HTTP {} {}",
+                response.status().as_u16(),
+                response.status().canonical_reason().unwrap_or("Unknown")
+            )));
+        }
+
+        response
+            .json()
+            .map(Some)
+            .map_err(|e| MetadataRetrievalError::ParseError(e.to_string()))
+    }
+}
+
+impl MetadataProvider for TraktProvider {
+    fn id(&self) -> String {
+        "trakt".to_string()
+    }
+
+    fn search_series(
+        &self,
+        series_name: &str,
+    ) -> Result<Vec<SeriesCandidate>, MetadataRetrievalError> {
+        let url = format!("{}/search/show", self.base_url);
+        let limit = MAX_CANDIDATES.to_string();
+
+        let results: Vec<TraktSearchResult> = self
+            .get_json(&url, &[("query", series_name), ("limit", &limit)])?
+            .unwrap_or_default();
+
+        let candidates: Vec<SeriesCandidate> = results
+            .into_iter()
+            .take(MAX_CANDIDATES)
+            .map(|result| SeriesCandidate {
+                id: result.show.ids.trakt,
+                name: result.show.title,
+                year: result.show.year,
+            })
+            .collect();
+
+        if candidates.is_empty() {
+            return Err(MetadataRetrievalError::SeriesNotFound(
+                series_name.to_string(),
+            ));
+        }
+
+        Ok(candidates)
+    }
+
+    fn fetch_series(
+        &self,
+        candidate: &SeriesCandidate,
+        season_numbers: Option<Vec<usize>>,
+    ) -> Result<TVSeries, MetadataRetrievalError> {
+        let url = format!("{}/shows/{}/seasons", self.base_url, candidate.id);
+
+        let seasons: Vec<TraktSeason> = self
+            .get_json(&url, &[("extended", "episodes,full")])?
+            .ok_or_else(|| MetadataRetrievalError::SeriesNotFound(candidate.name.clone()))?;
+
+        Ok(TVSeries {
+            name: candidate.name.clone(),
+            year: candidate.year,
+            seasons: convert_seasons(seasons, season_numbers),
+        })
+    }
+}
+
+/// Converts Trakt seasons, optionally filtered, into sorted seasons.
+///
+/// Trakt titles every season; generic titles like "Season 2" are left out,
+/// so only names like "Specials" end up as the season name.
+fn convert_seasons(seasons: Vec<TraktSeason>, season_filter: Option<Vec<usize>>) -> Vec<Season> {
+    let mut seasons: Vec<Season> = seasons
+        .into_iter()
+        .filter(|season| {
+            season_filter
+                .as_ref()
+                .is_none_or(|filter| filter.contains(&season.number))
+        })
+        .map(|season| {
+            let generic_title = format!("Season {}", season.number);
+            let season_name = season
+                .title
+                .filter(|title| !title.trim().is_empty() && *title != generic_title);
+            let mut episodes: Vec<Episode> = season
+                .episodes
+                .into_iter()
+                .map(|episode| convert_episode(episode, season_name.clone()))
+                .collect();
+            episodes.sort_by_key(|e| e.episode_number);
+            Season {
+                season_number: season.number,
+                episodes,
+            }
+        })
+        .filter(|season| !season.episodes.is_empty())
+        .collect();
+
+    seasons.sort_by_key(|s| s.season_number);
+    seasons
+}
+
+/// Converts a Trakt episode into the domain `Episode` type.
+fn convert_episode(trakt_episode: TraktEpisode, season_name: Option<String>) -> Episode {
+    let summary = trakt_episode
+        .overview
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default();
+
+    Episode {
+        season_number: trakt_episode.season,
+        episode_number: trakt_episode.number,
+        name: trakt_episode.title.unwrap_or_else(|| "Unknown".to_string()),
+        summary_source: (!summary.is_empty()).then_some(SummarySource::Trakt),
+        summary,
+        // The date of the UTC timestamp, which may be a day after the local one
+        airdate: trakt_episode
+            .first_aired
+            .and_then(|aired| aired.get(..10).map(str::to_string)),
+        runtime: trakt_episode.runtime,
+        season_name,
+        provider_id: Some(trakt_episode.ids.trakt),
+        local_title: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_seasons() {
+        let seasons: Vec<TraktSeason> = serde_json::from_str(
+            r#"[
+                {"number": 1, "title": "Season 1", "episodes": [
+                    {"season": 1, "number": 2, "title": "Cat's in the Bag...", "overview": " The body. ",
+                     "first_aired": "2008-01-28T03:00:00.000Z", "runtime": 48, "ids": {"trakt": 74}},
+                    {"season": 1, "number": 1, "title": null, "overview": null,
+                     "first_aired": null, "runtime": null, "ids": {"trakt": 73}}
+                ]},
+                {"number": 0, "title": "Specials", "episodes": [
+                    {"season": 0, "number": 1, "title": "Good Cop Bad Cop", "overview": "",
+                     "first_aired": "2009-02-17T03:00:00.000Z", "runtime": 3, "ids": {"trakt": 1}}
+                ]},
+                {"number": 2, "title": "Season 2"}
+            ]"#,
+        )
+        .unwrap();
+
+        let converted = convert_seasons(seasons, None);
+
+        let numbers: Vec<usize> = converted.iter().map(|s| s.season_number).collect();
+        assert_eq!(numbers, vec![0, 1]);
+        let special = &converted[0].episodes[0];
+        assert_eq!(special.season_name.as_deref(), Some("Specials"));
+        assert_eq!(special.summary_source, None);
+        let episodes = &converted[1].episodes;
+        assert_eq!(episodes[0].name, "Unknown");
+        assert_eq!(episodes[0].airdate, None);
+        assert_eq!(episodes[1].summary, "The body.");
+        assert_eq!(episodes[1].summary_source, Some(SummarySource::Trakt));
+        assert_eq!(episodes[1].airdate.as_deref(), Some("2008-01-28"));
+        assert_eq!(episodes[1].season_name, None);
+        assert_eq!(episodes[1].provider_id, Some(74));
+    }
+
+    #[test]
+    fn test_convert_seasons_filtered() {
+        let seasons: Vec<TraktSeason> = serde_json::from_str(
+            r#"[
+                {"number": 1, "episodes": [{"season": 1, "number": 1, "ids": {"trakt": 73}}]},
+                {"number": 2, "episodes": [{"season": 2, "number": 1, "ids": {"trakt": 80}}]}
+            ]"#,
+        )
+        .unwrap();
+
+        let converted = convert_seasons(seasons, Some(vec![2]));
+
+        assert_eq!(converted.len(), 1);
+        assert_eq!(converted[0].season_number, 2);
+    }
+}
@@ -0,0 +1,69 @@
+/// Trakt API response types for deserialization.
+///
+/// These structures mirror the subset of the Trakt API JSON needed to
+/// search for shows and fetch their episodes.
+use serde::Deserialize;
+
+// =========================================================
+// Search endpoint types (/search/show)
+// =========================================================
+
+/// A single search result from the `/search/show` endpoint.
+///
+/// Results arrive sorted by relevance, so the score isn't read.
+#[derive(Debug, Deserialize)]
+pub(super) struct TraktSearchResult {
+    pub show: TraktShow,
+}
+
+/// Show metadata within a search result.
+#[derive(Debug, Deserialize)]
+pub(super) struct TraktShow {
+    pub title: String,
+    /// Premiere year (null if unknown)
+    pub year: Option<u16>,
+    pub ids: TraktIds,
+}
+
+/// The IDs of a show or episode.
+///
+/// Trakt also returns the IDs of other databases (TVDB, IMDb, TMDB),
+/// which aren't needed here.
+#[derive(Debug, Deserialize)]
+pub(super) struct TraktIds {
+    pub trakt: u64,
+}
+
+// =========================================================
+// Season types (/shows/{id}/seasons?extended=episodes,full)
+// =========================================================
+
+/// A season with its episodes.
+#[derive(Debug, Deserialize)]
+pub(super) struct TraktSeason {
+    /// Season number (0 for specials)
+    pub number: usize,
+    /// Season title, e.g. "Season 1" or "Specials"
+    pub title: Option<String>,
+    /// Episodes of the season (absent for seasons without any)
+    #[serde(default)]
+    pub episodes: Vec<TraktEpisode>,
+}
+
+/// A single episode from the Trakt API.
+#[derive(Debug, Deserialize)]
+pub(super) struct TraktEpisode {
+    /// Season number (0 for specials)
+    pub season: usize,
+    /// Episode number within the season
+    pub number: usize,
+    /// Episode title (may be null for episodes without a title)
+    pub title: Option<String>,
+    /// Episode summary as plain text (may be null)
+    pub overview: Option<String>,
+    /// UTC timestamp like "2008-01-21T02:00:00.000Z" (null if not yet scheduled)
+    pub first_aired: Option<String>,
+    /// Runtime in minutes (null if unknown)
+    pub runtime: Option<u32>,
+    pub ids: TraktIds,
+}
@@ -1,9 +1,14 @@
 /// TVMaze metadata provider implementation.
 ///
 /// Uses the search endpoint to find candidates, then fetches episodes
-/// for the selected show in a separate request.
-use super::tvmaze_types::{TvMazeEpisode, TvMazeSearchResult};
-use super::{Episode, MetadataProvider, MetadataRetrievalError, Season, SeriesCandidate, TVSeries};
+/// and season names for the selected show in separate requests.
+use super::tvmaze_types::{
+    TvMazeAlternateEpisode, TvMazeAlternateList, TvMazeEpisode, TvMazeSearchResult, TvMazeSeason,
+};
+use super::{
+    Episode, EpisodeOrder, MetadataProvider, MetadataRetrievalError, Season, SeriesCandidate,
+    SummarySource, TVSeries,
+};
 use std::collections::HashMap;
 
 /// Maximum number of search results to return as candidates.
@@ -17,6 +22,7 @@ const MAX_CANDIDATES: usize = 10;
 pub(crate) struct TvMazeProvider {
     client: reqwest::blocking::Client,
     base_url: String,
+    order: EpisodeOrder,
 }
 
 impl TvMazeProvider {
@@ -25,25 +31,93 @@ impl TvMazeProvider {
         Self {
             client: reqwest::blocking::Client::new(),
             base_url: "https://api.tvmaze.com".to_string(),
+            order: EpisodeOrder::Default,
         }
     }
 
+    /// Numbers the episodes in the given order, if TVMaze has it for a show.
+    ///
+    /// Shows without an alternate list of that order keep the default order.
+    pub fn with_order(mut self, order: EpisodeOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Fetches the episodes of a show renumbered in an alternate order.
+    ///
+    /// Returns None if the show has no alternate list of the order.
+    fn fetch_alternate_episodes(
+        &self,
+        show_id: u64,
+    ) -> Result<Option<Vec<TvMazeEpisode>>, MetadataRetrievalError> {
+        let url = format!("{}/shows/{}/alternatelists", self.base_url, show_id);
+        let lists: Vec<TvMazeAlternateList> = self.get_json(&url, &[])?;
+        let Some(list_id) = select_alternate_list(&lists, self.order) else {
+            return Ok(None);
+        };
+
+        let url = format!(
+            "{}/alternatelists/{}/alternateepisodes",
+            self.base_url, list_id
+        );
+        let alternate: Vec<TvMazeAlternateEpisode> =
+            self.get_json(&url, &[("embed", "episodes")])?;
+        Ok(Some(renumber_alternate_episodes(alternate)))
+    }
+
+    /// Performs a GET request and deserializes the JSON response.
+    fn get_json<T>(&self, url: &str, query: &[(&str, &str)]) -> Result<T, MetadataRetrievalError>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        let response = self
+            .client
+            .get(url)
+            .query(query)
+            .send()
+            .map_err(|e| MetadataRetrievalError::RequestError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(MetadataRetrievalError::RequestError(format!(
+                "HTTP {} {}",
+                response.status().as_u16(),
+                response.status().canonical_reason().unwrap_or("Unknown")
+            )));
+        }
+
+        response
+            .json()
+            .map_err(|e| MetadataRetrievalError::ParseError(e.to_string()))
+    }
+
     /// Converts a TVMaze episode to our internal Episode structure.
-    fn convert_episode(tvmaze_episode: TvMazeEpisode) -> Episode {
+    fn convert_episode(
+        tvmaze_episode: TvMazeEpisode,
+        season_names: &HashMap<usize, String>,
+    ) -> Episode {
+        let summary = tvmaze_episode
+            .summary
+            .map(|s| nanohtml2text::html2text(&s).trim().to_string())
+            .unwrap_or_default();
+
         Episode {
             season_number: tvmaze_episode.season,
             episode_number: tvmaze_episode.number,
             name: tvmaze_episode.name.unwrap_or_else(|| "Unknown".to_string()),
-            summary: tvmaze_episode
-                .summary
-                .map(|s| nanohtml2text::html2text(&s).trim().to_string())
-                .unwrap_or_default(),
+            summary_source: (!summary.is_empty()).then_some(SummarySource::TvMaze),
+            summary,
+            airdate: tvmaze_episode.airdate.filter(|date| !date.is_empty()),
+            runtime: tvmaze_episode.runtime,
+            season_name: season_names.get(&tvmaze_episode.season).cloned(),
+            provider_id: Some(tvmaze_episode.id),
+            local_title: None,
         }
     }
 
     /// Groups a flat list of episodes into sorted seasons, optionally filtered.
     fn group_into_seasons(
         episodes: Vec<TvMazeEpisode>,
+        season_names: &HashMap<usize, String>,
         season_filter: Option<Vec<usize>>,
     ) -> Vec<Season> {
         let mut seasons_map: HashMap<usize, Vec<Episode>> = HashMap::new();
@@ -59,7 +133,7 @@ impl TvMazeProvider {
             seasons_map
                 .entry(tvmaze_episode.season)
                 .or_default()
-                .push(Self::convert_episode(tvmaze_episode));
+                .push(Self::convert_episode(tvmaze_episode, season_names));
         }
 
         // Convert HashMap to Vec<Season>, sorted by season number
@@ -78,6 +152,33 @@ impl TvMazeProvider {
         seasons
     }
 
+    /// Fetches the names of the seasons that have one.
+    ///
+    /// Season names are only used for naming files, so a failed request
+    /// leaves them out instead of failing the whole fetch.
+    fn fetch_season_names(&self, show_id: u64) -> HashMap<usize, String> {
+        let url = format!("{}/shows/{}/seasons", self.base_url, show_id);
+
+        let seasons: Vec<TvMazeSeason> = match self
+            .client
+            .get(&url)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+        {
+            Ok(seasons) => seasons,
+            Err(_) => return HashMap::new(),
+        };
+
+        seasons
+            .into_iter()
+            .filter_map(|season| {
+                let name = season.name.filter(|name| !name.trim().is_empty())?;
+                Some((season.number, name))
+            })
+            .collect()
+    }
+
     /// Extracts a four-digit year from an ISO date string like "2008-01-20".
     fn extract_year(premiered: &str) -> Option<u16> {
         premiered
@@ -88,6 +189,16 @@ impl TvMazeProvider {
 }
 
 impl MetadataProvider for TvMazeProvider {
+    /// Alternate orders number the episodes differently, so they are
+    /// cached under their own ID (e.g. `tvmaze-dvd`).
+    fn id(&self) -> String {
+        match self.order {
+            EpisodeOrder::Default => "tvmaze".to_string(),
+            EpisodeOrder::Dvd => "tvmaze-dvd".to_string(),
+            EpisodeOrder::Broadcast => "tvmaze-broadcast".to_string(),
+        }
+    }
+
     fn search_series(
         &self,
         series_name: &str,
@@ -161,15 +272,81 @@ impl MetadataProvider for TvMazeProvider {
             )));
         }
 
-        let episodes: Vec<TvMazeEpisode> = response
+        let mut episodes: Vec<TvMazeEpisode> = response
             .json()
             .map_err(|e| MetadataRetrievalError::ParseError(e.to_string()))?;
 
-        let seasons = Self::group_into_seasons(episodes, season_numbers);
+        if self.order != EpisodeOrder::Default
+            && let Some(alternate) = self.fetch_alternate_episodes(candidate.id)?
+        {
+            episodes = alternate;
+        }
+
+        let season_names = self.fetch_season_names(candidate.id);
+        let seasons = Self::group_into_seasons(episodes, &season_names, season_numbers);
 
         Ok(TVSeries {
             name: candidate.name.clone(),
+            year: candidate.year,
             seasons,
         })
     }
 }
+
+/// Picks the alternate list of the given order, if the show has one
+fn select_alternate_list(lists: &[TvMazeAlternateList], order: EpisodeOrder) -> Option<u64> {
+    lists
+        .iter()
+        .find(|list| match order {
+            EpisodeOrder::Default => false,
+            EpisodeOrder::Dvd => list.dvd_release,
+            EpisodeOrder::Broadcast => list.broadcast_premiere,
+        })
+        .map(|list| list.id)
+}
+
+/// Numbers the embedded episodes by their position in the alternate list
+///
+/// A position may hold several episodes, e.g. when two broadcast episodes
+/// were released as one on DVD; the first one stands for all of them.
+fn renumber_alternate_episodes(alternate: Vec<TvMazeAlternateEpisode>) -> Vec<TvMazeEpisode> {
+    alternate
+        .into_iter()
+        .filter_map(|position| {
+            let mut episode = position.embedded.episodes.into_iter().next()?;
+            episode.season = position.season;
+            episode.number = position.number;
+            Some(episode)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alternate_order() {
+        let lists: Vec<TvMazeAlternateList> = serde_json::from_str(
+            r#"[{"id": 7, "broadcast_premiere": true}, {"id": 9, "dvd_release": true}]"#,
+        )
+        .unwrap();
+        assert_eq!(select_alternate_list(&lists, EpisodeOrder::Dvd), Some(9));
+        assert_eq!(
+            select_alternate_list(&lists, EpisodeOrder::Broadcast),
+            Some(7)
+        );
+        assert_eq!(select_alternate_list(&lists, EpisodeOrder::Default), None);
+
+        let alternate: Vec<TvMazeAlternateEpisode> = serde_json::from_str(
+            r#"[{"season": 1, "number": 1, "_embedded": {"episodes": [
+                {"id": 11, "season": 1, "number": 11, "name": "Serenity"}
+            ]}}, {"season": 1, "number": 2, "_embedded": {"episodes": []}}]"#,
+        )
+        .unwrap();
+        let episodes = renumber_alternate_episodes(alternate);
+        assert_eq!(episodes.len(), 1);
+        assert_eq!((episodes[0].season, episodes[0].number), (1, 1));
+        assert_eq!(episodes[0].name.as_deref(), Some("Serenity"));
+    }
+}
@@ -39,6 +39,8 @@ pub(super) struct TvMazeSearchShow {
 /// A single episode from the TVMaze API.
 #[derive(Debug, Deserialize)]
 pub(super) struct TvMazeEpisode {
+    /// TVMaze episode ID
+    pub id: u64,
     /// Season number (0 for specials)
     pub season: usize,
     /// Episode number within the season
@@ -47,4 +49,57 @@ pub(super) struct TvMazeEpisode {
     pub name: Option<String>,
     /// Episode summary in HTML format (may be null)
     pub summary: Option<String>,
+    /// ISO date string like "2008-01-20" (empty or null if not yet scheduled)
+    pub airdate: Option<String>,
+    /// Runtime in minutes (null if unknown)
+    pub runtime: Option<u32>,
+}
+
+// =========================================================
+// Season types (/shows/{id}/seasons)
+// =========================================================
+
+/// A single season from the TVMaze API.
+#[derive(Debug, Deserialize)]
+pub(super) struct TvMazeSeason {
+    /// Season number (0 for specials)
+    pub number: usize,
+    /// Season name, empty for most seasons
+    pub name: Option<String>,
+}
+
+// =========================================================
+// Alternate list types (/shows/{id}/alternatelists)
+// =========================================================
+
+/// An alternate episode order of a show, like its DVD order.
+#[derive(Debug, Deserialize)]
+pub(super) struct TvMazeAlternateList {
+    /// TVMaze alternate list ID
+    pub id: u64,
+    /// Whether this is the order of the DVD release
+    #[serde(default)]
+    pub dvd_release: bool,
+    /// Whether this is the order the episodes were first broadcast in
+    #[serde(default)]
+    pub broadcast_premiere: bool,
+}
+
+/// An episode's position in an alternate list, with the episode embedded
+/// (`/alternatelists/{id}/alternateepisodes?embed=episodes`).
+#[derive(Debug, Deserialize)]
+pub(super) struct TvMazeAlternateEpisode {
+    /// Season number in the alternate order
+    pub season: usize,
+    /// Episode number within the season in the alternate order
+    pub number: usize,
+    #[serde(rename = "_embedded")]
+    pub embedded: TvMazeAlternateEmbedded,
+}
+
+/// Embedded resources of an alternate episode.
+#[derive(Debug, Deserialize)]
+pub(super) struct TvMazeAlternateEmbedded {
+    /// The episode(s) at this position, numbered in the default order
+    pub episodes: Vec<TvMazeEpisode>,
 }
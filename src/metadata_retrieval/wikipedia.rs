@@ -0,0 +1,610 @@
+/// Wikipedia metadata provider implementation.
+///
+/// A last resort for old or regional shows that TVMaze and Trakt don't
+/// know. Many of them have a "List of ... episodes" article on the English
+/// Wikipedia, whose episode tables hold the number, title, air date, and
+/// often a short description of every episode.
+use super::wikipedia_types::{WikipediaParseResponse, WikipediaSearchResponse};
+use super::{
+    Episode, MetadataProvider, MetadataRetrievalError, Season, SeriesCandidate, SummarySource,
+    TVSeries,
+};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+
+/// Maximum number of search results to return as candidates.
+const MAX_CANDIDATES: usize = 10;
+
+/// Metadata provider reading the episode list articles of Wikipedia.
+///
+/// Searches https://en.wikipedia.org for "List of ... episodes" articles
+/// and reads the episode tables of the selected one. Articles are laid
+/// out by hand, so seasons and episodes the tables don't follow the usual
+/// layout for are left out.
+pub(crate) struct WikipediaProvider {
+    client: reqwest::blocking::Client,
+    api_url: String,
+}
+
+impl WikipediaProvider {
+    /// Creates a new Wikipedia provider instance.
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            api_url: "https://en.wikipedia.org/w/api.php".to_string(),
+        }
+    }
+
+    /// Performs a request to the action API and deserializes the JSON response.
+    fn get_json<T: DeserializeOwned>(
+        &self,
+        query: &[(&str, &str)],
+    ) -> Result<T, MetadataRetrievalError> {
+        // Wikimedia asks API clients to identify themselves
+        let user_agent = concat!(
+            "DialogDetective/",
+            env!("CARGO_PKG_VERSION"),
+            " (",
+            env!("CARGO_PKG_REPOSITORY"),
+            ")"
+        );
+        let response = self
+            .client
+            .get(&self.api_url)
+            .header("User-Agent", user_agent)
+            .query(&[("format", "json"), ("formatversion", "2")])
+            .query(query)
+            .send()
+            .map_err(|e| MetadataRetrievalError::RequestError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(MetadataRetrievalError::RequestError(format!(
+                "HTTP {} {}",
+                response.status().as_u16(),
+                response.status().canonical_reason().unwrap_or("Unknown")
+            )));
+        }
+
+        response
+            .json()
+            .map_err(|e| MetadataRetrievalError::ParseError(e.to_string()))
+    }
+}
+
+impl MetadataProvider for WikipediaProvider {
+    fn id(&self) -> String {
+        "wikipedia".to_string()
+    }
+
+    fn search_series(
+        &self,
+        series_name: &str,
+    ) -> Result<Vec<SeriesCandidate>, MetadataRetrievalError> {
+        let search = format!("List of {} episodes", series_name);
+        let response: WikipediaSearchResponse = self.get_json(&[
+            ("action", "query"),
+            ("list", "search"),
+            ("srsearch", &search),
+            ("srlimit", "20"),
+        ])?;
+
+        let candidates: Vec<SeriesCandidate> = response
+            .query
+            .map(|query| query.search)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|hit| {
+                let (name, year) = series_from_title(&hit.title)?;
+                Some(SeriesCandidate {
+                    id: hit.pageid,
+                    name,
+                    year,
+                })
+            })
+            .take(MAX_CANDIDATES)
+            .collect();
+
+        if candidates.is_empty() {
+            return Err(MetadataRetrievalError::SeriesNotFound(
+                series_name.to_string(),
+            ));
+        }
+
+        Ok(candidates)
+    }
+
+    fn fetch_series(
+        &self,
+        candidate: &SeriesCandidate,
+        season_numbers: Option<Vec<usize>>,
+    ) -> Result<TVSeries, MetadataRetrievalError> {
+        let page_id = candidate.id.to_string();
+        let response: WikipediaParseResponse =
+            self.get_json(&[("action", "parse"), ("pageid", &page_id), ("prop", "text")])?;
+        let page = response
+            .parse
+            .ok_or_else(|| MetadataRetrievalError::SeriesNotFound(candidate.name.clone()))?;
+
+        let seasons: Vec<Season> = parse_episode_tables(&page.text)
+            .into_iter()
+            .filter(|season| {
+                season_numbers
+                    .as_ref()
+                    .is_none_or(|filter| filter.contains(&season.season_number))
+            })
+            .collect();
+        if seasons.is_empty() && season_numbers.is_none() {
+            return Err(MetadataRetrievalError::InvalidData(format!(
+                "no episode tables in the Wikipedia article of {}",
+                candidate.name
+            )));
+        }
+
+        Ok(TVSeries {
+            name: candidate.name.clone(),
+            year: candidate.year,
+            seasons,
+        })
+    }
+}
+
+/// Reads the series name and year from the title of an episode list article.
+///
+/// "List of The Flash (1990 TV series) episodes" becomes "The Flash" from
+/// 1990. Returns None for other articles.
+fn series_from_title(title: &str) -> Option<(String, Option<u16>)> {
+    let name = title.strip_prefix("List of ")?.strip_suffix(" episodes")?;
+
+    if let Some((name, qualifier)) = name.split_once(" (")
+        && qualifier.ends_with(')')
+    {
+        let year = qualifier.get(..4).and_then(|year| year.parse().ok());
+        return Some((name.to_string(), year));
+    }
+    Some((name.to_string(), None))
+}
+
+/// Extracts the seasons from the HTML of an episode list article.
+///
+/// Episode tables (class `wikiepisodetable`) are assigned to the season
+/// named in the heading above them, like "Season 2 (1991)" or "Series 2";
+/// tables under "Specials" headings become season 0, and tables under
+/// other headings continue the season numbering.
+fn parse_episode_tables(html: &str) -> Vec<Season> {
+    let mut seasons: BTreeMap<usize, BTreeMap<usize, Episode>> = BTreeMap::new();
+    let mut last_season = 0;
+
+    for table in elements(html, &["table"]) {
+        if !has_class(table.attributes, "wikiepisodetable") {
+            continue;
+        }
+        let season_number = match preceding_heading(&html[..table.start]).as_deref() {
+            Some(heading) if heading.to_lowercase().contains("special") => 0,
+            Some(heading) => season_from_heading(heading).unwrap_or(last_season + 1),
+            None => last_season + 1,
+        };
+        if season_number > 0 {
+            last_season = season_number;
+        }
+
+        let episodes = seasons.entry(season_number).or_default();
+        for episode in parse_episode_rows(table.inner, season_number) {
+            episodes.entry(episode.episode_number).or_insert(episode);
+        }
+    }
+
+    seasons
+        .into_iter()
+        .filter(|(_, episodes)| !episodes.is_empty())
+        .map(|(season_number, episodes)| Season {
+            season_number,
+            episodes: episodes.into_values().collect(),
+        })
+        .collect()
+}
+
+/// Reads the episodes from the rows of an episode table.
+///
+/// Each episode has a row (class `vevent`) with its numbers, title (class
+/// `summary`), and air date (class `dtstart`), optionally followed by a
+/// row with its description (class `description`).
+fn parse_episode_rows(table: &str, season_number: usize) -> Vec<Episode> {
+    let mut episodes: Vec<Episode> = Vec::new();
+    let mut number_column = None;
+
+    for row in elements(table, &["tr"]) {
+        let cells = elements(row.inner, &["th", "td"]);
+
+        if has_class(row.attributes, "vevent") {
+            let number = cells
+                .get(number_column.unwrap_or(0))
+                .and_then(|cell| leading_number(&html_text(cell.inner)));
+            let title = cells
+                .iter()
+                .find(|cell| has_class(cell.attributes, "summary"))
+                .map(|cell| episode_title(&html_text(cell.inner)));
+            let (Some(episode_number), Some(name)) = (number, title) else {
+                continue;
+            };
+            episodes.push(Episode {
+                season_number,
+                episode_number,
+                name,
+                summary: String::new(),
+                summary_source: None,
+                airdate: air_date(row.inner),
+                runtime: None,
+                season_name: None,
+                provider_id: None,
+                local_title: None,
+            });
+        } else if has_class(row.attributes, "expand-child") {
+            let description = cells
+                .iter()
+                .find(|cell| has_class(cell.attributes, "description"))
+                .map(|cell| collapse_whitespace(&html_text(cell.inner)))
+                .filter(|description| !description.is_empty());
+            if let (Some(episode), Some(description)) = (episodes.last_mut(), description)
+                && episode.summary.is_empty()
+            {
+                episode.summary = description;
+                episode.summary_source = Some(SummarySource::Wikipedia);
+            }
+        } else if number_column.is_none() && cells.iter().all(|cell| cell.name == "th") {
+            number_column = episode_number_column(&cells);
+        }
+    }
+
+    episodes
+}
+
+/// Finds the column holding the episode number within the season
+///
+/// Tables of shows with several seasons number the episodes overall and
+/// within the season ("No. in season", or "No. in series" in British
+/// articles); single-season tables only have a "No." column.
+fn episode_number_column(header: &[Element]) -> Option<usize> {
+    let names: Vec<String> = header
+        .iter()
+        .map(|cell| collapse_whitespace(&html_text(cell.inner)).to_lowercase())
+        .collect();
+
+    names
+        .iter()
+        .position(|name| name.contains("in season") || name.contains("in series"))
+        .or_else(|| names.iter().position(|name| name.starts_with("no")))
+}
+
+/// Reads the season number from a heading like "Season 2 (1991)"
+fn season_from_heading(heading: &str) -> Option<usize> {
+    let words: Vec<&str> = heading.split_whitespace().collect();
+    words.windows(2).find_map(|pair| {
+        let keyword = pair[0].to_lowercase();
+        if keyword == "season" || keyword == "series" {
+            leading_number(pair[1])
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the text of the last heading in an HTML fragment
+fn preceding_heading(html: &str) -> Option<String> {
+    let start = ["<h2", "<h3", "<h4"]
+        .iter()
+        .filter_map(|tag| html.rfind(tag))
+        .max()?;
+    let heading = elements(&html[start..], &["h2", "h3", "h4"])
+        .into_iter()
+        .next()?;
+    Some(collapse_whitespace(&html_text(heading.inner)))
+}
+
+/// Reads the ISO date of the `dtstart` element in a row
+fn air_date(row: &str) -> Option<String> {
+    let marker = row.find("dtstart")?;
+    let start = marker + row[marker..].find('>')? + 1;
+    let end = start + row[start..].find('<')?;
+    let date = row[start..end].trim();
+
+    let is_iso_date = date.len() == 10
+        && date.chars().enumerate().all(|(i, c)| {
+            if i == 4 || i == 7 {
+                c == '-'
+            } else {
+                c.is_ascii_digit()
+            }
+        });
+    is_iso_date.then(|| date.to_string())
+}
+
+/// Cleans up the text of a title cell
+///
+/// Titles are quoted, and the cell may list alternative titles on further
+/// lines, of which only the first is kept.
+fn episode_title(text: &str) -> String {
+    let first_line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    let title = first_line.trim_matches(|c| matches!(c, '"' | '“' | '”'));
+    collapse_whitespace(title)
+}
+
+/// Parses the number at the start of a text, like 12 of "12a"
+fn leading_number(text: &str) -> Option<usize> {
+    let digits: String = text
+        .trim()
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+/// Joins the words of a text with single spaces
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// An HTML element found by `elements`
+struct Element<'a> {
+    /// Name of the element, like "td"
+    name: &'a str,
+    /// Byte offset of the opening tag in the searched HTML
+    start: usize,
+    /// The attributes of the opening tag
+    attributes: &'a str,
+    /// Everything between the opening and closing tag
+    inner: &'a str,
+}
+
+/// Finds the outermost elements of the given names, in document order
+///
+/// Only the nesting of elements with the same name is tracked, which is
+/// enough for the tables, rows, and cells of Wikipedia's generated HTML.
+/// Unclosed elements extend to the end of the fragment.
+fn elements<'a>(html: &'a str, names: &[&'a str]) -> Vec<Element<'a>> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+
+    while let Some((start, name)) = names
+        .iter()
+        .filter_map(|name| find_opening_tag(&html[offset..], name).map(|i| (offset + i, *name)))
+        .min_by_key(|(start, _)| *start)
+    {
+        let Some(tag_end) = html[start..].find('>').map(|i| start + i) else {
+            break;
+        };
+        let attributes = &html[start + 1 + name.len()..tag_end];
+        let inner_start = tag_end + 1;
+        let (inner_end, after) = closing_tag(&html[inner_start..], name)
+            .map(|(end, after)| (inner_start + end, inner_start + after))
+            .unwrap_or((html.len(), html.len()));
+
+        found.push(Element {
+            name,
+            start,
+            attributes: attributes.trim_end_matches('/'),
+            inner: &html[inner_start..inner_end],
+        });
+        offset = after;
+    }
+
+    found
+}
+
+/// Finds the next opening tag of an element, like `<td>` or `<td class="x">`
+fn find_opening_tag(html: &str, name: &str) -> Option<usize> {
+    let pattern = format!("<{}", name);
+    let mut offset = 0;
+    while let Some(i) = html[offset..].find(&pattern) {
+        let start = offset + i;
+        let next = html[start + pattern.len()..].chars().next();
+        if matches!(next, Some(c) if c == '>' || c == '/' || c.is_whitespace()) {
+            return Some(start);
+        }
+        offset = start + pattern.len();
+    }
+    None
+}
+
+/// Finds the closing tag matching an opened element
+///
+/// Returns the offsets of the closing tag's start and end in the HTML
+/// following the opening tag.
+fn closing_tag(html: &str, name: &str) -> Option<(usize, usize)> {
+    let closing = format!("</{}>", name);
+    let mut depth = 0;
+    let mut offset = 0;
+
+    loop {
+        let close = offset + html[offset..].find(&closing)?;
+        match find_opening_tag(&html[offset..close], name) {
+            Some(open) => {
+                depth += 1;
+                offset += open + 1;
+            }
+            None if depth == 0 => return Some((close, close + closing.len())),
+            None => {
+                depth -= 1;
+                offset = close + closing.len();
+            }
+        }
+    }
+}
+
+/// Checks whether an element's attributes include the given class
+fn has_class(attributes: &str, class: &str) -> bool {
+    let Some(start) = attributes.find("class=\"").map(|i| i + "class=\"".len()) else {
+        return false;
+    };
+    let classes = &attributes[start..];
+    let classes = &classes[..classes.find('"').unwrap_or(classes.len())];
+    classes.split_whitespace().any(|c| c == class)
+}
+
+/// Converts an HTML fragment to plain text
+///
+/// Line breaks become newlines, and footnote markers, edit links, and
+/// hidden sort keys are left out. Entities are decoded.
+fn html_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>').map(|i| start + i) else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        let name: String = tag
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        if name == "br" {
+            text.push('\n');
+        }
+        let hidden = ["reference", "mw-editsection", "sortkey"]
+            .iter()
+            .any(|class| has_class(tag, class))
+            || tag.contains("display:none");
+        if hidden && !name.is_empty() && !tag.ends_with('/') {
+            let after = closing_tag(rest, &name).map_or(rest.len(), |(_, after)| after);
+            rest = &rest[after..];
+        }
+    }
+    text.push_str(rest);
+
+    decode_entities(&text)
+}
+
+/// Decodes the HTML entities in a text
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#')?.parse().ok(),
+                };
+                code.and_then(char::from_u32)
+                    .map(|c| if c == '\u{a0}' { ' ' } else { c })
+            }
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = r##"
+        <div class="mw-heading mw-heading2"><h2 id="Series_overview">Series overview</h2></div>
+        <table class="wikitable plainrowheaders"><tr><th>Series</th><th>Episodes</th></tr></table>
+        <div class="mw-heading mw-heading3"><h3 id="Series_1_(1975)">Series 1 (1975)</h3><span class="mw-editsection">[<a href="#">edit</a>]</span></div>
+        <table class="wikitable plainrowheaders wikiepisodetable" style="width:100%">
+        <tbody><tr style="color:white"><th scope="col">No.<br />overall</th><th scope="col">No. in<br />series</th><th scope="col">Title</th><th scope="col">Original air date</th></tr>
+        <tr class="vevent module-episode-list-row" style="text-align:center"><th scope="row" id="ep1">1</th><td>1</td><td class="summary" style="text-align:left">"<a href="/wiki/A_Touch_of_Class">A Touch of Class</a>"<sup class="reference"><a href="#cite_note-1">[1]</a></sup></td><td>19&#160;September&#160;1975<span style="display:none">&#160;(<span class="bday dtstart published updated">1975-09-19</span>)</span></td></tr>
+        <tr class="expand-child"><td colspan="4" class="description">A guest &amp; his &quot;wife&quot;
+        cause trouble.</td></tr>
+        <tr class="vevent module-episode-list-row"><th scope="row">2</th><td>2</td><td class="summary">"The Builders"<br />"Die Handwerker"</td><td>TBA</td></tr>
+        </tbody></table>
+        <h3><span class="mw-headline" id="Specials">Specials</span></h3>
+        <table class="wikitable wikiepisodetable"><tr><th>No.</th><th>Title</th></tr>
+        <tr class="vevent"><td>1</td><td class="summary">“Christmas Special”</td></tr></table>
+        <h3>Episodes in colour</h3>
+        <table class="wikitable wikiepisodetable"><tr><th>No.</th><th>Title</th></tr>
+        <tr class="vevent"><td>1</td><td class="summary">"Basil the Rat"</td></tr></table>
+    "##;
+
+    #[test]
+    fn test_parse_episode_tables() {
+        let seasons = parse_episode_tables(ARTICLE);
+
+        let numbers: Vec<usize> = seasons.iter().map(|s| s.season_number).collect();
+        assert_eq!(numbers, vec![0, 1, 2]);
+        assert_eq!(seasons[0].episodes[0].name, "Christmas Special");
+
+        let first = &seasons[1].episodes[0];
+        assert_eq!(first.episode_number, 1);
+        assert_eq!(first.name, "A Touch of Class");
+        assert_eq!(first.airdate.as_deref(), Some("1975-09-19"));
+        assert_eq!(first.summary, "A guest & his \"wife\" cause trouble.");
+        assert_eq!(first.summary_source, Some(SummarySource::Wikipedia));
+
+        let second = &seasons[1].episodes[1];
+        assert_eq!(second.episode_number, 2);
+        assert_eq!(second.name, "The Builders");
+        assert_eq!(second.airdate, None);
+        assert_eq!(second.summary_source, None);
+
+        assert_eq!(seasons[2].episodes[0].name, "Basil the Rat");
+    }
+
+    #[test]
+    fn test_series_from_title() {
+        assert_eq!(
+            series_from_title("List of Fawlty Towers episodes"),
+            Some(("Fawlty Towers".to_string(), None))
+        );
+        assert_eq!(
+            series_from_title("List of The Flash (1990 TV series) episodes"),
+            Some(("The Flash".to_string(), Some(1990)))
+        );
+        assert_eq!(
+            series_from_title("List of Doctor Who (season 1) episodes"),
+            Some(("Doctor Who".to_string(), None))
+        );
+        assert_eq!(series_from_title("Fawlty Towers"), None);
+    }
+
+    #[test]
+    fn test_html_text() {
+        assert_eq!(
+            html_text("\"<a href=\"/x\">Tom &amp; Jerry</a>\"<sup class=\"reference\">[1]</sup>"),
+            "\"Tom & Jerry\""
+        );
+        assert_eq!(
+            html_text(
+                "<span class=\"mw-editsection\"><span>[</span>edit<span>]</span></span>Season&#160;1"
+            ),
+            "Season 1"
+        );
+        assert_eq!(
+            decode_entities("Q&amp;A &#x27;&unknown; & more"),
+            "Q&A '&unknown; & more"
+        );
+    }
+}
@@ -0,0 +1,47 @@
+/// Wikipedia API response types for deserialization.
+///
+/// These structures mirror the subset of the MediaWiki action API JSON
+/// (with `formatversion=2`) needed to find and read episode list pages.
+use serde::Deserialize;
+
+// =========================================================
+// Search types (action=query&list=search)
+// =========================================================
+
+/// Response of a full-text search.
+#[derive(Debug, Deserialize)]
+pub(super) struct WikipediaSearchResponse {
+    /// Absent if the search failed
+    pub query: Option<WikipediaSearchQuery>,
+}
+
+/// The results of a full-text search.
+#[derive(Debug, Deserialize)]
+pub(super) struct WikipediaSearchQuery {
+    pub search: Vec<WikipediaSearchHit>,
+}
+
+/// A page found by a full-text search, sorted by relevance.
+#[derive(Debug, Deserialize)]
+pub(super) struct WikipediaSearchHit {
+    pub title: String,
+    pub pageid: u64,
+}
+
+// =========================================================
+// Parse types (action=parse&prop=text)
+// =========================================================
+
+/// Response of rendering a page.
+#[derive(Debug, Deserialize)]
+pub(super) struct WikipediaParseResponse {
+    /// Absent if the page doesn't exist
+    pub parse: Option<WikipediaPage>,
+}
+
+/// A rendered page.
+#[derive(Debug, Deserialize)]
+pub(super) struct WikipediaPage {
+    /// The page's content as HTML
+    pub text: String,
+}
@@ -0,0 +1,463 @@
+//! Metrics module
+//!
+//! Collects counters from the progress events of an investigation and
+//! renders them in the Prometheus text format. A single run writes them to a
+//! file for the textfile collector of the Prometheus node exporter, which
+//! picks up `*.prom` files from a directory. The queue runner in watch mode
+//! lives long enough to be scraped, so it can serve them at `/metrics`
+//! instead. The same counters make up the `RunSummary` shown after a run.
+
+use crate::{DialogDetectiveError, MatchResult, ProgressEvent, Stage};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write as _};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Stages in the order they are listed in the metrics
+const STAGES: [(Stage, &str); 4] = [
+    (Stage::Hashing, "hashing"),
+    (Stage::AudioExtraction, "audio_extraction"),
+    (Stage::Transcription, "transcription"),
+    (Stage::Matching, "matching"),
+];
+
+/// Counters of an investigation, updated from its progress events
+#[derive(Debug, Clone, Default)]
+pub struct RunMetrics {
+    videos_found: usize,
+    videos_processed: usize,
+    transcripts_cached: usize,
+    transcripts_created: usize,
+    matches_cached: usize,
+    matches_created: usize,
+    hash_matches: usize,
+    matches_queued: usize,
+    matcher_failures: usize,
+    stage_seconds: [f64; STAGES.len()],
+    confidence_sum: f64,
+    confidence_count: usize,
+    bytes_written: u64,
+    last_progress: Option<SystemTime>,
+    finished: Option<bool>,
+    queued_jobs: Option<usize>,
+}
+
+/// Overview of a run, e.g. to print after the investigation
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    /// Video files processed
+    pub videos_processed: usize,
+
+    /// Transcripts loaded from the cache
+    pub transcripts_cached: usize,
+
+    /// Transcripts created with the transcriber
+    pub transcripts_created: usize,
+
+    /// Matches loaded from the cache
+    pub matches_cached: usize,
+
+    /// Matches made by the matcher
+    pub matches_created: usize,
+
+    /// Videos identified by their file hash, without transcription or matching
+    pub hash_matches: usize,
+
+    /// Wall-clock time spent transcribing
+    pub transcription_time: Duration,
+
+    /// Mean confidence of the matches that reported one
+    pub average_confidence: Option<f64>,
+
+    /// Matcher calls that failed
+    pub matcher_failures: usize,
+
+    /// Bytes written by copying files
+    pub bytes_written: u64,
+}
+
+impl RunSummary {
+    /// Share of transcripts loaded from the cache, None if there were none
+    pub fn transcript_hit_rate(&self) -> Option<f64> {
+        hit_rate(
+            self.transcripts_cached,
+            self.transcripts_cached + self.transcripts_created,
+        )
+    }
+
+    /// Share of matches that didn't need the matcher, None if there were none
+    ///
+    /// Matches from the cache and by file hash both count as hits.
+    pub fn match_hit_rate(&self) -> Option<f64> {
+        let hits = self.matches_cached + self.hash_matches;
+        hit_rate(hits, hits + self.matches_created)
+    }
+}
+
+/// Divides hits by a total, None for a total of zero
+fn hit_rate(hits: usize, total: usize) -> Option<f64> {
+    (total > 0).then(|| hits as f64 / total as f64)
+}
+
+impl RunMetrics {
+    /// Creates empty metrics for a new run
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the counters from a progress event
+    pub fn record(&mut self, event: &ProgressEvent) {
+        self.last_progress = Some(SystemTime::now());
+        match event {
+            ProgressEvent::VideosFound { count } => self.videos_found = *count,
+            ProgressEvent::ProcessingVideo { .. } => self.videos_processed += 1,
+            ProgressEvent::TranscriptCacheHit { .. } => self.transcripts_cached += 1,
+            ProgressEvent::TranscriptionFinished { .. } => self.transcripts_created += 1,
+            ProgressEvent::HashMatched { .. } => self.hash_matches += 1,
+            ProgressEvent::MatchingQueued { .. } => self.matches_queued += 1,
+            ProgressEvent::MatchingFinished { .. } => self.matches_created += 1,
+            ProgressEvent::MatchingCacheHit { .. } => self.matches_cached += 1,
+            ProgressEvent::StageCompleted { stage, duration } => {
+                let index = STAGES.iter().position(|(s, _)| s == stage);
+                if let Some(index) = index {
+                    self.stage_seconds[index] += duration.as_secs_f64();
+                }
+            }
+            ProgressEvent::Complete { .. } => self.finished = Some(true),
+            _ => {}
+        }
+    }
+
+    /// Records that the investigation failed
+    pub fn record_failure(&mut self, error: &DialogDetectiveError) {
+        self.record_failed_video(error);
+        self.finished = Some(false);
+    }
+
+    /// Records that a video failed while the investigation went on
+    pub fn record_failed_video(&mut self, error: &DialogDetectiveError) {
+        if matches!(error, DialogDetectiveError::EpisodeMatching(_)) {
+            self.matcher_failures += 1;
+        }
+    }
+
+    /// Records the confidences of matches for the average confidence
+    pub fn record_matches(&mut self, matches: &[MatchResult]) {
+        for confidence in matches.iter().filter_map(|m| m.confidence) {
+            self.confidence_sum += confidence;
+            self.confidence_count += 1;
+        }
+    }
+
+    /// Records bytes written to disk, e.g. by copying a file
+    pub fn record_written(&mut self, bytes: u64) {
+        self.bytes_written += bytes;
+    }
+
+    /// Sets the number of jobs waiting in the queue
+    ///
+    /// Only the queue runner knows this, so the gauge is left out until set.
+    pub fn set_queued_jobs(&mut self, count: usize) {
+        self.queued_jobs = Some(count);
+    }
+
+    /// Returns the overview of the run so far
+    pub fn summary(&self) -> RunSummary {
+        let transcription = STAGES
+            .iter()
+            .position(|(stage, _)| *stage == Stage::Transcription)
+            .map_or(0.0, |index| self.stage_seconds[index]);
+
+        RunSummary {
+            videos_processed: self.videos_processed,
+            transcripts_cached: self.transcripts_cached,
+            transcripts_created: self.transcripts_created,
+            matches_cached: self.matches_cached,
+            matches_created: self.matches_created,
+            hash_matches: self.hash_matches,
+            transcription_time: Duration::from_secs_f64(transcription),
+            average_confidence: (self.confidence_count > 0)
+                .then(|| self.confidence_sum / self.confidence_count as f64),
+            matcher_failures: self.matcher_failures,
+            bytes_written: self.bytes_written,
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, f64)]| {
+            let _ = writeln!(out, "# HELP dialog_detective_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE dialog_detective_{} {}", name, kind);
+            for (labels, value) in samples {
+                let _ = writeln!(out, "dialog_detective_{}{} {}", name, labels, value);
+            }
+        };
+
+        metric(
+            "videos_found",
+            "gauge",
+            "Video files found in the directory.",
+            &[("", self.videos_found as f64)],
+        );
+        metric(
+            "videos_processed_total",
+            "counter",
+            "Video files processed.",
+            &[("", self.videos_processed as f64)],
+        );
+        metric(
+            "cache_hits_total",
+            "counter",
+            "Results loaded from a cache or identified by file hash.",
+            &[
+                ("{stage=\"transcription\"}", self.transcripts_cached as f64),
+                ("{stage=\"matching\"}", self.matches_cached as f64),
+                ("{stage=\"hash\"}", self.hash_matches as f64),
+            ],
+        );
+        metric(
+            "cache_misses_total",
+            "counter",
+            "Results that had to be computed.",
+            &[
+                ("{stage=\"transcription\"}", self.transcripts_created as f64),
+                ("{stage=\"matching\"}", self.matches_created as f64),
+            ],
+        );
+        metric(
+            "matcher_failures_total",
+            "counter",
+            "Matcher calls that failed the run.",
+            &[("", self.matcher_failures as f64)],
+        );
+        metric(
+            "matching_queue_depth",
+            "gauge",
+            "Videos waiting for the matcher.",
+            &[(
+                "",
+                self.matches_queued.saturating_sub(self.matches_created) as f64,
+            )],
+        );
+        let stage_labels: Vec<String> = STAGES
+            .iter()
+            .map(|(_, label)| format!("{{stage=\"{}\"}}", label))
+            .collect();
+        let stage_samples: Vec<(&str, f64)> = stage_labels
+            .iter()
+            .map(String::as_str)
+            .zip(self.stage_seconds)
+            .collect();
+        metric(
+            "stage_seconds_total",
+            "counter",
+            "Wall-clock seconds spent in each processing stage.",
+            &stage_samples,
+        );
+        metric(
+            "last_progress_timestamp_seconds",
+            "gauge",
+            "Time of the last progress event.",
+            &[("", unix_seconds(self.last_progress))],
+        );
+        metric(
+            "run_finished",
+            "gauge",
+            "Whether the investigation finished (1 succeeded, 0 failed, -1 running).",
+            &[(
+                "",
+                self.finished.map_or(-1.0, |ok| if ok { 1.0 } else { 0.0 }),
+            )],
+        );
+        if let Some(queued_jobs) = self.queued_jobs {
+            metric(
+                "queued_jobs",
+                "gauge",
+                "Jobs waiting in the queue.",
+                &[("", queued_jobs as f64)],
+            );
+        }
+        out
+    }
+
+    /// Writes the metrics to a file, replacing it atomically
+    ///
+    /// The textfile collector may read the file at any time, so it is
+    /// written next to the target and renamed into place.
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        fs::write(&temp, self.to_prometheus())?;
+        fs::rename(&temp, path)
+    }
+}
+
+/// Serves the metrics at `/metrics` for Prometheus to scrape
+///
+/// Binds to the address, e.g. `127.0.0.1:9184`, and answers requests on a
+/// background thread for as long as the program runs. Scrapes are rare, so
+/// they are answered one at a time. Returns the bound address, which tells
+/// the port when binding to port 0.
+pub fn serve_metrics(address: &str, metrics: Arc<Mutex<RunMetrics>>) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(address)?;
+    let local_address = listener.local_addr()?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A client that hangs up early is no reason to stop serving
+            let _ = answer_scrape(stream, &metrics);
+        }
+    });
+    Ok(local_address)
+}
+
+/// Answers a single HTTP request with the metrics or a 404
+fn answer_scrape(stream: TcpStream, metrics: &Mutex<RunMetrics>) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers, the request line is all that matters
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+    let (status, body) = match (method, path) {
+        ("GET" | "HEAD", "/metrics") => (
+            "200 OK",
+            metrics
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .to_prometheus(),
+        ),
+        _ => ("404 Not Found", "Not found, try /metrics\n".to_string()),
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    if method != "HEAD" {
+        response.push_str(&body);
+    }
+    (&stream).write_all(response.as_bytes())
+}
+
+/// Converts a time to seconds since the Unix epoch (0 if unknown)
+fn unix_seconds(time: Option<SystemTime>) -> f64 {
+    time.and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(0.0, |d| d.as_secs() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_run_metrics_to_prometheus() {
+        let video_path = PathBuf::from("a.mkv");
+        let mut metrics = RunMetrics::new();
+        for event in [
+            ProgressEvent::VideosFound { count: 2 },
+            ProgressEvent::TranscriptCacheHit {
+                video_path: video_path.clone(),
+                language: "en".to_string(),
+            },
+            ProgressEvent::MatchingQueued {
+                video_path: video_path.clone(),
+            },
+            ProgressEvent::StageCompleted {
+                stage: Stage::Transcription,
+                duration: Duration::from_millis(1500),
+            },
+        ] {
+            metrics.record(&event);
+        }
+
+        let text = metrics.to_prometheus();
+        assert!(text.contains("# TYPE dialog_detective_videos_found gauge\n"));
+        assert!(text.contains("dialog_detective_videos_found 2\n"));
+        assert!(text.contains("dialog_detective_cache_hits_total{stage=\"transcription\"} 1\n"));
+        assert!(text.contains("dialog_detective_matching_queue_depth 1\n"));
+        assert!(
+            text.contains("dialog_detective_stage_seconds_total{stage=\"transcription\"} 1.5\n")
+        );
+        assert!(text.contains("dialog_detective_run_finished -1\n"));
+
+        metrics.record(&ProgressEvent::Complete { match_count: 1 });
+        assert!(
+            metrics
+                .to_prometheus()
+                .contains("dialog_detective_run_finished 1\n")
+        );
+    }
+
+    #[test]
+    fn test_serve_metrics() {
+        let metrics = Arc::new(Mutex::new(RunMetrics::new()));
+        let address = serve_metrics("127.0.0.1:0", Arc::clone(&metrics)).unwrap();
+        metrics.lock().unwrap().set_queued_jobs(3);
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            io::Read::read_to_string(&mut stream, &mut response).unwrap();
+            response
+        };
+
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+        assert!(response.contains("\r\n\r\n# HELP dialog_detective_videos_found "));
+        assert!(response.ends_with("dialog_detective_queued_jobs 3\n"));
+
+        assert!(get("/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn test_run_summary() {
+        let video_path = PathBuf::from("a.mkv");
+        let mut metrics = RunMetrics::new();
+        assert_eq!(metrics.summary().transcript_hit_rate(), None);
+
+        for event in [
+            ProgressEvent::TranscriptCacheHit {
+                video_path: video_path.clone(),
+                language: "en".to_string(),
+            },
+            ProgressEvent::TranscriptionFinished {
+                video_path: video_path.clone(),
+                language: "en".to_string(),
+                text: "Say my name.".to_string(),
+            },
+            ProgressEvent::StageCompleted {
+                stage: Stage::Transcription,
+                duration: Duration::from_secs(90),
+            },
+        ] {
+            metrics.record(&event);
+        }
+        metrics.record_written(1_000);
+        metrics.record_written(500);
+
+        let summary = metrics.summary();
+        assert_eq!(summary.transcript_hit_rate(), Some(0.5));
+        assert_eq!(summary.match_hit_rate(), None);
+        assert_eq!(summary.transcription_time, Duration::from_secs(90));
+        assert_eq!(summary.average_confidence, None);
+        assert_eq!(summary.bytes_written, 1_500);
+    }
+}
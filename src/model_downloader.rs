@@ -2,12 +2,28 @@
 //!
 //! This module handles automatic downloading and caching of Whisper GGML models
 //! from Hugging Face. Models are stored in the system's standard cache directory
-//! and reused across runs.
+//! and reused across runs. Using a model updates the modification time of
+//! its file, so models that haven't been used for a long time can be pruned.
+//!
+//! Networks without access to Hugging Face can point the downloads to a
+//! mirror or a local directory with the same file names through the
+//! `DIALOG_DETECTIVE_MODEL_MIRROR` environment variable.
+//!
+//! Builds with the `coreml` feature on macOS also fetch the Core ML encoder
+//! of each model. whisper.cpp looks for it next to the model file and runs
+//! the encoder on the Apple Neural Engine, which is several times faster.
 
+use crate::file_lock::{FileLock, lock_path};
 use humansize::{BINARY, format_size};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::fmt;
 use std::fs;
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
 /// Errors that can occur during model download operations
@@ -42,6 +58,46 @@ pub enum ModelDownloadError {
     /// HTTP error during download
     #[error("HTTP error downloading model: {0}")]
     HttpError(String),
+
+    /// Failed to unpack the downloaded Core ML encoder
+    #[error("Failed to extract Core ML encoder {path}: {reason}")]
+    ExtractionFailed { path: PathBuf, reason: String },
+
+    /// Failed to read a model from a local mirror directory
+    #[error("Failed to read {path} from the model mirror: {source}")]
+    MirrorReadFailed {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// Failed to remove a model from the cache
+    #[error("Failed to remove {path}: {source}")]
+    RemoveFailed {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// The model would have to be downloaded, but downloads are disabled
+    #[error("Model '{model_name}' is not cached and downloads are disabled")]
+    NotCached { model_name: String },
+}
+
+impl ModelDownloadError {
+    /// Returns the stable code of the error, like `DD-MOD-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ModelDownloadError::CacheDirectoryNotFound => "DD-MOD-001",
+            ModelDownloadError::DirectoryCreationFailed { .. } => "DD-MOD-002",
+            ModelDownloadError::DownloadFailed { .. } => "DD-MOD-003",
+            ModelDownloadError::WriteFailed { .. } => "DD-MOD-004",
+            ModelDownloadError::InvalidModel { .. } => "DD-MOD-005",
+            ModelDownloadError::HttpError(_) => "DD-MOD-006",
+            ModelDownloadError::NotCached { .. } => "DD-MOD-007",
+            ModelDownloadError::ExtractionFailed { .. } => "DD-MOD-008",
+            ModelDownloadError::MirrorReadFailed { .. } => "DD-MOD-009",
+            ModelDownloadError::RemoveFailed { .. } => "DD-MOD-010",
+        }
+    }
 }
 
 /// Supported Whisper model names available from Hugging Face
@@ -64,6 +120,7 @@ pub const SUPPORTED_MODELS: &[&str] = &[
     "small-q5_1",
     "small.en-q5_1",
     "small-q8_0",
+    "small.en-tdrz",
     "medium",
     "medium.en",
     "medium-q5_0",
@@ -80,13 +137,286 @@ pub const SUPPORTED_MODELS: &[&str] = &[
     "large-v3-turbo-q8_0",
 ];
 
+/// Model used when none is selected
+pub const DEFAULT_MODEL: &str = "base";
+
 /// Base URL for Whisper models on Hugging Face
 const MODEL_BASE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
 
+/// Environment variable selecting where models are downloaded from
+pub const MODEL_MIRROR_ENV: &str = "DIALOG_DETECTIVE_MODEL_MIRROR";
+
 /// Minimum expected size for a model file (in bytes)
 /// This helps detect incomplete downloads or corrupted files
 const MIN_MODEL_SIZE: u64 = 1024 * 1024; // 1MB minimum
 
+/// Whether models are used with a Core ML encoder
+const COREML_ENCODERS: bool = cfg!(all(target_os = "macos", feature = "coreml"));
+
+/// Magic number at the start of GGML model files ("ggml" as little-endian u32)
+const GGML_MAGIC: [u8; 4] = [0x6c, 0x6d, 0x67, 0x67];
+
+/// Progress of a model download
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status")]
+pub enum DownloadProgress {
+    /// The model isn't cached and is being downloaded
+    Started {
+        model_name: String,
+        /// Where the model is downloaded from, like "Hugging Face" or a mirror URL
+        source: String,
+    },
+
+    /// Another percent of the model was downloaded
+    ///
+    /// Not reported if the server doesn't tell the size of the model.
+    Progress { percent: u32 },
+
+    /// Another process is downloading the model, and its download is awaited
+    Waiting { model_name: String },
+
+    /// The download completed
+    Downloaded,
+
+    /// The model was verified and stored in the cache
+    Cached { path: PathBuf },
+}
+
+/// Where models are downloaded from
+///
+/// Mirrors have to provide the files under the names Hugging Face uses,
+/// like `ggml-base.bin`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ModelSource {
+    /// The whisper.cpp repository on Hugging Face
+    #[default]
+    HuggingFace,
+
+    /// An HTTP server mirroring the repository, given by its base URL
+    Mirror(String),
+
+    /// A local or network directory holding the model files
+    ///
+    /// The files are copied into the cache like downloaded ones.
+    Directory(PathBuf),
+}
+
+impl ModelSource {
+    /// Reads the source from `DIALOG_DETECTIVE_MODEL_MIRROR`
+    ///
+    /// Values starting with `http://` or `https://` are mirror URLs,
+    /// anything else is a directory (optionally as a `file://` URL). Unset
+    /// or empty, models come from Hugging Face.
+    pub fn from_env() -> Self {
+        std::env::var(MODEL_MIRROR_ENV)
+            .map(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// Parses a mirror URL or directory, see `from_env`
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() {
+            ModelSource::HuggingFace
+        } else if value.starts_with("http://") || value.starts_with("https://") {
+            ModelSource::Mirror(value.trim_end_matches('/').to_string())
+        } else {
+            let path = value.strip_prefix("file://").unwrap_or(value);
+            ModelSource::Directory(PathBuf::from(path))
+        }
+    }
+
+    /// Opens a file of the source, returning it with its size if known
+    fn open(&self, file_name: &str) -> Result<(Box<dyn Read>, Option<u64>), ModelDownloadError> {
+        let base_url = match self {
+            ModelSource::HuggingFace => MODEL_BASE_URL,
+            ModelSource::Mirror(base_url) => base_url.as_str(),
+            ModelSource::Directory(dir) => {
+                let path = dir.join(file_name);
+                let read_failed = |source| ModelDownloadError::MirrorReadFailed {
+                    path: path.clone(),
+                    source,
+                };
+                let file = fs::File::open(&path).map_err(read_failed)?;
+                let size = file.metadata().map_err(read_failed)?.len();
+                return Ok((Box::new(file), Some(size)));
+            }
+        };
+        let url = format!("{}/{}", base_url, file_name);
+
+        // Create a blocking HTTP client
+        let client = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(600)) // 10 minute timeout
+            .build()
+            .map_err(|e| ModelDownloadError::DownloadFailed {
+                url: url.clone(),
+                source: e,
+            })?;
+
+        // Start the download
+        let response = client
+            .get(&url)
+            .send()
+            .map_err(|e| ModelDownloadError::DownloadFailed {
+                url: url.clone(),
+                source: e,
+            })?;
+
+        // Check HTTP status
+        if !response.status().is_success() {
+            return Err(ModelDownloadError::HttpError(format!(
+                "HTTP {} while downloading model from {}",
+                response.status(),
+                url
+            )));
+        }
+
+        // Get content length for progress reporting
+        let total_size = response.content_length();
+        Ok((Box::new(response), total_size))
+    }
+}
+
+impl fmt::Display for ModelSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelSource::HuggingFace => write!(f, "Hugging Face"),
+            ModelSource::Mirror(base_url) => write!(f, "{}", base_url),
+            ModelSource::Directory(dir) => write!(f, "{}", dir.display()),
+        }
+    }
+}
+
+/// The Whisper model to transcribe with
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ModelSpec {
+    /// A model file on disk, used as it is
+    Path(PathBuf),
+
+    /// One of the `SUPPORTED_MODELS`, downloaded if it isn't cached
+    Named(String),
+
+    /// The default model if it is cached, else the largest cached model
+    ///
+    /// Downloads the default model if no model is cached yet.
+    #[default]
+    Auto,
+}
+
+impl ModelSpec {
+    /// Returns the path of the model file, downloading the model if necessary
+    ///
+    /// Download progress is reported to the callback, which isn't called
+    /// if the model is already available.
+    pub fn resolve_with<F>(&self, progress: F) -> Result<PathBuf, ModelDownloadError>
+    where
+        F: FnMut(DownloadProgress),
+    {
+        match self {
+            ModelSpec::Path(path) => Ok(path.clone()),
+            ModelSpec::Named(name) => ensure_model_available_with(name, progress),
+            ModelSpec::Auto => {
+                let name = cached_auto_model()?.unwrap_or(DEFAULT_MODEL);
+                ensure_model_available_with(name, progress)
+            }
+        }
+    }
+
+    /// Returns the path of the model file without downloading anything
+    ///
+    /// Fails with `ModelDownloadError::NotCached` if a named model isn't
+    /// cached, or, for `ModelSpec::Auto`, if no model is cached at all.
+    pub fn resolve_cached(&self) -> Result<PathBuf, ModelDownloadError> {
+        let name = match self {
+            ModelSpec::Path(path) => return Ok(path.clone()),
+            ModelSpec::Named(name) => name.as_str(),
+            ModelSpec::Auto => cached_auto_model()?.unwrap_or(DEFAULT_MODEL),
+        };
+
+        let path = cached_model_path(name)?;
+        if validate_model_file(&path).is_err()
+            || (COREML_ENCODERS
+                && !is_tinydiarize_model(name)
+                && !coreml_encoder_path(&path).is_dir())
+        {
+            return Err(ModelDownloadError::NotCached {
+                model_name: name.to_string(),
+            });
+        }
+        mark_used(&path);
+        Ok(path)
+    }
+}
+
+impl FromStr for ModelSpec {
+    type Err = Infallible;
+
+    /// Parses a model name, `auto`, or the path of a model file
+    ///
+    /// Supported model names win over files of the same name in the working
+    /// directory. Anything containing a path separator, ending in `.bin`, or
+    /// naming an existing file is a path; the rest is taken as a model name.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value == "auto" {
+            return Ok(ModelSpec::Auto);
+        }
+        if SUPPORTED_MODELS.contains(&value) {
+            return Ok(ModelSpec::Named(value.to_string()));
+        }
+
+        let path = Path::new(value);
+        let is_path = value.contains(['/', std::path::MAIN_SEPARATOR])
+            || value.ends_with(".bin")
+            || path.is_file();
+        Ok(if is_path {
+            ModelSpec::Path(path.to_path_buf())
+        } else {
+            ModelSpec::Named(value.to_string())
+        })
+    }
+}
+
+impl fmt::Display for ModelSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelSpec::Path(path) => write!(f, "{}", path.display()),
+            ModelSpec::Named(name) => write!(f, "{}", name),
+            ModelSpec::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+impl From<&Path> for ModelSpec {
+    fn from(path: &Path) -> Self {
+        ModelSpec::Path(path.to_path_buf())
+    }
+}
+
+impl From<PathBuf> for ModelSpec {
+    fn from(path: PathBuf) -> Self {
+        ModelSpec::Path(path)
+    }
+}
+
+/// Picks the model `ModelSpec::Auto` resolves to among the cached ones
+///
+/// Models later in `SUPPORTED_MODELS` are larger, so the last valid one
+/// wins unless the default model is cached.
+fn cached_auto_model() -> Result<Option<&'static str>, ModelDownloadError> {
+    let cache_dir = get_model_cache_dir()?;
+    let cached: Vec<&'static str> = SUPPORTED_MODELS
+        .iter()
+        .copied()
+        .filter(|name| validate_model_file(&cache_dir.join(format!("ggml-{}.bin", name))).is_ok())
+        .collect();
+
+    Ok(if cached.contains(&DEFAULT_MODEL) {
+        Some(DEFAULT_MODEL)
+    } else {
+        cached.last().copied()
+    })
+}
+
 /// Ensures a Whisper model is available, downloading it if necessary
 ///
 /// This function checks if the specified model exists in the cache directory.
@@ -108,6 +438,34 @@ const MIN_MODEL_SIZE: u64 = 1024 * 1024; // 1MB minimum
 /// println!("Model ready at: {}", model_path.display());
 /// ```
 pub fn ensure_model_available(model_name: &str) -> Result<PathBuf, ModelDownloadError> {
+    ensure_model_available_with(model_name, print_download_progress)
+}
+
+/// Ensures a Whisper model is available, reporting download progress to a callback
+///
+/// Like `ensure_model_available`, but leaves reporting the download to the
+/// caller instead of printing it to stdout. The callback isn't called if
+/// the model is already cached. Models are downloaded from the source
+/// configured with `DIALOG_DETECTIVE_MODEL_MIRROR`.
+pub fn ensure_model_available_with<F>(
+    model_name: &str,
+    progress: F,
+) -> Result<PathBuf, ModelDownloadError>
+where
+    F: FnMut(DownloadProgress),
+{
+    ensure_model_available_from(model_name, &ModelSource::from_env(), progress)
+}
+
+/// Ensures a Whisper model is available, downloading it from the given source
+pub fn ensure_model_available_from<F>(
+    model_name: &str,
+    source: &ModelSource,
+    mut progress: F,
+) -> Result<PathBuf, ModelDownloadError>
+where
+    F: FnMut(DownloadProgress),
+{
     // Validate model name
     if !SUPPORTED_MODELS.contains(&model_name) {
         return Err(ModelDownloadError::InvalidModel {
@@ -119,91 +477,198 @@ pub fn ensure_model_available(model_name: &str) -> Result<PathBuf, ModelDownload
         });
     }
 
-    // Get the cache directory for models
-    let cache_dir = get_model_cache_dir()?;
-    let model_path = cache_dir.join(format!("ggml-{}.bin", model_name));
+    let model_path = cached_model_path(model_name)?;
 
     // Check if model already exists and is valid
-    if model_path.exists() {
-        // Verify the file has a reasonable size
-        match fs::metadata(&model_path) {
-            Ok(metadata) => {
-                let size = metadata.len();
-                if size >= MIN_MODEL_SIZE {
-                    // Model exists and looks valid
-                    return Ok(model_path);
-                } else {
-                    // File is too small, probably corrupted - remove and re-download
-                    let _ = fs::remove_file(&model_path);
-                }
-            }
-            Err(_) => {
-                // Can't read metadata, remove and re-download
-                let _ = fs::remove_file(&model_path);
-            }
+    if !is_downloaded(&model_path) {
+        let _lock = lock_download(&model_path, model_name, &mut progress)?;
+        // Another process may have downloaded the model while we waited
+        if !is_downloaded(&model_path) {
+            // Missing or too small, probably corrupted - remove and re-download
+            let _ = fs::remove_file(&model_path);
+            download_model(model_name, source, &model_path, &mut progress)?;
         }
     }
 
-    // Model doesn't exist or is invalid - download it
-    download_model(model_name, &model_path)?;
+    if COREML_ENCODERS && !is_tinydiarize_model(model_name) {
+        ensure_coreml_encoder(model_name, source, &model_path, &mut progress)?;
+    }
 
+    mark_used(&model_path);
     Ok(model_path)
 }
 
-/// Downloads a Whisper model from Hugging Face
+/// Checks whether a model file exists and has a reasonable size
+fn is_downloaded(model_path: &Path) -> bool {
+    fs::metadata(model_path).is_ok_and(|metadata| metadata.len() >= MIN_MODEL_SIZE)
+}
+
+/// Locks a file against downloads by other processes
 ///
-/// This function performs the actual HTTP download with progress reporting
-/// and saves the model to the specified path.
+/// Waits while another process holds the lock, reporting
+/// `DownloadProgress::Waiting` once. The lock is held until dropped and is
+/// released by the OS if the process crashes.
+fn lock_download(
+    target_path: &Path,
+    model_name: &str,
+    progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<FileLock, ModelDownloadError> {
+    let path = lock_path(target_path);
+    let on_wait = || {
+        progress(DownloadProgress::Waiting {
+            model_name: model_name.to_string(),
+        })
+    };
+    match FileLock::acquire(&path, None, on_wait) {
+        Ok(Some(lock)) => Ok(lock),
+        Ok(None) => unreachable!("locks without a timeout are always acquired"),
+        Err(source) => Err(ModelDownloadError::WriteFailed { path, source }),
+    }
+}
+
+/// Records that a cached model is used by touching its modification time
+///
+/// Failures are ignored, as they only make the model look older to
+/// `stale_models`.
+fn mark_used(model_path: &Path) {
+    if let Ok(file) = fs::File::options().write(true).open(model_path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// Checks whether a model is a tinydiarize model detecting speaker changes
+///
+/// These models (like `small.en-tdrz`) mark where the speaker changes in
+/// their output. Hugging Face hosts no Core ML encoders for them.
+pub fn is_tinydiarize_model(model_name: &str) -> bool {
+    model_name.ends_with("-tdrz")
+}
+
+/// Returns the path whisper.cpp looks for the Core ML encoder of a model at
+///
+/// Quantized models share the encoder of the model they were made from:
+/// both `ggml-base.bin` and `ggml-base-q8_0.bin` use
+/// `ggml-base-encoder.mlmodelc`.
+pub fn coreml_encoder_path(model_path: &Path) -> PathBuf {
+    let stem = model_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let base = match stem.rsplit_once('-') {
+        Some((base, suffix)) if is_quantization(suffix) => base,
+        _ => stem.as_str(),
+    };
+    model_path.with_file_name(format!("{}-encoder.mlmodelc", base))
+}
+
+/// Checks for quantization suffixes like `q5_0` or `q8_0`
+fn is_quantization(suffix: &str) -> bool {
+    let bytes = suffix.as_bytes();
+    bytes.len() == 4 && bytes[0] == b'q' && bytes[2] == b'_'
+}
+
+/// Downloads and unpacks the Core ML encoder of a model unless it is cached
+///
+/// Hugging Face hosts the encoders as zipped `.mlmodelc` directories, which
+/// are unpacked with `ditto` into the model cache.
+fn ensure_coreml_encoder(
+    model_name: &str,
+    source: &ModelSource,
+    model_path: &Path,
+    progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<(), ModelDownloadError> {
+    let encoder_path = coreml_encoder_path(model_path);
+    if encoder_path.is_dir() {
+        return Ok(());
+    }
+    let _lock = lock_download(&encoder_path, model_name, progress)?;
+    if encoder_path.is_dir() {
+        return Ok(());
+    }
+
+    let encoder_name = encoder_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let zip_path = encoder_path.with_file_name(format!("{}.zip", encoder_name));
+    download_file(
+        source,
+        &format!("{}.zip", encoder_name),
+        &format!("{} (Core ML encoder)", model_name),
+        &zip_path,
+        progress,
+    )?;
+
+    let extract_to = encoder_path.parent().unwrap_or(Path::new("."));
+    let extracted = crate::child_process::output(
+        Command::new("ditto")
+            .args(["-x", "-k"])
+            .arg(&zip_path)
+            .arg(extract_to),
+    );
+    let _ = fs::remove_file(&zip_path);
+
+    let failed = |reason: String| ModelDownloadError::ExtractionFailed {
+        path: zip_path.clone(),
+        reason,
+    };
+    match extracted {
+        Ok(output) if !output.status.success() => Err(failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )),
+        Ok(_) if !encoder_path.is_dir() => Err(failed(format!(
+            "{} is missing from the archive",
+            encoder_name
+        ))),
+        Ok(_) => Ok(()),
+        Err(e) => Err(failed(e.to_string())),
+    }
+}
+
+/// Downloads a Whisper model from Hugging Face or a mirror
+///
+/// This function downloads the model file with progress reporting and
+/// saves it to the specified path.
 ///
 /// # Arguments
 ///
 /// * `model_name` - Name of the model to download
+/// * `source` - Where to download the model from
 /// * `target_path` - Path where the model should be saved
+/// * `progress` - Callback receiving the progress of the download
 ///
 /// # Returns
 ///
 /// Ok(()) on success, or an error if download fails
-fn download_model(model_name: &str, target_path: &Path) -> Result<(), ModelDownloadError> {
-    let url = format!("{}/ggml-{}.bin", MODEL_BASE_URL, model_name);
-
-    println!("🔍 Preparing evidence kit...");
-    println!(
-        "📥 Downloading Whisper model '{}' from Hugging Face",
-        model_name
-    );
-    println!("   This may take a few minutes depending on your connection...");
-    print!("   Progress: ");
-    io::stdout().flush().ok();
+fn download_model(
+    model_name: &str,
+    source: &ModelSource,
+    target_path: &Path,
+    progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<(), ModelDownloadError> {
+    let file_name = format!("ggml-{}.bin", model_name);
+    download_file(source, &file_name, model_name, target_path, progress)
+}
 
-    // Create a blocking HTTP client
-    let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_secs(600)) // 10 minute timeout
-        .build()
-        .map_err(|e| ModelDownloadError::DownloadFailed {
-            url: url.clone(),
-            source: e,
-        })?;
-
-    // Start the download
-    let mut response = client
-        .get(&url)
-        .send()
-        .map_err(|e| ModelDownloadError::DownloadFailed {
-            url: url.clone(),
-            source: e,
-        })?;
-
-    // Check HTTP status
-    if !response.status().is_success() {
-        return Err(ModelDownloadError::HttpError(format!(
-            "HTTP {} while downloading model from {}",
-            response.status(),
-            url
-        )));
-    }
+/// Downloads a file of a model, reporting it under the given name
+///
+/// The file is written next to the target and renamed into place once
+/// complete, so an interrupted download never looks like a cached file.
+/// The caller holds the lock of the target, which keeps other processes
+/// from writing the same temporary file.
+fn download_file(
+    source: &ModelSource,
+    file_name: &str,
+    model_name: &str,
+    target_path: &Path,
+    progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<(), ModelDownloadError> {
+    progress(DownloadProgress::Started {
+        model_name: model_name.to_string(),
+        source: source.to_string(),
+    });
 
-    // Get content length for progress reporting
-    let total_size = response.content_length();
+    let (mut response, total_size) = source.open(file_name)?;
 
     // Create temporary file first (download to .tmp, then rename)
     let temp_path = target_path.with_extension("tmp");
@@ -238,18 +703,19 @@ fn download_model(model_name: &str, target_path: &Path) -> Result<(), ModelDownl
 
         downloaded += bytes_read as u64;
 
-        // Print progress every 10%
-        if let Some(total) = total_size {
-            let progress_percent = (downloaded * 100 / total) as u32;
-            if progress_percent >= last_progress_percent + 10 {
-                print!("{}% ", progress_percent);
-                io::stdout().flush().ok();
+        // Report progress every percent
+        if let Some(total) = total_size.filter(|&total| total > 0) {
+            let progress_percent = (downloaded * 100 / total).min(100) as u32;
+            if progress_percent > last_progress_percent {
+                progress(DownloadProgress::Progress {
+                    percent: progress_percent,
+                });
                 last_progress_percent = progress_percent;
             }
         }
     }
 
-    println!("100% ✓");
+    progress(DownloadProgress::Downloaded);
 
     // Verify downloaded file size
     if downloaded < MIN_MODEL_SIZE {
@@ -269,11 +735,52 @@ fn download_model(model_name: &str, target_path: &Path) -> Result<(), ModelDownl
         source: e,
     })?;
 
-    println!("✅ Model cached at: {}", target_path.display());
+    progress(DownloadProgress::Cached {
+        path: target_path.to_path_buf(),
+    });
 
     Ok(())
 }
 
+/// Prints the progress of a model download to stdout
+pub fn print_download_progress(progress: DownloadProgress) {
+    match progress {
+        DownloadProgress::Started { model_name, source } => {
+            println!("🔍 Preparing evidence kit...");
+            println!(
+                "📥 Downloading Whisper model '{}' from {}",
+                model_name, source
+            );
+            println!("   This may take a few minutes depending on your connection...");
+            print!("   {}", progress_bar(0));
+        }
+        DownloadProgress::Waiting { model_name } => {
+            println!(
+                "⏳ Waiting for another download of Whisper model '{}' to finish...",
+                model_name
+            );
+        }
+        DownloadProgress::Progress { percent } => print!("\r   {}", progress_bar(percent)),
+        DownloadProgress::Downloaded => println!("\r   {} ✓", progress_bar(100)),
+        DownloadProgress::Cached { path } => {
+            println!("✅ Model cached at: {}", path.display());
+        }
+    }
+    io::stdout().flush().ok();
+}
+
+/// Renders a percentage as a bar like `[██████░░░░░░░░░░░░░░]  30%`
+fn progress_bar(percent: u32) -> String {
+    const WIDTH: usize = 20;
+    let filled = (percent.min(100) as usize * WIDTH) / 100;
+    format!(
+        "[{}{}] {:>3}%",
+        "█".repeat(filled),
+        "░".repeat(WIDTH - filled),
+        percent
+    )
+}
+
 /// Gets the cache directory for Whisper models
 ///
 /// Returns the platform-specific cache directory path:
@@ -309,6 +816,9 @@ pub struct CachedModelInfo {
 
     /// File size in bytes
     pub size_bytes: u64,
+
+    /// When the model was last used or downloaded, if known
+    pub last_used: Option<SystemTime>,
 }
 
 impl CachedModelInfo {
@@ -318,6 +828,40 @@ impl CachedModelInfo {
     }
 }
 
+/// Returns the path a model is cached at, whether or not it was downloaded
+pub fn cached_model_path(model_name: &str) -> Result<PathBuf, ModelDownloadError> {
+    Ok(get_model_cache_dir()?.join(format!("ggml-{}.bin", model_name)))
+}
+
+/// Checks that a file looks like a usable Whisper model
+///
+/// Catches incomplete downloads and files that aren't GGML models at all,
+/// without loading the model. Returns the file size in bytes.
+pub fn validate_model_file(path: &Path) -> Result<u64, ModelDownloadError> {
+    let invalid = |reason: String| ModelDownloadError::InvalidModel {
+        path: path.to_path_buf(),
+        reason,
+    };
+
+    let mut file = fs::File::open(path).map_err(|e| invalid(e.to_string()))?;
+    let size = file.metadata().map_err(|e| invalid(e.to_string()))?.len();
+    if size < MIN_MODEL_SIZE {
+        return Err(invalid(format!(
+            "File is too small ({} bytes), expected at least {} bytes",
+            size, MIN_MODEL_SIZE
+        )));
+    }
+
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic)
+        .map_err(|e| invalid(e.to_string()))?;
+    if magic != GGML_MAGIC {
+        return Err(invalid("Not a GGML model file".to_string()));
+    }
+
+    Ok(size)
+}
+
 /// Returns the list of all supported model names
 ///
 /// This is a convenience function that returns the list of model names
@@ -338,11 +882,72 @@ pub fn get_cache_dir() -> Result<PathBuf, ModelDownloadError> {
 /// Returns a vector of `CachedModelInfo` containing details about each
 /// cached model including name, path, file size, etc.
 pub fn list_cached_models() -> Result<Vec<CachedModelInfo>, ModelDownloadError> {
-    let cache_dir = get_model_cache_dir()?;
+    Ok(list_models_in(&get_model_cache_dir()?))
+}
+
+/// Lists the cached models that weren't used for at least the given time
+///
+/// Models without a known last use are left alone.
+pub fn stale_models(unused_for: Duration) -> Result<Vec<CachedModelInfo>, ModelDownloadError> {
+    let models = list_cached_models()?;
+    Ok(unused_models(models, unused_for, SystemTime::now()))
+}
+
+/// Removes a cached model along with its Core ML encoder
+///
+/// Returns the number of bytes freed.
+pub fn remove_cached_model(model: &CachedModelInfo) -> Result<u64, ModelDownloadError> {
+    let remove_failed = |path: &Path, source| ModelDownloadError::RemoveFailed {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let encoder_path = coreml_encoder_path(&model.path);
+    let mut freed = 0;
+    if encoder_path.is_dir() {
+        freed += dir_size(&encoder_path);
+        fs::remove_dir_all(&encoder_path).map_err(|e| remove_failed(&encoder_path, e))?;
+    }
+    fs::remove_file(&model.path).map_err(|e| remove_failed(&model.path, e))?;
+    Ok(freed + model.size_bytes)
+}
 
+/// Keeps the models last used before `now - unused_for`
+fn unused_models(
+    models: Vec<CachedModelInfo>,
+    unused_for: Duration,
+    now: SystemTime,
+) -> Vec<CachedModelInfo> {
+    models
+        .into_iter()
+        .filter(|model| {
+            model
+                .last_used
+                .and_then(|used| now.duration_since(used).ok())
+                .is_some_and(|age| age >= unused_for)
+        })
+        .collect()
+}
+
+/// Adds up the sizes of all files below a directory
+fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Lists the models in a cache directory, sorted by name
+fn list_models_in(cache_dir: &Path) -> Vec<CachedModelInfo> {
     let mut models = Vec::new();
 
-    if let Ok(entries) = fs::read_dir(&cache_dir) {
+    if let Ok(entries) = fs::read_dir(cache_dir) {
         for entry in entries.flatten() {
             let path = entry.path();
             if path.is_file() {
@@ -356,14 +961,15 @@ pub fn list_cached_models() -> Result<Vec<CachedModelInfo>, ModelDownloadError>
                             .to_string();
 
                         if !model_name.is_empty() {
-                            // Get file size
-                            let size_bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                            // Get file size and last use
+                            let metadata = fs::metadata(&path).ok();
 
                             models.push(CachedModelInfo {
                                 model_name,
                                 path: path.clone(),
                                 file_name: file_name.to_string(),
-                                size_bytes,
+                                size_bytes: metadata.as_ref().map_or(0, |m| m.len()),
+                                last_used: metadata.and_then(|m| m.modified().ok()),
                             });
                         }
                     }
@@ -375,5 +981,182 @@ pub fn list_cached_models() -> Result<Vec<CachedModelInfo>, ModelDownloadError>
     // Sort by model name for consistent output
     models.sort_by(|a, b| a.model_name.cmp(&b.model_name));
 
-    Ok(models)
+    models
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_validate_model_file() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_validate_model");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let path = temp_dir.join("ggml-test.bin");
+
+        let mut model = GGML_MAGIC.to_vec();
+        model.resize(MIN_MODEL_SIZE as usize, 0);
+        fs::write(&path, &model).unwrap();
+        assert_eq!(validate_model_file(&path).unwrap(), MIN_MODEL_SIZE);
+
+        model[0] = b'<';
+        fs::write(&path, &model).unwrap();
+        assert!(validate_model_file(&path).is_err());
+
+        fs::write(&path, GGML_MAGIC).unwrap();
+        assert!(validate_model_file(&path).is_err());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_model_spec_from_str() {
+        let spec = |value: &str| value.parse::<ModelSpec>().unwrap();
+        assert_eq!(spec("auto"), ModelSpec::Auto);
+        assert_eq!(spec("base.en"), ModelSpec::Named("base.en".to_string()));
+        assert_eq!(spec("large-v9"), ModelSpec::Named("large-v9".to_string()));
+        assert_eq!(
+            spec("models/ggml-base.bin"),
+            ModelSpec::Path(PathBuf::from("models/ggml-base.bin"))
+        );
+        assert_eq!(
+            spec("custom.bin"),
+            ModelSpec::Path(PathBuf::from("custom.bin"))
+        );
+    }
+
+    #[test]
+    fn test_coreml_encoder_path() {
+        let encoder = |model: &str| coreml_encoder_path(Path::new(model));
+        assert_eq!(
+            encoder("/cache/ggml-base.en.bin"),
+            PathBuf::from("/cache/ggml-base.en-encoder.mlmodelc")
+        );
+        assert_eq!(
+            encoder("/cache/ggml-large-v3-turbo-q5_0.bin"),
+            PathBuf::from("/cache/ggml-large-v3-turbo-encoder.mlmodelc")
+        );
+        assert_eq!(
+            encoder("ggml-large-v3.bin"),
+            PathBuf::from("ggml-large-v3-encoder.mlmodelc")
+        );
+    }
+
+    #[test]
+    fn test_model_source_parse() {
+        assert_eq!(ModelSource::parse(""), ModelSource::HuggingFace);
+        assert_eq!(
+            ModelSource::parse("https://models.example.com/whisper/"),
+            ModelSource::Mirror("https://models.example.com/whisper".to_string())
+        );
+        assert_eq!(
+            ModelSource::parse("file:///srv/models"),
+            ModelSource::Directory(PathBuf::from("/srv/models"))
+        );
+        assert_eq!(
+            ModelSource::parse("/srv/models"),
+            ModelSource::Directory(PathBuf::from("/srv/models"))
+        );
+    }
+
+    #[test]
+    fn test_download_from_directory() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_model_mirror");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(&temp_dir).unwrap();
+        let mut model = GGML_MAGIC.to_vec();
+        model.resize(MIN_MODEL_SIZE as usize, 0);
+        fs::write(temp_dir.join("ggml-tiny.bin"), &model).unwrap();
+
+        let source = ModelSource::Directory(temp_dir.clone());
+        let target = temp_dir.join("cache.bin");
+        let mut events = Vec::new();
+        download_model("tiny", &source, &target, &mut |event| events.push(event)).unwrap();
+        assert_eq!(validate_model_file(&target).unwrap(), MIN_MODEL_SIZE);
+        assert_eq!(
+            events.last(),
+            Some(&DownloadProgress::Cached { path: target })
+        );
+
+        let missing = download_model("base", &source, &temp_dir.join("b.bin"), &mut |_| {});
+        assert_eq!(missing.unwrap_err().code(), "DD-MOD-009");
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_prune_unused_models() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_prune_models");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(temp_dir.join("ggml-tiny-encoder.mlmodelc")).unwrap();
+        fs::write(temp_dir.join("ggml-tiny-encoder.mlmodelc/weights"), [0; 10]).unwrap();
+        fs::write(temp_dir.join("ggml-tiny.bin"), [0; 100]).unwrap();
+        fs::write(temp_dir.join("ggml-base.bin"), [0; 100]).unwrap();
+        fs::write(temp_dir.join("notes.txt"), "").unwrap();
+
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let old = fs::File::options()
+            .write(true)
+            .open(temp_dir.join("ggml-tiny.bin"))
+            .unwrap();
+        old.set_modified(now - 100 * day).unwrap();
+
+        let models = list_models_in(&temp_dir);
+        assert_eq!(models.len(), 2);
+        let stale = unused_models(models, 90 * day, now);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].model_name, "tiny");
+
+        assert_eq!(remove_cached_model(&stale[0]).unwrap(), 110);
+        assert!(!temp_dir.join("ggml-tiny-encoder.mlmodelc").exists());
+        assert_eq!(list_models_in(&temp_dir).len(), 1);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_download_lock() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_download_lock");
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::create_dir_all(&temp_dir).unwrap();
+        let target = temp_dir.join("ggml-tiny.bin");
+        let lock_path = temp_dir.join("ggml-tiny.bin.lock");
+
+        let lock = lock_download(&target, "tiny", &mut |_| {}).unwrap();
+        assert!(lock_path.exists());
+        let holder = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            drop(lock);
+        });
+        let mut events = Vec::new();
+        let second = lock_download(&target, "tiny", &mut |event| events.push(event));
+        holder.join().unwrap();
+        assert!(second.is_ok());
+        assert_eq!(
+            events,
+            vec![DownloadProgress::Waiting {
+                model_name: "tiny".to_string()
+            }]
+        );
+        drop(second);
+
+        // Lock files left behind by crashed processes aren't locked
+        let mut events = Vec::new();
+        assert!(lock_download(&target, "tiny", &mut |event| events.push(event)).is_ok());
+        assert!(events.is_empty());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_progress_bar() {
+        assert_eq!(progress_bar(0), format!("[{}]   0%", "░".repeat(20)));
+        assert_eq!(
+            progress_bar(55),
+            format!("[{}{}]  55%", "█".repeat(11), "░".repeat(9))
+        );
+        assert_eq!(progress_bar(100), format!("[{}] 100%", "█".repeat(20)));
+    }
 }
@@ -0,0 +1,290 @@
+//! Plan diff module
+//!
+//! Dry runs on a folder that keeps growing list every file again, although
+//! only the new downloads need a look. The plan of the last dry run of
+//! each directory is saved in the data directory, so that the next one
+//! can show just what changed: files that are new, files whose match
+//! changed (e.g. because the metadata was updated), and files that are no
+//! longer planned.
+
+use crate::cache_namespace_for;
+use crate::file_operations::PlannedOperation;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use thiserror::Error;
+
+/// Directory in the data directory holding the saved plans
+const PLANS_DIR: &str = "plans";
+
+/// Errors that can occur while saving or loading plans
+#[derive(Debug, Error)]
+pub enum PlanDiffError {
+    /// Could not determine the data directory
+    #[error("Could not determine data directory")]
+    DataDirectoryNotFound,
+
+    /// Failed to read or write a saved plan
+    #[error("Failed to access saved plan {path}: {source}")]
+    IoFailed { path: PathBuf, source: io::Error },
+
+    /// A saved plan isn't valid
+    #[error("Failed to parse saved plan {path}: {source}")]
+    ParseFailed {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+impl PlanDiffError {
+    /// Returns the stable code of the error, like `DD-PLN-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            PlanDiffError::DataDirectoryNotFound => "DD-PLN-001",
+            PlanDiffError::IoFailed { .. } => "DD-PLN-002",
+            PlanDiffError::ParseFailed { .. } => "DD-PLN-003",
+        }
+    }
+}
+
+/// A planned file operation as saved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanEntry {
+    /// Source file, relative to the investigated directory if inside it
+    pub source: PathBuf,
+
+    /// Destination of the file, as planned
+    pub destination: PathBuf,
+}
+
+/// The plan of a dry run of a directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedPlan {
+    /// The investigated directory
+    pub directory: PathBuf,
+
+    /// Time the plan was saved, in seconds since the Unix epoch
+    pub saved_at: u64,
+
+    /// The planned operations
+    pub entries: Vec<PlanEntry>,
+}
+
+impl SavedPlan {
+    /// Creates the plan of a directory from the planned operations
+    pub fn new(directory: &Path, operations: &[PlannedOperation]) -> Self {
+        let saved_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            directory: directory.to_path_buf(),
+            saved_at,
+            entries: operations
+                .iter()
+                .map(|op| plan_entry(directory, op))
+                .collect(),
+        }
+    }
+}
+
+/// A file whose destination changed since the saved plan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanChange {
+    /// Source file, relative to the investigated directory if inside it
+    pub source: PathBuf,
+
+    /// Destination in the saved plan
+    pub previous: PathBuf,
+
+    /// Destination in the current plan
+    pub destination: PathBuf,
+}
+
+/// Differences between a saved plan and the current one
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanDiff {
+    /// Files that weren't in the saved plan
+    pub added: Vec<PlanEntry>,
+
+    /// Files planned with another destination than before
+    pub changed: Vec<PlanChange>,
+
+    /// Files that were in the saved plan but aren't planned anymore
+    pub removed: Vec<PlanEntry>,
+
+    /// Number of files planned the same way as before
+    pub unchanged: usize,
+}
+
+impl PlanDiff {
+    /// Returns whether the plan is the same as the saved one
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Returns the directory holding the saved plans in the data directory
+pub fn default_plans_dir() -> Result<PathBuf, PlanDiffError> {
+    let proj_dirs = directories::ProjectDirs::from("de", "westhoffswelt", "dialogdetective")
+        .ok_or(PlanDiffError::DataDirectoryNotFound)?;
+    Ok(proj_dirs.data_local_dir().join(PLANS_DIR))
+}
+
+/// Loads the saved plan of a directory, or None if there is none
+pub fn load_plan(plans_dir: &Path, directory: &Path) -> Result<Option<SavedPlan>, PlanDiffError> {
+    let path = plan_path(plans_dir, directory);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => return Err(PlanDiffError::IoFailed { path, source }),
+    };
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|source| PlanDiffError::ParseFailed { path, source })
+}
+
+/// Saves the plan of a directory, replacing the one saved before
+pub fn save_plan(plans_dir: &Path, plan: &SavedPlan) -> Result<(), PlanDiffError> {
+    let path = plan_path(plans_dir, &plan.directory);
+    let content = serde_json::to_string_pretty(plan).expect("plans serialize to JSON");
+    fs::create_dir_all(plans_dir)
+        .and_then(|_| fs::write(&path, content))
+        .map_err(|source| PlanDiffError::IoFailed { path, source })
+}
+
+/// Compares the planned operations with a saved plan
+///
+/// Files are identified by their source path. Entries are in the order of
+/// the operations, and removed ones in the order of the saved plan.
+pub fn diff_plan(previous: &SavedPlan, operations: &[PlannedOperation]) -> PlanDiff {
+    let saved: HashMap<&Path, &Path> = previous
+        .entries
+        .iter()
+        .map(|entry| (entry.source.as_path(), entry.destination.as_path()))
+        .collect();
+
+    let mut diff = PlanDiff::default();
+    let current: Vec<PlanEntry> = operations
+        .iter()
+        .map(|op| plan_entry(&previous.directory, op))
+        .collect();
+    for entry in &current {
+        match saved.get(entry.source.as_path()) {
+            None => diff.added.push(entry.clone()),
+            Some(previous) if *previous != entry.destination => diff.changed.push(PlanChange {
+                source: entry.source.clone(),
+                previous: previous.to_path_buf(),
+                destination: entry.destination.clone(),
+            }),
+            Some(_) => diff.unchanged += 1,
+        }
+    }
+
+    diff.removed = previous
+        .entries
+        .iter()
+        .filter(|entry| !current.iter().any(|c| c.source == entry.source))
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Returns the saved form of a planned operation
+fn plan_entry(directory: &Path, op: &PlannedOperation) -> PlanEntry {
+    PlanEntry {
+        source: op
+            .source
+            .strip_prefix(directory)
+            .unwrap_or(&op.source)
+            .to_path_buf(),
+        destination: op.destination.clone(),
+    }
+}
+
+/// Returns the path of the saved plan of a directory
+///
+/// Named like the cache namespace of the directory, so it is readable and
+/// the same for relative and absolute paths.
+fn plan_path(plans_dir: &Path, directory: &Path) -> PathBuf {
+    plans_dir.join(format!("{}.json", cache_namespace_for(directory)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Episode;
+
+    fn operation(source: &str, destination: &str) -> PlannedOperation {
+        PlannedOperation {
+            source: PathBuf::from(source),
+            destination: PathBuf::from(destination),
+            episode: Episode {
+                season_number: 1,
+                episode_number: 1,
+                name: "Pilot".to_string(),
+                summary: String::new(),
+                summary_source: None,
+                airdate: None,
+                runtime: None,
+                season_name: None,
+                provider_id: None,
+                local_title: None,
+            },
+            extra_episodes: Vec::new(),
+            duplicate_suffix: None,
+            part: None,
+            quarantined: false,
+        }
+    }
+
+    #[test]
+    fn test_diff_plan() {
+        let directory = Path::new("/rips");
+        let previous = SavedPlan::new(
+            directory,
+            &[
+                operation("/rips/a.mkv", "/rips/Show - S01E01 - Pilot.mkv"),
+                operation("/rips/b.mkv", "/rips/Show - S01E02 - TBA.mkv"),
+                operation("/rips/gone.mkv", "/rips/Show - S01E03 - Gone.mkv"),
+            ],
+        );
+        assert_eq!(previous.entries[0].source, PathBuf::from("a.mkv"));
+
+        let diff = diff_plan(
+            &previous,
+            &[
+                operation("/rips/a.mkv", "/rips/Show - S01E01 - Pilot.mkv"),
+                operation("/rips/b.mkv", "/rips/Show - S01E02 - Cat's in the Bag.mkv"),
+                operation("/rips/new.mkv", "/rips/Show - S01E04 - New.mkv"),
+            ],
+        );
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].source, PathBuf::from("new.mkv"));
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(
+            diff.changed[0].previous,
+            PathBuf::from("/rips/Show - S01E02 - TBA.mkv")
+        );
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].source, PathBuf::from("gone.mkv"));
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load_plan() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_plans");
+        fs::remove_dir_all(&temp_dir).ok();
+        let directory = Path::new("/rips");
+
+        assert_eq!(load_plan(&temp_dir, directory).unwrap(), None);
+        let plan = SavedPlan::new(directory, &[operation("/rips/a.mkv", "/tv/a.mkv")]);
+        save_plan(&temp_dir, &plan).unwrap();
+        assert_eq!(load_plan(&temp_dir, directory).unwrap(), Some(plan));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+}
@@ -0,0 +1,417 @@
+//! Job queue module
+//!
+//! Identifying a download takes minutes, and torrent clients finishing
+//! several downloads at once would otherwise start several investigations
+//! side by side. The hook can instead add its download to a persistent
+//! queue, which is worked off one job at a time. Jobs can be flagged as
+//! urgent, reordered, paused, and cancelled while they wait.
+//!
+//! A job stays in the queue while it runs and is only removed once it
+//! finished. The runner holds a lock on the job meanwhile, so a job left
+//! running by a crashed runner is picked up again by the next one.
+
+use crate::file_lock::{FileLock, lock_path};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+use walkdir::WalkDir;
+
+/// Name of the queue file in the data directory
+const QUEUE_FILE: &str = "queue.json";
+
+/// How long to wait for another process to release the queue
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors that can occur while working with the job queue
+#[derive(Debug, Error)]
+pub enum QueueError {
+    /// Could not determine the data directory
+    #[error("Could not determine data directory")]
+    DataDirectoryNotFound,
+
+    /// Failed to read or write the queue file
+    #[error("Failed to access queue {path}: {source}")]
+    IoFailed { path: PathBuf, source: io::Error },
+
+    /// The queue file isn't valid
+    #[error("Failed to parse queue {path}: {source}")]
+    ParseFailed {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    /// Another process kept the queue locked
+    #[error("Queue {0} is locked by another process")]
+    Locked(PathBuf),
+
+    /// No job with the given ID is queued
+    #[error("Job {0} not found")]
+    JobNotFound(u64),
+}
+
+impl QueueError {
+    /// Returns the stable code of the error, like `DD-QUE-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            QueueError::DataDirectoryNotFound => "DD-QUE-001",
+            QueueError::IoFailed { .. } => "DD-QUE-002",
+            QueueError::ParseFailed { .. } => "DD-QUE-003",
+            QueueError::Locked(_) => "DD-QUE-004",
+            QueueError::JobNotFound(_) => "DD-QUE-005",
+        }
+    }
+}
+
+/// A download waiting to be identified with a hook profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedJob {
+    /// ID of the job, unique within the queue
+    pub id: u64,
+
+    /// Name of the hook profile to identify the download with
+    pub profile: String,
+
+    /// Profiles file, if not the default one
+    #[serde(default)]
+    pub config: Option<PathBuf>,
+
+    /// The downloaded file or directory
+    pub path: PathBuf,
+
+    /// Size of the download in bytes
+    pub size: u64,
+
+    /// Whether the job runs before all others
+    #[serde(default)]
+    pub urgent: bool,
+
+    /// Whether the job is held back until resumed
+    #[serde(default)]
+    pub paused: bool,
+
+    /// Whether a runner has started the job
+    #[serde(default)]
+    pub running: bool,
+
+    /// Time the job was added, in seconds since the Unix epoch
+    pub added: u64,
+}
+
+/// Contents of the queue file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QueueFile {
+    next_id: u64,
+    jobs: Vec<QueuedJob>,
+}
+
+/// The job queue, locked against other processes while open
+///
+/// Changes are only stored by `save`. The lock is released when the queue
+/// is dropped, so it should be held only as long as needed.
+#[derive(Debug)]
+pub struct JobQueue {
+    path: PathBuf,
+    _lock: FileLock,
+    file: QueueFile,
+}
+
+/// A job claimed by a runner, until it is passed to `JobQueue::finish`
+///
+/// The claim is released when dropped, so a job whose runner died can be
+/// started again.
+#[derive(Debug)]
+pub struct RunningJob {
+    /// The job as it was started
+    pub job: QueuedJob,
+    _claim: FileLock,
+}
+
+/// Returns the path of the queue file in the data directory
+pub fn default_queue_path() -> Result<PathBuf, QueueError> {
+    let proj_dirs = directories::ProjectDirs::from("de", "westhoffswelt", "dialogdetective")
+        .ok_or(QueueError::DataDirectoryNotFound)?;
+    Ok(proj_dirs.data_local_dir().join(QUEUE_FILE))
+}
+
+impl JobQueue {
+    /// Opens and locks a queue file, starting an empty queue if it doesn't exist
+    pub fn open(path: &Path) -> Result<Self, QueueError> {
+        let io_failed = |source| QueueError::IoFailed {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(io_failed)?;
+        }
+        let lock = FileLock::acquire(&lock_path(path), Some(LOCK_TIMEOUT), || {})
+            .map_err(io_failed)?
+            .ok_or_else(|| QueueError::Locked(path.to_path_buf()))?;
+
+        let file = match fs::read_to_string(path) {
+            Ok(content) => {
+                serde_json::from_str(&content).map_err(|source| QueueError::ParseFailed {
+                    path: path.to_path_buf(),
+                    source,
+                })?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => QueueFile::default(),
+            Err(e) => return Err(io_failed(e)),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            _lock: lock,
+            file,
+        })
+    }
+
+    /// Returns the jobs in the order they are run
+    ///
+    /// Paused jobs keep their place but are skipped by `start_next`, as are
+    /// jobs running elsewhere.
+    pub fn jobs(&self) -> &[QueuedJob] {
+        &self.file.jobs
+    }
+
+    /// Adds a download to the queue
+    ///
+    /// Urgent jobs are placed behind the other urgent jobs, all others at
+    /// the end of the queue.
+    pub fn push(
+        &mut self,
+        profile: &str,
+        config: Option<&Path>,
+        path: &Path,
+        urgent: bool,
+    ) -> &QueuedJob {
+        self.file.next_id += 1;
+        let job = QueuedJob {
+            id: self.file.next_id,
+            profile: profile.to_string(),
+            config: config.map(Path::to_path_buf),
+            path: path.to_path_buf(),
+            size: download_size(path),
+            urgent,
+            paused: false,
+            running: false,
+            added: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        };
+
+        let jobs = &mut self.file.jobs;
+        let index = if urgent {
+            jobs.iter().position(|j| !j.urgent).unwrap_or(jobs.len())
+        } else {
+            jobs.len()
+        };
+        jobs.insert(index, job);
+        &jobs[index]
+    }
+
+    /// Starts the job to run next, marking it as running
+    ///
+    /// Urgent jobs come first, in queue order. Among the others, either the
+    /// first or, with `smallest_first`, the smallest download is started.
+    /// Jobs running in another process are skipped, unless their runner
+    /// has died. The job stays queued until it is passed to `finish`.
+    pub fn start_next(&mut self, smallest_first: bool) -> Result<Option<RunningJob>, QueueError> {
+        let mut candidates: Vec<usize> = (0..self.file.jobs.len())
+            .filter(|&index| !self.file.jobs[index].paused)
+            .collect();
+        candidates.sort_by_key(|&index| {
+            let job = &self.file.jobs[index];
+            let size = if smallest_first && !job.urgent {
+                job.size
+            } else {
+                0
+            };
+            (!job.urgent, size, index)
+        });
+
+        for index in candidates {
+            let claim_path = self.claim_path(self.file.jobs[index].id);
+            let claim =
+                FileLock::acquire(&claim_path, Some(Duration::ZERO), || {}).map_err(|source| {
+                    QueueError::IoFailed {
+                        path: claim_path,
+                        source,
+                    }
+                })?;
+            if let Some(claim) = claim {
+                let job = &mut self.file.jobs[index];
+                job.running = true;
+                return Ok(Some(RunningJob {
+                    job: job.clone(),
+                    _claim: claim,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes a job that finished running from the queue
+    ///
+    /// Jobs cancelled while running are already gone.
+    pub fn finish(&mut self, running: RunningJob) {
+        let id = running.job.id;
+        self.file.jobs.retain(|j| j.id != id);
+        // Nobody claims a job without holding the queue, so the claim's lock
+        // file can go once the claim is released
+        drop(running);
+        let _ = fs::remove_file(self.claim_path(id));
+    }
+
+    /// Moves a job to a position in the queue, counted from 1
+    pub fn move_job(&mut self, id: u64, position: usize) -> Result<(), QueueError> {
+        let index = self.index_of(id)?;
+        let job = self.file.jobs.remove(index);
+        let position = position.saturating_sub(1).min(self.file.jobs.len());
+        self.file.jobs.insert(position, job);
+        Ok(())
+    }
+
+    /// Holds a job back, or lets it run again
+    pub fn set_paused(&mut self, id: u64, paused: bool) -> Result<(), QueueError> {
+        let index = self.index_of(id)?;
+        self.file.jobs[index].paused = paused;
+        Ok(())
+    }
+
+    /// Removes a job from the queue
+    pub fn cancel(&mut self, id: u64) -> Result<QueuedJob, QueueError> {
+        let index = self.index_of(id)?;
+        Ok(self.file.jobs.remove(index))
+    }
+
+    /// Stores the queue, replacing the file atomically
+    pub fn save(&self) -> Result<(), QueueError> {
+        let temp = self.path.with_extension("json.tmp");
+        let content = serde_json::to_string_pretty(&self.file).expect("queue serializes to JSON");
+        fs::write(&temp, content)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|source| QueueError::IoFailed {
+                path: self.path.clone(),
+                source,
+            })
+    }
+
+    /// Returns the path of the lock file claiming a running job
+    fn claim_path(&self, id: u64) -> PathBuf {
+        lock_path(&self.path.with_extension(format!("{}.json", id)))
+    }
+
+    /// Finds the position of a job by its ID
+    fn index_of(&self, id: u64) -> Result<usize, QueueError> {
+        self.file
+            .jobs
+            .iter()
+            .position(|j| j.id == id)
+            .ok_or(QueueError::JobNotFound(id))
+    }
+}
+
+/// Returns the size of a file, or of all files in a directory
+///
+/// Symlinks are followed like when the download is investigated, while
+/// symlink loops are skipped.
+fn download_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_queue_order() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_job_queue_order");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let big = temp_dir.join("big.mkv");
+        let small = temp_dir.join("small.mkv");
+        fs::write(&big, vec![0; 100]).unwrap();
+        fs::write(&small, vec![0; 10]).unwrap();
+        let queue_path = temp_dir.join(QUEUE_FILE);
+
+        let mut queue = JobQueue::open(&queue_path).unwrap();
+        assert_eq!(queue.push("bb", None, &big, false).id, 1);
+        queue.push("bb", None, &small, false);
+        queue.push("bb", None, &big, true);
+        let ids: Vec<u64> = queue.jobs().iter().map(|j| j.id).collect();
+        assert_eq!(ids, vec![3, 1, 2]);
+        queue.save().unwrap();
+        drop(queue);
+
+        let mut queue = JobQueue::open(&queue_path).unwrap();
+        queue.set_paused(3, true).unwrap();
+        let second = queue.start_next(true).unwrap().unwrap();
+        assert_eq!(second.job.id, 2);
+        let first = queue.start_next(false).unwrap().unwrap();
+        assert_eq!(first.job.id, 1);
+        assert!(queue.start_next(false).unwrap().is_none());
+        queue.finish(second);
+        queue.save().unwrap();
+        drop(queue);
+
+        // A job stays queued while running, and is started again once its
+        // runner is gone
+        let mut queue = JobQueue::open(&queue_path).unwrap();
+        let ids: Vec<u64> = queue.jobs().iter().map(|j| j.id).collect();
+        assert_eq!(ids, vec![3, 1]);
+        assert!(queue.jobs()[1].running);
+        queue.set_paused(3, false).unwrap();
+        let third = queue.start_next(false).unwrap().unwrap();
+        assert_eq!(third.job.id, 3);
+        assert!(queue.start_next(false).unwrap().is_none());
+        drop(first);
+        let first = queue.start_next(false).unwrap().unwrap();
+        assert_eq!(first.job.id, 1);
+        queue.cancel(3).unwrap();
+        queue.finish(third);
+        queue.finish(first);
+        assert!(queue.jobs().is_empty());
+        drop(queue);
+
+        // Symlink loops in a download are skipped
+        #[cfg(unix)]
+        {
+            let download = temp_dir.join("download");
+            fs::create_dir_all(&download).unwrap();
+            fs::write(download.join("episode.mkv"), vec![0; 10]).unwrap();
+            let _ = std::os::unix::fs::symlink(&download, download.join("loop"));
+            assert_eq!(download_size(&download), 10);
+        }
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_job_queue_move_and_cancel() {
+        let temp_dir = std::env::temp_dir().join("dialog_detective_test_job_queue_move");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let mut queue = JobQueue::open(&temp_dir.join(QUEUE_FILE)).unwrap();
+        for name in ["a", "b", "c"] {
+            queue.push("bb", None, &temp_dir.join(name), false);
+        }
+
+        queue.move_job(3, 1).unwrap();
+        queue.move_job(1, 10).unwrap();
+        let ids: Vec<u64> = queue.jobs().iter().map(|j| j.id).collect();
+        assert_eq!(ids, vec![3, 2, 1]);
+        assert_eq!(queue.cancel(2).unwrap().path, temp_dir.join("b"));
+        assert_eq!(queue.jobs().len(), 2);
+        drop(queue);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+}
@@ -0,0 +1,181 @@
+//! Release tag module
+//!
+//! Scene and P2P releases carry their provenance in the file name:
+//! `Show.S01E02.1080p.WEB-DL.x265-GROUP.mkv`. Renaming throws that away,
+//! although it's what tells a better release apart when upgrading. This
+//! module picks the quality, source, and release group out of the original
+//! file name so the format can keep them.
+
+use std::path::Path;
+
+/// Video codecs named in release names
+const CODECS: &[&str] = &[
+    "x264", "x265", "h264", "h265", "h.264", "h.265", "hevc", "avc", "av1", "xvid", "divx",
+];
+
+/// Sources named in release names
+const SOURCES: &[&str] = &[
+    "web-dl", "webdl", "webrip", "web", "bluray", "blu-ray", "bdrip", "brrip", "remux", "hdtv",
+    "pdtv", "dvdrip", "dvd", "hdrip",
+];
+
+/// Release information read from an original file name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReleaseTags {
+    /// Resolution and video codec, e.g. "1080p x265"
+    pub quality: Option<String>,
+
+    /// Source of the release, e.g. "WEB-DL" or "BluRay REMUX"
+    pub source: Option<String>,
+
+    /// Release group, e.g. "NTb"
+    pub group: Option<String>,
+}
+
+/// Reads the release tags from the file name of a path
+///
+/// Tags are kept as spelled in the file name. The group is taken from a
+/// trailing `-GROUP` after other release tags, or from a leading
+/// `[Group]` as used by fansub releases.
+pub fn parse_release_tags(path: &Path) -> ReleaseTags {
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return ReleaseTags::default();
+    };
+
+    let tokens = release_tokens(stem);
+    let mut resolution = None;
+    let mut codec = None;
+    let mut sources = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let lower = token.to_lowercase();
+        // "H.264" is split at the dot
+        let dotted = tokens
+            .get(i + 1)
+            .map(|next| format!("{}.{}", token, next))
+            .filter(|dotted| CODECS.contains(&dotted.to_lowercase().as_str()));
+
+        if resolution.is_none() && is_resolution(&lower) {
+            resolution = Some(token.clone());
+        } else if codec.is_none() && (dotted.is_some() || CODECS.contains(&lower.as_str())) {
+            codec = Some(dotted.unwrap_or_else(|| token.clone()));
+        } else if SOURCES.contains(&lower.as_str()) && !sources.contains(token) {
+            sources.push(token.clone());
+        }
+    }
+
+    let quality: Vec<String> = resolution.into_iter().chain(codec).collect();
+    let has_tags = !quality.is_empty() || !sources.is_empty();
+    ReleaseTags {
+        quality: (!quality.is_empty()).then(|| quality.join(" ")),
+        source: (!sources.is_empty()).then(|| sources.join(" ")),
+        group: release_group(stem, has_tags),
+    }
+}
+
+/// Splits a name into the words release tags are made of
+///
+/// Dashes join words like `WEB-DL` but also separate the group, so dashed
+/// words are only kept together if they form a known tag.
+fn release_tokens(name: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in name.split([' ', '.', '_', '[', ']', '(', ')']) {
+        let parts: Vec<&str> = word.split('-').collect();
+        let mut i = 0;
+        while i < parts.len() {
+            if i + 1 < parts.len() && is_dashed_tag(&parts[i..i + 2].join("-")) {
+                tokens.push(parts[i..i + 2].join("-"));
+                i += 2;
+            } else {
+                tokens.push(parts[i].to_string());
+                i += 1;
+            }
+        }
+    }
+    tokens.retain(|token| !token.is_empty());
+    tokens
+}
+
+/// Checks whether a lowercase word is a resolution, codec, or source tag
+pub(crate) fn is_release_tag(word: &str) -> bool {
+    is_resolution(word) || CODECS.contains(&word) || SOURCES.contains(&word)
+}
+
+/// Checks for a tag that contains a dash, like `WEB-DL`
+fn is_dashed_tag(text: &str) -> bool {
+    SOURCES.contains(&text.to_lowercase().as_str())
+}
+
+/// Checks for a resolution like `1080p`, `720p`, `1080i`, or `4k`
+fn is_resolution(token: &str) -> bool {
+    if token == "4k" || token == "uhd" {
+        return true;
+    }
+    token.strip_suffix(['p', 'i']).is_some_and(|digits| {
+        (3..=4).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+/// Reads the release group from the start or the end of a name
+fn release_group(stem: &str, has_tags: bool) -> Option<String> {
+    if let Some(rest) = stem.strip_prefix('[')
+        && let Some((group, _)) = rest.split_once(']')
+        && !group.trim().is_empty()
+    {
+        return Some(group.trim().to_string());
+    }
+
+    // Only trust a trailing dash after other tags, so "Spider-Man" isn't a group
+    let (before, group) = stem.rsplit_once('-')?;
+    let previous = before
+        .rsplit([' ', '.', '_', '-'])
+        .next()
+        .unwrap_or_default();
+    let is_word = !group.is_empty() && group.chars().all(|c| c.is_ascii_alphanumeric());
+    let is_tag = is_dashed_tag(&format!("{}-{}", previous, group));
+    (has_tags && is_word && !is_tag).then(|| group.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(name: &str) -> (Option<String>, Option<String>, Option<String>) {
+        let tags = parse_release_tags(Path::new(name));
+        (tags.quality, tags.source, tags.group)
+    }
+
+    fn some(text: &str) -> Option<String> {
+        Some(text.to_string())
+    }
+
+    #[test]
+    fn test_parse_release_tags_scene_names() {
+        assert_eq!(
+            tags("Show.S01E02.1080p.WEB-DL.x265-GROUP.mkv"),
+            (some("1080p x265"), some("WEB-DL"), some("GROUP"))
+        );
+        assert_eq!(
+            tags("Show S01E02 2160p BluRay REMUX HEVC-FraMeSToR.mkv"),
+            (some("2160p HEVC"), some("BluRay REMUX"), some("FraMeSToR"))
+        );
+        assert_eq!(
+            tags("show.s01e02.720p.hdtv.h.264-lol.mp4"),
+            (some("720p h.264"), some("hdtv"), some("lol"))
+        );
+    }
+
+    #[test]
+    fn test_parse_release_tags_fansub_names() {
+        assert_eq!(
+            tags("[SubsPlease] Show - 02 (1080p) [A1B2C3D4].mkv"),
+            (some("1080p"), None, some("SubsPlease"))
+        );
+    }
+
+    #[test]
+    fn test_parse_release_tags_ignores_plain_names() {
+        assert_eq!(tags("Spider-Man - S01E02 - Title.mkv"), (None, None, None));
+        assert_eq!(tags("Show.S01E02.WEB-DL.mkv"), (None, some("WEB-DL"), None));
+        assert_eq!(tags("episode.mkv"), (None, None, None));
+    }
+}
@@ -0,0 +1,192 @@
+//! Retry module
+//!
+//! Video libraries often live on SMB or NFS shares, which now and then fail
+//! a read with a timeout or a stale handle although the file is fine. Reads
+//! and copies of such files are retried with a growing delay on transient
+//! errors, while permanent ones like a missing file fail right away.
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+use thiserror::Error;
+
+/// How often and how patiently to retry file operations failing transiently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, 0 to fail on the first error
+    pub retries: u32,
+
+    /// Delay before the first retry, doubled for each further one
+    pub initial_delay: Duration,
+
+    /// Longest delay between two attempts
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+        }
+    }
+}
+
+/// A transient error that persisted through all retries
+#[derive(Debug, Error)]
+#[error("{source} (transient error, gave up after {attempts} attempt(s))")]
+pub struct TransientError {
+    /// Number of attempts made, including the first
+    pub attempts: u32,
+
+    /// Error of the last attempt
+    pub source: io::Error,
+}
+
+impl RetryPolicy {
+    /// Creates a policy with the given number of retries and default delays
+    pub fn with_retries(retries: u32) -> Self {
+        Self {
+            retries,
+            ..Self::default()
+        }
+    }
+
+    /// Runs an operation, retrying it while it fails transiently
+    ///
+    /// Permanent errors are returned as they are. A transient error still
+    /// occurring after the last retry is returned wrapping a
+    /// `TransientError`, keeping the kind of the original error.
+    pub fn run<T>(&self, mut operation: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match operation() {
+                Err(e) if is_transient(&e) => {
+                    if attempts > self.retries {
+                        return Err(io::Error::new(
+                            e.kind(),
+                            TransientError {
+                                attempts,
+                                source: e,
+                            },
+                        ));
+                    }
+                    thread::sleep(self.delay(attempts));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns the delay after the given failed attempt, counted from 1
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+/// Checks whether an error is likely to go away when trying again
+///
+/// Covers timeouts, interruptions, and lost or stale network connections
+/// and handles. Anything else, like a missing file or lacking permissions,
+/// is considered permanent.
+pub fn is_transient(error: &io::Error) -> bool {
+    use io::ErrorKind::*;
+
+    if matches!(
+        error.kind(),
+        Interrupted
+            | TimedOut
+            | WouldBlock
+            | ResourceBusy
+            | ConnectionReset
+            | ConnectionAborted
+            | NotConnected
+            | BrokenPipe
+            | NetworkDown
+            | NetworkUnreachable
+            | HostUnreachable
+            | StaleNetworkFileHandle
+    ) {
+        return true;
+    }
+
+    // Errors of network file systems the standard library doesn't categorize
+    #[cfg(unix)]
+    let codes = [libc::EIO];
+    // ERROR_UNEXP_NET_ERR, ERROR_NETNAME_DELETED, ERROR_SEM_TIMEOUT
+    #[cfg(windows)]
+    let codes = [59, 64, 121];
+    #[cfg(not(any(unix, windows)))]
+    let codes: [i32; 0] = [];
+
+    error
+        .raw_os_error()
+        .is_some_and(|code| codes.contains(&code))
+}
+
+/// Checks whether an error is a transient one that persisted through all retries
+pub fn gave_up_retrying(error: &io::Error) -> bool {
+    error
+        .get_ref()
+        .is_some_and(|inner| inner.is::<TransientError>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy_run() {
+        let policy = RetryPolicy {
+            retries: 2,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+        };
+
+        let mut calls = 0;
+        let result = policy.run(|| {
+            calls += 1;
+            if calls < 3 {
+                Err(io::Error::from(io::ErrorKind::TimedOut))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let error = policy
+            .run(|| -> io::Result<()> {
+                calls += 1;
+                Err(io::Error::from(io::ErrorKind::TimedOut))
+            })
+            .unwrap_err();
+        assert_eq!(calls, 3);
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(gave_up_retrying(&error));
+
+        let mut calls = 0;
+        let error = policy
+            .run(|| -> io::Result<()> {
+                calls += 1;
+                Err(io::Error::from(io::ErrorKind::NotFound))
+            })
+            .unwrap_err();
+        assert_eq!(calls, 1);
+        assert!(!gave_up_retrying(&error));
+    }
+
+    #[test]
+    fn test_retry_policy_delay() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_millis(500));
+        assert_eq!(policy.delay(3), Duration::from_secs(2));
+        assert_eq!(policy.delay(10), Duration::from_secs(8));
+        assert_eq!(policy.delay(40), Duration::from_secs(8));
+    }
+}
@@ -0,0 +1,289 @@
+//! Season inference module
+//!
+//! Without a season filter, every episode of a show ends up in the matching
+//! prompt. This module narrows the candidates down to a single season ahead
+//! of matching, either from season markers in file and directory names
+//! (`S02E05`, `2x05`, `Season 2`) or from a short transcript excerpt. The
+//! same markers also provide the episode a file name claims to be, which is
+//! cross-checked against the dialogue match.
+
+use crate::metadata_retrieval::TVSeries;
+use crate::speech_to_text::Transcript;
+use serde::Serialize;
+use std::path::Path;
+
+/// Maximum number of transcript characters used to infer the season
+const EXCERPT_CHARS: usize = 2_000;
+
+/// Number of parent directories searched for season markers
+const PARENT_DIRS_SEARCHED: usize = 2;
+
+/// Where an inferred season came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SeasonSource {
+    /// A season marker in the file or directory name
+    Filename,
+    /// The LLM's guess based on a transcript excerpt
+    Transcript,
+}
+
+/// Reads the season from markers in a file name or its parent directories
+///
+/// The file name is checked first, followed by the closest parent
+/// directories (e.g. `Show/Season 2/episode.mkv`).
+pub(crate) fn season_from_path(path: &Path) -> Option<usize> {
+    let file_stem = path.file_stem().and_then(|s| s.to_str());
+    let parents = path
+        .ancestors()
+        .skip(1)
+        .take(PARENT_DIRS_SEARCHED)
+        .filter_map(|dir| dir.file_name().and_then(|s| s.to_str()));
+
+    file_stem
+        .into_iter()
+        .chain(parents)
+        .find_map(season_from_name)
+}
+
+/// Reads the season and episode number from the file name of a path
+///
+/// Only episode markers (`S02E05`, `2x05`) are considered; unlike seasons,
+/// episode numbers aren't taken from directory names.
+pub(crate) fn episode_from_path(path: &Path) -> Option<(usize, usize)> {
+    let name = path.file_stem()?.to_str()?.to_lowercase();
+    word_starts(&name).find_map(episode_marker)
+}
+
+/// Reads the season and all episodes from the file name of a path
+///
+/// Like `episode_from_path`, but multi-episode markers like `S04E12-E13` or
+/// `S04E12E14` yield every episode from the first to the last.
+pub(crate) fn episodes_from_path(path: &Path) -> Option<(usize, Vec<usize>)> {
+    let name = path.file_stem()?.to_str()?.to_lowercase();
+    word_starts(&name).find_map(|text| {
+        let (season, first, rest) = split_episode_marker(text)?;
+        let rest = rest.strip_prefix('-').unwrap_or(rest);
+        let last = rest
+            .strip_prefix('e')
+            .and_then(leading_episode)
+            .map_or(first, |(last, _)| last.max(first));
+        Some((season, (first..=last).collect()))
+    })
+}
+
+/// Reads the season from the first season marker in a name
+pub(crate) fn season_from_name(name: &str) -> Option<usize> {
+    let name = name.to_lowercase();
+
+    for rest in word_starts(&name) {
+        if let Some((season, _)) = episode_marker(rest) {
+            return Some(season);
+        }
+
+        for word in ["season", "staffel"] {
+            if let Some(after) = rest.strip_prefix(word)
+                && let Some((season, _)) =
+                    leading_number(after.trim_start_matches([' ', '.', '_', '-']))
+            {
+                return Some(season);
+            }
+        }
+    }
+
+    None
+}
+
+/// Returns the remainder of a name at the start of every word
+///
+/// Markers have to start a word, so "x264" or "doors1x02" don't count.
+fn word_starts(name: &str) -> impl Iterator<Item = &str> {
+    let bytes = name.as_bytes();
+    (0..bytes.len())
+        .filter(move |&start| {
+            name.is_char_boundary(start)
+                && (start == 0 || !bytes[start - 1].is_ascii_alphanumeric())
+        })
+        .map(move |start| &name[start..])
+}
+
+/// Reads season and episode from an episode marker like `s02e05` or `2x05`
+fn episode_marker(text: &str) -> Option<(usize, usize)> {
+    split_episode_marker(text).map(|(season, episode, _)| (season, episode))
+}
+
+/// Reads an episode marker like `episode_marker`, also returning the rest
+fn split_episode_marker(text: &str) -> Option<(usize, usize, &str)> {
+    let (season, rest, separator) = match text.strip_prefix('s') {
+        Some(after) => {
+            let (season, rest) = leading_number(after)?;
+            (season, rest, 'e')
+        }
+        None => {
+            let (season, rest) = leading_number(text)?;
+            (season, rest, 'x')
+        }
+    };
+
+    let (episode, rest) = leading_episode(rest.strip_prefix(separator)?)?;
+    Some((season, episode, rest))
+}
+
+/// Splits an episode number of any length off the start of a string
+fn leading_episode(text: &str) -> Option<(usize, &str)> {
+    let digits = text.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+
+    Some((text[..digits].parse().ok()?, &text[digits..]))
+}
+
+/// Splits a number of one or two digits off the start of a string
+fn leading_number(text: &str) -> Option<(usize, &str)> {
+    let digits = text.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 || digits > 2 {
+        return None;
+    }
+
+    Some((text[..digits].parse().ok()?, &text[digits..]))
+}
+
+/// Returns the start of a transcript, cut at a word boundary
+pub(crate) fn transcript_excerpt(transcript: &Transcript) -> Transcript {
+    let text = &transcript.text;
+    let excerpt = if text.chars().count() <= EXCERPT_CHARS {
+        text.as_str()
+    } else {
+        let end = text
+            .char_indices()
+            .nth(EXCERPT_CHARS)
+            .map_or(text.len(), |(i, _)| i);
+        let cut = &text[..end];
+        cut.rfind(char::is_whitespace).map_or(cut, |i| &cut[..i])
+    };
+
+    // The excerpt covers only part of the audio, so duration and timings don't apply
+    Transcript {
+        text: excerpt.to_string(),
+        language: transcript.language.clone(),
+        language_confidence: transcript.language_confidence,
+        duration: None,
+        model: transcript.model.clone(),
+        segments: Vec::new(),
+        average_probability: transcript.average_probability,
+        sample_length: None,
+    }
+}
+
+/// Returns a copy of the series containing only the given season
+///
+/// Returns None if the series has no such season.
+pub(crate) fn narrow_to_season(series: &TVSeries, season_number: usize) -> Option<TVSeries> {
+    let season = series
+        .seasons
+        .iter()
+        .find(|s| s.season_number == season_number)?;
+
+    Some(TVSeries {
+        name: series.name.clone(),
+        year: series.year,
+        seasons: vec![season.clone()],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_retrieval::Season;
+
+    #[test]
+    fn test_season_from_path_markers() {
+        assert_eq!(season_from_path(Path::new("/tv/Show.S02E05.mkv")), Some(2));
+        assert_eq!(season_from_path(Path::new("/tv/show 3x07.mp4")), Some(3));
+        assert_eq!(
+            season_from_path(Path::new("/tv/Show/Season 4/title_t01.mkv")),
+            Some(4)
+        );
+        assert_eq!(
+            season_from_path(Path::new("/tv/Show/Staffel_1/Disc 2/title.mkv")),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_season_from_path_ignores_false_positives() {
+        assert_eq!(season_from_path(Path::new("/rips/title_t00.mkv")), None);
+        assert_eq!(
+            season_from_path(Path::new("/rips/Movie.1920x1080.x264.mkv")),
+            None
+        );
+        assert_eq!(season_from_path(Path::new("/rips/S2020E01.mkv")), None);
+    }
+
+    #[test]
+    fn test_episodes_from_path() {
+        assert_eq!(
+            episodes_from_path(Path::new("/tv/Doctor Who - S04E12-E13 - Title.mkv")),
+            Some((4, vec![12, 13]))
+        );
+        assert_eq!(
+            episodes_from_path(Path::new("/tv/show.s01e01e03.mkv")),
+            Some((1, vec![1, 2, 3]))
+        );
+        assert_eq!(
+            episodes_from_path(Path::new("/tv/Show - S02E05 - 720p.mkv")),
+            Some((2, vec![5]))
+        );
+        assert_eq!(episodes_from_path(Path::new("/tv/Pilot.mkv")), None);
+    }
+
+    #[test]
+    fn test_episode_from_path() {
+        assert_eq!(
+            episode_from_path(Path::new("/tv/Show.S02E05.720p.mkv")),
+            Some((2, 5))
+        );
+        assert_eq!(
+            episode_from_path(Path::new("/tv/show 3x117.mp4")),
+            Some((3, 117))
+        );
+        assert_eq!(
+            episode_from_path(Path::new("/tv/Show/Season 4/title_t01.mkv")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_transcript_excerpt_cuts_at_word_boundary() {
+        let transcript = Transcript::new("word ".repeat(1_000), "en");
+
+        let excerpt = transcript_excerpt(&transcript);
+
+        assert!(excerpt.text.chars().count() <= EXCERPT_CHARS);
+        assert!(excerpt.text.ends_with("word"));
+        assert_eq!(excerpt.language, "en");
+    }
+
+    #[test]
+    fn test_narrow_to_season() {
+        let series = TVSeries {
+            name: "Show".to_string(),
+            year: None,
+            seasons: vec![
+                Season {
+                    season_number: 1,
+                    episodes: vec![],
+                },
+                Season {
+                    season_number: 2,
+                    episodes: vec![],
+                },
+            ],
+        };
+
+        let narrowed = narrow_to_season(&series, 2).unwrap();
+        assert_eq!(narrowed.seasons.len(), 1);
+        assert_eq!(narrowed.seasons[0].season_number, 2);
+        assert!(narrow_to_season(&series, 3).is_none());
+    }
+}
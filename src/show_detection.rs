@@ -0,0 +1,316 @@
+//! Show detection module
+//!
+//! Downloads and rips usually say which show they belong to somewhere:
+//! `Breaking.Bad.S01E02.720p.mkv`, or a directory called `Doctor Who
+//! (2005)`. This module reads candidate show names from file and directory
+//! names and keeps only those the metadata provider knows by exactly that
+//! name, so the show name can be left out when it is obvious.
+
+use crate::metadata_retrieval::{MetadataProvider, MetadataRetrievalError, SeriesCandidate};
+use crate::release_tags::is_release_tag;
+use crate::season_inference::season_from_name;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of guesses looked up with the metadata provider
+const MAX_GUESSES: usize = 5;
+
+/// Number of parent directories of the video directory used as guesses
+const PARENT_DIRS_SEARCHED: usize = 2;
+
+/// Words that end the show name part of a name, or are no show name at all
+const STOP_WORDS: &[&str] = &[
+    "season", "staffel", "series", "complete", "disc", "disk", "dvd", "part", "episode", "ep",
+    "title", "track", "video",
+];
+
+/// A show name read from a file or directory name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShowGuess {
+    /// The name, with separators replaced by spaces
+    pub name: String,
+
+    /// Premiere year following the name, e.g. "Doctor Who (2005)"
+    pub year: Option<u16>,
+}
+
+impl fmt::Display for ShowGuess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.year {
+            Some(year) => write!(f, "{} ({})", self.name, year),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Outcome of detecting the show of a directory
+#[derive(Debug, Clone)]
+pub struct ShowDetection {
+    /// Names read from the file and directory names, most likely first
+    pub guesses: Vec<ShowGuess>,
+
+    /// Series the metadata provider knows by the first guess it knows at all
+    ///
+    /// Empty if no guess was confirmed. Several candidates mean the name
+    /// is ambiguous, e.g. for remakes, and one has to be chosen.
+    pub candidates: Vec<SeriesCandidate>,
+}
+
+/// Reads show names from the videos of a directory and the directory itself
+///
+/// Names from files come first, the most frequent first, followed by the
+/// directory and its closest parents.
+pub(crate) fn guess_show_names(video_dir: &Path, video_paths: &[PathBuf]) -> Vec<ShowGuess> {
+    let mut counts: HashMap<String, (usize, ShowGuess)> = HashMap::new();
+    for guess in video_paths
+        .iter()
+        .filter_map(|path| path.file_stem()?.to_str())
+        .filter_map(guess_from_name)
+    {
+        counts
+            .entry(normalize(&guess.to_string()))
+            .or_insert((0, guess))
+            .0 += 1;
+    }
+    let mut from_files: Vec<(usize, ShowGuess)> = counts.into_values().collect();
+    from_files.sort_by(|(a, a_guess), (b, b_guess)| b.cmp(a).then(a_guess.name.cmp(&b_guess.name)));
+
+    let from_dirs = video_dir
+        .ancestors()
+        .take(PARENT_DIRS_SEARCHED + 1)
+        .filter_map(|dir| dir.file_name()?.to_str())
+        .filter_map(guess_from_name);
+
+    let mut guesses: Vec<ShowGuess> = Vec::new();
+    for guess in from_files
+        .into_iter()
+        .map(|(_, guess)| guess)
+        .chain(from_dirs)
+    {
+        if !guesses
+            .iter()
+            .any(|g| normalize(&g.to_string()) == normalize(&guess.to_string()))
+        {
+            guesses.push(guess);
+        }
+    }
+    guesses.truncate(MAX_GUESSES);
+    guesses
+}
+
+/// Looks the guesses up until the provider knows one of them by its name
+///
+/// Only series named exactly like the guess (ignoring case and punctuation)
+/// and, if the guess has one, premiering in its year count as confirmed.
+pub(crate) fn confirm_guesses(
+    provider: &dyn MetadataProvider,
+    guesses: &[ShowGuess],
+) -> Result<Vec<SeriesCandidate>, MetadataRetrievalError> {
+    for guess in guesses {
+        let name = normalize(&guess.name);
+        let confirmed: Vec<SeriesCandidate> = provider
+            .search_series(&guess.name)?
+            .into_iter()
+            .filter(|c| normalize(&c.name) == name)
+            .filter(|c| guess.year.is_none() || c.year == guess.year)
+            .collect();
+        if !confirmed.is_empty() {
+            return Ok(confirmed);
+        }
+    }
+
+    Ok(Vec::new())
+}
+
+/// Reads a show name from the start of a file or directory name
+///
+/// The name ends at the first episode marker, year, or release tag. Group
+/// tags in square brackets and anything after a ` - ` are left out.
+fn guess_from_name(name: &str) -> Option<ShowGuess> {
+    let name = without_square_brackets(name);
+    let name = name.split(" - ").next().unwrap_or_default();
+
+    let mut words = Vec::new();
+    let mut year = None;
+    for word in name
+        .split([' ', '.', '_', '(', ')'])
+        .filter(|w| !w.is_empty() && *w != "-")
+    {
+        let lower = word.to_lowercase();
+        // A year can't start the name, or "1883" would have none
+        if !words.is_empty()
+            && let Some(parsed) = parse_year(&lower)
+        {
+            year = Some(parsed);
+            break;
+        }
+        if STOP_WORDS.contains(&lower.as_str())
+            || is_release_tag(&lower)
+            || is_season_marker(&lower)
+        {
+            break;
+        }
+        words.push(word);
+    }
+
+    // Short names without letters are numbering, like "01" of "01 - Pilot"
+    let name = words.join(" ");
+    if name.len() < 4 && !name.chars().any(char::is_alphabetic) {
+        return None;
+    }
+
+    Some(ShowGuess { name, year })
+}
+
+/// Checks for episode markers (`s01e02`, `1x02`) and season packs (`s01`)
+fn is_season_marker(word: &str) -> bool {
+    let season_pack = word
+        .strip_prefix('s')
+        .is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()));
+    season_pack || season_from_name(word).is_some()
+}
+
+/// Parses a four-digit year between 1900 and 2099
+fn parse_year(word: &str) -> Option<u16> {
+    let year: u16 = word.parse().ok()?;
+    (word.len() == 4 && (1900..2100).contains(&year)).then_some(year)
+}
+
+/// Removes square brackets and their contents, like `[SubsPlease]`
+fn without_square_brackets(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut depth = 0usize;
+    for c in name.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => result.push(c),
+            _ => {}
+        }
+    }
+    result.trim().to_string()
+}
+
+/// Reduces a name to its lowercase letters and digits for comparison
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_retrieval::TVSeries;
+
+    fn guess(name: &str) -> Option<String> {
+        guess_from_name(name).map(|g| g.to_string())
+    }
+
+    #[test]
+    fn test_guess_from_name() {
+        assert_eq!(
+            guess("Breaking.Bad.S01E02.720p.WEB-DL-GROUP"),
+            Some("Breaking Bad".to_string())
+        );
+        assert_eq!(
+            guess("Doctor Who (2005) - S04E01"),
+            Some("Doctor Who (2005)".to_string())
+        );
+        assert_eq!(
+            guess("[SubsPlease] Spy x Family - 02 (1080p) [A1B2C3D4]"),
+            Some("Spy x Family".to_string())
+        );
+        assert_eq!(
+            guess("The_Expanse_Season_2"),
+            Some("The Expanse".to_string())
+        );
+        assert_eq!(guess("1883.S01.1080p"), Some("1883".to_string()));
+        assert_eq!(guess("Season 2"), None);
+        assert_eq!(guess("title_t00"), None);
+        assert_eq!(guess("01 - Pilot"), None);
+    }
+
+    #[test]
+    fn test_guess_show_names_order() {
+        let videos = vec![
+            PathBuf::from("/tv/Breaking Bad/Season 1/Breaking.Bad.S01E01.mkv"),
+            PathBuf::from("/tv/Breaking Bad/Season 1/Breaking.Bad.S01E02.mkv"),
+            PathBuf::from("/tv/Breaking Bad/Season 1/breaking bad 1x03.mkv"),
+            PathBuf::from("/tv/Breaking Bad/Season 1/Better.Call.Saul.S01E01.mkv"),
+        ];
+
+        let guesses: Vec<String> =
+            guess_show_names(Path::new("/tv/Breaking Bad/Season 1"), &videos)
+                .iter()
+                .map(ShowGuess::to_string)
+                .collect();
+        assert_eq!(guesses, vec!["Breaking Bad", "Better Call Saul", "tv"]);
+    }
+
+    struct FakeProvider;
+
+    impl MetadataProvider for FakeProvider {
+        fn id(&self) -> String {
+            "fake".to_string()
+        }
+
+        fn search_series(
+            &self,
+            series_name: &str,
+        ) -> Result<Vec<SeriesCandidate>, MetadataRetrievalError> {
+            let candidate = |id, name: &str, year| SeriesCandidate {
+                id,
+                name: name.to_string(),
+                year: Some(year),
+            };
+            Ok(match series_name {
+                "Doctor Who" => vec![
+                    candidate(1, "Doctor Who", 1963),
+                    candidate(2, "Doctor Who", 2005),
+                    candidate(3, "Doctor Who Confidential", 2005),
+                ],
+                "Downloads" => vec![candidate(4, "Download", 2014)],
+                _ => Vec::new(),
+            })
+        }
+
+        fn fetch_series(
+            &self,
+            candidate: &SeriesCandidate,
+            _season_numbers: Option<Vec<usize>>,
+        ) -> Result<TVSeries, MetadataRetrievalError> {
+            // Confirming guesses only searches
+            Err(MetadataRetrievalError::SeriesNotFound(
+                candidate.name.clone(),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_confirm_guesses() {
+        let guesses = |names: &[(&str, Option<u16>)]| -> Vec<ShowGuess> {
+            names
+                .iter()
+                .map(|(name, year)| ShowGuess {
+                    name: name.to_string(),
+                    year: *year,
+                })
+                .collect()
+        };
+        let ids =
+            |found: Vec<SeriesCandidate>| -> Vec<u64> { found.iter().map(|c| c.id).collect() };
+
+        let found = confirm_guesses(
+            &FakeProvider,
+            &guesses(&[("Downloads", None), ("Doctor Who", None)]),
+        );
+        assert_eq!(ids(found.unwrap()), vec![1, 2]);
+        let found = confirm_guesses(&FakeProvider, &guesses(&[("Doctor Who", Some(2005))]));
+        assert_eq!(ids(found.unwrap()), vec![2]);
+        let found = confirm_guesses(&FakeProvider, &guesses(&[("Downloads", None)]));
+        assert!(found.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+//! Sonarr integration module
+//!
+//! Libraries managed by Sonarr shouldn't be renamed behind its back. Instead
+//! of touching the files, this module hands the identified episodes to
+//! Sonarr's manual import, which then moves or copies them into the library
+//! and names them by its own rules.
+mod sonarr_types;
+
+use crate::MatchResult;
+use serde::de::DeserializeOwned;
+use sonarr_types::{
+    SonarrCommand, SonarrEpisode, SonarrImportCandidate, SonarrImportCommand, SonarrImportFile,
+    SonarrSeries,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Environment variable holding the Sonarr API key
+const API_KEY_ENV: &str = "SONARR_API_KEY";
+
+/// Errors that can occur while importing into Sonarr
+#[derive(Debug, Error)]
+pub enum SonarrError {
+    /// Request to the Sonarr API failed
+    #[error("Request failed: {0}")]
+    RequestError(String),
+
+    /// Failed to parse Sonarr's JSON response
+    #[error("Failed to parse API response: {0}")]
+    ParseError(String),
+
+    /// The series isn't in the Sonarr library
+    #[error("Series '{0}' not found in Sonarr")]
+    SeriesNotFound(String),
+}
+
+impl SonarrError {
+    /// Returns the stable code of the error, like `DD-SON-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            SonarrError::RequestError(_) => "DD-SON-001",
+            SonarrError::ParseError(_) => "DD-SON-002",
+            SonarrError::SeriesNotFound(_) => "DD-SON-003",
+        }
+    }
+}
+
+/// What Sonarr does with imported files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SonarrImportMode {
+    /// Move the files into the library (default)
+    #[default]
+    Move,
+    /// Copy the files into the library, leaving the originals
+    Copy,
+}
+
+/// Outcome of handing matches to Sonarr
+#[derive(Debug)]
+pub struct SonarrImport {
+    /// ID of the queued import command, None if no file could be imported
+    pub command_id: Option<u64>,
+
+    /// Files queued for import
+    pub queued: Vec<PathBuf>,
+
+    /// Files left out, with the reason
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// Client for the manual import of a Sonarr instance
+pub struct SonarrClient {
+    client: reqwest::blocking::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl SonarrClient {
+    /// Creates a client for the Sonarr instance at the given URL
+    pub fn new(url: &str, api_key: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            base_url: format!("{}/api/v3", url.trim_end_matches('/')),
+            api_key: api_key.into(),
+        }
+    }
+
+    /// Creates a client if `SONARR_API_KEY` is set in the environment
+    pub fn from_env(url: &str) -> Option<Self> {
+        std::env::var(API_KEY_ENV)
+            .ok()
+            .filter(|key| !key.trim().is_empty())
+            .map(|key| Self::new(url, key))
+    }
+
+    /// Queues a manual import of the matched files
+    ///
+    /// The series is looked up in the library by its name (or an alternate
+    /// title), using the premiere year to tell remakes apart. Sonarr has to
+    /// see the files at the same paths as DialogDetective; files its import
+    /// scan doesn't find, and episodes missing from its library, are
+    /// skipped. The import itself runs in Sonarr after this returns.
+    pub fn import(
+        &self,
+        matches: &[MatchResult],
+        series_name: &str,
+        year: Option<u16>,
+        mode: SonarrImportMode,
+    ) -> Result<SonarrImport, SonarrError> {
+        let series: Vec<SonarrSeries> = self.get("series", &[])?;
+        let series = find_series(&series, series_name, year)
+            .ok_or_else(|| SonarrError::SeriesNotFound(series_name.to_string()))?;
+        let series_id = series.id.to_string();
+
+        let episodes: Vec<SonarrEpisode> = self.get("episode", &[("seriesId", &series_id)])?;
+        let episode_ids: HashMap<(usize, usize), u64> = episodes
+            .iter()
+            .map(|e| ((e.season_number, e.episode_number), e.id))
+            .collect();
+
+        // Sonarr reports the quality and languages of the files it scanned
+        let folders: BTreeSet<PathBuf> = matches
+            .iter()
+            .filter_map(|m| absolute(&m.video.path).parent().map(Path::to_path_buf))
+            .collect();
+        let mut candidates = HashMap::new();
+        for folder in folders {
+            let folder = folder.to_string_lossy();
+            let scanned: Vec<SonarrImportCandidate> = self.get(
+                "manualimport",
+                &[
+                    ("folder", folder.as_ref()),
+                    ("seriesId", &series_id),
+                    ("filterExistingFiles", "false"),
+                ],
+            )?;
+            candidates.extend(scanned.into_iter().map(|c| (PathBuf::from(&c.path), c)));
+        }
+
+        let mut files = Vec::new();
+        let mut queued = Vec::new();
+        let mut skipped = Vec::new();
+        for result in matches {
+            let path = absolute(&result.video.path);
+            let Some(candidate) = candidates.remove(&path) else {
+                skipped.push((path, "not found by Sonarr's import scan".to_string()));
+                continue;
+            };
+
+            let wanted: Vec<(usize, usize)> = std::iter::once(&result.episode)
+                .chain(&result.extra_episodes)
+                .map(|e| (e.season_number, e.episode_number))
+                .collect();
+            let ids: Option<Vec<u64>> = wanted
+                .iter()
+                .map(|key| episode_ids.get(key).copied())
+                .collect();
+            let Some(ids) = ids else {
+                skipped.push((path, "episode not in the Sonarr library".to_string()));
+                continue;
+            };
+
+            files.push(SonarrImportFile {
+                path: candidate.path,
+                series_id: series.id,
+                episode_ids: ids,
+                quality: candidate.quality,
+                languages: candidate.languages,
+                release_group: candidate.release_group,
+            });
+            queued.push(path);
+        }
+
+        let command_id = if files.is_empty() {
+            None
+        } else {
+            let command = SonarrImportCommand {
+                name: "ManualImport",
+                import_mode: match mode {
+                    SonarrImportMode::Move => "move",
+                    SonarrImportMode::Copy => "copy",
+                },
+                files,
+            };
+            Some(self.post_command(&command)?.id)
+        };
+
+        Ok(SonarrImport {
+            command_id,
+            queued,
+            skipped,
+        })
+    }
+
+    /// Sends a GET request to an API endpoint and parses the JSON response
+    fn get<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, SonarrError> {
+        let response = self
+            .client
+            .get(format!("{}/{}", self.base_url, endpoint))
+            .header("X-Api-Key", &self.api_key)
+            .query(query)
+            .send()
+            .map_err(|e| SonarrError::RequestError(e.to_string()))?;
+        parse_response(response)
+    }
+
+    /// Queues a command
+    fn post_command(&self, command: &SonarrImportCommand) -> Result<SonarrCommand, SonarrError> {
+        let response = self
+            .client
+            .post(format!("{}/command", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .json(command)
+            .send()
+            .map_err(|e| SonarrError::RequestError(e.to_string()))?;
+        parse_response(response)
+    }
+}
+
+/// Checks the status of a response and parses its JSON body
+fn parse_response<T: DeserializeOwned>(
+    response: reqwest::blocking::Response,
+) -> Result<T, SonarrError> {
+    if !response.status().is_success() {
+        return Err(SonarrError::RequestError(format!(
+            "HTTP {} {}",
+            response.status().as_u16(),
+            response.status().canonical_reason().unwrap_or("Unknown")
+        )));
+    }
+
+    response
+        .json()
+        .map_err(|e| SonarrError::ParseError(e.to_string()))
+}
+
+/// Resolves a path the way Sonarr sees it, falling back to the path itself
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Finds a series by its title or an alternate title
+///
+/// Titles are compared by their letters and digits only, so "Marvel's
+/// Agents of S.H.I.E.L.D." matches "Marvels Agents of SHIELD". Among
+/// several series of the same name, the one from the given year wins.
+fn find_series<'a>(
+    library: &'a [SonarrSeries],
+    name: &str,
+    year: Option<u16>,
+) -> Option<&'a SonarrSeries> {
+    let normalize = |title: &str| -> String {
+        title
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    let name = normalize(name);
+
+    let found: Vec<&SonarrSeries> = library
+        .iter()
+        .filter(|series| {
+            std::iter::once(series.title.as_str())
+                .chain(series.alternate_titles.iter().map(|t| t.title.as_str()))
+                .any(|title| normalize(title) == name)
+        })
+        .collect();
+    found
+        .iter()
+        .find(|series| Some(series.year) == year)
+        .or(found.first())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_series() {
+        let library: Vec<SonarrSeries> = serde_json::from_str(
+            r#"[
+                {"id": 1, "title": "Doctor Who", "year": 1963, "alternateTitles": []},
+                {"id": 2, "title": "Doctor Who (2005)", "year": 2005, "alternateTitles": [{"title": "Doctor Who"}]},
+                {"id": 3, "title": "Marvel's Agents of S.H.I.E.L.D.", "year": 2013}
+            ]"#,
+        )
+        .unwrap();
+
+        let id = |name: &str, year: Option<u16>| find_series(&library, name, year).map(|s| s.id);
+        assert_eq!(id("Doctor Who", Some(2005)), Some(2));
+        assert_eq!(id("Doctor Who", Some(1963)), Some(1));
+        assert_eq!(id("doctor who", None), Some(1));
+        assert_eq!(id("Marvels Agents of SHIELD", None), Some(3));
+        assert_eq!(id("Torchwood", None), None);
+    }
+
+    #[test]
+    fn test_import_command_json() {
+        let command = SonarrImportCommand {
+            name: "ManualImport",
+            import_mode: "move",
+            files: vec![SonarrImportFile {
+                path: "/downloads/a.mkv".to_string(),
+                series_id: 2,
+                episode_ids: vec![10, 11],
+                quality: serde_json::json!({"quality": {"id": 7}}),
+                languages: serde_json::json!([{"id": 1}]),
+                release_group: None,
+            }],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&command).unwrap(),
+            serde_json::json!({
+                "name": "ManualImport",
+                "importMode": "move",
+                "files": [{
+                    "path": "/downloads/a.mkv",
+                    "seriesId": 2,
+                    "episodeIds": [10, 11],
+                    "quality": {"quality": {"id": 7}},
+                    "languages": [{"id": 1}]
+                }]
+            })
+        );
+    }
+}
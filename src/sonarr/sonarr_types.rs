@@ -0,0 +1,84 @@
+/// Sonarr API types for (de)serialization.
+///
+/// These structures mirror the subset of the Sonarr v3 REST API JSON
+/// needed to hand identified files to its manual import.
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// =========================================================
+// Library types (/series, /episode)
+// =========================================================
+
+/// A series in the Sonarr library.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SonarrSeries {
+    pub id: u64,
+    pub title: String,
+    /// Premiere year (0 if unknown)
+    #[serde(default)]
+    pub year: u16,
+    #[serde(default)]
+    pub alternate_titles: Vec<SonarrAlternateTitle>,
+}
+
+/// Another title a series is known by.
+#[derive(Debug, Deserialize)]
+pub(super) struct SonarrAlternateTitle {
+    pub title: String,
+}
+
+/// An episode of a series in the Sonarr library.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SonarrEpisode {
+    pub id: u64,
+    pub season_number: usize,
+    pub episode_number: usize,
+}
+
+// =========================================================
+// Manual import types (/manualimport, /command)
+// =========================================================
+
+/// A file found by Sonarr's manual import scan of a folder.
+///
+/// Quality and languages are passed back to the import command unchanged.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SonarrImportCandidate {
+    pub path: String,
+    #[serde(default)]
+    pub quality: Value,
+    #[serde(default)]
+    pub languages: Value,
+    pub release_group: Option<String>,
+}
+
+/// Body of the `ManualImport` command.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SonarrImportCommand {
+    pub name: &'static str,
+    pub import_mode: &'static str,
+    pub files: Vec<SonarrImportFile>,
+}
+
+/// A file to import, with the episodes it contains.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct SonarrImportFile {
+    pub path: String,
+    pub series_id: u64,
+    pub episode_ids: Vec<u64>,
+    pub quality: Value,
+    pub languages: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_group: Option<String>,
+}
+
+/// A queued command, as returned when posting one.
+#[derive(Debug, Deserialize)]
+pub(super) struct SonarrCommand {
+    pub id: u64,
+}
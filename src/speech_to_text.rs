@@ -1,14 +1,65 @@
 //! Speech-to-text module
 //!
-//! This module provides functionality to transcribe audio files to text
-//! using Whisper speech recognition.
+//! This module provides the `Transcriber` abstraction used by the pipeline
+//! to turn the dialogue of a video file into text. The built-in implementation
+//! transcribes audio locally using Whisper speech recognition and is only
+//! available with the `whisper` feature enabled. Builds without it can plug in
+//! an external transcriber (a cloud API, a remote service, ...) instead.
 
-use crate::audio_extraction::AudioFile;
+use crate::{DialogDetectiveError, ProgressEvent};
 use serde::{Deserialize, Serialize};
-use std::ops::Deref;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use thiserror::Error;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+#[cfg(feature = "whisper")]
+use crate::Stage;
+#[cfg(feature = "whisper")]
+use crate::audio_extraction::{AudioFile, audio_from_video};
+#[cfg(feature = "whisper")]
+use crate::file_resolver::VideoFile;
+#[cfg(feature = "whisper")]
+use crate::model_downloader::is_tinydiarize_model;
+#[cfg(feature = "whisper")]
+use std::ops::Deref;
+#[cfg(feature = "whisper")]
+use std::sync::{Arc, OnceLock};
+#[cfg(feature = "whisper")]
+use std::time::Instant;
+#[cfg(feature = "whisper")]
+use whisper_rs::{
+    FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState,
+};
+
+/// Sample rate of the audio passed to Whisper
+#[cfg(feature = "whisper")]
+const SAMPLE_RATE: u32 = 16_000;
+
+/// Marker inserted into the transcript text where the speaker changes
+///
+/// Only tinydiarize models (see `model_downloader::is_tinydiarize_model`)
+/// detect speaker changes.
+pub const SPEAKER_TURN_MARKER: &str = "[SPEAKER_TURN]";
+
+/// Fewest spoken words a transcript needs to count as having dialogue
+const MIN_DIALOGUE_WORDS: usize = 20;
+
+/// Fewest spoken words per minute of audio for a transcript to count as dialogue
+///
+/// Episodes have well over a hundred. Music and silence make Whisper
+/// produce a stray phrase every few minutes at most.
+const MIN_WORDS_PER_MINUTE: f64 = 5.0;
+
+/// Share of the audio with speech at which speech no longer lowers the quality score
+///
+/// Dialogue rarely fills more than half of an episode, and even quiet ones
+/// have speech in a third of it.
+const FULL_SPEECH_RATIO: f32 = 0.3;
+
+/// Length of audio Whisper considers when detecting the language
+#[cfg(feature = "whisper")]
+const LANGUAGE_DETECTION_WINDOW: Duration = Duration::from_secs(30);
 
 /// Errors that can occur during speech-to-text transcription
 #[derive(Debug, Error)]
@@ -36,57 +87,550 @@ pub enum SpeechToTextError {
     /// Model not initialized
     #[error("Whisper model not initialized")]
     ModelNotInitialized,
+
+    /// Transcription didn't finish within the transcription timeout
+    #[error("Transcription didn't finish within {}s and was stopped", .0.as_secs())]
+    TimedOut(Duration),
+}
+
+impl SpeechToTextError {
+    /// Returns the stable code of the error, like `DD-STT-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            SpeechToTextError::ModelLoadFailed { .. } => "DD-STT-001",
+            SpeechToTextError::AudioReadFailed { .. } => "DD-STT-002",
+            SpeechToTextError::InvalidAudioFormat(_) => "DD-STT-003",
+            SpeechToTextError::TranscriptionFailed(_) => "DD-STT-004",
+            SpeechToTextError::LanguageDetectionFailed(_) => "DD-STT-005",
+            SpeechToTextError::ModelNotInitialized => "DD-STT-006",
+            SpeechToTextError::TimedOut(_) => "DD-STT-007",
+        }
+    }
 }
 
 /// Represents a transcribed text with metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub(crate) struct Transcript {
+///
+/// Metadata a transcriber doesn't report is `None`. Transcripts cached by
+/// earlier versions lack it as well.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transcript {
     /// The transcribed text content
     pub text: String,
 
     /// Language detected during transcription
     pub language: String,
+
+    /// Probability of the detected language (0.0 to 1.0)
+    #[serde(default)]
+    pub language_confidence: Option<f32>,
+
+    /// Duration of the transcribed audio
+    #[serde(default)]
+    pub duration: Option<Duration>,
+
+    /// Name of the model that produced the transcript (e.g. "base")
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// The text split into timed segments, empty if the timings are unknown
+    #[serde(default)]
+    pub segments: Vec<TranscriptSegment>,
+
+    /// Mean probability of the transcribed tokens (0.0 to 1.0)
+    #[serde(default)]
+    pub average_probability: Option<f32>,
+
+    /// Length of the audio transcribed, if only its start was
+    #[serde(default)]
+    pub sample_length: Option<Duration>,
 }
 
-/// Transcribes audio to text using Whisper
-///
-/// This function analyzes the audio file and produces a text transcript
-/// of the spoken content. This is a key clue in solving the mystery of
-/// identifying unknown video files.
-///
-/// # Arguments
-///
-/// * `audio` - The audio file to transcribe
-/// * `model_path` - Path to the Whisper model file (e.g., ggml-base.bin)
+/// Heuristic quality of a transcript, see `Transcript::quality`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptQuality {
+    /// Overall score from 0.0 (useless) to 1.0 (no sign of problems)
+    pub score: f32,
+
+    /// Share of the audio covered by timed segments, None if unknown
+    pub speech_ratio: Option<f32>,
+
+    /// Mean probability of the transcribed tokens, None if unknown
+    pub average_probability: Option<f32>,
+
+    /// Share of segments (or sentences) repeating an earlier one
+    pub repetition_ratio: f32,
+}
+
+/// A stretch of the transcript with its position in the audio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    /// Start of the segment, from the start of the audio
+    pub start: Duration,
+
+    /// End of the segment, from the start of the audio
+    pub end: Duration,
+
+    /// The text spoken in the segment
+    pub text: String,
+
+    /// Whether a different speaker continues after the segment
+    ///
+    /// Only detected by tinydiarize models, false for all others.
+    #[serde(default)]
+    pub speaker_turn: bool,
+}
+
+impl Transcript {
+    /// Creates a transcript without metadata beyond the language
+    pub fn new(text: impl Into<String>, language: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            language: language.into(),
+            language_confidence: None,
+            duration: None,
+            model: None,
+            segments: Vec::new(),
+            average_probability: None,
+            sample_length: None,
+        }
+    }
+
+    /// Returns the length of the transcribed audio, if known
+    fn transcribed_duration(&self) -> Option<Duration> {
+        match (self.duration, self.sample_length) {
+            (Some(duration), Some(sample)) => Some(duration.min(sample)),
+            (duration, sample) => duration.or(sample),
+        }
+    }
+
+    /// Counts the spoken words, leaving out annotations of sounds and music
+    ///
+    /// Whisper puts sounds in brackets or parentheses (`[Music]`,
+    /// `(applause)`) and sung lyrics between music notes (`♪ ... ♪`), none
+    /// of which is dialogue.
+    pub fn spoken_words(&self) -> usize {
+        let mut spoken = String::with_capacity(self.text.len());
+        let mut depth = 0usize;
+        let mut singing = false;
+        for c in self.text.chars() {
+            match c {
+                '[' | '(' => depth += 1,
+                ']' | ')' => depth = depth.saturating_sub(1),
+                '♪' | '♫' => singing = !singing,
+                _ if depth == 0 && !singing => spoken.push(c),
+                _ => {}
+            }
+        }
+
+        spoken
+            .split_whitespace()
+            .filter(|word| word.chars().any(char::is_alphanumeric))
+            .count()
+    }
+
+    /// Checks whether the transcript has enough dialogue to be matched
+    ///
+    /// Concert recordings, blank videos, or broken audio yield transcripts
+    /// that are empty or nearly so, which matchers tend to match anyway by
+    /// making up an episode. Transcripts count as dialogue if they have a
+    /// minimum number of spoken words, and, if the duration of the audio is
+    /// known, a minimum number per minute.
+    pub fn has_dialogue(&self) -> bool {
+        let words = self.spoken_words();
+        if words < MIN_DIALOGUE_WORDS {
+            return false;
+        }
+        let minutes = self
+            .transcribed_duration()
+            .map_or(0.0, |d| d.as_secs_f64() / 60.0);
+        minutes == 0.0 || words as f64 / minutes >= MIN_WORDS_PER_MINUTE
+    }
+
+    /// Rates how trustworthy the transcript is
+    ///
+    /// Whisper fails in recognizable ways: on music or noise it gets stuck
+    /// repeating a phrase, it is unsure of the tokens it transcribes from
+    /// unclear audio, and it drops long stretches it can't make out. The
+    /// score multiplies the share of unrepeated segments, the mean token
+    /// probability, and the speech ratio relative to `FULL_SPEECH_RATIO`.
+    /// Measures that are unknown, as for transcripts of other transcribers,
+    /// don't lower the score. Transcripts without spoken words score 0.
+    pub fn quality(&self) -> TranscriptQuality {
+        let duration = self.transcribed_duration().map_or(0.0, |d| d.as_secs_f32());
+        let speech_ratio = (!self.segments.is_empty() && duration > 0.0).then(|| {
+            let speech: f32 = self
+                .segments
+                .iter()
+                .map(|s| s.end.saturating_sub(s.start).as_secs_f32())
+                .sum();
+            (speech / duration).min(1.0)
+        });
+
+        let lines: Vec<String> = if self.segments.is_empty() {
+            self.text
+                .split(['.', '!', '?'])
+                .map(normalize_line)
+                .filter(|line| !line.is_empty())
+                .collect()
+        } else {
+            self.segments
+                .iter()
+                .map(|s| normalize_line(&s.text))
+                .collect()
+        };
+        let mut seen = HashSet::new();
+        let repeated = lines.iter().filter(|line| !seen.insert(*line)).count();
+        let repetition_ratio = if lines.is_empty() {
+            0.0
+        } else {
+            repeated as f32 / lines.len() as f32
+        };
+
+        let score = if self.spoken_words() == 0 {
+            0.0
+        } else {
+            (1.0 - repetition_ratio)
+                * self.average_probability.unwrap_or(1.0)
+                * speech_ratio.map_or(1.0, |ratio| (ratio / FULL_SPEECH_RATIO).min(1.0))
+        };
+
+        TranscriptQuality {
+            score,
+            speech_ratio,
+            average_probability: self.average_probability,
+            repetition_ratio,
+        }
+    }
+
+    /// Renders the timed segments as SubRip (SRT) subtitles
+    ///
+    /// Returns None if the transcript has no timed segments.
+    pub fn to_srt(&self) -> Option<String> {
+        if self.segments.is_empty() {
+            return None;
+        }
+
+        let timestamp = |time: Duration| {
+            let millis = time.as_millis();
+            format!(
+                "{:02}:{:02}:{:02},{:03}",
+                millis / 3_600_000,
+                millis / 60_000 % 60,
+                millis / 1000 % 60,
+                millis % 1000
+            )
+        };
+
+        let srt = self
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(index, segment)| {
+                format!(
+                    "{}\n{} --> {}\n{}\n",
+                    index + 1,
+                    timestamp(segment.start),
+                    timestamp(segment.end),
+                    segment.text
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(srt)
+    }
+}
+
+/// Reduces a line to its lowercase letters, digits, and single spaces
+fn normalize_line(line: &str) -> String {
+    line.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Where the transcript of a video came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TranscriptSource {
+    /// Loaded from the transcript cache
+    Cache,
+    /// Transcribed during this run
+    Fresh,
+}
+
+/// Policy for reusing cached transcripts made with a different model
 ///
-/// # Returns
+/// Cached transcripts record the model that produced them. Transcripts
+/// cached before models were recorded are treated as made by an unknown
+/// model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TranscriptReuse {
+    /// Only reuse transcripts made with the transcriber's model (default)
+    #[default]
+    SameModel,
+    /// Also reuse transcripts made with a larger Whisper model
+    SameOrBetterModel,
+    /// Reuse any cached transcript, regardless of its model
+    AnyModel,
+}
+
+impl TranscriptReuse {
+    /// Decides whether a transcript cached with `cached_model` may be used
+    /// in place of transcribing with `model`
+    pub(crate) fn accepts(&self, cached_model: Option<&str>, model: Option<&str>) -> bool {
+        match self {
+            Self::SameModel => cached_model == model,
+            Self::SameOrBetterModel => {
+                cached_model == model
+                    || matches!(
+                        (cached_model.and_then(model_rank), model.and_then(model_rank)),
+                        (Some(cached), Some(wanted)) if cached >= wanted
+                    )
+            }
+            Self::AnyModel => true,
+        }
+    }
+}
+
+/// Ranks Whisper models by size, ignoring language and quantization variants
 ///
-/// A `Transcript` containing the transcribed text and metadata,
-/// or an error if transcription fails.
+/// Returns None for models that aren't known Whisper models.
+fn model_rank(model: &str) -> Option<u8> {
+    let family = model.split(['.', '-']).next()?;
+    ["tiny", "base", "small", "medium", "large"]
+        .iter()
+        .position(|&known| known == family)
+        .map(|rank| rank as u8)
+}
+
+/// The transcript a match was based on
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TranscriptEvidence {
+    /// The transcript passed to the matcher
+    pub transcript: Transcript,
+
+    /// Whether the transcript was cached or freshly transcribed
+    pub source: TranscriptSource,
+}
+
+/// A source of transcripts for video files
 ///
-/// # Examples
+/// The investigation pipeline only depends on this trait for turning a video
+/// into text. Implement it to use a transcription backend other than the
+/// built-in Whisper one, e.g. when building without the `whisper` feature.
+pub trait Transcriber {
+    /// Transcribes the dialogue of the given video file
+    ///
+    /// Implementations may report intermediate steps through
+    /// `progress_callback` (e.g. `ProgressEvent::AudioExtraction`). If no
+    /// `ProgressEvent::StageCompleted` event for `Stage::Transcription` is
+    /// reported, the whole call is timed as the transcription stage. Failures
+    /// of custom backends can be reported as
+    /// `SpeechToTextError::TranscriptionFailed`.
+    fn transcribe(
+        &self,
+        video_path: &Path,
+        progress_callback: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<Transcript, DialogDetectiveError>;
+
+    /// Identifies the model transcripts are produced with
+    ///
+    /// Used to decide whether a cached transcript can be reused (see
+    /// `TranscriptReuse`), so it should change with every setting that
+    /// affects the output. Defaults to None, which only matches transcripts
+    /// cached without a model.
+    fn model(&self) -> Option<String> {
+        None
+    }
+}
+
+impl<T: Transcriber + ?Sized> Transcriber for &T {
+    fn transcribe(
+        &self,
+        video_path: &Path,
+        progress_callback: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<Transcript, DialogDetectiveError> {
+        (**self).transcribe(video_path, progress_callback)
+    }
+
+    fn model(&self) -> Option<String> {
+        (**self).model()
+    }
+}
+
+/// Transcriber extracting audio with FFmpeg and transcribing it locally with Whisper
 ///
-/// ```ignore
-/// let audio = audio_from_video(&video).unwrap();
-/// let model_path = Path::new("models/ggml-base.bin");
-/// let transcript = audio_to_text(&audio, model_path).unwrap();
-/// println!("Transcribed: {}", transcript.text);
-/// ```
-pub(crate) fn audio_to_text(
-    audio: &AudioFile,
-    model_path: &Path,
-) -> Result<Transcript, SpeechToTextError> {
+/// The model is loaded on the first transcription and kept for all later
+/// ones. Clones share the loaded model, so one transcriber can serve
+/// several investigations. Builds with the `coreml` feature run the encoder
+/// with Core ML if its `.mlmodelc` directory lies next to the model (see
+/// `model_downloader::coreml_encoder_path`), and fall back to running it
+/// without Core ML otherwise.
+#[cfg(feature = "whisper")]
+#[derive(Clone)]
+pub struct WhisperTranscriber {
+    model_path: PathBuf,
+    context: Arc<OnceLock<WhisperContext>>,
+    sample_length: Option<Duration>,
+    extraction_timeout: Option<Duration>,
+    transcription_timeout: Option<Duration>,
+}
+
+#[cfg(feature = "whisper")]
+impl WhisperTranscriber {
+    /// Creates a new transcriber using the given Whisper model file
+    pub fn new(model_path: impl Into<PathBuf>) -> Self {
+        Self {
+            model_path: model_path.into(),
+            context: Arc::default(),
+            sample_length: None,
+            extraction_timeout: None,
+            transcription_timeout: None,
+        }
+    }
+
+    /// Transcribes only the start of the audio, up to the given length
+    ///
+    /// Much faster for long videos, and often enough dialogue to identify
+    /// the episode. The length becomes part of the model (`base@300s`), so
+    /// sampled transcripts are only reused by transcribers sampling the
+    /// same length. Clones share the loaded model, so one model can
+    /// transcribe samples of different lengths.
+    pub fn sample_length(mut self, length: Duration) -> Self {
+        self.sample_length = Some(length);
+        self
+    }
+
+    /// Stops extracting the audio of a video after the given time
+    ///
+    /// FFmpeg can hang on damaged files. It is killed once the timeout has
+    /// passed, and the transcription fails with
+    /// `AudioExtractionError::TimedOut`.
+    pub fn extraction_timeout(mut self, timeout: Duration) -> Self {
+        self.extraction_timeout = Some(timeout);
+        self
+    }
+
+    /// Stops transcribing a video after the given time
+    ///
+    /// Whisper is aborted once the timeout has passed, and the
+    /// transcription fails with `SpeechToTextError::TimedOut`. Loading the
+    /// model on the first transcription doesn't count towards it.
+    pub fn transcription_timeout(mut self, timeout: Duration) -> Self {
+        self.transcription_timeout = Some(timeout);
+        self
+    }
+
+    /// Loads the model now instead of on the first transcription
+    ///
+    /// Lets long-running processes pay the load time up front, and surfaces
+    /// a broken model file before any video is processed.
+    pub fn preload(&self) -> Result<(), SpeechToTextError> {
+        self.context().map(|_| ())
+    }
+
+    /// Returns the loaded model, loading it on first use
+    fn context(&self) -> Result<&WhisperContext, SpeechToTextError> {
+        if let Some(context) = self.context.get() {
+            return Ok(context);
+        }
+        let context = load_model(&self.model_path)?;
+        Ok(self.context.get_or_init(|| context))
+    }
+}
+
+#[cfg(feature = "whisper")]
+impl std::fmt::Debug for WhisperTranscriber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WhisperTranscriber")
+            .field("model_path", &self.model_path)
+            .field("sample_length", &self.sample_length)
+            .field("extraction_timeout", &self.extraction_timeout)
+            .field("transcription_timeout", &self.transcription_timeout)
+            .field("loaded", &self.context.get().is_some())
+            .finish()
+    }
+}
+
+#[cfg(feature = "whisper")]
+impl Transcriber for WhisperTranscriber {
+    fn transcribe(
+        &self,
+        video_path: &Path,
+        progress_callback: &mut dyn FnMut(ProgressEvent),
+    ) -> Result<Transcript, DialogDetectiveError> {
+        let video = VideoFile::new(video_path);
+
+        progress_callback(ProgressEvent::AudioExtraction {
+            video_path: video.path.clone(),
+            temp_path: PathBuf::new(), // Will be set after extraction
+        });
+        let started = Instant::now();
+        let audio = audio_from_video(&video, self.extraction_timeout)?;
+        progress_callback(ProgressEvent::AudioExtractionFinished {
+            video_path: video.path.clone(),
+            temp_path: audio.to_path_buf(),
+        });
+        progress_callback(ProgressEvent::StageCompleted {
+            stage: Stage::AudioExtraction,
+            duration: started.elapsed(),
+        });
+
+        progress_callback(ProgressEvent::Transcription {
+            video_path: video.path.clone(),
+            temp_path: audio.to_path_buf(),
+        });
+        let started = Instant::now();
+        let model = self.model();
+        let speaker_turns = model.as_deref().is_some_and(is_tinydiarize_model);
+        let context = self.context()?;
+        let transcript = Transcript {
+            model,
+            ..audio_to_text(
+                &audio,
+                context,
+                speaker_turns,
+                self.sample_length,
+                self.transcription_timeout,
+            )?
+        };
+        progress_callback(ProgressEvent::StageCompleted {
+            stage: Stage::Transcription,
+            duration: started.elapsed(),
+        });
+
+        Ok(transcript)
+    }
+
+    // Sampling settings are fixed, so the model name and the sample length
+    // identify the output
+    fn model(&self) -> Option<String> {
+        let name = model_name(&self.model_path)?;
+        Some(match self.sample_length {
+            Some(length) => format!("{}@{}s", name, length.as_secs()),
+            None => name,
+        })
+    }
+}
+
+/// Loads a Whisper model with GPU acceleration enabled
+#[cfg(feature = "whisper")]
+pub(crate) fn load_model(model_path: &Path) -> Result<WhisperContext, SpeechToTextError> {
     // Suppress whisper.cpp log output by installing logging hooks.
     // Since we don't have the log_backend or tracing_backend features enabled,
     // this effectively silences all whisper.cpp and GGML logs to stdout/stderr.
     // Safe to call multiple times - only has effect on first call.
     whisper_rs::install_logging_hooks();
 
-    // Load Whisper model with GPU acceleration enabled
     let mut params = WhisperContextParameters::default();
     params.use_gpu(true); // Enable GPU (Metal on macOS, CUDA, or Vulkan) - falls back to CPU if unavailable
 
-    let ctx = WhisperContext::new_with_params(
+    WhisperContext::new_with_params(
         model_path
             .to_str()
             .ok_or_else(|| SpeechToTextError::ModelLoadFailed {
@@ -98,8 +642,45 @@ pub(crate) fn audio_to_text(
     .map_err(|e| SpeechToTextError::ModelLoadFailed {
         path: model_path.to_path_buf(),
         message: e.to_string(),
-    })?;
+    })
+}
 
+/// Transcribes audio to text using Whisper
+///
+/// This function analyzes the audio file and produces a text transcript
+/// of the spoken content. This is a key clue in solving the mystery of
+/// identifying unknown video files.
+///
+/// # Arguments
+///
+/// * `audio` - The audio file to transcribe
+/// * `ctx` - The loaded Whisper model (see `load_model`)
+/// * `speaker_turns` - Whether to mark speaker changes, which requires a
+///   tinydiarize model
+/// * `sample_length` - Transcribe only this much of the start of the audio
+/// * `timeout` - Abort Whisper if it hasn't finished after this long
+///
+/// # Returns
+///
+/// A `Transcript` containing the transcribed text and metadata (without
+/// the model name), or an error if transcription fails.
+///
+/// # Examples
+///
+/// ```ignore
+/// let audio = audio_from_video(&video).unwrap();
+/// let ctx = load_model(Path::new("models/ggml-base.bin")).unwrap();
+/// let transcript = audio_to_text(&audio, &ctx, false, None, None).unwrap();
+/// println!("Transcribed: {}", transcript.text);
+/// ```
+#[cfg(feature = "whisper")]
+pub(crate) fn audio_to_text(
+    audio: &AudioFile,
+    ctx: &WhisperContext,
+    speaker_turns: bool,
+    sample_length: Option<Duration>,
+    timeout: Option<Duration>,
+) -> Result<Transcript, SpeechToTextError> {
     // Read WAV file
     let reader =
         hound::WavReader::open(audio.deref()).map_err(|e| SpeechToTextError::AudioReadFailed {
@@ -109,7 +690,7 @@ pub(crate) fn audio_to_text(
 
     // Verify audio format (16kHz mono as extracted by ffmpeg)
     let spec = reader.spec();
-    if spec.sample_rate != 16000 {
+    if spec.sample_rate != SAMPLE_RATE {
         return Err(SpeechToTextError::InvalidAudioFormat(format!(
             "Expected 16kHz sample rate, got {} Hz",
             spec.sample_rate
@@ -139,12 +720,23 @@ pub(crate) fn audio_to_text(
     // Drop i16 samples immediately to free memory
     drop(samples);
 
+    let duration = Duration::from_secs_f64(audio_data.len() as f64 / f64::from(SAMPLE_RATE));
+    let sample_length = sample_length.filter(|length| *length < duration);
+    if let Some(length) = sample_length {
+        audio_data.truncate((length.as_secs_f64() * f64::from(SAMPLE_RATE)) as usize);
+    }
+
     // Create transcription parameters
     let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
     params.set_print_special(false);
     params.set_print_progress(false);
     params.set_print_realtime(false);
     params.set_print_timestamps(false);
+    params.set_tdrz_enable(speaker_turns);
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    if let Some(deadline) = deadline {
+        params.set_abort_callback_safe(move || Instant::now() >= deadline);
+    }
 
     // Create a state for transcription
     let mut state = ctx.create_state().map_err(|e| {
@@ -154,25 +746,204 @@ pub(crate) fn audio_to_text(
     // Run transcription
     state
         .full(params, &audio_data[..])
-        .map_err(|e| SpeechToTextError::TranscriptionFailed(e.to_string()))?;
-
-    // Drop audio data immediately to free memory
-    drop(audio_data);
+        .map_err(|e| match timeout {
+            Some(timeout) if deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                SpeechToTextError::TimedOut(timeout)
+            }
+            _ => SpeechToTextError::TranscriptionFailed(e.to_string()),
+        })?;
 
     // Get detected language
     let lang_id = state.full_lang_id_from_state();
     let language = whisper_rs::get_lang_str(lang_id)
         .ok_or(SpeechToTextError::LanguageDetectionFailed(lang_id))?
         .to_string();
+    let language_confidence = language_probabilities(&mut state, &audio_data)
+        .and_then(|probabilities| probabilities.get(usize::try_from(lang_id).ok()?).copied());
 
-    // Extract transcribed text from segments
+    // Drop audio data immediately to free memory
+    drop(audio_data);
+
+    // Extract transcribed text from segments, with timestamps in centiseconds
     let mut text = String::new();
+    let mut segments = Vec::new();
+    let mut probability_sum = 0.0;
+    let mut token_count = 0usize;
     for segment in state.as_iter() {
-        text.push_str(&format!("{}", segment));
+        // Special tokens like timestamps don't carry transcribed speech
+        for token in (0..segment.n_tokens()).filter_map(|i| segment.get_token(i)) {
+            if token.to_str().is_ok_and(|t| !t.starts_with("[_")) {
+                probability_sum += token.token_probability();
+                token_count += 1;
+            }
+        }
+
+        let segment_text = format!("{}", segment);
+        let speaker_turn = speaker_turns && segment.next_segment_speaker_turn();
+        text.push_str(&segment_text);
+        if speaker_turn {
+            text.push(' ');
+            text.push_str(SPEAKER_TURN_MARKER);
+        }
+
+        let time = |centiseconds: i64| Duration::from_millis(centiseconds.max(0) as u64 * 10);
+        if !segment_text.trim().is_empty() {
+            segments.push(TranscriptSegment {
+                start: time(segment.start_timestamp()),
+                end: time(segment.end_timestamp()),
+                text: segment_text.trim().to_string(),
+                speaker_turn,
+            });
+        }
     }
 
     Ok(Transcript {
         text: text.trim().to_string(),
         language,
+        language_confidence,
+        duration: Some(duration),
+        model: None,
+        segments,
+        average_probability: (token_count > 0).then(|| probability_sum / token_count as f32),
+        sample_length,
     })
 }
+
+/// Returns Whisper's probability for every language, based on the start of the audio
+///
+/// Language detection is only used for reporting, so failures yield None.
+/// Has to run after the transcription, as it replaces the state's
+/// spectrogram.
+#[cfg(feature = "whisper")]
+fn language_probabilities(state: &mut WhisperState, audio_data: &[f32]) -> Option<Vec<f32>> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get().min(4));
+    let window =
+        (LANGUAGE_DETECTION_WINDOW.as_secs() as usize * SAMPLE_RATE as usize).min(audio_data.len());
+
+    state.pcm_to_mel(&audio_data[..window], threads).ok()?;
+    let (_, probabilities) = state.lang_detect(0, threads).ok()?;
+    Some(probabilities)
+}
+
+/// Derives a model name from a model file path ("ggml-base.bin" becomes "base")
+#[cfg(feature = "whisper")]
+fn model_name(model_path: &Path) -> Option<String> {
+    let stem = model_path.file_stem()?.to_str()?;
+    Some(stem.strip_prefix("ggml-").unwrap_or(stem).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript_without_metadata_deserializes() {
+        // Transcripts cached before the metadata was added
+        let transcript: Transcript =
+            serde_json::from_str(r#"{"text": "Say my name.", "language": "en"}"#).unwrap();
+
+        assert_eq!(transcript, Transcript::new("Say my name.", "en"));
+    }
+
+    #[test]
+    fn test_transcript_to_srt() {
+        let mut transcript = Transcript::new("Say my name. You're Heisenberg.", "en");
+        assert_eq!(transcript.to_srt(), None);
+
+        transcript.segments = vec![
+            TranscriptSegment {
+                start: Duration::from_millis(1_500),
+                end: Duration::from_millis(3_250),
+                text: "Say my name.".to_string(),
+                speaker_turn: false,
+            },
+            TranscriptSegment {
+                start: Duration::from_secs(3_725),
+                end: Duration::from_millis(3_727_010),
+                text: "You're Heisenberg.".to_string(),
+                speaker_turn: false,
+            },
+        ];
+        assert_eq!(
+            transcript.to_srt().unwrap(),
+            "1\n00:00:01,500 --> 00:00:03,250\nSay my name.\n\n\
+             2\n01:02:05,000 --> 01:02:07,010\nYou're Heisenberg.\n"
+        );
+    }
+
+    #[test]
+    fn test_transcript_has_dialogue() {
+        let dialogue = "Say my name. You're Heisenberg. You're goddamn right. \
+                        I am the one who knocks, so stop worrying about me, Skyler.";
+        let transcript = Transcript::new(dialogue, "en");
+        assert_eq!(transcript.spoken_words(), 20);
+        assert!(transcript.has_dialogue());
+
+        let transcript = Transcript::new("", "en");
+        assert!(!transcript.has_dialogue());
+
+        let music = "[Music] ♪ Oh, say can you see by the dawn's early light ♪ \
+                     (applause) [SPEAKER_TURN] Thank you.";
+        let transcript = Transcript::new(music, "en");
+        assert_eq!(transcript.spoken_words(), 2);
+        assert!(!transcript.has_dialogue());
+
+        // A few phrases in two hours of audio are no dialogue either
+        let transcript = Transcript {
+            duration: Some(Duration::from_secs(2 * 3600)),
+            ..Transcript::new(dialogue, "en")
+        };
+        assert!(!transcript.has_dialogue());
+    }
+
+    #[test]
+    fn test_transcript_quality() {
+        let segment = |start: u64, end: u64, text: &str| TranscriptSegment {
+            start: Duration::from_secs(start),
+            end: Duration::from_secs(end),
+            text: text.to_string(),
+            speaker_turn: false,
+        };
+
+        let mut transcript = Transcript::new("Say my name. You're Heisenberg.", "en");
+        let quality = transcript.quality();
+        assert_eq!(quality.score, 1.0);
+        assert_eq!(quality.speech_ratio, None);
+        assert_eq!(quality.repetition_ratio, 0.0);
+
+        transcript.duration = Some(Duration::from_secs(100));
+        transcript.average_probability = Some(0.8);
+        transcript.segments = vec![
+            segment(0, 5, "Say my name."),
+            segment(10, 15, "You're Heisenberg."),
+            segment(20, 25, "say my name"),
+            segment(30, 35, "Say my name!"),
+        ];
+        let quality = transcript.quality();
+        assert_eq!(quality.speech_ratio, Some(0.2));
+        assert_eq!(quality.repetition_ratio, 0.5);
+        assert!((quality.score - 0.5 * 0.8 * (0.2 / FULL_SPEECH_RATIO)).abs() < 1e-6);
+
+        // Only the sampled start of the audio was transcribed
+        transcript.sample_length = Some(Duration::from_secs(40));
+        assert_eq!(transcript.quality().speech_ratio, Some(0.5));
+
+        assert_eq!(Transcript::new("[Music]", "en").quality().score, 0.0);
+    }
+
+    #[test]
+    fn test_transcript_reuse_policies() {
+        let same = TranscriptReuse::SameModel;
+        assert!(same.accepts(Some("base"), Some("base")));
+        assert!(!same.accepts(Some("large-v3"), Some("base")));
+        assert!(!same.accepts(None, Some("base")));
+
+        let better = TranscriptReuse::SameOrBetterModel;
+        assert!(better.accepts(Some("large-v3-turbo"), Some("base")));
+        assert!(better.accepts(Some("small.en"), Some("small")));
+        assert!(!better.accepts(Some("tiny"), Some("base")));
+        assert!(!better.accepts(Some("custom"), Some("base")));
+
+        assert!(TranscriptReuse::AnyModel.accepts(None, Some("base")));
+    }
+}
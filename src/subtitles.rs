@@ -0,0 +1,133 @@
+//! Subtitle output module
+//!
+//! Whisper already produced a timed transcript of every matched video, which
+//! makes a serviceable subtitle track for files that have none. The
+//! transcript is written as a SubRip file, either next to the video with its
+//! new name or muxed into Matroska files as an additional subtitle track.
+
+use crate::tagging::{is_matroska, keep_modified_time};
+use crate::{MatchResult, PlannedOperation, Transcript};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where to put the subtitles generated from the transcripts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleMode {
+    /// An SRT file next to the video, e.g. `Show - S01E01.en.srt`
+    Sidecar,
+
+    /// A subtitle track in the video for MKV files, a sidecar for others
+    Embed,
+}
+
+/// Writes the transcripts of the matched videos as subtitles
+///
+/// Needs the transcripts on the match results (see
+/// `Investigation::include_transcripts`). Quarantined duplicates are
+/// skipped. Videos identified by their file hash and transcripts cached
+/// without timings have no subtitles to write and are reported as errors.
+/// Embedding keeps the modification time of the file. Returns the errors
+/// of files that got no subtitles.
+pub fn write_subtitles(
+    operations: &[PlannedOperation],
+    matches: &[MatchResult],
+    mode: SubtitleMode,
+) -> Vec<io::Error> {
+    let mut errors = Vec::new();
+
+    for op in operations.iter().filter(|op| !op.quarantined) {
+        let transcript = matches
+            .iter()
+            .find(|m| m.video.path == op.source)
+            .and_then(|m| m.transcript.as_ref())
+            .map(|evidence| &evidence.transcript);
+
+        let result = match transcript.and_then(|t| t.to_srt().map(|srt| (t, srt))) {
+            Some((transcript, srt)) => {
+                if mode == SubtitleMode::Embed && is_matroska(&op.destination) {
+                    keep_modified_time(&op.destination, || {
+                        embed_subtitles(&op.destination, &srt, &transcript.language)
+                    })
+                } else {
+                    fs::write(sidecar_path(&op.destination, transcript), srt)
+                }
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no timed transcript (cached transcripts from older versions have no timings)",
+            )),
+        };
+        if let Err(e) = result {
+            errors.push(io::Error::new(
+                e.kind(),
+                format!("{}: {}", op.destination.display(), e),
+            ));
+        }
+    }
+
+    errors
+}
+
+/// Builds the path of the SRT file next to a video, tagged with the language
+fn sidecar_path(video: &Path, transcript: &Transcript) -> PathBuf {
+    video.with_extension(format!("{}.srt", transcript.language))
+}
+
+/// Adds the subtitles as the last subtitle track by remuxing with FFmpeg
+#[cfg(feature = "ffmpeg")]
+fn embed_subtitles(path: &Path, srt: &str, language: &str) -> io::Result<()> {
+    let subtitles = crate::temp::create_temp_file("subtitles", "srt").map_err(io::Error::other)?;
+    fs::write(subtitles.path(), srt)?;
+    let track = subtitle_track_count(path)?;
+
+    crate::tagging::remux_in_place(path, |command| {
+        command
+            .arg("-i")
+            .arg(subtitles.path())
+            .args(["-map", "0", "-map", "1", "-c", "copy", "-map_metadata", "0"])
+            .arg(format!("-metadata:s:s:{}", track))
+            .arg(format!("language={}", language));
+    })
+}
+
+/// Fails, as embedding needs FFmpeg, which isn't available without the `ffmpeg` feature
+#[cfg(not(feature = "ffmpeg"))]
+fn embed_subtitles(_path: &Path, _srt: &str, _language: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "embedding subtitles requires the ffmpeg feature",
+    ))
+}
+
+/// Counts the subtitle tracks a video already has using ffprobe
+#[cfg(feature = "ffmpeg")]
+fn subtitle_track_count(path: &Path) -> io::Result<usize> {
+    let output = crate::child_process::output(
+        std::process::Command::new(ffmpeg_sidecar::ffprobe::ffprobe_path())
+            .args(["-v", "error", "-select_streams", "s"])
+            .args(["-show_entries", "stream=index", "-of", "csv=p=0"])
+            .arg(path),
+    )?;
+    crate::tagging::check_output("ffprobe", &output)?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_path() {
+        let transcript = Transcript::new("Say my name.", "en");
+
+        assert_eq!(
+            sidecar_path(Path::new("out/Breaking Bad - S05E07.mkv"), &transcript),
+            PathBuf::from("out/Breaking Bad - S05E07.en.srt")
+        );
+    }
+}
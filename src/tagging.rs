@@ -0,0 +1,291 @@
+//! Container tagging module
+//!
+//! File names get lost: media servers rename files, users move them around.
+//! Writing the match into the container itself lets the identity of an
+//! episode travel with the file. Matroska files are tagged in place with
+//! `mkvpropedit` if it is installed, or else remuxed with FFmpeg, which
+//! copies all streams unchanged but has to rewrite the whole file.
+
+use crate::PlannedOperation;
+use crate::child_process;
+use crate::temp::create_temp_file;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Writes the matched episode into each renamed or copied Matroska file
+///
+/// Sets the container title to `<show> - S01E02 - <title>` and adds
+/// Matroska tags for the show, season, episode, and air date. Files in
+/// other containers and quarantined duplicates are left alone. The
+/// modification time of tagged files is kept. Returns the errors of files
+/// that couldn't be tagged.
+pub fn write_tags(operations: &[PlannedOperation], show_name: &str) -> Vec<io::Error> {
+    let use_mkvpropedit = mkvpropedit_available();
+    let mut errors = Vec::new();
+
+    for op in operations.iter().filter(|op| !op.quarantined) {
+        if !is_matroska(&op.destination) {
+            continue;
+        }
+
+        let result = keep_modified_time(&op.destination, || {
+            if use_mkvpropedit {
+                tag_with_mkvpropedit(op, show_name)
+            } else {
+                tag_with_ffmpeg(op, show_name)
+            }
+        });
+        if let Err(e) = result {
+            errors.push(io::Error::new(
+                e.kind(),
+                format!("{}: {}", op.destination.display(), e),
+            ));
+        }
+    }
+
+    errors
+}
+
+/// Checks whether a file is a Matroska file by its extension
+pub(crate) fn is_matroska(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("mkv"))
+}
+
+/// Checks whether `mkvpropedit` (part of MKVToolNix) is in the PATH
+fn mkvpropedit_available() -> bool {
+    child_process::output(Command::new("mkvpropedit").arg("--version"))
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Runs an operation that rewrites a file and restores its modification time
+pub(crate) fn keep_modified_time(
+    path: &Path,
+    rewrite: impl FnOnce() -> io::Result<()>,
+) -> io::Result<()> {
+    let modified = fs::metadata(path)?.modified()?;
+    rewrite()?;
+    File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(modified)
+}
+
+/// Builds the container title, e.g. `Show - S01E02-E03 - Title1 & Title2`
+fn container_title(op: &PlannedOperation, show_name: &str) -> String {
+    let episodes: Vec<_> = std::iter::once(&op.episode)
+        .chain(&op.extra_episodes)
+        .collect();
+    let last = episodes[episodes.len() - 1];
+    let numbers = if episodes.len() > 1 {
+        format!(
+            "S{:02}E{:02}-E{:02}",
+            op.episode.season_number, op.episode.episode_number, last.episode_number
+        )
+    } else {
+        format!(
+            "S{:02}E{:02}",
+            op.episode.season_number, op.episode.episode_number
+        )
+    };
+    let titles: Vec<&str> = episodes.iter().map(|e| e.name.as_str()).collect();
+
+    format!("{} - {} - {}", show_name, numbers, titles.join(" & "))
+}
+
+/// Builds a Matroska tags XML document for a planned operation
+///
+/// Uses the target types of the Matroska specification: 70 for the show
+/// (collection), 60 for the season, and 50 for the episode.
+fn tags_xml(op: &PlannedOperation, show_name: &str) -> String {
+    let simple = |name: &str, value: &str| {
+        format!(
+            "    <Simple><Name>{}</Name><String>{}</String></Simple>\n",
+            name,
+            xml_escape(value)
+        )
+    };
+    let tag = |target: u32, simples: String| {
+        format!(
+            "  <Tag>\n    <Targets><TargetTypeValue>{}</TargetTypeValue></Targets>\n{}  </Tag>\n",
+            target, simples
+        )
+    };
+
+    let titles: Vec<&str> = std::iter::once(&op.episode)
+        .chain(&op.extra_episodes)
+        .map(|e| e.name.as_str())
+        .collect();
+    let mut episode = simple("TITLE", &titles.join(" & "));
+    episode += &simple("PART_NUMBER", &op.episode.episode_number.to_string());
+    if let Some(airdate) = &op.episode.airdate {
+        episode += &simple("DATE_RELEASED", airdate);
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Tags>\n{}{}{}</Tags>\n",
+        tag(70, simple("TITLE", show_name)),
+        tag(
+            60,
+            simple("PART_NUMBER", &op.episode.season_number.to_string())
+        ),
+        tag(50, episode)
+    )
+}
+
+/// Escapes the characters XML reserves in text
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Sets title and tags in place with `mkvpropedit`
+fn tag_with_mkvpropedit(op: &PlannedOperation, show_name: &str) -> io::Result<()> {
+    let tags = create_temp_file("tags", "xml").map_err(io::Error::other)?;
+    fs::write(tags.path(), tags_xml(op, show_name))?;
+
+    let output = child_process::output(
+        Command::new("mkvpropedit")
+            .arg(&op.destination)
+            .args(["--edit", "info", "--set"])
+            .arg(format!("title={}", container_title(op, show_name)))
+            .arg("--tags")
+            .arg(format!("global:{}", tags.display())),
+    )?;
+    check_output("mkvpropedit", &output)
+}
+
+/// Sets title and tags by remuxing the file with FFmpeg
+#[cfg(feature = "ffmpeg")]
+fn tag_with_ffmpeg(op: &PlannedOperation, show_name: &str) -> io::Result<()> {
+    let mut metadata = vec![
+        ("title", container_title(op, show_name)),
+        ("show", show_name.to_string()),
+        ("season_number", op.episode.season_number.to_string()),
+        ("episode_sort", op.episode.episode_number.to_string()),
+    ];
+    if let Some(airdate) = &op.episode.airdate {
+        metadata.push(("date", airdate.clone()));
+    }
+
+    remux_in_place(&op.destination, |command| {
+        command.args(["-map", "0", "-c", "copy", "-map_metadata", "0"]);
+        for (key, value) in metadata {
+            command.arg("-metadata").arg(format!("{}={}", key, value));
+        }
+    })
+}
+
+/// Remuxes a file with FFmpeg into a file next to it, then replaces it
+///
+/// The file is the first input; `configure` adds further inputs and the
+/// output options. The original is only replaced if FFmpeg succeeds.
+#[cfg(feature = "ffmpeg")]
+pub(crate) fn remux_in_place(path: &Path, configure: impl FnOnce(&mut Command)) -> io::Result<()> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("video");
+    // Removed unless renamed over the original, also if interrupted
+    let remuxed = crate::temp::TempGuard::file(path.with_file_name(format!(
+        ".{}.{}.remux.mkv",
+        file_name,
+        ulid::Ulid::new()
+    )));
+
+    let mut command = Command::new(ffmpeg_sidecar::paths::ffmpeg_path());
+    command.args(["-v", "error", "-nostdin", "-i"]).arg(path);
+    configure(&mut command);
+    command.arg("-y").arg(remuxed.path());
+
+    let output = child_process::output(&mut command)?;
+    check_output("ffmpeg", &output)?;
+    fs::rename(remuxed.path(), path)
+}
+
+/// Fails without `mkvpropedit`, as FFmpeg isn't available without the `ffmpeg` feature
+#[cfg(not(feature = "ffmpeg"))]
+fn tag_with_ffmpeg(_op: &PlannedOperation, _show_name: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "tagging requires mkvpropedit (MKVToolNix)",
+    ))
+}
+
+/// Turns a failed tool run into an error with its output
+pub(crate) fn check_output(tool: &str, output: &Output) -> io::Result<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+
+    // mkvpropedit reports errors on stdout, FFmpeg on stderr
+    let message = [&output.stderr, &output.stdout]
+        .iter()
+        .map(|text| String::from_utf8_lossy(text).trim().to_string())
+        .find(|text| !text.is_empty())
+        .unwrap_or_else(|| output.status.to_string());
+    Err(io::Error::other(format!("{} failed: {}", tool, message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Episode;
+    use std::path::PathBuf;
+
+    fn operation(extra: &[(usize, &str)]) -> PlannedOperation {
+        let episode = |number: usize, name: &str| Episode {
+            season_number: 4,
+            episode_number: number,
+            name: name.to_string(),
+            summary: String::new(),
+            summary_source: None,
+            airdate: Some("2008-06-28".to_string()),
+            runtime: None,
+            season_name: None,
+            provider_id: None,
+            local_title: None,
+        };
+        PlannedOperation {
+            source: PathBuf::from("a.mkv"),
+            destination: PathBuf::from("b.mkv"),
+            episode: episode(12, "The Stolen Earth"),
+            extra_episodes: extra.iter().map(|(n, name)| episode(*n, name)).collect(),
+            duplicate_suffix: None,
+            part: None,
+            quarantined: false,
+        }
+    }
+
+    #[test]
+    fn test_container_title() {
+        assert_eq!(
+            container_title(&operation(&[]), "Doctor Who"),
+            "Doctor Who - S04E12 - The Stolen Earth"
+        );
+        assert_eq!(
+            container_title(&operation(&[(13, "Journey's End")]), "Doctor Who"),
+            "Doctor Who - S04E12-E13 - The Stolen Earth & Journey's End"
+        );
+    }
+
+    #[test]
+    fn test_tags_xml() {
+        let xml = tags_xml(&operation(&[]), "Law & Order <UK>");
+
+        assert!(xml.contains("<TargetTypeValue>70</TargetTypeValue>"));
+        assert!(xml.contains("<String>Law &amp; Order &lt;UK&gt;</String>"));
+        assert!(xml.contains("<Name>PART_NUMBER</Name><String>4</String>"));
+        assert!(xml.contains("<Name>PART_NUMBER</Name><String>12</String>"));
+        assert!(xml.contains("<Name>DATE_RELEASED</Name><String>2008-06-28</String>"));
+    }
+
+    #[test]
+    fn test_is_matroska() {
+        assert!(is_matroska(Path::new("Show - S01E01.MKV")));
+        assert!(!is_matroska(Path::new("Show - S01E01.mp4")));
+        assert!(!is_matroska(Path::new("mkv")));
+    }
+}
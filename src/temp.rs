@@ -1,13 +1,30 @@
 //! Temporary file management module
 //!
 //! This module provides RAII-based temporary file handling with automatic cleanup.
+//!
+//! Guards remove their files when dropped. If the process is terminated by a
+//! signal, no destructors run, so the paths of the files are also kept in a
+//! registry the signal handler removes them from (see `child_process`).
 
 use std::fs::{self, File};
 use std::io;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::sync::atomic::{AtomicPtr, Ordering};
 use thiserror::Error;
 
+/// Most temporary files registered for removal at once
+///
+/// Further files are still removed by their guards, just not on signals.
+#[cfg(unix)]
+const MAX_REGISTERED: usize = 64;
+
+/// Paths of the temporary files, as C strings, null for a free slot
+#[cfg(unix)]
+static REGISTERED: [AtomicPtr<libc::c_char>; MAX_REGISTERED] =
+    [const { AtomicPtr::new(std::ptr::null_mut()) }; MAX_REGISTERED];
+
 /// Errors that can occur during temporary file operations
 #[derive(Debug, Error)]
 pub enum TempError {
@@ -16,20 +33,35 @@ pub enum TempError {
     CreateFileFailed(#[from] io::Error),
 }
 
+impl TempError {
+    /// Returns the stable code of the error, like `DD-TMP-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            TempError::CreateFileFailed(_) => "DD-TMP-001",
+        }
+    }
+}
+
 /// Guard for temporary resources that automatically cleans up on drop
 #[derive(Debug)]
 pub(crate) enum TempGuard {
     /// Temporary file that will be deleted when dropped
-    File(PathBuf),
+    File(PathBuf, Registration),
     // Future: Directory variant can be added here
     // Directory(PathBuf),
 }
 
 impl TempGuard {
+    /// Guards a file that is yet to be created, e.g. by FFmpeg
+    pub(crate) fn file(path: PathBuf) -> Self {
+        let registration = Registration::new(&path);
+        TempGuard::File(path, registration)
+    }
+
     /// Get the path to the temporary resource
     pub(crate) fn path(&self) -> &Path {
         match self {
-            TempGuard::File(path) => path,
+            TempGuard::File(path, _) => path,
         }
     }
 }
@@ -37,9 +69,10 @@ impl TempGuard {
 impl Drop for TempGuard {
     fn drop(&mut self) {
         match self {
-            TempGuard::File(path) => {
+            TempGuard::File(path, registration) => {
                 // Silently ignore errors during cleanup
                 let _ = fs::remove_file(path);
+                registration.release();
             }
         }
     }
@@ -53,6 +86,96 @@ impl Deref for TempGuard {
     }
 }
 
+/// Slot of a temporary file in the registry, freed when dropped
+///
+/// Holds the slot and the address of the registered path, if a slot was
+/// free.
+#[derive(Debug)]
+pub(crate) struct Registration(Option<(usize, usize)>);
+
+impl Registration {
+    /// Adds a path to the registry, if a slot is free
+    #[cfg(unix)]
+    fn new(path: &Path) -> Self {
+        use std::os::unix::ffi::OsStrExt;
+
+        let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+            return Self(None);
+        };
+        let path = path.into_raw();
+        let slot = REGISTERED.iter().position(|slot| {
+            slot.compare_exchange(
+                std::ptr::null_mut(),
+                path,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+        });
+        if slot.is_none() {
+            // SAFETY: the pointer was just created by into_raw and not shared
+            drop(unsafe { std::ffi::CString::from_raw(path) });
+        }
+        Self(slot.map(|slot| (slot, path as usize)))
+    }
+
+    /// Keeps no registry, as there are no signal handlers to use it
+    #[cfg(not(unix))]
+    fn new(_path: &Path) -> Self {
+        Self(None)
+    }
+
+    /// Removes the path from the registry
+    #[cfg(unix)]
+    fn release(&mut self) {
+        let Some((slot, path)) = self.0.take() else {
+            return;
+        };
+        // If the signal handler took the path out of the slot, another file
+        // may have taken the slot since, and the path is leaked as the
+        // process is exiting anyway
+        let path = path as *mut libc::c_char;
+        if REGISTERED[slot]
+            .compare_exchange(
+                path,
+                std::ptr::null_mut(),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+        {
+            // SAFETY: the pointer was created by into_raw in Registration::new
+            drop(unsafe { std::ffi::CString::from_raw(path) });
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn release(&mut self) {}
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Removes the registered temporary files
+///
+/// Meant for shutting down from a signal handler: only calls that are safe
+/// in a signal handler are made, and the paths aren't freed.
+#[cfg(unix)]
+pub(crate) fn remove_registered_files() {
+    for slot in &REGISTERED {
+        let path = slot.swap(std::ptr::null_mut(), Ordering::SeqCst);
+        if !path.is_null() {
+            // SAFETY: the path is a valid C string that is never freed once taken
+            unsafe {
+                libc::unlink(path);
+            }
+        }
+    }
+}
+
 /// Creates a temporary file and returns a guard that will clean it up on drop
 ///
 /// The file is created in the system's temporary directory with a unique name
@@ -83,7 +206,7 @@ pub(crate) fn create_temp_file(prefix: &str, extension: &str) -> Result<TempGuar
     // Create the file
     File::create(&path)?;
 
-    Ok(TempGuard::File(path))
+    Ok(TempGuard::file(path))
 }
 
 #[cfg(test)]
@@ -146,4 +269,23 @@ mod tests {
         // File should be gone after guard is dropped
         assert!(!path.exists());
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_temp_file_registered_until_dropped() {
+        // Only pointer values are compared, as other tests free their paths
+        // concurrently
+        let is_registered =
+            |(slot, path): (usize, usize)| REGISTERED[slot].load(Ordering::SeqCst) as usize == path;
+
+        let temp = create_temp_file("test", "txt").unwrap();
+        let TempGuard::File(_, Registration(Some(registration))) = &temp else {
+            panic!("temporary file isn't registered");
+        };
+        let registration = *registration;
+        assert!(is_registered(registration));
+
+        drop(temp);
+        assert!(!is_registered(registration));
+    }
 }
@@ -0,0 +1,540 @@
+//! Trakt integration module
+//!
+//! Users who track their library on Trakt want identified episodes to show
+//! up in their collection without adding them by hand. This module signs
+//! in to Trakt with the OAuth device flow, keeps the token in the
+//! configuration directory, and adds matched episodes to the signed-in
+//! user's collection.
+mod trakt_types;
+
+use crate::MatchResult;
+use crate::metadata_retrieval::{MetadataProvider, MetadataRetrievalError, TraktProvider};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+use trakt_types::{
+    TraktCollectionEpisode, TraktCollectionRequest, TraktCollectionSeason, TraktCollectionShow,
+    TraktDeviceCodeRequest, TraktDeviceCodeResponse, TraktDeviceTokenRequest,
+    TraktRefreshTokenRequest, TraktRevokeRequest, TraktShowIds, TraktSyncResponse,
+    TraktTokenResponse,
+};
+
+/// Environment variable holding the client secret of the Trakt API app
+const CLIENT_SECRET_ENV: &str = "TRAKT_CLIENT_SECRET";
+
+/// Name of the token file in the configuration directory
+const TOKEN_FILE: &str = "trakt_token.json";
+
+/// Redirect URI of apps without a web page to return to
+const REDIRECT_URI: &str = "urn:ietf:wg:oauth:2.0:oob";
+
+/// Access tokens expiring within this time are refreshed before use
+const REFRESH_MARGIN: Duration = Duration::from_secs(10 * 60);
+
+/// Errors that can occur while talking to Trakt
+#[derive(Debug, Error)]
+pub enum TraktError {
+    /// Request to the Trakt API failed
+    #[error("Request failed: {0}")]
+    RequestError(String),
+
+    /// Failed to parse Trakt's JSON response
+    #[error("Failed to parse API response: {0}")]
+    ParseError(String),
+
+    /// No user is signed in
+    #[error("Not signed in to Trakt")]
+    NotSignedIn,
+
+    /// Signing in failed, e.g. because the user denied access
+    #[error("Authorization failed: {0}")]
+    AuthorizationFailed(String),
+
+    /// The series isn't known to Trakt
+    #[error("Series '{0}' not found on Trakt")]
+    SeriesNotFound(String),
+
+    /// Could not determine the configuration directory
+    #[error("Could not determine configuration directory")]
+    ConfigDirectoryNotFound,
+
+    /// Failed to read or write the token file
+    #[error("Failed to access the Trakt token in {path}: {source}")]
+    TokenStorage { path: PathBuf, source: io::Error },
+}
+
+impl TraktError {
+    /// Returns the stable code of the error, like `DD-TRK-001`
+    pub fn code(&self) -> &'static str {
+        match self {
+            TraktError::RequestError(_) => "DD-TRK-001",
+            TraktError::ParseError(_) => "DD-TRK-002",
+            TraktError::NotSignedIn => "DD-TRK-003",
+            TraktError::AuthorizationFailed(_) => "DD-TRK-004",
+            TraktError::SeriesNotFound(_) => "DD-TRK-005",
+            TraktError::ConfigDirectoryNotFound => "DD-TRK-006",
+            TraktError::TokenStorage { .. } => "DD-TRK-007",
+        }
+    }
+}
+
+/// A pending sign-in, waiting for the user to enter the code on Trakt
+#[derive(Debug, Clone)]
+pub struct TraktDeviceCode {
+    /// Code the user enters at the verification URL
+    pub user_code: String,
+
+    /// Page the user signs in and enters the code on
+    pub verification_url: String,
+
+    /// Time the codes are valid for
+    pub expires_in: Duration,
+
+    /// Time to wait between polls for the token
+    interval: Duration,
+
+    /// Code identifying this device when polling for the token
+    device_code: String,
+}
+
+/// Outcome of adding episodes to the collection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraktCollection {
+    /// Episodes newly added to the collection
+    pub added: usize,
+
+    /// Episodes that were already collected
+    pub existing: usize,
+
+    /// Episodes Trakt doesn't know, e.g. because it numbers them differently
+    pub not_found: usize,
+}
+
+/// The token of the signed-in user, as stored in the token file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct TraktToken {
+    access_token: String,
+    refresh_token: String,
+    /// Unix timestamp the access token expires at
+    expires_at: u64,
+}
+
+impl TraktToken {
+    /// Whether the access token expires within the refresh margin
+    fn needs_refresh(&self, now: SystemTime) -> bool {
+        let now = now
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        now + REFRESH_MARGIN >= Duration::from_secs(self.expires_at)
+    }
+}
+
+impl From<TraktTokenResponse> for TraktToken {
+    fn from(response: TraktTokenResponse) -> Self {
+        Self {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            expires_at: response.created_at + response.expires_in,
+        }
+    }
+}
+
+/// Client for the collection of a Trakt user
+///
+/// Requires a Trakt API app (created at https://trakt.tv/oauth/applications)
+/// for its client ID and secret. The user's token is stored in
+/// `trakt_token.json` in the configuration directory.
+pub struct TraktClient {
+    client: reqwest::blocking::Client,
+    base_url: String,
+    client_id: String,
+    client_secret: String,
+}
+
+impl TraktClient {
+    /// Creates a client for the Trakt API app with the given credentials
+    pub fn new(client_id: impl Into<String>, client_secret: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            base_url: "https://api.trakt.tv".to_string(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+        }
+    }
+
+    /// Creates a client if `TRAKT_CLIENT_ID` and `TRAKT_CLIENT_SECRET` are set
+    pub fn from_env() -> Option<Self> {
+        let client_id = TraktProvider::client_id_from_env()?;
+        let client_secret = std::env::var(CLIENT_SECRET_ENV)
+            .ok()
+            .filter(|secret| !secret.trim().is_empty())?;
+        Some(Self::new(client_id, client_secret))
+    }
+
+    /// Starts signing in with the OAuth device flow
+    ///
+    /// The user has to enter the returned code at its verification URL,
+    /// while `wait_for_authorization` waits for them to do so.
+    pub fn request_device_code(&self) -> Result<TraktDeviceCode, TraktError> {
+        let body = TraktDeviceCodeRequest {
+            client_id: &self.client_id,
+        };
+        let response: TraktDeviceCodeResponse =
+            parse_response(self.post("oauth/device/code", &body, None)?)?;
+
+        Ok(TraktDeviceCode {
+            user_code: response.user_code,
+            verification_url: response.verification_url,
+            expires_in: Duration::from_secs(response.expires_in),
+            interval: Duration::from_secs(response.interval.max(1)),
+            device_code: response.device_code,
+        })
+    }
+
+    /// Waits for the user to enter the code and stores their token
+    ///
+    /// Polls Trakt at the interval it asked for until the user granted or
+    /// denied access, or the code expired.
+    pub fn wait_for_authorization(&self, code: &TraktDeviceCode) -> Result<(), TraktError> {
+        let body = TraktDeviceTokenRequest {
+            code: &code.device_code,
+            client_id: &self.client_id,
+            client_secret: &self.client_secret,
+        };
+        let deadline = SystemTime::now() + code.expires_in;
+        let mut interval = code.interval;
+
+        while SystemTime::now() < deadline {
+            thread::sleep(interval);
+            let response = self.post("oauth/device/token", &body, None)?;
+            let reason = match response.status().as_u16() {
+                200..=299 => {
+                    let token: TraktTokenResponse = parse_response(response)?;
+                    return save_token(&default_token_path()?, &token.into());
+                }
+                // Pending: the user hasn't entered the code yet
+                400 => continue,
+                429 => {
+                    interval += Duration::from_secs(1);
+                    continue;
+                }
+                404 => "invalid device code",
+                409 => "code already used",
+                410 => "code expired",
+                418 => "access denied",
+                _ => return Err(status_error(&response)),
+            };
+            return Err(TraktError::AuthorizationFailed(reason.to_string()));
+        }
+
+        Err(TraktError::AuthorizationFailed("code expired".to_string()))
+    }
+
+    /// Whether a user is signed in
+    pub fn is_signed_in(&self) -> bool {
+        default_token_path().is_ok_and(|path| load_token(&path).is_ok())
+    }
+
+    /// Signs the user out, returning whether anyone was signed in
+    ///
+    /// The token is revoked on Trakt if possible and removed either way.
+    pub fn sign_out(&self) -> Result<bool, TraktError> {
+        let path = default_token_path()?;
+        let token = match load_token(&path) {
+            Ok(token) => token,
+            Err(TraktError::NotSignedIn) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        let body = TraktRevokeRequest {
+            token: &token.access_token,
+            client_id: &self.client_id,
+            client_secret: &self.client_secret,
+        };
+        // A token Trakt doesn't accept anymore is as good as revoked
+        let _ = self.post("oauth/revoke", &body, None);
+
+        fs::remove_file(&path).map_err(|source| TraktError::TokenStorage { path, source })?;
+        Ok(true)
+    }
+
+    /// Adds the matched episodes to the signed-in user's collection
+    ///
+    /// The series is looked up on Trakt by its name, using the premiere
+    /// year to tell remakes apart. Episodes are identified by their season
+    /// and episode numbers in Trakt's order.
+    pub fn collect(
+        &self,
+        matches: &[MatchResult],
+        series_name: &str,
+        year: Option<u16>,
+    ) -> Result<TraktCollection, TraktError> {
+        let access_token = self.access_token()?;
+        let show_id = self.find_show_id(series_name, year)?;
+
+        let request = collection_request(show_id, matches);
+        let requested: usize = request.shows[0]
+            .seasons
+            .iter()
+            .map(|season| season.episodes.len())
+            .sum();
+        let response: TraktSyncResponse =
+            parse_response(self.post("sync/collection", &request, Some(&access_token))?)?;
+
+        let existing = response.updated.episodes + response.existing.episodes;
+        Ok(TraktCollection {
+            added: response.added.episodes,
+            existing,
+            not_found: requested.saturating_sub(response.added.episodes + existing),
+        })
+    }
+
+    /// Returns the stored access token, refreshing it if it is about to expire
+    fn access_token(&self) -> Result<String, TraktError> {
+        let path = default_token_path()?;
+        let token = load_token(&path)?;
+        if !token.needs_refresh(SystemTime::now()) {
+            return Ok(token.access_token);
+        }
+
+        let body = TraktRefreshTokenRequest {
+            refresh_token: &token.refresh_token,
+            client_id: &self.client_id,
+            client_secret: &self.client_secret,
+            redirect_uri: REDIRECT_URI,
+            grant_type: "refresh_token",
+        };
+        let response = self.post("oauth/token", &body, None)?;
+        if response.status() == 401 {
+            // The refresh token expired or was revoked
+            return Err(TraktError::NotSignedIn);
+        }
+        let refreshed: TraktToken = parse_response::<TraktTokenResponse>(response)?.into();
+        save_token(&path, &refreshed)?;
+        Ok(refreshed.access_token)
+    }
+
+    /// Looks up the Trakt ID of a series, preferring the one from the given year
+    fn find_show_id(&self, series_name: &str, year: Option<u16>) -> Result<u64, TraktError> {
+        let provider = TraktProvider::new(self.client_id.clone());
+        let candidates = provider.search_series(series_name).map_err(|e| match e {
+            MetadataRetrievalError::SeriesNotFound(name) => TraktError::SeriesNotFound(name),
+            MetadataRetrievalError::ParseError(message) => TraktError::ParseError(message),
+            e => TraktError::RequestError(e.to_string()),
+        })?;
+
+        candidates
+            .iter()
+            .find(|candidate| year.is_some() && candidate.year == year)
+            .or(candidates.first())
+            .map(|candidate| candidate.id)
+            .ok_or_else(|| TraktError::SeriesNotFound(series_name.to_string()))
+    }
+
+    /// Sends a POST request with a JSON body, optionally as the signed-in user
+    fn post<B: Serialize>(
+        &self,
+        endpoint: &str,
+        body: &B,
+        access_token: Option<&str>,
+    ) -> Result<reqwest::blocking::Response, TraktError> {
+        let mut request = self
+            .client
+            .post(format!("{}/{}", self.base_url, endpoint))
+            .header("trakt-api-version", "2")
+            .header("trakt-api-key", &self.client_id)
+            .json(body);
+        if let Some(token) = access_token {
+            request = request.bearer_auth(token);
+        }
+        request
+            .send()
+            .map_err(|e| TraktError::RequestError(e.to_string()))
+    }
+}
+
+/// Checks the status of a response and parses its JSON body
+fn parse_response<T: DeserializeOwned>(
+    response: reqwest::blocking::Response,
+) -> Result<T, TraktError> {
+    if !response.status().is_success() {
+        return Err(status_error(&response));
+    }
+
+    response
+        .json()
+        .map_err(|e| TraktError::ParseError(e.to_string()))
+}
+
+/// Returns an error describing an unexpected response status
+fn status_error(response: &reqwest::blocking::Response) -> TraktError {
+    TraktError::RequestError(format!(
+        "HTTP {} {}",
+        response.status().as_u16(),
+        response.status().canonical_reason().unwrap_or("Unknown")
+    ))
+}
+
+/// Builds the request adding the matched episodes of a show
+///
+/// Every episode is listed once, including the extra episodes of files
+/// holding several, sorted by season and episode number.
+fn collection_request(show_id: u64, matches: &[MatchResult]) -> TraktCollectionRequest {
+    let mut seasons: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+    for episode in matches
+        .iter()
+        .flat_map(|result| std::iter::once(&result.episode).chain(&result.extra_episodes))
+    {
+        seasons
+            .entry(episode.season_number)
+            .or_default()
+            .insert(episode.episode_number);
+    }
+
+    TraktCollectionRequest {
+        shows: vec![TraktCollectionShow {
+            ids: TraktShowIds { trakt: show_id },
+            seasons: seasons
+                .into_iter()
+                .map(|(number, episodes)| TraktCollectionSeason {
+                    number,
+                    episodes: episodes
+                        .into_iter()
+                        .map(|number| TraktCollectionEpisode { number })
+                        .collect(),
+                })
+                .collect(),
+        }],
+    }
+}
+
+/// Returns the path of the token file in the configuration directory
+fn default_token_path() -> Result<PathBuf, TraktError> {
+    let proj_dirs = directories::ProjectDirs::from("de", "westhoffswelt", "dialogdetective")
+        .ok_or(TraktError::ConfigDirectoryNotFound)?;
+    Ok(proj_dirs.config_dir().join(TOKEN_FILE))
+}
+
+/// Reads the stored token, failing with `NotSignedIn` if there is none
+fn load_token(path: &Path) -> Result<TraktToken, TraktError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(TraktError::NotSignedIn),
+        Err(source) => {
+            return Err(TraktError::TokenStorage {
+                path: path.to_path_buf(),
+                source,
+            });
+        }
+    };
+    serde_json::from_str(&content).map_err(|_| TraktError::NotSignedIn)
+}
+
+/// Stores a token, readable by the current user only
+fn save_token(path: &Path, token: &TraktToken) -> Result<(), TraktError> {
+    let storage_error = |source| TraktError::TokenStorage {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(storage_error)?;
+    }
+
+    let content = serde_json::to_string_pretty(token).expect("tokens serialize to JSON");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(storage_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Episode, StageTimings, VideoFile};
+
+    #[test]
+    fn test_token_needs_refresh() {
+        let token = TraktToken {
+            access_token: "access".to_string(),
+            refresh_token: "refresh".to_string(),
+            expires_at: 1_000_000,
+        };
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+
+        assert!(!token.needs_refresh(at(900_000)));
+        assert!(token.needs_refresh(at(999_900)));
+        assert!(token.needs_refresh(at(2_000_000)));
+    }
+
+    fn episode(season_number: usize, episode_number: usize) -> Episode {
+        Episode {
+            season_number,
+            episode_number,
+            name: format!("Episode {}", episode_number),
+            summary: String::new(),
+            summary_source: None,
+            airdate: None,
+            runtime: None,
+            season_name: None,
+            provider_id: None,
+            local_title: None,
+        }
+    }
+
+    fn result(episode: Episode, extra_episodes: Vec<Episode>) -> MatchResult {
+        MatchResult {
+            video: VideoFile::new("/videos/a.mkv"),
+            video_hash: "abc".to_string(),
+            episode,
+            confidence: Some(0.9),
+            alternatives: Vec::new(),
+            reasoning: None,
+            extra_episodes,
+            filename_conflict: None,
+            runtime_mismatch: None,
+            video_duration: None,
+            part: None,
+            transcript: None,
+            transcript_quality: None,
+            timings: StageTimings::default(),
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn test_collection_request_json() {
+        let matches = vec![
+            result(episode(2, 1), vec![]),
+            result(episode(1, 3), vec![episode(1, 4)]),
+            result(episode(1, 3), vec![]),
+        ];
+
+        let request = collection_request(42, &matches);
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "shows": [{
+                    "ids": {"trakt": 42},
+                    "seasons": [
+                        {"number": 1, "episodes": [{"number": 3}, {"number": 4}]},
+                        {"number": 2, "episodes": [{"number": 1}]}
+                    ]
+                }]
+            })
+        );
+    }
+}
@@ -0,0 +1,118 @@
+/// Trakt API types for (de)serialization.
+///
+/// These structures mirror the subset of the Trakt API JSON needed to sign
+/// in with the OAuth device flow and add episodes to the collection.
+use serde::{Deserialize, Serialize};
+
+// =========================================================
+// OAuth types (/oauth/device/code, /oauth/device/token, /oauth/token)
+// =========================================================
+
+/// Body requesting a device code.
+#[derive(Debug, Serialize)]
+pub(super) struct TraktDeviceCodeRequest<'a> {
+    pub client_id: &'a str,
+}
+
+/// A device code with the user code to enter on the Trakt website.
+#[derive(Debug, Deserialize)]
+pub(super) struct TraktDeviceCodeResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_url: String,
+    /// Seconds until the codes expire
+    pub expires_in: u64,
+    /// Seconds to wait between polls for the token
+    pub interval: u64,
+}
+
+/// Body polling for the token of an authorized device code.
+#[derive(Debug, Serialize)]
+pub(super) struct TraktDeviceTokenRequest<'a> {
+    pub code: &'a str,
+    pub client_id: &'a str,
+    pub client_secret: &'a str,
+}
+
+/// Body exchanging a refresh token for a new access token.
+#[derive(Debug, Serialize)]
+pub(super) struct TraktRefreshTokenRequest<'a> {
+    pub refresh_token: &'a str,
+    pub client_id: &'a str,
+    pub client_secret: &'a str,
+    pub redirect_uri: &'a str,
+    pub grant_type: &'a str,
+}
+
+/// Body revoking an access token.
+#[derive(Debug, Serialize)]
+pub(super) struct TraktRevokeRequest<'a> {
+    pub token: &'a str,
+    pub client_id: &'a str,
+    pub client_secret: &'a str,
+}
+
+/// An access token, as returned by the device and refresh token endpoints.
+#[derive(Debug, Deserialize)]
+pub(super) struct TraktTokenResponse {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Seconds the access token is valid for, counted from `created_at`
+    pub expires_in: u64,
+    /// Unix timestamp of the token's creation
+    pub created_at: u64,
+}
+
+// =========================================================
+// Collection types (/sync/collection)
+// =========================================================
+
+/// Body adding episodes to the collection.
+#[derive(Debug, Serialize)]
+pub(super) struct TraktCollectionRequest {
+    pub shows: Vec<TraktCollectionShow>,
+}
+
+/// A show with the episodes to add.
+#[derive(Debug, Serialize)]
+pub(super) struct TraktCollectionShow {
+    pub ids: TraktShowIds,
+    pub seasons: Vec<TraktCollectionSeason>,
+}
+
+/// The Trakt ID of a show.
+#[derive(Debug, Serialize)]
+pub(super) struct TraktShowIds {
+    pub trakt: u64,
+}
+
+/// A season with the episodes to add.
+#[derive(Debug, Serialize)]
+pub(super) struct TraktCollectionSeason {
+    pub number: usize,
+    pub episodes: Vec<TraktCollectionEpisode>,
+}
+
+/// An episode to add, by its number within the season.
+#[derive(Debug, Serialize)]
+pub(super) struct TraktCollectionEpisode {
+    pub number: usize,
+}
+
+/// Outcome of adding items to the collection.
+#[derive(Debug, Deserialize)]
+pub(super) struct TraktSyncResponse {
+    #[serde(default)]
+    pub added: TraktSyncCounts,
+    #[serde(default)]
+    pub updated: TraktSyncCounts,
+    #[serde(default)]
+    pub existing: TraktSyncCounts,
+}
+
+/// Number of episodes affected by a sync request.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct TraktSyncCounts {
+    #[serde(default)]
+    pub episodes: usize,
+}
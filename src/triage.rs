@@ -0,0 +1,180 @@
+//! Confidence triage module
+//!
+//! Not every match is equally trustworthy. This module sorts match results
+//! into buckets by the confidence the matcher reported, so that confident
+//! matches can be applied automatically while doubtful ones are set aside
+//! for a human to review.
+
+use crate::MatchResult;
+use serde::Serialize;
+
+/// Confidence thresholds separating the triage buckets
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TriageThresholds {
+    /// Minimum confidence for a match to be applied automatically
+    pub auto_apply: f64,
+
+    /// Minimum confidence for a match to be kept for review
+    ///
+    /// Matches below this are considered unmatched.
+    pub review: f64,
+}
+
+impl Default for TriageThresholds {
+    fn default() -> Self {
+        Self {
+            auto_apply: 0.8,
+            review: 0.5,
+        }
+    }
+}
+
+/// Match results sorted into triage buckets
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Triage {
+    /// Confident matches that can be applied without asking
+    pub auto_apply: Vec<MatchResult>,
+
+    /// Plausible matches that should be checked before applying them
+    pub review: Vec<MatchResult>,
+
+    /// Matches too uncertain to be used
+    pub unmatched: Vec<MatchResult>,
+}
+
+/// Sorts match results into buckets by their confidence
+///
+/// Matches without a reported confidence are treated as confident, as the
+/// matcher gave no reason to doubt them. Matches contradicting the episode
+/// stated in their file name or the episode's runtime always need review. The order of the matches is
+/// preserved within each bucket.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dialog_detective::{TriageThresholds, triage_matches};
+/// # let matches = Vec::new();
+///
+/// let triage = triage_matches(matches, TriageThresholds::default());
+/// println!("{} matches need review", triage.review.len());
+/// ```
+pub fn triage_matches(matches: Vec<MatchResult>, thresholds: TriageThresholds) -> Triage {
+    let mut triage = Triage {
+        auto_apply: Vec::new(),
+        review: Vec::new(),
+        unmatched: Vec::new(),
+    };
+
+    for result in matches {
+        if result.filename_conflict.is_some() || result.runtime_mismatch.is_some() {
+            triage.review.push(result);
+            continue;
+        }
+
+        match result.confidence {
+            Some(confidence) if confidence < thresholds.review => triage.unmatched.push(result),
+            Some(confidence) if confidence < thresholds.auto_apply => triage.review.push(result),
+            _ => triage.auto_apply.push(result),
+        }
+    }
+
+    triage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Episode, FilenameConflict, RuntimeMismatch, StageTimings, VideoFile};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn result(file: &str, confidence: Option<f64>) -> MatchResult {
+        MatchResult {
+            video: VideoFile::new(file),
+            video_hash: String::new(),
+            episode: Episode {
+                season_number: 1,
+                episode_number: 1,
+                name: "Pilot".to_string(),
+                summary: String::new(),
+                summary_source: None,
+                airdate: None,
+                runtime: None,
+                season_name: None,
+                provider_id: None,
+                local_title: None,
+            },
+            confidence,
+            alternatives: Vec::new(),
+            reasoning: None,
+            extra_episodes: Vec::new(),
+            filename_conflict: None,
+            runtime_mismatch: None,
+            video_duration: None,
+            part: None,
+            transcript: None,
+            transcript_quality: None,
+            timings: StageTimings::default(),
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn test_triage_by_confidence() {
+        let matches = vec![
+            result("sure.mkv", Some(0.95)),
+            result("maybe.mkv", Some(0.6)),
+            result("doubtful.mkv", Some(0.2)),
+            result("unknown.mkv", None),
+            result("border.mkv", Some(0.8)),
+        ];
+
+        let triage = triage_matches(matches, TriageThresholds::default());
+
+        let paths = |bucket: &[MatchResult]| -> Vec<PathBuf> {
+            bucket.iter().map(|r| r.video.path.clone()).collect()
+        };
+        assert_eq!(
+            paths(&triage.auto_apply),
+            vec![
+                PathBuf::from("sure.mkv"),
+                PathBuf::from("unknown.mkv"),
+                PathBuf::from("border.mkv")
+            ]
+        );
+        assert_eq!(paths(&triage.review), vec![PathBuf::from("maybe.mkv")]);
+        assert_eq!(
+            paths(&triage.unmatched),
+            vec![PathBuf::from("doubtful.mkv")]
+        );
+    }
+
+    #[test]
+    fn test_filename_conflict_needs_review() {
+        let mut conflicting = result("Show.S01E02.mkv", Some(0.95));
+        conflicting.filename_conflict = Some(FilenameConflict {
+            season_number: 1,
+            episode_number: 2,
+            episode: None,
+        });
+
+        let triage = triage_matches(vec![conflicting], TriageThresholds::default());
+
+        assert!(triage.auto_apply.is_empty());
+        assert_eq!(triage.review.len(), 1);
+    }
+
+    #[test]
+    fn test_runtime_mismatch_needs_review() {
+        let mut mismatched = result("short.mkv", Some(0.95));
+        mismatched.runtime_mismatch = Some(RuntimeMismatch {
+            video_duration: Duration::from_secs(25 * 60),
+            episode_runtime: Duration::from_secs(90 * 60),
+        });
+
+        let triage = triage_matches(vec![mismatched], TriageThresholds::default());
+
+        assert!(triage.auto_apply.is_empty());
+        assert_eq!(triage.review.len(), 1);
+    }
+}